@@ -0,0 +1,44 @@
+//! Bundles every chain logo in `static/chains` into the binary.
+//!
+//! Writes `$OUT_DIR/chain_logos.rs`, a slice of `(chain_id, extension, bytes)` for each
+//! `{chain_id}.{ext}` file, which `assets` includes. Dropping a file into the directory
+//! is all it takes to serve it; no route has to be added.
+
+use std::fmt::Write;
+use std::path::Path;
+
+fn main() {
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let dir = Path::new(&manifest_dir).join("../../static/chains");
+    println!("cargo:rerun-if-changed={}", dir.display());
+
+    let mut logos = Vec::new();
+    for entry in std::fs::read_dir(&dir).unwrap() {
+        let path = entry.unwrap().path();
+        let (Some(stem), Some(ext)) = (
+            path.file_stem().and_then(|s| s.to_str()),
+            path.extension().and_then(|e| e.to_str()),
+        ) else {
+            continue;
+        };
+        let Ok(chain_id) = stem.parse::<i32>() else {
+            println!("cargo:warning=ignoring {}", path.display());
+            continue;
+        };
+        logos.push((chain_id, ext.to_string(), path.canonicalize().unwrap()));
+    }
+    logos.sort();
+
+    let mut out = String::from("&[\n");
+    for (chain_id, ext, path) in logos {
+        writeln!(
+            out,
+            "    ({chain_id}, {ext:?}, include_bytes!({:?}).as_slice()),",
+            path.display().to_string()
+        )
+        .unwrap();
+    }
+    out.push(']');
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    std::fs::write(Path::new(&out_dir).join("chain_logos.rs"), out).unwrap();
+}
@@ -0,0 +1,317 @@
+//! Recommended Prometheus alerting rules, for `kizami gen-alerts`.
+//!
+//! The rules use the series `/metrics` exports and take their thresholds from the
+//! environment the server would read, so they fire when the server's own health grades
+//! would: per chain, lag past `HEALTH_MAX_LAG_BLOCKS` or a cursor idle past
+//! `HEALTH_MAX_CURSOR_AGE_SECS` (stretched to twice the chain's `EXPECTED_DELAY_SECS`
+//! where that is larger), SQD error rates at `HEALTH_MAX_ERROR_RATE`, stalls when
+//! `CHAIN_STALL_BLOCK_TIMES` is on, p99 latency over `SLO_P99_MS` and clock skew past
+//! `CLOCK_SKEW_TOLERANCE_SECS`. Free disk space comes from node_exporter, since the
+//! server only knows how much it uses.
+//!
+//! Chains that stop ingesting at a sunset block get no lag rule; their cursor stops by
+//! design.
+
+use std::fmt::Write;
+
+use kizami_shared::chains::{ChainConfig, CHAINS};
+use kizami_shared::clock;
+
+use crate::freshness::Freshness;
+use crate::routes::status::{self, HealthThresholds};
+use crate::slo::SloTracker;
+
+/// Deployment details the server's configuration doesn't know.
+#[derive(Debug, Clone)]
+pub struct AlertOptions {
+    /// Prometheus job scraping kizami's `/metrics`.
+    pub job: String,
+    /// Mount point of the data directory, as node_exporter labels it.
+    pub mountpoint: String,
+}
+
+impl Default for AlertOptions {
+    fn default() -> Self {
+        Self {
+            job: "kizami".into(),
+            mountpoint: "/".into(),
+        }
+    }
+}
+
+/// Thresholds the rules are written with.
+struct AlertConfig {
+    health: HealthThresholds,
+    stall_block_times: u32,
+    freshness: Freshness,
+    slo_p99_secs: f64,
+    clock_tolerance_secs: f64,
+}
+
+impl AlertConfig {
+    fn from_env() -> Self {
+        Self {
+            health: status::health_thresholds(),
+            stall_block_times: status::stall_block_times(),
+            freshness: Freshness::from_env(),
+            slo_p99_secs: SloTracker::from_env().p99_threshold_ms() / 1000.0,
+            clock_tolerance_secs: clock::tolerance().num_milliseconds() as f64 / 1000.0,
+        }
+    }
+
+    /// Cursor age past which `chain` counts as lagging.
+    fn max_cursor_age_secs(&self, chain: &ChainConfig) -> i64 {
+        let expected = self.freshness.expected_delay_secs(chain.chain_id);
+        self.health
+            .max_cursor_age_secs
+            .max(expected.unwrap_or(0) * 2)
+    }
+}
+
+struct Rule {
+    alert: &'static str,
+    expr: String,
+    for_: &'static str,
+    severity: &'static str,
+    chain: Option<&'static str>,
+    summary: String,
+}
+
+/// Single-quoted YAML scalar.
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+fn render_group(out: &mut String, name: &str, rules: &[Rule]) {
+    let _ = writeln!(out, "  - name: {name}\n    rules:");
+    for rule in rules {
+        let _ = writeln!(
+            out,
+            "      - alert: {}\n        expr: {}\n        for: {}\n        labels:\n          severity: {}",
+            rule.alert,
+            quote(&rule.expr),
+            rule.for_,
+            rule.severity
+        );
+        if let Some(chain) = rule.chain {
+            let _ = writeln!(out, "          chain: {}", quote(chain));
+        }
+        let _ = writeln!(
+            out,
+            "        annotations:\n          summary: {}",
+            quote(&rule.summary)
+        );
+    }
+}
+
+fn render(config: &AlertConfig, options: &AlertOptions) -> String {
+    let job = format!("job=\"{}\"", options.job);
+    let disk = format!("mountpoint=\"{}\"", options.mountpoint);
+    let health = &config.health;
+
+    let mut out = format!(
+        "# Prometheus alerting rules for kizami, from `kizami gen-alerts`.\n\
+         # HEALTH_MAX_LAG_BLOCKS={} HEALTH_MAX_CURSOR_AGE_SECS={} HEALTH_MAX_ERROR_RATE={}\n\
+         # CHAIN_STALL_BLOCK_TIMES={} SLO_P99_MS={} CLOCK_SKEW_TOLERANCE_SECS={}\n\
+         groups:\n",
+        health.max_lag_blocks,
+        health.max_cursor_age_secs,
+        health.max_error_rate,
+        config.stall_block_times,
+        config.slo_p99_secs * 1000.0,
+        config.clock_tolerance_secs,
+    );
+
+    render_group(
+        &mut out,
+        "kizami-availability",
+        &[Rule {
+            alert: "KizamiDown",
+            expr: format!("up{{{job}}} == 0"),
+            for_: "5m",
+            severity: "critical",
+            chain: None,
+            summary: "kizami on {{ $labels.instance }} is not answering scrapes".into(),
+        }],
+    );
+
+    let mut ingestion: Vec<Rule> = CHAINS
+        .iter()
+        .filter(|chain| chain.sunset_block().is_none())
+        .map(|chain| {
+            let selector = format!("{job},chain_id=\"{}\"", chain.chain_id);
+            let max_age = config.max_cursor_age_secs(chain);
+            Rule {
+                alert: "KizamiChainLagging",
+                expr: format!(
+                    "kizami_chain_lag_blocks{{{selector}}} > {} or kizami_chain_cursor_age_seconds{{{selector}}} > {max_age}",
+                    health.max_lag_blocks
+                ),
+                for_: "15m",
+                severity: "warning",
+                chain: Some(chain.name),
+                summary: format!(
+                    "{} is over {} blocks behind its dataset or its cursor has not moved for {max_age}s",
+                    chain.name, health.max_lag_blocks
+                ),
+            }
+        })
+        .collect();
+    ingestion.push(Rule {
+        alert: "KizamiSqdErroring",
+        expr: format!(
+            "kizami_sqd_error_rate{{{job}}} >= {}",
+            health.max_error_rate
+        ),
+        for_: "10m",
+        severity: "critical",
+        chain: None,
+        summary: "most SQD requests for chain {{ $labels.chain_id }} are failing".into(),
+    });
+    if config.stall_block_times > 0 {
+        ingestion.push(Rule {
+            alert: "KizamiChainStalled",
+            expr: format!("kizami_chain_health{{{job},grade=\"stalled\"}} == 1"),
+            for_: "5m",
+            severity: "warning",
+            chain: None,
+            summary: "chain {{ $labels.chain_id }} or its dataset has stopped producing blocks"
+                .into(),
+        });
+    }
+    render_group(&mut out, "kizami-ingestion", &ingestion);
+
+    render_group(
+        &mut out,
+        "kizami-storage",
+        &[
+            Rule {
+                alert: "KizamiWritePressure",
+                expr: format!("kizami_storage_write_pressure{{{job}}} > 0.8"),
+                for_: "15m",
+                severity: "warning",
+                chain: None,
+                summary: "storage compaction is close to stalling writes".into(),
+            },
+            Rule {
+                alert: "KizamiDiskSpaceLow",
+                expr: format!(
+                    "node_filesystem_avail_bytes{{{disk}}} / node_filesystem_size_bytes{{{disk}}} < 0.1"
+                ),
+                for_: "10m",
+                severity: "critical",
+                chain: None,
+                summary: format!("less than 10% free on {}", options.mountpoint),
+            },
+            Rule {
+                alert: "KizamiDiskFillingUp",
+                expr: format!(
+                    "predict_linear(node_filesystem_avail_bytes{{{disk}}}[6h], 86400) < 0"
+                ),
+                for_: "1h",
+                severity: "warning",
+                chain: None,
+                summary: format!("{} fills up within a day at the current rate", options.mountpoint),
+            },
+        ],
+    );
+
+    render_group(
+        &mut out,
+        "kizami-serving",
+        &[
+            Rule {
+                alert: "KizamiLatencySlo",
+                expr: format!(
+                    "kizami_http_request_duration_seconds{{{job},quantile=\"0.99\"}} > {}",
+                    config.slo_p99_secs
+                ),
+                for_: "15m",
+                severity: "warning",
+                chain: None,
+                summary: "p99 latency of {{ $labels.route }} is over the SLO".into(),
+            },
+            Rule {
+                alert: "KizamiClockSkew",
+                expr: format!(
+                    "abs(kizami_clock_skew_seconds{{{job}}}) > {}",
+                    config.clock_tolerance_secs
+                ),
+                for_: "5m",
+                severity: "warning",
+                chain: None,
+                summary: "host clock is skewed; timestamps are being corrected".into(),
+            },
+        ],
+    );
+    out
+}
+
+/// Alerting rules in Prometheus rule file format, with thresholds from the environment.
+pub fn alert_rules(options: &AlertOptions) -> String {
+    render(&AlertConfig::from_env(), options)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    /// Sources of every series `/metrics` exports.
+    const METRICS_SOURCES: [&str; 4] = [
+        include_str!("routes/slo.rs"),
+        include_str!("slo.rs"),
+        include_str!("exemplars.rs"),
+        include_str!("tenants.rs"),
+    ];
+
+    fn config(expected_delays: HashMap<i32, i64>) -> AlertConfig {
+        AlertConfig {
+            health: HealthThresholds {
+                max_lag_blocks: 500,
+                max_cursor_age_secs: 1_800,
+                max_error_rate: 0.25,
+            },
+            stall_block_times: 0,
+            freshness: Freshness::new(expected_delays),
+            slo_p99_secs: 0.05,
+            clock_tolerance_secs: 2.0,
+        }
+    }
+
+    #[test]
+    fn rules_follow_config_and_use_exported_series() {
+        let options = AlertOptions {
+            job: "kizami-eu".into(),
+            mountpoint: "/var/lib/kizami".into(),
+        };
+        let rules = render(&config(HashMap::from([(8453, 1_200)])), &options);
+
+        // chains with an expected delay get a longer cursor age allowance
+        assert!(rules.contains(
+            "kizami_chain_lag_blocks{job=\"kizami-eu\",chain_id=\"1\"} > 500 or kizami_chain_cursor_age_seconds{job=\"kizami-eu\",chain_id=\"1\"} > 1800"
+        ));
+        assert!(rules.contains(
+            "kizami_chain_cursor_age_seconds{job=\"kizami-eu\",chain_id=\"8453\"} > 2400"
+        ));
+        assert!(rules.contains("kizami_sqd_error_rate{job=\"kizami-eu\"} >= 0.25"));
+        assert!(rules.contains("node_filesystem_avail_bytes{mountpoint=\"/var/lib/kizami\"}"));
+        assert!(!rules.contains("KizamiChainStalled"));
+        let lag_rules = rules.matches("alert: KizamiChainLagging").count();
+        let sunset = CHAINS.iter().filter(|c| c.sunset_block().is_some()).count();
+        assert_eq!(lag_rules, CHAINS.len() - sunset);
+
+        let mut referenced: Vec<&str> = rules
+            .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .filter(|word| word.starts_with("kizami_"))
+            .collect();
+        referenced.dedup();
+        for name in referenced {
+            assert!(
+                METRICS_SOURCES.iter().any(|source| source.contains(name)),
+                "{name} is not exported"
+            );
+        }
+    }
+}
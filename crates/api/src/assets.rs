@@ -0,0 +1,190 @@
+//! Chain logos.
+//!
+//! Every chain's logo is served at `/static/chains/{chain_id}.{ext}`, the URL
+//! `ChainResponse.logo_url` points to. Logos in `static/chains` are bundled into the
+//! binary by `build.rs`; other chains' logos are redirected to a public icon CDN. A
+//! bundled file replaces the CDN logo of its chain.
+//!
+//! The URLs aren't versioned, so responses are cached for a day rather than forever.
+//! Bundled logos carry an `ETag` to make revalidation cheap.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use axum::extract::Path;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use ring::digest;
+
+/// `(chain_id, extension, bytes)` of every file in `static/chains`.
+const BUNDLED: &[(i32, &str, &[u8])] = include!(concat!(env!("OUT_DIR"), "/chain_logos.rs"));
+
+const ICONS: &str = "https://cdn.jsdelivr.net/gh/Amichain/chain-icons/svg";
+const SMOLD: &str = "https://assets.smold.app/api/chain";
+
+/// CDN logos of chains without a bundled one.
+static REMOTE: LazyLock<Vec<(i32, String)>> = LazyLock::new(|| {
+    let icons = [
+        1, 10, 56, 100, 137, 146, 169, 204, 324, 1088, 5000, 7777777, 8453, 42161, 42220, 42793,
+        43114, 57073, 60808, 81457, 167000, 534352,
+    ];
+    let smold = [14, 130, 59144, 80094];
+    icons
+        .into_iter()
+        .map(|id| (id, format!("{ICONS}/{id}.svg")))
+        .chain(
+            smold
+                .into_iter()
+                .map(|id| (id, format!("{SMOLD}/{id}/logo-128.png"))),
+        )
+        .collect()
+});
+
+const CACHE_CONTROL: &str = "public, max-age=86400";
+
+enum Source {
+    Bundled { bytes: &'static [u8], etag: String },
+    Remote(&'static str),
+}
+
+struct Logo {
+    ext: &'static str,
+    source: Source,
+}
+
+static LOGOS: LazyLock<HashMap<i32, Logo>> = LazyLock::new(|| {
+    let remote = REMOTE.iter().map(|(chain_id, url)| {
+        let ext = url.rsplit('.').next().unwrap_or_default();
+        let source = Source::Remote(url);
+        (*chain_id, Logo { ext, source })
+    });
+    let bundled = BUNDLED.iter().map(|&(chain_id, ext, bytes)| {
+        let hash = digest::digest(&digest::SHA256, bytes);
+        let etag = hash.as_ref()[..8]
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<String>();
+        let source = Source::Bundled {
+            bytes,
+            etag: format!("\"{etag}\""),
+        };
+        (chain_id, Logo { ext, source })
+    });
+    remote.chain(bundled).collect()
+});
+
+fn content_type(ext: &str) -> &'static str {
+    match ext {
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "webp" => "image/webp",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Where the logo of `chain_id` is served, if it has one.
+pub fn logo_url(chain_id: i32) -> Option<String> {
+    LOGOS
+        .get(&chain_id)
+        .map(|logo| format!("/static/chains/{chain_id}.{}", logo.ext))
+}
+
+/// Serves `/static/chains/{file}`.
+pub async fn chain_logo(Path(file): Path<String>, headers: HeaderMap) -> Response {
+    let logo = file.split_once('.').and_then(|(id, ext)| {
+        let logo = LOGOS.get(&id.parse().ok()?)?;
+        (logo.ext == ext).then_some(logo)
+    });
+    let Some(logo) = logo else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    match &logo.source {
+        Source::Remote(url) => (
+            StatusCode::FOUND,
+            [
+                (header::LOCATION, *url),
+                (header::CACHE_CONTROL, CACHE_CONTROL),
+            ],
+        )
+            .into_response(),
+        Source::Bundled { bytes, etag } => {
+            let fresh = headers
+                .get(header::IF_NONE_MATCH)
+                .is_some_and(|v| v.as_bytes() == etag.as_bytes());
+            let cache = [
+                (header::ETAG, etag.as_str()),
+                (header::CACHE_CONTROL, CACHE_CONTROL),
+            ];
+            if fresh {
+                return (StatusCode::NOT_MODIFIED, cache).into_response();
+            }
+            (
+                cache,
+                [(header::CONTENT_TYPE, content_type(logo.ext))],
+                *bytes,
+            )
+                .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::routing::get;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    use kizami_shared::chains::{self, CHAINS};
+
+    use super::*;
+
+    #[test]
+    fn every_chain_has_a_logo() {
+        for chain in CHAINS {
+            assert!(logo_url(chain.chain_id).is_some(), "{}", chain.name);
+        }
+        for &(chain_id, ext, _) in BUNDLED {
+            assert!(chains::chain_by_id(chain_id).is_some(), "{chain_id}");
+            assert_ne!(content_type(ext), "application/octet-stream", "{ext}");
+        }
+        assert_eq!(logo_url(4200).as_deref(), Some("/static/chains/4200.webp"));
+        assert_eq!(logo_url(-1), None);
+    }
+
+    #[tokio::test]
+    async fn serves_bundled_logos_and_redirects_remote_ones() {
+        let app = Router::new().route("/static/chains/{file}", get(chain_logo));
+        let get = |uri: &str, etag: Option<&str>| {
+            let mut req = axum::http::Request::get(uri);
+            if let Some(etag) = etag {
+                req = req.header(header::IF_NONE_MATCH, etag);
+            }
+            app.clone().oneshot(req.body(Body::empty()).unwrap())
+        };
+
+        let bundled = get("/static/chains/143.svg", None).await.unwrap();
+        assert_eq!(bundled.status(), StatusCode::OK);
+        assert_eq!(bundled.headers()[header::CONTENT_TYPE], "image/svg+xml");
+        assert_eq!(bundled.headers()[header::CACHE_CONTROL], CACHE_CONTROL);
+        let etag = bundled.headers()[header::ETAG].to_str().unwrap();
+        let revalidated = get("/static/chains/143.svg", Some(etag)).await.unwrap();
+        assert_eq!(revalidated.status(), StatusCode::NOT_MODIFIED);
+
+        let remote = get("/static/chains/8453.svg", None).await.unwrap();
+        assert_eq!(remote.status(), StatusCode::FOUND);
+        assert_eq!(
+            remote.headers()[header::LOCATION],
+            format!("{ICONS}/8453.svg")
+        );
+
+        for missing in [
+            "/static/chains/143.png",
+            "/static/chains/-1.svg",
+            "/static/chains/x",
+        ] {
+            let response = get(missing, None).await.unwrap();
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "{missing}");
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! Scheduled backups taken while the server keeps running.
+//!
+//! When `BACKUP_DIR` is set, a scheduled job copies the database into a new
+//! `kizami-<UTC time>` directory there every `BACKUP_INTERVAL_SECS` (see
+//! `Storage::backup_to`): one fjall snapshot, so the copy is consistent without
+//! pausing ingestion, read at no more than `BACKUP_MAX_MB_PER_SEC`. Each backup is
+//! written under a `.tmp` name and renamed when complete, and only the newest
+//! `BACKUP_KEEP` are kept. A restart doesn't trigger an early backup: the startup run
+//! is skipped while the newest one is younger than the interval.
+//!
+//! To restore, stop the server and point `DATA_DIR` at a copy of a backup directory.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+use kizami_shared::clock;
+use kizami_shared::scheduler::Scheduler;
+use kizami_shared::storage::Storage;
+
+const DEFAULT_INTERVAL_SECS: u64 = 24 * 60 * 60;
+const DEFAULT_KEEP: usize = 3;
+const DEFAULT_MAX_MB_PER_SEC: u64 = 50;
+
+const NAME_PREFIX: &str = "kizami-";
+const NAME_TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// Where backups go, how often they're taken and how many are kept.
+#[derive(Debug, Clone)]
+pub struct Backups {
+    dir: PathBuf,
+    interval: Duration,
+    keep: usize,
+    /// `None` copies as fast as the disk allows.
+    max_bytes_per_sec: Option<u64>,
+}
+
+impl Backups {
+    pub fn new(
+        dir: PathBuf,
+        interval: Duration,
+        keep: usize,
+        max_bytes_per_sec: Option<u64>,
+    ) -> Self {
+        Self {
+            dir,
+            interval,
+            keep: keep.max(1),
+            max_bytes_per_sec,
+        }
+    }
+
+    /// Reads `BACKUP_DIR`, `BACKUP_INTERVAL_SECS` (default one day), `BACKUP_KEEP`
+    /// (default 3) and `BACKUP_MAX_MB_PER_SEC` (default 50, 0 unthrottled). Returns
+    /// `None` (backups disabled) when the directory is unset or the interval is zero.
+    pub fn from_env() -> Option<Self> {
+        let dir = std::env::var("BACKUP_DIR").ok().filter(|d| !d.is_empty())?;
+        let env = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+        let secs = env("BACKUP_INTERVAL_SECS", DEFAULT_INTERVAL_SECS);
+        let keep = env("BACKUP_KEEP", DEFAULT_KEEP as u64);
+        let mb_per_sec = env("BACKUP_MAX_MB_PER_SEC", DEFAULT_MAX_MB_PER_SEC);
+        (secs > 0).then(|| {
+            Self::new(
+                PathBuf::from(dir),
+                Duration::from_secs(secs),
+                keep as usize,
+                (mb_per_sec > 0).then(|| mb_per_sec * 1_000_000),
+            )
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    pub fn keep(&self) -> usize {
+        self.keep
+    }
+
+    pub fn max_bytes_per_sec(&self) -> Option<u64> {
+        self.max_bytes_per_sec
+    }
+
+    /// Finished backups in the directory with the time each was taken, oldest first.
+    fn existing(&self) -> std::io::Result<Vec<(DateTime<Utc>, PathBuf)>> {
+        let mut backups = Vec::new();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let taken_at = name
+                .to_str()
+                .and_then(|n| n.strip_prefix(NAME_PREFIX))
+                .and_then(|t| NaiveDateTime::parse_from_str(t, NAME_TIME_FORMAT).ok());
+            if let Some(taken_at) = taken_at {
+                backups.push((taken_at.and_utc(), entry.path()));
+            }
+        }
+        backups.sort();
+        Ok(backups)
+    }
+
+    /// Deletes backups left half-written by a run that didn't finish.
+    fn remove_unfinished(&self) -> std::io::Result<()> {
+        for entry in std::fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if name.starts_with(NAME_PREFIX) && name.ends_with(".tmp") {
+                std::fs::remove_dir_all(entry.path())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Takes a backup as of `now` unless the newest one is younger than the interval,
+    /// then deletes all but the newest `keep`. Returns the new backup's path, if any.
+    fn run(&self, storage: &Storage, now: DateTime<Utc>) -> Result<Option<PathBuf>, String> {
+        let dir = self.dir.display();
+        std::fs::create_dir_all(&self.dir).map_err(|e| format!("cannot create {dir}: {e}"))?;
+        let existing = self
+            .existing()
+            .map_err(|e| format!("cannot list {dir}: {e}"))?;
+        let interval = chrono::Duration::from_std(self.interval).unwrap_or(chrono::Duration::MAX);
+        if existing
+            .last()
+            .is_some_and(|(taken_at, _)| now - *taken_at < interval)
+        {
+            tracing::debug!("latest backup is recent enough, skipping");
+            return Ok(None);
+        }
+
+        self.remove_unfinished()
+            .map_err(|e| format!("cannot clean up {dir}: {e}"))?;
+        let name = format!("{NAME_PREFIX}{}", now.format(NAME_TIME_FORMAT));
+        let tmp = self.dir.join(format!("{name}.tmp"));
+        let stats = storage
+            .backup_to(&tmp, self.max_bytes_per_sec)
+            .map_err(|e| e.to_string())?;
+        let path = self.dir.join(&name);
+        std::fs::rename(&tmp, &path).map_err(|e| format!("cannot rename {name}: {e}"))?;
+        tracing::info!(
+            path = %path.display(),
+            entries = stats.entries,
+            bytes = stats.bytes,
+            "backup written"
+        );
+
+        let existing = self
+            .existing()
+            .map_err(|e| format!("cannot list {dir}: {e}"))?;
+        let expired = existing.len().saturating_sub(self.keep);
+        for (_, old) in &existing[..expired] {
+            if let Err(e) = std::fs::remove_dir_all(old) {
+                tracing::warn!(path = %old.display(), error = %e, "cannot delete old backup");
+            }
+        }
+        Ok(Some(path))
+    }
+
+    /// Takes backups at startup (if due) and then every interval, as the `backups`
+    /// job. Copying blocks, so it runs on the blocking pool.
+    pub fn schedule(self: Arc<Self>, scheduler: &Scheduler, storage: Storage) {
+        let interval = self.interval;
+        scheduler.spawn("backups", interval, interval / 10, move || {
+            let backups = self.clone();
+            let storage = storage.clone();
+            async move {
+                tokio::task::spawn_blocking(move || backups.run(&storage, clock::now()))
+                    .await
+                    .map_err(|e| e.to_string())?
+                    .map(|_| ())
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn takes_due_backups_and_keeps_the_newest() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path().join("db")).unwrap();
+        storage.insert_blocks(1, &[1, 2], &[100, 112]).unwrap();
+        storage.upsert_cursor(1, 2).unwrap();
+        let backups = Backups::new(
+            dir.path().join("backups"),
+            Duration::from_secs(3600),
+            2,
+            None,
+        );
+        let at = |hour| Utc.with_ymd_and_hms(2026, 3, 1, hour, 0, 0).unwrap();
+
+        let unfinished = backups.dir().join("kizami-20260228T000000Z.tmp");
+        std::fs::create_dir_all(&unfinished).unwrap();
+        let first = backups.run(&storage, at(0)).unwrap().unwrap();
+        assert!(!unfinished.exists());
+        assert!(first.ends_with("kizami-20260301T000000Z"));
+        // younger than the interval, as after a restart
+        assert_eq!(
+            backups.run(&storage, at(0) + Duration::from_secs(60)),
+            Ok(None)
+        );
+        backups.run(&storage, at(1)).unwrap().unwrap();
+        backups.run(&storage, at(2)).unwrap().unwrap();
+
+        let kept: Vec<_> = backups
+            .existing()
+            .unwrap()
+            .into_iter()
+            .map(|(taken_at, _)| taken_at)
+            .collect();
+        assert_eq!(kept, vec![at(1), at(2)]);
+        assert!(!first.exists());
+
+        drop(storage);
+        let restored = Storage::open(backups.dir().join("kizami-20260301T020000Z")).unwrap();
+        assert_eq!(restored.get_cursor(1).unwrap(), 2);
+    }
+}
@@ -0,0 +1,915 @@
+//! Lookup result cache with request coalescing.
+//!
+//! Block lookups are cached in moka keyed by the full query. On a miss, concurrent
+//! identical queries are coalesced so only one of them reads fjall and the rest wait
+//! for its result (single-flight). Without this, a hot timestamp expiring from the
+//! cache turns into a synchronized burst of identical storage reads.
+//!
+//! Entries expire by tier, based on how far the matched block sits behind the indexed
+//! tip. Deep answers (at least [`DEFAULT_DEEP_BLOCKS`] behind) cannot change and keep
+//! the long TTL. Near-tip answers get a short TTL, since a `before` match at the tip
+//! is superseded as soon as the next block is indexed. Misses are never cached. When
+//! ingestion advances a chain, entries whose query timestamp falls in or after the
+//! newly indexed window are invalidated right away rather than waiting out their TTL.
+//! TTLs and the bypass window run on the cache's [`Clock`] (see
+//! [`LookupCache::with_clock`]).
+//!
+//! Individual lookups are logged for 1 in `LOG_SAMPLE_LOOKUPS_EVERY` requests (off by
+//! default); hit and miss totals go out as a periodic `lookup_summary` event instead.
+//!
+//! With `SHARED_CACHE_URL` set, a Redis [`SharedCache`] sits behind moka as a second
+//! level shared by every replica: a local miss asks Redis before storage, and answers
+//! loaded from storage are written through to it, so one replica's read warms them
+//! all. Only deep answers are shared by default. Near-tip ones are invalidated locally
+//! as blocks are indexed, which can't reach copies in Redis, so sharing them
+//! (`SHARED_CACHE_NEAR_TIP_TTL_SECS`) trades that many seconds of staleness for hits.
+//! Redis failing or answering slowly only costs the storage read it would have saved.
+//!
+//! A request can skip the cache to rule it out when an answer looks stale (see
+//! [`LookupCache::reload`]). Bypasses read storage every time, so only
+//! `CACHE_BYPASS_PER_MIN` of them are allowed per minute across all clients; past
+//! that, requests are served from the cache as usual.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use moka::future::Cache;
+use moka::Expiry;
+use tokio::sync::OnceCell;
+
+use kizami_shared::clock::{self, Clock};
+use kizami_shared::error::AppError;
+use kizami_shared::models::{Bound, CacheStatsResponse, Direction};
+use kizami_shared::redis::RedisClient;
+use kizami_shared::scheduler::Scheduler;
+
+/// Default time-to-live for deep lookups. These answers are final, so this only
+/// bounds memory held by cold keys.
+const DEFAULT_TTL_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// Default time-to-live for near-tip lookups, roughly one Ethereum block.
+const DEFAULT_NEAR_TIP_TTL_SECS: u64 = 12;
+
+/// Default number of blocks behind the indexed tip at which an answer counts as deep.
+const DEFAULT_DEEP_BLOCKS: i64 = 1000;
+
+/// Default maximum number of cached lookups.
+const DEFAULT_MAX_ENTRIES: u64 = 100_000;
+
+/// Default cache bypasses allowed per minute.
+const DEFAULT_BYPASS_PER_MIN: u64 = 60;
+
+/// Default time-to-live for deep lookups in the shared cache. Bounds Redis memory.
+const DEFAULT_SHARED_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// Longest a shared cache read may take before the lookup goes to storage instead.
+const SHARED_READ_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// Prefix of shared cache keys in Redis.
+const SHARED_PREFIX: &str = "kizami:lookup:";
+
+/// Length of a bypass window.
+const BYPASS_WINDOW: Duration = Duration::from_secs(60);
+
+/// Fixed-window limit on cache bypasses, shared by all clients.
+#[derive(Debug)]
+struct BypassLimiter {
+    per_minute: u64,
+    /// Start of the current window and bypasses granted in it.
+    window: Mutex<(Instant, u64)>,
+}
+
+impl BypassLimiter {
+    /// A limiter whose first window starts at `now`.
+    fn new(per_minute: u64, now: Instant) -> Self {
+        Self {
+            per_minute,
+            window: Mutex::new((now, 0)),
+        }
+    }
+
+    fn try_acquire(&self, now: Instant) -> bool {
+        let mut window = self.window.lock().unwrap();
+        if now.duration_since(window.0) >= BYPASS_WINDOW {
+            *window = (now, 0);
+        }
+        if window.1 >= self.per_minute {
+            return false;
+        }
+        window.1 += 1;
+        true
+    }
+}
+
+/// Picks 1 in `every` events for logging; 0 picks none.
+#[derive(Debug, Default)]
+struct LogSampler {
+    every: u64,
+    seen: AtomicU64,
+}
+
+impl LogSampler {
+    fn new(every: u64) -> Self {
+        Self {
+            every,
+            seen: AtomicU64::new(0),
+        }
+    }
+
+    fn sample(&self) -> bool {
+        self.every > 0
+            && self
+                .seen
+                .fetch_add(1, Ordering::Relaxed)
+                .is_multiple_of(self.every)
+    }
+}
+
+/// A block lookup query. Two requests with equal keys always have the same answer.
+///
+/// Fixed-size and `Copy` so cache probes on the hot path never allocate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LookupKey {
+    pub chain_id: i32,
+    pub timestamp: i64,
+    pub bound: Bound,
+}
+
+/// Coalesces concurrent calls with the same key into one execution.
+///
+/// Successful results are shared with every waiter. Errors are not: if the leader
+/// fails, the next waiter runs its own attempt.
+pub struct SingleFlight<K, V> {
+    inflight: Mutex<HashMap<K, Arc<OnceCell<V>>>>,
+}
+
+impl<K, V> Default for SingleFlight<K, V> {
+    fn default() -> Self {
+        Self {
+            inflight: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> SingleFlight<K, V> {
+    pub async fn run<F, Fut, E>(&self, key: K, f: F) -> Result<V, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V, E>>,
+    {
+        let cell = {
+            let mut inflight = self.inflight.lock().unwrap();
+            inflight.entry(key.clone()).or_default().clone()
+        };
+
+        let result = cell.get_or_try_init(f).await.cloned();
+
+        // the first finisher clears the slot so later calls start a fresh flight
+        let mut inflight = self.inflight.lock().unwrap();
+        if inflight.get(&key).is_some_and(|c| Arc::ptr_eq(c, &cell)) {
+            inflight.remove(&key);
+        }
+        result
+    }
+}
+
+/// Second-level lookup cache in Redis, shared by every replica pointed at it.
+///
+/// Keys are `kizami:lookup:{chain_id}:{timestamp}:{before|after|nearest}:{0|1}` (the last
+/// part is `inclusive`), values the matched block's number and timestamp as 16
+/// big-endian bytes.
+pub struct SharedCache {
+    client: RedisClient,
+    deep_ttl: Duration,
+    /// Zero keeps near-tip answers out of Redis.
+    near_tip_ttl: Duration,
+    hits: AtomicU64,
+    errors: Arc<AtomicU64>,
+}
+
+impl SharedCache {
+    pub fn new(client: RedisClient, deep_ttl: Duration, near_tip_ttl: Duration) -> Self {
+        Self {
+            client: client.with_timeout(SHARED_READ_TIMEOUT),
+            deep_ttl,
+            near_tip_ttl,
+            hits: AtomicU64::new(0),
+            errors: Arc::default(),
+        }
+    }
+
+    fn redis_key(key: &LookupKey) -> String {
+        let direction = match key.bound.direction() {
+            Direction::Before => "before",
+            Direction::After => "after",
+            Direction::Nearest => "nearest",
+        };
+        format!(
+            "{SHARED_PREFIX}{}:{}:{direction}:{}",
+            key.chain_id,
+            key.timestamp,
+            key.bound.inclusive() as u8
+        )
+    }
+
+    /// The shared answer for `key`, if Redis has one and answers in time.
+    async fn get(&self, key: &LookupKey) -> Option<(i64, i64)> {
+        let redis_key = Self::redis_key(key);
+        let value = match self.client.query(&[b"GET", redis_key.as_bytes()]).await {
+            Ok(reply) => reply.into_data()?,
+            Err(e) => {
+                self.errors.fetch_add(1, Ordering::Relaxed);
+                tracing::debug!(error = %e, "shared cache read failed");
+                return None;
+            }
+        };
+        let row = (
+            i64::from_be_bytes(value.get(..8)?.try_into().ok()?),
+            i64::from_be_bytes(value.get(8..16)?.try_into().ok()?),
+        );
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        Some(row)
+    }
+
+    /// Writes `row` through to Redis for `ttl` in the background, so the request
+    /// doesn't wait on it. A zero `ttl` writes nothing.
+    fn put(&self, key: &LookupKey, row: (i64, i64), ttl: Duration) {
+        if ttl.is_zero() {
+            return;
+        }
+        let redis_key = Self::redis_key(key);
+        let mut value = row.0.to_be_bytes().to_vec();
+        value.extend_from_slice(&row.1.to_be_bytes());
+        let ttl_ms = ttl.as_millis().to_string();
+        let client = self.client.clone();
+        let errors = self.errors.clone();
+        tokio::spawn(async move {
+            let set: &[&[u8]] = &[
+                b"SET",
+                redis_key.as_bytes(),
+                &value,
+                b"PX",
+                ttl_ms.as_bytes(),
+            ];
+            if let Err(e) = client.query(set).await {
+                errors.fetch_add(1, Ordering::Relaxed);
+                tracing::debug!(error = %e, "shared cache write failed");
+            }
+        });
+    }
+}
+
+/// A cached answer with the TTL of the tier it was stored in.
+#[derive(Debug, Clone, Copy)]
+struct CachedRow {
+    row: (i64, i64),
+    ttl: Duration,
+    /// When the TTL runs out on the cache's clock; `None` if that is out of range.
+    /// Moka evicts by the TTL on its own clock, this stops serving the entry by ours.
+    expires_at: Option<Instant>,
+}
+
+/// Expires each entry after the TTL chosen for it at insert time.
+struct TieredExpiry;
+
+impl Expiry<LookupKey, CachedRow> for TieredExpiry {
+    fn expire_after_create(
+        &self,
+        _key: &LookupKey,
+        value: &CachedRow,
+        _created_at: Instant,
+    ) -> Option<Duration> {
+        Some(value.ttl)
+    }
+}
+
+/// Cached, coalesced block lookups.
+pub struct LookupCache {
+    cache: Cache<LookupKey, CachedRow>,
+    flights: SingleFlight<LookupKey, Option<(i64, i64)>>,
+    deep_ttl: Duration,
+    near_tip_ttl: Duration,
+    deep_blocks: i64,
+    max_entries: u64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    bypass: BypassLimiter,
+    bypasses: AtomicU64,
+    bypasses_limited: AtomicU64,
+    /// Hits and misses as of the last `lookup_summary` event.
+    summarized: Mutex<(u64, u64)>,
+    log_sampler: LogSampler,
+    shared: Option<SharedCache>,
+    clock: Arc<dyn Clock>,
+}
+
+impl LookupCache {
+    /// Answers at least `deep_blocks` behind the indexed tip live for `deep_ttl`, the
+    /// rest for `near_tip_ttl`.
+    pub fn new(
+        deep_ttl: Duration,
+        near_tip_ttl: Duration,
+        deep_blocks: i64,
+        max_entries: u64,
+    ) -> Self {
+        let clock = clock::system();
+        Self {
+            cache: Cache::builder()
+                .max_capacity(max_entries)
+                .expire_after(TieredExpiry)
+                .support_invalidation_closures()
+                .build(),
+            flights: SingleFlight::default(),
+            deep_ttl,
+            near_tip_ttl,
+            deep_blocks,
+            max_entries,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            bypass: BypassLimiter::new(DEFAULT_BYPASS_PER_MIN, clock.instant()),
+            bypasses: AtomicU64::new(0),
+            bypasses_limited: AtomicU64::new(0),
+            summarized: Mutex::default(),
+            log_sampler: LogSampler::default(),
+            shared: None,
+            clock,
+        }
+    }
+
+    /// Puts `shared` behind the in-process cache as a second level.
+    pub fn with_shared(mut self, shared: SharedCache) -> Self {
+        self.shared = Some(shared);
+        self
+    }
+
+    /// Logs 1 in `every` lookups as a `job = "lookup"` event; 0 disables them.
+    pub fn with_log_sampling(mut self, every: u64) -> Self {
+        self.log_sampler = LogSampler::new(every);
+        self
+    }
+
+    /// Allows `per_minute` cache bypasses per minute; 0 refuses them all.
+    pub fn with_bypass_limit(mut self, per_minute: u64) -> Self {
+        self.bypass = BypassLimiter::new(per_minute, self.clock.instant());
+        self
+    }
+
+    /// Times TTLs and bypass windows on `clock` instead of the process clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.bypass = BypassLimiter::new(self.bypass.per_minute, clock.instant());
+        self.clock = clock;
+        self
+    }
+
+    /// Reads `CACHE_TTL_SECS` (default 30 days), `CACHE_NEAR_TIP_TTL_SECS` (default 12),
+    /// `CACHE_DEEP_BLOCKS` (default 1000), `CACHE_MAX_ENTRIES` (default 100k),
+    /// `CACHE_BYPASS_PER_MIN` (default 60) and `LOG_SAMPLE_LOOKUPS_EVERY` (default 0),
+    /// and with `SHARED_CACHE_URL` set, `SHARED_CACHE_TTL_SECS` (default 1 day) and
+    /// `SHARED_CACHE_NEAR_TIP_TTL_SECS` (default 0). An invalid URL is logged and
+    /// leaves the cache local.
+    pub fn from_env() -> Self {
+        fn var<T: std::str::FromStr>(name: &str, default: T) -> T {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        }
+        let cache = Self::new(
+            Duration::from_secs(var("CACHE_TTL_SECS", DEFAULT_TTL_SECS)),
+            Duration::from_secs(var("CACHE_NEAR_TIP_TTL_SECS", DEFAULT_NEAR_TIP_TTL_SECS)),
+            var("CACHE_DEEP_BLOCKS", DEFAULT_DEEP_BLOCKS),
+            var("CACHE_MAX_ENTRIES", DEFAULT_MAX_ENTRIES),
+        )
+        .with_bypass_limit(var("CACHE_BYPASS_PER_MIN", DEFAULT_BYPASS_PER_MIN))
+        .with_log_sampling(var("LOG_SAMPLE_LOOKUPS_EVERY", 0));
+        let Some(url) = std::env::var("SHARED_CACHE_URL")
+            .ok()
+            .filter(|v| !v.is_empty())
+        else {
+            return cache;
+        };
+        match RedisClient::from_url(&url) {
+            Ok(client) => cache.with_shared(SharedCache::new(
+                client,
+                Duration::from_secs(var("SHARED_CACHE_TTL_SECS", DEFAULT_SHARED_TTL_SECS)),
+                Duration::from_secs(var("SHARED_CACHE_NEAR_TIP_TTL_SECS", 0)),
+            )),
+            Err(e) => {
+                tracing::warn!(error = %e, "ignoring invalid SHARED_CACHE_URL, caching lookups locally only");
+                cache
+            }
+        }
+    }
+
+    /// Whether an answer matching block `number` is deep with the tip at
+    /// `indexed_up_to`.
+    fn is_deep(&self, number: i64, indexed_up_to: i64) -> bool {
+        indexed_up_to.saturating_sub(number) >= self.deep_blocks
+    }
+
+    /// TTL for an answer matching block `number` with the tip at `indexed_up_to`.
+    fn ttl_for(&self, number: i64, indexed_up_to: i64) -> Duration {
+        if self.is_deep(number, indexed_up_to) {
+            self.deep_ttl
+        } else {
+            self.near_tip_ttl
+        }
+    }
+
+    /// An answer matching block `number` with the tip at `indexed_up_to`, to cache in
+    /// its tier from now.
+    fn cached(&self, row: (i64, i64), indexed_up_to: i64) -> CachedRow {
+        let ttl = self.ttl_for(row.0, indexed_up_to);
+        CachedRow {
+            row,
+            ttl,
+            expires_at: self.clock.instant().checked_add(ttl),
+        }
+    }
+
+    /// Writes an answer loaded from storage through to the shared cache, if any, with
+    /// the shared TTL of its tier.
+    fn share(&self, key: &LookupKey, row: (i64, i64), indexed_up_to: i64) {
+        if let Some(shared) = &self.shared {
+            let ttl = if self.is_deep(row.0, indexed_up_to) {
+                shared.deep_ttl
+            } else {
+                shared.near_tip_ttl
+            };
+            shared.put(key, row, ttl);
+        }
+    }
+
+    /// Drops cached lookups on `chain_id` at or after `from_timestamp`, the start of a
+    /// newly indexed window. Earlier answers are unaffected by blocks appended after them,
+    /// except `nearest` ones that a block at `from_timestamp` would now be closer for.
+    pub fn invalidate_from(&self, chain_id: i32, from_timestamp: i64) {
+        let result = self.cache.invalidate_entries_if(move |k, v| {
+            k.chain_id == chain_id
+                && (k.timestamp >= from_timestamp
+                    || (k.bound == Bound::Nearest
+                        && from_timestamp - k.timestamp < k.timestamp - v.row.1))
+        });
+        if let Err(e) = result {
+            tracing::warn!(chain_id, error = %e, "failed to invalidate cached lookups");
+        }
+    }
+
+    /// Drops every cached lookup, for when invalidations may have been missed.
+    pub fn invalidate_all(&self) {
+        self.cache.invalidate_all();
+    }
+
+    pub fn log_sample_every(&self) -> u64 {
+        self.log_sampler.every
+    }
+
+    pub fn deep_ttl(&self) -> Duration {
+        self.deep_ttl
+    }
+
+    pub fn near_tip_ttl(&self) -> Duration {
+        self.near_tip_ttl
+    }
+
+    pub fn deep_blocks(&self) -> i64 {
+        self.deep_blocks
+    }
+
+    pub fn bypass_per_minute(&self) -> u64 {
+        self.bypass.per_minute
+    }
+
+    /// Address of the shared cache's Redis, if one is configured.
+    pub fn shared_addr(&self) -> Option<&str> {
+        self.shared.as_ref().map(|s| s.client.addr())
+    }
+
+    /// TTLs of deep and near-tip answers in the shared cache, if one is configured.
+    pub fn shared_ttls(&self) -> Option<(Duration, Duration)> {
+        self.shared.as_ref().map(|s| (s.deep_ttl, s.near_tip_ttl))
+    }
+
+    /// Size and hit counters since startup.
+    pub fn stats(&self) -> CacheStatsResponse {
+        CacheStatsResponse {
+            entries: self.cache.entry_count(),
+            max_entries: self.max_entries,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            bypasses: self.bypasses.load(Ordering::Relaxed),
+            bypasses_limited: self.bypasses_limited.load(Ordering::Relaxed),
+            shared_hits: self
+                .shared
+                .as_ref()
+                .map_or(0, |s| s.hits.load(Ordering::Relaxed)),
+            shared_errors: self
+                .shared
+                .as_ref()
+                .map_or(0, |s| s.errors.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Takes one bypass from this minute's allowance. False once it is spent.
+    pub fn try_bypass(&self) -> bool {
+        let allowed = self.bypass.try_acquire(self.clock.instant());
+        let counter = if allowed {
+            &self.bypasses
+        } else {
+            &self.bypasses_limited
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        allowed
+    }
+
+    /// Runs `load` without consulting the cache or joining an in-flight lookup, and
+    /// replaces whatever was cached for `key` with the result. Callers take a
+    /// [`Self::try_bypass`] first.
+    pub async fn reload<F, Fut>(
+        &self,
+        key: LookupKey,
+        indexed_up_to: i64,
+        load: F,
+    ) -> Result<Option<(i64, i64)>, AppError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Option<(i64, i64)>, AppError>>,
+    {
+        let result = load().await?;
+        match result {
+            Some(row) => {
+                self.cache
+                    .insert(key, self.cached(row, indexed_up_to))
+                    .await;
+                self.share(&key, row, indexed_up_to);
+            }
+            None => self.cache.invalidate(&key).await,
+        }
+        self.log_lookup(&key, "bypass", result);
+        Ok(result)
+    }
+
+    /// Returns the cached answer for `key`, or runs `load` (coalesced with any
+    /// identical in-flight lookup) and caches the result in its tier. With a shared
+    /// cache, a local miss is looked up there before running `load`.
+    pub async fn get_or_load<F, Fut>(
+        &self,
+        key: LookupKey,
+        indexed_up_to: i64,
+        load: F,
+    ) -> Result<Option<(i64, i64)>, AppError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Option<(i64, i64)>, AppError>>,
+    {
+        let now = self.clock.instant();
+        let hit = self.cache.get(&key).await;
+        if let Some(hit) = hit.filter(|h| h.expires_at.is_none_or(|at| at > now)) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            self.log_lookup(&key, "hit", Some(hit.row));
+            return Ok(Some(hit.row));
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let result = self
+            .flights
+            .run(key, || async {
+                if let Some(shared) = &self.shared {
+                    if let Some(row) = shared.get(&key).await {
+                        return Ok(Some(row));
+                    }
+                }
+                let result = load().await?;
+                if let Some(row) = result {
+                    self.share(&key, row, indexed_up_to);
+                }
+                Ok::<_, AppError>(result)
+            })
+            .await?;
+        if let Some(row) = result {
+            self.cache
+                .insert(key, self.cached(row, indexed_up_to))
+                .await;
+        }
+        self.log_lookup(&key, "miss", result);
+        Ok(result)
+    }
+
+    fn log_lookup(&self, key: &LookupKey, cache: &'static str, row: Option<(i64, i64)>) {
+        if !self.log_sampler.sample() {
+            return;
+        }
+        tracing::info!(
+            job = "lookup",
+            chain_id = key.chain_id,
+            timestamp = key.timestamp,
+            direction = ?key.bound.direction(),
+            inclusive = key.bound.inclusive(),
+            cache = cache,
+            block = ?row.map(|(number, _)| number),
+            sample_every = self.log_sampler.every,
+        );
+    }
+
+    /// Logs hit and miss counts since the previous call as a `job = "lookup_summary"`
+    /// event, skipping idle intervals.
+    fn log_summary(&self, interval: Duration) {
+        let stats = self.stats();
+        let mut summarized = self.summarized.lock().unwrap();
+        let (hits, misses) = *summarized;
+        let (new_hits, new_misses) = (stats.hits - hits, stats.misses - misses);
+        *summarized = (stats.hits, stats.misses);
+        if new_hits + new_misses == 0 {
+            return;
+        }
+        tracing::info!(
+            job = "lookup_summary",
+            interval_secs = interval.as_secs(),
+            lookups = new_hits + new_misses,
+            cache_hits = new_hits,
+            cache_misses = new_misses,
+            cache_entries = stats.entries,
+        );
+    }
+
+    /// Logs a lookup summary every `interval` as the `lookup_summary` job.
+    pub fn schedule_summaries(self: Arc<Self>, scheduler: &Scheduler, interval: Duration) {
+        scheduler.spawn("lookup_summary", interval, Duration::ZERO, move || {
+            self.log_summary(interval);
+            std::future::ready(Ok::<_, Infallible>(()))
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use chrono::Utc;
+    use kizami_shared::clock::MockClock;
+
+    use super::*;
+
+    fn key(timestamp: i64, bound: Bound) -> LookupKey {
+        LookupKey {
+            chain_id: 1,
+            timestamp,
+            bound,
+        }
+    }
+
+    #[test]
+    fn log_sampler_picks_one_in_n() {
+        let sampler = LogSampler::new(3);
+        let picked: Vec<bool> = (0..7).map(|_| sampler.sample()).collect();
+        assert_eq!(picked, [true, false, false, true, false, false, true]);
+        assert!(!LogSampler::new(0).sample());
+        assert!((0..5).all(|_| LogSampler::new(1).sample()));
+    }
+
+    #[tokio::test]
+    async fn concurrent_misses_share_one_load() {
+        let cache = Arc::new(LookupCache::new(
+            Duration::from_secs(60),
+            Duration::from_secs(60),
+            0,
+            100,
+        ));
+        let loads = Arc::new(AtomicUsize::new(0));
+
+        let tasks: Vec<_> = (0..10)
+            .map(|_| {
+                let cache = cache.clone();
+                let loads = loads.clone();
+                tokio::spawn(async move {
+                    cache
+                        .get_or_load(key(1000, Bound::AtOrAfter), 0, || async move {
+                            loads.fetch_add(1, Ordering::SeqCst);
+                            tokio::time::sleep(Duration::from_millis(50)).await;
+                            Ok(Some((100, 1000)))
+                        })
+                        .await
+                })
+            })
+            .collect();
+
+        for task in tasks {
+            assert_eq!(task.await.unwrap().unwrap(), Some((100, 1000)));
+        }
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+
+        let hit = cache
+            .get_or_load(key(1000, Bound::AtOrAfter), 0, || async { Ok(None) })
+            .await
+            .unwrap();
+        assert_eq!(hit, Some((100, 1000)));
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.max_entries), (1, 10, 100));
+    }
+
+    #[tokio::test]
+    async fn replicas_share_deep_answers_through_redis() {
+        let redis = kizami_fixtures::redis::MockRedis::spawn().await;
+        let replica = || {
+            let client = RedisClient::from_url(&redis.url()).unwrap();
+            tiered().with_shared(SharedCache::new(
+                client,
+                Duration::from_secs(60),
+                Duration::ZERO,
+            ))
+        };
+        let (a, b) = (replica(), replica());
+        let (deep, near_tip) = (key(1000, Bound::AtOrAfter), key(9000, Bound::AtOrBefore));
+
+        assert_eq!(
+            a.get_or_load(deep, 5000, || async { Ok(Some((100, 1000))) })
+                .await
+                .unwrap(),
+            Some((100, 1000))
+        );
+        a.get_or_load(near_tip, 5000, || async { Ok(Some((4990, 8990))) })
+            .await
+            .unwrap();
+        // written through in the background
+        let stored = "kizami:lookup:1:1000:after:1";
+        for _ in 0..100 {
+            if redis.get(stored).is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert_eq!(redis.get(stored).unwrap().len(), 16);
+        assert_eq!(redis.get("kizami:lookup:1:9000:before:1"), None);
+
+        let from_redis = b
+            .get_or_load(deep, 5000, || async { panic!("should come from redis") })
+            .await
+            .unwrap();
+        assert_eq!(from_redis, Some((100, 1000)));
+        let from_storage = b
+            .get_or_load(near_tip, 5000, || async { Ok(Some((4990, 8990))) })
+            .await
+            .unwrap();
+        assert_eq!(from_storage, Some((4990, 8990)));
+        let stats = b.stats();
+        assert_eq!(
+            (stats.misses, stats.shared_hits, stats.shared_errors),
+            (2, 1, 0)
+        );
+    }
+
+    #[tokio::test]
+    async fn unreachable_redis_falls_back_to_storage() {
+        let client = RedisClient::from_url("redis://127.0.0.1:1").unwrap();
+        let cache = tiered().with_shared(SharedCache::new(
+            client,
+            Duration::from_secs(60),
+            Duration::ZERO,
+        ));
+        let row = cache
+            .get_or_load(key(1000, Bound::AtOrAfter), 5000, || async {
+                Ok(Some((100, 1000)))
+            })
+            .await
+            .unwrap();
+        assert_eq!(row, Some((100, 1000)));
+        assert!(cache.stats().shared_errors >= 1);
+    }
+
+    fn tiered() -> LookupCache {
+        LookupCache::new(
+            Duration::from_secs(60),
+            Duration::from_millis(50),
+            1000,
+            100,
+        )
+    }
+
+    #[tokio::test]
+    async fn near_tip_answer_expires_quickly() {
+        let clock = Arc::new(MockClock::new(Utc::now()));
+        let cache = tiered().with_clock(clock.clone());
+        let k = key(5000, Bound::AtOrBefore);
+
+        // block 102 is the indexed tip, so a closer block may still appear
+        let first = cache.get_or_load(k, 102, || async { Ok(Some((102, 4000))) });
+        assert_eq!(first.await.unwrap(), Some((102, 4000)));
+
+        let cached = cache.get_or_load(k, 103, || async { Ok(Some((103, 4500))) });
+        assert_eq!(cached.await.unwrap(), Some((102, 4000)));
+
+        clock.advance(Duration::from_millis(150));
+        let refreshed = cache.get_or_load(k, 103, || async { Ok(Some((103, 4500))) });
+        assert_eq!(refreshed.await.unwrap(), Some((103, 4500)));
+    }
+
+    #[tokio::test]
+    async fn deep_answer_outlives_near_tip_ttl() {
+        let clock = Arc::new(MockClock::new(Utc::now()));
+        let cache = tiered().with_clock(clock.clone());
+        let k = key(1000, Bound::AtOrBefore);
+
+        let first = cache.get_or_load(k, 5000, || async { Ok(Some((100, 1000))) });
+        assert_eq!(first.await.unwrap(), Some((100, 1000)));
+
+        clock.advance(Duration::from_millis(150));
+        let second = cache.get_or_load(k, 5000, || async { Ok(None) });
+        assert_eq!(second.await.unwrap(), Some((100, 1000)));
+    }
+
+    #[tokio::test]
+    async fn errors_are_not_cached() {
+        let cache = LookupCache::new(Duration::from_secs(60), Duration::from_secs(60), 0, 100);
+        let k = key(1000, Bound::AtOrAfter);
+
+        let err = cache
+            .get_or_load(k, 0, || async { Err(AppError::CorruptData("boom".into())) })
+            .await;
+        assert!(err.is_err());
+
+        let ok = cache.get_or_load(k, 0, || async { Ok(Some((1, 1000))) });
+        assert_eq!(ok.await.unwrap(), Some((1, 1000)));
+    }
+
+    #[tokio::test]
+    async fn invalidate_from_drops_entries_in_new_window() {
+        let cache = LookupCache::new(Duration::from_secs(60), Duration::from_secs(60), 0, 100);
+        let old = key(1000, Bound::AtOrBefore);
+        let tip = key(5000, Bound::AtOrBefore);
+        let other_chain = LookupKey { chain_id: 2, ..tip };
+
+        for k in [old, tip, other_chain] {
+            cache
+                .get_or_load(k, 0, || async { Ok(Some((1, 1))) })
+                .await
+                .unwrap();
+        }
+        // a nearest answer the new window can beat goes too; one it can't stays
+        let beaten = key(3990, Bound::Nearest);
+        let kept = key(3000, Bound::Nearest);
+        for k in [beaten, kept] {
+            cache
+                .get_or_load(k, 0, || async { Ok(Some((1, 2900))) })
+                .await
+                .unwrap();
+        }
+        cache.invalidate_from(1, 4000);
+
+        let reload = || async { Ok(Some((2, 2))) };
+        assert_eq!(
+            cache.get_or_load(beaten, 0, reload).await.unwrap(),
+            Some((2, 2))
+        );
+        assert_eq!(
+            cache.get_or_load(kept, 0, reload).await.unwrap(),
+            Some((1, 2900))
+        );
+        assert_eq!(
+            cache.get_or_load(old, 0, reload).await.unwrap(),
+            Some((1, 1))
+        );
+        assert_eq!(
+            cache.get_or_load(tip, 0, reload).await.unwrap(),
+            Some((2, 2))
+        );
+        assert_eq!(
+            cache.get_or_load(other_chain, 0, reload).await.unwrap(),
+            Some((1, 1))
+        );
+    }
+
+    #[tokio::test]
+    async fn reload_skips_and_refreshes_the_cache_within_its_limit() {
+        let clock = Arc::new(MockClock::new(Utc::now()));
+        let cache = tiered().with_bypass_limit(1).with_clock(clock.clone());
+        let k = key(1000, Bound::AtOrBefore);
+        let stale = cache.get_or_load(k, 5000, || async { Ok(Some((1, 1))) });
+        assert_eq!(stale.await.unwrap(), Some((1, 1)));
+
+        assert!(cache.try_bypass());
+        let fresh = cache.reload(k, 5000, || async { Ok(Some((2, 2))) });
+        assert_eq!(fresh.await.unwrap(), Some((2, 2)));
+        let cached = cache.get_or_load(k, 5000, || async { Ok(None) });
+        assert_eq!(cached.await.unwrap(), Some((2, 2)));
+
+        assert!(!cache.try_bypass());
+        let stats = cache.stats();
+        assert_eq!((stats.bypasses, stats.bypasses_limited), (1, 1));
+        clock.advance(BYPASS_WINDOW);
+        assert!(cache.try_bypass());
+        assert!(!tiered().with_bypass_limit(0).try_bypass());
+    }
+
+    #[test]
+    fn ttl_tiers_by_depth() {
+        let cache = tiered();
+        assert_eq!(cache.ttl_for(4000, 5000), Duration::from_secs(60));
+        assert_eq!(cache.ttl_for(4001, 5000), Duration::from_millis(50));
+        assert_eq!(cache.ttl_for(5000, 5000), Duration::from_millis(50));
+    }
+}
@@ -0,0 +1,218 @@
+//! JSON Schema of the deployment configuration.
+//!
+//! kizami is configured entirely through environment variables, so the schema
+//! describes an object of them: a `.env` file loaded as JSON or YAML, a compose
+//! `environment:` map, a Kubernetes `env` rendered to a map. Editors and CI can
+//! validate a deployment against it with `kizami config-schema`.
+//!
+//! Descriptions are taken from the variable list in the binary's docs (`main.rs`), the
+//! same text the readme table is written from; [`SETTINGS`] only adds the value type.
+//! A test keeps the two in step.
+
+use serde_json::{json, Map, Value};
+
+/// Accepted values of a setting.
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    /// A non-negative integer.
+    Integer,
+    /// A non-negative decimal.
+    Number,
+    /// `true`/`false`/`1`/`0`.
+    Bool,
+    /// One of a fixed set of words.
+    OneOf(&'static [&'static str]),
+    /// Free-form text: paths, lists, filters, secrets.
+    Text,
+}
+
+impl Kind {
+    /// Environment values are strings, but JSON and YAML files often hold them as
+    /// numbers or booleans, so both spellings validate.
+    fn schema(self) -> Value {
+        match self {
+            Self::Integer => json!({
+                "type": ["integer", "string"],
+                "minimum": 0,
+                "pattern": "^\\s*[0-9]+\\s*$",
+            }),
+            Self::Number => json!({
+                "type": ["number", "string"],
+                "minimum": 0,
+                "pattern": "^\\s*[0-9]+(\\.[0-9]+)?\\s*$",
+            }),
+            Self::Bool => json!({ "enum": [true, false, "true", "false", "1", "0"] }),
+            Self::OneOf(words) => json!({ "type": "string", "enum": words }),
+            Self::Text => json!({ "type": "string" }),
+        }
+    }
+}
+
+/// Every documented setting and its type.
+const SETTINGS: &[(&str, Kind)] = &[
+    ("DATA_DIR", Kind::Text),
+    ("PORT", Kind::Integer),
+    ("REUSE_PORT", Kind::Bool),
+    ("LISTEN_FDS", Kind::Integer),
+    ("LISTEN_PID", Kind::Integer),
+    ("HTTP2", Kind::Bool),
+    ("HTTP_KEEP_ALIVE", Kind::Bool),
+    ("HTTP_KEEP_ALIVE_TIMEOUT_SECS", Kind::Integer),
+    ("HTTP2_KEEP_ALIVE_INTERVAL_SECS", Kind::Integer),
+    ("HTTP2_MAX_CONCURRENT_STREAMS", Kind::Integer),
+    ("MAX_BODY_BYTES", Kind::Integer),
+    ("SERVED_BY", Kind::Text),
+    ("DRAIN_TIMEOUT_SECS", Kind::Integer),
+    ("TLS_CERT_FILE", Kind::Text),
+    ("TLS_KEY_FILE", Kind::Text),
+    ("TLS_CLIENT_CA_FILE", Kind::Text),
+    ("CLIENT_CERT_MODE", Kind::OneOf(&["required", "admin"])),
+    ("ADMIN_CLIENT_CERTS", Kind::Text),
+    ("RUST_LOG", Kind::Text),
+    ("LOG_FORMAT", Kind::OneOf(&["json", "pretty", "compact"])),
+    ("LOG_COLOR", Kind::Bool),
+    ("LOG_LEVELS", Kind::Text),
+    ("LOG_SAMPLE_INGEST_EVERY", Kind::Integer),
+    ("LOG_SAMPLE_LOOKUPS_EVERY", Kind::Integer),
+    ("LOG_SUMMARY_INTERVAL_SECS", Kind::Integer),
+    ("INGEST_INTERVAL_SECS", Kind::Integer),
+    ("WORKER_THREADS", Kind::Integer),
+    ("MAX_BLOCKING_THREADS", Kind::Integer),
+    ("BLOCKING_THREAD_KEEP_ALIVE_SECS", Kind::Integer),
+    ("INGEST_WORKER_THREADS", Kind::Integer),
+    ("INGEST_MAX_BLOCKING_THREADS", Kind::Integer),
+    ("HEAD_POLL_INTERVAL_SECS", Kind::Integer),
+    ("RPC_URLS", Kind::Text),
+    ("SQD_REQUESTS_PER_CYCLE", Kind::Integer),
+    ("SQD_DAILY_EGRESS_BUDGET_MB", Kind::Integer),
+    ("TIMESTAMP_JUMP_ALERT_SECS", Kind::Integer),
+    ("WORK_QUEUE_INTERVAL_SECS", Kind::Integer),
+    ("CATCHUP_LAG_SECS", Kind::Integer),
+    ("CATCHUP_PARALLELISM", Kind::Integer),
+    ("INGEST_ERROR_LOG_SIZE", Kind::Integer),
+    ("CLOCK_SKEW_TOLERANCE_SECS", Kind::Number),
+    ("NTP_SERVER", Kind::Text),
+    (
+        "PERSIST_MODE",
+        Kind::OneOf(&["batch", "periodic", "buffer"]),
+    ),
+    ("PERSIST_EVERY_N_CYCLES", Kind::Integer),
+    ("CURSOR_CHECK_EVERY_N_CYCLES", Kind::Integer),
+    ("CURSOR_HEAL", Kind::Bool),
+    ("ADMIN_TOKEN", Kind::Text),
+    ("SLO_P99_MS", Kind::Number),
+    ("CHAIN_STALL_BLOCK_TIMES", Kind::Integer),
+    ("HEALTH_MAX_LAG_BLOCKS", Kind::Integer),
+    ("HEALTH_MAX_CURSOR_AGE_SECS", Kind::Integer),
+    ("HEALTH_MAX_ERROR_RATE", Kind::Number),
+    ("EXPECTED_DELAY_SECS", Kind::Text),
+    (
+        "DEFAULT_DIRECTION",
+        Kind::OneOf(&["before", "after", "nearest"]),
+    ),
+    ("DEFAULT_INCLUSIVE", Kind::Bool),
+    ("CACHE_TTL_SECS", Kind::Integer),
+    ("CACHE_NEAR_TIP_TTL_SECS", Kind::Integer),
+    ("CACHE_DEEP_BLOCKS", Kind::Integer),
+    ("CACHE_MAX_ENTRIES", Kind::Integer),
+    ("CACHE_BYPASS_PER_MIN", Kind::Integer),
+    ("SHARED_CACHE_URL", Kind::Text),
+    ("SHARED_CACHE_TTL_SECS", Kind::Integer),
+    ("SHARED_CACHE_NEAR_TIP_TTL_SECS", Kind::Integer),
+    ("GENESIS_TIMESTAMPS", Kind::Text),
+    ("CHAIN_ALIASES", Kind::Text),
+    ("APPROXIMATE_CHAINS", Kind::Text),
+    ("CURSOR_HISTORY_INTERVAL_SECS", Kind::Integer),
+    ("CURSOR_HISTORY_RETENTION_DAYS", Kind::Integer),
+    ("INDEX_SNAPSHOT_INTERVAL_SECS", Kind::Integer),
+    ("BACKUP_DIR", Kind::Text),
+    ("BACKUP_INTERVAL_SECS", Kind::Integer),
+    ("BACKUP_KEEP", Kind::Integer),
+    ("BACKUP_MAX_MB_PER_SEC", Kind::Integer),
+    ("RANGE_MAX_WINDOW_DAYS", Kind::Integer),
+    ("RANGE_MAX_ROWS", Kind::Integer),
+    ("PAGINATION_SECRET", Kind::Text),
+    ("IDEMPOTENCY_TTL_SECS", Kind::Integer),
+    ("DEMO_MODE", Kind::Bool),
+    ("DEMO_RATE_LIMIT_PER_MIN", Kind::Integer),
+    ("DEMO_TRUST_FORWARDED_FOR", Kind::Bool),
+    ("DEMO_ATTRIBUTION", Kind::Text),
+    ("TRUSTED_NETWORKS", Kind::Text),
+    ("INTERNAL_TOKEN", Kind::Text),
+    ("TENANTS", Kind::Text),
+    ("TENANT_RATE_LIMIT_PER_MIN", Kind::Integer),
+    ("TENANT_STORE_URL", Kind::Text),
+];
+
+/// The binary's docs, whose `` - `NAME`: description `` lines describe each setting.
+const BINARY_DOCS: &str = include_str!("main.rs");
+
+/// Descriptions by setting name. A line documenting several names (`` `A`/`B` ``)
+/// describes each of them.
+fn documented() -> Vec<(&'static str, &'static str)> {
+    BINARY_DOCS
+        .lines()
+        .filter_map(|line| line.strip_prefix("//! - "))
+        .filter_map(|line| line.split_once(": "))
+        .flat_map(|(names, description)| {
+            names
+                .split('/')
+                .map(move |name| (name.trim_matches('`'), description))
+        })
+        .collect()
+}
+
+/// The JSON Schema (draft 2020-12) of the environment kizami reads. Other variables
+/// are allowed, since a deployment's environment holds more than kizami's settings.
+pub fn config_schema() -> Value {
+    let docs = documented();
+    let properties: Map<String, Value> = SETTINGS
+        .iter()
+        .map(|&(name, kind)| {
+            let mut schema = kind.schema();
+            if let Some((_, description)) = docs.iter().find(|(doc, _)| *doc == name) {
+                schema["description"] = json!(description);
+            }
+            (name.to_string(), schema)
+        })
+        .collect();
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "kizami configuration",
+        "description": "Environment variables read by kizami-api. Values may be strings, as in the environment, or the JSON types they parse as.",
+        "type": "object",
+        "properties": properties,
+        "additionalProperties": true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_documented_setting_has_a_type_and_a_description() {
+        let docs = documented();
+        assert!(docs.len() > 50);
+        for (name, _) in &docs {
+            assert!(
+                SETTINGS.iter().any(|(setting, _)| setting == name),
+                "{name} is documented but has no type"
+            );
+        }
+        let schema = config_schema();
+        let properties = schema["properties"].as_object().unwrap();
+        for (name, property) in properties {
+            assert!(
+                property["description"].is_string(),
+                "{name} is undocumented"
+            );
+        }
+        assert_eq!(properties.len(), SETTINGS.len());
+        assert_eq!(properties["PERSIST_MODE"]["enum"][1], "periodic");
+        assert!(properties["TLS_KEY_FILE"]["description"]
+            .as_str()
+            .unwrap()
+            .starts_with("PEM certificate chain and key"));
+    }
+}
@@ -0,0 +1,126 @@
+//! Persisted history of ingestion cursors.
+//!
+//! A scheduled job copies every chain's persisted cursor into the `cursor_history`
+//! keyspace every `CURSOR_HISTORY_INTERVAL_SECS`, so "how far was chain X indexed at
+//! time T" can be answered long after the fact, across restarts (see
+//! `/v1/admin/chains/{id}/ingestion/cursor-at`). Entries older than
+//! `CURSOR_HISTORY_RETENTION_DAYS` are pruned as new ones are written.
+
+use std::time::Duration;
+
+use kizami_shared::clock;
+use kizami_shared::error::AppError;
+use kizami_shared::scheduler::Scheduler;
+use kizami_shared::storage::Storage;
+
+const DEFAULT_INTERVAL_SECS: u64 = 300;
+const DEFAULT_RETENTION_DAYS: u64 = 90;
+
+/// How often cursors are recorded and how long entries are kept.
+#[derive(Debug, Clone, Copy)]
+pub struct CursorHistory {
+    interval: Duration,
+    /// `None` keeps entries forever.
+    retention: Option<Duration>,
+}
+
+impl CursorHistory {
+    pub fn new(interval: Duration, retention: Option<Duration>) -> Self {
+        Self {
+            interval,
+            retention,
+        }
+    }
+
+    /// Reads `CURSOR_HISTORY_INTERVAL_SECS` (default 300) and
+    /// `CURSOR_HISTORY_RETENTION_DAYS` (default 90, 0 keeps everything). Returns `None`
+    /// (history disabled) when the interval is zero.
+    pub fn from_env() -> Option<Self> {
+        let env = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+        let secs = env("CURSOR_HISTORY_INTERVAL_SECS", DEFAULT_INTERVAL_SECS);
+        let days = env("CURSOR_HISTORY_RETENTION_DAYS", DEFAULT_RETENTION_DAYS);
+        (secs > 0).then(|| {
+            Self::new(
+                Duration::from_secs(secs),
+                (days > 0).then(|| Duration::from_secs(days * 24 * 60 * 60)),
+            )
+        })
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    pub fn retention(&self) -> Option<Duration> {
+        self.retention
+    }
+
+    /// Records the current cursors as of `now` (Unix seconds) and prunes expired
+    /// entries.
+    fn record(&self, storage: &Storage, now: i64) -> Result<(), AppError> {
+        let cursors: Vec<(i32, i64)> = storage
+            .get_all_cursors()?
+            .into_iter()
+            .map(|(chain_id, last_block, _)| (chain_id, last_block))
+            .collect();
+        storage.record_cursor_history(now, &cursors)?;
+        if let Some(retention) = self.retention {
+            let pruned = storage.prune_cursor_history(now - retention.as_secs() as i64)?;
+            if pruned > 0 {
+                tracing::debug!(pruned, "expired cursor history pruned");
+            }
+        }
+        Ok(())
+    }
+
+    /// Records cursors now and then every interval, as the `cursor_history` job.
+    /// Failures are retried on the next run.
+    pub fn schedule(self, scheduler: &Scheduler, storage: Storage) {
+        scheduler.spawn(
+            "cursor_history",
+            self.interval,
+            self.interval / 10,
+            move || {
+                let storage = storage.clone();
+                async move {
+                    tokio::task::spawn_blocking(move || {
+                        self.record(&storage, clock::now().timestamp())
+                    })
+                    .await
+                    .map_err(|e| e.to_string())?
+                    .map_err(|e| e.to_string())
+                }
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_cursors_and_prunes_expired_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        let history = CursorHistory::new(Duration::from_secs(60), Some(Duration::from_secs(100)));
+
+        storage.upsert_cursor(1, 10).unwrap();
+        history.record(&storage, 1_000).unwrap();
+        storage.upsert_cursor(1, 20).unwrap();
+        history.record(&storage, 1_060).unwrap();
+        assert_eq!(storage.cursor_at(1, 1_059).unwrap(), Some((1_000, 10)));
+
+        storage.upsert_cursor(1, 30).unwrap();
+        history.record(&storage, 1_120).unwrap();
+        // the 1000 entry fell out of the 100s retention window
+        assert_eq!(storage.cursor_at(1, 1_059).unwrap(), None);
+        assert_eq!(storage.cursor_at(1, 1_119).unwrap(), Some((1_060, 20)));
+        assert_eq!(storage.cursor_at(1, i64::MAX).unwrap(), Some((1_120, 30)));
+    }
+}
@@ -0,0 +1,312 @@
+//! Public demo mode.
+//!
+//! When `DEMO_MODE` is enabled the server is assumed to be the hosted public instance:
+//! operator surfaces (admin API, metrics) are switched off, every `/v1` request counts
+//! against a per-IP quota, and responses carry attribution headers pointing people at
+//! self-hosting. Docs, the landing page and `/health` are not rate limited, so the "try
+//! it" flow in the docs keeps working. Neither are trusted internal clients (see
+//! [`crate::trust`]).
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::{HeaderName, HeaderValue};
+use axum::middleware::Next;
+use axum::response::Response;
+use moka::future::Cache;
+
+use kizami_shared::error::AppError;
+
+use crate::trust::Trusted;
+
+/// Path prefixes that are unavailable in demo mode.
+const DISABLED_PREFIXES: &[&str] = &["/v1/admin", "/metrics"];
+
+/// Path suffixes that are unavailable in demo mode: bulk exports, which a per-request
+/// quota doesn't bound.
+const DISABLED_SUFFIXES: &[&str] = &["/blocks/export"];
+
+/// Only API routes count against the quota.
+const LIMITED_PREFIX: &str = "/v1/";
+
+/// Default requests allowed per IP per window.
+const DEFAULT_REQUESTS_PER_WINDOW: u32 = 60;
+
+/// Length of a quota window.
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Maximum number of client IPs tracked at once; the least recently seen are evicted.
+const MAX_TRACKED_CLIENTS: u64 = 100_000;
+
+const DEFAULT_ATTRIBUTION: &str =
+    "Kizami public demo (https://github.com/prettyirrelevant/kizami); self-host for production use";
+
+static X_KIZAMI_DEMO: HeaderName = HeaderName::from_static("x-kizami-demo");
+static X_RATELIMIT_LIMIT: HeaderName = HeaderName::from_static("x-ratelimit-limit");
+static X_RATELIMIT_REMAINING: HeaderName = HeaderName::from_static("x-ratelimit-remaining");
+
+/// Request count for one client within the current fixed window.
+struct Window {
+    started: Instant,
+    count: u32,
+}
+
+/// Demo mode settings and per-IP quota state.
+pub struct DemoMode {
+    requests_per_window: u32,
+    trust_forwarded_for: bool,
+    attribution: HeaderValue,
+    clients: Cache<IpAddr, Arc<Mutex<Window>>>,
+}
+
+impl DemoMode {
+    pub fn new(requests_per_window: u32, trust_forwarded_for: bool, attribution: &str) -> Self {
+        Self {
+            requests_per_window,
+            trust_forwarded_for,
+            attribution: HeaderValue::from_str(attribution)
+                .unwrap_or_else(|_| HeaderValue::from_static(DEFAULT_ATTRIBUTION)),
+            clients: Cache::builder()
+                .max_capacity(MAX_TRACKED_CLIENTS)
+                .time_to_idle(WINDOW * 2)
+                .build(),
+        }
+    }
+
+    /// Reads `DEMO_MODE`, `DEMO_RATE_LIMIT_PER_MIN`, `DEMO_TRUST_FORWARDED_FOR` and
+    /// `DEMO_ATTRIBUTION`. Returns `None` unless `DEMO_MODE` is `true` or `1`.
+    pub fn from_env() -> Option<Self> {
+        let enabled = std::env::var("DEMO_MODE")
+            .map(|v| matches!(v.as_str(), "1" | "true"))
+            .unwrap_or(false);
+        if !enabled {
+            return None;
+        }
+        let limit = std::env::var("DEMO_RATE_LIMIT_PER_MIN")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_REQUESTS_PER_WINDOW);
+        let trust_forwarded_for = std::env::var("DEMO_TRUST_FORWARDED_FOR")
+            .map(|v| matches!(v.as_str(), "1" | "true"))
+            .unwrap_or(false);
+        let attribution =
+            std::env::var("DEMO_ATTRIBUTION").unwrap_or_else(|_| DEFAULT_ATTRIBUTION.to_string());
+        Some(Self::new(limit, trust_forwarded_for, &attribution))
+    }
+
+    pub fn requests_per_window(&self) -> u32 {
+        self.requests_per_window
+    }
+
+    /// The client address: the last `X-Forwarded-For` hop when the proxy is trusted,
+    /// otherwise the socket peer. Earlier hops are written by the client and could
+    /// change on every request; the last is the address the proxy saw, as in
+    /// [`crate::trust`].
+    fn client_ip(&self, req: &Request) -> IpAddr {
+        if self.trust_forwarded_for {
+            let forwarded = req
+                .headers()
+                .get_all("x-forwarded-for")
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .flat_map(|v| v.split(','))
+                .last()
+                .and_then(|v| v.trim().parse().ok());
+            if let Some(ip) = forwarded {
+                return ip;
+            }
+        }
+        req.extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip())
+            .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED))
+    }
+
+    /// Counts a request for `ip`. Returns the remaining quota, or the seconds until the
+    /// window resets if the quota is exhausted.
+    async fn acquire(&self, ip: IpAddr) -> Result<u32, u64> {
+        let window = self
+            .clients
+            .get_with(ip, async {
+                Arc::new(Mutex::new(Window {
+                    started: Instant::now(),
+                    count: 0,
+                }))
+            })
+            .await;
+        let mut window = window.lock().unwrap();
+
+        let elapsed = window.started.elapsed();
+        if elapsed >= WINDOW {
+            window.started = Instant::now();
+            window.count = 0;
+        }
+        if window.count >= self.requests_per_window {
+            return Err((WINDOW - elapsed.min(WINDOW)).as_secs().max(1));
+        }
+        window.count += 1;
+        Ok(self.requests_per_window - window.count)
+    }
+}
+
+/// Middleware enforcing demo mode restrictions. Installed only when demo mode is on.
+pub async fn demo_guard(
+    State(demo): State<Arc<DemoMode>>,
+    req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let path = req.uri().path();
+    if DISABLED_PREFIXES.iter().any(|p| path.starts_with(p))
+        || DISABLED_SUFFIXES.iter().any(|s| path.ends_with(s))
+    {
+        return Err(AppError::AdminDisabled);
+    }
+
+    let trusted = req.extensions().get::<Trusted>().is_some();
+    let remaining = if path.starts_with(LIMITED_PREFIX) && !trusted {
+        let ip = demo.client_ip(&req);
+        match demo.acquire(ip).await {
+            Ok(remaining) => Some(remaining),
+            Err(retry_after_secs) => return Err(AppError::RateLimited { retry_after_secs }),
+        }
+    } else {
+        None
+    };
+
+    let mut response = next.run(req).await;
+    let headers = response.headers_mut();
+    headers.insert(X_KIZAMI_DEMO.clone(), demo.attribution.clone());
+    if let Some(remaining) = remaining {
+        headers.insert(X_RATELIMIT_LIMIT.clone(), demo.requests_per_window.into());
+        headers.insert(X_RATELIMIT_REMAINING.clone(), remaining.into());
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::StatusCode;
+    use axum::routing::get;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn app(demo: DemoMode) -> Router {
+        Router::new()
+            .route("/v1/chains", get(|| async { "[]" }))
+            .route("/v1/admin/slo", get(|| async { "{}" }))
+            .route("/health", get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(
+                Arc::new(demo),
+                demo_guard,
+            ))
+    }
+
+    async fn send(app: &Router, uri: &str, forwarded_for: &str) -> Response {
+        let req = axum::http::Request::get(uri)
+            .header("x-forwarded-for", forwarded_for)
+            .body(Body::empty())
+            .unwrap();
+        app.clone().oneshot(req).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn quota_is_enforced_per_ip() {
+        let app = app(DemoMode::new(2, true, "demo"));
+
+        let first = send(&app, "/v1/chains", "10.0.0.1").await;
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_eq!(first.headers()["x-ratelimit-remaining"], "1");
+        assert_eq!(first.headers()["x-kizami-demo"], "demo");
+        assert_eq!(
+            send(&app, "/v1/chains", "10.0.0.1").await.status(),
+            StatusCode::OK
+        );
+
+        let limited = send(&app, "/v1/chains", "10.0.0.1").await;
+        assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(limited.headers().contains_key("retry-after"));
+
+        // a different client has its own quota
+        assert_eq!(
+            send(&app, "/v1/chains", "10.0.0.2").await.status(),
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn client_written_hops_do_not_reset_the_quota() {
+        let app = app(DemoMode::new(1, true, "demo"));
+        assert_eq!(
+            send(&app, "/v1/chains", "10.0.0.1").await.status(),
+            StatusCode::OK
+        );
+        // the proxy appends the address it saw after whatever the client sent
+        let spoofed = send(&app, "/v1/chains", "203.0.113.7, 10.0.0.1").await;
+        assert_eq!(spoofed.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[tokio::test]
+    async fn non_api_routes_are_not_limited() {
+        let app = app(DemoMode::new(1, true, "demo"));
+        for _ in 0..3 {
+            let response = send(&app, "/health", "10.0.0.1").await;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert!(!response.headers().contains_key("x-ratelimit-remaining"));
+        }
+    }
+
+    #[tokio::test]
+    async fn admin_routes_are_disabled() {
+        let app = app(DemoMode::new(10, true, "demo"));
+        let response = send(&app, "/v1/admin/slo", "10.0.0.1").await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = send(&app, "/v1/chains/1/blocks/export", "10.0.0.1").await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn trusted_clients_skip_the_quota() {
+        let policy = Arc::new(crate::trust::TrustPolicy::new(Vec::new(), Some("internal")));
+        let app = app(DemoMode::new(1, true, "demo")).layer(axum::middleware::from_fn_with_state(
+            policy,
+            crate::trust::mark_trusted,
+        ));
+        for _ in 0..3 {
+            let req = axum::http::Request::get("/v1/chains")
+                .header("x-forwarded-for", "10.0.0.1")
+                .header("x-internal-token", "internal")
+                .body(Body::empty())
+                .unwrap();
+            let response = app.clone().oneshot(req).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert!(!response.headers().contains_key("x-ratelimit-remaining"));
+        }
+        assert_eq!(
+            send(&app, "/v1/chains", "10.0.0.1").await.status(),
+            StatusCode::OK
+        );
+        assert_eq!(
+            send(&app, "/v1/chains", "10.0.0.1").await.status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+    }
+
+    #[tokio::test]
+    async fn forwarded_for_is_ignored_unless_trusted() {
+        let app = app(DemoMode::new(1, false, "demo"));
+        assert_eq!(
+            send(&app, "/v1/chains", "10.0.0.1").await.status(),
+            StatusCode::OK
+        );
+        // spoofed header doesn't grant a fresh quota
+        assert_eq!(
+            send(&app, "/v1/chains", "10.0.0.2").await.status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+    }
+}
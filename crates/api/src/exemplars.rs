@@ -0,0 +1,194 @@
+//! Block lookup latency histogram with trace exemplars.
+//!
+//! Lookup routes (`/v1/chains/{chain_id}/block/...`) are recorded into a fixed-bucket
+//! histogram alongside the rolling SLO windows. When a request arrives with a sampled
+//! W3C `traceparent` header (set by OTel-instrumented callers and proxies), its trace
+//! id is kept as the exemplar of the bucket it landed in, latest request wins. A
+//! scraper that negotiates OpenMetrics gets the exemplars on `/metrics`, so a slow
+//! bucket in Grafana links straight to a trace that hit it. Plain Prometheus text
+//! carries the same histogram without them, since that format has no exemplar syntax.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use axum::http::HeaderMap;
+
+use kizami_shared::clock;
+
+/// Upper bounds of the histogram buckets in seconds; `+Inf` is implied.
+const BUCKETS: [f64; 10] = [0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0];
+
+/// Matched route prefix of single and batch block lookups.
+const LOOKUP_ROUTE_PREFIX: &str = "/v1/chains/{chain_id}/block/";
+
+pub fn is_lookup_route(route: &str) -> bool {
+    route.starts_with(LOOKUP_ROUTE_PREFIX)
+}
+
+/// The trace id of a sampled W3C `traceparent` header
+/// (`00-<32 hex trace id>-<16 hex span id>-<2 hex flags>`), lowercase hex.
+pub fn sampled_trace_id(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get("traceparent")?.to_str().ok()?;
+    let mut parts = value.trim().split('-');
+    let (version, trace_id, span_id, flags) =
+        (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+    let is_hex = |s: &str, len: usize| s.len() == len && s.bytes().all(|b| b.is_ascii_hexdigit());
+    if version == "ff" || !is_hex(version, 2) || !is_hex(span_id, 16) || !is_hex(flags, 2) {
+        return None;
+    }
+    if !is_hex(trace_id, 32) || trace_id.bytes().all(|b| b == b'0') {
+        return None;
+    }
+    let sampled = u8::from_str_radix(flags, 16).ok()? & 0x01 == 1;
+    sampled.then(|| trace_id.to_ascii_lowercase())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Exemplar {
+    trace_id: String,
+    /// Observed latency in seconds.
+    value: f64,
+    /// Unix seconds.
+    at: f64,
+}
+
+#[derive(Default)]
+struct RouteHistogram {
+    /// Per-bucket counts (not cumulative), the last one being `+Inf`.
+    counts: [u64; BUCKETS.len() + 1],
+    exemplars: [Option<Exemplar>; BUCKETS.len() + 1],
+    sum: f64,
+}
+
+/// Lookup latency histograms per route.
+#[derive(Default)]
+pub struct LookupHistogram {
+    routes: Mutex<HashMap<String, RouteHistogram>>,
+}
+
+impl LookupHistogram {
+    pub fn record(&self, route: &str, latency: Duration, trace_id: Option<String>) {
+        let secs = latency.as_secs_f64();
+        let bucket = BUCKETS.partition_point(|&le| le < secs);
+        let mut routes = self.routes.lock().unwrap();
+        if !routes.contains_key(route) {
+            routes.insert(route.to_owned(), RouteHistogram::default());
+        }
+        let histogram = routes.get_mut(route).unwrap();
+        histogram.counts[bucket] += 1;
+        histogram.sum += secs;
+        if let Some(trace_id) = trace_id {
+            histogram.exemplars[bucket] = Some(Exemplar {
+                trace_id,
+                value: secs,
+                at: clock::now().timestamp_millis() as f64 / 1000.0,
+            });
+        }
+    }
+
+    /// Renders the histograms; exemplars are only valid in OpenMetrics text, so they
+    /// are left out unless `openmetrics` is set.
+    pub fn render(&self, openmetrics: bool) -> String {
+        let routes = self.routes.lock().unwrap();
+        let mut names: Vec<&String> = routes.keys().collect();
+        names.sort();
+        let mut out = String::from(
+            "# HELP kizami_lookup_duration_seconds Block lookup latency per route.\n\
+             # TYPE kizami_lookup_duration_seconds histogram\n",
+        );
+        for route in names {
+            let histogram = &routes[route];
+            let mut cumulative = 0;
+            for (i, count) in histogram.counts.iter().enumerate() {
+                cumulative += count;
+                let le = BUCKETS.get(i).map_or("+Inf".to_string(), |b| b.to_string());
+                let _ = write!(
+                    out,
+                    "kizami_lookup_duration_seconds_bucket{{route=\"{route}\",le=\"{le}\"}} {cumulative}"
+                );
+                if let Some(e) = histogram.exemplars[i].as_ref().filter(|_| openmetrics) {
+                    let _ = write!(
+                        out,
+                        " # {{trace_id=\"{}\"}} {} {:.3}",
+                        e.trace_id, e.value, e.at
+                    );
+                }
+                out.push('\n');
+            }
+            let _ = writeln!(
+                out,
+                "kizami_lookup_duration_seconds_sum{{route=\"{route}\"}} {}\n\
+                 kizami_lookup_duration_seconds_count{{route=\"{route}\"}} {cumulative}",
+                histogram.sum
+            );
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    fn traceparent(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("traceparent", HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn only_sampled_well_formed_traceparents_yield_a_trace_id() {
+        let id = "4BF92F3577B34DA6A3CE929D0E0E4736";
+        assert_eq!(
+            sampled_trace_id(&traceparent(&format!("00-{id}-00f067aa0ba902b7-01"))),
+            Some(id.to_ascii_lowercase())
+        );
+        // not sampled
+        assert_eq!(
+            sampled_trace_id(&traceparent(&format!("00-{id}-00f067aa0ba902b7-00"))),
+            None
+        );
+        for bad in [
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6-00f067aa0ba902b7-01",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "garbage",
+        ] {
+            assert_eq!(sampled_trace_id(&traceparent(bad)), None, "{bad}");
+        }
+        assert_eq!(sampled_trace_id(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn exemplars_render_only_in_openmetrics() {
+        let histogram = LookupHistogram::default();
+        let route = "/v1/chains/{chain_id}/block/{direction}/{timestamp}";
+        histogram.record(route, Duration::from_micros(800), None);
+        histogram.record(route, Duration::from_millis(70), Some("abc123".into()));
+        histogram.record(route, Duration::from_secs(3), None);
+
+        let text = histogram.render(true);
+        let bucket = |le: &str| {
+            text.lines()
+                .find(|l| l.contains(&format!("le=\"{le}\"")))
+                .unwrap()
+                .to_string()
+        };
+        assert!(bucket("0.001").ends_with("} 1"));
+        assert!(bucket("0.1").starts_with(&format!(
+            "kizami_lookup_duration_seconds_bucket{{route=\"{route}\",le=\"0.1\"}} 2 # {{trace_id=\"abc123\"}} 0.07 "
+        )));
+        assert!(bucket("+Inf").ends_with("} 3"));
+        assert!(text.contains(&format!(
+            "kizami_lookup_duration_seconds_count{{route=\"{route}\"}} 3"
+        )));
+
+        assert!(!histogram.render(false).contains("trace_id"));
+        assert!(is_lookup_route(route));
+        assert!(!is_lookup_route("/v1/chains/{chain_id}"));
+    }
+}
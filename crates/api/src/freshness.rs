@@ -0,0 +1,88 @@
+//! Expected ingestion delay per chain.
+//!
+//! How long after a block is produced it becomes queryable here: finality on the
+//! chain, SQD picking it up, and the ingestion interval combined. Each cursor advance
+//! that reaches the finalized head records `now - tip timestamp`; the median of the
+//! last [`MAX_SAMPLES`] is reported. `EXPECTED_DELAY_SECS` (`chain_id:secs` pairs)
+//! overrides the measurement for chains where a fixed figure is preferred.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+/// Measurements kept per chain.
+const MAX_SAMPLES: usize = 32;
+
+#[derive(Default)]
+pub struct Freshness {
+    configured: HashMap<i32, i64>,
+    measured: Mutex<HashMap<i32, VecDeque<i64>>>,
+}
+
+impl Freshness {
+    pub fn new(configured: HashMap<i32, i64>) -> Self {
+        Self {
+            configured,
+            measured: Default::default(),
+        }
+    }
+
+    /// Reads `EXPECTED_DELAY_SECS`, e.g. `1:900,8453:1200`. Malformed pairs are skipped.
+    pub fn from_env() -> Self {
+        Self::new(parse_delays(
+            &std::env::var("EXPECTED_DELAY_SECS").unwrap_or_default(),
+        ))
+    }
+
+    /// Records the delay seen when `chain_id` caught up to a tip block with
+    /// `tip_timestamp`, at `now` (both Unix seconds).
+    pub fn record(&self, chain_id: i32, tip_timestamp: i64, now: i64) {
+        let mut measured = self.measured.lock().unwrap();
+        let samples = measured.entry(chain_id).or_default();
+        if samples.len() == MAX_SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back((now - tip_timestamp).max(0));
+    }
+
+    /// Configured delay, else the median measurement. `None` before the chain has
+    /// caught up once.
+    pub fn expected_delay_secs(&self, chain_id: i32) -> Option<i64> {
+        if let Some(delay) = self.configured.get(&chain_id) {
+            return Some(*delay);
+        }
+        let measured = self.measured.lock().unwrap();
+        let mut samples: Vec<i64> = measured.get(&chain_id)?.iter().copied().collect();
+        samples.sort_unstable();
+        samples.get(samples.len() / 2).copied()
+    }
+}
+
+fn parse_delays(spec: &str) -> HashMap<i32, i64> {
+    spec.split(',')
+        .filter_map(|pair| {
+            let (id, secs) = pair.trim().split_once(':')?;
+            let secs: i64 = secs.trim().parse().ok()?;
+            (secs >= 0).then_some((id.trim().parse().ok()?, secs))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn configured_delay_wins_over_median_measurement() {
+        let freshness = Freshness::new(parse_delays("1:900, 10:x, 56:-1"));
+        assert_eq!(freshness.expected_delay_secs(8453), None);
+
+        for (tip, now) in [(100, 130), (200, 1_000), (300, 340)] {
+            freshness.record(8453, tip, now);
+            freshness.record(1, tip, now);
+        }
+        // samples 30, 800, 40: the outlier doesn't move the median
+        assert_eq!(freshness.expected_delay_secs(8453), Some(40));
+        assert_eq!(freshness.expected_delay_secs(1), Some(900));
+        assert_eq!(freshness.expected_delay_secs(56), None);
+    }
+}
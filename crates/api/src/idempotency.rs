@@ -0,0 +1,278 @@
+//! `Idempotency-Key` support for admin mutations.
+//!
+//! Automation retries admin POSTs aggressively, and a retry that races the original
+//! (or follows a dropped response) must not apply the mutation twice. A POST carrying
+//! an `Idempotency-Key` header runs at most once per key within the retention window;
+//! later requests with the same key get the stored response replayed, and concurrent
+//! duplicates wait for the first one to finish.
+//!
+//! A key is bound to the request that first used it (method, path and body). Reusing
+//! it for a different request is rejected. 5xx responses are not stored, so a retry
+//! after a transient failure runs again.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::{Body, Bytes};
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+use moka::future::Cache;
+use tokio::sync::OnceCell;
+
+use kizami_shared::error::AppError;
+
+static IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+static IDEMPOTENT_REPLAYED: HeaderName = HeaderName::from_static("idempotent-replayed");
+
+/// Default retention for stored responses.
+const DEFAULT_TTL_SECS: u64 = 10 * 60;
+
+/// Maximum number of keys retained at once.
+const MAX_KEYS: u64 = 10_000;
+
+/// Longest accepted key. UUIDs and similar fit comfortably.
+const MAX_KEY_LEN: usize = 255;
+
+/// Largest request body buffered for fingerprinting. Admin bodies are tiny.
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// A response kept for replay.
+#[derive(Clone)]
+struct StoredResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl StoredResponse {
+    /// Rebuilds the response, marking it `Idempotent-Replayed` unless this request is
+    /// the one that produced it.
+    fn to_response(&self, replayed: bool) -> Response {
+        let mut response = Response::new(Body::from(self.body.clone()));
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers.clone();
+        if replayed {
+            response.headers_mut().insert(
+                IDEMPOTENT_REPLAYED.clone(),
+                HeaderValue::from_static("true"),
+            );
+        }
+        response
+    }
+}
+
+/// Slot for one key: the fingerprint of the request that claimed it and its result.
+struct Entry {
+    fingerprint: u64,
+    response: OnceCell<StoredResponse>,
+}
+
+/// Short-lived store of responses keyed by `Idempotency-Key`.
+pub struct IdempotencyStore {
+    entries: Cache<String, Arc<Entry>>,
+}
+
+impl IdempotencyStore {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            entries: Cache::builder()
+                .max_capacity(MAX_KEYS)
+                .time_to_live(ttl)
+                .build(),
+        }
+    }
+
+    /// Reads `IDEMPOTENCY_TTL_SECS`.
+    pub fn from_env() -> Self {
+        let ttl = std::env::var("IDEMPOTENCY_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_TTL_SECS);
+        Self::new(Duration::from_secs(ttl))
+    }
+
+    /// How long a stored response is replayed.
+    pub fn ttl(&self) -> Duration {
+        self.entries.policy().time_to_live().unwrap_or_default()
+    }
+}
+
+fn fingerprint(method: &Method, path: &str, body: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    method.hash(&mut hasher);
+    path.hash(&mut hasher);
+    body.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Middleware deduplicating POSTs that carry an `Idempotency-Key` header. Requests
+/// without the header, and non-POST requests, pass straight through.
+pub async fn idempotent(
+    State(store): State<Arc<IdempotencyStore>>,
+    req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    if req.method() != Method::POST {
+        return Ok(next.run(req).await);
+    }
+    let Some(raw_key) = req.headers().get(&IDEMPOTENCY_KEY) else {
+        return Ok(next.run(req).await);
+    };
+    let key = raw_key
+        .to_str()
+        .ok()
+        .filter(|k| !k.is_empty() && k.len() <= MAX_KEY_LEN)
+        .ok_or_else(|| {
+            AppError::InvalidIdempotencyKey(format!(
+                "must be 1-{MAX_KEY_LEN} visible ASCII characters"
+            ))
+        })?
+        .to_string();
+
+    let (parts, body) = req.into_parts();
+    let body = axum::body::to_bytes(body, MAX_BODY_BYTES)
+        .await
+        .map_err(|_| AppError::PayloadTooLarge {
+            max_bytes: MAX_BODY_BYTES,
+        })?;
+    let print = fingerprint(&parts.method, parts.uri.path(), &body);
+
+    let entry = store
+        .entries
+        .get_with(key, async {
+            Arc::new(Entry {
+                fingerprint: print,
+                response: OnceCell::new(),
+            })
+        })
+        .await;
+    if entry.fingerprint != print {
+        return Err(AppError::IdempotencyKeyReused);
+    }
+
+    let req = Request::from_parts(parts, Body::from(body));
+    let mut fresh = false;
+    let result = entry
+        .response
+        .get_or_try_init(|| async {
+            fresh = true;
+            let response = next.run(req).await;
+            if response.status().is_server_error() {
+                // not stored: hand the response back to this caller only
+                return Err(response);
+            }
+            let (parts, body) = response.into_parts();
+            let body = axum::body::to_bytes(body, usize::MAX)
+                .await
+                .unwrap_or_default();
+            Ok(StoredResponse {
+                status: parts.status,
+                headers: parts.headers,
+                body,
+            })
+        })
+        .await;
+
+    Ok(match result {
+        Ok(stored) => stored.to_response(!fresh),
+        Err(response) => response,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use axum::routing::post;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn app(calls: Arc<AtomicUsize>, status: StatusCode) -> Router {
+        Router::new()
+            .route(
+                "/v1/admin/action",
+                post(move || {
+                    let calls = calls.clone();
+                    async move {
+                        let n = calls.fetch_add(1, Ordering::SeqCst) + 1;
+                        (status, n.to_string())
+                    }
+                }),
+            )
+            .layer(axum::middleware::from_fn_with_state(
+                Arc::new(IdempotencyStore::new(Duration::from_secs(60))),
+                idempotent,
+            ))
+    }
+
+    async fn send(app: &Router, key: Option<&str>, body: &str) -> Response {
+        let mut req = axum::http::Request::post("/v1/admin/action");
+        if let Some(key) = key {
+            req = req.header("idempotency-key", key);
+        }
+        app.clone()
+            .oneshot(req.body(Body::from(body.to_string())).unwrap())
+            .await
+            .unwrap()
+    }
+
+    async fn text(response: Response) -> String {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn repeated_key_replays_first_response() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let app = app(calls.clone(), StatusCode::OK);
+
+        let first = send(&app, Some("abc"), "{}").await;
+        assert!(!first.headers().contains_key("idempotent-replayed"));
+        assert_eq!(text(first).await, "1");
+
+        let retry = send(&app, Some("abc"), "{}").await;
+        assert_eq!(retry.headers()["idempotent-replayed"], "true");
+        assert_eq!(text(retry).await, "1");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn requests_without_key_always_run() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let app = app(calls.clone(), StatusCode::OK);
+
+        send(&app, None, "{}").await;
+        send(&app, None, "{}").await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn key_reused_with_different_body_is_rejected() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let app = app(calls.clone(), StatusCode::OK);
+
+        send(&app, Some("abc"), r#"{"numbers":[1]}"#).await;
+        let reused = send(&app, Some("abc"), r#"{"numbers":[2]}"#).await;
+        assert_eq!(reused.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_not_stored() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let app = app(calls.clone(), StatusCode::SERVICE_UNAVAILABLE);
+
+        send(&app, Some("abc"), "{}").await;
+        let retry = send(&app, Some("abc"), "{}").await;
+        assert_eq!(retry.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,165 @@
+//! Periodically regenerated per-chain index files.
+//!
+//! When `INDEX_SNAPSHOT_INTERVAL_SECS` is set, a scheduled job rewrites one
+//! Brotli-compressed index file per chain (see `kizami_shared::index_file`) under
+//! `$DATA_DIR/snapshots` on that interval, and `/v1/chains/{id}/index` serves them.
+//! Files are written to a temporary name and renamed into place, so a download never
+//! sees a half-written file.
+
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use kizami_shared::chains::CHAINS;
+use kizami_shared::clock;
+use kizami_shared::index_file::{IndexHeader, IndexWriter};
+use kizami_shared::scheduler::Scheduler;
+use kizami_shared::storage::ReadStorage;
+
+/// Blocks read from storage per pass while writing a file.
+const SCAN_PAGE_ROWS: usize = 100_000;
+
+/// Where index files live and how often they are rebuilt.
+pub struct IndexSnapshots {
+    dir: PathBuf,
+    interval: Duration,
+}
+
+impl IndexSnapshots {
+    pub fn new(dir: PathBuf, interval: Duration) -> Self {
+        Self { dir, interval }
+    }
+
+    /// Reads `INDEX_SNAPSHOT_INTERVAL_SECS`. Returns `None` (snapshots disabled) when
+    /// unset or zero.
+    pub fn from_env(data_dir: &str) -> Option<Self> {
+        let secs: u64 = std::env::var("INDEX_SNAPSHOT_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|v| *v > 0)?;
+        Some(Self::new(
+            Path::new(data_dir).join("snapshots"),
+            Duration::from_secs(secs),
+        ))
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Path of a chain's index file.
+    pub fn path(&self, chain_id: i32) -> PathBuf {
+        self.dir.join(format!("{chain_id}.kzix.br"))
+    }
+
+    /// Rewrites one chain's index file and returns the number of blocks written.
+    /// Chains with no blocks get no file.
+    pub fn write_chain(&self, storage: &ReadStorage, chain_id: i32) -> io::Result<u64> {
+        let tmp = self.dir.join(format!("{chain_id}.kzix.br.tmp"));
+        let header = IndexHeader {
+            chain_id,
+            generated_at: clock::now().timestamp(),
+        };
+        let mut writer = IndexWriter::new(BufWriter::new(File::create(&tmp)?), header)?;
+
+        let mut written = 0u64;
+        let mut from = (0, 0);
+        loop {
+            let rows = storage
+                .scan_blocks(chain_id, from, i64::MAX, SCAN_PAGE_ROWS)
+                .map_err(io::Error::other)?;
+            for &(number, timestamp) in &rows {
+                writer.push(number, timestamp)?;
+            }
+            written += rows.len() as u64;
+            match rows.last() {
+                Some(&(number, timestamp)) if rows.len() == SCAN_PAGE_ROWS => {
+                    from = (timestamp, number + 1);
+                }
+                _ => break,
+            }
+        }
+
+        let file = writer.finish()?.into_inner().map_err(|e| e.into_error())?;
+        file.sync_all()?;
+        if written == 0 {
+            std::fs::remove_file(&tmp)?;
+        } else {
+            std::fs::rename(&tmp, self.path(chain_id))?;
+        }
+        Ok(written)
+    }
+
+    /// Rewrites every chain's index file. Failures are logged per chain; the error
+    /// counts the chains that failed.
+    fn write_all(&self, storage: &ReadStorage) -> Result<(), String> {
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("cannot create {}: {e}", self.dir.display()))?;
+        let mut failed = 0;
+        for chain in CHAINS {
+            match self.write_chain(storage, chain.chain_id) {
+                Ok(blocks) => {
+                    tracing::debug!(chain = chain.name, blocks, "index snapshot written")
+                }
+                Err(e) => {
+                    tracing::error!(chain = chain.name, error = %e, "index snapshot failed");
+                    failed += 1;
+                }
+            }
+        }
+        match failed {
+            0 => Ok(()),
+            n => Err(format!("{n} of {} chains failed", CHAINS.len())),
+        }
+    }
+
+    /// Regenerates all files now and then every interval, as the `index_snapshots`
+    /// job. Writing is CPU-bound, so it runs on the blocking pool.
+    pub fn schedule(self: Arc<Self>, scheduler: &Scheduler, storage: ReadStorage) {
+        let interval = self.interval;
+        scheduler.spawn("index_snapshots", interval, interval / 10, move || {
+            let snapshots = self.clone();
+            let storage = storage.clone();
+            async move {
+                tokio::task::spawn_blocking(move || snapshots.write_all(&storage))
+                    .await
+                    .map_err(|e| e.to_string())?
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kizami_shared::index_file::read_index;
+    use kizami_shared::storage::Storage;
+
+    use super::*;
+
+    #[test]
+    fn writes_readable_file_across_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path().join("db")).unwrap();
+        let count = SCAN_PAGE_ROWS as i64 + 3;
+        let numbers: Vec<i64> = (0..count).collect();
+        let timestamps: Vec<i64> = numbers.iter().map(|n| 1000 + n / 2).collect();
+        storage.insert_blocks(1, &numbers, &timestamps).unwrap();
+
+        let snapshots = IndexSnapshots::new(dir.path().join("snapshots"), Duration::ZERO);
+        std::fs::create_dir_all(&snapshots.dir).unwrap();
+        let storage = storage.reader();
+        assert_eq!(snapshots.write_chain(&storage, 1).unwrap(), count as u64);
+        assert_eq!(snapshots.write_chain(&storage, 10).unwrap(), 0);
+        assert!(!snapshots.path(10).exists());
+
+        let (header, rows) = read_index(File::open(snapshots.path(1)).unwrap()).unwrap();
+        assert_eq!(header.chain_id, 1);
+        assert_eq!(rows.len() as i64, count);
+        assert_eq!(
+            rows[count as usize - 1],
+            (count - 1, 1000 + (count - 1) / 2)
+        );
+    }
+}
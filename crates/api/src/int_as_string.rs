@@ -0,0 +1,130 @@
+//! Integers as strings, for clients that parse JSON into doubles.
+//!
+//! JavaScript's `JSON.parse` loses precision on integers above 2^53. Clients that pass
+//! `?int_as_string=true`, or send `Accept: application/vnd.kizami.int-as-string+json`,
+//! get every integer in a JSON response as a decimal string instead (`"number":
+//! "19000000"`). Floats stay numbers, and so do problem details, whose `status` is a
+//! number by RFC 9457. Non-JSON responses (NDJSON exports, index files) pass through.
+//!
+//! Done once here on the serialized body, so models and handlers don't need to know.
+
+use axum::body::{to_bytes, Body};
+use axum::extract::Request;
+use axum::http::{header, HeaderValue};
+use axum::middleware::Next;
+use axum::response::Response;
+use serde_json::Value;
+
+/// Media type that asks for integers as strings.
+pub const INT_AS_STRING_JSON: &str = "application/vnd.kizami.int-as-string+json";
+
+/// Whether the request asks for integers as strings.
+fn wants_strings(req: &Request) -> bool {
+    let by_query = req.uri().query().is_some_and(|query| {
+        form_urlencoded::parse(query.as_bytes())
+            .any(|(key, value)| key == "int_as_string" && (value == "true" || value == "1"))
+    });
+    by_query
+        || req
+            .headers()
+            .get(header::ACCEPT)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.contains(INT_AS_STRING_JSON))
+}
+
+/// Replaces every integer in `value` with its decimal string.
+pub fn stringify_integers(value: &mut Value) {
+    match value {
+        Value::Number(n) if n.is_i64() || n.is_u64() => *value = Value::String(n.to_string()),
+        Value::Array(items) => items.iter_mut().for_each(stringify_integers),
+        Value::Object(fields) => fields.values_mut().for_each(stringify_integers),
+        _ => {}
+    }
+}
+
+/// Middleware that rewrites integers in `application/json` responses to strings when
+/// the request asks for it. JSON responses get `Vary: Accept` either way.
+pub async fn int_as_string(req: Request, next: Next) -> Response {
+    let wants = wants_strings(&req);
+    let mut response = next.run(req).await;
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    if !is_json {
+        return response;
+    }
+    // the body depends on Accept, so shared caches must key on it
+    response
+        .headers_mut()
+        .append(header::VARY, HeaderValue::from_static("accept"));
+    if !wants {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = to_bytes(body, usize::MAX).await else {
+        return Response::from_parts(parts, Body::empty());
+    };
+    let Ok(mut value) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, bytes.into());
+    };
+    stringify_integers(&mut value);
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, serde_json::to_vec(&value).unwrap_or_default().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use axum::routing::get;
+    use axum::{Json, Router};
+    use http_body_util::BodyExt;
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn fetch(uri: &str, accept: Option<&str>) -> Value {
+        let app = Router::new()
+            .route(
+                "/block",
+                get(|| async {
+                    Json(json!({
+                        "number": 9_007_199_254_740_993_i64,
+                        "timestamp": 1_700_000_000,
+                        "ratio": 0.5,
+                        "blocks": [{"number": -1}],
+                        "approximate": false,
+                    }))
+                }),
+            )
+            .layer(axum::middleware::from_fn(int_as_string));
+        let mut req = Request::get(uri);
+        if let Some(accept) = accept {
+            req = req.header(header::ACCEPT, accept);
+        }
+        let response = app.oneshot(req.body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn integers_become_strings_on_request() {
+        let plain = fetch("/block", None).await;
+        assert_eq!(plain["number"], 9_007_199_254_740_993_i64);
+
+        let expected = json!({
+            "number": "9007199254740993",
+            "timestamp": "1700000000",
+            "ratio": 0.5,
+            "blocks": [{"number": "-1"}],
+            "approximate": false,
+        });
+        assert_eq!(fetch("/block?int_as_string=true", None).await, expected);
+        assert_eq!(fetch("/block", Some(INT_AS_STRING_JSON)).await, expected);
+        assert_eq!(fetch("/block?int_as_string=false", None).await, plain);
+    }
+}
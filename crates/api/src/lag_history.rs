@@ -0,0 +1,117 @@
+//! In-memory ingestion lag history for the admin dashboard.
+//!
+//! A scheduled job samples the progress map every [`SAMPLE_INTERVAL`] and keeps the
+//! last [`MAX_SAMPLES`] head-minus-cursor readings per chain. Chains whose head has
+//! not been fetched yet are skipped. History starts empty on every restart.
+
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use kizami_shared::chains::CHAINS;
+use kizami_shared::clock;
+use kizami_shared::models::{LagHistoryResponse, LagSampleResponse};
+use kizami_shared::scheduler::Scheduler;
+use kizami_shared::storage::{ChainProgress, ProgressMap};
+
+/// How often lag is sampled.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Samples kept per chain: 24 hours at one per minute.
+const MAX_SAMPLES: usize = 24 * 60;
+
+/// Recent lag samples per chain id, oldest first.
+#[derive(Default)]
+pub struct LagHistory {
+    samples: Mutex<HashMap<i32, VecDeque<LagSampleResponse>>>,
+}
+
+impl LagHistory {
+    /// Records one sample per chain with a known head, taken at `at` (Unix seconds).
+    fn record(&self, at: i64, progress: &HashMap<String, ChainProgress>) {
+        let mut samples = self.samples.lock().unwrap();
+        for chain in CHAINS {
+            let Some(p) = progress.get(chain.sqd_slug) else {
+                continue;
+            };
+            let Some(head) = p.head else {
+                continue;
+            };
+            let history = samples.entry(chain.chain_id).or_default();
+            if history.len() == MAX_SAMPLES {
+                history.pop_front();
+            }
+            history.push_back(LagSampleResponse {
+                at,
+                lag: (head - p.cursor).max(0),
+            });
+        }
+    }
+
+    /// History for every chain that has at least one sample, by chain id.
+    pub fn snapshot(&self) -> Vec<LagHistoryResponse> {
+        let samples = self.samples.lock().unwrap();
+        let mut history: Vec<_> = CHAINS
+            .iter()
+            .filter_map(|chain| {
+                let s = samples.get(&chain.chain_id)?;
+                Some(LagHistoryResponse {
+                    chain_id: chain.chain_id,
+                    name: chain.name,
+                    samples: s.iter().copied().collect(),
+                })
+            })
+            .collect();
+        history.sort_by_key(|h| h.chain_id);
+        history
+    }
+
+    /// Samples the progress map as the `lag_history` job. No jitter, so samples stay
+    /// a minute apart.
+    pub fn schedule(self: Arc<Self>, scheduler: &Scheduler, progress: ProgressMap) {
+        scheduler.spawn("lag_history", SAMPLE_INTERVAL, Duration::ZERO, move || {
+            let history = self.clone();
+            let progress = progress.clone();
+            async move {
+                let map = progress.read().await;
+                history.record(clock::now().timestamp(), &map);
+                Ok::<_, Infallible>(())
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(cursor: i64, head: Option<i64>) -> ChainProgress {
+        ChainProgress {
+            cursor,
+            head,
+            updated_at: None,
+            paused: false,
+            head_advanced_at: None,
+        }
+    }
+
+    #[test]
+    fn keeps_a_bounded_window_per_chain() {
+        let history = LagHistory::default();
+        let mut map = HashMap::new();
+        map.insert("base-mainnet".to_string(), progress(0, None));
+        for i in 0..MAX_SAMPLES as i64 + 5 {
+            map.insert("ethereum-mainnet".to_string(), progress(100, Some(100 + i)));
+            history.record(i, &map);
+        }
+
+        let snapshot = history.snapshot();
+        // base has no head yet, so no samples
+        assert_eq!(snapshot.len(), 1);
+        let eth = &snapshot[0].samples;
+        assert_eq!(eth.len(), MAX_SAMPLES);
+        assert_eq!((eth[0].at, eth[0].lag), (5, 5));
+        assert_eq!(eth[MAX_SAMPLES - 1].lag, MAX_SAMPLES as i64 + 4);
+    }
+}
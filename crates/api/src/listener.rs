@@ -0,0 +1,116 @@
+//! Listening socket setup.
+//!
+//! Three ways to get the socket, so a restart doesn't have to refuse connections:
+//!
+//! - systemd socket activation: with `LISTEN_PID` naming this process and
+//!   `LISTEN_FDS` set, the first passed descriptor (fd 3) is used as is. systemd keeps
+//!   it open across restarts and queues connections while the new process starts.
+//! - `REUSE_PORT=true`: the port is bound with `SO_REUSEPORT`, so a new process can
+//!   bind it while the old one drains (see [`crate::server`]). Start the new one, wait
+//!   for `/health`, then stop the old one.
+//! - otherwise a plain bind on `0.0.0.0:PORT`.
+
+use std::io;
+use std::net::SocketAddr;
+
+use tokio::net::{TcpListener, TcpSocket};
+
+/// First descriptor systemd passes (`SD_LISTEN_FDS_START`).
+#[cfg(unix)]
+const LISTEN_FDS_START: i32 = 3;
+
+/// Pending connection queue length, as std's `TcpListener::bind` uses.
+const BACKLOG: u32 = 1024;
+
+/// Where the listening socket came from, for the startup log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListenerSource {
+    SocketActivation,
+    ReusePort,
+    Bind,
+}
+
+/// Whether systemd passed sockets to this process, given `LISTEN_PID` and
+/// `LISTEN_FDS`.
+fn activation_requested(listen_pid: Option<&str>, listen_fds: Option<&str>, pid: u32) -> bool {
+    let for_us = listen_pid.and_then(|p| p.trim().parse::<u32>().ok()) == Some(pid);
+    let count = listen_fds.and_then(|n| n.trim().parse::<u32>().ok());
+    for_us && count.is_some_and(|n| n >= 1)
+}
+
+/// Opens the listening socket for `port` (see the module docs).
+pub fn open(port: &str, reuse_port: bool) -> io::Result<(TcpListener, ListenerSource)> {
+    if let Some(listener) = inherited()? {
+        return Ok((listener, ListenerSource::SocketActivation));
+    }
+    let addr: SocketAddr = format!("0.0.0.0:{port}")
+        .parse()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let socket = TcpSocket::new_v4()?;
+    socket.set_reuseaddr(true)?;
+    #[cfg(unix)]
+    if reuse_port {
+        socket.set_reuseport(true)?;
+    }
+    socket.bind(addr)?;
+    let source = if reuse_port && cfg!(unix) {
+        ListenerSource::ReusePort
+    } else {
+        ListenerSource::Bind
+    };
+    Ok((socket.listen(BACKLOG)?, source))
+}
+
+/// The socket passed by systemd socket activation, if any.
+#[cfg(unix)]
+fn inherited() -> io::Result<Option<TcpListener>> {
+    use std::os::fd::FromRawFd;
+
+    let listen_pid = std::env::var("LISTEN_PID").ok();
+    let listen_fds = std::env::var("LISTEN_FDS").ok();
+    if !activation_requested(
+        listen_pid.as_deref(),
+        listen_fds.as_deref(),
+        std::process::id(),
+    ) {
+        return Ok(None);
+    }
+    // SAFETY: LISTEN_PID names this process, so systemd passed open descriptors
+    // starting at fd 3 and nothing else in the process owns fd 3.
+    let listener = unsafe { std::net::TcpListener::from_raw_fd(LISTEN_FDS_START) };
+    listener.set_nonblocking(true)?;
+    TcpListener::from_std(listener).map(Some)
+}
+
+#[cfg(not(unix))]
+fn inherited() -> io::Result<Option<TcpListener>> {
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn activation_needs_our_pid_and_at_least_one_fd() {
+        assert!(activation_requested(Some("42"), Some("1"), 42));
+        assert!(activation_requested(Some(" 42 "), Some("2"), 42));
+        assert!(!activation_requested(Some("41"), Some("1"), 42));
+        assert!(!activation_requested(Some("42"), Some("0"), 42));
+        assert!(!activation_requested(None, Some("1"), 42));
+        assert!(!activation_requested(Some("42"), None, 42));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn reuse_port_lets_a_second_process_bind_while_the_first_listens() {
+        let (first, source) = open("0", true).unwrap();
+        assert_eq!(source, ListenerSource::ReusePort);
+        let port = first.local_addr().unwrap().port().to_string();
+        let (second, _) = open(&port, true).unwrap();
+        assert_eq!(second.local_addr().unwrap(), first.local_addr().unwrap());
+
+        drop(second);
+        assert!(open(&port, false).is_err());
+    }
+}
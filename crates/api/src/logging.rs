@@ -0,0 +1,135 @@
+//! Tracing subscriber setup.
+//!
+//! Release builds log JSON lines by default; debug builds default to the compact
+//! human-readable format. The level filter starts at `info`, then applies `RUST_LOG`,
+//! then `LOG_LEVELS`, where a later directive for the same target replaces an earlier
+//! one. That lets a deployment raise or lower single modules from its config without
+//! restating the whole `RUST_LOG` filter.
+
+use std::io::IsTerminal;
+
+use tracing_subscriber::EnvFilter;
+
+/// Output format of log events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// One JSON object per line.
+    Json,
+    /// Multi-line, indented events with source locations.
+    Pretty,
+    /// One line per event.
+    Compact,
+}
+
+impl LogFormat {
+    fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "pretty" => Some(Self::Pretty),
+            "compact" => Some(Self::Compact),
+            _ => None,
+        }
+    }
+}
+
+/// Resolved logging settings.
+#[derive(Debug, Clone)]
+pub struct LogConfig {
+    pub format: LogFormat,
+    /// ANSI colors for the human-readable formats; JSON output never has them.
+    pub ansi: bool,
+    /// Filter directives after merging the default, `RUST_LOG` and `LOG_LEVELS`.
+    pub directives: String,
+}
+
+impl LogConfig {
+    /// Reads `LOG_FORMAT`, `LOG_COLOR`, `RUST_LOG` and `LOG_LEVELS`. An unknown
+    /// `LOG_FORMAT` falls back to the build default.
+    pub fn from_env() -> Self {
+        let default_format = if cfg!(debug_assertions) {
+            LogFormat::Compact
+        } else {
+            LogFormat::Json
+        };
+        let format = std::env::var("LOG_FORMAT")
+            .ok()
+            .and_then(|v| LogFormat::parse(&v))
+            .unwrap_or(default_format);
+        let ansi = std::env::var("LOG_COLOR")
+            .map(|v| matches!(v.as_str(), "1" | "true"))
+            .unwrap_or_else(|_| std::io::stdout().is_terminal());
+        let directives = merge_directives(&[
+            "info",
+            &std::env::var("RUST_LOG").unwrap_or_default(),
+            &std::env::var("LOG_LEVELS").unwrap_or_default(),
+        ]);
+        Self {
+            format,
+            ansi,
+            directives,
+        }
+    }
+}
+
+/// Merges comma-separated filter directives, keeping the last one per target (the
+/// part before `=`; a bare level is the global default).
+fn merge_directives(sources: &[&str]) -> String {
+    let mut merged: Vec<&str> = Vec::new();
+    for directive in sources
+        .iter()
+        .flat_map(|s| s.split(','))
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+    {
+        let target = |d: &str| d.rsplit_once('=').map(|(t, _)| t.to_string());
+        let key = target(directive);
+        merged.retain(|d| target(d) != key);
+        merged.push(directive);
+    }
+    merged.join(",")
+}
+
+/// Installs the global tracing subscriber.
+pub fn init(config: &LogConfig) {
+    // invalid directives are reported on stderr and skipped
+    let filter = EnvFilter::builder().parse_lossy(&config.directives);
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_ansi(config.ansi && config.format != LogFormat::Json);
+    match config.format {
+        LogFormat::Json => builder.json().with_target(false).init(),
+        LogFormat::Pretty => builder.pretty().init(),
+        LogFormat::Compact => builder.compact().init(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn later_directives_replace_earlier_ones_per_target() {
+        assert_eq!(merge_directives(&["info", "", ""]), "info");
+        assert_eq!(
+            merge_directives(&[
+                "info",
+                "warn,kizami_ingestion=debug,tower_http=info",
+                "kizami_ingestion=trace, fjall=warn",
+            ]),
+            "warn,tower_http=info,kizami_ingestion=trace,fjall=warn"
+        );
+        // span filters keep their brackets as part of the target
+        assert_eq!(
+            merge_directives(&["info", "kizami_api[lookup{chain=1}]=debug"]),
+            "info,kizami_api[lookup{chain=1}]=debug"
+        );
+    }
+
+    #[test]
+    fn formats_parse_case_insensitively() {
+        assert_eq!(LogFormat::parse("JSON"), Some(LogFormat::Json));
+        assert_eq!(LogFormat::parse(" pretty "), Some(LogFormat::Pretty));
+        assert_eq!(LogFormat::parse("compact"), Some(LogFormat::Compact));
+        assert_eq!(LogFormat::parse("logfmt"), None);
+    }
+}
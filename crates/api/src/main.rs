@@ -6,154 +6,97 @@
 //! Environment variables:
 //! - `DATA_DIR`: path to fjall data directory (default: ./data)
 //! - `PORT`: HTTP listen port (default: 8080)
+//! - `REUSE_PORT`: bind with `SO_REUSEPORT` so a new process can take over the port while the old one drains (default: false)
+//! - `LISTEN_FDS`/`LISTEN_PID`: set by systemd socket activation; the passed socket is used instead of binding `PORT`
+//! - `HTTP2`: also accept cleartext HTTP/2 with prior knowledge (default: false)
+//! - `HTTP_KEEP_ALIVE`: reuse HTTP/1 connections across requests (default: true)
+//! - `HTTP_KEEP_ALIVE_TIMEOUT_SECS`: how long an idle connection waits for its next request, and the HTTP/2 ping timeout; keep it above the load balancer's idle timeout (default: 75)
+//! - `HTTP2_KEEP_ALIVE_INTERVAL_SECS`: seconds between HTTP/2 keep-alive pings (default: none)
+//! - `HTTP2_MAX_CONCURRENT_STREAMS`: concurrent streams per HTTP/2 connection (default: 200)
+//! - `MAX_BODY_BYTES`: largest accepted JSON request body, e.g. batch lookups (default: 8 MiB)
+//! - `SERVED_BY`: region or replica name sent in `X-Kizami-Served-By` and on `/v1/indexing-status`, e.g. `eu-west-1/replica-2` (default: none)
+//! - `DRAIN_TIMEOUT_SECS`: how long shutdown waits for in-flight requests before stopping ingestion (default: 30)
+//! - `TLS_CERT_FILE`/`TLS_KEY_FILE`: PEM certificate chain and key; when both are set the server only speaks HTTPS
+//! - `TLS_CLIENT_CA_FILE`: PEM CAs whose client certificates are accepted (mTLS; off if unset)
+//! - `CLIENT_CERT_MODE`: `required` on every connection, or `admin` to require one only for admin routes, in place of `ADMIN_TOKEN` (default: required)
+//! - `ADMIN_CLIENT_CERTS`: comma-separated SHA-256 fingerprints of the client certificates allowed on admin routes in `admin` mode (required by it)
 //! - `RUST_LOG`: tracing env filter (default: info)
+//! - `LOG_FORMAT`: `json`, `pretty` or `compact` (default: json in release builds, compact in debug builds)
+//! - `LOG_COLOR`: ANSI colors in `pretty` and `compact` output (default: when stdout is a terminal)
+//! - `LOG_LEVELS`: per-module level overrides applied over `RUST_LOG`, e.g. `kizami_ingestion=debug,tower_http=warn`
+//! - `LOG_SAMPLE_INGEST_EVERY`: log per-chain ingest successes and cycle summaries every Nth cycle only (default: 1)
+//! - `LOG_SAMPLE_LOOKUPS_EVERY`: log 1 in N block lookups with their cache outcome (default: 0, off)
+//! - `LOG_SUMMARY_INTERVAL_SECS`: seconds between aggregated ingest and lookup counter events, 0 disables (default: 60)
 //! - `INGEST_INTERVAL_SECS`: seconds between ingestion cycles (default: 60)
-
-mod routes;
-mod state;
-
-use std::collections::HashMap;
-use std::env;
-use std::sync::Arc;
-
-use axum::http::{header, Method};
-use axum::routing::get;
-use tokio::sync::RwLock;
-use tower_http::cors::{Any, CorsLayer};
-use tracing_subscriber::EnvFilter;
-use utoipa::OpenApi;
-use utoipa_axum::router::OpenApiRouter;
-use utoipa_axum::routes;
-use utoipa_scalar::{Scalar, Servable};
-
-use kizami_shared::sqd::SqdClient;
-use kizami_shared::storage::{ChainProgress, Storage};
-
-use crate::state::AppState;
-
-#[derive(OpenApi)]
-#[openapi(
-    info(
-        title = "Kizami API",
-        description = "Block-by-timestamp lookup API for EVM chains",
-        version = "1.0.0",
-        license(name = "MIT")
-    ),
-    tags(
-        (name = "Chains", description = "Chain information endpoints"),
-        (name = "Blocks", description = "Block lookup endpoints"),
-        (name = "Status", description = "Indexing status endpoints")
-    )
-)]
-struct ApiDoc;
-
-#[tokio::main]
-async fn main() {
-    tracing_subscriber::fmt()
-        .json()
-        .with_env_filter(EnvFilter::from_default_env())
-        .with_target(false)
-        .init();
-
-    let data_dir = env::var("DATA_DIR").unwrap_or_else(|_| "./data".to_string());
-    let port = env::var("PORT").unwrap_or_else(|_| "8080".to_string());
-
-    let storage = Storage::open(&data_dir).expect("failed to open storage");
-
-    tracing::info!(data_dir = %data_dir, "storage opened");
-
-    // populate progress map from persisted cursors
-    let cursors = storage
-        .get_all_cursors()
-        .expect("failed to read cursors from storage");
-    let mut map = HashMap::new();
-    for (slug, last_block, updated_at) in cursors {
-        map.insert(
-            slug,
-            ChainProgress {
-                cursor: last_block,
-                head: None,
-                updated_at: Some(updated_at),
-            },
-        );
-    }
-    let progress = Arc::new(RwLock::new(map));
-
-    let state = AppState {
-        storage: storage.clone(),
-        progress: progress.clone(),
-    };
-
-    // graceful shutdown: ctrl-c signals both the server and ingestion loop
-    let shutdown = tokio::signal::ctrl_c();
-    let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
-
-    // spawn ingestion as a background task in the same process
-    let sqd_client = SqdClient::new();
-    tokio::spawn(async move {
-        kizami_ingestion::run_ingestion_loop(storage, sqd_client, progress, shutdown_rx).await;
-    });
-
-    let cors = CorsLayer::new()
-        .allow_methods([Method::GET])
-        .allow_origin(Any);
-
-    let (router, api) = OpenApiRouter::with_openapi(ApiDoc::openapi())
-        .routes(routes!(routes::chains::list_chains))
-        .routes(routes!(routes::chains::get_chain))
-        .routes(routes!(routes::blocks::find_block))
-        .routes(routes!(routes::status::indexing_status))
-        .with_state(state)
-        .split_for_parts();
-
-    let app = router
-        .merge(Scalar::with_url("/docs", api))
-        .route("/health", get(|| async { "ok" }))
-        .route(
-            "/",
-            get(|| async { axum::response::Html(include_str!("../../../static/index.html")) }),
-        )
-        .route(
-            "/static/chains/143.svg",
-            get(|| async {
-                (
-                    [(header::CONTENT_TYPE, "image/svg+xml")],
-                    include_str!("../../../static/chains/143.svg"),
-                )
-            }),
-        )
-        .route(
-            "/static/chains/1116.svg",
-            get(|| async {
-                (
-                    [(header::CONTENT_TYPE, "image/svg+xml")],
-                    include_str!("../../../static/chains/1116.svg"),
-                )
-            }),
-        )
-        .route(
-            "/static/chains/4200.webp",
-            get(|| async {
-                (
-                    [(header::CONTENT_TYPE, "image/webp")],
-                    include_bytes!("../../../static/chains/4200.webp").as_slice(),
-                )
-            }),
-        )
-        .layer(cors);
-
-    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{port}"))
-        .await
-        .expect("failed to bind");
-
-    tracing::info!(port = %port, "server listening");
-
-    axum::serve(listener, app)
-        .with_graceful_shutdown(async move {
-            let _ = shutdown.await;
-            let _ = shutdown_tx.send(());
-            tracing::info!("shutdown signal received");
-        })
-        .await
-        .expect("server error");
+//! - `WORKER_THREADS`: worker threads of the runtime serving requests (default: one per available core)
+//! - `MAX_BLOCKING_THREADS`: most threads of the serving runtime's blocking pool, which storage work runs on (default: 512)
+//! - `BLOCKING_THREAD_KEEP_ALIVE_SECS`: seconds an idle blocking thread is kept before the pool shrinks (default: 10)
+//! - `INGEST_WORKER_THREADS`: worker threads of the dedicated ingestion runtime (default: 1)
+//! - `INGEST_MAX_BLOCKING_THREADS`: most blocking threads of the ingestion runtime, which block writes run on (default: 512)
+//! - `HEAD_POLL_INTERVAL_SECS`: seconds between head-only polls of every chain, 0 fetches heads once per cycle instead (default: 30)
+//! - `RPC_URLS`: chain RPC endpoints polled with heads to measure SQD dataset lag, e.g. `1=https://eth.example,8453=https://base.example`
+//! - `SQD_REQUESTS_PER_CYCLE`: SQD requests per ingestion cycle across all chains, tip-following chains first (default: unlimited)
+//! - `SQD_DAILY_EGRESS_BUDGET_MB`: megabytes all chains may download from SQD per UTC day before deep backfill and the work queue wait for the next day; tip-following chains are exempt (default: unlimited)
+//! - `TIMESTAMP_JUMP_ALERT_SECS`: gap between consecutive block timestamps recorded as an anomaly, 0 reports only backwards timestamps (default: 3600)
+//! - `WORK_QUEUE_INTERVAL_SECS`: seconds between runs draining queued backfill and repair ranges, 0 disables (default: 30)
+//! - `CATCHUP_LAG_SECS`: age of a chain's newest block at startup that triggers accelerated catch-up, 0 disables (default: 21600)
+//! - `CATCHUP_PARALLELISM`: batches fetched concurrently per chain while catching up (default: 4)
+//! - `INGEST_ERROR_LOG_SIZE`: ingestion errors kept per chain for `/v1/admin/errors`, 0 keeps none (default: 100)
+//! - `CLOCK_SKEW_TOLERANCE_SECS`: host clock skew tolerated before timestamps are corrected by it (default: 2)
+//! - `NTP_SERVER`: NTP server (`host` or `host:port`) to measure clock skew against instead of SQD `Date` headers
+//! - `PERSIST_MODE`: fsync policy, one of `batch`, `periodic`, `buffer` (default: periodic)
+//! - `PERSIST_EVERY_N_CYCLES`: cycles between fsyncs in `periodic` mode (default: 5)
+//! - `CURSOR_CHECK_EVERY_N_CYCLES`: cycles between cursor vs stored data checks (default: 60)
+//! - `CURSOR_HEAL`: lower cursors found ahead of stored data (default: false)
+//! - `ADMIN_TOKEN`: bearer token for `/v1/admin/*` routes (admin API disabled if unset)
+//! - `SLO_P99_MS`: p99 latency target per route in milliseconds (default: 50)
+//! - `CHAIN_STALL_BLOCK_TIMES`: average block times without a head advance before a chain is reported stalled, 0 disables (default: 100)
+//! - `HEALTH_MAX_LAG_BLOCKS`: blocks behind the head before a chain's health is `lagging` (default: 1000)
+//! - `HEALTH_MAX_CURSOR_AGE_SECS`: seconds without a cursor move before a chain's health is `lagging` (default: 1800)
+//! - `HEALTH_MAX_ERROR_RATE`: recent SQD error rate (0 to 1) at which a chain's health is `erroring` (default: 0.5)
+//! - `EXPECTED_DELAY_SECS`: fixed expected ingestion delay per chain instead of the measured one, e.g. `1:900,8453:1200`
+//! - `DEFAULT_DIRECTION`: direction of `/v1/chains/{id}/block/{timestamp}` and of batch queries without one, `before`, `after` or `nearest` (default: before)
+//! - `DEFAULT_INCLUSIVE`: `inclusive` of lookups that leave it out (default: false)
+//! - `CACHE_TTL_SECS`: cache time-to-live for lookups deep behind the tip (default: 30 days)
+//! - `CACHE_NEAR_TIP_TTL_SECS`: cache time-to-live for near-tip lookups (default: 12)
+//! - `CACHE_DEEP_BLOCKS`: blocks behind the tip at which a lookup is deep (default: 1000)
+//! - `CACHE_MAX_ENTRIES`: lookup cache capacity (default: 100000)
+//! - `CACHE_BYPASS_PER_MIN`: lookups per minute allowed to skip the cache with `Cache-Control: no-cache` or `fresh=true`, 0 disables (default: 60)
+//! - `SHARED_CACHE_URL`: Redis (`redis://[user:password@]host[:port][/db]`) shared by every replica as a second-level lookup cache (default: none)
+//! - `SHARED_CACHE_TTL_SECS`: time-to-live of deep lookups in the shared cache (default: 86400)
+//! - `SHARED_CACHE_NEAR_TIP_TTL_SECS`: time-to-live of near-tip lookups in the shared cache, 0 keeps them out of it (default: 0)
+//! - `GENESIS_TIMESTAMPS`: override built-in genesis timestamps, e.g. `8453:1686789347`
+//! - `CHAIN_ALIASES`: redirect retired chain ids to another chain, e.g. `1101:137,5:1`
+//! - `APPROXIMATE_CHAINS`: store every Nth block and interpolate lookups, e.g. `137:100,56:1000`
+//! - `CURSOR_HISTORY_INTERVAL_SECS`: seconds between persisted cursor snapshots, 0 disables (default: 300)
+//! - `CURSOR_HISTORY_RETENTION_DAYS`: days of cursor snapshots kept, 0 keeps all (default: 90)
+//! - `INDEX_SNAPSHOT_INTERVAL_SECS`: rebuild downloadable per-chain index files this often (off if unset)
+//! - `BACKUP_DIR`: directory for scheduled backups taken while serving (off if unset)
+//! - `BACKUP_INTERVAL_SECS`: seconds between backups, 0 disables (default: 86400)
+//! - `BACKUP_KEEP`: newest backups kept, older ones are deleted (default: 3)
+//! - `BACKUP_MAX_MB_PER_SEC`: most MB per second a backup copies, 0 unthrottled (default: 50)
+//! - `RANGE_MAX_WINDOW_DAYS`: longest export window, 0 for no cap (default: 366)
+//! - `RANGE_MAX_ROWS`: most blocks one export may cover, 0 for no cap (default: 5000000)
+//! - `PAGINATION_SECRET`: key signing pagination cursors; keep it stable across deploys
+//! - `IDEMPOTENCY_TTL_SECS`: how long admin `Idempotency-Key` responses are kept (default: 600)
+//! - `DEMO_MODE`: public demo mode: per-IP quotas, admin, metrics and exports off (default: false)
+//! - `DEMO_RATE_LIMIT_PER_MIN`: per-IP requests per minute in demo mode (default: 60)
+//! - `DEMO_TRUST_FORWARDED_FOR`: key demo quotas on the last `X-Forwarded-For` hop (default: false)
+//! - `DEMO_ATTRIBUTION`: value of the `X-Kizami-Demo` response header in demo mode
+//! - `TRUSTED_NETWORKS`: CIDRs or addresses of internal clients that skip demo quotas, e.g. `10.0.0.0/8`
+//! - `INTERNAL_TOKEN`: `X-Internal-Token` value that marks a request as trusted from any address
+//! - `TENANTS`: tenant namespaces under `/t/{tenant}/v1`, as `name:api_key[:per_minute]` pairs
+//! - `TENANT_RATE_LIMIT_PER_MIN`: default per-tenant requests per minute (default: 600)
+//! - `TENANT_STORE_URL`: Redis (`redis://[user:password@]host[:port][/db]`) holding tenant keys and quota windows shared by every replica (default: kept in the process)
+
+use kizami_api::logging::{self, LogConfig};
+use kizami_api::runtime::RuntimeConfig;
+
+fn main() {
+    logging::init(&LogConfig::from_env());
+
+    let runtime = RuntimeConfig::from_env()
+        .build()
+        .expect("failed to build runtime");
+    runtime.block_on(kizami_api::serve());
 }
@@ -0,0 +1,105 @@
+//! Startup recovery report.
+//!
+//! Built once right after storage opens and before ingestion starts, so it describes
+//! the state the node came back in: how much journal fjall replayed, how long that
+//! took, what each chain has on disk, and which cursors point past their data. It is
+//! logged as one structured event and served by `GET /v1/admin/recovery`.
+
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+
+use kizami_shared::chains::CHAINS;
+use kizami_shared::error::AppError;
+use kizami_shared::models::{
+    ChainExtentResponse, CursorDiscrepancyResponse, RecoveryReportResponse,
+};
+use kizami_shared::storage::Storage;
+
+/// Builds the report. `opened_at` and `open_duration` describe the `Storage::open` call.
+pub fn build(
+    storage: &Storage,
+    opened_at: DateTime<Utc>,
+    open_duration: Duration,
+) -> Result<RecoveryReportResponse, AppError> {
+    let started = Instant::now();
+    let (journal_files, journal_bytes) = storage.journal_stats()?;
+
+    let mut chains = Vec::with_capacity(CHAINS.len());
+    let mut cursor_discrepancies = Vec::new();
+    for chain in CHAINS {
+        let extent = storage.block_extent(chain.chain_id)?;
+        chains.push(ChainExtentResponse {
+            chain_id: chain.chain_id,
+            name: chain.name.to_string(),
+            cursor: storage.get_cursor(chain.chain_id)?,
+            first_block: extent.map(|(first, _)| first),
+            last_block: extent.map(|(_, last)| last),
+            quarantined: storage.rejected_count(chain.chain_id)?,
+        });
+        if let Some(d) = kizami_ingestion::find_cursor_discrepancy(storage, chain)? {
+            cursor_discrepancies.push(CursorDiscrepancyResponse {
+                chain_id: d.chain_id,
+                cursor: d.cursor,
+                max_stored_block: d.max_stored_block,
+            });
+        }
+    }
+
+    Ok(RecoveryReportResponse {
+        opened_at,
+        open_duration_ms: open_duration.as_millis() as u64,
+        report_duration_ms: started.elapsed().as_millis() as u64,
+        journal_files,
+        journal_bytes,
+        disk_bytes: storage.disk_space()?,
+        chains,
+        cursor_discrepancies,
+    })
+}
+
+/// Emits the report as one structured event.
+pub fn log(report: &RecoveryReportResponse) {
+    tracing::info!(
+        job = "recovery",
+        open_duration_ms = report.open_duration_ms,
+        report_duration_ms = report.report_duration_ms,
+        journal_files = report.journal_files,
+        journal_bytes = report.journal_bytes,
+        disk_bytes = report.disk_bytes,
+        chains_with_data = report
+            .chains
+            .iter()
+            .filter(|c| c.last_block.is_some())
+            .count(),
+        blocks_quarantined = report.chains.iter().map(|c| c.quarantined).sum::<u64>(),
+        cursor_discrepancies = report.cursor_discrepancies.len(),
+        "recovery report"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_extents_and_cursor_discrepancies() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        storage
+            .insert_blocks(1, &[1, 2, 3], &[100, 112, 124])
+            .unwrap();
+        let eth = kizami_shared::chains::chain_by_id(1).unwrap();
+        storage.upsert_cursor(eth.chain_id, 9).unwrap();
+
+        let report = build(&storage, Utc::now(), Duration::from_millis(5)).unwrap();
+        assert_eq!(report.open_duration_ms, 5);
+        assert_eq!(report.chains.len(), CHAINS.len());
+        let eth_extent = report.chains.iter().find(|c| c.chain_id == 1).unwrap();
+        assert_eq!(eth_extent.cursor, 9);
+        assert_eq!(eth_extent.first_block.unwrap().number, 1);
+        assert_eq!(eth_extent.last_block.unwrap().timestamp, 124);
+        assert_eq!(report.cursor_discrepancies.len(), 1);
+        assert_eq!(report.cursor_discrepancies[0].max_stored_block, Some(3));
+    }
+}
@@ -0,0 +1,471 @@
+//! Admin endpoints for reviewing quarantined blocks.
+//!
+//! Headers that fail ingest validation land in the `rejected` keyspace. These handlers
+//! let operators list them, re-run validation (e.g. after fixing a genesis timestamp),
+//! force-accept them into the serving index, or purge them. All routes require
+//! `Authorization: Bearer $ADMIN_TOKEN` and are disabled when `ADMIN_TOKEN` is unset.
+
+use axum::extract::{Path, Request, State};
+use axum::http::{header, HeaderMap, HeaderValue};
+use axum::middleware::Next;
+use axum::response::Response;
+use axum::{Extension, Json};
+use serde::Deserialize;
+
+use kizami_shared::auth::constant_time_eq;
+use kizami_shared::chains::{self, ChainConfig};
+use kizami_shared::clock;
+use kizami_shared::error::AppError;
+use kizami_shared::models::{QuarantineActionResponse, RejectedBlockResponse};
+use kizami_shared::sqd::BlockHeader;
+use kizami_shared::storage::Storage;
+use kizami_shared::validation;
+
+use crate::pagination::Scope;
+use crate::state::AppState;
+use crate::validate::{ValidJson, ValidQuery, Validate, Violations};
+
+/// Default and maximum page size for quarantine listings.
+const DEFAULT_LIST_LIMIT: usize = 100;
+const MAX_LIST_LIMIT: usize = 1000;
+
+#[derive(Deserialize)]
+pub struct ListQuery {
+    #[serde(default)]
+    limit: Option<usize>,
+    #[serde(default)]
+    cursor: Option<String>,
+}
+
+impl Validate for ListQuery {
+    fn validate(&self, v: &mut Violations) {
+        v.limit("limit", self.limit, MAX_LIST_LIMIT);
+    }
+}
+
+/// Selects which quarantined blocks an action applies to. Omitting `numbers` selects all.
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct QuarantineSelection {
+    /// Block numbers to act on. Numbers that aren't quarantined are ignored.
+    #[serde(default)]
+    numbers: Option<Vec<i64>>,
+}
+
+impl Validate for QuarantineSelection {
+    fn validate(&self, _: &mut Violations) {}
+}
+
+/// Middleware guarding admin routes with a bearer token.
+pub async fn require_admin(
+    State(state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let expected = state
+        .admin_token
+        .as_deref()
+        .ok_or(AppError::AdminDisabled)?;
+    let provided = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .ok_or(AppError::Unauthorized)?;
+
+    if !constant_time_eq(provided.as_bytes(), expected.as_bytes()) {
+        return Err(AppError::Unauthorized);
+    }
+    Ok(next.run(req).await)
+}
+
+fn chain_or_404(chain_id: i32) -> Result<&'static ChainConfig, AppError> {
+    chains::chain_by_id(chain_id).ok_or_else(|| AppError::ChainNotFound(chain_id.to_string()))
+}
+
+/// Lists quarantined blocks for a chain in block-number order.
+///
+/// When more entries remain, the response carries a `Link: <...>; rel="next"` header
+/// whose URL holds a signed cursor for the next page.
+#[utoipa::path(
+    get,
+    path = "/v1/admin/chains/{chain_id}/quarantine",
+    tag = "Admin",
+    summary = "List quarantined blocks",
+    security(("admin_token" = [])),
+    params(
+        ("chain_id" = i32, Path, description = "The chain ID"),
+        ("limit" = Option<usize>, Query, description = "Maximum entries to return (default 100, max 1000)"),
+        ("cursor" = Option<String>, Query, description = "Opaque cursor from a previous page's `Link` header")
+    ),
+    responses(
+        (status = 200, description = "Quarantined blocks", body = Vec<RejectedBlockResponse>),
+        (status = 400, description = "Invalid cursor or limit", body = kizami_shared::models::ErrorBody),
+        (status = 401, description = "Missing or invalid admin token", body = kizami_shared::models::ErrorBody),
+        (status = 404, description = "Chain not found", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn list_quarantine(
+    State(state): State<AppState>,
+    Path(chain_id): Path<i32>,
+    ValidQuery(query): ValidQuery<ListQuery>,
+) -> Result<(HeaderMap, Json<Vec<RejectedBlockResponse>>), AppError> {
+    let requested_id = chain_id;
+    let chain_id = chain_or_404(chain_id)?.chain_id;
+    let limit = query.limit.unwrap_or(DEFAULT_LIST_LIMIT);
+    let from = match query.cursor.as_deref() {
+        Some(token) => {
+            let (last, _) = state.cursors.decode(Scope::Quarantine, chain_id, token)?;
+            last.saturating_add(1)
+        }
+        None => 0,
+    };
+
+    // one extra entry tells us whether there's a next page
+    let mut entries = state.storage.list_rejected(chain_id, from, limit + 1)?;
+    let mut headers = HeaderMap::new();
+    if entries.len() > limit {
+        entries.truncate(limit);
+        if let Some(last) = entries.last() {
+            let cursor = state
+                .cursors
+                .encode(Scope::Quarantine, chain_id, (last.number, 0));
+            let link = format!(
+                "</v1/admin/chains/{requested_id}/quarantine?limit={limit}&cursor={cursor}>; rel=\"next\""
+            );
+            if let Ok(value) = HeaderValue::from_str(&link) {
+                headers.insert(header::LINK, value);
+            }
+        }
+    }
+
+    Ok((
+        headers,
+        Json(
+            entries
+                .into_iter()
+                .map(|r| RejectedBlockResponse {
+                    number: r.number,
+                    timestamp: r.timestamp,
+                    reason: r.reason.as_str(),
+                    rejected_at: r.rejected_at,
+                })
+                .collect(),
+        ),
+    ))
+}
+
+/// Re-runs validation on all quarantined blocks for a chain and indexes those that pass.
+#[utoipa::path(
+    post,
+    path = "/v1/admin/chains/{chain_id}/quarantine/revalidate",
+    tag = "Admin",
+    summary = "Re-validate quarantined blocks",
+    security(("admin_token" = [])),
+    params(("chain_id" = i32, Path, description = "The chain ID")),
+    responses(
+        (status = 200, description = "Blocks that now pass validation were indexed", body = QuarantineActionResponse),
+        (status = 401, description = "Missing or invalid admin token", body = kizami_shared::models::ErrorBody),
+        (status = 404, description = "Chain not found", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn revalidate_quarantine(
+    Extension(storage): Extension<Storage>,
+    Path(chain_id): Path<i32>,
+) -> Result<Json<QuarantineActionResponse>, AppError> {
+    let chain = chain_or_404(chain_id)?;
+    let chain_id = chain.chain_id;
+    let now = clock::now().timestamp();
+
+    let passing: Vec<i64> = storage
+        .list_rejected(chain_id, 0, usize::MAX)?
+        .into_iter()
+        .filter(|r| {
+            let header = BlockHeader {
+                number: r.number,
+                timestamp: r.timestamp,
+            };
+            validation::validate_header(chain, &header, now).is_ok()
+        })
+        .map(|r| r.number)
+        .collect();
+
+    let affected = storage.accept_rejected(chain_id, Some(&passing))?;
+    Ok(Json(QuarantineActionResponse {
+        affected,
+        remaining: storage.rejected_count(chain_id)?,
+    }))
+}
+
+/// Force-accepts quarantined blocks into the serving index, bypassing validation.
+#[utoipa::path(
+    post,
+    path = "/v1/admin/chains/{chain_id}/quarantine/accept",
+    tag = "Admin",
+    summary = "Accept quarantined blocks",
+    security(("admin_token" = [])),
+    params(("chain_id" = i32, Path, description = "The chain ID")),
+    request_body = QuarantineSelection,
+    responses(
+        (status = 200, description = "Blocks indexed", body = QuarantineActionResponse),
+        (status = 401, description = "Missing or invalid admin token", body = kizami_shared::models::ErrorBody),
+        (status = 404, description = "Chain not found", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn accept_quarantine(
+    Extension(storage): Extension<Storage>,
+    Path(chain_id): Path<i32>,
+    ValidJson(selection): ValidJson<QuarantineSelection>,
+) -> Result<Json<QuarantineActionResponse>, AppError> {
+    let chain_id = chain_or_404(chain_id)?.chain_id;
+    let affected = storage.accept_rejected(chain_id, selection.numbers.as_deref())?;
+    Ok(Json(QuarantineActionResponse {
+        affected,
+        remaining: storage.rejected_count(chain_id)?,
+    }))
+}
+
+/// Deletes quarantined blocks without indexing them.
+#[utoipa::path(
+    post,
+    path = "/v1/admin/chains/{chain_id}/quarantine/purge",
+    tag = "Admin",
+    summary = "Purge quarantined blocks",
+    security(("admin_token" = [])),
+    params(("chain_id" = i32, Path, description = "The chain ID")),
+    request_body = QuarantineSelection,
+    responses(
+        (status = 200, description = "Blocks purged", body = QuarantineActionResponse),
+        (status = 401, description = "Missing or invalid admin token", body = kizami_shared::models::ErrorBody),
+        (status = 404, description = "Chain not found", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn purge_quarantine(
+    Extension(storage): Extension<Storage>,
+    Path(chain_id): Path<i32>,
+    ValidJson(selection): ValidJson<QuarantineSelection>,
+) -> Result<Json<QuarantineActionResponse>, AppError> {
+    let chain_id = chain_or_404(chain_id)?.chain_id;
+    let affected = storage.purge_rejected(chain_id, selection.numbers.as_deref())?;
+    Ok(Json(QuarantineActionResponse {
+        affected,
+        remaining: storage.rejected_count(chain_id)?,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::routing::{get, post};
+    use axum::Router;
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    use kizami_shared::storage::Storage;
+    use kizami_shared::validation::RejectReason;
+
+    use super::*;
+
+    fn test_state(admin_token: Option<&str>) -> (AppState, Storage, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        let state = AppState {
+            admin_token: admin_token.map(Arc::from),
+            ..AppState::for_tests(&storage)
+        };
+        (state, storage, dir)
+    }
+
+    fn app(state: AppState, storage: &Storage) -> Router {
+        Router::new()
+            .route(
+                "/v1/admin/chains/{chain_id}/quarantine",
+                get(list_quarantine),
+            )
+            .route(
+                "/v1/admin/chains/{chain_id}/quarantine/revalidate",
+                post(revalidate_quarantine),
+            )
+            .route(
+                "/v1/admin/chains/{chain_id}/quarantine/purge",
+                post(purge_quarantine),
+            )
+            .layer(axum::middleware::from_fn_with_state(
+                state.clone(),
+                require_admin,
+            ))
+            .layer(Extension(storage.clone()))
+            .with_state(state)
+    }
+
+    async fn send(
+        app: Router,
+        method: &str,
+        uri: &str,
+        token: Option<&str>,
+        body: &str,
+    ) -> (StatusCode, serde_json::Value) {
+        let mut req = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::CONTENT_TYPE, "application/json");
+        if let Some(token) = token {
+            req = req.header(header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+        let response = app
+            .oneshot(req.body(Body::from(body.to_string())).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        (status, json)
+    }
+
+    fn quarantine(storage: &Storage, blocks: &[(i64, i64)]) {
+        let rejected: Vec<_> = blocks
+            .iter()
+            .map(|&(number, timestamp)| {
+                (
+                    BlockHeader { number, timestamp },
+                    RejectReason::BeforeGenesis,
+                )
+            })
+            .collect();
+        storage.insert_rejected(1, &rejected).unwrap();
+    }
+
+    #[tokio::test]
+    async fn listing_pages_with_signed_cursor() {
+        let (state, storage, _dir) = test_state(Some("secret"));
+        quarantine(&storage, &[(1, 1000), (2, 2000), (3, 3000)]);
+
+        let response = app(state.clone(), &storage)
+            .oneshot(
+                Request::get("/v1/admin/chains/1/quarantine?limit=2")
+                    .header(header::AUTHORIZATION, "Bearer secret")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let link = response.headers()[header::LINK]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let next = link
+            .strip_prefix('<')
+            .and_then(|l| l.split_once(">; rel=\"next\""))
+            .unwrap()
+            .0;
+
+        let (status, json) = send(
+            app(state.clone(), &storage),
+            "GET",
+            next,
+            Some("secret"),
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json.as_array().unwrap().len(), 1);
+        assert_eq!(json[0]["number"], 3);
+
+        let forged = "/v1/admin/chains/1/quarantine?cursor=AAAA";
+        let (status, json) = send(app(state, &storage), "GET", forged, Some("secret"), "").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(json["error"]["code"], "INVALID_CURSOR");
+    }
+
+    #[tokio::test]
+    async fn disabled_without_token() {
+        let (state, storage, _dir) = test_state(None);
+        let (status, json) = send(
+            app(state, &storage),
+            "GET",
+            "/v1/admin/chains/1/quarantine",
+            Some("x"),
+            "",
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(json["error"]["code"], "ADMIN_DISABLED");
+    }
+
+    #[tokio::test]
+    async fn wrong_token_returns_401() {
+        let (state, storage, _dir) = test_state(Some("secret"));
+        let (status, json) = send(
+            app(state, &storage),
+            "GET",
+            "/v1/admin/chains/1/quarantine",
+            Some("guess"),
+            "",
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(json["error"]["code"], "UNAUTHORIZED");
+    }
+
+    #[tokio::test]
+    async fn list_returns_quarantined_blocks() {
+        let (state, storage, _dir) = test_state(Some("secret"));
+        quarantine(&storage, &[(5, 100)]);
+
+        let (status, json) = send(
+            app(state, &storage),
+            "GET",
+            "/v1/admin/chains/1/quarantine",
+            Some("secret"),
+            "",
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json[0]["number"], 5);
+        assert_eq!(json[0]["reason"], "before_genesis");
+    }
+
+    #[tokio::test]
+    async fn revalidate_accepts_only_passing_blocks() {
+        let (state, storage, _dir) = test_state(Some("secret"));
+        // block 6 has a plausible post-genesis timestamp, block 5 does not
+        quarantine(&storage, &[(5, 100), (6, 1_500_000_000)]);
+
+        let (status, json) = send(
+            app(state.clone(), &storage),
+            "POST",
+            "/v1/admin/chains/1/quarantine/revalidate",
+            Some("secret"),
+            "",
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["affected"], 1);
+        assert_eq!(json["remaining"], 1);
+    }
+
+    #[tokio::test]
+    async fn purge_selected_blocks() {
+        let (state, storage, _dir) = test_state(Some("secret"));
+        quarantine(&storage, &[(5, 100), (6, 200)]);
+
+        let (status, json) = send(
+            app(state, &storage),
+            "POST",
+            "/v1/admin/chains/1/quarantine/purge",
+            Some("secret"),
+            r#"{"numbers":[6]}"#,
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["affected"], 1);
+        assert_eq!(json["remaining"], 1);
+    }
+}
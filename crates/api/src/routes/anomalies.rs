@@ -0,0 +1,78 @@
+//! Admin endpoint for inspecting timestamp anomalies seen during ingestion.
+//!
+//! Ingestion compares each fetched block's timestamp with the block before it and
+//! records blocks that go backwards or jump further than `TIMESTAMP_JUMP_ALERT_SECS`
+//! (see `kizami_shared::validation::find_timestamp_anomalies`). The blocks themselves
+//! are indexed as usual; this is where an operator checks whether an alert was source
+//! corruption, a chain halt, or noise.
+
+use axum::extract::{Path, State};
+use axum::Json;
+use serde::Deserialize;
+
+use kizami_shared::chains;
+use kizami_shared::error::AppError;
+use kizami_shared::models::TimestampAnomalyResponse;
+
+use crate::state::AppState;
+use crate::validate::{ValidQuery, Validate, Violations};
+
+/// Default and maximum number of anomalies returned.
+const DEFAULT_LIST_LIMIT: usize = 100;
+const MAX_LIST_LIMIT: usize = 1000;
+
+#[derive(Deserialize)]
+pub struct AnomalyQuery {
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+impl Validate for AnomalyQuery {
+    fn validate(&self, v: &mut Violations) {
+        v.limit("limit", self.limit, MAX_LIST_LIMIT);
+    }
+}
+
+/// Lists a chain's recorded timestamp anomalies, highest block number first.
+#[utoipa::path(
+    get,
+    path = "/v1/admin/chains/{chain_id}/anomalies",
+    tag = "Admin",
+    summary = "List timestamp anomalies",
+    description = "Blocks whose timestamp was earlier than the previous block's, or later by more than TIMESTAMP_JUMP_ALERT_SECS, when ingested. Newest first.",
+    security(("admin_token" = [])),
+    params(
+        ("chain_id" = i32, Path, description = "The chain ID"),
+        ("limit" = Option<usize>, Query, description = "Maximum entries to return (default 100, max 1000)")
+    ),
+    responses(
+        (status = 200, description = "Recorded anomalies", body = Vec<TimestampAnomalyResponse>),
+        (status = 400, description = "Invalid limit", body = kizami_shared::models::ErrorBody),
+        (status = 401, description = "Missing or invalid admin token", body = kizami_shared::models::ErrorBody),
+        (status = 404, description = "Chain not found", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn list_anomalies(
+    State(state): State<AppState>,
+    Path(chain_id): Path<i32>,
+    ValidQuery(query): ValidQuery<AnomalyQuery>,
+) -> Result<Json<Vec<TimestampAnomalyResponse>>, AppError> {
+    let chain = chains::chain_by_id(chain_id)
+        .ok_or_else(|| AppError::ChainNotFound(chain_id.to_string()))?;
+    let limit = query.limit.unwrap_or(DEFAULT_LIST_LIMIT);
+    let anomalies = state.storage.list_anomalies(chain.chain_id, limit)?;
+    Ok(Json(
+        anomalies
+            .into_iter()
+            .map(|r| TimestampAnomalyResponse {
+                kind: r.anomaly.kind.as_str(),
+                number: r.anomaly.number,
+                timestamp: r.anomaly.timestamp,
+                prev_number: r.anomaly.prev_number,
+                prev_timestamp: r.anomaly.prev_timestamp,
+                gap_secs: r.anomaly.timestamp - r.anomaly.prev_timestamp,
+                detected_at: r.detected_at,
+            })
+            .collect(),
+    ))
+}
@@ -0,0 +1,288 @@
+//! Beacon chain slot and epoch endpoints for Ethereum.
+//!
+//! Converts between beacon slots, epochs and timestamps with fixed 12s slot math, and
+//! maps them to execution blocks through the regular Ethereum block index. Only
+//! post-merge slots carry execution blocks.
+
+use axum::extract::{Path, State};
+use axum::Json;
+
+use kizami_shared::beacon::{self, ETHEREUM_CHAIN_ID, MERGE_SLOT};
+use kizami_shared::chains;
+use kizami_shared::error::AppError;
+use kizami_shared::models::{BeaconEpochResponse, BeaconSlotResponse, Bound};
+
+use crate::cache::LookupKey;
+use crate::state::AppState;
+
+async fn indexed_up_to(state: &AppState) -> i64 {
+    let slug = chains::chain_by_id(ETHEREUM_CHAIN_ID)
+        .map(|c| c.sqd_slug)
+        .unwrap_or_default();
+    let map = state.progress.read().await;
+    map.get(slug).map(|p| p.cursor).unwrap_or(0)
+}
+
+/// First Ethereum block at or after `timestamp`, or `NOT_YET_INDEXED`.
+async fn first_block_from(
+    state: &AppState,
+    timestamp: i64,
+    indexed_up_to: i64,
+) -> Result<(i64, i64), AppError> {
+    let key = LookupKey {
+        chain_id: ETHEREUM_CHAIN_ID,
+        timestamp,
+        bound: Bound::AtOrAfter,
+    };
+    let storage = &state.storage;
+    state
+        .lookups
+        .get_or_load(key, indexed_up_to, || async move {
+            storage.find_block(ETHEREUM_CHAIN_ID, timestamp, Bound::AtOrAfter)
+        })
+        .await?
+        .ok_or_else(|| AppError::NotYetIndexed {
+            chain_id: ETHEREUM_CHAIN_ID.to_string(),
+            timestamp,
+            indexed_up_to,
+        })
+}
+
+async fn slot_response(state: &AppState, slot: i64) -> Result<BeaconSlotResponse, AppError> {
+    let timestamp = beacon::slot_timestamp(slot)
+        .filter(|_| slot >= 0)
+        .ok_or_else(|| AppError::InvalidTimestamp(format!("slot {slot} out of range")))?;
+    let indexed_up_to = indexed_up_to(state).await;
+
+    let (block_number, missed) = if slot >= MERGE_SLOT {
+        let (number, block_ts) = first_block_from(state, timestamp, indexed_up_to).await?;
+        if block_ts == timestamp {
+            (Some(number), false)
+        } else {
+            (None, true)
+        }
+    } else {
+        (None, false)
+    };
+
+    Ok(BeaconSlotResponse {
+        slot,
+        epoch: beacon::epoch_of(slot),
+        timestamp,
+        block_number,
+        missed,
+        indexed_up_to,
+    })
+}
+
+/// Returns the start time, epoch and execution block of a beacon slot.
+#[utoipa::path(
+    get,
+    path = "/v1/beacon/slots/{slot}",
+    tag = "Beacon",
+    summary = "Look up a beacon slot",
+    description = "Returns the slot's start time, epoch, and the execution block proposed in it (null if the slot was missed or is pre-merge).",
+    params(("slot" = i64, Path, description = "Beacon slot number")),
+    responses(
+        (status = 200, description = "Slot details", body = BeaconSlotResponse),
+        (status = 400, description = "Slot out of range", body = kizami_shared::models::ErrorBody),
+        (status = 404, description = "Slot not yet indexed", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn get_slot(
+    State(state): State<AppState>,
+    Path(slot): Path<i64>,
+) -> Result<Json<BeaconSlotResponse>, AppError> {
+    Ok(Json(slot_response(&state, slot).await?))
+}
+
+/// Returns the beacon slot in progress at a Unix timestamp.
+#[utoipa::path(
+    get,
+    path = "/v1/beacon/timestamp/{timestamp}",
+    tag = "Beacon",
+    summary = "Find the beacon slot at a timestamp",
+    params(("timestamp" = i64, Path, description = "Unix timestamp in seconds")),
+    responses(
+        (status = 200, description = "Slot in progress at the timestamp", body = BeaconSlotResponse),
+        (status = 400, description = "Timestamp before beacon genesis", body = kizami_shared::models::ErrorBody),
+        (status = 404, description = "Slot not yet indexed", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn slot_at_timestamp(
+    State(state): State<AppState>,
+    Path(timestamp): Path<i64>,
+) -> Result<Json<BeaconSlotResponse>, AppError> {
+    let slot = beacon::slot_at(timestamp).ok_or_else(|| {
+        AppError::InvalidTimestamp(format!("{timestamp} is before beacon genesis"))
+    })?;
+    Ok(Json(slot_response(&state, slot).await?))
+}
+
+/// Returns an epoch's slot range, time range and first execution block.
+#[utoipa::path(
+    get,
+    path = "/v1/beacon/epochs/{epoch}",
+    tag = "Beacon",
+    summary = "Look up a beacon epoch",
+    description = "Returns the epoch's slots and times, and the first execution block at or after its start (null for pre-merge epochs).",
+    params(("epoch" = i64, Path, description = "Beacon epoch number")),
+    responses(
+        (status = 200, description = "Epoch details", body = BeaconEpochResponse),
+        (status = 400, description = "Epoch out of range", body = kizami_shared::models::ErrorBody),
+        (status = 404, description = "Epoch not yet indexed", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn get_epoch(
+    State(state): State<AppState>,
+    Path(epoch): Path<i64>,
+) -> Result<Json<BeaconEpochResponse>, AppError> {
+    let out_of_range = || AppError::InvalidTimestamp(format!("epoch {epoch} out of range"));
+    let (start_slot, end_slot) = beacon::epoch_slots(epoch)
+        .filter(|_| epoch >= 0)
+        .ok_or_else(out_of_range)?;
+    let start_timestamp = beacon::slot_timestamp(start_slot).ok_or_else(out_of_range)?;
+    let end_timestamp = beacon::slot_timestamp(end_slot).ok_or_else(out_of_range)?;
+    let indexed_up_to = indexed_up_to(&state).await;
+
+    let block = if end_slot >= MERGE_SLOT {
+        // the merge epoch starts with proof-of-work blocks; skip past them
+        let from = start_timestamp.max(beacon::slot_timestamp(MERGE_SLOT).unwrap_or_default());
+        Some(first_block_from(&state, from, indexed_up_to).await?)
+    } else {
+        None
+    };
+
+    Ok(Json(BeaconEpochResponse {
+        epoch,
+        start_slot,
+        end_slot,
+        start_timestamp,
+        end_timestamp,
+        block_number: block.map(|b| b.0),
+        block_timestamp: block.map(|b| b.1),
+        indexed_up_to,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::routing::get;
+    use axum::Router;
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    use kizami_shared::storage::Storage;
+
+    use super::*;
+
+    fn test_state() -> (AppState, Storage, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        let state = AppState::for_tests(&storage);
+        (state, storage, dir)
+    }
+
+    fn app(state: AppState) -> Router {
+        Router::new()
+            .route("/v1/beacon/slots/{slot}", get(get_slot))
+            .route("/v1/beacon/timestamp/{timestamp}", get(slot_at_timestamp))
+            .route("/v1/beacon/epochs/{epoch}", get(get_epoch))
+            .with_state(state)
+    }
+
+    async fn get_json(app: Router, uri: &str) -> (StatusCode, serde_json::Value) {
+        let response = app
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    /// Indexes post-merge blocks for slots `MERGE_SLOT..MERGE_SLOT + 40`, skipping
+    /// slot `MERGE_SLOT + 2` as missed.
+    fn index_merge_blocks(storage: &Storage) {
+        let mut numbers = Vec::new();
+        let mut timestamps = Vec::new();
+        let mut number = 15537394;
+        for slot in MERGE_SLOT..MERGE_SLOT + 40 {
+            if slot == MERGE_SLOT + 2 {
+                continue;
+            }
+            numbers.push(number);
+            timestamps.push(beacon::slot_timestamp(slot).unwrap());
+            number += 1;
+        }
+        // last proof-of-work block
+        numbers.push(15537393);
+        timestamps.push(1663224162);
+        storage.insert_blocks(1, &numbers, &timestamps).unwrap();
+    }
+
+    #[tokio::test]
+    async fn slot_maps_to_execution_block() {
+        let (state, storage, _dir) = test_state();
+        index_merge_blocks(&storage);
+
+        let (status, json) = get_json(app(state), &format!("/v1/beacon/slots/{MERGE_SLOT}")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["block_number"], 15537394);
+        assert_eq!(json["timestamp"], 1663224179);
+        assert_eq!(json["epoch"], 146875);
+        assert_eq!(json["missed"], false);
+    }
+
+    #[tokio::test]
+    async fn missed_slot_has_no_block() {
+        let (state, storage, _dir) = test_state();
+        index_merge_blocks(&storage);
+
+        let uri = format!("/v1/beacon/slots/{}", MERGE_SLOT + 2);
+        let (status, json) = get_json(app(state), &uri).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(json["block_number"].is_null());
+        assert_eq!(json["missed"], true);
+    }
+
+    #[tokio::test]
+    async fn timestamp_resolves_to_containing_slot() {
+        let (state, storage, _dir) = test_state();
+        index_merge_blocks(&storage);
+
+        let (status, json) = get_json(app(state), "/v1/beacon/timestamp/1663224190").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["slot"], MERGE_SLOT);
+    }
+
+    #[tokio::test]
+    async fn merge_epoch_skips_proof_of_work_blocks() {
+        let (state, storage, _dir) = test_state();
+        index_merge_blocks(&storage);
+
+        let (status, json) = get_json(app(state), "/v1/beacon/epochs/146875").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["start_slot"], 4700000);
+        assert_eq!(json["block_number"], 15537394);
+    }
+
+    #[tokio::test]
+    async fn pre_merge_epoch_has_no_block() {
+        let (state, _, _dir) = test_state();
+        let (status, json) = get_json(app(state), "/v1/beacon/epochs/0").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["start_timestamp"], beacon::BEACON_GENESIS_TIMESTAMP);
+        assert!(json["block_number"].is_null());
+    }
+
+    #[tokio::test]
+    async fn unindexed_slot_returns_not_yet_indexed() {
+        let (state, _, _dir) = test_state();
+        let (status, json) = get_json(app(state), "/v1/beacon/slots/9000000").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(json["error"]["code"], "NOT_YET_INDEXED");
+    }
+}
@@ -0,0 +1,216 @@
+//! Paged listing of the blocks in a time window.
+//!
+//! Walks the `blocks` keyspace from `from_ts` in key order and returns up to `limit`
+//! `(number, timestamp)` pairs per page, for analytics that need every block of an hour
+//! or a day rather than the closest one. Pages continue from a signed cursor holding
+//! the last key returned, so blocks sharing a timestamp are never split or repeated
+//! across pages. Each page is bounded, so unlike the export there is no cap on the
+//! window; whole chains are better fetched as the index file.
+
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, HeaderValue};
+use axum::Json;
+use serde::Deserialize;
+
+use kizami_shared::chains;
+use kizami_shared::error::AppError;
+use kizami_shared::models::{BlockListResponse, BlockRef};
+
+use crate::pagination::Scope;
+use crate::state::AppState;
+use crate::validate::{ValidQuery, Validate, Violations};
+
+const DEFAULT_LIST_LIMIT: usize = 100;
+const MAX_LIST_LIMIT: usize = 1000;
+
+#[derive(Deserialize)]
+pub struct BlockListQuery {
+    from_ts: i64,
+    to_ts: i64,
+    #[serde(default)]
+    limit: Option<usize>,
+    /// Cursor from a previous page.
+    #[serde(default)]
+    cursor: Option<String>,
+}
+
+impl Validate for BlockListQuery {
+    fn validate(&self, v: &mut Violations) {
+        v.window(("from_ts", self.from_ts), ("to_ts", self.to_ts));
+        v.limit("limit", self.limit, MAX_LIST_LIMIT);
+    }
+}
+
+/// Lists blocks with `from_ts <= timestamp < to_ts`, a page at a time.
+///
+/// When more blocks remain, `next_cursor` is set and a `Link: <...>; rel="next"`
+/// header carries the URL of the next page.
+#[utoipa::path(
+    get,
+    path = "/v1/chains/{chain_id}/blocks",
+    tag = "Blocks",
+    summary = "List blocks in a time window",
+    description = "Returns blocks with from_ts <= timestamp < to_ts in timestamp order, up to limit per page (default 100, max 1000). When more remain, next_cursor is set; pass it as cursor, or follow the Link rel=\"next\" header, for the next page.",
+    params(
+        ("chain_id" = i32, Path, description = "The chain ID (e.g. 1 for Ethereum, 8453 for Base)"),
+        ("from_ts" = i64, Query, description = "Window start (Unix seconds, inclusive)"),
+        ("to_ts" = i64, Query, description = "Window end (Unix seconds, exclusive)"),
+        ("limit" = Option<usize>, Query, description = "Maximum blocks per page (default 100, max 1000)"),
+        ("cursor" = Option<String>, Query, description = "Opaque cursor from a previous page's `next_cursor`")
+    ),
+    responses(
+        (status = 200, description = "A page of blocks", body = BlockListResponse),
+        (status = 400, description = "Invalid window, limit or cursor", body = kizami_shared::models::ErrorBody),
+        (status = 404, description = "Chain not found", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn list_blocks(
+    State(state): State<AppState>,
+    Path(chain_id): Path<i32>,
+    ValidQuery(query): ValidQuery<BlockListQuery>,
+) -> Result<(HeaderMap, Json<BlockListResponse>), AppError> {
+    let requested_id = chain_id;
+    let chain = chains::chain_by_id(chain_id)
+        .ok_or_else(|| AppError::ChainNotFound(chain_id.to_string()))?;
+    let limit = query.limit.unwrap_or(DEFAULT_LIST_LIMIT);
+    let from = match query.cursor.as_deref() {
+        Some(token) => {
+            let (ts, number) = state
+                .cursors
+                .decode(Scope::BlockList, chain.chain_id, token)?;
+            (ts, number.saturating_add(1)).max((query.from_ts, 0))
+        }
+        None => (query.from_ts, 0),
+    };
+
+    // one extra row tells us whether there's a next page
+    let mut rows = state
+        .storage
+        .scan_blocks(chain.chain_id, from, query.to_ts, limit + 1)?;
+    let mut headers = HeaderMap::new();
+    let mut next_cursor = None;
+    if rows.len() > limit {
+        rows.truncate(limit);
+        if let Some(&(number, timestamp)) = rows.last() {
+            let cursor =
+                state
+                    .cursors
+                    .encode(Scope::BlockList, chain.chain_id, (timestamp, number));
+            let link = format!(
+                "</v1/chains/{requested_id}/blocks?from_ts={}&to_ts={}&limit={limit}&cursor={cursor}>; rel=\"next\"",
+                query.from_ts, query.to_ts
+            );
+            if let Ok(value) = HeaderValue::from_str(&link) {
+                headers.insert(header::LINK, value);
+            }
+            next_cursor = Some(cursor);
+        }
+    }
+
+    Ok((
+        headers,
+        Json(BlockListResponse {
+            chain_id: chain.chain_id,
+            from_ts: query.from_ts,
+            to_ts: query.to_ts,
+            blocks: rows
+                .into_iter()
+                .map(|(number, timestamp)| BlockRef { number, timestamp })
+                .collect(),
+            next_cursor,
+        }),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::routing::get;
+    use axum::Router;
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    use kizami_shared::storage::Storage;
+
+    use super::*;
+
+    async fn get_page(state: AppState, uri: &str) -> (StatusCode, HeaderMap, serde_json::Value) {
+        let app = Router::new()
+            .route("/v1/chains/{chain_id}/blocks", get(list_blocks))
+            .with_state(state);
+        let response = app
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let (status, headers) = (response.status(), response.headers().clone());
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, headers, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn pages_through_window_without_splitting_ties() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        storage
+            .insert_blocks(1, &[9, 10, 11, 12, 13, 14], &[99, 100, 100, 100, 101, 102])
+            .unwrap();
+        let state = AppState::for_tests(&storage);
+
+        let (status, headers, page) = get_page(
+            state.clone(),
+            "/v1/chains/1/blocks?from_ts=100&to_ts=102&limit=2",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            page["blocks"],
+            serde_json::json!([{"number": 10, "timestamp": 100}, {"number": 11, "timestamp": 100}])
+        );
+        let link = headers[header::LINK].to_str().unwrap();
+        let next = link
+            .strip_prefix('<')
+            .and_then(|l| l.split_once('>'))
+            .unwrap()
+            .0;
+        assert!(next.ends_with(page["next_cursor"].as_str().unwrap()));
+
+        // block 12 shares its timestamp with the last row of the first page
+        let (_, headers, rest) = get_page(state.clone(), next).await;
+        let numbers: Vec<_> = rest["blocks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|b| b["number"].as_i64().unwrap())
+            .collect();
+        assert_eq!(numbers, vec![12, 13]);
+        assert!(rest["next_cursor"].is_null());
+        assert!(headers.get(header::LINK).is_none());
+
+        // a cursor is only good for the chain it was issued on
+        let cursor = page["next_cursor"].as_str().unwrap();
+        let uri = format!("/v1/chains/10/blocks?from_ts=100&to_ts=102&cursor={cursor}");
+        let (status, _, body) = get_page(state, &uri).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["code"], "INVALID_CURSOR");
+    }
+
+    #[tokio::test]
+    async fn rejects_bad_window_and_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        let state = AppState::for_tests(&storage);
+        for uri in [
+            "/v1/chains/1/blocks?from_ts=10&to_ts=10",
+            "/v1/chains/1/blocks?from_ts=10&to_ts=20&limit=0",
+            "/v1/chains/1/blocks?from_ts=10&to_ts=20&limit=1001",
+        ] {
+            let (status, _, _) = get_page(state.clone(), uri).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{uri}");
+        }
+        let (status, _, body) =
+            get_page(state, "/v1/chains/999999/blocks?from_ts=10&to_ts=20").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"]["code"], "CHAIN_NOT_FOUND");
+    }
+}
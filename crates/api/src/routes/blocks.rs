@@ -1,136 +1,793 @@
 //! Block lookup endpoint.
 //!
-//! Finds the closest block before or after a given Unix timestamp for a specific chain.
+//! Finds the closest block before or after a given Unix timestamp for a specific chain,
+//! or with `nearest` whichever of the two is closer in time (the earlier on a tie).
 //! Results come from the embedded fjall storage. The `indexed_up_to` field tells clients
-//! how far ingestion has progressed.
+//! how far ingestion has progressed. On approximate-mode chains (see
+//! `kizami_shared::approximate`) answers are interpolated and flagged `approximate`.
+//!
+//! A deployment can set the direction and `inclusive` used when a request leaves them
+//! out (`DEFAULT_DIRECTION`, `DEFAULT_INCLUSIVE`); `/v1/chains/{id}/block/{timestamp}`
+//! is the lookup with the default direction.
+//!
+//! Timestamps in the path are Unix seconds or RFC 3339 date-times
+//! (`2024-03-01T00:00:00Z`, `2024-03-01T09:00:00+09:00`), converted to Unix seconds
+//! before the lookup; fractions of a second are dropped.
+//!
+//! `/v1/block/{direction}/{timestamp}` runs the same lookup on many chains at once, for
+//! cross-chain snapshot tooling.
+//!
+//! With `?allow_estimate=true`, a timestamp past the last indexed block (or past
+//! genesis, on a chain with nothing indexed yet) gets a block extrapolated at the
+//! chain's average block time, flagged `estimated`, instead of `NOT_YET_INDEXED` or the
+//! indexed tip. Without it lookups stay strict.
+//!
+//! `Cache-Control: no-cache` or `?fresh=true` makes a lookup skip the cache and read
+//! storage, within the cache's bypass limit; `X-Kizami-Cache` then says `bypass`, or
+//! `bypass-limited` when the limit was spent and the answer came through the cache.
 
-use axum::extract::{Path, Query, State};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue};
 use axum::Json;
+use chrono::{DateTime, FixedOffset};
+use futures_util::future::join_all;
 use serde::Deserialize;
 
-use kizami_shared::chains;
+use kizami_shared::approximate::{self, Estimate};
+use kizami_shared::chains::{self, ChainConfig};
+use kizami_shared::clock;
 use kizami_shared::error::AppError;
-use kizami_shared::models::BlockResponse;
+use kizami_shared::models::{
+    BatchItemResponse, BatchItemStatus, BatchLookupResponse, BlockRef, BlockResponse, Bound,
+    Direction, MultiChainBlockResponse, Tie,
+};
 
+use crate::cache::LookupKey;
+use crate::routes::{snapshot, status};
 use crate::state::AppState;
+use crate::validate::{ValidJson, ValidQuery, Validate, Violations};
 
-/// Valid directions for block lookup.
-#[derive(utoipa::ToSchema)]
-#[schema(rename_all = "lowercase")]
-#[allow(dead_code)]
-enum Direction {
-    Before,
-    After,
-}
-
+/// Path parameters for block lookups. `direction` and `timestamp` stay strings here so
+/// that bad values surface as `INVALID_DIRECTION` and `INVALID_TIMESTAMP` rather than
+/// axum's plain-text rejection.
 #[derive(Deserialize)]
 pub struct BlockPath {
     chain_id: i32,
     direction: String,
-    timestamp: i64,
+    timestamp: String,
+}
+
+/// Path parameters for the lookup in the default direction.
+#[derive(Deserialize)]
+pub struct DefaultBlockPath {
+    chain_id: i32,
+    timestamp: String,
+}
+
+/// Unix seconds from a path timestamp: an integer, or an RFC 3339 date-time.
+fn parse_timestamp(value: &str) -> Result<i64, AppError> {
+    value
+        .parse()
+        .or_else(|_| DateTime::<FixedOffset>::parse_from_rfc3339(value).map(|at| at.timestamp()))
+        .map_err(|_| {
+            AppError::InvalidTimestamp(format!(
+                "{value} (expected Unix seconds or an RFC 3339 date-time like 2024-03-01T00:00:00Z)"
+            ))
+        })
 }
 
 #[derive(Deserialize)]
 pub struct InclusiveQuery {
     #[serde(default)]
     inclusive: Option<bool>,
+    #[serde(default)]
+    allow_future: Option<bool>,
+    #[serde(default)]
+    limit: Option<usize>,
+    #[serde(default)]
+    fresh: Option<bool>,
+    #[serde(default)]
+    tie: Option<Tie>,
+    #[serde(default)]
+    allow_estimate: Option<bool>,
+}
+
+impl Validate for InclusiveQuery {
+    fn validate(&self, v: &mut Violations) {
+        v.limit("limit", self.limit, MAX_LOOKUP_LIMIT);
+    }
+}
+
+/// Most blocks a single lookup may return with `limit`.
+pub const MAX_LOOKUP_LIMIT: usize = 100;
+
+/// How far past the current wall clock a lookup timestamp may be before it is rejected.
+/// Catches millisecond timestamps passed as seconds, which would otherwise scan to the
+/// chain's end and return a misleading "latest block" answer.
+pub const MAX_FUTURE_SKEW_SECS: i64 = 24 * 60 * 60;
+
+/// Direction and inclusivity of lookups that don't specify them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LookupDefaults {
+    pub direction: Direction,
+    pub inclusive: bool,
+}
+
+/// `DEFAULT_DIRECTION` (default `before`) and `DEFAULT_INCLUSIVE` (default false), read
+/// once on first access. Unparseable values keep the defaults.
+static LOOKUP_DEFAULTS: LazyLock<LookupDefaults> = LazyLock::new(|| {
+    let direction = std::env::var("DEFAULT_DIRECTION").ok();
+    let direction = direction
+        .as_deref()
+        .map(str::trim)
+        .filter(|v| !v.is_empty());
+    LookupDefaults {
+        direction: match direction.map(str::parse) {
+            None => Direction::Before,
+            Some(Ok(direction)) => direction,
+            Some(Err(_)) => {
+                tracing::warn!(
+                    default_direction = direction,
+                    "unknown DEFAULT_DIRECTION, falling back to before"
+                );
+                Direction::Before
+            }
+        },
+        inclusive: std::env::var("DEFAULT_INCLUSIVE")
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(false),
+    }
+});
+
+pub fn lookup_defaults() -> LookupDefaults {
+    *LOOKUP_DEFAULTS
+}
+
+static X_KIZAMI_CACHE: HeaderName = HeaderName::from_static("x-kizami-cache");
+
+/// Whether the request asks to skip the lookup cache.
+fn wants_fresh(headers: &HeaderMap, fresh: Option<bool>) -> bool {
+    let no_cache = headers
+        .get_all(header::CACHE_CONTROL)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|directive| directive.trim().eq_ignore_ascii_case("no-cache"));
+    no_cache || fresh.unwrap_or(false)
+}
+
+static DEPRECATION: HeaderName = HeaderName::from_static("deprecation");
+static SUNSET: HeaderName = HeaderName::from_static("sunset");
+
+/// `Deprecation` and `Sunset` (RFC 8594) headers for lookups on a deprecated chain.
+fn lifecycle_headers(chain: &ChainConfig) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if !chain.is_deprecated() {
+        return headers;
+    }
+    headers.insert(DEPRECATION.clone(), HeaderValue::from_static("true"));
+    let sunset = chain
+        .sunset_at()
+        .and_then(|ts| DateTime::from_timestamp(ts, 0))
+        .and_then(|at| {
+            HeaderValue::from_str(&at.format("%a, %d %b %Y %H:%M:%S GMT").to_string()).ok()
+        });
+    if let Some(sunset) = sunset {
+        headers.insert(SUNSET.clone(), sunset);
+    }
+    headers
 }
 
 /// Finds the closest block before or after a given Unix timestamp.
 ///
 /// The lookup queries fjall storage using a range scan on the composite key
-/// `(chain_id, timestamp, number)`. The `inclusive` query parameter controls
-/// whether blocks at exactly the given timestamp are included.
+/// `(chain_id, timestamp, number)`. The `inclusive` query parameter controls whether
+/// blocks at exactly the given timestamp are included (default `DEFAULT_INCLUSIVE`).
+/// Final answers are cached, and concurrent identical misses share a single storage
+/// read. Timestamps more than a day in the future are rejected unless `allow_future` is
+/// set. Lookups on deprecated chains carry `Deprecation` and `Sunset` headers. With
+/// `limit`, the response also lists that many blocks in the lookup direction, read with
+/// one bounded range scan (uncached). When several blocks share the matched timestamp,
+/// `tie` picks the lowest or highest of them; without it the key order gives the one
+/// nearest the query. `nearest` returns whichever of the `before` and `after` blocks is
+/// closer in time, the `before` one when both are equally far, and is always inclusive
+/// (see [`Bound`]). `allow_estimate` answers timestamps past the indexed tip with an
+/// extrapolated block (see [`estimate_past_tip`]).
 #[utoipa::path(
     get,
     path = "/v1/chains/{chain_id}/block/{direction}/{timestamp}",
     tag = "Blocks",
     summary = "Find a block by timestamp",
-    description = "Finds the closest block before or after a given Unix timestamp for the specified chain.",
+    description = "Finds the closest block before or after a given Unix timestamp for the specified chain. `nearest` returns whichever of the two is closer in time, the earlier one on a tie.",
     params(
         ("chain_id" = i32, Path, description = "The chain ID (e.g. 1 for Ethereum, 8453 for Base)"),
-        ("direction" = inline(Direction), Path, description = "Whether to find the closest block before or after the timestamp"),
-        ("timestamp" = i64, Path, description = "Unix timestamp in seconds"),
-        ("inclusive" = Option<bool>, Query, description = "If true, includes blocks at exactly the given timestamp")
+        ("direction" = inline(Direction), Path, description = "Whether to find the closest block before or after the timestamp, or the nearer of the two"),
+        ("timestamp" = String, Path, description = "Unix timestamp in seconds, or an RFC 3339 date-time such as `2024-03-01T00:00:00Z`"),
+        ("inclusive" = Option<bool>, Query, description = "If true, includes blocks at exactly the given timestamp (default set by the deployment, normally false; always true for `nearest`, which rejects false)"),
+        ("allow_future" = Option<bool>, Query, description = "If true, accepts timestamps more than a day in the future"),
+        ("limit" = Option<usize>, Query, description = "Also return up to this many blocks (1 to 100) in the lookup direction, closest first"),
+        ("fresh" = Option<bool>, Query, description = "If true, skips the lookup cache like `Cache-Control: no-cache` (rate limited; see `X-Kizami-Cache`)"),
+        ("tie" = Option<Tie>, Query, description = "Which of several blocks sharing the matched timestamp to return: `low` or `high` block number (default: the one nearest the query, `high` for before and nearest, `low` for after)"),
+        ("allow_estimate" = Option<bool>, Query, description = "If true, a timestamp past the last indexed block gets a block extrapolated at the chain's average block time, flagged `estimated`, instead of 404 NOT_YET_INDEXED")
     ),
     responses(
         (status = 200, description = "Block found", body = BlockResponse),
-        (status = 400, description = "Invalid timestamp or direction", body = kizami_shared::models::ErrorBody),
-        (status = 404, description = "Chain or block not found", body = kizami_shared::models::ErrorBody)
+        (status = 400, description = "Invalid timestamp, direction or limit, or timestamp too far in the future", body = kizami_shared::models::ErrorBody),
+        (status = 404, description = "Chain or block not found, or not yet indexed", body = kizami_shared::models::ErrorBody),
+        (status = 500, description = "Storage error or corrupt data", body = kizami_shared::models::ErrorBody),
+        (status = 503, description = "Storage unavailable", body = kizami_shared::models::ErrorBody)
     )
 )]
 pub async fn find_block(
     State(state): State<AppState>,
     Path(params): Path<BlockPath>,
-    Query(query): Query<InclusiveQuery>,
-) -> Result<Json<BlockResponse>, AppError> {
+    headers: HeaderMap,
+    ValidQuery(query): ValidQuery<InclusiveQuery>,
+) -> Result<(HeaderMap, Json<BlockResponse>), AppError> {
     let BlockPath {
         chain_id,
         direction,
         timestamp,
     } = params;
-    let inclusive = query.inclusive.unwrap_or(false);
+    let bound = lookup_bound(direction.parse()?, query.inclusive)?;
+    let timestamp = parse_timestamp(&timestamp)?;
+    let fresh = wants_fresh(&headers, query.fresh);
+    lookup(&state, chain_id, bound, timestamp, query, fresh).await
+}
 
-    if direction != "before" && direction != "after" {
-        return Err(AppError::InvalidDirection(direction));
-    }
+/// Finds the closest block in the deployment's default direction.
+///
+/// Same as [`find_block`] with the direction taken from `DEFAULT_DIRECTION`, for
+/// clients that always look the same way (Etherscan's `closest=before`).
+#[utoipa::path(
+    get,
+    path = "/v1/chains/{chain_id}/block/{timestamp}",
+    tag = "Blocks",
+    summary = "Find a block by timestamp in the default direction",
+    description = "Finds the closest block to a Unix timestamp in the direction configured for the deployment (`before` unless changed). Otherwise identical to `/v1/chains/{chain_id}/block/{direction}/{timestamp}`.",
+    params(
+        ("chain_id" = i32, Path, description = "The chain ID (e.g. 1 for Ethereum, 8453 for Base)"),
+        ("timestamp" = String, Path, description = "Unix timestamp in seconds, or an RFC 3339 date-time such as `2024-03-01T00:00:00Z`"),
+        ("inclusive" = Option<bool>, Query, description = "If true, includes blocks at exactly the given timestamp (default set by the deployment, normally false; always true for `nearest`, which rejects false)"),
+        ("allow_future" = Option<bool>, Query, description = "If true, accepts timestamps more than a day in the future"),
+        ("limit" = Option<usize>, Query, description = "Also return up to this many blocks (1 to 100) in the lookup direction, closest first"),
+        ("fresh" = Option<bool>, Query, description = "If true, skips the lookup cache like `Cache-Control: no-cache` (rate limited; see `X-Kizami-Cache`)"),
+        ("tie" = Option<Tie>, Query, description = "Which of several blocks sharing the matched timestamp to return: `low` or `high` block number (default: the one nearest the query, `high` for before and `low` for after)"),
+        ("allow_estimate" = Option<bool>, Query, description = "If true, a timestamp past the last indexed block gets a block extrapolated at the chain's average block time, flagged `estimated`, instead of 404 NOT_YET_INDEXED")
+    ),
+    responses(
+        (status = 200, description = "Block found", body = BlockResponse),
+        (status = 400, description = "Invalid timestamp, limit or inclusive, or timestamp too far in the future", body = kizami_shared::models::ErrorBody),
+        (status = 404, description = "Chain or block not found, or not yet indexed", body = kizami_shared::models::ErrorBody),
+        (status = 500, description = "Storage error or corrupt data", body = kizami_shared::models::ErrorBody),
+        (status = 503, description = "Storage unavailable", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn find_block_default(
+    State(state): State<AppState>,
+    Path(DefaultBlockPath {
+        chain_id,
+        timestamp,
+    }): Path<DefaultBlockPath>,
+    headers: HeaderMap,
+    ValidQuery(query): ValidQuery<InclusiveQuery>,
+) -> Result<(HeaderMap, Json<BlockResponse>), AppError> {
+    let bound = lookup_bound(lookup_defaults().direction, query.inclusive)?;
+    let timestamp = parse_timestamp(&timestamp)?;
+    let fresh = wants_fresh(&headers, query.fresh);
+    lookup(&state, chain_id, bound, timestamp, query, fresh).await
+}
 
+/// Rejects negative timestamps, and ones more than a day ahead unless `allow_future`.
+fn check_timestamp(timestamp: i64, allow_future: bool) -> Result<(), AppError> {
     if timestamp < 0 {
         return Err(AppError::InvalidTimestamp(timestamp.to_string()));
     }
+    if !allow_future && timestamp > clock::now().timestamp() + MAX_FUTURE_SKEW_SECS {
+        return Err(AppError::TimestampInFuture {
+            timestamp,
+            max_skew_secs: MAX_FUTURE_SKEW_SECS,
+        });
+    }
+    Ok(())
+}
+
+/// The bound of a lookup in `direction`, with `inclusive` from the request or the
+/// deployment default.
+fn lookup_bound(direction: Direction, inclusive: Option<bool>) -> Result<Bound, AppError> {
+    Bound::from_request(direction, inclusive, lookup_defaults().inclusive)
+}
+
+async fn lookup(
+    state: &AppState,
+    chain_id: i32,
+    bound: Bound,
+    timestamp: i64,
+    query: InclusiveQuery,
+    fresh: bool,
+) -> Result<(HeaderMap, Json<BlockResponse>), AppError> {
+    check_timestamp(timestamp, query.allow_future.unwrap_or(false))?;
 
     let chain = chains::chain_by_id(chain_id)
         .ok_or_else(|| AppError::ChainNotFound(chain_id.to_string()))?;
+    // aliased ids resolve to the target chain's data
+    let chain_id = chain.chain_id;
+
+    // read indexed_up_to, and the head with when it was seen, from the progress map
+    let (indexed_up_to, head) = {
+        let map = state.progress.read().await;
+        map.get(chain.sqd_slug)
+            .map(|p| {
+                let seen_at = p.head_advanced_at.unwrap_or_else(clock::now).timestamp();
+                (p.cursor, p.head.map(|head| (head, seen_at)))
+            })
+            .unwrap_or((0, None))
+    };
 
-    let row = state
-        .storage
-        .find_block(chain_id, timestamp, &direction, inclusive)?
-        .ok_or_else(|| AppError::BlockNotFound {
+    let mut cache_outcome = None;
+    let row = match approximate::sample_every(chain_id) {
+        // interpolated answers are cheap to recompute and bypass the cache
+        Some(_) => approximate::find_blocks(&state.storage, chain_id, &[(timestamp, bound)])?
+            .pop()
+            .flatten(),
+        None => {
+            let key = LookupKey {
+                chain_id,
+                timestamp,
+                bound,
+            };
+            let storage = &state.storage;
+            let load = || async move { storage.find_block(chain_id, timestamp, bound) };
+            let row = if fresh && state.lookups.try_bypass() {
+                cache_outcome = Some("bypass");
+                state.lookups.reload(key, indexed_up_to, load).await?
+            } else {
+                cache_outcome = fresh.then_some("bypass-limited");
+                state.lookups.get_or_load(key, indexed_up_to, load).await?
+            };
+            // the cache holds the natural tie-break; the other end of a run of blocks
+            // sharing the timestamp costs one more seek
+            let row = match (row, query.tie) {
+                (Some((_, found_ts)), Some(tie))
+                    if tie != Tie::natural(bound.direction().side(timestamp, found_ts)) =>
+                {
+                    state
+                        .storage
+                        .tied_block(chain_id, found_ts, tie)?
+                        .map(|number| (number, found_ts))
+                }
+                (row, _) => row,
+            };
+            row.map(|(number, timestamp)| Estimate {
+                number,
+                timestamp,
+                approximate: false,
+            })
+        }
+    };
+    let estimated = match query.allow_estimate {
+        Some(true) => estimate_past_tip(state, chain, head, timestamp, bound)?,
+        _ => None,
+    };
+    let row = match estimated {
+        Some((number, timestamp)) => Some(Estimate {
+            number,
+            timestamp,
+            approximate: false,
+        }),
+        None => row,
+    };
+    let row = row.ok_or_else(|| match bound.direction() {
+        // nothing after T yet can only mean ingestion hasn't reached it, and nothing on
+        // either side that nothing is indexed yet
+        Direction::After | Direction::Nearest => AppError::NotYetIndexed {
             chain_id: chain_id.to_string(),
             timestamp,
-            direction: direction.clone(),
-        })?;
+            indexed_up_to,
+        },
+        Direction::Before => AppError::BlockNotFound {
+            chain_id: chain_id.to_string(),
+            timestamp,
+            direction: bound.direction().to_string(),
+        },
+    })?;
+    let near_tip = indexed_up_to - row.number < state.lookups.deep_blocks();
+    // stored blocks would start behind an estimated `number`, so an estimate has none
+    let blocks = match query.limit.filter(|_| estimated.is_none()) {
+        Some(limit) => Some(
+            state
+                .storage
+                .find_blocks_near(chain_id, timestamp, bound, limit)?
+                .into_iter()
+                .map(|(number, timestamp)| BlockRef { number, timestamp })
+                .collect(),
+        ),
+        None => None,
+    };
+
+    let mut headers = lifecycle_headers(chain);
+    if let Some(outcome) = cache_outcome {
+        headers.insert(X_KIZAMI_CACHE.clone(), HeaderValue::from_static(outcome));
+    }
+    Ok((
+        headers,
+        Json(BlockResponse {
+            number: row.number,
+            timestamp: row.timestamp,
+            indexed_up_to,
+            approximate: row.approximate,
+            estimated: estimated.is_some(),
+            expected_delay_secs: near_tip
+                .then(|| state.freshness.expected_delay_secs(chain_id))
+                .flatten(),
+            blocks,
+        }),
+    ))
+}
+
+/// The lookup's answer extrapolated from the chain's last stored block, when the
+/// timestamp is past it (so the real answer may not be indexed yet). The block time is
+/// the recent average, or the average since genesis when too few blocks are stored for
+/// that.
+///
+/// A chain with nothing indexed yet is extrapolated from its genesis timestamp instead,
+/// at the average block time between genesis and `head`, the SQD head as `(number,
+/// unix time it was seen)`; without a head there is nothing to base that on.
+///
+/// `None` when the timestamp is within the stored range, no block time is known, or
+/// the extrapolation lands on the last block itself.
+fn estimate_past_tip(
+    state: &AppState,
+    chain: &ChainConfig,
+    head: Option<(i64, i64)>,
+    timestamp: i64,
+    bound: Bound,
+) -> Result<Option<(i64, i64)>, AppError> {
+    let storage = &state.storage;
+    let genesis = chain.effective_genesis_timestamp();
+    let Some(last) = storage.find_block(chain.chain_id, i64::MAX, Bound::AtOrBefore)? else {
+        let block_time = head
+            .filter(|&(number, seen_at)| number > 0 && seen_at > genesis)
+            .map(|(number, seen_at)| (seen_at - genesis) as f64 / number as f64);
+        let genesis = (0, genesis);
+        let past_genesis = timestamp > genesis.1 || (timestamp == genesis.1 && bound.inclusive());
+        return Ok(block_time
+            .filter(|_| past_genesis)
+            .map(|secs| approximate::extrapolate(genesis, secs, timestamp, bound)));
+    };
+    let past_tip = timestamp > last.1 || (timestamp == last.1 && bound == Bound::After);
+    if !past_tip {
+        return Ok(None);
+    }
+    let since_genesis = || (last.0 > 0).then(|| (last.1 - genesis) as f64 / last.0 as f64);
+    let block_time = storage
+        .recent_block_time(chain.chain_id, status::BLOCK_TIME_SPAN)?
+        .or_else(since_genesis)
+        .filter(|&secs| secs > 0.0);
+    Ok(block_time
+        .map(|secs| approximate::extrapolate(last, secs, timestamp, bound))
+        .filter(|&row| row != last))
+}
+
+/// Maximum number of chains in one multi-chain lookup.
+pub const MAX_MULTI_CHAINS: usize = 256;
+
+/// Path parameters for the multi-chain lookup.
+#[derive(Deserialize)]
+pub struct MultiChainPath {
+    direction: String,
+    timestamp: String,
+}
+
+#[derive(Deserialize)]
+pub struct MultiChainQuery {
+    /// Comma-separated chain IDs; every supported chain when left out.
+    #[serde(default)]
+    chains: Option<String>,
+    #[serde(default)]
+    inclusive: Option<bool>,
+    #[serde(default)]
+    allow_future: Option<bool>,
+    #[serde(default)]
+    fresh: Option<bool>,
+    #[serde(default)]
+    tie: Option<Tie>,
+    #[serde(default)]
+    allow_estimate: Option<bool>,
+}
+
+impl MultiChainQuery {
+    /// Requested chain IDs in order without duplicates, or the first entry that isn't
+    /// a chain ID.
+    fn chain_ids(&self) -> Result<Vec<i32>, String> {
+        let Some(chains) = &self.chains else {
+            return Ok(chains::CHAINS.iter().map(|c| c.chain_id).collect());
+        };
+        let mut seen = HashSet::new();
+        let mut ids = Vec::new();
+        for entry in chains.split(',').map(str::trim) {
+            let id: i32 = entry.parse().map_err(|_| entry.to_string())?;
+            if seen.insert(id) {
+                ids.push(id);
+            }
+        }
+        Ok(ids)
+    }
+}
+
+impl Validate for MultiChainQuery {
+    fn validate(&self, v: &mut Violations) {
+        match self.chain_ids() {
+            Ok(ids) => v.batch_size("chains", ids.len(), MAX_MULTI_CHAINS),
+            Err(entry) => v.add("chains", AppError::ChainNotFound(entry)),
+        }
+    }
+}
+
+/// Finds the closest block before or after one timestamp on many chains.
+///
+/// Each chain gets the same lookup as [`find_block`], all run concurrently, so a
+/// cross-chain snapshot costs one request instead of one per chain. Unknown chains and
+/// chains without an answer are listed under `unresolved` with the error code a
+/// single lookup would return; other failures fail the whole request.
+#[utoipa::path(
+    get,
+    path = "/v1/block/{direction}/{timestamp}",
+    tag = "Blocks",
+    summary = "Find a block by timestamp on many chains",
+    description = "Runs the before, after or nearest lookup for one timestamp on every chain in `chains` (all supported chains by default) concurrently. Returns the answers keyed by chain ID, plus the chains that couldn't be answered.",
+    params(
+        ("direction" = inline(Direction), Path, description = "Whether to find the closest block before or after the timestamp, or the nearer of the two"),
+        ("timestamp" = String, Path, description = "Unix timestamp in seconds, or an RFC 3339 date-time such as `2024-03-01T00:00:00Z`"),
+        ("chains" = Option<String>, Query, description = "Comma-separated chain IDs, e.g. `1,8453,42161` (default: every supported chain, at most 256)"),
+        ("inclusive" = Option<bool>, Query, description = "If true, includes blocks at exactly the given timestamp (default set by the deployment, normally false; always true for `nearest`, which rejects false)"),
+        ("allow_future" = Option<bool>, Query, description = "If true, accepts timestamps more than a day in the future"),
+        ("fresh" = Option<bool>, Query, description = "If true, skips the lookup cache like `Cache-Control: no-cache` (rate limited, one bypass per chain)"),
+        ("tie" = Option<Tie>, Query, description = "Which of several blocks sharing the matched timestamp to return: `low` or `high` block number"),
+        ("allow_estimate" = Option<bool>, Query, description = "If true, chains not indexed up to the timestamp answer with an extrapolated block flagged `estimated` instead of being unresolved")
+    ),
+    responses(
+        (status = 200, description = "Blocks by chain and unresolved chains", body = MultiChainBlockResponse),
+        (status = 400, description = "Invalid timestamp, direction or chain list, or timestamp too far in the future", body = kizami_shared::models::ErrorBody),
+        (status = 500, description = "Storage error or corrupt data", body = kizami_shared::models::ErrorBody),
+        (status = 503, description = "Storage unavailable", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn find_block_multi(
+    State(state): State<AppState>,
+    Path(MultiChainPath {
+        direction,
+        timestamp,
+    }): Path<MultiChainPath>,
+    headers: HeaderMap,
+    ValidQuery(query): ValidQuery<MultiChainQuery>,
+) -> Result<Json<MultiChainBlockResponse>, AppError> {
+    let bound = lookup_bound(direction.parse()?, query.inclusive)?;
+    let timestamp = parse_timestamp(&timestamp)?;
+    check_timestamp(timestamp, query.allow_future.unwrap_or(false))?;
+    let fresh = wants_fresh(&headers, query.fresh);
+    let chain_ids = query.chain_ids().unwrap_or_default();
+
+    // one task per chain, so storage reads run on all worker threads
+    let tasks = chain_ids.iter().map(|&chain_id| {
+        let state = state.clone();
+        let query = InclusiveQuery {
+            inclusive: query.inclusive,
+            allow_future: query.allow_future,
+            limit: None,
+            fresh: query.fresh,
+            tie: query.tie,
+            allow_estimate: query.allow_estimate,
+        };
+        tokio::spawn(async move { lookup(&state, chain_id, bound, timestamp, query, fresh).await })
+    });
+    let results = join_all(tasks).await;
+
+    let mut blocks = BTreeMap::new();
+    let mut unresolved = Vec::new();
+    for (chain_id, result) in chain_ids.into_iter().zip(results) {
+        let result = result.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+        match snapshot::per_chain(chain_id, result)? {
+            Ok((_, Json(block))) => {
+                blocks.insert(chain_id, block);
+            }
+            Err(failure) => unresolved.push(failure),
+        }
+    }
+    Ok(Json(MultiChainBlockResponse { blocks, unresolved }))
+}
+
+/// Maximum number of queries in one batch request.
+pub const MAX_BATCH_SIZE: usize = 1000;
+
+/// Deadline applied when a batch request doesn't set `deadline_ms`, and the most a
+/// client may ask for.
+pub const DEFAULT_BATCH_DEADLINE_MS: u64 = 1000;
+pub const MAX_BATCH_DEADLINE_MS: u64 = 10_000;
+
+/// Queries answered per storage pass. The deadline is checked between chunks.
+const BATCH_CHUNK_SIZE: usize = 64;
+
+/// A single query within a batch lookup.
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct BatchQuery {
+    /// Unix timestamp in seconds.
+    timestamp: i64,
+    /// Defaults to the deployment's default direction, normally `before`.
+    #[serde(default)]
+    direction: Option<Direction>,
+    /// If true, includes blocks at exactly the given timestamp. Defaults to the
+    /// deployment's default, normally false; `nearest` is always inclusive and rejects
+    /// false.
+    #[serde(default)]
+    inclusive: Option<bool>,
+}
+
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct BatchRequest {
+    /// Up to 1000 queries, answered in order.
+    queries: Vec<BatchQuery>,
+    /// Time budget in milliseconds (default 1000, max 10000). Queries not answered in
+    /// time come back with status `timeout` instead of failing the whole batch.
+    #[serde(default)]
+    deadline_ms: Option<u64>,
+}
+
+impl Validate for BatchRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.batch_size("queries", self.queries.len(), MAX_BATCH_SIZE);
+        for (i, q) in self.queries.iter().enumerate() {
+            v.timestamp(format!("queries[{i}].timestamp"), q.timestamp);
+        }
+    }
+}
+
+/// Answers many block lookups on one chain in a single request.
+///
+/// Queries are answered in chunks through `find_blocks_multi`, checking the deadline
+/// between chunks. When it passes, the remaining queries are marked `timeout` and the
+/// response is flagged `partial`. Unlike the single lookup, far-future timestamps are
+/// not rejected; they simply resolve to `not_found` or the latest block.
+#[utoipa::path(
+    post,
+    path = "/v1/chains/{chain_id}/block/batch",
+    tag = "Blocks",
+    summary = "Find blocks for many timestamps",
+    description = "Answers up to 1000 before/after lookups on one chain. Returns partial results with per-item status if the deadline passes.",
+    params(
+        ("chain_id" = i32, Path, description = "The chain ID (e.g. 1 for Ethereum, 8453 for Base)")
+    ),
+    request_body = BatchRequest,
+    responses(
+        (status = 200, description = "Per-query results, possibly partial", body = BatchLookupResponse),
+        (status = 400, description = "Invalid timestamp or direction, or batch too large", body = kizami_shared::models::ErrorBody),
+        (status = 404, description = "Chain not found", body = kizami_shared::models::ErrorBody),
+        (status = 500, description = "Storage error or corrupt data", body = kizami_shared::models::ErrorBody),
+        (status = 503, description = "Storage unavailable", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn find_blocks_batch(
+    State(state): State<AppState>,
+    Path(chain_id): Path<i32>,
+    ValidJson(body): ValidJson<BatchRequest>,
+) -> Result<(HeaderMap, Json<BatchLookupResponse>), AppError> {
+    let started = Instant::now();
+    let deadline = Duration::from_millis(
+        body.deadline_ms
+            .unwrap_or(DEFAULT_BATCH_DEADLINE_MS)
+            .min(MAX_BATCH_DEADLINE_MS),
+    );
+
+    let chain = chains::chain_by_id(chain_id)
+        .ok_or_else(|| AppError::ChainNotFound(chain_id.to_string()))?;
+    // aliased ids resolve to the target chain's data
+    let chain_id = chain.chain_id;
 
-    // read indexed_up_to from the in-memory progress map
     let indexed_up_to = {
         let map = state.progress.read().await;
         map.get(chain.sqd_slug).map(|p| p.cursor).unwrap_or(0)
     };
 
-    Ok(Json(BlockResponse {
-        number: row.0,
-        timestamp: row.1,
-        indexed_up_to,
-    }))
+    let defaults = lookup_defaults();
+    let queries: Vec<(i64, Bound)> = body
+        .queries
+        .iter()
+        .map(|q| {
+            let direction = q.direction.unwrap_or(defaults.direction);
+            Ok((q.timestamp, lookup_bound(direction, q.inclusive)?))
+        })
+        .collect::<Result<_, AppError>>()?;
+
+    let mut results = Vec::with_capacity(queries.len());
+    for chunk in queries.chunks(BATCH_CHUNK_SIZE) {
+        if started.elapsed() >= deadline {
+            break;
+        }
+        let rows = match approximate::sample_every(chain_id) {
+            Some(_) => approximate::find_blocks(&state.storage, chain_id, chunk)?,
+            None => state
+                .storage
+                .find_blocks_multi(chain_id, chunk)?
+                .into_iter()
+                .map(|row| {
+                    row.map(|(number, timestamp)| Estimate {
+                        number,
+                        timestamp,
+                        approximate: false,
+                    })
+                })
+                .collect(),
+        };
+        for row in rows {
+            results.push(match row {
+                Some(row) => BatchItemResponse {
+                    status: BatchItemStatus::Ok,
+                    number: Some(row.number),
+                    timestamp: Some(row.timestamp),
+                    approximate: row.approximate,
+                },
+                None => BatchItemResponse {
+                    status: BatchItemStatus::NotFound,
+                    number: None,
+                    timestamp: None,
+                    approximate: false,
+                },
+            });
+        }
+    }
+
+    let partial = results.len() < queries.len();
+    results.resize(
+        queries.len(),
+        BatchItemResponse {
+            status: BatchItemStatus::Timeout,
+            number: None,
+            timestamp: None,
+            approximate: false,
+        },
+    );
+
+    Ok((
+        lifecycle_headers(chain),
+        Json(BatchLookupResponse {
+            results,
+            partial,
+            indexed_up_to,
+        }),
+    ))
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::time::Duration;
+
     use axum::body::Body;
     use axum::http::{Request, StatusCode};
-    use axum::routing::get;
+    use axum::routing::{get, post};
     use axum::Router;
     use http_body_util::BodyExt;
     use tower::ServiceExt;
 
-    use std::collections::HashMap;
-    use std::sync::Arc;
-
-    use tokio::sync::RwLock;
-
     use kizami_shared::storage::{ChainProgress, Storage};
 
+    use crate::cache::LookupCache;
+    use crate::freshness::Freshness;
     use crate::state::AppState;
 
     use super::*;
 
-    fn test_state() -> (AppState, tempfile::TempDir) {
+    fn test_state() -> (AppState, Storage, tempfile::TempDir) {
         let dir = tempfile::tempdir().unwrap();
-        let state = AppState {
-            storage: Storage::open(dir.path()).unwrap(),
-            progress: Arc::new(RwLock::new(HashMap::new())),
-        };
-        (state, dir)
+        let storage = Storage::open(dir.path()).unwrap();
+        let state = AppState::for_tests(&storage);
+        (state, storage, dir)
     }
 
     fn app(state: AppState) -> Router {
@@ -139,9 +796,34 @@ mod tests {
                 "/v1/chains/{chain_id}/block/{direction}/{timestamp}",
                 get(find_block),
             )
+            .route(
+                "/v1/chains/{chain_id}/block/{timestamp}",
+                get(find_block_default),
+            )
+            .route("/v1/chains/{chain_id}/block/batch", post(find_blocks_batch))
+            .route("/v1/block/{direction}/{timestamp}", get(find_block_multi))
             .with_state(state)
     }
 
+    async fn post_json(
+        app: Router,
+        uri: &str,
+        body: serde_json::Value,
+    ) -> (StatusCode, serde_json::Value) {
+        let response = app
+            .oneshot(
+                Request::post(uri)
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
     async fn get_json(app: Router, uri: &str) -> (StatusCode, serde_json::Value) {
         let response = app
             .oneshot(Request::get(uri).body(Body::empty()).unwrap())
@@ -155,7 +837,7 @@ mod tests {
 
     #[tokio::test]
     async fn invalid_direction_returns_400() {
-        let (state, _dir) = test_state();
+        let (state, _, _dir) = test_state();
         let (status, json) = get_json(app(state), "/v1/chains/1/block/sideways/1000").await;
 
         assert_eq!(status, StatusCode::BAD_REQUEST);
@@ -164,16 +846,240 @@ mod tests {
 
     #[tokio::test]
     async fn negative_timestamp_returns_400() {
-        let (state, _dir) = test_state();
+        let (state, _, _dir) = test_state();
         let (status, json) = get_json(app(state), "/v1/chains/1/block/before/-1").await;
 
         assert_eq!(status, StatusCode::BAD_REQUEST);
         assert_eq!(json["error"]["code"], "INVALID_TIMESTAMP");
     }
 
+    #[tokio::test]
+    async fn rfc3339_timestamp_is_converted_to_unix_seconds() {
+        let (state, storage, _dir) = test_state();
+        // 2024-03-01T00:00:00Z is 1709251200
+        storage
+            .insert_blocks(1, &[100, 101], &[1_709_251_199, 1_709_251_212])
+            .unwrap();
+
+        for uri in [
+            "/v1/chains/1/block/before/2024-03-01T00:00:00Z",
+            "/v1/chains/1/block/before/2024-03-01T09:00:00.75+09:00",
+            "/v1/chains/1/block/2024-03-01T00:00:00Z",
+        ] {
+            let (status, json) = get_json(app(state.clone()), uri).await;
+            assert_eq!(status, StatusCode::OK, "{uri}");
+            assert_eq!(json["number"], 100, "{uri}");
+        }
+        let (_, json) = get_json(
+            app(state.clone()),
+            "/v1/block/after/2024-03-01T00:00:00Z?chains=1",
+        )
+        .await;
+        assert_eq!(json["blocks"]["1"]["number"], 101);
+
+        for uri in [
+            "/v1/chains/1/block/before/2024-03-01",
+            "/v1/chains/1/block/before/yesterday",
+        ] {
+            let (status, json) = get_json(app(state.clone()), uri).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{uri}");
+            assert_eq!(json["error"]["code"], "INVALID_TIMESTAMP");
+            assert!(json["error"]["message"]
+                .as_str()
+                .unwrap()
+                .contains("RFC 3339"));
+        }
+    }
+
+    #[tokio::test]
+    async fn far_future_timestamp_returns_400() {
+        let (state, _, _dir) = test_state();
+        let (status, json) = get_json(app(state), "/v1/chains/1/block/before/1700000000000").await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(json["error"]["code"], "TIMESTAMP_IN_FUTURE");
+    }
+
+    #[tokio::test]
+    async fn far_future_timestamp_allowed_with_flag() {
+        let (state, storage, _dir) = test_state();
+        storage.insert_blocks(1, &[100], &[1000]).unwrap();
+
+        let (status, json) = get_json(
+            app(state),
+            "/v1/chains/1/block/before/1700000000000?allow_future=true",
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["number"], 100);
+    }
+
+    #[tokio::test]
+    async fn tie_picks_the_lowest_or_highest_of_shared_timestamps() {
+        let (state, storage, _dir) = test_state();
+        storage
+            .insert_blocks(42161, &[10, 11, 12, 13], &[1000, 1001, 1001, 1001])
+            .unwrap();
+
+        for (query, expected) in [
+            ("before/1001?inclusive=true", 13),
+            ("before/1001?inclusive=true&tie=low", 11),
+            ("before/1002?tie=high", 13),
+            ("after/1001?inclusive=true", 11),
+            ("after/1000?tie=high", 13),
+            ("after/1000?tie=low", 11),
+        ] {
+            let uri = format!("/v1/chains/42161/block/{query}");
+            let (status, json) = get_json(app(state.clone()), &uri).await;
+            assert_eq!(status, StatusCode::OK, "{query}");
+            assert_eq!(json["number"], expected, "{query}");
+            assert_eq!(json["timestamp"], 1001, "{query}");
+        }
+
+        let (status, _) =
+            get_json(app(state), "/v1/chains/42161/block/before/1001?tie=middle").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn nearest_returns_the_closer_side() {
+        let (state, storage, _dir) = test_state();
+        let (status, json) =
+            get_json(app(state.clone()), "/v1/chains/42161/block/nearest/1000").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(json["error"]["code"], "NOT_YET_INDEXED");
+        storage
+            .insert_blocks(42161, &[10, 11, 12, 13], &[1000, 1010, 1020, 1020])
+            .unwrap();
+
+        for (query, expected) in [
+            ("nearest/1003", 10),
+            ("nearest/1007", 11),
+            // equally far: the earlier block
+            ("nearest/1005", 10),
+            // a block at the timestamp is always the nearest, whatever the default
+            ("nearest/1010", 11),
+            ("nearest/1010?inclusive=true", 11),
+            ("nearest/1020", 13),
+            ("nearest/1020?tie=low", 12),
+            ("nearest/9999", 13),
+            ("nearest/1015?limit=3", 11),
+        ] {
+            let uri = format!("/v1/chains/42161/block/{query}");
+            let (status, json) = get_json(app(state.clone()), &uri).await;
+            assert_eq!(status, StatusCode::OK, "{query}");
+            assert_eq!(json["number"], expected, "{query}");
+        }
+        let (status, json) = get_json(
+            app(state.clone()),
+            "/v1/chains/42161/block/nearest/1010?inclusive=false",
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(json["error"]["code"], "INVALID_DIRECTION");
+
+        // closest first across both sides
+        let (_, json) = get_json(app(state), "/v1/chains/42161/block/nearest/1016?limit=4").await;
+        let numbers: Vec<_> = json["blocks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|b| b["number"].as_i64().unwrap())
+            .collect();
+        assert_eq!(numbers, vec![12, 13, 11, 10]);
+    }
+
+    #[tokio::test]
+    async fn allow_estimate_extrapolates_past_the_tip() {
+        let (state, storage, _dir) = test_state();
+        let (status, _) = get_json(
+            app(state.clone()),
+            "/v1/chains/42161/block/after/1100?allow_estimate=true",
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        // a block every 12s, the last one at 1048
+        storage
+            .insert_blocks(42161, &[0, 1, 2, 3, 4], &[1000, 1012, 1024, 1036, 1048])
+            .unwrap();
+
+        let (status, json) =
+            get_json(app(state.clone()), "/v1/chains/42161/block/after/1100").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(json["error"]["code"], "NOT_YET_INDEXED");
+
+        for (query, expected) in [
+            ("after/1100", Some((9, 1108))),
+            ("before/1100", Some((8, 1096))),
+            ("nearest/1100", Some((8, 1096))),
+            ("after/1048", Some((5, 1060))),
+            // within the stored range, or less than a block past it
+            ("before/1040", None),
+            ("after/1040", None),
+            ("before/1050", None),
+        ] {
+            let uri = format!("/v1/chains/42161/block/{query}?allow_estimate=true");
+            let (status, json) = get_json(app(state.clone()), &uri).await;
+            assert_eq!(status, StatusCode::OK, "{query}");
+            match expected {
+                Some((number, timestamp)) => {
+                    assert_eq!(json["number"], number, "{query}");
+                    assert_eq!(json["timestamp"], timestamp, "{query}");
+                    assert_eq!(json["estimated"], true, "{query}");
+                }
+                None => assert!(json.get("estimated").is_none(), "{query}"),
+            }
+        }
+
+        // an estimate carries no stored neighbours, which would start behind it
+        let (_, json) = get_json(
+            app(state),
+            "/v1/chains/42161/block/after/1100?allow_estimate=true&limit=3",
+        )
+        .await;
+        assert_eq!(json["estimated"], true);
+        assert!(json.get("blocks").is_none());
+    }
+
+    #[tokio::test]
+    async fn allow_estimate_extrapolates_from_genesis_before_any_block() {
+        let (state, _, _dir) = test_state();
+        let genesis = chains::chain_by_id(1)
+            .unwrap()
+            .effective_genesis_timestamp();
+        let uri = |query: &str| format!("/v1/chains/1/block/{query}?allow_estimate=true");
+        let before = format!("before/{}", genesis + 1205);
+
+        // no head yet, so no block time to extrapolate with
+        let (status, _) = get_json(app(state.clone()), &uri(&before)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // 1000 blocks seen 12000s after genesis: a block every 12s
+        let seen_at = chrono::DateTime::from_timestamp(genesis + 12_000, 0).unwrap();
+        state.progress.write().await.insert(
+            "ethereum-mainnet".to_string(),
+            ChainProgress {
+                cursor: 0,
+                head: Some(1000),
+                updated_at: None,
+                paused: false,
+                head_advanced_at: Some(seen_at),
+            },
+        );
+        let (status, json) = get_json(app(state.clone()), &uri(&before)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["number"], 100);
+        assert_eq!(json["timestamp"], genesis + 1200);
+        assert_eq!(json["estimated"], true);
+
+        let (status, _) = get_json(app(state), &uri(&format!("before/{}", genesis - 1))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
     #[tokio::test]
     async fn unknown_chain_returns_404() {
-        let (state, _dir) = test_state();
+        let (state, _, _dir) = test_state();
         let (status, json) = get_json(app(state), "/v1/chains/999999/block/before/1000").await;
 
         assert_eq!(status, StatusCode::NOT_FOUND);
@@ -182,18 +1088,29 @@ mod tests {
 
     #[tokio::test]
     async fn block_not_found_returns_404() {
-        let (state, _dir) = test_state();
+        let (state, _, _dir) = test_state();
         let (status, json) = get_json(app(state), "/v1/chains/1/block/before/1000").await;
 
         assert_eq!(status, StatusCode::NOT_FOUND);
         assert_eq!(json["error"]["code"], "BLOCK_NOT_FOUND");
     }
 
+    #[tokio::test]
+    async fn after_beyond_index_returns_not_yet_indexed() {
+        let (state, storage, _dir) = test_state();
+        storage.insert_blocks(1, &[100], &[1000]).unwrap();
+
+        let (status, json) = get_json(app(state), "/v1/chains/1/block/after/2000").await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(json["error"]["code"], "NOT_YET_INDEXED");
+        assert_eq!(json["error"]["indexed_up_to"], 0);
+    }
+
     #[tokio::test]
     async fn successful_block_lookup() {
-        let (state, _dir) = test_state();
-        state
-            .storage
+        let (state, storage, _dir) = test_state();
+        storage
             .insert_blocks(1, &[100, 101, 102], &[1000, 2000, 3000])
             .unwrap();
 
@@ -206,6 +1123,8 @@ mod tests {
                     cursor: 102,
                     head: None,
                     updated_at: None,
+                    paused: false,
+                    head_advanced_at: None,
                 },
             );
         }
@@ -217,4 +1136,253 @@ mod tests {
         assert_eq!(json["timestamp"], 2000);
         assert_eq!(json["indexed_up_to"], 102);
     }
+
+    #[tokio::test]
+    async fn near_tip_lookup_reports_expected_delay() {
+        let (mut state, storage, _dir) = test_state();
+        state.lookups = Arc::new(LookupCache::new(
+            Duration::from_secs(60),
+            Duration::from_secs(60),
+            10,
+            1000,
+        ));
+        state.freshness = Arc::new(Freshness::new(HashMap::from([(1, 600)])));
+        storage
+            .insert_blocks(1, &[100, 195], &[1000, 2000])
+            .unwrap();
+        state.progress.write().await.insert(
+            "ethereum-mainnet".to_string(),
+            ChainProgress {
+                cursor: 200,
+                head: None,
+                updated_at: None,
+                paused: false,
+                head_advanced_at: None,
+            },
+        );
+
+        let (_, near) = get_json(app(state.clone()), "/v1/chains/1/block/before/2500").await;
+        assert_eq!(near["number"], 195);
+        assert_eq!(near["expected_delay_secs"], 600);
+
+        let (_, deep) = get_json(app(state), "/v1/chains/1/block/before/1500").await;
+        assert_eq!(deep["number"], 100);
+        assert!(deep.get("expected_delay_secs").is_none());
+    }
+
+    #[tokio::test]
+    async fn limit_returns_closest_blocks_in_direction() {
+        let (state, storage, _dir) = test_state();
+        storage
+            .insert_blocks(1, &[100, 101, 102, 103], &[1000, 2000, 3000, 4000])
+            .unwrap();
+
+        let (status, json) =
+            get_json(app(state.clone()), "/v1/chains/1/block/before/3500?limit=3").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["number"], 102);
+        let numbers: Vec<_> = json["blocks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|b| b["number"].as_i64().unwrap())
+            .collect();
+        assert_eq!(numbers, vec![102, 101, 100]);
+
+        let (_, json) = get_json(
+            app(state.clone()),
+            "/v1/chains/1/block/after/2000?inclusive=true&limit=10",
+        )
+        .await;
+        assert_eq!(json["blocks"].as_array().unwrap().len(), 3);
+        assert_eq!(json["blocks"][0]["timestamp"], 2000);
+
+        let (_, json) = get_json(app(state.clone()), "/v1/chains/1/block/after/2000").await;
+        assert!(json.get("blocks").is_none());
+
+        let (status, json) = get_json(app(state), "/v1/chains/1/block/before/3500?limit=101").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(json["error"]["code"], "INVALID_LIMIT");
+    }
+
+    #[tokio::test]
+    async fn no_cache_reads_past_a_stale_cached_answer() {
+        let (state, storage, _dir) = test_state();
+        storage.insert_blocks(1, &[100], &[1000]).unwrap();
+        let (_, cached) = get_json(app(state.clone()), "/v1/chains/1/block/before/2500").await;
+        assert_eq!(cached["number"], 100);
+        // written behind the cache's back, as if invalidation had been missed
+        storage.insert_blocks(1, &[101], &[2000]).unwrap();
+        let (_, stale) = get_json(app(state.clone()), "/v1/chains/1/block/before/2500").await;
+        assert_eq!(stale["number"], 100);
+
+        let request = Request::get("/v1/chains/1/block/before/2500")
+            .header(header::CACHE_CONTROL, "max-age=0, no-cache")
+            .body(Body::empty())
+            .unwrap();
+        let response = app(state.clone()).oneshot(request).await.unwrap();
+        assert_eq!(response.headers()["x-kizami-cache"], "bypass");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let fresh: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(fresh["number"], 101);
+
+        // the bypass refreshed the cache for everyone
+        let (_, json) = get_json(app(state.clone()), "/v1/chains/1/block/before/2500").await;
+        assert_eq!(json["number"], 101);
+        let (_, json) = get_json(app(state), "/v1/chains/1/block/before/2500?fresh=true").await;
+        assert_eq!(json["number"], 101);
+    }
+
+    #[tokio::test]
+    async fn shorthand_route_uses_default_direction() {
+        let (state, storage, _dir) = test_state();
+        storage
+            .insert_blocks(1, &[100, 101, 102], &[1000, 2000, 3000])
+            .unwrap();
+        assert_eq!(
+            lookup_defaults(),
+            LookupDefaults {
+                direction: Direction::Before,
+                inclusive: false,
+            }
+        );
+
+        let (status, json) = get_json(app(state.clone()), "/v1/chains/1/block/2000").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["number"], 100);
+
+        let (_, json) =
+            get_json(app(state.clone()), "/v1/chains/1/block/2000?inclusive=true").await;
+        assert_eq!(json["number"], 101);
+
+        let (status, json) = post_json(
+            app(state),
+            "/v1/chains/1/block/batch",
+            serde_json::json!({"queries": [{"timestamp": 3000}]}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["results"][0]["number"], 101);
+    }
+
+    #[tokio::test]
+    async fn batch_returns_per_item_status() {
+        let (state, storage, _dir) = test_state();
+        storage
+            .insert_blocks(1, &[100, 101, 102], &[1000, 2000, 3000])
+            .unwrap();
+
+        let (status, json) = post_json(
+            app(state),
+            "/v1/chains/1/block/batch",
+            serde_json::json!({
+                "queries": [
+                    {"timestamp": 2500, "direction": "before"},
+                    {"timestamp": 500, "direction": "before"},
+                    {"timestamp": 2000, "direction": "after", "inclusive": true},
+                ]
+            }),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["partial"], false);
+        assert_eq!(json["results"][0]["status"], "ok");
+        assert_eq!(json["results"][0]["number"], 101);
+        assert_eq!(json["results"][1]["status"], "not_found");
+        assert!(json["results"][1].get("number").is_none());
+        assert_eq!(json["results"][2]["number"], 101);
+    }
+
+    #[tokio::test]
+    async fn batch_past_deadline_returns_timeouts() {
+        let (state, storage, _dir) = test_state();
+        storage.insert_blocks(1, &[100], &[1000]).unwrap();
+
+        let (status, json) = post_json(
+            app(state),
+            "/v1/chains/1/block/batch",
+            serde_json::json!({
+                "queries": [{"timestamp": 1000, "direction": "before", "inclusive": true}],
+                "deadline_ms": 0
+            }),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["partial"], true);
+        assert_eq!(json["results"][0]["status"], "timeout");
+    }
+
+    #[tokio::test]
+    async fn multi_chain_lookup_keys_answers_by_chain() {
+        let (state, storage, _dir) = test_state();
+        storage
+            .insert_blocks(1, &[100, 101], &[1000, 2000])
+            .unwrap();
+        storage.insert_blocks(8453, &[7, 8], &[1500, 2500]).unwrap();
+
+        let (status, json) = get_json(
+            app(state.clone()),
+            "/v1/block/before/2200?chains=1,8453,42161,1,999999",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["blocks"]["1"]["number"], 101);
+        assert_eq!(json["blocks"]["8453"]["number"], 7);
+        let unresolved: Vec<_> = json["unresolved"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|u| (u["chain_id"].as_i64().unwrap(), u["code"].as_str().unwrap()))
+            .collect();
+        assert_eq!(
+            unresolved,
+            vec![(42161, "BLOCK_NOT_FOUND"), (999999, "CHAIN_NOT_FOUND")]
+        );
+
+        for uri in [
+            "/v1/block/sideways/2200?chains=1",
+            "/v1/block/before/-1?chains=1",
+            "/v1/block/before/2200?chains=1,eth",
+        ] {
+            let (status, _) = get_json(app(state.clone()), uri).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn oversized_batch_returns_400() {
+        let (state, _, _dir) = test_state();
+        let queries: Vec<_> = (0..=MAX_BATCH_SIZE)
+            .map(|i| serde_json::json!({"timestamp": i, "direction": "after"}))
+            .collect();
+
+        let (status, json) = post_json(
+            app(state),
+            "/v1/chains/1/block/batch",
+            serde_json::json!({ "queries": queries }),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(json["error"]["code"], "BATCH_TOO_LARGE");
+    }
+
+    #[test]
+    fn deprecated_chain_gets_lifecycle_headers() {
+        let eth = chains::chain_by_id(1).unwrap();
+        assert!(lifecycle_headers(eth).is_empty());
+
+        let retired = ChainConfig {
+            lifecycle: chains::Lifecycle::Deprecated {
+                sunset_at: Some(1_800_000_000),
+                sunset_block: None,
+            },
+            ..*eth
+        };
+        let headers = lifecycle_headers(&retired);
+        assert_eq!(headers["deprecation"], "true");
+        assert_eq!(headers["sunset"], "Fri, 15 Jan 2027 08:00:00 GMT");
+    }
 }
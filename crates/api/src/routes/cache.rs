@@ -0,0 +1,24 @@
+//! Lookup cache statistics endpoint. Admin-only.
+
+use axum::extract::State;
+use axum::Json;
+
+use kizami_shared::models::CacheStatsResponse;
+
+use crate::state::AppState;
+
+/// Returns the lookup cache's size and hit counters since startup.
+#[utoipa::path(
+    get,
+    path = "/v1/admin/cache",
+    tag = "Admin",
+    summary = "Lookup cache statistics",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Cache size and hit counters", body = CacheStatsResponse),
+        (status = 401, description = "Missing or invalid admin token", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn cache_stats(State(state): State<AppState>) -> Json<CacheStatsResponse> {
+    Json(state.lookups.stats())
+}
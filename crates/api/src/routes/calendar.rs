@@ -0,0 +1,337 @@
+//! Calendar-based block range endpoints.
+//!
+//! Resolves calendar periods to the first and last block inside them, so reporting
+//! users don't have to compute timezone-shifted epochs (and get DST wrong) by hand.
+//! A range is only answered once a block past its end is indexed, so the last block
+//! is final.
+
+use axum::extract::{Path, State};
+use axum::Json;
+use serde::Deserialize;
+
+use kizami_shared::calendar;
+use kizami_shared::chains::{self, ChainConfig};
+use kizami_shared::error::AppError;
+use kizami_shared::models::{
+    BlockRef, Bound, DayBoundariesResponse, Direction, PeriodRangeResponse,
+};
+
+use crate::state::AppState;
+use crate::validate::{ValidQuery, Validate, Violations};
+
+#[derive(Deserialize)]
+pub struct DayQuery {
+    date: String,
+    #[serde(default)]
+    tz: Option<String>,
+}
+
+impl Validate for DayQuery {
+    fn validate(&self, v: &mut Violations) {
+        v.date("date", &self.date);
+        v.timezone("tz", self.tz.as_deref());
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PeriodQuery {
+    period: String,
+    #[serde(default)]
+    tz: Option<String>,
+}
+
+impl Validate for PeriodQuery {
+    fn validate(&self, v: &mut Violations) {
+        v.period("period", &self.period);
+        v.timezone("tz", self.tz.as_deref());
+    }
+}
+
+async fn indexed_up_to(state: &AppState, chain: &ChainConfig) -> i64 {
+    let map = state.progress.read().await;
+    map.get(chain.sqd_slug).map(|p| p.cursor).unwrap_or(0)
+}
+
+/// First and last block with `start <= timestamp < end`.
+///
+/// Fails with `NOT_YET_INDEXED` until a block at or after `end` is indexed, and with
+/// `BLOCK_NOT_FOUND` if the range holds no blocks.
+fn blocks_in_range(
+    state: &AppState,
+    chain_id: i32,
+    start: i64,
+    end: i64,
+    indexed_up_to: i64,
+) -> Result<(BlockRef, BlockRef), AppError> {
+    let rows = state.storage.find_blocks_multi(
+        chain_id,
+        &[
+            (start, Bound::AtOrAfter),
+            (end, Bound::Before),
+            (end, Bound::AtOrAfter),
+        ],
+    )?;
+    if rows[2].is_none() {
+        return Err(AppError::NotYetIndexed {
+            chain_id: chain_id.to_string(),
+            timestamp: end,
+            indexed_up_to,
+        });
+    }
+    let first = rows[0].filter(|&(_, ts)| ts < end);
+    let last = rows[1].filter(|&(_, ts)| ts >= start);
+    match (first, last) {
+        (Some(first), Some(last)) => Ok((
+            BlockRef {
+                number: first.0,
+                timestamp: first.1,
+            },
+            BlockRef {
+                number: last.0,
+                timestamp: last.1,
+            },
+        )),
+        _ => Err(AppError::BlockNotFound {
+            chain_id: chain_id.to_string(),
+            timestamp: start,
+            direction: Direction::After.to_string(),
+        }),
+    }
+}
+
+/// Returns the first and last block of a local calendar day.
+#[utoipa::path(
+    get,
+    path = "/v1/chains/{chain_id}/blocks/day-boundaries",
+    tag = "Blocks",
+    summary = "First and last block of a local day",
+    description = "Returns the first and last block of a calendar day in the given IANA timezone, accounting for DST.",
+    params(
+        ("chain_id" = i32, Path, description = "The chain ID (e.g. 1 for Ethereum, 8453 for Base)"),
+        ("date" = String, Query, description = "Local date as YYYY-MM-DD"),
+        ("tz" = Option<String>, Query, description = "IANA timezone, e.g. America/New_York (default UTC)")
+    ),
+    responses(
+        (status = 200, description = "Day boundaries", body = DayBoundariesResponse),
+        (status = 400, description = "Invalid date or timezone", body = kizami_shared::models::ErrorBody),
+        (status = 404, description = "Chain not found, no blocks that day, or day not fully indexed", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn day_boundaries(
+    State(state): State<AppState>,
+    Path(chain_id): Path<i32>,
+    ValidQuery(query): ValidQuery<DayQuery>,
+) -> Result<Json<DayBoundariesResponse>, AppError> {
+    let tz = calendar::parse_tz(query.tz.as_deref().unwrap_or("UTC"))?;
+    let date = calendar::parse_date(&query.date)?;
+    let chain = chains::chain_by_id(chain_id)
+        .ok_or_else(|| AppError::ChainNotFound(chain_id.to_string()))?;
+
+    let (start_timestamp, end_timestamp) = calendar::day_bounds(date, tz)?;
+    let indexed_up_to = indexed_up_to(&state, chain).await;
+    let (first_block, last_block) = blocks_in_range(
+        &state,
+        chain.chain_id,
+        start_timestamp,
+        end_timestamp,
+        indexed_up_to,
+    )?;
+
+    Ok(Json(DayBoundariesResponse {
+        date: date.to_string(),
+        tz: tz.name().to_string(),
+        start_timestamp,
+        end_timestamp,
+        first_block,
+        last_block,
+        indexed_up_to,
+    }))
+}
+
+/// Returns the first and last block of a calendar period, and the block count.
+#[utoipa::path(
+    get,
+    path = "/v1/chains/{chain_id}/blocks/period",
+    tag = "Blocks",
+    summary = "Block range of a calendar period",
+    description = "Returns the first and last block of a year (`2024`), quarter (`2024-Q1`), month (`2024-06`) or day (`2024-06-01`) in the given IANA timezone, with the number of blocks in between.",
+    params(
+        ("chain_id" = i32, Path, description = "The chain ID (e.g. 1 for Ethereum, 8453 for Base)"),
+        ("period" = String, Query, description = "YYYY, YYYY-Qn, YYYY-MM or YYYY-MM-DD"),
+        ("tz" = Option<String>, Query, description = "IANA timezone, e.g. America/New_York (default UTC)")
+    ),
+    responses(
+        (status = 200, description = "Period block range", body = PeriodRangeResponse),
+        (status = 400, description = "Invalid period or timezone", body = kizami_shared::models::ErrorBody),
+        (status = 404, description = "Chain not found, no blocks in the period, or period not fully indexed", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn period_range(
+    State(state): State<AppState>,
+    Path(chain_id): Path<i32>,
+    ValidQuery(query): ValidQuery<PeriodQuery>,
+) -> Result<Json<PeriodRangeResponse>, AppError> {
+    let tz = calendar::parse_tz(query.tz.as_deref().unwrap_or("UTC"))?;
+    let (start_timestamp, end_timestamp) = calendar::period_bounds(&query.period, tz)?;
+    let chain = chains::chain_by_id(chain_id)
+        .ok_or_else(|| AppError::ChainNotFound(chain_id.to_string()))?;
+
+    let indexed_up_to = indexed_up_to(&state, chain).await;
+    let (start_block, end_block) = blocks_in_range(
+        &state,
+        chain.chain_id,
+        start_timestamp,
+        end_timestamp,
+        indexed_up_to,
+    )?;
+
+    Ok(Json(PeriodRangeResponse {
+        period: query.period,
+        tz: tz.name().to_string(),
+        start_timestamp,
+        end_timestamp,
+        start_block,
+        end_block,
+        block_count: end_block.number - start_block.number + 1,
+        indexed_up_to,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::routing::get;
+    use axum::Router;
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    use kizami_shared::storage::Storage;
+
+    use super::*;
+
+    /// 2024-06-01T00:00:00Z
+    const JUNE_1_UTC: i64 = 1717200000;
+
+    fn test_state() -> (AppState, Storage, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        let state = AppState::for_tests(&storage);
+        (state, storage, dir)
+    }
+
+    fn app(state: AppState) -> Router {
+        Router::new()
+            .route(
+                "/v1/chains/{chain_id}/blocks/day-boundaries",
+                get(day_boundaries),
+            )
+            .route("/v1/chains/{chain_id}/blocks/period", get(period_range))
+            .with_state(state)
+    }
+
+    async fn get_json(app: Router, uri: &str) -> (StatusCode, serde_json::Value) {
+        let response = app
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    /// One block per hour from 2024-05-31T00:00Z through 2024-06-02T23:00Z.
+    fn index_hourly(storage: &Storage) {
+        let start = JUNE_1_UTC - 86400;
+        let numbers: Vec<i64> = (0..72).collect();
+        let timestamps: Vec<i64> = numbers.iter().map(|n| start + n * 3600).collect();
+        storage.insert_blocks(1, &numbers, &timestamps).unwrap();
+    }
+
+    #[tokio::test]
+    async fn utc_day_boundaries() {
+        let (state, storage, _dir) = test_state();
+        index_hourly(&storage);
+
+        let (status, json) = get_json(
+            app(state),
+            "/v1/chains/1/blocks/day-boundaries?date=2024-06-01",
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["tz"], "UTC");
+        assert_eq!(json["first_block"]["number"], 24);
+        assert_eq!(json["first_block"]["timestamp"], JUNE_1_UTC);
+        assert_eq!(json["last_block"]["number"], 47);
+    }
+
+    #[tokio::test]
+    async fn timezone_shifts_boundaries() {
+        let (state, storage, _dir) = test_state();
+        index_hourly(&storage);
+
+        let (status, json) = get_json(
+            app(state),
+            "/v1/chains/1/blocks/day-boundaries?date=2024-06-01&tz=America/New_York",
+        )
+        .await;
+
+        // EDT is UTC-4, so the local day starts at 04:00Z
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["first_block"]["number"], 28);
+        assert_eq!(json["last_block"]["number"], 51);
+    }
+
+    #[tokio::test]
+    async fn incomplete_day_returns_not_yet_indexed() {
+        let (state, storage, _dir) = test_state();
+        index_hourly(&storage);
+
+        let (status, json) = get_json(
+            app(state),
+            "/v1/chains/1/blocks/day-boundaries?date=2024-06-02",
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(json["error"]["code"], "NOT_YET_INDEXED");
+    }
+
+    #[tokio::test]
+    async fn unknown_timezone_returns_400() {
+        let (state, _, _dir) = test_state();
+        let (status, json) = get_json(
+            app(state),
+            "/v1/chains/1/blocks/day-boundaries?date=2024-06-01&tz=Nowhere/Land",
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(json["error"]["code"], "INVALID_TIMEZONE");
+    }
+
+    #[tokio::test]
+    async fn period_range_counts_blocks() {
+        let (state, storage, _dir) = test_state();
+        index_hourly(&storage);
+
+        let (status, json) =
+            get_json(app(state), "/v1/chains/1/blocks/period?period=2024-06-01").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["start_block"]["number"], 24);
+        assert_eq!(json["end_block"]["number"], 47);
+        assert_eq!(json["block_count"], 24);
+    }
+
+    #[tokio::test]
+    async fn malformed_period_returns_400() {
+        let (state, _, _dir) = test_state();
+        let (status, json) =
+            get_json(app(state), "/v1/chains/1/blocks/period?period=2024-Q5").await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(json["error"]["code"], "INVALID_PERIOD");
+    }
+}
@@ -0,0 +1,123 @@
+//! Capabilities endpoint.
+//!
+//! Describes what this deployment supports and how far: lookup and batch limits,
+//! chains in approximate mode, response formats and bulk downloads, and the quotas
+//! clients are held to. Everything it reports is fixed by configuration at startup,
+//! so the response is built once in [`build`] and served as-is.
+
+use std::sync::Arc;
+
+use axum::{Extension, Json};
+
+use kizami_shared::approximate;
+use kizami_shared::models::{
+    BatchCapabilities, CapabilitiesResponse, EstimationCapabilities, ExportCapabilities,
+    FormatCapabilities, LookupCapabilities, RateLimitCapabilities,
+};
+
+use crate::demo::DemoMode;
+use crate::int_as_string::INT_AS_STRING_JSON;
+use crate::routes::{blocks, export, snapshot};
+use crate::state::AppState;
+
+/// Capabilities of a server running with `state`, and `demo` when demo mode is on.
+pub fn build(state: &AppState, demo: Option<&DemoMode>) -> CapabilitiesResponse {
+    let range = export::range_limits();
+    CapabilitiesResponse {
+        lookup: LookupCapabilities {
+            timestamp_formats: vec!["unix_seconds", "rfc3339"],
+            max_future_skew_secs: blocks::MAX_FUTURE_SKEW_SECS,
+            max_limit: blocks::MAX_LOOKUP_LIMIT,
+            max_chains: blocks::MAX_MULTI_CHAINS.min(snapshot::MAX_SNAPSHOT_CHAINS),
+            hot_blocks: false,
+            fresh_lookups_per_min: state.lookups.bypass_per_minute(),
+        },
+        batch: BatchCapabilities {
+            max_queries: blocks::MAX_BATCH_SIZE,
+            default_deadline_ms: blocks::DEFAULT_BATCH_DEADLINE_MS,
+            max_deadline_ms: blocks::MAX_BATCH_DEADLINE_MS,
+        },
+        estimation: EstimationCapabilities {
+            approximate_chains: approximate::sampled_chains().into_iter().collect(),
+        },
+        formats: FormatCapabilities {
+            response_types: vec!["application/json", INT_AS_STRING_JSON],
+            // demo mode turns exports off
+            export: demo.is_none().then_some(ExportCapabilities {
+                max_rows_per_response: export::EXPORT_MAX_ROWS,
+                max_window_secs: range.max_window_secs,
+                max_rows: range.max_rows,
+            }),
+            index_files: state.index_snapshots.is_some(),
+            events: true,
+        },
+        rate_limits: RateLimitCapabilities {
+            per_ip_per_min: demo.map(|d| d.requests_per_window()),
+            tenants: state.tenants.is_some(),
+        },
+    }
+}
+
+/// Returns the features and limits of this deployment.
+#[utoipa::path(
+    get,
+    path = "/v1/capabilities",
+    tag = "Status",
+    summary = "Get supported capabilities",
+    description = "Describes what this deployment supports: lookup, multi-chain and batch limits, timestamp formats, chains answered approximately, response types, whether exports, index files and the event stream are available, and the rate limits clients are held to. Fixed at startup, so clients can read it once and adapt instead of hardcoding one deployment's configuration.",
+    responses(
+        (status = 200, description = "Capabilities of this deployment", body = CapabilitiesResponse)
+    )
+)]
+pub async fn capabilities(
+    Extension(capabilities): Extension<Arc<CapabilitiesResponse>>,
+) -> Json<CapabilitiesResponse> {
+    Json(capabilities.as_ref().clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use kizami_shared::storage::Storage;
+
+    use crate::cache::LookupCache;
+
+    use super::*;
+
+    fn test_state(storage: &Storage) -> AppState {
+        AppState {
+            lookups: Arc::new(
+                LookupCache::new(Duration::from_secs(60), Duration::from_secs(60), 0, 1000)
+                    .with_bypass_limit(30),
+            ),
+            ..AppState::for_tests(storage)
+        }
+    }
+
+    #[test]
+    fn reflects_configuration() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        let state = test_state(&storage);
+
+        let full = build(&state, None);
+        assert_eq!(full.lookup.fresh_lookups_per_min, 30);
+        assert_eq!(full.batch.max_queries, blocks::MAX_BATCH_SIZE);
+        assert!(full.formats.export.is_some());
+        assert!(!full.formats.index_files);
+        assert_eq!(full.rate_limits.per_ip_per_min, None);
+
+        let demo = DemoMode::new(20, false, "");
+        let public = build(&state, Some(&demo));
+        assert!(public.formats.export.is_none());
+        assert_eq!(public.rate_limits.per_ip_per_min, Some(20));
+
+        let json = serde_json::to_value(&public).unwrap();
+        assert_eq!(
+            json["lookup"]["timestamp_formats"],
+            serde_json::json!(["unix_seconds", "rfc3339"])
+        );
+        assert!(json["formats"]["export"].is_null());
+    }
+}
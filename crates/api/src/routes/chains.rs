@@ -1,16 +1,22 @@
 //! Chain information endpoints.
 //!
-//! These handlers serve static chain configuration data. No database access is needed
-//! since all chain info is compiled into the binary.
+//! These handlers serve static chain configuration data, compiled into the binary,
+//! plus each chain's expected ingestion delay and timestamp quirks. The only database
+//! access is one seek per chain for a block 0 stored at timestamp 0.
 
-use axum::extract::Path;
+use axum::extract::{Path, State};
 use axum::Json;
 
-use kizami_shared::chains::{self, CHAINS};
+use kizami_shared::chains::{self, ChainConfig, CHAINS};
 use kizami_shared::error::AppError;
-use kizami_shared::models::ChainResponse;
+use kizami_shared::models::{Bound, ChainResponse, TimestampQuirk};
+use kizami_shared::storage::ReadStorage;
 
-/// Returns all supported chains with their name, chain ID, and genesis timestamp.
+use crate::assets;
+use crate::state::AppState;
+
+/// Returns all supported chains with their name, chain ID, genesis timestamp, and
+/// deprecation status.
 #[utoipa::path(
     get,
     path = "/v1/chains",
@@ -20,16 +26,14 @@ use kizami_shared::models::ChainResponse;
         (status = 200, description = "List of chains", body = Vec<ChainResponse>)
     )
 )]
-pub async fn list_chains() -> Json<Vec<ChainResponse>> {
-    let chains: Vec<ChainResponse> = CHAINS
+pub async fn list_chains(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<ChainResponse>>, AppError> {
+    CHAINS
         .iter()
-        .map(|c| ChainResponse {
-            name: c.name,
-            chain_id: c.chain_id,
-            genesis_timestamp: c.genesis_timestamp,
-        })
-        .collect();
-    Json(chains)
+        .map(|chain| chain_response(chain, &state))
+        .collect::<Result<_, _>>()
+        .map(Json)
 }
 
 /// Returns details for a single chain by its EIP-155 chain ID.
@@ -46,38 +50,110 @@ pub async fn list_chains() -> Json<Vec<ChainResponse>> {
         (status = 404, description = "Chain not found", body = kizami_shared::models::ErrorBody)
     )
 )]
-pub async fn get_chain(Path(chain_id): Path<i32>) -> Result<Json<ChainResponse>, AppError> {
+pub async fn get_chain(
+    State(state): State<AppState>,
+    Path(chain_id): Path<i32>,
+) -> Result<Json<ChainResponse>, AppError> {
     let chain = chains::chain_by_id(chain_id)
         .ok_or_else(|| AppError::ChainNotFound(chain_id.to_string()))?;
 
-    Ok(Json(ChainResponse {
+    chain_response(chain, &state).map(Json)
+}
+
+/// The chain's timestamp quirks: sub-second block times from its config, a zero
+/// block 0 timestamp from the stored data.
+fn timestamp_quirks(
+    storage: &ReadStorage,
+    chain: &ChainConfig,
+) -> Result<Vec<TimestampQuirk>, AppError> {
+    let mut quirks = Vec::new();
+    if chain.shares_timestamps() {
+        quirks.push(TimestampQuirk::SharedTimestamps);
+    }
+    if storage.find_block(chain.chain_id, 0, Bound::AtOrBefore)? == Some((0, 0)) {
+        quirks.push(TimestampQuirk::ZeroGenesisTimestamp);
+    }
+    Ok(quirks)
+}
+
+fn chain_response(chain: &ChainConfig, state: &AppState) -> Result<ChainResponse, AppError> {
+    Ok(ChainResponse {
         name: chain.name,
         chain_id: chain.chain_id,
-        genesis_timestamp: chain.genesis_timestamp,
-    }))
+        genesis_timestamp: chain.effective_genesis_timestamp(),
+        deprecated: chain.is_deprecated(),
+        sunset_at: chain.sunset_at(),
+        expected_delay_secs: state.freshness.expected_delay_secs(chain.chain_id),
+        logo_url: assets::logo_url(chain.chain_id),
+        timestamp_quirks: timestamp_quirks(&state.storage, chain)?,
+    })
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use kizami_shared::storage::Storage;
+
+    use crate::freshness::Freshness;
+
     use super::*;
 
+    fn test_state(freshness: Freshness) -> (AppState, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        let state = AppState {
+            freshness: Arc::new(freshness),
+            ..AppState::for_tests(&storage)
+        };
+        (state, dir)
+    }
+
     #[tokio::test]
     async fn list_chains_returns_all_chains() {
-        let Json(chains) = list_chains().await;
+        let (state, _dir) = test_state(Freshness::default());
+        let Json(chains) = list_chains(State(state)).await.unwrap();
         assert_eq!(chains.len(), CHAINS.len());
+        assert!(chains.iter().all(|c| c.expected_delay_secs.is_none()));
+    }
+
+    #[tokio::test]
+    async fn quirks_come_from_config_and_stored_block_zero() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        storage
+            .insert_blocks(1, &[0, 1], &[0, 1_438_269_988])
+            .unwrap();
+        let (mut state, _state_dir) = test_state(Freshness::default());
+        state.storage = storage.reader();
+
+        let Json(eth) = get_chain(State(state.clone()), Path(1)).await.unwrap();
+        assert_eq!(eth.timestamp_quirks, [TimestampQuirk::ZeroGenesisTimestamp]);
+        let Json(arbitrum) = get_chain(State(state.clone()), Path(42161)).await.unwrap();
+        assert_eq!(
+            arbitrum.timestamp_quirks,
+            [TimestampQuirk::SharedTimestamps]
+        );
+        let Json(base) = get_chain(State(state), Path(8453)).await.unwrap();
+        assert!(base.timestamp_quirks.is_empty());
     }
 
     #[tokio::test]
     async fn get_chain_returns_ethereum() {
-        let result = get_chain(Path(1)).await;
+        let (state, _dir) = test_state(Freshness::new(HashMap::from([(1, 900)])));
+        let result = get_chain(State(state), Path(1)).await;
         let Json(chain) = result.unwrap();
         assert_eq!(chain.name, "Ethereum");
         assert_eq!(chain.chain_id, 1);
+        assert_eq!(chain.expected_delay_secs, Some(900));
+        assert_eq!(chain.logo_url.as_deref(), Some("/static/chains/1.svg"));
     }
 
     #[tokio::test]
     async fn get_chain_unknown_returns_not_found() {
-        let result = get_chain(Path(999999)).await;
+        let (state, _dir) = test_state(Freshness::default());
+        let result = get_chain(State(state), Path(999999)).await;
         let err = result.unwrap_err();
         assert_eq!(err.code(), "CHAIN_NOT_FOUND");
     }
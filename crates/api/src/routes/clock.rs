@@ -0,0 +1,59 @@
+//! Host clock skew endpoint.
+//!
+//! Reports the skew last measured by [`kizami_shared::clock`] against SQD `Date`
+//! headers or the `NTP_SERVER`, and whether timestamps are being corrected for it.
+
+use axum::Json;
+
+use kizami_shared::clock;
+use kizami_shared::models::ClockResponse;
+
+fn to_response(skew: Option<clock::Skew>) -> ClockResponse {
+    ClockResponse {
+        server_time: clock::now(),
+        skew_secs: skew.map(|s| s.offset.num_milliseconds() as f64 / 1000.0),
+        source: skew.map(|s| s.source.as_str()),
+        measured_at: skew.map(|s| s.measured_at),
+        tolerance_secs: clock::tolerance().num_milliseconds() as f64 / 1000.0,
+        corrected: skew.is_some_and(|s| s.corrected),
+    }
+}
+
+/// Returns the host clock's measured skew.
+#[utoipa::path(
+    get,
+    path = "/v1/clock",
+    tag = "Status",
+    summary = "Get host clock skew",
+    description = "Skew of the host clock against SQD response `Date` headers (accurate to about a second) or, when configured, an NTP server. Beyond the tolerance, kizami corrects every timestamp it produces (`updatedAt`, cycle records, future-timestamp checks) by the measured offset.",
+    responses(
+        (status = 200, description = "Latest skew measurement", body = ClockResponse)
+    )
+)]
+pub async fn clock_status() -> Json<ClockResponse> {
+    Json(to_response(clock::skew()))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeDelta, Utc};
+
+    use super::*;
+
+    #[test]
+    fn reports_measured_skew() {
+        let unmeasured = to_response(None);
+        assert_eq!(unmeasured.skew_secs, None);
+        assert!(!unmeasured.corrected);
+
+        let response = to_response(Some(clock::Skew {
+            offset: TimeDelta::milliseconds(-7_250),
+            source: clock::Source::Ntp,
+            measured_at: Utc::now(),
+            corrected: true,
+        }));
+        assert_eq!(response.skew_secs, Some(-7.25));
+        assert_eq!(response.source, Some("ntp"));
+        assert!(response.corrected);
+    }
+}
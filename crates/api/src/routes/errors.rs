@@ -0,0 +1,149 @@
+//! Admin endpoint for reading the ingestion error log.
+//!
+//! Every failure the ingestion loop counts against a chain (head or block fetch,
+//! quarantine, insert, cursor write) is kept in storage with its error code, the newest
+//! `INGEST_ERROR_LOG_SIZE` per chain. Enough to see why a chain is stuck without
+//! access to the log platform.
+
+use axum::extract::State;
+use axum::Json;
+use chrono::DateTime;
+use serde::Deserialize;
+
+use kizami_shared::chains;
+use kizami_shared::error::AppError;
+use kizami_shared::models::IngestErrorResponse;
+
+use crate::state::AppState;
+use crate::validate::{ValidQuery, Validate, Violations};
+
+/// Default and maximum number of errors returned.
+const DEFAULT_LIST_LIMIT: usize = 100;
+const MAX_LIST_LIMIT: usize = 1000;
+
+#[derive(Deserialize)]
+pub struct ErrorLogQuery {
+    #[serde(default)]
+    chain_id: Option<i32>,
+    #[serde(default)]
+    since: Option<i64>,
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+impl Validate for ErrorLogQuery {
+    fn validate(&self, v: &mut Violations) {
+        if let Some(since) = self.since {
+            v.timestamp("since", since);
+        }
+        v.limit("limit", self.limit, MAX_LIST_LIMIT);
+    }
+}
+
+/// Lists logged ingestion errors, newest first.
+#[utoipa::path(
+    get,
+    path = "/v1/admin/errors",
+    tag = "Admin",
+    summary = "List recent ingestion errors",
+    description = "Failures the ingestion loop hit per chain (head or block fetch, quarantine, insert, cursor write) with their error code and message, newest first. Only the newest INGEST_ERROR_LOG_SIZE per chain are kept.",
+    security(("admin_token" = [])),
+    params(
+        ("chain_id" = Option<i32>, Query, description = "Only this chain's errors (default: every chain)"),
+        ("since" = Option<i64>, Query, description = "Only errors at or after this time (Unix seconds)"),
+        ("limit" = Option<usize>, Query, description = "Maximum entries to return (default 100, max 1000)")
+    ),
+    responses(
+        (status = 200, description = "Logged errors", body = Vec<IngestErrorResponse>),
+        (status = 400, description = "Invalid since or limit", body = kizami_shared::models::ErrorBody),
+        (status = 401, description = "Missing or invalid admin token", body = kizami_shared::models::ErrorBody),
+        (status = 404, description = "Chain not found", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn list_errors(
+    State(state): State<AppState>,
+    ValidQuery(query): ValidQuery<ErrorLogQuery>,
+) -> Result<Json<Vec<IngestErrorResponse>>, AppError> {
+    let chain_id = match query.chain_id {
+        Some(chain_id) => Some(
+            chains::chain_by_id(chain_id)
+                .ok_or_else(|| AppError::ChainNotFound(chain_id.to_string()))?
+                .chain_id,
+        ),
+        None => None,
+    };
+    let since = DateTime::from_timestamp(query.since.unwrap_or(0), 0).unwrap_or_default();
+    let limit = query.limit.unwrap_or(DEFAULT_LIST_LIMIT);
+    let errors = state.storage.list_ingest_errors(chain_id, since, limit)?;
+    Ok(Json(
+        errors
+            .into_iter()
+            .map(|e| IngestErrorResponse {
+                chain_id: e.chain_id,
+                name: chains::chain_by_id(e.chain_id).map_or("", |c| c.name),
+                at: e.at,
+                code: e.code,
+                message: e.message,
+            })
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use kizami_shared::storage::Storage;
+
+    use super::*;
+
+    fn query(chain_id: Option<i32>, since: Option<i64>) -> ValidQuery<ErrorLogQuery> {
+        ValidQuery(ErrorLogQuery {
+            chain_id,
+            since,
+            limit: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn lists_errors_by_chain_and_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        let err = AppError::SqdApi("HTTP 503 for ethereum-mainnet".into());
+        storage
+            .record_ingest_error(1, err.code(), &err.to_string(), 10)
+            .unwrap();
+        storage
+            .record_ingest_error(8453, "STORAGE_ERROR", "disk full", 10)
+            .unwrap();
+        let state = AppState::for_tests(&storage);
+
+        let Json(all) = list_errors(State(state.clone()), query(None, None))
+            .await
+            .unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(
+            (all[0].chain_id, all[0].code.as_str()),
+            (8453, "STORAGE_ERROR")
+        );
+
+        let Json(eth) = list_errors(State(state.clone()), query(Some(1), None))
+            .await
+            .unwrap();
+        assert_eq!(eth.len(), 1);
+        assert_eq!(
+            (eth[0].name, eth[0].code.as_str()),
+            ("Ethereum", "SQD_API_ERROR")
+        );
+        assert!(eth[0].message.contains("HTTP 503"));
+
+        let future = kizami_shared::clock::now().timestamp() + 60;
+        let Json(none) = list_errors(State(state.clone()), query(None, Some(future)))
+            .await
+            .unwrap();
+        assert!(none.is_empty());
+
+        let err = list_errors(State(state), query(Some(999_999), None))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), "CHAIN_NOT_FOUND");
+    }
+}
@@ -0,0 +1,154 @@
+//! Server-sent stream of indexed blocks.
+//!
+//! Forwards the ingestion [`EventBus`] to HTTP clients: one `blocks` event per batch
+//! written, so dashboards and indexers can follow progress without polling
+//! `/v1/indexing-status`. A client too slow to keep up gets a `lagged` event with how
+//! many events it missed, and the stream carries on from the newest.
+
+use std::convert::Infallible;
+
+use axum::extract::Query;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::Extension;
+use futures_util::stream::{self, Stream};
+use serde::Deserialize;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+
+use kizami_ingestion::events::EventBus;
+use kizami_ingestion::CursorAdvance;
+use kizami_shared::chains;
+use kizami_shared::error::AppError;
+use kizami_shared::models::BlocksIndexedEvent;
+
+#[derive(Deserialize)]
+pub struct EventsQuery {
+    /// Only events for this chain.
+    #[serde(default)]
+    chain_id: Option<i32>,
+}
+
+fn to_event(advance: CursorAdvance) -> BlocksIndexedEvent {
+    BlocksIndexedEvent {
+        chain_id: advance.chain_id,
+        from_block: advance.from_block,
+        to_block: advance.to_block,
+        blocks: advance.blocks,
+        from_timestamp: advance.from_timestamp,
+        to_timestamp: advance.to_timestamp,
+        at_head: advance.at_head,
+    }
+}
+
+/// The next event for `chain_id` (or any chain), `None` once the bus is gone.
+async fn next_event(
+    receiver: &mut Receiver<CursorAdvance>,
+    chain_id: Option<i32>,
+) -> Option<Event> {
+    loop {
+        match receiver.recv().await {
+            Ok(advance) if chain_id.is_none_or(|id| id == advance.chain_id) => {
+                let data = serde_json::to_string(&to_event(advance)).unwrap_or_default();
+                return Some(Event::default().event("blocks").data(data));
+            }
+            Ok(_) => continue,
+            Err(RecvError::Lagged(missed)) => {
+                return Some(
+                    Event::default()
+                        .event("lagged")
+                        .data(format!("{{\"missed\":{missed}}}")),
+                )
+            }
+            Err(RecvError::Closed) => return None,
+        }
+    }
+}
+
+fn event_stream(
+    receiver: Receiver<CursorAdvance>,
+    chain_id: Option<i32>,
+) -> impl Stream<Item = Result<Event, Infallible>> {
+    stream::unfold(receiver, move |mut receiver| async move {
+        let event = next_event(&mut receiver, chain_id).await?;
+        Some((Ok(event), receiver))
+    })
+}
+
+/// Streams indexed block ranges as server-sent events.
+#[utoipa::path(
+    get,
+    path = "/v1/events",
+    tag = "Status",
+    summary = "Stream indexed blocks",
+    description = "Server-sent events, one `blocks` event (a BlocksIndexedEvent as JSON) per batch of blocks written by ingestion or the work queue, from the moment of connecting. A client that falls more than 1024 events behind gets a `lagged` event with the number missed (`{\"missed\": n}`) and continues from the newest.",
+    params(("chain_id" = Option<i32>, Query, description = "Only events for this chain")),
+    responses(
+        (status = 200, description = "Event stream", content_type = "text/event-stream", body = BlocksIndexedEvent),
+        (status = 404, description = "Chain not found", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn stream_events(
+    Extension(events): Extension<EventBus>,
+    Query(query): Query<EventsQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    if let Some(chain_id) = query.chain_id {
+        chains::chain_by_id(chain_id)
+            .ok_or_else(|| AppError::ChainNotFound(chain_id.to_string()))?;
+    }
+    let stream = event_stream(events.subscribe(), query.chain_id);
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+
+    use super::*;
+
+    fn advance(chain_id: i32, to_block: i64) -> CursorAdvance {
+        CursorAdvance {
+            chain_id,
+            from_block: to_block - 9,
+            to_block,
+            blocks: 10,
+            from_timestamp: 1_000,
+            to_timestamp: 1_018,
+            at_head: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn streams_events_for_the_chain_until_the_bus_is_gone() {
+        let bus = EventBus::new(2);
+        let mut receiver = bus.subscribe();
+        bus.publish(advance(10, 9));
+        bus.publish(advance(1, 19));
+        let event = next_event(&mut receiver, Some(1)).await.unwrap();
+        assert!(format!("{event:?}").contains("\\\"to_block\\\":19"));
+
+        for to_block in [29, 39, 49] {
+            bus.publish(advance(1, to_block));
+        }
+        let lagged = next_event(&mut receiver, Some(1)).await.unwrap();
+        assert!(format!("{lagged:?}").contains("lagged"));
+
+        // the two events still buffered, then the end of the stream
+        let events = event_stream(receiver, Some(1));
+        drop(bus);
+        assert_eq!(events.count().await, 2);
+    }
+
+    #[tokio::test]
+    async fn unknown_chain_is_not_found() {
+        let err = stream_events(
+            Extension(EventBus::default()),
+            Query(EventsQuery {
+                chain_id: Some(999_999),
+            }),
+        )
+        .await
+        .err()
+        .unwrap();
+        assert_eq!(err.code(), "CHAIN_NOT_FOUND");
+    }
+}
@@ -0,0 +1,422 @@
+//! Streaming block export.
+//!
+//! Exports every block in a time window as newline-delimited JSON, read from storage
+//! and written to the socket one page at a time so memory stays flat however large the
+//! window is. A single response stops after `EXPORT_MAX_ROWS` rows; since headers are
+//! already sent by then, the continuation is the stream's last line,
+//! `{"next": "<url>"}`, carrying a signed cursor that resumes right after the last row.
+//!
+//! Paging keeps memory flat but not storage work, so an export is refused up front with
+//! `RANGE_TOO_LARGE` when its window is longer than `RANGE_MAX_WINDOW_DAYS` or holds
+//! more than `RANGE_MAX_ROWS` blocks (estimated from the block numbers at its ends).
+//! The error's hint points at the downloadable index file where one is served.
+
+use std::convert::Infallible;
+use std::sync::{Arc, LazyLock};
+
+use axum::body::{Body, Bytes};
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderValue};
+use axum::response::{IntoResponse, Response};
+use futures_util::stream;
+use serde::Deserialize;
+use serde_json::json;
+
+use kizami_shared::approximate;
+use kizami_shared::chains;
+use kizami_shared::error::AppError;
+use kizami_shared::models::Bound;
+use kizami_shared::storage::ReadStorage;
+
+use crate::pagination::{CursorSigner, Scope};
+use crate::state::AppState;
+use crate::validate::{ValidQuery, Validate, Violations};
+
+/// Rows read from storage and flushed to the client per chunk.
+const EXPORT_PAGE_ROWS: usize = 10_000;
+
+/// Most rows a single export response carries before handing off to `next`.
+pub const EXPORT_MAX_ROWS: usize = 1_000_000;
+
+const DEFAULT_RANGE_MAX_WINDOW_DAYS: i64 = 366;
+const DEFAULT_RANGE_MAX_ROWS: i64 = 5_000_000;
+
+/// Caps on what one export covers, across all of its `next` pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RangeLimits {
+    /// Longest `to_ts - from_ts`, in seconds.
+    pub max_window_secs: Option<i64>,
+    /// Most blocks the window may hold.
+    pub max_rows: Option<i64>,
+}
+
+/// `RANGE_MAX_WINDOW_DAYS` and `RANGE_MAX_ROWS`, read once on first access. 0 lifts a
+/// cap.
+static RANGE_LIMITS: LazyLock<RangeLimits> = LazyLock::new(|| {
+    let cap = |name: &str, default: i64| {
+        let value = std::env::var(name)
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(default);
+        (value > 0).then_some(value)
+    };
+    RangeLimits {
+        max_window_secs: cap("RANGE_MAX_WINDOW_DAYS", DEFAULT_RANGE_MAX_WINDOW_DAYS)
+            .map(|days| days.saturating_mul(24 * 60 * 60)),
+        max_rows: cap("RANGE_MAX_ROWS", DEFAULT_RANGE_MAX_ROWS),
+    }
+});
+
+pub fn range_limits() -> RangeLimits {
+    *RANGE_LIMITS
+}
+
+impl RangeLimits {
+    /// Fails with `RANGE_TOO_LARGE` when `from_ts <= timestamp < to_ts` on `chain_id`
+    /// is over a cap. The window is checked as requested; rows from `resume_ts`, so a
+    /// resumed export only counts what is left.
+    fn check(
+        &self,
+        storage: &ReadStorage,
+        chain_id: i32,
+        (from_ts, resume_ts, to_ts): (i64, i64, i64),
+        hint: &str,
+    ) -> Result<(), AppError> {
+        let too_large = |unit, requested, max| AppError::RangeTooLarge {
+            unit,
+            requested,
+            max,
+            hint: hint.to_string(),
+        };
+        let window = to_ts.saturating_sub(from_ts);
+        if let Some(max) = self.max_window_secs.filter(|&max| window > max) {
+            return Err(too_large("seconds", window, max));
+        }
+        let Some(max) = self.max_rows else {
+            return Ok(());
+        };
+        let first = storage
+            .find_block(chain_id, resume_ts, Bound::AtOrAfter)?
+            .filter(|&(_, ts)| ts < to_ts);
+        let last = storage
+            .find_block(chain_id, to_ts - 1, Bound::AtOrBefore)?
+            .filter(|&(_, ts)| ts >= resume_ts);
+        if let Some(((first, _), (last, _))) = first.zip(last) {
+            // sampled chains only store every Nth block
+            let rows = (last - first + 1) / approximate::sample_every(chain_id).unwrap_or(1);
+            if rows > max {
+                return Err(too_large("rows", rows, max));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    from_ts: i64,
+    to_ts: i64,
+    /// Cursor from a previous response's `next` link.
+    #[serde(default)]
+    cursor: Option<String>,
+}
+
+impl Validate for ExportQuery {
+    fn validate(&self, v: &mut Violations) {
+        v.window(("from_ts", self.from_ts), ("to_ts", self.to_ts));
+    }
+}
+
+/// Position of an in-flight export stream.
+struct Export {
+    storage: ReadStorage,
+    chain_id: i32,
+    /// Next key to read, or `None` once the stream is finished.
+    from: Option<(i64, i64)>,
+    to_ts: i64,
+    emitted: usize,
+    max_rows: usize,
+    cursors: Arc<CursorSigner>,
+    /// Path and fixed query of this export, for building the `next` link.
+    base: String,
+}
+
+impl Export {
+    /// Reads and encodes the next page. Storage errors end the stream with an
+    /// `{"error": ...}` line, since the status code is already sent.
+    fn next_chunk(&mut self) -> Option<Bytes> {
+        let from = self.from.take()?;
+        let want = EXPORT_PAGE_ROWS.min(self.max_rows - self.emitted);
+        let mut out = Vec::new();
+
+        let rows = match self
+            .storage
+            .scan_blocks(self.chain_id, from, self.to_ts, want)
+        {
+            Ok(rows) => rows,
+            Err(err) => {
+                tracing::error!(chain_id = self.chain_id, error = %err, "export scan failed");
+                let line = json!({ "error": { "code": err.code(), "message": err.to_string() } });
+                out.extend_from_slice(line.to_string().as_bytes());
+                out.push(b'\n');
+                return Some(Bytes::from(out));
+            }
+        };
+        for &(number, timestamp) in &rows {
+            let line = json!({ "number": number, "timestamp": timestamp });
+            out.extend_from_slice(line.to_string().as_bytes());
+            out.push(b'\n');
+        }
+        self.emitted += rows.len();
+
+        let &(number, timestamp) = rows.last()?;
+        if rows.len() < want {
+            // short page: the window is exhausted
+        } else if self.emitted < self.max_rows {
+            self.from = Some((timestamp, number + 1));
+        } else if self
+            .storage
+            .scan_blocks(self.chain_id, (timestamp, number + 1), self.to_ts, 1)
+            .is_ok_and(|more| !more.is_empty())
+        {
+            let cursor =
+                self.cursors
+                    .encode(Scope::BlockExport, self.chain_id, (timestamp, number));
+            let next = format!("{}&cursor={cursor}", self.base);
+            out.extend_from_slice(json!({ "next": next }).to_string().as_bytes());
+            out.push(b'\n');
+        }
+        Some(Bytes::from(out))
+    }
+}
+
+/// Streams every block with `from_ts <= timestamp < to_ts` as NDJSON.
+///
+/// Each line is `{"number": n, "timestamp": t}` in timestamp order. Responses stop
+/// after 1,000,000 rows; when more remain, the final line is `{"next": url}`.
+#[utoipa::path(
+    get,
+    path = "/v1/chains/{chain_id}/blocks/export",
+    tag = "Blocks",
+    summary = "Export blocks in a time window",
+    description = "Streams all blocks with from_ts <= timestamp < to_ts as newline-delimited JSON. Responses are capped at 1,000,000 rows; when more remain, the last line is {\"next\": url} to continue from. Windows longer than RANGE_MAX_WINDOW_DAYS or holding more than RANGE_MAX_ROWS blocks are refused with RANGE_TOO_LARGE.",
+    params(
+        ("chain_id" = i32, Path, description = "The chain ID (e.g. 1 for Ethereum, 8453 for Base)"),
+        ("from_ts" = i64, Query, description = "Window start (Unix seconds, inclusive)"),
+        ("to_ts" = i64, Query, description = "Window end (Unix seconds, exclusive)"),
+        ("cursor" = Option<String>, Query, description = "Opaque cursor from a previous `next` link")
+    ),
+    responses(
+        (status = 200, description = "NDJSON stream of blocks", content_type = "application/x-ndjson", body = String),
+        (status = 400, description = "Invalid window or resume point, or range too large", body = kizami_shared::models::ErrorBody),
+        (status = 404, description = "Chain not found", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn export_blocks(
+    State(state): State<AppState>,
+    Path(chain_id): Path<i32>,
+    ValidQuery(query): ValidQuery<ExportQuery>,
+) -> Result<Response, AppError> {
+    let ExportQuery {
+        from_ts,
+        to_ts,
+        cursor,
+    } = query;
+
+    let chain = chains::chain_by_id(chain_id)
+        .ok_or_else(|| AppError::ChainNotFound(chain_id.to_string()))?;
+    let from = match cursor.as_deref() {
+        Some(token) => {
+            let (ts, number) = state
+                .cursors
+                .decode(Scope::BlockExport, chain.chain_id, token)?;
+            (ts, number.saturating_add(1)).max((from_ts, 0))
+        }
+        None => (from_ts, 0),
+    };
+    let hint = match state.index_snapshots {
+        Some(_) => format!(
+            "download the whole chain from /v1/chains/{}/index or export smaller windows",
+            chain.chain_id
+        ),
+        None => "export smaller windows".to_string(),
+    };
+    range_limits().check(
+        &state.storage,
+        chain.chain_id,
+        (from_ts, from.0, to_ts),
+        &hint,
+    )?;
+
+    let export = Export {
+        storage: state.storage.clone(),
+        chain_id: chain.chain_id,
+        from: Some(from),
+        to_ts,
+        emitted: 0,
+        max_rows: EXPORT_MAX_ROWS,
+        cursors: state.cursors.clone(),
+        base: format!("/v1/chains/{chain_id}/blocks/export?from_ts={from_ts}&to_ts={to_ts}"),
+    };
+    let body = Body::from_stream(stream::unfold(export, |mut export| async move {
+        let chunk = export.next_chunk()?;
+        Some((Ok::<_, Infallible>(chunk), export))
+    }));
+
+    Ok((
+        [(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/x-ndjson"),
+        )],
+        body,
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Request, StatusCode};
+    use axum::routing::get;
+    use axum::Router;
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    use kizami_shared::storage::Storage;
+
+    use super::*;
+
+    fn test_state() -> (AppState, Storage, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        let state = AppState::for_tests(&storage);
+        (state, storage, dir)
+    }
+
+    async fn get_lines(state: AppState, uri: &str) -> (StatusCode, Vec<serde_json::Value>) {
+        let app = Router::new()
+            .route("/v1/chains/{chain_id}/blocks/export", get(export_blocks))
+            .with_state(state);
+        let response = app
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let lines = body
+            .split(|&b| b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        (status, lines)
+    }
+
+    #[tokio::test]
+    async fn streams_window_across_pages() {
+        let (state, storage, _dir) = test_state();
+        let count = EXPORT_PAGE_ROWS as i64 + 5;
+        let numbers: Vec<i64> = (0..count).collect();
+        let timestamps: Vec<i64> = numbers.iter().map(|n| 1000 + n).collect();
+        storage.insert_blocks(1, &numbers, &timestamps).unwrap();
+
+        let uri = format!(
+            "/v1/chains/1/blocks/export?from_ts=1001&to_ts={}",
+            1000 + count - 1
+        );
+        let (status, lines) = get_lines(state, &uri).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(lines.len() as i64, count - 2);
+        assert_eq!(lines[0]["number"], 1);
+        assert_eq!(lines.last().unwrap()["number"], count - 2);
+        assert!(lines.iter().all(|l| l.get("next").is_none()));
+    }
+
+    #[tokio::test]
+    async fn row_cap_links_to_the_rest() {
+        let (state, storage, _dir) = test_state();
+        storage
+            .insert_blocks(1, &[10, 11, 12, 13], &[100, 100, 101, 102])
+            .unwrap();
+
+        let mut export = Export {
+            storage: state.storage.clone(),
+            chain_id: 1,
+            from: Some((100, 0)),
+            to_ts: 200,
+            emitted: 0,
+            max_rows: 2,
+            cursors: state.cursors.clone(),
+            base: "/v1/chains/1/blocks/export?from_ts=100&to_ts=200".into(),
+        };
+        let chunk = export.next_chunk().unwrap();
+        let lines: Vec<serde_json::Value> = chunk
+            .split(|&b| b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert!(export.next_chunk().is_none());
+
+        // the cursor resumes after block 11, which shares its timestamp with block 10
+        let next = lines[2]["next"].as_str().unwrap();
+        let (_, rest) = get_lines(state, next).await;
+        let numbers: Vec<_> = rest.iter().map(|l| l["number"].clone()).collect();
+        assert_eq!(numbers, vec![12, 13]);
+    }
+
+    #[tokio::test]
+    async fn rejects_bad_window_and_cursor() {
+        let (state, _, _dir) = test_state();
+        let (status, _) = get_lines(
+            state.clone(),
+            "/v1/chains/1/blocks/export?from_ts=10&to_ts=10",
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, lines) = get_lines(
+            state,
+            "/v1/chains/1/blocks/export?from_ts=10&to_ts=20&cursor=nope",
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(lines[0]["error"]["code"], "INVALID_CURSOR");
+    }
+
+    #[test]
+    fn range_limits_cap_window_and_rows() {
+        let (state, storage, _dir) = test_state();
+        let numbers: Vec<i64> = (0..100).collect();
+        let timestamps: Vec<i64> = numbers.iter().map(|n| 1000 + n * 10).collect();
+        storage.insert_blocks(1, &numbers, &timestamps).unwrap();
+        let limits = RangeLimits {
+            max_window_secs: Some(600),
+            max_rows: Some(50),
+        };
+        let check = |from_ts, resume_ts, to_ts| {
+            limits.check(&state.storage, 1, (from_ts, resume_ts, to_ts), "hint")
+        };
+
+        assert!(check(1000, 1000, 1500).is_ok());
+        let err = check(1000, 1000, 1700).unwrap_err();
+        assert_eq!(err.code(), "RANGE_TOO_LARGE");
+        assert_eq!(err.details().unwrap()["unit"], "seconds");
+
+        let unlimited_window = RangeLimits {
+            max_window_secs: None,
+            ..limits
+        };
+        let err = unlimited_window
+            .check(&state.storage, 1, (0, 0, 5000), "hint")
+            .unwrap_err();
+        assert_eq!(err.details().unwrap()["requested"], 100);
+        // resumed past the first 60 blocks, 40 remain
+        assert!(unlimited_window
+            .check(&state.storage, 1, (0, 1600, 5000), "hint")
+            .is_ok());
+        // an empty window is never too large by rows
+        assert!(unlimited_window
+            .check(&state.storage, 1, (50_000, 50_000, 60_000), "hint")
+            .is_ok());
+    }
+}
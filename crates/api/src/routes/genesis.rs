@@ -0,0 +1,195 @@
+//! Admin audit of a chain's genesis timestamp.
+//!
+//! Re-derives genesis from the first stored blocks and from the dataset's first blocks
+//! on SQD, with the same rule ingestion uses for fresh chains
+//! ([`ChainConfig::genesis_mismatch`]), and reports where they disagree with the
+//! timestamp in use. A wrong value can then be fixed with `GENESIS_TIMESTAMPS` without
+//! a release.
+
+use axum::extract::{Path, State};
+use axum::{Extension, Json};
+
+use kizami_shared::chains::{self, ChainConfig};
+use kizami_shared::error::AppError;
+use kizami_shared::models::{GenesisObservation, GenesisReportResponse};
+use kizami_shared::sqd::{BlockHeader, SqdClient};
+use kizami_shared::storage::ReadStorage;
+
+use crate::state::AppState;
+
+/// Checks a source's first headers, sorted by number. `None` without headers.
+fn observe(
+    chain: &ChainConfig,
+    source: &'static str,
+    headers: &[BlockHeader],
+) -> Option<GenesisObservation> {
+    let genesis = chains::derive_genesis(headers);
+    let block = genesis.or(headers.first())?;
+    Some(GenesisObservation {
+        source,
+        number: block.number,
+        timestamp: block.timestamp,
+        is_genesis: genesis.is_some(),
+        offset_secs: block.timestamp - chain.effective_genesis_timestamp(),
+        consistent: chain.genesis_mismatch(headers).is_none(),
+    })
+}
+
+/// The chain's first two stored blocks by number, or fewer if that's all there is.
+fn stored_headers(storage: &ReadStorage, chain_id: i32) -> Result<Vec<BlockHeader>, AppError> {
+    let mut headers = Vec::new();
+    let mut from = 0;
+    while headers.len() < 2 {
+        let Some((number, timestamp)) =
+            storage.find_block_by_number(chain_id, from, 0, i64::MAX)?
+        else {
+            break;
+        };
+        headers.push(BlockHeader { number, timestamp });
+        from = number + 1;
+    }
+    Ok(headers)
+}
+
+/// The dataset's first two blocks.
+async fn fetched_headers(
+    sqd_client: &SqdClient,
+    chain: &ChainConfig,
+) -> Result<Vec<BlockHeader>, AppError> {
+    let start = sqd_client.fetch_metadata(chain.sqd_slug).await?.start_block;
+    sqd_client
+        .fetch_blocks(chain.sqd_slug, start, start + 1)
+        .await
+}
+
+/// Audits a chain's genesis timestamp against stored and freshly fetched blocks.
+#[utoipa::path(
+    get,
+    path = "/v1/admin/chains/{chain_id}/genesis",
+    tag = "Admin",
+    summary = "Audit a chain's genesis timestamp",
+    description = "Re-derives genesis (block 0, or block 1 when block 0 has timestamp 0) from the chain's first stored blocks and from the SQD dataset's first blocks, and compares both with the genesis timestamp in use (built in, or overridden by `GENESIS_TIMESTAMPS`). Datasets that start after genesis are checked only for not predating it. An unreachable SQD is reported in `sqd_error`, not as a failure.",
+    security(("admin_token" = [])),
+    params(("chain_id" = i32, Path, description = "The chain ID")),
+    responses(
+        (status = 200, description = "Genesis audit", body = GenesisReportResponse),
+        (status = 401, description = "Missing or invalid admin token", body = kizami_shared::models::ErrorBody),
+        (status = 404, description = "Chain not found", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn audit_genesis(
+    State(state): State<AppState>,
+    Extension(sqd_client): Extension<SqdClient>,
+    Path(chain_id): Path<i32>,
+) -> Result<Json<GenesisReportResponse>, AppError> {
+    let chain = chains::chain_by_id(chain_id)
+        .ok_or_else(|| AppError::ChainNotFound(chain_id.to_string()))?;
+
+    let stored = stored_headers(&state.storage, chain.chain_id)?;
+    let (fetched, sqd_error) = match fetched_headers(&sqd_client, chain).await {
+        Ok(headers) => (headers, None),
+        Err(e) => (Vec::new(), Some(e.to_string())),
+    };
+    let observations: Vec<_> = [("stored", stored), ("sqd", fetched)]
+        .iter()
+        .filter_map(|(source, headers)| observe(chain, source, headers))
+        .collect();
+
+    let genesis_timestamp = chain.effective_genesis_timestamp();
+    let consistent = observations.iter().all(|o| o.consistent);
+    if !consistent {
+        tracing::warn!(
+            chain_id = chain.chain_id,
+            genesis_timestamp = genesis_timestamp,
+            observations = ?observations,
+            "genesis audit found a discrepancy"
+        );
+    }
+    Ok(Json(GenesisReportResponse {
+        chain_id: chain.chain_id,
+        name: chain.name,
+        genesis_timestamp,
+        builtin_genesis_timestamp: chain.genesis_timestamp,
+        overridden: genesis_timestamp != chain.genesis_timestamp,
+        observations,
+        sqd_error,
+        consistent,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use kizami_fixtures::portal::MockPortal;
+    use kizami_shared::storage::Storage;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn reports_stored_and_fetched_genesis() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        let eth = chains::chain_by_id(1).unwrap();
+        let genesis = eth.effective_genesis_timestamp();
+        // stored block 1 is off by a second, the dataset agrees
+        storage
+            .insert_blocks(1, &[0, 1, 2], &[0, genesis + 1, genesis + 20])
+            .unwrap();
+        let headers = vec![
+            BlockHeader {
+                number: 0,
+                timestamp: 0,
+            },
+            BlockHeader {
+                number: 1,
+                timestamp: genesis,
+            },
+        ];
+        let portal = MockPortal::spawn(eth.sqd_slug, headers).await;
+        let sqd_client = SqdClient::with_base_url(portal.base_url());
+
+        let Json(report) = audit_genesis(
+            State(AppState::for_tests(&storage)),
+            Extension(sqd_client),
+            Path(1),
+        )
+        .await
+        .unwrap();
+        assert!(!report.overridden);
+        assert_eq!(report.sqd_error, None);
+        assert!(!report.consistent);
+        let stored = &report.observations[0];
+        assert_eq!((stored.source, stored.number), ("stored", 1));
+        assert!(stored.is_genesis && !stored.consistent);
+        assert_eq!(stored.offset_secs, 1);
+        let fetched = &report.observations[1];
+        assert_eq!((fetched.source, fetched.offset_secs), ("sqd", 0));
+        assert!(fetched.consistent);
+    }
+
+    #[tokio::test]
+    async fn unreachable_sqd_is_reported_not_failed() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        let sqd_client = SqdClient::with_base_url("http://127.0.0.1:1");
+
+        let Json(report) = audit_genesis(
+            State(AppState::for_tests(&storage)),
+            Extension(sqd_client),
+            Path(1),
+        )
+        .await
+        .unwrap();
+        assert!(report.observations.is_empty());
+        assert!(report.sqd_error.is_some());
+        assert!(report.consistent);
+
+        let err = audit_genesis(
+            State(AppState::for_tests(&storage)),
+            Extension(SqdClient::with_base_url("http://127.0.0.1:1")),
+            Path(999_999),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.code(), "CHAIN_NOT_FOUND");
+    }
+}
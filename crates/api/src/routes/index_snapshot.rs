@@ -0,0 +1,184 @@
+//! Downloadable per-chain index snapshot.
+//!
+//! Serves the files written by `IndexSnapshots` as-is (Brotli-compressed, see
+//! `kizami_shared::index_file` for the layout), with `Last-Modified` and
+//! `If-Modified-Since` so mirrors only re-download after a regeneration.
+
+use std::time::SystemTime;
+
+use axum::body::{Body, Bytes};
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use futures_util::stream;
+use tokio::io::AsyncReadExt;
+
+use kizami_shared::chains;
+use kizami_shared::error::AppError;
+
+use crate::state::AppState;
+
+/// Bytes read from disk per body chunk.
+const CHUNK_BYTES: usize = 64 * 1024;
+
+const HTTP_DATE: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+/// Downloads a chain's whole block index as a compact binary file.
+#[utoipa::path(
+    get,
+    path = "/v1/chains/{chain_id}/index",
+    tag = "Blocks",
+    summary = "Download a chain's index snapshot",
+    description = "Returns the chain's full (number, timestamp) index as a Brotli-compressed, delta-encoded binary file, regenerated periodically. Supports If-Modified-Since.",
+    params(
+        ("chain_id" = i32, Path, description = "The chain ID (e.g. 1 for Ethereum, 8453 for Base)")
+    ),
+    responses(
+        (status = 200, description = "Index file", content_type = "application/octet-stream", body = Vec<u8>),
+        (status = 304, description = "Not modified since If-Modified-Since"),
+        (status = 404, description = "Chain not found or no snapshot available", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn download_index(
+    State(state): State<AppState>,
+    Path(chain_id): Path<i32>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let chain = chains::chain_by_id(chain_id)
+        .ok_or_else(|| AppError::ChainNotFound(chain_id.to_string()))?;
+    let unavailable = || AppError::SnapshotUnavailable(chain_id.to_string());
+    let snapshots = state.index_snapshots.as_ref().ok_or_else(unavailable)?;
+
+    let file = tokio::fs::File::open(snapshots.path(chain.chain_id))
+        .await
+        .map_err(|_| unavailable())?;
+    let metadata = file.metadata().await.map_err(|_| unavailable())?;
+    // HTTP dates have one-second resolution; compare at that granularity
+    let modified: DateTime<Utc> = metadata.modified().unwrap_or(SystemTime::now()).into();
+    let modified_secs = modified.timestamp();
+    let last_modified = modified.format(HTTP_DATE).to_string();
+
+    let since = headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| DateTime::parse_from_rfc2822(v).ok());
+    if since.is_some_and(|since| modified_secs <= since.timestamp()) {
+        return Ok((
+            StatusCode::NOT_MODIFIED,
+            [(header::LAST_MODIFIED, last_modified)],
+        )
+            .into_response());
+    }
+
+    let body = Body::from_stream(stream::unfold(Some(file), |file| async move {
+        let mut file = file?;
+        let mut buf = vec![0u8; CHUNK_BYTES];
+        match file.read(&mut buf).await {
+            Ok(0) => None,
+            Ok(n) => {
+                buf.truncate(n);
+                Some((Ok::<_, std::io::Error>(Bytes::from(buf)), Some(file)))
+            }
+            Err(e) => Some((Err(e), None)),
+        }
+    }));
+
+    let disposition = format!("attachment; filename=\"{}.kzix.br\"", chain.chain_id);
+    Ok((
+        [
+            (
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/octet-stream"),
+            ),
+            (header::CONTENT_LENGTH, HeaderValue::from(metadata.len())),
+            (
+                header::LAST_MODIFIED,
+                HeaderValue::from_str(&last_modified).map_err(|_| unavailable())?,
+            ),
+            (
+                header::CONTENT_DISPOSITION,
+                HeaderValue::from_str(&disposition).map_err(|_| unavailable())?,
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use axum::http::Request;
+    use axum::routing::get;
+    use axum::Router;
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    use kizami_shared::index_file::read_index;
+    use kizami_shared::storage::Storage;
+
+    use crate::index_snapshots::IndexSnapshots;
+
+    use super::*;
+
+    fn test_state(dir: &std::path::Path, storage: &Storage) -> AppState {
+        let snapshots = IndexSnapshots::new(dir.join("snapshots"), Duration::from_secs(60));
+        std::fs::create_dir_all(dir.join("snapshots")).unwrap();
+        AppState {
+            index_snapshots: Some(Arc::new(snapshots)),
+            ..AppState::for_tests(storage)
+        }
+    }
+
+    async fn fetch(state: AppState, uri: &str, since: Option<&str>) -> Response {
+        let app = Router::new()
+            .route("/v1/chains/{chain_id}/index", get(download_index))
+            .with_state(state);
+        let mut req = Request::get(uri);
+        if let Some(since) = since {
+            req = req.header(header::IF_MODIFIED_SINCE, since);
+        }
+        app.oneshot(req.body(Body::empty()).unwrap()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn serves_snapshot_and_honours_if_modified_since() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path().join("db")).unwrap();
+        let state = test_state(dir.path(), &storage);
+        storage
+            .insert_blocks(1, &[1, 2, 3], &[100, 112, 124])
+            .unwrap();
+        let snapshots = state.index_snapshots.clone().unwrap();
+        snapshots.write_chain(&state.storage, 1).unwrap();
+
+        let response = fetch(state.clone(), "/v1/chains/1/index", None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let last_modified = response.headers()[header::LAST_MODIFIED]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let (_, rows) = read_index(&body[..]).unwrap();
+        assert_eq!(rows, vec![(1, 100), (2, 112), (3, 124)]);
+
+        let response = fetch(state.clone(), "/v1/chains/1/index", Some(&last_modified)).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        let stale = "Mon, 01 Jan 2001 00:00:00 GMT";
+        let response = fetch(state, "/v1/chains/1/index", Some(stale)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn missing_snapshot_returns_404() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        let state = test_state(dir.path(), &storage);
+        let response = fetch(state, "/v1/chains/10/index", None).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
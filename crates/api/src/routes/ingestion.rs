@@ -0,0 +1,261 @@
+//! Admin endpoints for controlling ingestion and reading its lag and cursor history.
+//!
+//! Pausing sets a flag in the shared progress map that the ingestion loop checks
+//! before each chain. A paused chain still has its finalized head refreshed, so its
+//! lag keeps growing in status and history. Pauses are not persisted.
+
+use axum::extract::{Path, State};
+use axum::Json;
+use serde::Deserialize;
+
+use kizami_shared::chains;
+use kizami_shared::error::AppError;
+use kizami_shared::models::{CursorHistoryResponse, IngestionControlResponse, LagHistoryResponse};
+use kizami_shared::storage::ChainProgress;
+
+use crate::state::AppState;
+use crate::validate::{ValidQuery, Validate, Violations};
+
+/// Sets or clears the pause flag for a chain.
+async fn set_paused(
+    state: &AppState,
+    chain_id: i32,
+    paused: bool,
+) -> Result<IngestionControlResponse, AppError> {
+    let chain = chains::chain_by_id(chain_id)
+        .ok_or_else(|| AppError::ChainNotFound(chain_id.to_string()))?;
+    let mut map = state.progress.write().await;
+    map.entry(chain.sqd_slug.to_string())
+        .or_insert(ChainProgress {
+            cursor: 0,
+            head: None,
+            updated_at: None,
+            paused: false,
+            head_advanced_at: None,
+        })
+        .paused = paused;
+    tracing::info!(chain = chain.name, paused, "ingestion pause changed");
+    Ok(IngestionControlResponse {
+        chain_id: chain.chain_id,
+        name: chain.name,
+        paused,
+    })
+}
+
+/// Stops ingesting a chain until resumed or restarted. Lookups keep being served.
+#[utoipa::path(
+    post,
+    path = "/v1/admin/chains/{chain_id}/ingestion/pause",
+    tag = "Admin",
+    summary = "Pause ingestion for a chain",
+    security(("admin_token" = [])),
+    params(
+        ("chain_id" = i32, Path, description = "The chain ID")
+    ),
+    responses(
+        (status = 200, description = "Ingestion paused", body = IngestionControlResponse),
+        (status = 401, description = "Missing or invalid admin token", body = kizami_shared::models::ErrorBody),
+        (status = 404, description = "Chain not found", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn pause_ingestion(
+    State(state): State<AppState>,
+    Path(chain_id): Path<i32>,
+) -> Result<Json<IngestionControlResponse>, AppError> {
+    set_paused(&state, chain_id, true).await.map(Json)
+}
+
+/// Resumes ingesting a paused chain from its cursor on the next cycle.
+#[utoipa::path(
+    post,
+    path = "/v1/admin/chains/{chain_id}/ingestion/resume",
+    tag = "Admin",
+    summary = "Resume ingestion for a chain",
+    security(("admin_token" = [])),
+    params(
+        ("chain_id" = i32, Path, description = "The chain ID")
+    ),
+    responses(
+        (status = 200, description = "Ingestion resumed", body = IngestionControlResponse),
+        (status = 401, description = "Missing or invalid admin token", body = kizami_shared::models::ErrorBody),
+        (status = 404, description = "Chain not found", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn resume_ingestion(
+    State(state): State<AppState>,
+    Path(chain_id): Path<i32>,
+) -> Result<Json<IngestionControlResponse>, AppError> {
+    set_paused(&state, chain_id, false).await.map(Json)
+}
+
+/// Returns per-minute ingestion lag for the last 24 hours.
+#[utoipa::path(
+    get,
+    path = "/v1/admin/ingestion/history",
+    tag = "Admin",
+    summary = "Ingestion lag history",
+    description = "Blocks between finalized head and cursor per chain, sampled every minute for the last 24 hours. Starts empty after a restart.",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Lag history per chain", body = Vec<LagHistoryResponse>),
+        (status = 401, description = "Missing or invalid admin token", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn lag_history(State(state): State<AppState>) -> Json<Vec<LagHistoryResponse>> {
+    Json(state.lag_history.snapshot())
+}
+
+#[derive(Deserialize)]
+pub struct CursorAtQuery {
+    at: i64,
+}
+
+impl Validate for CursorAtQuery {
+    fn validate(&self, v: &mut Violations) {
+        v.timestamp("at", self.at);
+    }
+}
+
+/// Returns how far a chain was indexed at a past moment.
+///
+/// Answered from the persisted cursor history, so it reaches back across restarts as
+/// far as `CURSOR_HISTORY_RETENTION_DAYS`, at `CURSOR_HISTORY_INTERVAL_SECS`
+/// resolution.
+#[utoipa::path(
+    get,
+    path = "/v1/admin/chains/{chain_id}/ingestion/cursor-at",
+    tag = "Admin",
+    summary = "Indexing progress at a past time",
+    description = "Returns the chain's cursor from the latest history snapshot taken at or before `at`, for checking what data was queryable when a user reported a problem.",
+    security(("admin_token" = [])),
+    params(
+        ("chain_id" = i32, Path, description = "The chain ID"),
+        ("at" = i64, Query, description = "Moment to look up (Unix seconds)")
+    ),
+    responses(
+        (status = 200, description = "Cursor as of the snapshot", body = CursorHistoryResponse),
+        (status = 400, description = "Invalid timestamp", body = kizami_shared::models::ErrorBody),
+        (status = 401, description = "Missing or invalid admin token", body = kizami_shared::models::ErrorBody),
+        (status = 404, description = "Chain not found, or no snapshot at or before `at`", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn cursor_at(
+    State(state): State<AppState>,
+    Path(chain_id): Path<i32>,
+    ValidQuery(query): ValidQuery<CursorAtQuery>,
+) -> Result<Json<CursorHistoryResponse>, AppError> {
+    let at = query.at;
+    let chain = chains::chain_by_id(chain_id)
+        .ok_or_else(|| AppError::ChainNotFound(chain_id.to_string()))?;
+    let (recorded_at, indexed_up_to) =
+        state
+            .storage
+            .cursor_at(chain.chain_id, at)?
+            .ok_or_else(|| AppError::NoCursorHistory {
+                chain_id: chain.chain_id.to_string(),
+                at,
+            })?;
+    Ok(Json(CursorHistoryResponse {
+        chain_id: chain.chain_id,
+        name: chain.name,
+        at,
+        indexed_up_to,
+        recorded_at,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::routing::{get, post};
+    use axum::Router;
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    use kizami_shared::storage::Storage;
+
+    use super::*;
+
+    fn test_state() -> (AppState, Storage, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        let state = AppState::for_tests(&storage);
+        (state, storage, dir)
+    }
+
+    #[tokio::test]
+    async fn pause_and_resume_flip_the_progress_flag() {
+        let (state, _, _dir) = test_state();
+        let app = Router::new()
+            .route(
+                "/v1/admin/chains/{chain_id}/ingestion/pause",
+                post(pause_ingestion),
+            )
+            .route(
+                "/v1/admin/chains/{chain_id}/ingestion/resume",
+                post(resume_ingestion),
+            )
+            .with_state(state.clone());
+        let send = |uri: &'static str| {
+            let app = app.clone();
+            async move {
+                app.oneshot(Request::post(uri).body(Body::empty()).unwrap())
+                    .await
+                    .unwrap()
+                    .status()
+            }
+        };
+
+        assert_eq!(
+            send("/v1/admin/chains/1/ingestion/pause").await,
+            StatusCode::OK
+        );
+        assert!(state.progress.read().await["ethereum-mainnet"].paused);
+        assert_eq!(
+            send("/v1/admin/chains/1/ingestion/resume").await,
+            StatusCode::OK
+        );
+        assert!(!state.progress.read().await["ethereum-mainnet"].paused);
+        assert_eq!(
+            send("/v1/admin/chains/999999/ingestion/pause").await,
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[tokio::test]
+    async fn cursor_at_reads_the_snapshot_in_effect() {
+        let (state, storage, _dir) = test_state();
+        storage.record_cursor_history(1_000, &[(1, 100)]).unwrap();
+        storage.record_cursor_history(2_000, &[(1, 150)]).unwrap();
+        let app = Router::new()
+            .route(
+                "/v1/admin/chains/{chain_id}/ingestion/cursor-at",
+                get(cursor_at),
+            )
+            .with_state(state);
+        let get_json = |uri: &'static str| {
+            let app = app.clone();
+            async move {
+                let response = app
+                    .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                    .await
+                    .unwrap();
+                let status = response.status();
+                let body = response.into_body().collect().await.unwrap().to_bytes();
+                let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                (status, json)
+            }
+        };
+
+        let (status, json) = get_json("/v1/admin/chains/1/ingestion/cursor-at?at=1500").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["indexed_up_to"], 100);
+        assert_eq!(json["recorded_at"], 1_000);
+        assert_eq!(json["at"], 1_500);
+
+        let (status, json) = get_json("/v1/admin/chains/1/ingestion/cursor-at?at=999").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(json["error"]["code"], "NO_CURSOR_HISTORY");
+    }
+}
@@ -0,0 +1,25 @@
+//! Background job status endpoint. Admin-only.
+
+use axum::extract::State;
+use axum::Json;
+
+use kizami_shared::models::JobStatusResponse;
+
+use crate::state::AppState;
+
+/// Returns every background job with its schedule and recent runs.
+#[utoipa::path(
+    get,
+    path = "/v1/admin/jobs",
+    tag = "Admin",
+    summary = "Background job status",
+    description = "Lists the scheduler's jobs (ingest, lag_history, cursor_history, index_snapshots, lookup_summary, whichever are enabled) with their interval and jitter, run and failure counts since startup, the last run's start, duration and error, and when the next run is due.",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Jobs by name", body = Vec<JobStatusResponse>),
+        (status = 401, description = "Missing or invalid admin token", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn list_jobs(State(state): State<AppState>) -> Json<Vec<JobStatusResponse>> {
+    Json(state.jobs.status())
+}
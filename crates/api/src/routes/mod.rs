@@ -1,3 +1,25 @@
+pub mod admin;
+pub mod anomalies;
+pub mod beacon;
+pub mod block_list;
 pub mod blocks;
+pub mod cache;
+pub mod calendar;
+pub mod capabilities;
 pub mod chains;
+pub mod clock;
+pub mod errors;
+pub mod events;
+pub mod export;
+pub mod genesis;
+pub mod index_snapshot;
+pub mod ingestion;
+pub mod jobs;
+pub mod recovery;
+pub mod sample;
+pub mod slo;
+pub mod snapshot;
 pub mod status;
+pub mod tenants;
+pub mod uptime;
+pub mod work_queue;
@@ -0,0 +1,27 @@
+//! Startup recovery report endpoint.
+//!
+//! Serves the report built by [`crate::recovery`] when the server started. Admin-only.
+
+use axum::extract::State;
+use axum::Json;
+
+use kizami_shared::models::RecoveryReportResponse;
+
+use crate::state::AppState;
+
+/// Returns the state storage was found in at startup.
+#[utoipa::path(
+    get,
+    path = "/v1/admin/recovery",
+    tag = "Admin",
+    summary = "Startup recovery report",
+    description = "Journal replay size and time, per-chain extents and cursor consistency as found when the server started.",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Recovery report", body = RecoveryReportResponse),
+        (status = 401, description = "Missing or invalid admin token", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn recovery_report(State(state): State<AppState>) -> Json<RecoveryReportResponse> {
+    Json(state.recovery.as_ref().clone())
+}
@@ -0,0 +1,296 @@
+//! Uniform random sampling of blocks.
+//!
+//! Returns `n` blocks drawn uniformly from a time window, for spot-checking indexed
+//! data against a node or estimating block-time statistics without exporting the whole
+//! range. Block numbers are drawn from the window's span and each one is located with a
+//! jump-seek ([`Storage::find_block_by_number`]), so the cost depends on `n`, not on the
+//! size of the window.
+//!
+//! [`Storage::find_block_by_number`]: kizami_shared::storage::Storage::find_block_by_number
+
+use std::collections::BTreeSet;
+
+use axum::extract::{Path, State};
+use axum::Json;
+use serde::Deserialize;
+
+use kizami_shared::chains;
+use kizami_shared::error::AppError;
+use kizami_shared::models::{BlockRef, BlockSampleResponse, Bound};
+
+use crate::state::AppState;
+use crate::validate::{ValidQuery, Validate, Violations};
+
+const DEFAULT_SAMPLE_SIZE: usize = 100;
+
+/// Largest sample a single request may ask for.
+const MAX_SAMPLE_SIZE: usize = 1_000;
+
+#[derive(Deserialize)]
+pub struct SampleQuery {
+    #[serde(default)]
+    n: Option<usize>,
+    #[serde(default)]
+    from_ts: Option<i64>,
+    #[serde(default)]
+    to_ts: Option<i64>,
+    #[serde(default)]
+    seed: Option<u64>,
+}
+
+impl Validate for SampleQuery {
+    fn validate(&self, v: &mut Violations) {
+        let n = self.n.unwrap_or(DEFAULT_SAMPLE_SIZE);
+        v.sample_size("n", n, MAX_SAMPLE_SIZE);
+        let from_ts = self.from_ts.unwrap_or(0);
+        v.window(
+            ("from_ts", from_ts),
+            ("to_ts", self.to_ts.unwrap_or(i64::MAX)),
+        );
+    }
+}
+
+/// SplitMix64, seeded per request so a sample can be reproduced.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform integer in `[0, bound)`.
+    fn below(&mut self, bound: u64) -> u64 {
+        ((self.next_u64() as u128 * bound as u128) >> 64) as u64
+    }
+}
+
+/// Draws `k` distinct offsets from `[0, population)` (Floyd's algorithm), sorted.
+fn sample_offsets(rng: &mut SplitMix64, population: u64, k: u64) -> BTreeSet<u64> {
+    let mut picked = BTreeSet::new();
+    for j in population - k..population {
+        let t = rng.below(j + 1);
+        if !picked.insert(t) {
+            picked.insert(j);
+        }
+    }
+    picked
+}
+
+/// A seed from the clock, kept below 2^53 so JSON clients can echo it back exactly.
+fn fresh_seed() -> u64 {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64;
+    SplitMix64(nanos).next_u64() >> 11
+}
+
+/// Returns up to `n` blocks sampled uniformly from `from_ts <= timestamp < to_ts`.
+///
+/// Block numbers are drawn uniformly from the window's span. On chains that store
+/// every block that makes every stored block equally likely; where blocks are missing,
+/// draws landing in a gap resolve to the next stored block.
+#[utoipa::path(
+    get,
+    path = "/v1/chains/{chain_id}/blocks/sample",
+    tag = "Blocks",
+    summary = "Random sample of blocks",
+    description = "Returns up to n blocks drawn uniformly at random from the window from_ts <= timestamp < to_ts, in ascending order. The response echoes the seed; passing it back returns the same sample while the window's data is unchanged.",
+    params(
+        ("chain_id" = i32, Path, description = "The chain ID (e.g. 1 for Ethereum, 8453 for Base)"),
+        ("n" = Option<usize>, Query, description = "Sample size, 1 to 1000 (default 100)"),
+        ("from_ts" = Option<i64>, Query, description = "Window start (Unix seconds, inclusive; default 0)"),
+        ("to_ts" = Option<i64>, Query, description = "Window end (Unix seconds, exclusive; default unbounded)"),
+        ("seed" = Option<u64>, Query, description = "Seed for a reproducible sample (default random)")
+    ),
+    responses(
+        (status = 200, description = "Sampled blocks", body = BlockSampleResponse),
+        (status = 400, description = "Invalid window or sample size", body = kizami_shared::models::ErrorBody),
+        (status = 404, description = "Chain not found", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn sample_blocks(
+    State(state): State<AppState>,
+    Path(chain_id): Path<i32>,
+    ValidQuery(query): ValidQuery<SampleQuery>,
+) -> Result<Json<BlockSampleResponse>, AppError> {
+    let n = query.n.unwrap_or(DEFAULT_SAMPLE_SIZE);
+    let from_ts = query.from_ts.unwrap_or(0);
+    let to_ts = query.to_ts.unwrap_or(i64::MAX);
+    let chain = chains::chain_by_id(chain_id)
+        .ok_or_else(|| AppError::ChainNotFound(chain_id.to_string()))?;
+    let seed = query.seed.unwrap_or_else(fresh_seed);
+
+    let storage = &state.storage;
+    let first = storage
+        .find_block(chain.chain_id, from_ts, Bound::AtOrAfter)?
+        .filter(|&(_, ts)| ts < to_ts);
+    let last = storage
+        .find_block(chain.chain_id, to_ts - 1, Bound::AtOrBefore)?
+        .filter(|&(_, ts)| ts >= from_ts);
+
+    let (population, blocks) = match first.zip(last) {
+        None => (0, Vec::new()),
+        Some(((first, _), (last, _))) => {
+            let population = last - first + 1;
+            let rows = if population as u64 <= n as u64 {
+                storage.scan_blocks(chain.chain_id, (from_ts, 0), to_ts, n)?
+            } else {
+                let offsets = sample_offsets(&mut SplitMix64(seed), population as u64, n as u64);
+                let mut rows: Vec<(i64, i64)> = Vec::with_capacity(n);
+                for offset in offsets {
+                    let number = first + offset as i64;
+                    // a gap can map several draws onto the same stored block
+                    if rows.last().is_some_and(|&(last, _)| last >= number) {
+                        continue;
+                    }
+                    if let Some(row) =
+                        storage.find_block_by_number(chain.chain_id, number, from_ts, to_ts)?
+                    {
+                        if rows.last() != Some(&row) {
+                            rows.push(row);
+                        }
+                    }
+                }
+                rows
+            };
+            (population, rows)
+        }
+    };
+
+    Ok(Json(BlockSampleResponse {
+        chain_id: chain.chain_id,
+        from_ts,
+        to_ts,
+        seed,
+        population,
+        blocks: blocks
+            .into_iter()
+            .map(|(number, timestamp)| BlockRef { number, timestamp })
+            .collect(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::routing::get;
+    use axum::Router;
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    use kizami_shared::storage::Storage;
+
+    use super::*;
+
+    fn test_state() -> (AppState, Storage, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        let state = AppState::for_tests(&storage);
+        (state, storage, dir)
+    }
+
+    async fn get_json(state: AppState, uri: &str) -> (StatusCode, serde_json::Value) {
+        let app = Router::new()
+            .route("/v1/chains/{chain_id}/blocks/sample", get(sample_blocks))
+            .with_state(state);
+        let response = app
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    fn numbers(body: &serde_json::Value) -> Vec<i64> {
+        body["blocks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|b| b["number"].as_i64().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn offsets_are_distinct_and_in_range() {
+        let mut rng = SplitMix64(7);
+        let offsets = sample_offsets(&mut rng, 50, 50);
+        assert_eq!(offsets, (0..50).collect());
+
+        let offsets = sample_offsets(&mut rng, 1_000_000, 1_000);
+        assert_eq!(offsets.len(), 1_000);
+        assert!(offsets.iter().all(|&o| o < 1_000_000));
+    }
+
+    #[tokio::test]
+    async fn samples_are_reproducible_and_stay_in_window() {
+        let (state, storage, _dir) = test_state();
+        // two blocks per timestamp, so the seek has to pick within a timestamp
+        let numbers_in: Vec<i64> = (0..2_000).collect();
+        let timestamps: Vec<i64> = numbers_in.iter().map(|n| 1_000 + n / 2).collect();
+        storage.insert_blocks(1, &numbers_in, &timestamps).unwrap();
+
+        let uri = "/v1/chains/1/blocks/sample?n=50&from_ts=1100&to_ts=1600&seed=42";
+        let (status, body) = get_json(state.clone(), uri).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["population"], 1_000);
+        assert_eq!(body["seed"], 42);
+
+        let sampled = numbers(&body);
+        assert_eq!(sampled.len(), 50);
+        assert!(sampled.windows(2).all(|w| w[0] < w[1]));
+        assert!(sampled.iter().all(|n| (200..1_200).contains(n)));
+        for block in body["blocks"].as_array().unwrap() {
+            let number = block["number"].as_i64().unwrap();
+            assert_eq!(block["timestamp"], 1_000 + number / 2);
+        }
+
+        let (_, again) = get_json(state.clone(), uri).await;
+        assert_eq!(numbers(&again), sampled);
+        let (_, other) = get_json(state, &uri.replace("seed=42", "seed=43")).await;
+        assert_ne!(numbers(&other), sampled);
+    }
+
+    #[tokio::test]
+    async fn small_windows_return_every_block() {
+        let (state, storage, _dir) = test_state();
+        storage
+            .insert_blocks(1, &[10, 11, 12, 13], &[100, 100, 101, 102])
+            .unwrap();
+
+        let (status, body) = get_json(state.clone(), "/v1/chains/1/blocks/sample?n=10").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(numbers(&body), vec![10, 11, 12, 13]);
+
+        let (_, body) = get_json(state, "/v1/chains/1/blocks/sample?from_ts=200").await;
+        assert_eq!(body["population"], 0);
+        assert_eq!(numbers(&body), Vec::<i64>::new());
+    }
+
+    #[tokio::test]
+    async fn rejects_bad_sample_size_and_window() {
+        let (state, _, _dir) = test_state();
+        let (status, body) = get_json(state.clone(), "/v1/chains/1/blocks/sample?n=0").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["code"], "INVALID_SAMPLE_SIZE");
+
+        let (status, _) = get_json(state.clone(), "/v1/chains/1/blocks/sample?n=1001").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _) = get_json(
+            state.clone(),
+            "/v1/chains/1/blocks/sample?from_ts=10&to_ts=10",
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _) = get_json(state, "/v1/chains/999999/blocks/sample").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,279 @@
+//! Latency SLO report and Prometheus metrics endpoints.
+//!
+//! Both read from the in-memory [`SloTracker`](crate::slo::SloTracker) fed by the
+//! latency middleware. The report is admin-only; `/metrics` is meant for scrapers and
+//! also carries storage write pressure and size, operation counts per storage role,
+//! host clock skew, per-chain health grades, lag, cursor age and SQD error rates
+//! (the series `kizami gen-alerts` writes rules for), and SQD egress against the daily
+//! budget. Scrapers that accept OpenMetrics get it, with trace exemplars on the
+//! lookup histogram; everything else gets Prometheus text.
+
+use std::fmt::Write;
+
+use axum::extract::State;
+use axum::http::{header, HeaderMap};
+use axum::response::IntoResponse;
+use axum::Json;
+
+use kizami_shared::chains::CHAINS;
+use kizami_shared::clock;
+use kizami_shared::models::{
+    ChainHealth, IndexingStatusResponse, RouteLatencyResponse, SloReportResponse,
+};
+use kizami_shared::sqd::SqdHealth;
+use kizami_shared::storage::StorageMetrics;
+
+use crate::routes::status;
+use crate::state::AppState;
+
+/// Returns rolling p50/p95/p99 latency per route and whether each meets the p99 SLO.
+#[utoipa::path(
+    get,
+    path = "/v1/admin/slo",
+    tag = "Admin",
+    summary = "Per-route latency SLO report",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Latency percentiles per route", body = SloReportResponse),
+        (status = 401, description = "Missing or invalid admin token", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn slo_report(State(state): State<AppState>) -> Json<SloReportResponse> {
+    let threshold = state.slo.p99_threshold_ms();
+    let routes = state
+        .slo
+        .snapshot()
+        .into_iter()
+        .map(|r| RouteLatencyResponse {
+            violating: r.p99_ms > threshold,
+            route: r.route,
+            total_requests: r.total,
+            window_samples: r.samples,
+            p50_ms: r.p50_ms,
+            p95_ms: r.p95_ms,
+            p99_ms: r.p99_ms,
+        })
+        .collect();
+
+    Json(SloReportResponse {
+        p99_threshold_ms: threshold,
+        routes,
+    })
+}
+
+const OPENMETRICS_CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Operation counts and time per storage role, and blocks written.
+fn render_storage_roles(out: &mut String, metrics: &StorageMetrics, openmetrics: bool) {
+    // OpenMetrics names counter families without the `_total` sample suffix
+    let family = |name: &str| {
+        if openmetrics {
+            name.to_string()
+        } else {
+            format!("{name}_total")
+        }
+    };
+    let (ops, secs, blocks) = (
+        family("kizami_storage_operations"),
+        family("kizami_storage_operation_seconds"),
+        family("kizami_storage_blocks_written"),
+    );
+    let _ = writeln!(
+        out,
+        "# HELP {ops} Storage operations by handle role.\n\
+         # TYPE {ops} counter"
+    );
+    let roles = [
+        ("read", metrics.reads.snapshot()),
+        ("write", metrics.writes.snapshot()),
+    ];
+    for (role, stats) in roles {
+        let _ = writeln!(
+            out,
+            "kizami_storage_operations_total{{role=\"{role}\"}} {}",
+            stats.operations
+        );
+    }
+    let _ = writeln!(
+        out,
+        "# HELP {secs} Time spent in storage operations by handle role.\n\
+         # TYPE {secs} counter"
+    );
+    for (role, stats) in roles {
+        let _ = writeln!(
+            out,
+            "kizami_storage_operation_seconds_total{{role=\"{role}\"}} {}",
+            stats.busy_secs
+        );
+    }
+    let _ = writeln!(
+        out,
+        "# HELP {blocks} Blocks inserted into the index.\n\
+         # TYPE {blocks} counter\n\
+         kizami_storage_blocks_written_total {}",
+        metrics.blocks_written()
+    );
+}
+
+/// Bytes downloaded from SQD per chain, since startup and today (UTC), and the daily
+/// budget when one is set. Chains with nothing downloaded yet are left out.
+fn render_sqd_egress(out: &mut String, health: &SqdHealth, openmetrics: bool) {
+    let total = if openmetrics {
+        "kizami_sqd_egress_bytes"
+    } else {
+        "kizami_sqd_egress_bytes_total"
+    };
+    let today = clock::now().date_naive();
+    let datasets: Vec<_> = CHAINS
+        .iter()
+        .filter_map(|chain| Some((chain.chain_id, health.get(chain.sqd_slug)?)))
+        .filter(|(_, h)| h.egress_bytes > 0 || h.egress_bytes_on(today) > 0)
+        .collect();
+    let _ = writeln!(
+        out,
+        "# HELP {total} Response bytes downloaded from SQD for the chain since startup.\n\
+         # TYPE {total} counter"
+    );
+    for (chain_id, h) in &datasets {
+        let _ = writeln!(
+            out,
+            "kizami_sqd_egress_bytes_total{{chain_id=\"{chain_id}\"}} {}",
+            h.egress_bytes
+        );
+    }
+    out.push_str(
+        "# HELP kizami_sqd_egress_bytes_today Bytes downloaded from SQD for the chain on the current UTC day.\n\
+         # TYPE kizami_sqd_egress_bytes_today gauge\n",
+    );
+    for (chain_id, h) in &datasets {
+        let _ = writeln!(
+            out,
+            "kizami_sqd_egress_bytes_today{{chain_id=\"{chain_id}\"}} {}",
+            h.egress_bytes_on(today)
+        );
+    }
+    if let Some(budget) = health.egress_budget() {
+        let _ = writeln!(
+            out,
+            "# HELP kizami_sqd_egress_budget_bytes Daily SQD egress budget across all chains.\n\
+             # TYPE kizami_sqd_egress_budget_bytes gauge\n\
+             kizami_sqd_egress_budget_bytes {budget}"
+        );
+    }
+}
+
+/// Health grades, lag, cursor age and SQD error rate per chain. Series without a value
+/// yet (head never fetched, nothing ingested, no SQD request made) are left out.
+fn render_chain_statuses(out: &mut String, statuses: &[IndexingStatusResponse]) {
+    out.push_str(
+        "# HELP kizami_chain_health Chain health grade, 1 for the current one.\n\
+         # TYPE kizami_chain_health gauge\n",
+    );
+    for s in statuses {
+        for grade in ChainHealth::ALL {
+            let _ = writeln!(
+                out,
+                "kizami_chain_health{{chain_id=\"{}\",grade=\"{}\"}} {}",
+                s.chain_id,
+                grade.as_str(),
+                u8::from(s.health == grade)
+            );
+        }
+    }
+    out.push_str(
+        "# HELP kizami_chain_lag_blocks Blocks between the cursor and the dataset head.\n\
+         # TYPE kizami_chain_lag_blocks gauge\n",
+    );
+    for s in statuses {
+        if let Some(head) = s.latest_known_block {
+            let _ = writeln!(
+                out,
+                "kizami_chain_lag_blocks{{chain_id=\"{}\"}} {}",
+                s.chain_id,
+                (head - s.last_indexed_block).max(0)
+            );
+        }
+    }
+    out.push_str(
+        "# HELP kizami_chain_cursor_age_seconds Seconds since the chain's cursor last moved.\n\
+         # TYPE kizami_chain_cursor_age_seconds gauge\n",
+    );
+    let now = clock::now();
+    for s in statuses {
+        if let Some(updated_at) = s.updated_at {
+            let _ = writeln!(
+                out,
+                "kizami_chain_cursor_age_seconds{{chain_id=\"{}\"}} {}",
+                s.chain_id,
+                (now - updated_at).num_seconds().max(0)
+            );
+        }
+    }
+    out.push_str(
+        "# HELP kizami_sqd_error_rate Share of recent SQD requests for the chain that failed.\n\
+         # TYPE kizami_sqd_error_rate gauge\n",
+    );
+    for s in statuses {
+        if let Some(sqd) = &s.sqd {
+            let _ = writeln!(
+                out,
+                "kizami_sqd_error_rate{{chain_id=\"{}\"}} {}",
+                s.chain_id, sqd.error_rate
+            );
+        }
+    }
+}
+
+/// Prometheus text exposition of the latency windows and storage write pressure, or
+/// OpenMetrics when the scraper's `Accept` header asks for it.
+pub async fn metrics(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    let openmetrics = headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("application/openmetrics-text"));
+    let mut body = state.slo.render_prometheus(openmetrics);
+    let pressure = state.storage.write_pressure();
+    let _ = writeln!(
+        body,
+        "# HELP kizami_storage_write_pressure Compaction debt relative to fjall's write stall threshold.\n\
+         # TYPE kizami_storage_write_pressure gauge\n\
+         kizami_storage_write_pressure {}\n\
+         # HELP kizami_storage_l0_tables Level-0 tables awaiting compaction in the blocks keyspace.\n\
+         # TYPE kizami_storage_l0_tables gauge\n\
+         kizami_storage_l0_tables {}",
+        pressure.level(),
+        pressure.l0_tables
+    );
+    render_storage_roles(&mut body, state.storage.metrics(), openmetrics);
+    if let Some(skew) = clock::skew() {
+        let _ = writeln!(
+            body,
+            "# HELP kizami_clock_skew_seconds Reference time minus host time, last measured.\n\
+             # TYPE kizami_clock_skew_seconds gauge\n\
+             kizami_clock_skew_seconds {}",
+            skew.offset.num_milliseconds() as f64 / 1000.0
+        );
+    }
+    if let Ok(bytes) = state.storage.disk_space() {
+        let _ = writeln!(
+            body,
+            "# HELP kizami_storage_disk_bytes Bytes the storage directory takes on disk.\n\
+             # TYPE kizami_storage_disk_bytes gauge\n\
+             kizami_storage_disk_bytes {bytes}"
+        );
+    }
+    if let Ok(statuses) = status::chain_statuses(&state).await {
+        render_chain_statuses(&mut body, &statuses);
+    }
+    render_sqd_egress(&mut body, &state.sqd_health, openmetrics);
+    if let Some(tenants) = &state.tenants {
+        body.push_str(&tenants.render_prometheus(openmetrics));
+    }
+    let content_type = if openmetrics {
+        body.push_str("# EOF\n");
+        OPENMETRICS_CONTENT_TYPE
+    } else {
+        "text/plain; version=0.0.4"
+    };
+    ([(header::CONTENT_TYPE, content_type)], body)
+}
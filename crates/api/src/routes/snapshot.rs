@@ -0,0 +1,242 @@
+//! Cross-chain snapshot endpoint.
+//!
+//! Resolves the block each chain was at for a single timestamp in one request, for
+//! "state of all chains at time T" jobs. Chains that can't be answered are listed
+//! separately instead of failing the whole snapshot.
+
+use std::collections::HashSet;
+
+use axum::extract::State;
+use axum::Json;
+use serde::Deserialize;
+
+use kizami_shared::chains::{self, CHAINS};
+use kizami_shared::error::AppError;
+use kizami_shared::models::{Bound, Direction, SnapshotEntry, SnapshotFailure, SnapshotResponse};
+
+use crate::state::AppState;
+use crate::validate::{ValidJson, Validate, Violations};
+
+/// Maximum number of chains in one snapshot request.
+pub const MAX_SNAPSHOT_CHAINS: usize = 256;
+
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct SnapshotRequest {
+    /// Unix timestamp in seconds.
+    timestamp: i64,
+    /// Chain IDs to resolve. Defaults to every supported chain; duplicates are ignored.
+    #[serde(default)]
+    chains: Option<Vec<i32>>,
+}
+
+impl Validate for SnapshotRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.timestamp("timestamp", self.timestamp);
+        if let Some(chains) = &self.chains {
+            let distinct = chains.iter().collect::<HashSet<_>>().len();
+            v.batch_size("chains", distinct, MAX_SNAPSHOT_CHAINS);
+        }
+    }
+}
+
+/// Latest block at or before `timestamp` on one chain.
+///
+/// The answer is only final once a block after `timestamp` is indexed, so until then
+/// the chain is reported as `NOT_YET_INDEXED`.
+async fn resolve(
+    state: &AppState,
+    chain_id: i32,
+    timestamp: i64,
+) -> Result<SnapshotEntry, AppError> {
+    let chain = chains::chain_by_id(chain_id)
+        .ok_or_else(|| AppError::ChainNotFound(chain_id.to_string()))?;
+    let indexed_up_to = {
+        let map = state.progress.read().await;
+        map.get(chain.sqd_slug).map(|p| p.cursor).unwrap_or(0)
+    };
+
+    let rows = state.storage.find_blocks_multi(
+        chain.chain_id,
+        &[(timestamp, Bound::AtOrBefore), (timestamp, Bound::After)],
+    )?;
+    if rows[1].is_none() {
+        return Err(AppError::NotYetIndexed {
+            chain_id: chain_id.to_string(),
+            timestamp,
+            indexed_up_to,
+        });
+    }
+    let (block_number, block_timestamp) = rows[0].ok_or_else(|| AppError::BlockNotFound {
+        chain_id: chain_id.to_string(),
+        timestamp,
+        direction: Direction::Before.to_string(),
+    })?;
+
+    Ok(SnapshotEntry {
+        chain_id,
+        block_number,
+        block_timestamp,
+        indexed_up_to,
+    })
+}
+
+/// Returns the block every requested chain was at for a single timestamp.
+///
+/// Each chain resolves to its latest block at or before the timestamp. Unknown chains,
+/// timestamps before a chain's genesis and timestamps ingestion hasn't passed yet are
+/// listed under `unresolved` with the error code a single lookup would return. Storage
+/// failures fail the whole request.
+#[utoipa::path(
+    post,
+    path = "/v1/snapshot",
+    tag = "Blocks",
+    summary = "Snapshot all chains at a timestamp",
+    description = "Returns the latest block at or before a timestamp on each requested chain (all chains by default), plus the chains that couldn't be resolved.",
+    request_body = SnapshotRequest,
+    responses(
+        (status = 200, description = "Per-chain blocks and unresolved chains", body = SnapshotResponse),
+        (status = 400, description = "Invalid timestamp or too many chains", body = kizami_shared::models::ErrorBody),
+        (status = 500, description = "Storage error or corrupt data", body = kizami_shared::models::ErrorBody),
+        (status = 503, description = "Storage unavailable", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn snapshot(
+    State(state): State<AppState>,
+    ValidJson(body): ValidJson<SnapshotRequest>,
+) -> Result<Json<SnapshotResponse>, AppError> {
+    let timestamp = body.timestamp;
+
+    let mut seen = HashSet::new();
+    let chain_ids: Vec<i32> = body
+        .chains
+        .unwrap_or_else(|| CHAINS.iter().map(|c| c.chain_id).collect())
+        .into_iter()
+        .filter(|id| seen.insert(*id))
+        .collect();
+
+    let mut results = Vec::new();
+    let mut unresolved = Vec::new();
+    for chain_id in chain_ids {
+        match per_chain(chain_id, resolve(&state, chain_id, timestamp).await)? {
+            Ok(entry) => results.push(entry),
+            Err(failure) => unresolved.push(failure),
+        }
+    }
+
+    Ok(Json(SnapshotResponse {
+        timestamp,
+        results,
+        unresolved,
+    }))
+}
+
+/// Splits one chain's answer in a cross-chain lookup from its failure. A chain that is
+/// unknown or has no block for the timestamp becomes a [`SnapshotFailure`] listed in
+/// `unresolved`; any other error fails the whole request.
+pub fn per_chain<T>(
+    chain_id: i32,
+    result: Result<T, AppError>,
+) -> Result<Result<T, SnapshotFailure>, AppError> {
+    match result {
+        Ok(value) => Ok(Ok(value)),
+        Err(
+            err @ (AppError::ChainNotFound(_)
+            | AppError::BlockNotFound { .. }
+            | AppError::NotYetIndexed { .. }),
+        ) => Ok(Err(SnapshotFailure {
+            chain_id,
+            code: err.code().to_string(),
+            message: err.to_string(),
+        })),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::routing::post;
+    use axum::Router;
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    use kizami_shared::storage::Storage;
+
+    use super::*;
+
+    fn test_state() -> (AppState, Storage, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        let state = AppState::for_tests(&storage);
+        (state, storage, dir)
+    }
+
+    async fn post_json(state: AppState, body: &str) -> (StatusCode, serde_json::Value) {
+        let app = Router::new()
+            .route("/v1/snapshot", post(snapshot))
+            .with_state(state);
+        let response = app
+            .oneshot(
+                Request::post("/v1/snapshot")
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn resolves_chains_and_reports_failures() {
+        let (state, storage, _dir) = test_state();
+        storage
+            .insert_blocks(1, &[100, 101], &[1000, 1012])
+            .unwrap();
+        storage
+            .insert_blocks(8453, &[50, 51, 52], &[990, 1001, 1010])
+            .unwrap();
+        storage.insert_blocks(10, &[7], &[900]).unwrap();
+
+        let (status, json) = post_json(
+            state,
+            r#"{"timestamp": 1005, "chains": [1, 8453, 10, 999999, 1]}"#,
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        let results = json["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["chain_id"], 1);
+        assert_eq!(results[0]["block_number"], 100);
+        assert_eq!(results[1]["chain_id"], 8453);
+        assert_eq!(results[1]["block_timestamp"], 1001);
+
+        let unresolved = json["unresolved"].as_array().unwrap();
+        assert_eq!(unresolved.len(), 2);
+        assert_eq!(unresolved[0]["chain_id"], 10);
+        assert_eq!(unresolved[0]["code"], "NOT_YET_INDEXED");
+        assert_eq!(unresolved[1]["code"], "CHAIN_NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn defaults_to_all_chains() {
+        let (state, _, _dir) = test_state();
+        let (status, json) = post_json(state, r#"{"timestamp": 1005}"#).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["unresolved"].as_array().unwrap().len(), CHAINS.len());
+    }
+
+    #[tokio::test]
+    async fn negative_timestamp_returns_400() {
+        let (state, _, _dir) = test_state();
+        let (status, json) = post_json(state, r#"{"timestamp": -1, "chains": [1]}"#).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(json["error"]["code"], "INVALID_TIMESTAMP");
+    }
+}
@@ -1,17 +1,128 @@
 //! Indexing status endpoint.
 //!
 //! Returns the indexing progress for all supported chains by combining static chain
-//! configuration and the in-memory progress map (cursor, head, updated_at).
+//! configuration, the in-memory progress map (cursor, head, updated_at), the
+//! quarantined block counts from storage, and SQD dataset health.
+//!
+//! A chain is reported `chain_stalled` when its SQD head hasn't moved for more than
+//! `CHAIN_STALL_BLOCK_TIMES` times its recent average block time (never less than
+//! [`MIN_STALL_SECS`]). That is the chain or its dataset halting, as opposed to kizami
+//! falling behind a head that is still moving. When an RPC endpoint shows the chain
+//! ahead of the dataset, the chain is producing blocks and the flag stays off.
+//!
+//! Each chain also gets a `health` grade for routers that want a single field: it is
+//! `erroring` when the recent SQD error rate reaches `HEALTH_MAX_ERROR_RATE`, else
+//! `stalled` when `chain_stalled`, else `lagging` when the cursor trails the head by
+//! more than `HEALTH_MAX_LAG_BLOCKS` or hasn't moved for `HEALTH_MAX_CURSOR_AGE_SECS`,
+//! else `fresh`. `/metrics` exports the same grades as `kizami_chain_health`.
+//!
+//! With `SERVED_BY` set, each chain names the replica that reported it (see
+//! [`crate::served_by`]).
+
+use std::sync::LazyLock;
 
 use axum::extract::State;
 use axum::Json;
+use chrono::{DateTime, Utc};
 
 use kizami_shared::chains::CHAINS;
+use kizami_shared::clock;
 use kizami_shared::error::AppError;
-use kizami_shared::models::IndexingStatusResponse;
+use kizami_shared::models::{ChainHealth, IndexingStatusResponse, SqdHealthResponse};
 
+use crate::served_by;
 use crate::state::AppState;
 
+const DEFAULT_STALL_BLOCK_TIMES: u32 = 100;
+
+/// Shortest head silence ever reported as a stall. Finalized heads advance in steps
+/// (an epoch, an L1 batch), so a few missed block times on fast chains is normal.
+pub const MIN_STALL_SECS: i64 = 900;
+
+/// Blocks used to estimate a chain's average block time.
+pub const BLOCK_TIME_SPAN: i64 = 1_000;
+
+/// `CHAIN_STALL_BLOCK_TIMES`, read once on first access. 0 disables stall detection.
+static STALL_BLOCK_TIMES: LazyLock<u32> = LazyLock::new(|| {
+    std::env::var("CHAIN_STALL_BLOCK_TIMES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_STALL_BLOCK_TIMES)
+});
+
+pub fn stall_block_times() -> u32 {
+    *STALL_BLOCK_TIMES
+}
+
+/// Limits past which a chain stops being graded `fresh`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HealthThresholds {
+    /// Blocks the cursor may trail the SQD head by.
+    pub max_lag_blocks: i64,
+    /// Seconds since the cursor last moved. Above [`MIN_STALL_SECS`] by default, so a
+    /// halted chain shows as stalled rather than lagging.
+    pub max_cursor_age_secs: i64,
+    /// Share of recent SQD requests (0 to 1) that may fail.
+    pub max_error_rate: f64,
+}
+
+/// `HEALTH_MAX_LAG_BLOCKS` (default 1000), `HEALTH_MAX_CURSOR_AGE_SECS` (default 1800)
+/// and `HEALTH_MAX_ERROR_RATE` (default 0.5), read once on first access.
+static HEALTH_THRESHOLDS: LazyLock<HealthThresholds> = LazyLock::new(|| {
+    fn env<T: std::str::FromStr>(name: &str, default: T) -> T {
+        std::env::var(name)
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(default)
+    }
+    HealthThresholds {
+        max_lag_blocks: env("HEALTH_MAX_LAG_BLOCKS", 1_000),
+        max_cursor_age_secs: env("HEALTH_MAX_CURSOR_AGE_SECS", 1_800),
+        max_error_rate: env("HEALTH_MAX_ERROR_RATE", 0.5),
+    }
+});
+
+pub fn health_thresholds() -> HealthThresholds {
+    *HEALTH_THRESHOLDS
+}
+
+/// Grades a chain. Unknown lag or cursor age (nothing fetched or ingested yet) counts
+/// as lagging; an unknown error rate (no requests yet) doesn't count against it.
+fn grade(
+    thresholds: &HealthThresholds,
+    lag_blocks: Option<i64>,
+    cursor_age_secs: Option<i64>,
+    error_rate: Option<f64>,
+    stalled: bool,
+) -> ChainHealth {
+    if error_rate.is_some_and(|rate| rate >= thresholds.max_error_rate) {
+        ChainHealth::Erroring
+    } else if stalled {
+        ChainHealth::Stalled
+    } else if lag_blocks.is_none_or(|lag| lag > thresholds.max_lag_blocks)
+        || cursor_age_secs.is_none_or(|age| age > thresholds.max_cursor_age_secs)
+    {
+        ChainHealth::Lagging
+    } else {
+        ChainHealth::Fresh
+    }
+}
+
+/// Whether a head last seen moving at `advanced_at` counts as stalled at `now`, given
+/// the chain's average block time. Unknown block times never stall.
+fn is_stalled(
+    advanced_at: DateTime<Utc>,
+    now: DateTime<Utc>,
+    block_time_secs: Option<f64>,
+    block_times: u32,
+) -> bool {
+    let Some(block_time) = block_time_secs.filter(|_| block_times > 0) else {
+        return false;
+    };
+    let threshold = ((block_time * block_times as f64) as i64).max(MIN_STALL_SECS);
+    (now - advanced_at).num_seconds() > threshold
+}
+
 /// Returns the indexing status for all supported chains.
 #[utoipa::path(
     get,
@@ -25,15 +136,47 @@ use crate::state::AppState;
 pub async fn indexing_status(
     State(state): State<AppState>,
 ) -> Result<Json<Vec<IndexingStatusResponse>>, AppError> {
+    chain_statuses(&state).await.map(Json)
+}
+
+/// Status of every supported chain, sorted by chain id.
+pub async fn chain_statuses(state: &AppState) -> Result<Vec<IndexingStatusResponse>, AppError> {
     let map = state.progress.read().await;
     let mut results = Vec::with_capacity(CHAINS.len());
+    let now = clock::now();
 
     for chain in CHAINS {
-        let (last_indexed_block, latest_known_block, updated_at) = match map.get(chain.sqd_slug) {
-            Some(p) => (p.cursor, p.head, p.updated_at),
-            None => (0, None, None),
+        let (last_indexed_block, latest_known_block, updated_at, paused, head_advanced_at) =
+            match map.get(chain.sqd_slug) {
+                Some(p) => (p.cursor, p.head, p.updated_at, p.paused, p.head_advanced_at),
+                None => (0, None, None, false, None),
+            };
+        let sqd_health = state.sqd_health.get(chain.sqd_slug);
+        let dataset_lag = sqd_health.as_ref().and_then(|h| {
+            h.chain_head
+                .zip(latest_known_block)
+                .map(|(chain_head, dataset_head)| (chain_head - dataset_head).max(0))
+        });
+        let chain_stalled = match head_advanced_at {
+            Some(advanced_at) if dataset_lag.is_none_or(|lag| lag == 0) => is_stalled(
+                advanced_at,
+                now,
+                state
+                    .storage
+                    .recent_block_time(chain.chain_id, BLOCK_TIME_SPAN)?,
+                stall_block_times(),
+            ),
+            _ => false,
         };
 
+        let health = grade(
+            &health_thresholds(),
+            latest_known_block.map(|head| (head - last_indexed_block).max(0)),
+            updated_at.map(|at| (now - at).num_seconds()),
+            sqd_health.as_ref().and_then(|h| h.error_rate()),
+            chain_stalled,
+        );
+
         let progress = latest_known_block.map(|head| {
             if head == 0 {
                 0.0
@@ -49,9 +192,79 @@ pub async fn indexing_status(
             latest_known_block,
             progress,
             updated_at,
+            rejected_blocks: state.storage.rejected_count(chain.chain_id)?,
+            paused,
+            head_advanced_at,
+            chain_stalled,
+            health,
+            sqd: sqd_health.map(|h| SqdHealthResponse {
+                error_rate: h.error_rate().unwrap_or(0.0),
+                recent_requests: h.recent_requests() as u32,
+                last_success_at: h.last_success_at,
+                last_error_at: h.last_error_at,
+                last_error: h.last_error.clone(),
+                chain_head: h.chain_head,
+                dataset_lag,
+            }),
+            served_by: served_by::served_by(),
         });
     }
 
     results.sort_by_key(|r| r.chain_id);
-    Ok(Json(results))
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    #[test]
+    fn stall_threshold_scales_with_block_time_above_a_floor() {
+        let now = Utc::now();
+        let ago = |secs| now - Duration::seconds(secs);
+        // 12s blocks: 100 block times is 1200s
+        assert!(!is_stalled(ago(1_100), now, Some(12.0), 100));
+        assert!(is_stalled(ago(1_300), now, Some(12.0), 100));
+        // 0.25s blocks: 25s is under the floor
+        assert!(!is_stalled(ago(600), now, Some(0.25), 100));
+        assert!(is_stalled(ago(MIN_STALL_SECS + 1), now, Some(0.25), 100));
+        // disabled, or no block time to go by
+        assert!(!is_stalled(ago(100_000), now, Some(12.0), 0));
+        assert!(!is_stalled(ago(100_000), now, None, 100));
+    }
+
+    #[test]
+    fn health_grade_prefers_errors_then_stalls_then_lag() {
+        let t = HealthThresholds {
+            max_lag_blocks: 100,
+            max_cursor_age_secs: 600,
+            max_error_rate: 0.5,
+        };
+        let healthy = (Some(10), Some(30), Some(0.1));
+        let grade_with = |(lag, age, errors), stalled| grade(&t, lag, age, errors, stalled);
+        assert_eq!(grade_with(healthy, false), ChainHealth::Fresh);
+        assert_eq!(
+            grade_with((Some(10), Some(30), None), false),
+            ChainHealth::Fresh
+        );
+        assert_eq!(
+            grade_with((Some(101), Some(30), None), false),
+            ChainHealth::Lagging
+        );
+        assert_eq!(
+            grade_with((Some(10), Some(601), None), false),
+            ChainHealth::Lagging
+        );
+        assert_eq!(grade_with((None, None, None), false), ChainHealth::Lagging);
+        assert_eq!(
+            grade_with((Some(10), Some(601), None), true),
+            ChainHealth::Stalled
+        );
+        assert_eq!(
+            grade_with((Some(500), Some(30), Some(0.5)), true),
+            ChainHealth::Erroring
+        );
+    }
 }
@@ -0,0 +1,33 @@
+//! Tenant usage endpoint.
+//!
+//! Reports the per-tenant counters kept by [`crate::tenants`]. Admin-only.
+
+use axum::extract::State;
+use axum::Json;
+
+use kizami_shared::models::TenantUsageResponse;
+
+use crate::state::AppState;
+
+/// Returns request, quota and error counts per tenant namespace since startup.
+#[utoipa::path(
+    get,
+    path = "/v1/admin/tenants",
+    tag = "Admin",
+    summary = "Per-tenant usage",
+    description = "Requests, rate-limited requests and error responses per tenant namespace since startup. Empty when TENANTS is unset.",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Usage per tenant", body = Vec<TenantUsageResponse>),
+        (status = 401, description = "Missing or invalid admin token", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn tenant_usage(State(state): State<AppState>) -> Json<Vec<TenantUsageResponse>> {
+    Json(
+        state
+            .tenants
+            .as_ref()
+            .map(|t| t.usage())
+            .unwrap_or_default(),
+    )
+}
@@ -0,0 +1,139 @@
+//! Ingestion uptime endpoint.
+//!
+//! The ingestion loop persists a [`CycleSummary`] per cycle (see
+//! `kizami_ingestion::CYCLE_RETENTION_DAYS` for how long they are kept). Uptime over a
+//! window is the share of it covered by cycles, each weighted by the fraction of
+//! chains it handled without error: a cycle where one chain in ten failed counts as
+//! 90% up for its span. A cycle covers the time until the next one starts, but no more
+//! than its duration plus two ingest intervals, so stretches where the process was down
+//! count against uptime.
+
+use axum::extract::State;
+use axum::Json;
+use chrono::{DateTime, Duration, Utc};
+
+use kizami_shared::clock;
+use kizami_shared::error::AppError;
+use kizami_shared::models::{UptimeResponse, UptimeWindowResponse};
+use kizami_shared::storage::CycleSummary;
+
+use crate::state::AppState;
+
+/// Windows reported, in days.
+const WINDOWS_DAYS: [u32; 2] = [7, 30];
+
+/// Uptime percentage of `cycles` (oldest first, all started in `[from, to)`), measured
+/// from the first cycle when it started after `from`.
+fn window_uptime(
+    cycles: &[CycleSummary],
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> (Option<DateTime<Utc>>, Option<f64>) {
+    let Some(first) = cycles.first() else {
+        return (None, None);
+    };
+    let measured_from = first.started_at.max(from);
+    let mut up_ms = 0.0;
+    for (i, cycle) in cycles.iter().enumerate() {
+        let longest = cycle.duration_ms as i64 + 2_000 * cycle.interval_secs as i64;
+        let next = cycles.get(i + 1).map_or(to, |c| c.started_at);
+        let end = (cycle.started_at + Duration::milliseconds(longest)).min(next);
+        let covered = (end - cycle.started_at).num_milliseconds().max(0) as f64;
+        let healthy = match cycle.chains_checked {
+            0 => 1.0,
+            checked => 1.0 - cycle.chain_errors.min(checked) as f64 / checked as f64,
+        };
+        up_ms += covered * healthy;
+    }
+    let total_ms = (to - measured_from).num_milliseconds().max(1) as f64;
+    let percent = (up_ms / total_ms * 100.0).min(100.0);
+    (Some(measured_from), Some((percent * 100.0).round() / 100.0))
+}
+
+/// Returns ingestion uptime over the last 7 and 30 days.
+#[utoipa::path(
+    get,
+    path = "/v1/uptime",
+    tag = "Status",
+    summary = "Get ingestion uptime",
+    description = "Percentage of the last 7 and 30 days during which ingestion cycles ran, weighted by the share of chains each cycle handled without error. Time with no cycle running counts as down. Windows reaching back before the recorded history are measured from the first recorded cycle.",
+    responses(
+        (status = 200, description = "Uptime per window", body = UptimeResponse)
+    )
+)]
+pub async fn uptime(State(state): State<AppState>) -> Result<Json<UptimeResponse>, AppError> {
+    let now = clock::now();
+    let longest = WINDOWS_DAYS.iter().max().copied().unwrap_or_default();
+    let cycles = state
+        .storage
+        .cycles_between(now - Duration::days(longest.into()), now)?;
+
+    let windows = WINDOWS_DAYS
+        .iter()
+        .map(|&days| {
+            let from = now - Duration::days(days.into());
+            let start = cycles.partition_point(|c| c.started_at < from);
+            let in_window = &cycles[start..];
+            let (measured_from, uptime_percent) = window_uptime(in_window, from, now);
+            UptimeWindowResponse {
+                days,
+                measured_from,
+                uptime_percent,
+                cycles: in_window.len() as u64,
+                cycles_with_errors: in_window.iter().filter(|c| c.chain_errors > 0).count() as u64,
+            }
+        })
+        .collect();
+
+    Ok(Json(UptimeResponse {
+        last_cycle_at: cycles.last().map(|c| c.started_at),
+        windows,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cycle(started_at: DateTime<Utc>, chain_errors: u32) -> CycleSummary {
+        CycleSummary {
+            started_at,
+            duration_ms: 10_000,
+            interval_secs: 50,
+            chains_checked: 10,
+            chains_behind: 2,
+            chains_deferred: 0,
+            chain_errors,
+        }
+    }
+
+    #[test]
+    fn uptime_weighs_errors_and_counts_gaps_as_down() {
+        let from = DateTime::from_timestamp(1_000_000, 0).unwrap();
+        let at = |mins: i64| from + Duration::minutes(mins);
+
+        // a clean cycle every minute for ten minutes
+        let cycles: Vec<_> = (0..10).map(|m| cycle(at(m), 0)).collect();
+        assert_eq!(
+            window_uptime(&cycles, from, at(10)),
+            (Some(from), Some(100.0))
+        );
+
+        // one chain in ten failing for half the time
+        let cycles: Vec<_> = (0..10).map(|m| cycle(at(m), (m % 2) as u32)).collect();
+        assert_eq!(window_uptime(&cycles, from, at(10)).1, Some(95.0));
+
+        // the process was down between minutes 1 and 9: the first cycle covers its 10s
+        // run plus two 50s intervals, the second the last minute (170s of 600s)
+        let cycles = vec![cycle(at(0), 0), cycle(at(9), 0)];
+        assert_eq!(window_uptime(&cycles, from, at(10)).1, Some(28.33));
+
+        // history starting mid-window is measured from the first cycle
+        let cycles = vec![cycle(at(5), 0)];
+        assert_eq!(
+            window_uptime(&cycles, from, at(6)),
+            (Some(at(5)), Some(100.0))
+        );
+        assert_eq!(window_uptime(&[], from, at(6)), (None, None));
+    }
+}
@@ -0,0 +1,188 @@
+//! Admin endpoints for the persisted backfill and repair queue.
+//!
+//! Queued ranges are worked off by the `work_queue` job (see
+//! `kizami_ingestion::work_queue`), highest priority first. They are stored in fjall, so
+//! they survive restarts.
+
+use axum::extract::{Path, State};
+use axum::{Extension, Json};
+use serde::Deserialize;
+
+use kizami_ingestion::work_queue::MAX_ATTEMPTS;
+use kizami_shared::chains;
+use kizami_shared::error::AppError;
+use kizami_shared::models::{WorkItemResponse, WorkKind};
+use kizami_shared::storage::{Storage, WorkItem};
+
+use crate::state::AppState;
+use crate::validate::{ValidJson, Validate, Violations};
+
+/// A block range to queue.
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct EnqueueWork {
+    /// First block of the range.
+    from_block: i64,
+    /// Last block of the range, inclusive.
+    to_block: i64,
+    /// Higher runs first (0-255, default 0).
+    #[serde(default)]
+    priority: u8,
+    /// Defaults to `backfill`.
+    #[serde(default)]
+    kind: WorkKind,
+}
+
+impl Validate for EnqueueWork {
+    fn validate(&self, v: &mut Violations) {
+        v.block_range("from_block", self.from_block, self.to_block);
+    }
+}
+
+fn to_response(item: WorkItem) -> WorkItemResponse {
+    WorkItemResponse {
+        id: item.id,
+        priority: item.priority,
+        kind: item.kind,
+        chain_id: item.chain_id,
+        from_block: item.from_block,
+        to_block: item.to_block,
+        attempts: item.attempts,
+        exhausted: item.attempts >= MAX_ATTEMPTS,
+        enqueued_at: item.enqueued_at,
+        not_before: item.not_before,
+        last_error: item.last_error,
+    }
+}
+
+/// Lists queued ranges in the order they will run.
+#[utoipa::path(
+    get,
+    path = "/v1/admin/work-queue",
+    tag = "Admin",
+    summary = "List queued backfill and repair ranges",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Queued ranges, next to run first", body = Vec<WorkItemResponse>),
+        (status = 401, description = "Missing or invalid admin token", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn list_work(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<WorkItemResponse>>, AppError> {
+    let items = state.storage.work_items()?;
+    Ok(Json(items.into_iter().map(to_response).collect()))
+}
+
+/// Queues a block range of a chain to be fetched and written again.
+#[utoipa::path(
+    post,
+    path = "/v1/admin/chains/{chain_id}/work-queue",
+    tag = "Admin",
+    summary = "Queue a backfill or repair range",
+    description = "Queues `from_block..=to_block` to be re-fetched from SQD, validated and written by the `work_queue` job, in batches of up to 50k blocks. The chain's cursor is not moved. Failed batches are retried with backoff.",
+    security(("admin_token" = [])),
+    params(("chain_id" = i32, Path, description = "The chain ID")),
+    request_body = EnqueueWork,
+    responses(
+        (status = 200, description = "Range queued", body = WorkItemResponse),
+        (status = 400, description = "Invalid block range", body = kizami_shared::models::ErrorBody),
+        (status = 401, description = "Missing or invalid admin token", body = kizami_shared::models::ErrorBody),
+        (status = 404, description = "Chain not found", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn enqueue_work(
+    Extension(storage): Extension<Storage>,
+    Path(chain_id): Path<i32>,
+    ValidJson(request): ValidJson<EnqueueWork>,
+) -> Result<Json<WorkItemResponse>, AppError> {
+    let chain = chains::chain_by_id(chain_id)
+        .ok_or_else(|| AppError::ChainNotFound(chain_id.to_string()))?;
+    let item = storage.enqueue_work(
+        chain.chain_id,
+        request.from_block,
+        request.to_block,
+        request.priority,
+        request.kind,
+    )?;
+    tracing::info!(
+        id = item.id,
+        chain = chain.name,
+        from_block = item.from_block,
+        to_block = item.to_block,
+        priority = item.priority,
+        kind = ?item.kind,
+        "range queued"
+    );
+    Ok(Json(to_response(item)))
+}
+
+/// Removes a queued range, e.g. one that ran out of retries.
+#[utoipa::path(
+    delete,
+    path = "/v1/admin/work-queue/{id}",
+    tag = "Admin",
+    summary = "Remove a queued range",
+    security(("admin_token" = [])),
+    params(("id" = u64, Path, description = "The work item ID")),
+    responses(
+        (status = 200, description = "The removed range", body = WorkItemResponse),
+        (status = 401, description = "Missing or invalid admin token", body = kizami_shared::models::ErrorBody),
+        (status = 404, description = "Work item not found", body = kizami_shared::models::ErrorBody)
+    )
+)]
+pub async fn remove_work(
+    Extension(storage): Extension<Storage>,
+    Path(id): Path<u64>,
+) -> Result<Json<WorkItemResponse>, AppError> {
+    let item = storage
+        .remove_work(id)?
+        .ok_or(AppError::WorkItemNotFound(id))?;
+    Ok(Json(to_response(item)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_state() -> (AppState, Storage, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        let state = AppState::for_tests(&storage);
+        (state, storage, dir)
+    }
+
+    #[tokio::test]
+    async fn rejects_inverted_ranges_and_unknown_ids() {
+        let (state, storage, _dir) = test_state();
+
+        let request = |from_block, to_block| EnqueueWork {
+            from_block,
+            to_block,
+            priority: 3,
+            kind: WorkKind::Repair,
+        };
+        let err = request(10, 5).check().err().unwrap();
+        assert_eq!(err.code(), "INVALID_BLOCK_RANGE");
+        let request = |from_block, to_block| ValidJson(request(from_block, to_block));
+        let err = enqueue_work(Extension(storage.clone()), Path(999_999), request(0, 5))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), "CHAIN_NOT_FOUND");
+
+        let Json(item) = enqueue_work(Extension(storage.clone()), Path(1), request(5, 10))
+            .await
+            .unwrap();
+        assert_eq!((item.priority, item.exhausted), (3, false));
+        let Json(items) = list_work(State(state.clone())).await.unwrap();
+        assert_eq!(items.len(), 1);
+
+        let Json(removed) = remove_work(Extension(storage.clone()), Path(item.id))
+            .await
+            .unwrap();
+        assert_eq!(removed.from_block, 5);
+        let err = remove_work(Extension(storage), Path(item.id))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), "WORK_ITEM_NOT_FOUND");
+    }
+}
@@ -0,0 +1,95 @@
+//! Tokio runtime tuning for the API server.
+//!
+//! The `kizami-api` binary builds its runtime from [`RuntimeConfig::from_env`] instead
+//! of tokio's defaults, so a small container doesn't start a worker per host core and
+//! a large host can cap the blocking pool that storage work runs on. Ingestion has its
+//! own runtime, tuned with `INGEST_WORKER_THREADS` and `INGEST_MAX_BLOCKING_THREADS`.
+
+use std::io;
+use std::time::Duration;
+
+use tokio::runtime::Runtime;
+
+/// Default cap on blocking threads, tokio's own default.
+pub const DEFAULT_MAX_BLOCKING_THREADS: usize = 512;
+
+/// Default idle time before a blocking thread exits, tokio's own default.
+pub const DEFAULT_BLOCKING_KEEP_ALIVE: Duration = Duration::from_secs(10);
+
+/// Resolved runtime settings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeConfig {
+    /// Workers running async tasks (`WORKER_THREADS`, default one per available core).
+    pub worker_threads: usize,
+    /// Most threads the blocking pool grows to (`MAX_BLOCKING_THREADS`, default 512).
+    pub max_blocking_threads: usize,
+    /// How long an idle blocking thread is kept before the pool shrinks
+    /// (`BLOCKING_THREAD_KEEP_ALIVE_SECS`, default 10).
+    pub blocking_keep_alive: Duration,
+}
+
+/// A positive count, or `None` on bad or zero input.
+fn positive(value: Option<&str>) -> Option<usize> {
+    value.and_then(|v| v.trim().parse().ok()).filter(|n| *n > 0)
+}
+
+impl RuntimeConfig {
+    /// Reads `WORKER_THREADS`, `MAX_BLOCKING_THREADS` and
+    /// `BLOCKING_THREAD_KEEP_ALIVE_SECS`. Bad or zero values fall back to the defaults.
+    pub fn from_env() -> Self {
+        let var = |name| std::env::var(name).ok();
+        Self::parse(
+            var("WORKER_THREADS").as_deref(),
+            var("MAX_BLOCKING_THREADS").as_deref(),
+            var("BLOCKING_THREAD_KEEP_ALIVE_SECS").as_deref(),
+        )
+    }
+
+    fn parse(
+        worker_threads: Option<&str>,
+        max_blocking_threads: Option<&str>,
+        keep_alive_secs: Option<&str>,
+    ) -> Self {
+        Self {
+            worker_threads: positive(worker_threads)
+                .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get())),
+            max_blocking_threads: positive(max_blocking_threads)
+                .unwrap_or(DEFAULT_MAX_BLOCKING_THREADS),
+            blocking_keep_alive: positive(keep_alive_secs)
+                .map_or(DEFAULT_BLOCKING_KEEP_ALIVE, |secs| {
+                    Duration::from_secs(secs as u64)
+                }),
+        }
+    }
+
+    /// Builds a multi-threaded runtime with these settings.
+    pub fn build(&self) -> io::Result<Runtime> {
+        tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(self.worker_threads)
+            .max_blocking_threads(self.max_blocking_threads)
+            .thread_keep_alive(self.blocking_keep_alive)
+            .thread_name("kizami-api")
+            .enable_all()
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bad_values_fall_back_and_the_runtime_uses_the_rest() {
+        let defaults = RuntimeConfig::parse(None, Some("0"), Some("soon"));
+        assert!(defaults.worker_threads >= 1);
+        assert_eq!(defaults.max_blocking_threads, DEFAULT_MAX_BLOCKING_THREADS);
+        assert_eq!(defaults.blocking_keep_alive, DEFAULT_BLOCKING_KEEP_ALIVE);
+
+        let config = RuntimeConfig::parse(Some(" 2 "), Some("8"), Some("30"));
+        assert_eq!(config.worker_threads, 2);
+        assert_eq!(config.max_blocking_threads, 8);
+        assert_eq!(config.blocking_keep_alive, Duration::from_secs(30));
+        let runtime = config.build().unwrap();
+        assert_eq!(runtime.metrics().num_workers(), 2);
+    }
+}
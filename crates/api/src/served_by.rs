@@ -0,0 +1,69 @@
+//! Replica attribution.
+//!
+//! With `SERVED_BY` set (a region or replica name, e.g. `eu-west-1/replica-2`), every
+//! response carries it in `X-Kizami-Served-By` and each chain in
+//! `/v1/indexing-status` reports it as `served_by`. Behind a load balancer spreading
+//! traffic over replicas, clients and dashboards can then tell which one served a
+//! stale answer or a slow request. Unset, nothing is added.
+
+use std::sync::LazyLock;
+
+use axum::extract::Request;
+use axum::http::HeaderValue;
+use axum::middleware::Next;
+use axum::response::Response;
+
+/// Response header naming the replica.
+pub const SERVED_BY_HEADER: &str = "x-kizami-served-by";
+
+/// `SERVED_BY`, when set to a value that can go in a header.
+static SERVED_BY: LazyLock<Option<HeaderValue>> = LazyLock::new(|| {
+    let value = std::env::var("SERVED_BY").ok()?;
+    parse(&value).or_else(|| {
+        tracing::warn!(served_by = %value, "SERVED_BY is not a valid header value, ignoring it");
+        None
+    })
+});
+
+/// Trims `value`; `None` when empty or not printable ASCII.
+fn parse(value: &str) -> Option<HeaderValue> {
+    let value = value.trim();
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_graphic() || b == b' ') {
+        return None;
+    }
+    HeaderValue::from_str(value).ok()
+}
+
+/// The configured `SERVED_BY`, if any.
+pub fn served_by() -> Option<&'static str> {
+    SERVED_BY.as_ref().and_then(|v| v.to_str().ok())
+}
+
+/// Middleware adding `X-Kizami-Served-By` to every response when `SERVED_BY` is set.
+pub async fn stamp_served_by(req: Request, next: Next) -> Response {
+    let mut response = next.run(req).await;
+    if let Some(value) = SERVED_BY.as_ref() {
+        response
+            .headers_mut()
+            .insert(SERVED_BY_HEADER, value.clone());
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_printable_names_are_accepted() {
+        assert_eq!(
+            parse(" eu-west-1/replica-2 ").unwrap(),
+            "eu-west-1/replica-2"
+        );
+        assert_eq!(parse("us east").unwrap(), "us east");
+        assert!(parse("").is_none());
+        assert!(parse("   ").is_none());
+        assert!(parse("eu\nwest").is_none());
+        assert!(parse("région").is_none());
+    }
+}
@@ -0,0 +1,326 @@
+//! HTTP connection handling.
+//!
+//! Replaces `axum::serve` so connection settings can be tuned for the load balancer in
+//! front: HTTP/2 (cleartext, prior knowledge) alongside HTTP/1, keep-alive, how long an
+//! idle connection may wait for its next request, and HTTP/2 stream limits. The body
+//! limit for JSON POSTs (batch lookups, snapshots) is applied as a router layer in
+//! [`crate::run`].
+//!
+//! With TLS configured (see [`crate::tls`]) each connection's handshake runs on its own
+//! task, so a slow client can't hold up accepting others.
+//!
+//! On shutdown the listener is closed first, then open connections are drained: idle
+//! ones close at once, busy ones after their in-flight response (including streamed
+//! exports) has been written, for up to `DRAIN_TIMEOUT_SECS`. Only then does `run`
+//! stop ingestion.
+
+use std::future::Future;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use axum::extract::ConnectInfo;
+use axum::Router;
+use hyper_util::rt::{TokioExecutor, TokioIo, TokioTimer};
+use hyper_util::server::conn::auto::Builder;
+use hyper_util::server::graceful::{GracefulShutdown, Watcher};
+use hyper_util::service::TowerToHyperService;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+use tower::ServiceExt;
+
+use crate::tls::ClientCert;
+
+const DEFAULT_KEEP_ALIVE_TIMEOUT_SECS: u64 = 75;
+const DEFAULT_HTTP2_MAX_CONCURRENT_STREAMS: u32 = 200;
+const DEFAULT_DRAIN_TIMEOUT_SECS: u64 = 30;
+
+/// Default body limit for JSON POSTs; a full 1000-query batch with RFC3339
+/// timestamps and per-query options stays well under it.
+pub const DEFAULT_MAX_BODY_BYTES: usize = 8 * 1024 * 1024;
+
+/// Backoff after a failed accept (e.g. out of file descriptors).
+const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_secs(1);
+
+/// How long a client may take to complete the TLS handshake.
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Connection settings, from `HTTP2`, `HTTP_KEEP_ALIVE`, `HTTP_KEEP_ALIVE_TIMEOUT_SECS`,
+/// `HTTP2_KEEP_ALIVE_INTERVAL_SECS`, `HTTP2_MAX_CONCURRENT_STREAMS`, `MAX_BODY_BYTES`
+/// and `DRAIN_TIMEOUT_SECS`.
+#[derive(Debug, Clone, Copy)]
+pub struct ServerConfig {
+    /// Accept HTTP/2 (h2c with prior knowledge) next to HTTP/1.
+    pub http2: bool,
+    /// Reuse HTTP/1 connections across requests.
+    pub keep_alive: bool,
+    /// How long a connection may sit idle waiting for the next request's headers.
+    /// Should exceed the load balancer's idle timeout, so the balancer closes idle
+    /// connections first and never sends a request onto one being torn down.
+    pub keep_alive_timeout: Duration,
+    /// Interval of HTTP/2 keep-alive pings; `None` sends none.
+    pub http2_keep_alive_interval: Option<Duration>,
+    pub http2_max_concurrent_streams: u32,
+    pub max_body_bytes: usize,
+    /// How long shutdown waits for in-flight requests before abandoning them.
+    pub drain_timeout: Duration,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            http2: false,
+            keep_alive: true,
+            keep_alive_timeout: Duration::from_secs(DEFAULT_KEEP_ALIVE_TIMEOUT_SECS),
+            http2_keep_alive_interval: None,
+            http2_max_concurrent_streams: DEFAULT_HTTP2_MAX_CONCURRENT_STREAMS,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            drain_timeout: Duration::from_secs(DEFAULT_DRAIN_TIMEOUT_SECS),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Reads the settings from the environment. Unset or unparsable values keep their
+    /// defaults.
+    pub fn from_env() -> Self {
+        fn env<T: std::str::FromStr>(name: &str) -> Option<T> {
+            std::env::var(name).ok().and_then(|v| v.trim().parse().ok())
+        }
+        let flag = |name: &str| env::<String>(name).map(|v| v == "true" || v == "1");
+        let defaults = Self::default();
+        Self {
+            http2: flag("HTTP2").unwrap_or(defaults.http2),
+            keep_alive: flag("HTTP_KEEP_ALIVE").unwrap_or(defaults.keep_alive),
+            keep_alive_timeout: env("HTTP_KEEP_ALIVE_TIMEOUT_SECS")
+                .filter(|&secs: &u64| secs > 0)
+                .map_or(defaults.keep_alive_timeout, Duration::from_secs),
+            http2_keep_alive_interval: env("HTTP2_KEEP_ALIVE_INTERVAL_SECS")
+                .filter(|&secs: &u64| secs > 0)
+                .map(Duration::from_secs),
+            http2_max_concurrent_streams: env("HTTP2_MAX_CONCURRENT_STREAMS")
+                .filter(|&n: &u32| n > 0)
+                .unwrap_or(defaults.http2_max_concurrent_streams),
+            max_body_bytes: env("MAX_BODY_BYTES")
+                .filter(|&n: &usize| n > 0)
+                .unwrap_or(defaults.max_body_bytes),
+            drain_timeout: env("DRAIN_TIMEOUT_SECS")
+                .map_or(defaults.drain_timeout, Duration::from_secs),
+        }
+    }
+
+    fn builder(&self) -> Builder<TokioExecutor> {
+        let mut builder = Builder::new(TokioExecutor::new());
+        builder
+            .http1()
+            .timer(TokioTimer::new())
+            .keep_alive(self.keep_alive)
+            .header_read_timeout(self.keep_alive_timeout);
+        builder
+            .http2()
+            .timer(TokioTimer::new())
+            .max_concurrent_streams(self.http2_max_concurrent_streams)
+            .keep_alive_interval(self.http2_keep_alive_interval)
+            .keep_alive_timeout(self.keep_alive_timeout);
+        if self.http2 {
+            builder
+        } else {
+            builder.http1_only()
+        }
+    }
+}
+
+/// Serves `app` on `listener` until `shutdown` completes, then stops accepting and
+/// waits up to [`ServerConfig::drain_timeout`] for open connections to finish their
+/// in-flight requests. Handlers see the peer address as
+/// `ConnectInfo<std::net::SocketAddr>`. With `tls`, connections are TLS and requests
+/// from a client that presented a verified certificate carry a [`ClientCert`].
+pub async fn serve(
+    listener: TcpListener,
+    app: Router,
+    config: ServerConfig,
+    tls: Option<TlsAcceptor>,
+    shutdown: impl Future<Output = ()>,
+) {
+    let builder = config.builder();
+    let graceful = GracefulShutdown::new();
+    tokio::pin!(shutdown);
+
+    loop {
+        let (stream, remote) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    tracing::error!(error = %e, "failed to accept connection");
+                    tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
+                    continue;
+                }
+            },
+            _ = &mut shutdown => break,
+        };
+        let (app, builder, watcher) = (app.clone(), builder.clone(), graceful.watcher());
+        match tls.clone() {
+            None => {
+                tokio::spawn(serve_connection(
+                    stream, remote, None, app, builder, watcher,
+                ));
+            }
+            Some(acceptor) => {
+                tokio::spawn(async move {
+                    let handshake =
+                        tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, acceptor.accept(stream));
+                    let stream = match handshake.await {
+                        Ok(Ok(stream)) => stream,
+                        Ok(Err(e)) => {
+                            tracing::debug!(error = %e, remote = %remote, "TLS handshake failed");
+                            return;
+                        }
+                        Err(_) => {
+                            tracing::debug!(remote = %remote, "TLS handshake timed out");
+                            return;
+                        }
+                    };
+                    let cert = stream
+                        .get_ref()
+                        .1
+                        .peer_certificates()
+                        .and_then(|chain| chain.first())
+                        .map(|leaf| ClientCert::from_der(leaf));
+                    serve_connection(stream, remote, cert, app, builder, watcher).await;
+                });
+            }
+        }
+    }
+
+    drop(listener);
+    let open = graceful.count();
+    tracing::info!(
+        connections = open as u64,
+        drain_timeout_secs = config.drain_timeout.as_secs(),
+        "stopped accepting connections, draining"
+    );
+    let started = std::time::Instant::now();
+    match tokio::time::timeout(config.drain_timeout, graceful.shutdown()).await {
+        Ok(()) => tracing::info!(
+            duration_ms = started.elapsed().as_millis() as u64,
+            "connections drained"
+        ),
+        Err(_) => tracing::warn!(
+            drain_timeout_secs = config.drain_timeout.as_secs(),
+            "drain timeout reached, abandoning open connections"
+        ),
+    }
+}
+
+/// Serves one connection until it closes, or shutdown drains it.
+async fn serve_connection<S>(
+    stream: S,
+    remote: SocketAddr,
+    cert: Option<ClientCert>,
+    app: Router,
+    builder: Builder<TokioExecutor>,
+    watcher: Watcher,
+) where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let service = app.map_request(move |mut req: axum::extract::Request<_>| {
+        req.extensions_mut().insert(ConnectInfo(remote));
+        if let Some(cert) = &cert {
+            req.extensions_mut().insert(cert.clone());
+        }
+        req
+    });
+    let conn = builder
+        .serve_connection(TokioIo::new(stream), TowerToHyperService::new(service))
+        .into_owned();
+    if let Err(e) = watcher.watch(conn).await {
+        tracing::debug!(error = %e, remote = %remote, "connection closed with error");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::routing::get;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn serves_http1_and_optionally_http2() {
+        for http2 in [false, true] {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let app =
+                Router::new().route(
+                    "/peer",
+                    get(|ConnectInfo(peer): ConnectInfo<SocketAddr>| async move {
+                        peer.ip().to_string()
+                    }),
+                );
+            let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+            let config = ServerConfig {
+                http2,
+                ..Default::default()
+            };
+            let server = tokio::spawn(serve(listener, app, config, None, async {
+                let _ = stopped.await;
+            }));
+
+            let url = format!("http://{addr}/peer");
+            let http1 = reqwest::get(&url).await.unwrap();
+            assert_eq!(http1.version(), reqwest::Version::HTTP_11);
+            assert_eq!(http1.text().await.unwrap(), "127.0.0.1");
+
+            let prior_knowledge = reqwest::Client::builder()
+                .http2_prior_knowledge()
+                .build()
+                .unwrap()
+                .get(&url)
+                .send()
+                .await;
+            assert_eq!(prior_knowledge.is_ok(), http2, "http2 = {http2}");
+
+            stop.send(()).unwrap();
+            server.await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn shutdown_waits_for_in_flight_requests_up_to_the_drain_timeout() {
+        for (drain_timeout, completes) in [(Duration::from_secs(5), true), (Duration::ZERO, false)]
+        {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let app = Router::new().route(
+                "/slow",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_millis(300)).await;
+                    "done"
+                }),
+            );
+            let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+            let config = ServerConfig {
+                drain_timeout,
+                ..Default::default()
+            };
+            let server = tokio::spawn(serve(listener, app, config, None, async {
+                let _ = stopped.await;
+            }));
+
+            let request = tokio::spawn(reqwest::get(format!("http://{addr}/slow")));
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            stop.send(()).unwrap();
+            server.await.unwrap();
+            // new connections are refused once shutdown starts
+            assert!(reqwest::get(format!("http://{addr}/slow")).await.is_err());
+
+            if completes {
+                let response = request.await.unwrap().unwrap();
+                assert_eq!(response.text().await.unwrap(), "done");
+            } else {
+                // serve returned without waiting for it
+                assert!(!request.is_finished());
+                request.abort();
+            }
+        }
+    }
+}
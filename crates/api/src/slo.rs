@@ -0,0 +1,328 @@
+//! Per-route latency tracking against a p99 SLO.
+//!
+//! A middleware records the latency of every request into a fixed-size window per
+//! matched route. Percentiles are computed on demand for the admin `/slo` endpoint and
+//! the Prometheus `/metrics` endpoint. Every [`CHECK_EVERY`] samples, a route's rolling
+//! p99 is checked against the configured threshold and, when it exceeds it, a
+//! `slo_violation` alert event is logged (at most once per route per
+//! [`ALERT_COOLDOWN`]). Each route's window has its own lock, so requests to different
+//! routes never wait on each other.
+//!
+//! Block lookups are also recorded into a [`LookupHistogram`] carrying trace
+//! exemplars (see [`crate::exemplars`]).
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use axum::extract::{MatchedPath, Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+
+use crate::exemplars::{self, LookupHistogram};
+
+/// Number of most recent samples kept per route.
+const WINDOW_SIZE: usize = 1024;
+
+/// Minimum samples before a route's p99 is checked against the threshold, so a single
+/// cold-start request can't trip an alert.
+const MIN_SAMPLES_FOR_ALERT: usize = 100;
+
+/// Samples recorded on a route between two checks of its p99, so the selection over
+/// the window runs once per this many requests instead of on every one.
+const CHECK_EVERY: u64 = 64;
+
+/// Minimum time between two violation alerts for the same route.
+const ALERT_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// Default p99 target in milliseconds.
+pub const DEFAULT_P99_THRESHOLD_MS: f64 = 50.0;
+
+#[derive(Default)]
+struct RouteWindow {
+    /// Latencies in microseconds, oldest first.
+    samples: VecDeque<u64>,
+    total: u64,
+    last_alert: Option<Instant>,
+}
+
+/// Latency percentiles for a single route over the current window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteLatency {
+    pub route: String,
+    /// Requests recorded since startup.
+    pub total: u64,
+    /// Samples in the current window.
+    pub samples: usize,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+}
+
+/// Rolling per-route latency windows with a shared p99 threshold.
+pub struct SloTracker {
+    p99_threshold_ms: f64,
+    routes: RwLock<HashMap<String, Arc<Mutex<RouteWindow>>>>,
+    lookups: LookupHistogram,
+}
+
+impl SloTracker {
+    pub fn new(p99_threshold_ms: f64) -> Self {
+        Self {
+            p99_threshold_ms,
+            routes: RwLock::new(HashMap::new()),
+            lookups: LookupHistogram::default(),
+        }
+    }
+
+    /// Reads the threshold from `SLO_P99_MS` (default 50).
+    pub fn from_env() -> Self {
+        let threshold = std::env::var("SLO_P99_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|v: &f64| *v > 0.0)
+            .unwrap_or(DEFAULT_P99_THRESHOLD_MS);
+        Self::new(threshold)
+    }
+
+    pub fn p99_threshold_ms(&self) -> f64 {
+        self.p99_threshold_ms
+    }
+
+    /// Records one request and logs an alert if the route's p99 now breaches the SLO.
+    pub fn record(&self, route: &str, latency: Duration) {
+        let window = self.window(route);
+        let mut window = window.lock().unwrap();
+        if window.samples.len() == WINDOW_SIZE {
+            window.samples.pop_front();
+        }
+        window.samples.push_back(latency.as_micros() as u64);
+        window.total += 1;
+
+        if window.samples.len() < MIN_SAMPLES_FOR_ALERT || !window.total.is_multiple_of(CHECK_EVERY)
+        {
+            return;
+        }
+        if window
+            .last_alert
+            .is_some_and(|t| t.elapsed() < ALERT_COOLDOWN)
+        {
+            return;
+        }
+        let p99_ms = select_percentile_ms(&window.samples, 0.99);
+        if p99_ms > self.p99_threshold_ms {
+            window.last_alert = Some(Instant::now());
+            tracing::warn!(
+                alert = "slo_violation",
+                route = route,
+                p99_ms = p99_ms,
+                threshold_ms = self.p99_threshold_ms,
+                samples = window.samples.len() as u64,
+                "route p99 latency exceeds SLO"
+            );
+        }
+    }
+
+    /// The route's window, created the first time the route is seen.
+    fn window(&self, route: &str) -> Arc<Mutex<RouteWindow>> {
+        if let Some(window) = self.routes.read().unwrap().get(route) {
+            return window.clone();
+        }
+        self.routes
+            .write()
+            .unwrap()
+            .entry(route.to_owned())
+            .or_default()
+            .clone()
+    }
+
+    /// Returns percentiles for every route seen so far, sorted by route.
+    pub fn snapshot(&self) -> Vec<RouteLatency> {
+        let routes = self.routes.read().unwrap();
+        let mut out: Vec<RouteLatency> = routes
+            .iter()
+            .map(|(route, window)| {
+                let window = window.lock().unwrap();
+                let sorted = sorted(&window.samples);
+                RouteLatency {
+                    route: route.clone(),
+                    total: window.total,
+                    samples: sorted.len(),
+                    p50_ms: percentile_ms(&sorted, 0.50),
+                    p95_ms: percentile_ms(&sorted, 0.95),
+                    p99_ms: percentile_ms(&sorted, 0.99),
+                }
+            })
+            .collect();
+        out.sort_by(|a, b| a.route.cmp(&b.route));
+        out
+    }
+
+    /// Records a block lookup into the exemplar histogram.
+    pub fn record_lookup(&self, route: &str, latency: Duration, trace_id: Option<String>) {
+        self.lookups.record(route, latency, trace_id);
+    }
+
+    /// Renders the latency windows and the lookup histogram in Prometheus text
+    /// exposition format, with exemplars when `openmetrics` is set.
+    pub fn render_prometheus(&self, openmetrics: bool) -> String {
+        let mut out = String::new();
+        out.push_str(
+            "# HELP kizami_http_request_duration_seconds Rolling request latency per route.\n",
+        );
+        out.push_str("# TYPE kizami_http_request_duration_seconds summary\n");
+        for r in self.snapshot() {
+            for (q, ms) in [("0.5", r.p50_ms), ("0.95", r.p95_ms), ("0.99", r.p99_ms)] {
+                let _ = writeln!(
+                    out,
+                    "kizami_http_request_duration_seconds{{route=\"{}\",quantile=\"{q}\"}} {}",
+                    r.route,
+                    ms / 1000.0
+                );
+            }
+            let _ = writeln!(
+                out,
+                "kizami_http_request_duration_seconds_count{{route=\"{}\"}} {}",
+                r.route, r.total
+            );
+        }
+        let _ = writeln!(
+            out,
+            "# HELP kizami_slo_p99_threshold_seconds Configured p99 latency SLO.\n\
+             # TYPE kizami_slo_p99_threshold_seconds gauge\n\
+             kizami_slo_p99_threshold_seconds {}",
+            self.p99_threshold_ms / 1000.0
+        );
+        out.push_str(&self.lookups.render(openmetrics));
+        out
+    }
+}
+
+fn sorted(samples: &VecDeque<u64>) -> Vec<u64> {
+    let mut v: Vec<u64> = samples.iter().copied().collect();
+    v.sort_unstable();
+    v
+}
+
+/// Nearest-rank percentile over unsorted microsecond samples, in milliseconds, found
+/// by selection rather than a full sort.
+fn select_percentile_ms(samples: &VecDeque<u64>, q: f64) -> f64 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let mut v: Vec<u64> = samples.iter().copied().collect();
+    let rank = ((q * v.len() as f64).ceil() as usize).clamp(1, v.len());
+    let (_, nth, _) = v.select_nth_unstable(rank - 1);
+    *nth as f64 / 1000.0
+}
+
+/// Nearest-rank percentile over sorted microsecond samples, in milliseconds.
+fn percentile_ms(sorted: &[u64], q: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = ((q * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len());
+    sorted[rank - 1] as f64 / 1000.0
+}
+
+/// Middleware recording request latency under the matched route template.
+pub async fn track_latency(
+    State(tracker): State<Arc<SloTracker>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    // MatchedPath is an Arc<str> internally, so cloning it doesn't allocate
+    let route = req.extensions().get::<MatchedPath>().cloned();
+    let route = route.as_ref().map_or("unmatched", |p| p.as_str());
+    let lookup = exemplars::is_lookup_route(route);
+    let trace_id = lookup
+        .then(|| exemplars::sampled_trace_id(req.headers()))
+        .flatten();
+    let start = Instant::now();
+    let response = next.run(req).await;
+    let latency = start.elapsed();
+    tracker.record(route, latency);
+    if lookup {
+        tracker.record_lookup(route, latency, trace_id);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_use_nearest_rank() {
+        let samples: Vec<u64> = (1..=100).map(|ms| ms * 1000).collect();
+        assert_eq!(percentile_ms(&samples, 0.50), 50.0);
+        assert_eq!(percentile_ms(&samples, 0.99), 99.0);
+        assert_eq!(percentile_ms(&[], 0.99), 0.0);
+    }
+
+    #[test]
+    fn selection_matches_sorted_percentile() {
+        let samples: VecDeque<u64> = (1..=100).rev().map(|ms| ms * 1000).collect();
+        assert_eq!(select_percentile_ms(&samples, 0.99), 99.0);
+        assert_eq!(
+            select_percentile_ms(&samples, 0.50),
+            percentile_ms(&sorted(&samples), 0.50)
+        );
+    }
+
+    #[test]
+    fn p99_is_checked_every_n_samples() {
+        let tracker = SloTracker::new(50.0);
+        let last_alert = |tracker: &SloTracker| {
+            tracker.routes.read().unwrap()["/a"]
+                .lock()
+                .unwrap()
+                .last_alert
+        };
+        for _ in 0..(CHECK_EVERY * 2 - 1) {
+            tracker.record("/a", Duration::from_millis(80));
+        }
+        assert!(last_alert(&tracker).is_none());
+        tracker.record("/a", Duration::from_millis(80));
+        assert!(last_alert(&tracker).is_some());
+    }
+
+    #[test]
+    fn snapshot_tracks_routes_separately() {
+        let tracker = SloTracker::new(50.0);
+        for _ in 0..10 {
+            tracker.record("/a", Duration::from_millis(5));
+        }
+        tracker.record("/b", Duration::from_millis(80));
+
+        let snap = tracker.snapshot();
+        assert_eq!(snap.len(), 2);
+        assert_eq!(snap[0].route, "/a");
+        assert_eq!(snap[0].total, 10);
+        assert_eq!(snap[0].p99_ms, 5.0);
+        assert_eq!(snap[1].p99_ms, 80.0);
+    }
+
+    #[test]
+    fn window_is_bounded() {
+        let tracker = SloTracker::new(50.0);
+        for _ in 0..(WINDOW_SIZE + 10) {
+            tracker.record("/a", Duration::from_millis(1));
+        }
+        let snap = tracker.snapshot();
+        assert_eq!(snap[0].samples, WINDOW_SIZE);
+        assert_eq!(snap[0].total, (WINDOW_SIZE + 10) as u64);
+    }
+
+    #[test]
+    fn prometheus_output_contains_quantiles() {
+        let tracker = SloTracker::new(50.0);
+        tracker.record("/v1/chains", Duration::from_millis(2));
+        let text = tracker.render_prometheus(false);
+        assert!(text.contains(
+            "kizami_http_request_duration_seconds{route=\"/v1/chains\",quantile=\"0.99\"} 0.002"
+        ));
+        assert!(text.contains("kizami_slo_p99_threshold_seconds 0.05"));
+    }
+}
@@ -0,0 +1,159 @@
+//! Effective configuration logging.
+//!
+//! Emits one structured `effective configuration` event at startup with every setting
+//! as resolved after defaults and parsing, so a misconfigured deployment can be
+//! diagnosed from its logs alone. Secrets (`ADMIN_TOKEN`, `PAGINATION_SECRET`,
+//! `INTERNAL_TOKEN`, tenant API keys) are only reported as set or unset.
+
+use std::path::Path;
+
+use kizami_ingestion::IngestConfig;
+use kizami_shared::approximate;
+use kizami_shared::chains::{self, CHAINS};
+use kizami_shared::clock;
+
+use crate::backups::Backups;
+use crate::cursor_history::CursorHistory;
+use crate::demo::DemoMode;
+use crate::idempotency::IdempotencyStore;
+use crate::logging::LogConfig;
+use crate::routes::{blocks, export, status};
+use crate::runtime::RuntimeConfig;
+use crate::served_by;
+use crate::server::ServerConfig;
+use crate::state::AppState;
+use crate::tls::TlsConfig;
+use crate::trust::TrustPolicy;
+
+/// Reports a secret's presence without its value.
+fn redacted(set: bool) -> &'static str {
+    if set {
+        "<redacted>"
+    } else {
+        "unset"
+    }
+}
+
+/// Everything resolved at startup that the configuration event reports.
+pub struct EffectiveConfig<'a> {
+    pub data_dir: &'a str,
+    pub port: &'a str,
+    pub state: &'a AppState,
+    pub ingest: &'a IngestConfig,
+    pub idempotency: &'a IdempotencyStore,
+    pub demo: Option<&'a DemoMode>,
+    pub trust: Option<&'a TrustPolicy>,
+    pub cursor_history: Option<&'a CursorHistory>,
+    pub backups: Option<&'a Backups>,
+    pub server: &'a ServerConfig,
+    pub tls: Option<&'a TlsConfig>,
+}
+
+/// Logs the effective configuration.
+pub fn log_effective_config(config: EffectiveConfig<'_>) {
+    let EffectiveConfig {
+        data_dir,
+        port,
+        state,
+        ingest,
+        idempotency,
+        demo,
+        trust,
+        cursor_history,
+        backups,
+        server,
+        tls,
+    } = config;
+    let data_dir = Path::new(data_dir)
+        .canonicalize()
+        .map(|p| p.display().to_string())
+        .unwrap_or_else(|_| data_dir.to_string());
+    let pagination_secret = std::env::var("PAGINATION_SECRET").is_ok_and(|v| !v.is_empty());
+    let tenants: Vec<String> = state
+        .tenants
+        .as_ref()
+        .map(|t| t.usage().into_iter().map(|u| u.tenant).collect())
+        .unwrap_or_default();
+    let log = LogConfig::from_env();
+    let runtime = RuntimeConfig::from_env();
+
+    tracing::info!(
+        version = env!("CARGO_PKG_VERSION"),
+        data_dir = %data_dir,
+        port = %port,
+        log_format = ?log.format,
+        log_filter = %log.directives,
+        chains = CHAINS.len(),
+        chain_aliases = ?chains::aliases(),
+        genesis_overrides = ?chains::genesis_overrides(),
+        approximate_chains = ?approximate::sampled_chains(),
+        ingest_interval_secs = ingest.interval_secs,
+        worker_threads = runtime.worker_threads,
+        max_blocking_threads = runtime.max_blocking_threads,
+        blocking_thread_keep_alive_secs = runtime.blocking_keep_alive.as_secs(),
+        ingest_worker_threads = ingest.worker_threads,
+        ingest_max_blocking_threads = ingest.max_blocking_threads,
+        sqd_requests_per_cycle = ?ingest.sqd_requests_per_cycle,
+        sqd_daily_egress_budget_bytes = ?ingest.sqd_daily_egress_budget,
+        head_poll_interval_secs = ?ingest.head_poll_interval.map(|i| i.as_secs()),
+        rpc_chains = ?ingest.rpc.chain_ids(),
+        timestamp_jump_alert_secs = ?ingest.timestamp_jump_alert_secs,
+        work_queue_interval_secs = ?ingest.work_queue_interval.map(|i| i.as_secs()),
+        catchup_lag_secs = ?ingest.catchup_lag_secs,
+        catchup_parallelism = ingest.catchup_parallelism,
+        error_log_size = ingest.error_log_size,
+        clock_skew_tolerance_secs = clock::tolerance().num_milliseconds() as f64 / 1000.0,
+        ntp_server = ?clock::ntp_server(),
+        persist_policy = ?ingest.persist_policy,
+        cursor_check_every_n_cycles = ingest.cursor_check_every,
+        cursor_heal = ingest.cursor_heal,
+        log_sample_ingest_every = ingest.log_every_n_cycles,
+        log_sample_lookups_every = state.lookups.log_sample_every(),
+        log_summary_interval_secs = ?ingest.log_summary_interval.map(|i| i.as_secs()),
+        cache_ttl_secs = state.lookups.deep_ttl().as_secs(),
+        cache_near_tip_ttl_secs = state.lookups.near_tip_ttl().as_secs(),
+        cache_deep_blocks = state.lookups.deep_blocks(),
+        cache_max_entries = state.lookups.stats().max_entries,
+        cache_bypass_per_min = state.lookups.bypass_per_minute(),
+        shared_cache = ?state.lookups.shared_addr(),
+        shared_cache_ttl_secs = ?state.lookups.shared_ttls().map(|(deep, _)| deep.as_secs()),
+        shared_cache_near_tip_ttl_secs = ?state.lookups.shared_ttls().map(|(_, near_tip)| near_tip.as_secs()),
+        http2 = server.http2,
+        http_keep_alive = server.keep_alive,
+        http_keep_alive_timeout_secs = server.keep_alive_timeout.as_secs(),
+        http2_keep_alive_interval_secs = ?server.http2_keep_alive_interval.map(|i| i.as_secs()),
+        http2_max_concurrent_streams = server.http2_max_concurrent_streams,
+        max_body_bytes = server.max_body_bytes,
+        drain_timeout_secs = server.drain_timeout.as_secs(),
+        served_by = ?served_by::served_by(),
+        tls = tls.is_some(),
+        client_cert_mode = ?tls.and_then(|t| t.client_cert_mode()),
+        admin_client_certs = tls.map_or(0, |t| t.admin_certs().len()),
+        slo_p99_ms = state.slo.p99_threshold_ms(),
+        chain_stall_block_times = status::stall_block_times(),
+        health_thresholds = ?status::health_thresholds(),
+        default_direction = ?blocks::lookup_defaults().direction,
+        default_inclusive = blocks::lookup_defaults().inclusive,
+        cursor_history_interval_secs = ?cursor_history.map(|h| h.interval().as_secs()),
+        cursor_history_retention_days = ?cursor_history
+            .and_then(|h| h.retention())
+            .map(|r| r.as_secs() / (24 * 60 * 60)),
+        range_max_window_secs = ?export::range_limits().max_window_secs,
+        range_max_rows = ?export::range_limits().max_rows,
+        index_snapshot_interval_secs = ?state.index_snapshots.as_ref().map(|s| s.interval().as_secs()),
+        backup_dir = ?backups.map(|b| b.dir().display().to_string()),
+        backup_interval_secs = ?backups.map(|b| b.interval().as_secs()),
+        backup_keep = ?backups.map(|b| b.keep()),
+        backup_max_bytes_per_sec = ?backups.and_then(|b| b.max_bytes_per_sec()),
+        idempotency_ttl_secs = idempotency.ttl().as_secs(),
+        demo_mode = demo.is_some(),
+        demo_rate_limit_per_min = ?demo.map(|d| d.requests_per_window()),
+        trusted_networks = ?trust.map(|t| t.networks().iter().map(ToString::to_string).collect::<Vec<_>>()),
+        internal_token = redacted(trust.is_some_and(|t| t.has_token())),
+        tenants = ?tenants,
+        tenant_store = ?state.tenants.as_ref().map(|t| t.store_kind()),
+        admin_token = redacted(state.admin_token.is_some()),
+        pagination_secret = redacted(pagination_secret),
+        "effective configuration"
+    );
+}
@@ -3,17 +3,88 @@
 //! Contains the embedded storage handle and the in-memory progress map.
 //! The progress map is populated from fjall on startup and updated by ingestion.
 
-use kizami_shared::storage::{ProgressMap, Storage};
+use std::sync::Arc;
+
+use kizami_shared::models::RecoveryReportResponse;
+use kizami_shared::scheduler::Scheduler;
+use kizami_shared::sqd::SqdHealth;
+use kizami_shared::storage::{ProgressMap, ReadStorage};
+
+use crate::cache::LookupCache;
+use crate::freshness::Freshness;
+use crate::index_snapshots::IndexSnapshots;
+use crate::lag_history::LagHistory;
+use crate::pagination::CursorSigner;
+use crate::slo::SloTracker;
+use crate::tenants::Tenants;
 
 /// Shared state passed to all axum handlers via `State<AppState>`.
 #[derive(Clone)]
 pub struct AppState {
-    /// Embedded fjall storage handle for block lookups and cursor reads.
-    /// Wraps two keyspaces: `blocks` (keyed by chain_id|timestamp|number) and
-    /// `cursors` (keyed by sqd_slug). Thread-safe via internal Arc.
-    pub storage: Storage,
+    /// Read-only handle to the embedded fjall storage, for block lookups and cursor
+    /// reads. Admin routes that change data take the write handle as an
+    /// `Extension<Storage>` instead. Thread-safe via internal Arc.
+    pub storage: ReadStorage,
     /// In-memory progress map: sqd_slug -> ChainProgress (cursor, head, updated_at).
     /// Populated from fjall on startup, updated by the ingestion loop on every batch.
     /// Head values are ephemeral (not persisted), cursor values mirror fjall state.
     pub progress: ProgressMap,
+    /// Bearer token required by `/v1/admin/*` routes (`ADMIN_TOKEN`).
+    /// `None` disables the admin API entirely.
+    pub admin_token: Option<Arc<str>>,
+    /// Rolling per-route latency windows, fed by the `track_latency` middleware.
+    pub slo: Arc<SloTracker>,
+    /// Cache of final block lookup answers with single-flight miss coalescing.
+    pub lookups: Arc<LookupCache>,
+    /// Signs and verifies keyset pagination cursors for list endpoints.
+    pub cursors: Arc<CursorSigner>,
+    /// Per-chain index files served by `/v1/chains/{id}/index`.
+    /// `None` when `INDEX_SNAPSHOT_INTERVAL_SECS` is unset.
+    pub index_snapshots: Option<Arc<IndexSnapshots>>,
+    /// What storage looked like at startup, served by `/v1/admin/recovery`.
+    pub recovery: Arc<RecoveryReportResponse>,
+    /// Tenant namespaces and their usage counters. `None` when `TENANTS` is unset.
+    pub tenants: Option<Arc<Tenants>>,
+    /// Per-minute ingestion lag samples for the admin dashboard.
+    pub lag_history: Arc<LagHistory>,
+    /// Per-dataset SQD request outcomes, shared with the ingestion client.
+    pub sqd_health: Arc<SqdHealth>,
+    /// Expected delay between a block's timestamp and it being queryable, per chain.
+    pub freshness: Arc<Freshness>,
+    /// Background jobs, including ingestion, for `/v1/admin/jobs`.
+    pub jobs: Scheduler,
+}
+
+#[cfg(test)]
+impl AppState {
+    /// State over `storage` with an empty progress map, a one-minute lookup cache and
+    /// every optional feature off, for route tests. Tests that need more set the fields
+    /// they care about.
+    pub fn for_tests(storage: &kizami_shared::storage::Storage) -> Self {
+        use std::collections::HashMap;
+        use std::time::Duration;
+
+        use tokio::sync::RwLock;
+
+        Self {
+            storage: storage.reader(),
+            progress: Arc::new(RwLock::new(HashMap::new())),
+            admin_token: None,
+            slo: Arc::new(SloTracker::new(50.0)),
+            lookups: Arc::new(LookupCache::new(
+                Duration::from_secs(60),
+                Duration::from_secs(60),
+                0,
+                1000,
+            )),
+            cursors: Arc::new(CursorSigner::new(b"test")),
+            index_snapshots: None,
+            recovery: Default::default(),
+            tenants: None,
+            lag_history: Default::default(),
+            sqd_health: Default::default(),
+            freshness: Default::default(),
+            jobs: Default::default(),
+        }
+    }
 }
@@ -0,0 +1,532 @@
+//! Multi-tenant namespaces.
+//!
+//! When `TENANTS` is set, each tenant listed there gets a namespace `/t/{tenant}/v1/...`
+//! that serves the same routes and block index as `/v1/...`. Requests in a namespace
+//! must carry the tenant's API key (`Authorization: Bearer <key>` or `X-Api-Key`),
+//! count against that tenant's own per-minute quota, and are metered per tenant.
+//! Usage is reported at `/v1/admin/tenants` and on `/metrics`. The plain `/v1` routes
+//! are unaffected, and admin routes are never reachable through a namespace.
+//!
+//! `TENANTS` is a comma-separated list of `name:api_key` or `name:api_key:per_minute`
+//! entries, e.g. `analytics:s3cret:600,billing:t0ken`. Entries without a quota use
+//! `TENANT_RATE_LIMIT_PER_MIN`.
+//!
+//! Keys and quota windows are kept in a [`TenantStore`]: in the process by default, or
+//! in Redis with `TENANT_STORE_URL`, so replicas share quotas and keys (see [`store`]).
+//!
+//! The namespace is stripped before the API's routes are matched, so this middleware
+//! wraps the whole app as a fallback service rather than layering its routes.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderName, StatusCode, Uri};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+use kizami_shared::auth::constant_time_eq;
+use kizami_shared::error::AppError;
+use kizami_shared::models::TenantUsageResponse;
+use kizami_shared::redis::RedisClient;
+
+pub mod store;
+
+use store::{LocalStore, Quota, RedisStore, TenantStore};
+
+/// Prefix of namespaced paths: `/t/{tenant}/...`.
+const NAMESPACE_PREFIX: &str = "/t/";
+
+/// Default requests allowed per tenant per window.
+const DEFAULT_REQUESTS_PER_WINDOW: u32 = 600;
+
+/// Length of a quota window.
+const WINDOW: Duration = Duration::from_secs(60);
+
+static X_API_KEY: HeaderName = HeaderName::from_static("x-api-key");
+static X_RATELIMIT_LIMIT: HeaderName = HeaderName::from_static("x-ratelimit-limit");
+static X_RATELIMIT_REMAINING: HeaderName = HeaderName::from_static("x-ratelimit-remaining");
+
+#[derive(Default)]
+struct Usage {
+    requests: AtomicU64,
+    rate_limited: AtomicU64,
+    client_errors: AtomicU64,
+    server_errors: AtomicU64,
+}
+
+struct Tenant {
+    requests_per_window: u32,
+    usage: Usage,
+}
+
+/// Configured tenants, keyed by namespace name.
+pub struct Tenants {
+    tenants: BTreeMap<String, Tenant>,
+    /// Keys from `TENANTS` and this process's windows. Used without a shared store, and
+    /// whenever it fails.
+    local: LocalStore,
+    shared: Option<Box<dyn TenantStore>>,
+}
+
+impl Tenants {
+    /// Builds tenants from `(name, api_key, requests_per_minute)` entries, with keys
+    /// and quota windows kept in the process.
+    pub fn new(entries: impl IntoIterator<Item = (String, String, u32)>) -> Self {
+        let mut keys = std::collections::HashMap::new();
+        let tenants = entries
+            .into_iter()
+            .map(|(name, api_key, requests_per_window)| {
+                keys.insert(name.clone(), api_key);
+                let tenant = Tenant {
+                    requests_per_window,
+                    usage: Usage::default(),
+                };
+                (name, tenant)
+            })
+            .collect();
+        Self {
+            tenants,
+            local: LocalStore::new(keys),
+            shared: None,
+        }
+    }
+
+    /// Keeps keys and quota windows in `store`, shared with other replicas.
+    pub fn with_store(mut self, store: Box<dyn TenantStore>) -> Self {
+        self.shared = Some(store);
+        self
+    }
+
+    /// Reads `TENANTS`, `TENANT_RATE_LIMIT_PER_MIN` and `TENANT_STORE_URL`. Returns
+    /// `None` (namespaces disabled) when no valid tenant is configured. An invalid
+    /// store URL is logged and leaves the store local.
+    pub fn from_env() -> Option<Self> {
+        let default_limit = std::env::var("TENANT_RATE_LIMIT_PER_MIN")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_REQUESTS_PER_WINDOW);
+        let mut tenants = Self::new(parse_tenants(
+            &std::env::var("TENANTS").unwrap_or_default(),
+            default_limit,
+        ));
+        if let Some(url) = std::env::var("TENANT_STORE_URL")
+            .ok()
+            .filter(|v| !v.is_empty())
+        {
+            match RedisClient::from_url(&url) {
+                Ok(client) => tenants = tenants.with_store(Box::new(RedisStore::new(client))),
+                Err(e) => {
+                    tracing::warn!(error = %e, "ignoring invalid TENANT_STORE_URL, keeping tenant state local")
+                }
+            }
+        }
+        (!tenants.tenants.is_empty()).then_some(tenants)
+    }
+
+    /// Where keys and quota windows are kept: "local" or the shared store's kind.
+    pub fn store_kind(&self) -> &'static str {
+        self.shared.as_ref().map_or(self.local.kind(), |s| s.kind())
+    }
+
+    /// The tenant's key: the shared store's if it has one, else the configured one.
+    async fn api_key(&self, tenant: &str) -> Option<String> {
+        if let Some(shared) = &self.shared {
+            match shared.api_key(tenant).await {
+                Ok(Some(key)) => return Some(key),
+                Ok(None) => {}
+                Err(e) => tracing::warn!(
+                    tenant,
+                    store = shared.kind(),
+                    error = %e,
+                    "tenant store unavailable, using the configured key"
+                ),
+            }
+        }
+        self.local.key(tenant).map(str::to_string)
+    }
+
+    /// Counts a request against the tenant's quota, in this process's window if the
+    /// shared store fails.
+    async fn acquire(&self, tenant: &str, limit: u32) -> Quota {
+        if let Some(shared) = &self.shared {
+            match shared.acquire(tenant, limit).await {
+                Ok(quota) => return quota,
+                Err(e) => tracing::warn!(
+                    tenant,
+                    store = shared.kind(),
+                    error = %e,
+                    "tenant store unavailable, counting the quota locally"
+                ),
+            }
+        }
+        self.local.acquire_now(tenant, limit)
+    }
+
+    /// Usage counters per tenant, in name order.
+    pub fn usage(&self) -> Vec<TenantUsageResponse> {
+        self.tenants
+            .iter()
+            .map(|(name, t)| TenantUsageResponse {
+                tenant: name.clone(),
+                requests_per_minute: t.requests_per_window,
+                requests: t.usage.requests.load(Ordering::Relaxed),
+                rate_limited: t.usage.rate_limited.load(Ordering::Relaxed),
+                client_errors: t.usage.client_errors.load(Ordering::Relaxed),
+                server_errors: t.usage.server_errors.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+
+    /// Prometheus text exposition of the usage counters.
+    pub fn render_prometheus(&self, openmetrics: bool) -> String {
+        // OpenMetrics names the counter family without the `_total` sample suffix
+        let family = if openmetrics {
+            "kizami_tenant_requests"
+        } else {
+            "kizami_tenant_requests_total"
+        };
+        let mut out = format!(
+            "# HELP {family} Requests per tenant namespace by outcome.\n\
+             # TYPE {family} counter\n"
+        );
+        for u in self.usage() {
+            // the counters are loaded one by one, so a request finishing in between can
+            // leave the outcomes briefly ahead of `requests`
+            let ok = u
+                .requests
+                .saturating_sub(u.rate_limited)
+                .saturating_sub(u.client_errors)
+                .saturating_sub(u.server_errors);
+            for (outcome, count) in [
+                ("ok", ok),
+                ("rate_limited", u.rate_limited),
+                ("client_error", u.client_errors),
+                ("server_error", u.server_errors),
+            ] {
+                let _ = writeln!(
+                    out,
+                    "kizami_tenant_requests_total{{tenant=\"{}\",outcome=\"{outcome}\"}} {count}",
+                    u.tenant
+                );
+            }
+        }
+        out
+    }
+}
+
+/// Parses `name:key[:per_minute]` entries separated by commas. Malformed entries and
+/// duplicate names are skipped with a warning; names must be URL-safe.
+fn parse_tenants(raw: &str, default_limit: u32) -> Vec<(String, String, u32)> {
+    let mut tenants: Vec<(String, String, u32)> = Vec::new();
+    for entry in raw.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let mut parts = entry.split(':').map(str::trim);
+        let (name, key, limit) = (parts.next(), parts.next(), parts.next());
+        let limit = match limit {
+            None => Some(default_limit),
+            Some(v) => v.parse().ok(),
+        };
+        match (name, key, limit, parts.next()) {
+            (Some(name), Some(key), Some(limit), None)
+                if is_valid_name(name)
+                    && !key.is_empty()
+                    && !tenants.iter().any(|(n, _, _)| n == name) =>
+            {
+                tenants.push((name.to_string(), key.to_string(), limit));
+            }
+            _ => {
+                let name = entry.split(':').next().unwrap_or_default();
+                tracing::warn!(tenant = name, "ignoring invalid TENANTS entry");
+            }
+        }
+    }
+    tenants
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+/// The API key presented with a request, from `Authorization: Bearer` or `X-Api-Key`.
+fn presented_key(req: &Request) -> Option<&str> {
+    let headers = req.headers();
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .or_else(|| headers.get(&X_API_KEY).and_then(|v| v.to_str().ok()))
+}
+
+/// Splits `/t/{tenant}/rest?query` into the tenant and the un-namespaced URI.
+fn strip_namespace(uri: &Uri) -> Option<(&str, Uri)> {
+    let rest = uri.path().strip_prefix(NAMESPACE_PREFIX)?;
+    let (tenant, path) = rest.split_at(rest.find('/')?);
+    let path_and_query = match uri.query() {
+        Some(q) => format!("{path}?{q}"),
+        None => path.to_string(),
+    };
+    Some((tenant, path_and_query.parse().ok()?))
+}
+
+/// Middleware serving tenant namespaces. Requests outside `/t/` pass through untouched.
+pub async fn tenant_namespace(
+    State(tenants): State<Arc<Tenants>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let Some((name, uri)) = strip_namespace(req.uri()) else {
+        if req.uri().path().starts_with(NAMESPACE_PREFIX) {
+            return StatusCode::NOT_FOUND.into_response();
+        }
+        return next.run(req).await;
+    };
+
+    // unknown tenants get the same answer as a wrong key, so names can't be probed
+    let Some(tenant) = tenants.tenants.get(name) else {
+        return AppError::InvalidApiKey.into_response();
+    };
+    let api_key = tenants.api_key(name).await;
+    let valid = match (presented_key(&req), api_key) {
+        (Some(presented), Some(key)) => constant_time_eq(presented.as_bytes(), key.as_bytes()),
+        _ => false,
+    };
+    if !valid {
+        return AppError::InvalidApiKey.into_response();
+    }
+    if !uri.path().starts_with("/v1/") {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    if uri.path().starts_with("/v1/admin") {
+        return AppError::AdminDisabled.into_response();
+    }
+
+    let usage = &tenant.usage;
+    usage.requests.fetch_add(1, Ordering::Relaxed);
+    let remaining = match tenants.acquire(name, tenant.requests_per_window).await {
+        Quota::Allowed { remaining } => remaining,
+        Quota::Exhausted { retry_after_secs } => {
+            usage.rate_limited.fetch_add(1, Ordering::Relaxed);
+            return AppError::RateLimited { retry_after_secs }.into_response();
+        }
+    };
+
+    *req.uri_mut() = uri;
+    let mut response = next.run(req).await;
+    if response.status().is_client_error() {
+        usage.client_errors.fetch_add(1, Ordering::Relaxed);
+    } else if response.status().is_server_error() {
+        usage.server_errors.fetch_add(1, Ordering::Relaxed);
+    }
+    let headers = response.headers_mut();
+    headers.insert(X_RATELIMIT_LIMIT.clone(), tenant.requests_per_window.into());
+    headers.insert(X_RATELIMIT_REMAINING.clone(), remaining.into());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::routing::get;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn app(tenants: Arc<Tenants>) -> Router {
+        let inner = Router::new()
+            .route("/v1/chains", get(|| async { "[]" }))
+            .route("/v1/admin/slo", get(|| async { "{}" }));
+        Router::new()
+            .fallback_service(inner)
+            .layer(axum::middleware::from_fn_with_state(
+                tenants,
+                tenant_namespace,
+            ))
+    }
+
+    fn tenants() -> Arc<Tenants> {
+        Arc::new(Tenants::new([
+            ("alpha".into(), "key-a".into(), 2),
+            ("beta".into(), "key-b".into(), 10),
+        ]))
+    }
+
+    async fn send(app: &Router, uri: &str, key: Option<&str>) -> Response {
+        let mut req = axum::http::Request::get(uri);
+        if let Some(key) = key {
+            req = req.header("x-api-key", key);
+        }
+        app.clone()
+            .oneshot(req.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn namespaced_requests_need_the_tenants_key() {
+        let tenants = tenants();
+        let app = app(tenants.clone());
+
+        let ok = send(&app, "/t/alpha/v1/chains?x=1", Some("key-a")).await;
+        assert_eq!(ok.status(), StatusCode::OK);
+        assert_eq!(ok.headers()["x-ratelimit-remaining"], "1");
+
+        for (uri, key) in [
+            ("/t/alpha/v1/chains", None),
+            ("/t/alpha/v1/chains", Some("key-b")),
+            ("/t/nobody/v1/chains", Some("key-a")),
+        ] {
+            assert_eq!(
+                send(&app, uri, key).await.status(),
+                StatusCode::UNAUTHORIZED
+            );
+        }
+        // the shared routes stay open
+        assert_eq!(
+            send(&app, "/v1/chains", None).await.status(),
+            StatusCode::OK
+        );
+        assert_eq!(
+            send(&app, "/t/alpha/v1/admin/slo", Some("key-a"))
+                .await
+                .status(),
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    #[tokio::test]
+    async fn quotas_and_usage_are_per_tenant() {
+        let tenants = tenants();
+        let app = app(tenants.clone());
+        for _ in 0..2 {
+            send(&app, "/t/alpha/v1/chains", Some("key-a")).await;
+        }
+        let limited = send(&app, "/t/alpha/v1/chains", Some("key-a")).await;
+        assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(
+            send(&app, "/t/beta/v1/chains", Some("key-b"))
+                .await
+                .status(),
+            StatusCode::OK
+        );
+        send(&app, "/t/beta/v1/missing", Some("key-b")).await;
+
+        let usage = tenants.usage();
+        assert_eq!(
+            (
+                usage[0].tenant.as_str(),
+                usage[0].requests,
+                usage[0].rate_limited
+            ),
+            ("alpha", 3, 1)
+        );
+        assert_eq!((usage[1].requests, usage[1].client_errors), (2, 1));
+        assert!(tenants
+            .render_prometheus(false)
+            .contains("kizami_tenant_requests_total{tenant=\"beta\",outcome=\"client_error\"} 1"));
+    }
+
+    /// A shared store in memory: clones see the same keys and counts, like replicas
+    /// pointed at one Redis. `None` counts make every call fail.
+    #[derive(Clone)]
+    struct SharedStore {
+        keys: std::collections::HashMap<String, String>,
+        counts: Option<Arc<std::sync::Mutex<u32>>>,
+    }
+
+    impl TenantStore for SharedStore {
+        fn kind(&self) -> &'static str {
+            "shared"
+        }
+
+        fn api_key<'a>(
+            &'a self,
+            tenant: &'a str,
+        ) -> futures_util::future::BoxFuture<'a, Result<Option<String>, AppError>> {
+            Box::pin(async move {
+                match self.counts {
+                    Some(_) => Ok(self.keys.get(tenant).cloned()),
+                    None => Err(AppError::Redis("down".into())),
+                }
+            })
+        }
+
+        fn acquire<'a>(
+            &'a self,
+            _tenant: &'a str,
+            limit: u32,
+        ) -> futures_util::future::BoxFuture<'a, Result<Quota, AppError>> {
+            Box::pin(async move {
+                let counts = self.counts.as_ref().ok_or(AppError::Redis("down".into()))?;
+                let mut count = counts.lock().unwrap();
+                *count += 1;
+                Ok(match limit.checked_sub(*count) {
+                    Some(remaining) => Quota::Allowed { remaining },
+                    None => Quota::Exhausted {
+                        retry_after_secs: 1,
+                    },
+                })
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn replicas_share_keys_and_quotas_through_a_store() {
+        let store = SharedStore {
+            keys: [("alpha".to_string(), "rotated".to_string())].into(),
+            counts: Some(Arc::default()),
+        };
+        let replicas: Vec<_> = (0..2)
+            .map(|_| {
+                let tenants = Tenants::new([("alpha".into(), "key-a".into(), 2)])
+                    .with_store(Box::new(store.clone()));
+                assert_eq!(tenants.store_kind(), "shared");
+                app(Arc::new(tenants))
+            })
+            .collect();
+
+        // the store's key replaces the configured one
+        let stale = send(&replicas[0], "/t/alpha/v1/chains", Some("key-a")).await;
+        assert_eq!(stale.status(), StatusCode::UNAUTHORIZED);
+        for replica in &replicas {
+            let ok = send(replica, "/t/alpha/v1/chains", Some("rotated")).await;
+            assert_eq!(ok.status(), StatusCode::OK);
+        }
+        let limited = send(&replicas[0], "/t/alpha/v1/chains", Some("rotated")).await;
+        assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[tokio::test]
+    async fn a_failing_store_falls_back_to_local_state() {
+        let store = SharedStore {
+            keys: Default::default(),
+            counts: None,
+        };
+        let tenants = Tenants::new([("alpha".into(), "key-a".into(), 2)]);
+        let app = app(Arc::new(tenants.with_store(Box::new(store))));
+        let ok = send(&app, "/t/alpha/v1/chains", Some("key-a")).await;
+        assert_eq!(ok.status(), StatusCode::OK);
+        assert_eq!(ok.headers()["x-ratelimit-remaining"], "1");
+        send(&app, "/t/alpha/v1/chains", Some("key-a")).await;
+        let limited = send(&app, "/t/alpha/v1/chains", Some("key-a")).await;
+        assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[test]
+    fn parses_tenants_and_skips_bad_entries() {
+        let parsed = parse_tenants("a:k1, b:k2:30, a:dup, bad name:k, c:, d:k:x, e:k:1:2", 600);
+        assert_eq!(
+            parsed,
+            vec![
+                ("a".to_string(), "k1".to_string(), 600),
+                ("b".to_string(), "k2".to_string(), 30),
+            ]
+        );
+    }
+}
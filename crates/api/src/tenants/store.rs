@@ -0,0 +1,192 @@
+//! Where tenant API keys and quota windows live.
+//!
+//! By default both stay in the process ([`LocalStore`]): keys come from `TENANTS` and
+//! each replica counts its own windows, so N replicas together let a tenant through N
+//! times its quota. With `TENANT_STORE_URL` set, a [`RedisStore`] shares them: every
+//! replica counts into the same per-minute window, and a key set in Redis replaces the
+//! one in `TENANTS`, so it can be rotated everywhere at once.
+//!
+//! Another backend only has to implement [`TenantStore`]. Failures fall back to the
+//! local store (see [`Tenants`](super::Tenants)), so an outage of the shared store
+//! loosens quotas instead of failing tenant requests.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use futures_util::future::BoxFuture;
+use moka::future::Cache;
+
+use kizami_shared::clock;
+use kizami_shared::error::AppError;
+use kizami_shared::redis::RedisClient;
+
+use super::WINDOW;
+
+/// How long a key read from Redis is used before it is read again.
+const KEY_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Prefix of every Redis key the store writes.
+const REDIS_PREFIX: &str = "kizami:tenants:";
+
+/// Outcome of counting a request against a tenant's quota.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quota {
+    Allowed { remaining: u32 },
+    Exhausted { retry_after_secs: u64 },
+}
+
+/// Storage for tenant keys and quota counters.
+pub trait TenantStore: Send + Sync {
+    /// Short name for logs, e.g. "redis".
+    fn kind(&self) -> &'static str;
+
+    /// The tenant's API key, or `None` if the store has none for it.
+    fn api_key<'a>(&'a self, tenant: &'a str) -> BoxFuture<'a, Result<Option<String>, AppError>>;
+
+    /// Counts a request in the tenant's current window of `limit` requests.
+    fn acquire<'a>(&'a self, tenant: &'a str, limit: u32)
+        -> BoxFuture<'a, Result<Quota, AppError>>;
+}
+
+/// Request count within the current fixed window.
+struct Window {
+    started: Instant,
+    count: u32,
+}
+
+/// Keys from `TENANTS` and quota windows in this process.
+pub struct LocalStore {
+    keys: HashMap<String, String>,
+    windows: Mutex<HashMap<String, Window>>,
+}
+
+impl LocalStore {
+    /// Holds the given `tenant -> key` pairs.
+    pub fn new(keys: HashMap<String, String>) -> Self {
+        Self {
+            keys,
+            windows: Mutex::default(),
+        }
+    }
+
+    pub(super) fn key(&self, tenant: &str) -> Option<&str> {
+        self.keys.get(tenant).map(String::as_str)
+    }
+
+    pub(super) fn acquire_now(&self, tenant: &str, limit: u32) -> Quota {
+        let mut windows = self.windows.lock().unwrap();
+        let window = windows.entry(tenant.to_string()).or_insert(Window {
+            started: Instant::now(),
+            count: 0,
+        });
+        let elapsed = window.started.elapsed();
+        if elapsed >= WINDOW {
+            window.started = Instant::now();
+            window.count = 0;
+        }
+        if window.count >= limit {
+            return Quota::Exhausted {
+                retry_after_secs: (WINDOW - elapsed.min(WINDOW)).as_secs().max(1),
+            };
+        }
+        window.count += 1;
+        Quota::Allowed {
+            remaining: limit - window.count,
+        }
+    }
+}
+
+impl TenantStore for LocalStore {
+    fn kind(&self) -> &'static str {
+        "local"
+    }
+
+    fn api_key<'a>(&'a self, tenant: &'a str) -> BoxFuture<'a, Result<Option<String>, AppError>> {
+        Box::pin(async move { Ok(self.key(tenant).map(str::to_string)) })
+    }
+
+    fn acquire<'a>(
+        &'a self,
+        tenant: &'a str,
+        limit: u32,
+    ) -> BoxFuture<'a, Result<Quota, AppError>> {
+        Box::pin(async move { Ok(self.acquire_now(tenant, limit)) })
+    }
+}
+
+/// Keys and quota windows in Redis, shared by every replica pointed at it.
+///
+/// Keys are read from the `kizami:tenants:keys` hash (field = tenant name) and cached
+/// for [`KEY_CACHE_TTL`]. Each window is a counter `kizami:tenants:quota:{tenant}:{n}`,
+/// where `n` counts minutes since the Unix epoch, incremented per request and expiring
+/// a window after it ends. Windows follow the clock, not the tenant's first request.
+pub struct RedisStore {
+    client: RedisClient,
+    keys: Cache<String, Option<String>>,
+}
+
+impl RedisStore {
+    pub fn new(client: RedisClient) -> Self {
+        Self {
+            client,
+            keys: Cache::builder().time_to_live(KEY_CACHE_TTL).build(),
+        }
+    }
+}
+
+impl TenantStore for RedisStore {
+    fn kind(&self) -> &'static str {
+        "redis"
+    }
+
+    fn api_key<'a>(&'a self, tenant: &'a str) -> BoxFuture<'a, Result<Option<String>, AppError>> {
+        Box::pin(async move {
+            if let Some(key) = self.keys.get(tenant).await {
+                return Ok(key);
+            }
+            let hash = format!("{REDIS_PREFIX}keys");
+            let key = self
+                .client
+                .query(&[b"HGET", hash.as_bytes(), tenant.as_bytes()])
+                .await?
+                .into_string()
+                .filter(|k| !k.is_empty());
+            self.keys.insert(tenant.to_string(), key.clone()).await;
+            Ok(key)
+        })
+    }
+
+    fn acquire<'a>(
+        &'a self,
+        tenant: &'a str,
+        limit: u32,
+    ) -> BoxFuture<'a, Result<Quota, AppError>> {
+        Box::pin(async move {
+            let window_secs = WINDOW.as_secs() as i64;
+            let now = clock::now().timestamp();
+            let counter = format!("{REDIS_PREFIX}quota:{tenant}:{}", now / window_secs);
+            let ttl = (2 * window_secs).to_string();
+            let replies = self
+                .client
+                .pipeline(&[
+                    &[b"INCR", counter.as_bytes()],
+                    &[b"EXPIRE", counter.as_bytes(), ttl.as_bytes()],
+                ])
+                .await?;
+            let count = replies
+                .first()
+                .and_then(|r| r.as_int())
+                .ok_or_else(|| AppError::Redis(format!("INCR {counter} returned no count")))?;
+            Ok(if count > limit as i64 {
+                Quota::Exhausted {
+                    retry_after_secs: (window_secs - now % window_secs).max(1) as u64,
+                }
+            } else {
+                Quota::Allowed {
+                    remaining: (limit as i64 - count) as u32,
+                }
+            })
+        })
+    }
+}
@@ -0,0 +1,396 @@
+//! TLS termination and client certificate (mTLS) authentication.
+//!
+//! Off unless `TLS_CERT_FILE` and `TLS_KEY_FILE` (PEM) are set; the server then only
+//! speaks HTTPS. With `TLS_CLIENT_CA_FILE` as well, clients authenticate with
+//! certificates issued by one of the CAs in that file, for deployments that can't hand
+//! out API keys. `CLIENT_CERT_MODE` says where a certificate is needed:
+//!
+//! - `required` (default): on every connection. The handshake fails without one.
+//! - `admin`: only on admin routes, where a verified certificate whose SHA-256
+//!   fingerprint is listed in `ADMIN_CLIENT_CERTS` replaces `ADMIN_TOKEN`. Public
+//!   routes accept clients without one.
+//!
+//! A partial setup (only one of `TLS_CERT_FILE`/`TLS_KEY_FILE`, a client CA without
+//! them, or `admin` mode without an allow-list) fails startup rather than falling back
+//! to plain HTTP or an open admin API.
+//!
+//! Requests on a connection with a verified certificate carry its identity as a
+//! [`ClientCert`] extension, so handlers can attribute what they do. Admin requests
+//! authenticated by certificate are logged with it.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+use ring::digest;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::RootCertStore;
+use tokio_rustls::TlsAcceptor;
+
+use kizami_shared::error::AppError;
+
+/// Where client certificates are required.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientCertMode {
+    Required,
+    Admin,
+}
+
+/// Identity of a verified client certificate. Request extension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientCert {
+    /// Subject distinguished name, e.g. `CN=batch-jobs, O=Acme`.
+    pub subject: String,
+    /// Hex SHA-256 of the DER certificate; changes when the certificate is reissued.
+    pub fingerprint: String,
+}
+
+impl ClientCert {
+    pub fn from_der(der: &[u8]) -> Self {
+        let subject = x509_parser::parse_x509_certificate(der)
+            .map(|(_, cert)| cert.subject().to_string())
+            .unwrap_or_default();
+        let fingerprint = digest::digest(&digest::SHA256, der)
+            .as_ref()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        Self {
+            subject,
+            fingerprint,
+        }
+    }
+}
+
+/// TLS settings, from `TLS_CERT_FILE`, `TLS_KEY_FILE`, `TLS_CLIENT_CA_FILE`,
+/// `CLIENT_CERT_MODE` and `ADMIN_CLIENT_CERTS`.
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    cert_file: PathBuf,
+    key_file: PathBuf,
+    client_ca_file: Option<PathBuf>,
+    mode: ClientCertMode,
+    admin_certs: Arc<[String]>,
+}
+
+impl TlsConfig {
+    pub fn new(
+        cert_file: impl Into<PathBuf>,
+        key_file: impl Into<PathBuf>,
+        client_ca_file: Option<PathBuf>,
+        mode: ClientCertMode,
+    ) -> Self {
+        Self {
+            cert_file: cert_file.into(),
+            key_file: key_file.into(),
+            client_ca_file,
+            mode,
+            admin_certs: Arc::new([]),
+        }
+    }
+
+    /// Sets the SHA-256 fingerprints of the client certificates allowed on admin
+    /// routes in `admin` mode. Hex, case and `:` separators don't matter.
+    pub fn with_admin_certs<I, S>(mut self, fingerprints: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.admin_certs = fingerprints
+            .into_iter()
+            .map(|f| f.as_ref().trim().replace(':', "").to_ascii_lowercase())
+            .filter(|f| !f.is_empty())
+            .collect();
+        self
+    }
+
+    /// Returns `Ok(None)` (plain HTTP) when none of the TLS files are set. Fails on a
+    /// partial setup: only one of the certificate and key, a client CA without them,
+    /// or `admin` mode with no `ADMIN_CLIENT_CERTS`. An unknown `CLIENT_CERT_MODE`
+    /// falls back to `required`.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Option<Self>, String> {
+        let var = |name| var(name).filter(|v| !v.is_empty());
+        let mode = match var("CLIENT_CERT_MODE").as_deref() {
+            Some("admin") => ClientCertMode::Admin,
+            None | Some("required") => ClientCertMode::Required,
+            Some(other) => {
+                tracing::warn!(mode = other, "unknown CLIENT_CERT_MODE, using required");
+                ClientCertMode::Required
+            }
+        };
+        let client_ca_file = var("TLS_CLIENT_CA_FILE").map(PathBuf::from);
+        let (cert_file, key_file) = match (var("TLS_CERT_FILE"), var("TLS_KEY_FILE")) {
+            (Some(cert), Some(key)) => (cert, key),
+            (None, None) if client_ca_file.is_none() => return Ok(None),
+            (None, None) => {
+                return Err("TLS_CLIENT_CA_FILE needs TLS_CERT_FILE and TLS_KEY_FILE".into())
+            }
+            (Some(_), None) => return Err("TLS_CERT_FILE is set without TLS_KEY_FILE".into()),
+            (None, Some(_)) => return Err("TLS_KEY_FILE is set without TLS_CERT_FILE".into()),
+        };
+        let config = Self::new(cert_file, key_file, client_ca_file, mode)
+            .with_admin_certs(var("ADMIN_CLIENT_CERTS").unwrap_or_default().split(','));
+        if config.client_cert_mode() == Some(ClientCertMode::Admin) && config.admin_certs.is_empty()
+        {
+            return Err("CLIENT_CERT_MODE=admin needs ADMIN_CLIENT_CERTS".into());
+        }
+        Ok(Some(config))
+    }
+
+    /// Fingerprints of the client certificates allowed on admin routes, for
+    /// [`require_client_cert`].
+    pub fn admin_certs(&self) -> Arc<[String]> {
+        self.admin_certs.clone()
+    }
+
+    /// Where client certificates are required; `None` when they aren't checked at all.
+    pub fn client_cert_mode(&self) -> Option<ClientCertMode> {
+        self.client_ca_file.as_ref().map(|_| self.mode)
+    }
+
+    /// Loads the certificates and key. `http2` also offers `h2` through ALPN.
+    pub fn acceptor(&self, http2: bool) -> Result<TlsAcceptor, String> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let chain = CertificateDer::pem_file_iter(&self.cert_file)
+            .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| format!("{}: {e}", self.cert_file.display()))?;
+        let key = PrivateKeyDer::from_pem_file(&self.key_file)
+            .map_err(|e| format!("{}: {e}", self.key_file.display()))?;
+
+        let builder = rustls::ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(|e| e.to_string())?;
+        let builder = match &self.client_ca_file {
+            Some(path) => {
+                let mut roots = RootCertStore::empty();
+                for cert in CertificateDer::pem_file_iter(path)
+                    .map_err(|e| format!("{}: {e}", path.display()))?
+                {
+                    let cert = cert.map_err(|e| format!("{}: {e}", path.display()))?;
+                    roots
+                        .add(cert)
+                        .map_err(|e| format!("{}: {e}", path.display()))?;
+                }
+                let verifier = WebPkiClientVerifier::builder_with_provider(roots.into(), provider);
+                let verifier = match self.mode {
+                    ClientCertMode::Required => verifier,
+                    ClientCertMode::Admin => verifier.allow_unauthenticated(),
+                };
+                builder.with_client_cert_verifier(verifier.build().map_err(|e| e.to_string())?)
+            }
+            None => builder.with_no_client_auth(),
+        };
+        let mut config = builder
+            .with_single_cert(chain, key)
+            .map_err(|e| e.to_string())?;
+        config.alpn_protocols = if http2 {
+            vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+        } else {
+            vec![b"http/1.1".to_vec()]
+        };
+        Ok(TlsAcceptor::from(Arc::new(config)))
+    }
+}
+
+/// Middleware guarding admin routes with a client certificate whose fingerprint is in
+/// `allowed`, in place of [`crate::routes::admin::require_admin`] in `admin` mode.
+pub async fn require_client_cert(
+    State(allowed): State<Arc<[String]>>,
+    req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let cert = req
+        .extensions()
+        .get::<ClientCert>()
+        .ok_or(AppError::Unauthorized)?;
+    if !allowed.contains(&cert.fingerprint) {
+        tracing::warn!(
+            method = %req.method(),
+            path = req.uri().path(),
+            client_subject = %cert.subject,
+            client_fingerprint = %cert.fingerprint,
+            "admin request with a certificate not in ADMIN_CLIENT_CERTS"
+        );
+        return Err(AppError::Unauthorized);
+    }
+    tracing::info!(
+        method = %req.method(),
+        path = req.uri().path(),
+        client_subject = %cert.subject,
+        client_fingerprint = %cert.fingerprint,
+        "admin request"
+    );
+    Ok(next.run(req).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::routing::get;
+    use axum::{Extension, Router};
+    use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, KeyPair};
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::server::{self, ServerConfig};
+
+    /// A CA, a server certificate for `localhost` and two client certificates, all
+    /// PEM, with the SHA-256 fingerprint of the first.
+    struct Pki {
+        dir: tempfile::TempDir,
+        ca: String,
+        client: String,
+        client_fingerprint: String,
+        other_client: String,
+    }
+
+    fn pki() -> Pki {
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca_params = CertificateParams::new(Vec::new()).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = ca_params.self_signed(&ca_key).unwrap();
+
+        let server_key = KeyPair::generate().unwrap();
+        let server = CertificateParams::new(vec!["localhost".into()])
+            .unwrap()
+            .signed_by(&server_key, &ca, &ca_key)
+            .unwrap();
+        let client_cert = |name: &str| {
+            let key = KeyPair::generate().unwrap();
+            let mut params = CertificateParams::new(Vec::new()).unwrap();
+            params.distinguished_name.push(DnType::CommonName, name);
+            let cert = params.signed_by(&key, &ca, &ca_key).unwrap();
+            let fingerprint = ClientCert::from_der(cert.der()).fingerprint;
+            (
+                format!("{}{}", cert.pem(), key.serialize_pem()),
+                fingerprint,
+            )
+        };
+        let (client, client_fingerprint) = client_cert("batch-jobs");
+        let (other_client, _) = client_cert("someone-else");
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("ca.pem"), ca.pem()).unwrap();
+        std::fs::write(dir.path().join("server.pem"), server.pem()).unwrap();
+        std::fs::write(dir.path().join("server.key"), server_key.serialize_pem()).unwrap();
+        Pki {
+            dir,
+            ca: ca.pem(),
+            client,
+            client_fingerprint,
+            other_client,
+        }
+    }
+
+    fn client(pki: &Pki, identity: Option<&str>) -> reqwest::Client {
+        let mut builder = reqwest::Client::builder()
+            .use_rustls_tls()
+            .add_root_certificate(reqwest::Certificate::from_pem(pki.ca.as_bytes()).unwrap());
+        if let Some(pem) = identity {
+            builder = builder.identity(reqwest::Identity::from_pem(pem.as_bytes()).unwrap());
+        }
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn partial_tls_setup_is_rejected() {
+        let from = |vars: &[(&str, &str)]| {
+            let vars: Vec<(String, String)> = vars
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            TlsConfig::from_vars(|name| {
+                vars.iter().find(|(k, _)| k == name).map(|(_, v)| v.clone())
+            })
+        };
+        assert!(from(&[]).unwrap().is_none());
+        assert!(from(&[("TLS_CERT_FILE", "c.pem")]).is_err());
+        assert!(from(&[("TLS_KEY_FILE", "k.pem")]).is_err());
+        assert!(from(&[("TLS_CLIENT_CA_FILE", "ca.pem")]).is_err());
+
+        let full = [
+            ("TLS_CERT_FILE", "c.pem"),
+            ("TLS_KEY_FILE", "k.pem"),
+            ("TLS_CLIENT_CA_FILE", "ca.pem"),
+            ("CLIENT_CERT_MODE", "admin"),
+        ];
+        assert!(from(&full).is_err());
+        let config = from(&[&full[..], &[("ADMIN_CLIENT_CERTS", "AB:cd, ef01")]].concat())
+            .unwrap()
+            .unwrap();
+        assert_eq!(&*config.admin_certs(), ["abcd", "ef01"]);
+    }
+
+    #[tokio::test]
+    async fn client_certificates_identify_callers() {
+        let pki = pki();
+        for mode in [ClientCertMode::Required, ClientCertMode::Admin] {
+            let config = TlsConfig::new(
+                pki.dir.path().join("server.pem"),
+                pki.dir.path().join("server.key"),
+                Some(pki.dir.path().join("ca.pem")),
+                mode,
+            )
+            .with_admin_certs([&pki.client_fingerprint]);
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let app = Router::new()
+                .route(
+                    "/v1/admin/whoami",
+                    get(|Extension(cert): Extension<ClientCert>| async move { cert.subject }),
+                )
+                .layer(axum::middleware::from_fn_with_state(
+                    config.admin_certs(),
+                    require_client_cert,
+                ))
+                .route("/health", get(|| async { "ok" }));
+            let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+            let acceptor = config.acceptor(false).unwrap();
+            let server = tokio::spawn(server::serve(
+                listener,
+                app,
+                ServerConfig::default(),
+                Some(acceptor),
+                async {
+                    let _ = stopped.await;
+                },
+            ));
+
+            let url = |path| format!("https://localhost:{port}{path}");
+            let whoami = client(&pki, Some(&pki.client))
+                .get(url("/v1/admin/whoami"))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(whoami.text().await.unwrap(), "CN=batch-jobs");
+
+            let unlisted = client(&pki, Some(&pki.other_client))
+                .get(url("/v1/admin/whoami"))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(unlisted.status(), 401);
+
+            let anonymous = client(&pki, None).get(url("/health")).send().await;
+            let anonymous_admin = client(&pki, None).get(url("/v1/admin/whoami")).send().await;
+            match mode {
+                // the handshake itself fails
+                ClientCertMode::Required => assert!(anonymous.is_err()),
+                ClientCertMode::Admin => {
+                    assert_eq!(anonymous.unwrap().text().await.unwrap(), "ok");
+                    assert_eq!(anonymous_admin.unwrap().status(), 401);
+                }
+            }
+
+            stop.send(()).unwrap();
+            server.await.unwrap();
+        }
+    }
+}
@@ -0,0 +1,202 @@
+//! Trusted internal clients.
+//!
+//! Internal services (batch jobs, other backends) share the public per-IP quotas unless
+//! they are recognised as trusted. A request is trusted when either
+//!
+//! - it carries `X-Internal-Token` matching `INTERNAL_TOKEN`, or
+//! - its socket peer is in `TRUSTED_NETWORKS` (comma-separated CIDRs or bare IPs, e.g.
+//!   `10.0.0.0/8,fd00::/8,127.0.0.1`). When the request also has `X-Forwarded-For`,
+//!   the peer is a proxy and the last hop (the address the proxy saw) must be trusted
+//!   too, so an internal load balancer doesn't make all public traffic trusted. Hops a
+//!   client writes itself come before the proxy's and are ignored.
+//!
+//! Trusted requests get a [`Trusted`] extension and skip the demo mode quota. Tenant
+//! quotas still apply, since those are per-tenant agreements rather than abuse limits.
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::HeaderName;
+use axum::middleware::Next;
+use axum::response::Response;
+use ipnet::IpNet;
+
+use kizami_shared::auth::constant_time_eq;
+
+static X_INTERNAL_TOKEN: HeaderName = HeaderName::from_static("x-internal-token");
+
+/// Why a request was trusted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrustedBy {
+    Network,
+    Token,
+}
+
+/// Request extension marking a trusted request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Trusted(pub TrustedBy);
+
+/// Which clients are trusted.
+#[derive(Debug, Default)]
+pub struct TrustPolicy {
+    networks: Vec<IpNet>,
+    token: Option<Arc<str>>,
+}
+
+impl TrustPolicy {
+    pub fn new(networks: Vec<IpNet>, token: Option<&str>) -> Self {
+        Self {
+            networks,
+            token: token.filter(|t| !t.is_empty()).map(Arc::from),
+        }
+    }
+
+    /// Reads `TRUSTED_NETWORKS` and `INTERNAL_TOKEN`. Returns `None` (nothing trusted)
+    /// when neither is set.
+    pub fn from_env() -> Option<Self> {
+        let networks = std::env::var("TRUSTED_NETWORKS")
+            .map(|raw| parse_networks(&raw))
+            .unwrap_or_default();
+        let token = std::env::var("INTERNAL_TOKEN").ok();
+        let policy = Self::new(networks, token.as_deref());
+        (!policy.networks.is_empty() || policy.token.is_some()).then_some(policy)
+    }
+
+    pub fn networks(&self) -> &[IpNet] {
+        &self.networks
+    }
+
+    pub fn has_token(&self) -> bool {
+        self.token.is_some()
+    }
+
+    fn trusted_ip(&self, ip: IpAddr) -> bool {
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+            v4 => v4,
+        };
+        self.networks.iter().any(|net| net.contains(&ip))
+    }
+
+    /// Whether `req` is trusted, and why.
+    fn check(&self, req: &Request) -> Option<TrustedBy> {
+        if let Some(expected) = &self.token {
+            let provided = req.headers().get(&X_INTERNAL_TOKEN).map(|v| v.as_bytes());
+            if provided.is_some_and(|p| constant_time_eq(p, expected.as_bytes())) {
+                return Some(TrustedBy::Token);
+            }
+        }
+        let peer = req
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip())?;
+        if !self.trusted_ip(peer) {
+            return None;
+        }
+        let forwarded = req
+            .headers()
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .last();
+        match forwarded {
+            None => Some(TrustedBy::Network),
+            Some(hop) => hop
+                .trim()
+                .parse()
+                .ok()
+                .filter(|&ip| self.trusted_ip(ip))
+                .map(|_| TrustedBy::Network),
+        }
+    }
+}
+
+/// Parses comma-separated CIDRs; a bare address is a single-host network. Malformed
+/// entries are skipped with a warning.
+fn parse_networks(raw: &str) -> Vec<IpNet> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|e| !e.is_empty())
+        .filter_map(|entry| {
+            let net = entry
+                .parse::<IpNet>()
+                .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from));
+            match net {
+                Ok(net) => Some(net.trunc()),
+                Err(_) => {
+                    tracing::warn!(entry, "ignoring invalid TRUSTED_NETWORKS entry");
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Middleware adding [`Trusted`] to trusted requests. The internal token header is
+/// removed either way, so it never reaches handlers or logs.
+pub async fn mark_trusted(
+    State(policy): State<Arc<TrustPolicy>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let trusted = policy.check(&req);
+    req.headers_mut().remove(&X_INTERNAL_TOKEN);
+    if let Some(by) = trusted {
+        req.extensions_mut().insert(Trusted(by));
+    }
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+
+    use super::*;
+
+    fn request(peer: &str, forwarded_for: &[&str], token: Option<&str>) -> Request {
+        let mut builder = Request::builder().uri("/v1/chains");
+        for hop in forwarded_for {
+            builder = builder.header("x-forwarded-for", *hop);
+        }
+        if let Some(token) = token {
+            builder = builder.header("x-internal-token", token);
+        }
+        let mut req = builder.body(Body::empty()).unwrap();
+        let peer: SocketAddr = peer.parse().unwrap();
+        req.extensions_mut().insert(ConnectInfo(peer));
+        req
+    }
+
+    #[test]
+    fn trusts_internal_peers_unless_forwarding_public_traffic() {
+        let policy = TrustPolicy::new(parse_networks("10.0.0.0/8, 192.168.1.7, nonsense"), None);
+        assert_eq!(policy.networks().len(), 2);
+
+        let by = |peer, forwarded: &[&str]| policy.check(&request(peer, forwarded, None));
+        assert_eq!(by("10.1.2.3:5000", &[]), Some(TrustedBy::Network));
+        assert_eq!(by("192.168.1.7:5000", &[]), Some(TrustedBy::Network));
+        assert_eq!(by("[::ffff:10.0.0.9]:5000", &[]), Some(TrustedBy::Network));
+        assert_eq!(by("203.0.113.5:5000", &[]), None);
+        // a public client can't claim an internal address directly
+        assert_eq!(by("203.0.113.5:5000", &["10.0.0.1"]), None);
+
+        // behind an internal proxy the hop it appended decides
+        assert_eq!(by("10.0.0.2:5000", &["10.9.9.9"]), Some(TrustedBy::Network));
+        assert_eq!(by("10.0.0.2:5000", &["203.0.113.5"]), None);
+        assert_eq!(by("10.0.0.2:5000", &["10.1.1.1, 203.0.113.5"]), None);
+        assert_eq!(by("10.0.0.2:5000", &["10.1.1.1", "203.0.113.5"]), None);
+        assert_eq!(by("10.0.0.2:5000", &["garbage"]), None);
+    }
+
+    #[test]
+    fn token_trusts_any_address() {
+        let policy = TrustPolicy::new(Vec::new(), Some("s3cret"));
+        let check = |token| policy.check(&request("203.0.113.5:5000", &[], token));
+        assert_eq!(check(Some("s3cret")), Some(TrustedBy::Token));
+        assert_eq!(check(Some("wrong")), None);
+        assert_eq!(check(None), None);
+        assert!(TrustPolicy::new(Vec::new(), Some("")).token.is_none());
+    }
+}
@@ -0,0 +1,288 @@
+//! Request validation.
+//!
+//! Handlers take [`ValidJson`] and [`ValidQuery`] instead of axum's `Json` and `Query`.
+//! Both deserialize with the path of the field that failed, then run the type's
+//! [`Validate`] rules, and reject with every failed field at once as
+//! [`AppError::InvalidRequest`]. Range rules shared by several endpoints (limits,
+//! batch sizes, timestamp windows, dates) live on [`Violations`], so the same mistake
+//! gets the same code everywhere.
+
+use axum::extract::rejection::JsonRejection;
+use axum::extract::{FromRequest, FromRequestParts, Request};
+use axum::http::request::Parts;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::de::DeserializeOwned;
+
+use kizami_shared::calendar;
+use kizami_shared::error::{AppError, FieldError};
+
+/// Checks a deserialized request.
+pub trait Validate: Sized {
+    /// Records every rule `self` breaks.
+    fn validate(&self, violations: &mut Violations);
+
+    /// Returns `self` if it breaks no rules.
+    fn check(self) -> Result<Self, AppError> {
+        let mut violations = Violations::default();
+        self.validate(&mut violations);
+        violations.finish().map(|()| self)
+    }
+}
+
+/// Failed fields of one request, in the order they were checked.
+#[derive(Debug, Default)]
+pub struct Violations(Vec<FieldError>);
+
+impl Violations {
+    pub fn add(&mut self, field: impl Into<String>, err: AppError) {
+        self.0.push(err.at(field));
+    }
+
+    /// A result limit, when given, must be between 1 and `max`.
+    pub fn limit(&mut self, field: &str, limit: Option<usize>, max: usize) {
+        if let Some(limit) = limit.filter(|&l| l == 0 || l > max) {
+            self.add(field, AppError::InvalidLimit { limit, max });
+        }
+    }
+
+    /// A sample size must be between 1 and `max`.
+    pub fn sample_size(&mut self, field: &str, size: usize, max: usize) {
+        if size == 0 || size > max {
+            self.add(field, AppError::InvalidSampleSize { size, max });
+        }
+    }
+
+    /// A batch may hold at most `max` entries.
+    pub fn batch_size(&mut self, field: &str, size: usize, max: usize) {
+        if size > max {
+            self.add(field, AppError::BatchTooLarge { size, max });
+        }
+    }
+
+    /// Unix timestamps can't be negative.
+    pub fn timestamp(&mut self, field: impl Into<String>, timestamp: i64) {
+        if timestamp < 0 {
+            self.add(field, AppError::InvalidTimestamp(timestamp.to_string()));
+        }
+    }
+
+    /// A `from <= timestamp < to` window must start at or after 0 and not be empty.
+    /// The emptiness failure is reported on the `to` field.
+    pub fn window(&mut self, (from_field, from): (&str, i64), (to_field, to): (&str, i64)) {
+        self.timestamp(from_field, from);
+        if to <= from {
+            self.add(
+                to_field,
+                AppError::InvalidTimestamp(format!(
+                    "{to_field} ({to}) must be greater than {from_field} ({from})"
+                )),
+            );
+        }
+    }
+
+    /// An inclusive block range must start at or after 0 and not run backwards.
+    pub fn block_range(&mut self, field: &str, from_block: i64, to_block: i64) {
+        if from_block < 0 || from_block > to_block {
+            self.add(
+                field,
+                AppError::InvalidBlockRange {
+                    from_block,
+                    to_block,
+                },
+            );
+        }
+    }
+
+    /// A `YYYY-MM-DD` date.
+    pub fn date(&mut self, field: &str, date: &str) {
+        if let Err(e) = calendar::parse_date(date) {
+            self.add(field, e);
+        }
+    }
+
+    /// An IANA timezone name, when given.
+    pub fn timezone(&mut self, field: &str, tz: Option<&str>) {
+        if let Some(Err(e)) = tz.map(calendar::parse_tz) {
+            self.add(field, e);
+        }
+    }
+
+    /// A `YYYY`, `YYYY-Qn`, `YYYY-MM` or `YYYY-MM-DD` period.
+    pub fn period(&mut self, field: &str, period: &str) {
+        if let Err(e) = calendar::parse_period(period) {
+            self.add(field, e);
+        }
+    }
+
+    pub fn finish(self) -> Result<(), AppError> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::InvalidRequest(self.0))
+        }
+    }
+}
+
+/// Attributes a deserialization failure to the field it happened at, or to `whole`
+/// when serde couldn't tell (e.g. a missing field).
+fn malformed<E: std::fmt::Display>(err: serde_path_to_error::Error<E>, whole: &str) -> AppError {
+    let path = err.path().to_string();
+    let field = if path == "." { whole.to_string() } else { path };
+    AppError::InvalidRequest(vec![FieldError::malformed(field, err.inner().to_string())])
+}
+
+/// A validated JSON body. Requests without a JSON content type and bodies over the
+/// body limit get axum's own 415 and 413 responses.
+#[derive(Debug)]
+pub struct ValidJson<T>(pub T);
+
+impl<T, S> FromRequest<S> for ValidJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<serde_json::Value>::from_request(req, state)
+            .await
+            .map_err(|rejection| match rejection {
+                JsonRejection::JsonSyntaxError(e) => {
+                    let field = FieldError::malformed("body", e.body_text());
+                    AppError::InvalidRequest(vec![field]).into_response()
+                }
+                other => other.into_response(),
+            })?;
+        let body: T = serde_path_to_error::deserialize(value)
+            .map_err(|e| malformed(e, "body").into_response())?;
+        body.check()
+            .map(ValidJson)
+            .map_err(IntoResponse::into_response)
+    }
+}
+
+/// Validated query parameters.
+#[derive(Debug)]
+pub struct ValidQuery<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for ValidQuery<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let query = parts.uri.query().unwrap_or_default();
+        let deserializer =
+            serde_urlencoded::Deserializer::new(form_urlencoded::parse(query.as_bytes()));
+        let query: T =
+            serde_path_to_error::deserialize(deserializer).map_err(|e| malformed(e, "query"))?;
+        query.check().map(ValidQuery)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::{to_bytes, Body};
+    use axum::http::StatusCode;
+    use axum::routing::{get, post};
+    use axum::Router;
+    use serde::Deserialize;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Window {
+        from_ts: i64,
+        to_ts: i64,
+        #[serde(default)]
+        limit: Option<usize>,
+    }
+
+    impl Validate for Window {
+        fn validate(&self, v: &mut Violations) {
+            v.window(("from_ts", self.from_ts), ("to_ts", self.to_ts));
+            v.limit("limit", self.limit, 100);
+        }
+    }
+
+    #[derive(Deserialize)]
+    struct Batch {
+        timestamps: Vec<i64>,
+    }
+
+    impl Validate for Batch {
+        fn validate(&self, v: &mut Violations) {
+            v.batch_size("timestamps", self.timestamps.len(), 2);
+            for (i, &ts) in self.timestamps.iter().enumerate() {
+                v.timestamp(format!("timestamps[{i}]"), ts);
+            }
+        }
+    }
+
+    async fn send(req: axum::http::Request<Body>) -> (StatusCode, serde_json::Value) {
+        let app = Router::new()
+            .route("/window", get(|ValidQuery(_): ValidQuery<Window>| async {}))
+            .route("/batch", post(|ValidJson(_): ValidJson<Batch>| async {}));
+        let response = app.oneshot(req).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or_default())
+    }
+
+    fn fields(body: &serde_json::Value) -> Vec<&str> {
+        body["error"]["details"]["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| f["field"].as_str().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn query_failures_name_every_field() {
+        let get = |uri| axum::http::Request::get(uri).body(Body::empty()).unwrap();
+
+        let (status, _) = send(get("/window?from_ts=1&to_ts=2")).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, body) = send(get("/window?from_ts=-1&to_ts=-5&limit=500")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["code"], "INVALID_TIMESTAMP");
+        assert_eq!(fields(&body), ["from_ts", "to_ts", "limit"]);
+
+        let (status, body) = send(get("/window?from_ts=abc&to_ts=2")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["code"], "INVALID_REQUEST");
+        assert_eq!(fields(&body), ["from_ts"]);
+    }
+
+    #[tokio::test]
+    async fn body_failures_name_every_field() {
+        let post = |body: &str| {
+            axum::http::Request::post("/batch")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        let (status, body) = send(post(r#"{"timestamps": [1, -2, -3]}"#)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["code"], "BATCH_TOO_LARGE");
+        assert_eq!(body["error"]["details"]["max"], 2);
+        assert_eq!(
+            fields(&body),
+            ["timestamps", "timestamps[1]", "timestamps[2]"]
+        );
+
+        let (_, body) = send(post(r#"{"timestamps": [1, "x"]}"#)).await;
+        assert_eq!(body["error"]["code"], "INVALID_REQUEST");
+        assert_eq!(fields(&body), ["timestamps[1]"]);
+
+        let (_, body) = send(post("{")).await;
+        assert_eq!(fields(&body), ["body"]);
+    }
+}
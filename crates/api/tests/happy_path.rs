@@ -0,0 +1,146 @@
+//! End-to-end test of the full server: boots the real app on a local port with storage
+//! in a temp dir and a mock SQD portal, lets the first ingestion cycle run, then checks
+//! lookups and indexing status over HTTP, and the events an embedder sees.
+
+use std::time::{Duration, Instant};
+
+use axum::http::StatusCode;
+use serde_json::Value;
+use tokio::net::TcpListener;
+
+use kizami_fixtures::portal::MockPortal;
+use kizami_fixtures::{synthetic_chain, SyntheticSpec};
+use kizami_ingestion::events::EventBus;
+use kizami_shared::chains;
+use kizami_shared::sqd::SqdClient;
+
+async fn get_json(client: &reqwest::Client, url: &str) -> (StatusCode, Value) {
+    let resp = client.get(url).send().await.unwrap();
+    let status = StatusCode::from_u16(resp.status().as_u16()).unwrap();
+    (status, resp.json().await.unwrap())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn ingests_from_portal_and_serves_lookups() {
+    let eth = chains::chain_by_id(1).unwrap();
+    let headers = synthetic_chain(
+        eth,
+        &SyntheticSpec {
+            blocks_per_chain: 3_000,
+            ..SyntheticSpec::default()
+        },
+    );
+    let last = headers.last().unwrap().clone();
+    let portal = MockPortal::spawn(eth.sqd_slug, headers.clone()).await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let data_dir = dir.path().to_str().unwrap().to_string();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let (stop_tx, stop_rx) = tokio::sync::oneshot::channel::<()>();
+    let events = EventBus::default();
+    let mut advances = events.subscribe();
+    let server = tokio::spawn(async move {
+        kizami_api::run(
+            &data_dir,
+            listener,
+            SqdClient::with_base_url(portal.base_url()),
+            events,
+            async {
+                let _ = stop_rx.await;
+            },
+        )
+        .await
+    });
+
+    // the first ingestion cycle starts right away; wait for it to reach the head
+    let client = reqwest::Client::new();
+    let deadline = Instant::now() + Duration::from_secs(30);
+    let status = loop {
+        let (code, status) = get_json(&client, &format!("{base}/v1/indexing-status")).await;
+        assert_eq!(code, StatusCode::OK);
+        let eth_status = status
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|c| c["chain_id"] == 1)
+            .unwrap()
+            .clone();
+        if eth_status["last_indexed_block"] == last.number {
+            break eth_status;
+        }
+        assert!(
+            Instant::now() < deadline,
+            "ingestion did not catch up: {eth_status}"
+        );
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    };
+    assert_eq!(status["latest_known_block"], last.number);
+    assert_eq!(status["progress"], 100.0);
+    assert_eq!(status["rejected_blocks"], 0);
+    assert_eq!(status["sqd"]["error_rate"], 0.0);
+    assert!(status["sqd"]["last_success_at"].is_string());
+    assert!(status["sqd"]["dataset_lag"].is_null());
+
+    // lookups agree with the source data, including across stream page boundaries;
+    // lookups exclude the exact timestamp unless inclusive=true
+    for h in headers.iter().step_by(97).chain([&last]) {
+        let (code, before) = get_json(
+            &client,
+            &format!(
+                "{base}/v1/chains/1/block/before/{}?inclusive=true",
+                h.timestamp
+            ),
+        )
+        .await;
+        assert_eq!(code, StatusCode::OK, "{before}");
+        let expected = headers
+            .iter()
+            .rev()
+            .find(|b| b.timestamp <= h.timestamp)
+            .unwrap();
+        assert_eq!(before["number"], expected.number);
+        assert_eq!(before["timestamp"], expected.timestamp);
+        assert_eq!(before["indexed_up_to"], last.number);
+
+        let (_, after) = get_json(
+            &client,
+            &format!("{base}/v1/chains/1/block/after/{}", h.timestamp),
+        )
+        .await;
+        match headers.iter().find(|b| b.timestamp > h.timestamp) {
+            Some(next) => assert_eq!(after["number"], next.number),
+            // past the tip the block may still come, so it's not-yet-indexed, not missing
+            None => assert_eq!(after["error"]["code"], "NOT_YET_INDEXED"),
+        }
+    }
+
+    // embedders saw every block go in, in order
+    let mut next_block = headers[0].number;
+    while next_block <= last.number {
+        let advance = tokio::time::timeout(Duration::from_secs(10), advances.recv())
+            .await
+            .expect("no event for the indexed blocks")
+            .unwrap();
+        assert_eq!((advance.chain_id, advance.from_block), (1, next_block));
+        assert_eq!(
+            advance.blocks as i64,
+            advance.to_block - advance.from_block + 1
+        );
+        next_block = advance.to_block + 1;
+    }
+
+    // chains the portal doesn't serve stay empty
+    let (code, _) = get_json(
+        &client,
+        &format!("{base}/v1/chains/8453/block/before/1700000000"),
+    )
+    .await;
+    assert_eq!(code, StatusCode::NOT_FOUND);
+
+    stop_tx.send(()).unwrap();
+    tokio::time::timeout(Duration::from_secs(10), server)
+        .await
+        .expect("server did not shut down")
+        .unwrap();
+}
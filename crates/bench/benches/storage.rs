@@ -0,0 +1,96 @@
+//! Storage hot-path benchmarks: key encoding, point lookups, batched lookups, and
+//! insert throughput.
+//!
+//! Run with `cargo bench -p kizami-bench`. See the crate docs for dataset settings.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+
+use kizami_bench::{
+    dataset_keys, generate, open_dataset, BENCH_BLOCK_TIME, BENCH_CHAIN_ID, BENCH_GENESIS,
+};
+use kizami_shared::models::Bound;
+use kizami_shared::storage::{decode_block_key, encode_block_key, Storage};
+
+fn key_encoding(c: &mut Criterion) {
+    c.bench_function("encode_block_key", |b| {
+        b.iter(|| encode_block_key(black_box(1), black_box(1_700_000_000), black_box(42)))
+    });
+
+    let key = encode_block_key(1, 1_700_000_000, 42);
+    c.bench_function("decode_block_key", |b| {
+        b.iter(|| decode_block_key(black_box(&key)).unwrap())
+    });
+}
+
+fn lookups(c: &mut Criterion) {
+    let keys = dataset_keys();
+    let storage = open_dataset(keys);
+    let span = keys * BENCH_BLOCK_TIME;
+
+    // deterministic spread of timestamps across the whole dataset
+    let probe = |i: u64| BENCH_GENESIS + ((i.wrapping_mul(2_654_435_761) % span as u64) as i64);
+
+    let mut group = c.benchmark_group("find_block");
+    for (name, bound) in [
+        ("before_inclusive", Bound::AtOrBefore),
+        ("before_exclusive", Bound::Before),
+        ("after_inclusive", Bound::AtOrAfter),
+        ("after_exclusive", Bound::After),
+    ] {
+        let mut i = 0u64;
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                i += 1;
+                storage.find_block(BENCH_CHAIN_ID, probe(i), bound).unwrap()
+            })
+        });
+    }
+    group.finish();
+
+    let batch: Vec<(i64, Bound)> = (0..100)
+        .map(|i| (BENCH_GENESIS + i * 600, Bound::AtOrBefore))
+        .collect();
+    let mut group = c.benchmark_group("find_blocks_multi");
+    group.throughput(Throughput::Elements(batch.len() as u64));
+    group.bench_function("clustered_100", |b| {
+        b.iter(|| {
+            storage
+                .find_blocks_multi(BENCH_CHAIN_ID, black_box(&batch))
+                .unwrap()
+        })
+    });
+    group.bench_function("loop_of_find_block_100", |b| {
+        b.iter(|| {
+            batch
+                .iter()
+                .map(|&(ts, bound)| storage.find_block(BENCH_CHAIN_ID, ts, bound).unwrap())
+                .collect::<Vec<_>>()
+        })
+    });
+    group.finish();
+}
+
+fn inserts(c: &mut Criterion) {
+    const BATCH: i64 = 50_000;
+
+    let mut group = c.benchmark_group("insert_blocks");
+    group.throughput(Throughput::Elements(BATCH as u64));
+    group.sample_size(10);
+    group.bench_function("batch_50k", |b| {
+        b.iter_batched(
+            || {
+                let dir = tempfile::tempdir().unwrap();
+                let storage = Storage::open(dir.path()).unwrap();
+                (dir, storage)
+            },
+            |(_dir, storage)| generate(&storage, 0, BATCH),
+            BatchSize::PerIteration,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, key_encoding, lookups, inserts);
+criterion_main!(benches);
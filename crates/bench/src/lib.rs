@@ -0,0 +1,76 @@
+//! Shared fixtures for kizami benchmarks.
+//!
+//! Benchmarks run against a synthetic single-chain dataset that is generated once and
+//! reused across runs, since building the default 100M-key index takes minutes.
+//!
+//! Environment variables:
+//! - `KIZAMI_BENCH_DIR`: where the dataset lives (default: `target/bench-data`)
+//! - `KIZAMI_BENCH_KEYS`: number of blocks to generate (default: 100,000,000)
+
+use std::env;
+use std::path::PathBuf;
+
+use kizami_shared::storage::Storage;
+
+/// Chain ID the synthetic dataset is written under.
+pub const BENCH_CHAIN_ID: i32 = 1;
+
+/// Timestamp of block 0 in the synthetic dataset.
+pub const BENCH_GENESIS: i64 = 1_438_269_988;
+
+/// Seconds between consecutive synthetic blocks.
+pub const BENCH_BLOCK_TIME: i64 = 12;
+
+/// Blocks inserted per `insert_blocks` call while generating.
+const GENERATE_CHUNK: i64 = 50_000;
+
+const DEFAULT_KEYS: i64 = 100_000_000;
+
+/// Returns the configured dataset size.
+pub fn dataset_keys() -> i64 {
+    env::var("KIZAMI_BENCH_KEYS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_KEYS)
+}
+
+/// Opens the benchmark dataset, generating it first if the directory doesn't already
+/// hold `keys` blocks. The bench chain's cursor records how many blocks it holds.
+pub fn open_dataset(keys: i64) -> Storage {
+    let dir = env::var("KIZAMI_BENCH_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("target/bench-data"))
+        .join(keys.to_string());
+    let storage = Storage::open(&dir).expect("failed to open bench storage");
+
+    if storage
+        .get_cursor(BENCH_CHAIN_ID)
+        .expect("failed to read marker")
+        != keys
+    {
+        eprintln!("generating {keys} synthetic blocks in {}", dir.display());
+        generate(&storage, 0, keys);
+        storage
+            .upsert_cursor(BENCH_CHAIN_ID, keys)
+            .expect("failed to write marker");
+        storage.persist().expect("failed to persist bench storage");
+    }
+    storage
+}
+
+/// Inserts synthetic blocks `[from, to)` with a fixed block time.
+pub fn generate(storage: &Storage, from: i64, to: i64) {
+    let mut start = from;
+    while start < to {
+        let end = (start + GENERATE_CHUNK).min(to);
+        let numbers: Vec<i64> = (start..end).collect();
+        let timestamps: Vec<i64> = numbers
+            .iter()
+            .map(|n| BENCH_GENESIS + n * BENCH_BLOCK_TIME)
+            .collect();
+        storage
+            .insert_blocks(BENCH_CHAIN_ID, &numbers, &timestamps)
+            .expect("failed to insert bench blocks");
+        start = end;
+    }
+}
@@ -0,0 +1,427 @@
+//! Kizami operator CLI.
+//!
+//! Subcommands:
+//! - `seed --synthetic`: fill a data directory with deterministic synthetic blocks for
+//!   every chain (see `kizami-fixtures`), for integration tests and local demos.
+//! - `lookup --index FILE TIMESTAMP`: answer a block lookup from a downloaded index
+//!   snapshot (`/v1/chains/{id}/index`) without a server, via `kizami-client`.
+//! - `openapi`: print the API's OpenAPI document, byte-identical between runs, for
+//!   generating clients in CI without starting a server.
+//! - `config-schema`: print a JSON Schema of the server's configuration (its environment
+//!   variables), for validating deployment configs in editors and CI.
+//! - `gen-alerts`: print recommended Prometheus alerting rules for the server's
+//!   metrics, with thresholds from the same environment the server reads.
+//! - `loadtest --target URL`: drive a running server with point and batch lookups drawn
+//!   from its own stored blocks at a fixed rate, and print latency percentiles per
+//!   request kind (see `kizami_client::loadtest`).
+//! - `repair-timestamps`: compare a chain's stored block timestamps with SQD or the
+//!   chain's RPC over a block range and move misdated blocks to their correct keys
+//!   (see `kizami_shared::repair`). Dry run unless `--apply` is given.
+
+use std::io::Write;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use clap::{Parser, Subcommand, ValueEnum};
+
+use kizami_client::loadtest::{LoadMix, LoadTest};
+use kizami_client::IndexReader;
+use kizami_fixtures::{seed_storage, SyntheticSpec};
+use kizami_shared::chains;
+use kizami_shared::error::AppError;
+use kizami_shared::models::{Bound, Direction};
+use kizami_shared::repair;
+use kizami_shared::rpc::{parse_rpc_urls, RpcEndpoints};
+use kizami_shared::sqd::{BlockHeader, SqdClient};
+use kizami_shared::storage::Storage;
+
+#[derive(Parser)]
+#[command(name = "kizami", about = "Kizami operator tooling")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Seed a storage directory with block data.
+    Seed {
+        /// Generate deterministic synthetic data (currently the only supported source).
+        #[arg(long)]
+        synthetic: bool,
+        /// Storage directory to write into.
+        #[arg(long, env = "DATA_DIR", default_value = "./data")]
+        data_dir: String,
+        /// Blocks to generate per chain.
+        #[arg(long, default_value_t = 10_000)]
+        blocks: i64,
+        /// PRNG seed; the same seed always produces the same data.
+        #[arg(long, default_value_t = 42)]
+        seed: u64,
+        /// Comma-separated chain IDs to seed (default: all chains).
+        #[arg(long, value_delimiter = ',')]
+        chains: Option<Vec<i32>>,
+    },
+    /// Look up a block in a downloaded index snapshot, offline.
+    Lookup {
+        /// Index file from `/v1/chains/{id}/index`.
+        #[arg(long)]
+        index: String,
+        /// `before`, `after` or `nearest`.
+        #[arg(long, default_value = "before")]
+        direction: Direction,
+        /// Exclude blocks at exactly the timestamp. Not allowed with `nearest`.
+        #[arg(long)]
+        exclusive: bool,
+        /// Unix timestamp in seconds.
+        timestamp: i64,
+    },
+    /// Print the OpenAPI document as JSON.
+    Openapi {
+        /// Write to this file instead of stdout.
+        #[arg(long)]
+        output: Option<String>,
+    },
+    /// Print the JSON Schema of the server's configuration.
+    ConfigSchema {
+        /// Write to this file instead of stdout.
+        #[arg(long)]
+        output: Option<String>,
+    },
+    /// Print Prometheus alerting rules for kizami's metrics.
+    ///
+    /// Thresholds come from the environment, so run it with the server's.
+    GenAlerts {
+        /// Prometheus job that scrapes kizami.
+        #[arg(long, default_value = "kizami")]
+        job: String,
+        /// Mount point of the data directory, for node_exporter's disk space series.
+        #[arg(long, default_value = "/")]
+        mountpoint: String,
+        /// Write to this file instead of stdout.
+        #[arg(long)]
+        output: Option<String>,
+    },
+    /// Send lookups drawn from a server's data at a fixed rate and report latencies.
+    Loadtest {
+        /// Base URL of the server, e.g. `http://localhost:8080`.
+        #[arg(long)]
+        target: String,
+        /// Requests per second; a batch counts as one.
+        #[arg(long, default_value_t = 100.0)]
+        qps: f64,
+        /// How long to send for, in seconds. Long runs make a soak test.
+        #[arg(long, default_value_t = 60)]
+        duration_secs: u64,
+        /// Weights of request kinds, e.g. `batch:10,point:90`.
+        #[arg(long, default_value = "point:90,batch:10")]
+        mix: LoadMix,
+        /// Queries per batch request.
+        #[arg(long, default_value_t = 100)]
+        batch_size: usize,
+        /// Comma-separated chain IDs to query (default: every indexed chain).
+        #[arg(long, value_delimiter = ',')]
+        chains: Option<Vec<i32>>,
+        /// PRNG seed; the same seed sends the same queries.
+        #[arg(long, default_value_t = 42)]
+        seed: u64,
+        /// Requests waiting for an answer at once; more are skipped and counted.
+        #[arg(long, default_value_t = 512)]
+        max_in_flight: usize,
+    },
+    /// Find blocks stored under the wrong timestamp and move them to the right one.
+    ///
+    /// The server must be stopped: it holds the storage directory open.
+    RepairTimestamps {
+        /// Storage directory to repair.
+        #[arg(long, env = "DATA_DIR", default_value = "./data")]
+        data_dir: String,
+        /// Chain ID.
+        #[arg(long)]
+        chain: i32,
+        /// First block to check.
+        #[arg(long)]
+        from_block: i64,
+        /// Last block to check, inclusive.
+        #[arg(long)]
+        to_block: i64,
+        /// Where the correct timestamps come from.
+        #[arg(long, value_enum, default_value_t = Source::Sqd)]
+        source: Source,
+        /// RPC endpoints as `chain_id=url` pairs, for `--source rpc`.
+        #[arg(long, env = "RPC_URLS", default_value = "")]
+        rpc_urls: String,
+        /// Rewrite the affected keys; without it the mismatches are only listed.
+        #[arg(long)]
+        apply: bool,
+    },
+}
+
+/// Reference source for `repair-timestamps`.
+#[derive(Clone, Copy, ValueEnum)]
+enum Source {
+    /// Re-fetch the range from the chain's SQD dataset.
+    Sqd,
+    /// Read each block from the chain's JSON-RPC endpoint.
+    Rpc,
+}
+
+/// Writes a generated JSON document, with a trailing newline, to `output` or stdout.
+fn write_document(mut json: String, output: Option<&str>, what: &str) -> ExitCode {
+    json.push('\n');
+    write_text(&json, output, what)
+}
+
+/// Writes generated text to `output` or stdout.
+fn write_text(text: &str, output: Option<&str>, what: &str) -> ExitCode {
+    let written = match output {
+        Some(path) => std::fs::write(path, text),
+        None => std::io::stdout().write_all(text.as_bytes()),
+    };
+    match written {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("failed to write {what}: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    match Cli::parse().command {
+        Command::Seed {
+            synthetic,
+            data_dir,
+            blocks,
+            seed,
+            chains,
+        } => {
+            if !synthetic {
+                eprintln!("only --synthetic seeding is supported");
+                return ExitCode::FAILURE;
+            }
+            let storage = match Storage::open(&data_dir) {
+                Ok(s) => s,
+                Err(e) => {
+                    eprintln!("failed to open storage at {data_dir}: {e}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            let spec = SyntheticSpec {
+                seed,
+                blocks_per_chain: blocks,
+                ..SyntheticSpec::default()
+            };
+            match seed_storage(&storage, &spec, chains.as_deref()) {
+                Ok(summary) => {
+                    println!(
+                        "seeded {} blocks across {} chains into {data_dir}",
+                        summary.blocks, summary.chains
+                    );
+                    ExitCode::SUCCESS
+                }
+                Err(e) => {
+                    eprintln!("seeding failed: {e}");
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Command::Lookup {
+            index,
+            direction,
+            exclusive,
+            timestamp,
+        } => {
+            let bound = match Bound::new(direction, !exclusive) {
+                Ok(bound) => bound,
+                Err(e) => {
+                    eprintln!("{e}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            let reader = match IndexReader::open(&index) {
+                Ok(r) => r,
+                Err(e) => {
+                    eprintln!("failed to read index: {e}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            match reader.find(timestamp, bound) {
+                Some((number, block_timestamp)) => {
+                    println!(
+                        "chain {} block {number} at {block_timestamp}",
+                        reader.chain_id()
+                    );
+                    ExitCode::SUCCESS
+                }
+                None => {
+                    eprintln!("no block {bound} {timestamp} in {index}");
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Command::Openapi { output } => {
+            let json = serde_json::to_string_pretty(&kizami_api::openapi())
+                .expect("OpenAPI document serializes");
+            write_document(json, output.as_deref(), "OpenAPI document")
+        }
+        Command::ConfigSchema { output } => {
+            let json = serde_json::to_string_pretty(&kizami_api::config_schema())
+                .expect("config schema serializes");
+            write_document(json, output.as_deref(), "config schema")
+        }
+        Command::GenAlerts {
+            job,
+            mountpoint,
+            output,
+        } => {
+            let rules = kizami_api::alert_rules(&kizami_api::AlertOptions { job, mountpoint });
+            write_text(&rules, output.as_deref(), "alerting rules")
+        }
+        Command::Loadtest {
+            target,
+            qps,
+            duration_secs,
+            mix,
+            batch_size,
+            chains,
+            seed,
+            max_in_flight,
+        } => {
+            let target = match target.parse() {
+                Ok(url) => url,
+                Err(e) => {
+                    eprintln!("invalid --target {target}: {e}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            let test = LoadTest {
+                qps,
+                duration: Duration::from_secs(duration_secs),
+                mix,
+                batch_size,
+                chains: chains.unwrap_or_default(),
+                seed,
+                max_in_flight,
+                ..LoadTest::new(target)
+            };
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("failed to build tokio runtime");
+            match runtime.block_on(test.run()) {
+                Ok(report) => {
+                    println!("{report}");
+                    ExitCode::SUCCESS
+                }
+                Err(e) => {
+                    eprintln!("load test failed: {e}");
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Command::RepairTimestamps {
+            data_dir,
+            chain,
+            from_block,
+            to_block,
+            source,
+            rpc_urls,
+            apply,
+        } => {
+            let Some(chain) = chains::chain_by_id(chain) else {
+                eprintln!("unknown chain {chain}");
+                return ExitCode::FAILURE;
+            };
+            if from_block > to_block {
+                eprintln!("--from-block must not be after --to-block");
+                return ExitCode::FAILURE;
+            }
+            let storage = match Storage::open(&data_dir) {
+                Ok(s) => s,
+                Err(e) => {
+                    eprintln!("failed to open storage at {data_dir}: {e}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("failed to build tokio runtime");
+            let reference = runtime.block_on(fetch_reference(
+                chain.chain_id,
+                chain.sqd_slug,
+                from_block,
+                to_block,
+                source,
+                &rpc_urls,
+            ));
+            let reference = match reference {
+                Ok(headers) => headers,
+                Err(e) => {
+                    eprintln!("failed to fetch reference timestamps: {e}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            let stored = match storage.blocks_by_number(chain.chain_id, from_block, to_block) {
+                Ok(rows) => rows,
+                Err(e) => {
+                    eprintln!("failed to read stored blocks: {e}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            let fixes = repair::plan(&stored, &reference);
+            for fix in &fixes {
+                println!(
+                    "block {} stored at {}, reference {}",
+                    fix.number, fix.stored, fix.reference
+                );
+            }
+            println!(
+                "{}: {} stored entries checked against {} reference blocks, {} misdated",
+                chain.name,
+                stored.len(),
+                reference.len(),
+                fixes.len()
+            );
+            if !apply || fixes.is_empty() {
+                return ExitCode::SUCCESS;
+            }
+            match storage
+                .retime_blocks(chain.chain_id, &fixes)
+                .and_then(|moved| storage.persist().map(|()| moved))
+            {
+                Ok(moved) => {
+                    println!("moved {moved} blocks to their reference timestamps");
+                    ExitCode::SUCCESS
+                }
+                Err(e) => {
+                    eprintln!("repair failed: {e}");
+                    ExitCode::FAILURE
+                }
+            }
+        }
+    }
+}
+
+/// Headers of `from_block..=to_block` from the chosen reference source.
+async fn fetch_reference(
+    chain_id: i32,
+    sqd_slug: &str,
+    from_block: i64,
+    to_block: i64,
+    source: Source,
+    rpc_urls: &str,
+) -> Result<Vec<BlockHeader>, AppError> {
+    match source {
+        Source::Sqd => {
+            SqdClient::new()
+                .fetch_blocks(sqd_slug, from_block, to_block)
+                .await
+        }
+        Source::Rpc => RpcEndpoints::new(parse_rpc_urls(rpc_urls))
+            .block_headers(chain_id, from_block, to_block)
+            .await?
+            .ok_or_else(|| AppError::Rpc(format!("no RPC URL for chain {chain_id}"))),
+    }
+}
@@ -0,0 +1,176 @@
+//! Client-side tooling for kizami data.
+//!
+//! [`IndexReader`] loads a per-chain index snapshot downloaded from
+//! `/v1/chains/{id}/index` (format in `kizami_shared::index_file`) and answers the same
+//! before/after lookups as the API with a binary search, so offline and air-gapped
+//! analysis can run without a server.
+//!
+//! [`ShardedClient`] (in [`shard`]) spreads batch lookups over several replicas by
+//! chain, with a consistent-hash ring, and merges the answers. [`loadtest`] drives a
+//! server with queries drawn from its own data and reports latencies.
+
+pub mod loadtest;
+pub mod shard;
+
+pub use shard::{Lookup, ShardedBatch, ShardedClient};
+
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use kizami_shared::error::AppError;
+use kizami_shared::index_file::{self, IndexHeader};
+use kizami_shared::models::{Bound, Direction};
+
+/// An in-memory index for one chain, sorted by `(timestamp, number)`.
+pub struct IndexReader {
+    header: IndexHeader,
+    /// `(timestamp, number)`, the same order as the server's `blocks` keyspace.
+    blocks: Vec<(i64, i64)>,
+}
+
+impl IndexReader {
+    /// Loads an index file from disk.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, AppError> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .map_err(|e| AppError::CorruptData(format!("{}: {e}", path.display())))?;
+        Self::from_reader(BufReader::new(file))
+    }
+
+    /// Loads an index from any reader, e.g. an HTTP response body.
+    pub fn from_reader(input: impl Read) -> Result<Self, AppError> {
+        let (header, rows) = index_file::read_index(input)?;
+        let mut blocks: Vec<(i64, i64)> = rows.into_iter().map(|(n, ts)| (ts, n)).collect();
+        // files are written in key order; sort anyway so a hand-edited file can't
+        // silently break the binary search
+        if !blocks.is_sorted() {
+            blocks.sort_unstable();
+        }
+        Ok(Self { header, blocks })
+    }
+
+    pub fn chain_id(&self) -> i32 {
+        self.header.chain_id
+    }
+
+    /// Unix seconds at which the server generated the file.
+    pub fn generated_at(&self) -> i64 {
+        self.header.generated_at
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Finds the closest block to `timestamp` within `bound`, returning
+    /// `(number, timestamp)`. Matches the API: `at or before` picks the last block at
+    /// or before the timestamp, `before` the last strictly before it, and so on for
+    /// the after side; `nearest` whichever of the at-or-before and after blocks is
+    /// closer (the earlier on a tie).
+    pub fn find(&self, timestamp: i64, bound: Bound) -> Option<(i64, i64)> {
+        let found = match bound {
+            Bound::AtOrBefore => self.last_where(|ts| ts <= timestamp),
+            Bound::Before => self.last_where(|ts| ts < timestamp),
+            Bound::AtOrAfter => self.first_where(|ts| ts >= timestamp),
+            Bound::After => self.first_where(|ts| ts > timestamp),
+            Bound::Nearest => {
+                let before = self.find(timestamp, Bound::AtOrBefore);
+                let after = self.find(timestamp, Bound::After);
+                return Direction::nearest_of(timestamp, before, after, |&(_, ts)| ts);
+            }
+        };
+        found.map(|&(ts, number)| (number, ts))
+    }
+
+    /// Blocks with `from_ts <= timestamp < to_ts`, as `(number, timestamp)`.
+    pub fn range(&self, from_ts: i64, to_ts: i64) -> impl Iterator<Item = (i64, i64)> + '_ {
+        let start = self.blocks.partition_point(|&(ts, _)| ts < from_ts);
+        let end = self
+            .blocks
+            .partition_point(|&(ts, _)| ts < to_ts)
+            .max(start);
+        self.blocks[start..end].iter().map(|&(ts, n)| (n, ts))
+    }
+
+    fn first_where(&self, matches: impl Fn(i64) -> bool) -> Option<&(i64, i64)> {
+        let idx = self.blocks.partition_point(|&(ts, _)| !matches(ts));
+        self.blocks.get(idx)
+    }
+
+    fn last_where(&self, matches: impl Fn(i64) -> bool) -> Option<&(i64, i64)> {
+        let idx = self.blocks.partition_point(|&(ts, _)| matches(ts));
+        idx.checked_sub(1).map(|i| &self.blocks[i])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kizami_fixtures::{synthetic_chain, SyntheticSpec};
+    use kizami_shared::chains;
+    use kizami_shared::index_file::IndexWriter;
+    use kizami_shared::storage::Storage;
+
+    use super::*;
+
+    fn write_index(chain_id: i32, rows: &[(i64, i64)]) -> Vec<u8> {
+        let header = IndexHeader {
+            chain_id,
+            generated_at: 1717200000,
+        };
+        let mut writer = IndexWriter::new(Vec::new(), header).unwrap();
+        for &(number, ts) in rows {
+            writer.push(number, ts).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn lookups_match_storage() {
+        // Arbitrum's sub-second blocks produce runs sharing a timestamp
+        let chain = chains::chain_by_id(42161).unwrap();
+        let spec = SyntheticSpec {
+            blocks_per_chain: 5_000,
+            ..SyntheticSpec::default()
+        };
+        let headers = synthetic_chain(chain, &spec);
+        let rows: Vec<(i64, i64)> = headers.iter().map(|h| (h.number, h.timestamp)).collect();
+
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        storage
+            .insert_block_headers(chain.chain_id, &headers)
+            .unwrap();
+        let reader = IndexReader::from_reader(&write_index(chain.chain_id, &rows)[..]).unwrap();
+        assert_eq!(reader.chain_id(), 42161);
+        assert_eq!(reader.len(), rows.len());
+
+        let first = rows[0].1;
+        let last = rows[rows.len() - 1].1;
+        for ts in (first - 2..last + 2).step_by(7) {
+            for bound in Bound::ALL {
+                assert_eq!(
+                    reader.find(ts, bound),
+                    storage.find_block(chain.chain_id, ts, bound).unwrap(),
+                    "{ts} {bound}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn range_is_half_open() {
+        let rows = [(1, 100), (2, 100), (3, 105), (4, 110)];
+        let reader = IndexReader::from_reader(&write_index(1, &rows)[..]).unwrap();
+        assert_eq!(
+            reader.range(100, 110).collect::<Vec<_>>(),
+            vec![(1, 100), (2, 100), (3, 105)]
+        );
+        assert_eq!(reader.range(111, 200).count(), 0);
+        assert_eq!(reader.range(110, 100).count(), 0);
+    }
+}
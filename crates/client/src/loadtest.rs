@@ -0,0 +1,500 @@
+//! Load test against a running kizami server, for `kizami loadtest`.
+//!
+//! Queries are drawn from the server's own data: every indexed chain is sampled
+//! through `/v1/chains/{id}/blocks/sample`, and each query takes a sampled block's
+//! timestamp, shifted by up to a minute so they don't all hit exact block times, with a
+//! random chain and direction. Requests are sent open-loop at the target rate, split
+//! between single lookups and batch lookups by the [`LoadMix`], so a slow server shows
+//! up as latency rather than as a lower rate. Requests that would exceed
+//! `max_in_flight` are skipped and counted instead.
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use reqwest::{Client, Url};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tokio::time::MissedTickBehavior;
+
+use kizami_shared::error::AppError;
+use kizami_shared::models::{BlockRef, Direction};
+
+/// Blocks sampled per chain to draw query timestamps from.
+const SAMPLE_SIZE: usize = 1_000;
+
+/// Largest shift added to a sampled block's timestamp, in seconds.
+const JITTER_SECS: u64 = 60;
+
+/// SplitMix64, so a seed reproduces the same queries.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n.max(1)
+    }
+}
+
+/// Kinds of request a load test sends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestKind {
+    /// `GET /v1/chains/{id}/block/{direction}/{timestamp}`.
+    Point,
+    /// `POST /v1/chains/{id}/block/batch`.
+    Batch,
+}
+
+impl RequestKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Point => "point",
+            Self::Batch => "batch",
+        }
+    }
+}
+
+/// Relative weights of the request kinds, e.g. `batch:10,point:90`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoadMix {
+    pub point: u32,
+    pub batch: u32,
+}
+
+impl Default for LoadMix {
+    fn default() -> Self {
+        Self {
+            point: 90,
+            batch: 10,
+        }
+    }
+}
+
+impl FromStr for LoadMix {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let mut mix = Self { point: 0, batch: 0 };
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (kind, weight) = part
+                .split_once(':')
+                .ok_or_else(|| format!("expected kind:weight, got {part}"))?;
+            let weight: u32 = weight
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid weight in {part}"))?;
+            match kind.trim() {
+                "point" => mix.point = weight,
+                "batch" => mix.batch = weight,
+                other => return Err(format!("unknown request kind {other} (point, batch)")),
+            }
+        }
+        if mix.point + mix.batch == 0 {
+            return Err("mix needs a non-zero weight".into());
+        }
+        Ok(mix)
+    }
+}
+
+impl LoadMix {
+    fn pick(&self, rng: &mut SplitMix64) -> RequestKind {
+        if rng.below((self.point + self.batch) as u64) < self.point as u64 {
+            RequestKind::Point
+        } else {
+            RequestKind::Batch
+        }
+    }
+}
+
+/// Settings of a load test run.
+#[derive(Debug, Clone)]
+pub struct LoadTest {
+    pub target: Url,
+    /// Requests per second, counting a batch as one request.
+    pub qps: f64,
+    pub duration: Duration,
+    pub mix: LoadMix,
+    /// Queries per batch request.
+    pub batch_size: usize,
+    /// Chains to query; every indexed chain when empty.
+    pub chains: Vec<i32>,
+    pub seed: u64,
+    /// Requests allowed to wait for an answer at once.
+    pub max_in_flight: usize,
+    /// Timeout of one request.
+    pub timeout: Duration,
+}
+
+impl LoadTest {
+    pub fn new(target: Url) -> Self {
+        Self {
+            target,
+            qps: 100.0,
+            duration: Duration::from_secs(60),
+            mix: LoadMix::default(),
+            batch_size: 100,
+            chains: Vec::new(),
+            seed: 42,
+            max_in_flight: 512,
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// Runs the test and reports latencies per request kind.
+    pub async fn run(&self) -> Result<LoadReport, AppError> {
+        let client = Client::builder()
+            .timeout(self.timeout)
+            .build()
+            .map_err(|e| AppError::Replica(e.to_string()))?;
+        let samples = self.sample_chains(&client).await?;
+        let mut rng = SplitMix64(self.seed);
+        let results = Arc::new(Mutex::new(Results::default()));
+        let in_flight = Arc::new(Semaphore::new(self.max_in_flight.max(1)));
+        let mut tasks = JoinSet::new();
+        let mut skipped = 0;
+
+        let mut ticks = tokio::time::interval(Duration::from_secs_f64(1.0 / self.qps.max(0.001)));
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Burst);
+        let started = Instant::now();
+        while started.elapsed() < self.duration {
+            ticks.tick().await;
+            let Ok(permit) = in_flight.clone().try_acquire_owned() else {
+                skipped += 1;
+                continue;
+            };
+            let kind = self.mix.pick(&mut rng);
+            let request = self.request(&client, kind, &samples, &mut rng);
+            let results = results.clone();
+            tasks.spawn(async move {
+                let sent = Instant::now();
+                let ok = match request.send().await {
+                    Ok(response) => {
+                        let status = response.status();
+                        // read the body so the latency covers the whole answer
+                        response.bytes().await.is_ok() && status.is_success()
+                    }
+                    Err(_) => false,
+                };
+                results.lock().unwrap().record(kind, sent.elapsed(), ok);
+                drop(permit);
+            });
+        }
+        while tasks.join_next().await.is_some() {}
+        let elapsed = started.elapsed();
+
+        let results = std::mem::take(&mut *results.lock().unwrap());
+        Ok(LoadReport {
+            elapsed,
+            skipped,
+            kinds: [RequestKind::Point, RequestKind::Batch]
+                .into_iter()
+                .filter_map(|kind| results.report(kind))
+                .collect(),
+        })
+    }
+
+    /// Stored block timestamps per chain to draw queries from.
+    async fn sample_chains(&self, client: &Client) -> Result<Vec<(i32, Vec<i64>)>, AppError> {
+        let chains = if self.chains.is_empty() {
+            let statuses: Vec<Value> = self.get(client, "/v1/indexing-status").await?;
+            statuses
+                .iter()
+                .filter(|s| s["last_indexed_block"].as_i64().unwrap_or(0) > 0)
+                .filter_map(|s| s["chain_id"].as_i64())
+                .map(|id| id as i32)
+                .collect()
+        } else {
+            self.chains.clone()
+        };
+
+        #[derive(Deserialize)]
+        struct Sample {
+            blocks: Vec<BlockRef>,
+        }
+        let mut samples = Vec::new();
+        for chain_id in chains {
+            let path = format!(
+                "/v1/chains/{chain_id}/blocks/sample?n={SAMPLE_SIZE}&seed={}",
+                self.seed
+            );
+            let sample: Sample = self.get(client, &path).await?;
+            if !sample.blocks.is_empty() {
+                let timestamps = sample.blocks.iter().map(|b| b.timestamp).collect();
+                samples.push((chain_id, timestamps));
+            }
+        }
+        if samples.is_empty() {
+            return Err(AppError::Replica(format!(
+                "{}: no indexed blocks to query",
+                self.target
+            )));
+        }
+        Ok(samples)
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(
+        &self,
+        client: &Client,
+        path: &str,
+    ) -> Result<T, AppError> {
+        let url = self.url(path);
+        let failed = |e: &dyn fmt::Display| AppError::Replica(format!("{url}: {e}"));
+        let response = client.get(&url).send().await.map_err(|e| failed(&e))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(failed(&status));
+        }
+        response.json().await.map_err(|e| failed(&e))
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{path}", self.target.as_str().trim_end_matches('/'))
+    }
+
+    /// A random query timestamp on a random sampled chain.
+    fn query(samples: &[(i32, Vec<i64>)], rng: &mut SplitMix64) -> (i32, i64, Direction) {
+        let (chain_id, timestamps) = &samples[rng.below(samples.len() as u64) as usize];
+        let timestamp =
+            timestamps[rng.below(timestamps.len() as u64) as usize] + rng.below(JITTER_SECS) as i64;
+        let direction = if rng.below(2) == 0 {
+            Direction::Before
+        } else {
+            Direction::After
+        };
+        (*chain_id, timestamp, direction)
+    }
+
+    fn request(
+        &self,
+        client: &Client,
+        kind: RequestKind,
+        samples: &[(i32, Vec<i64>)],
+        rng: &mut SplitMix64,
+    ) -> reqwest::RequestBuilder {
+        let (chain_id, timestamp, direction) = Self::query(samples, rng);
+        match kind {
+            RequestKind::Point => client.get(self.url(&format!(
+                "/v1/chains/{chain_id}/block/{direction}/{timestamp}"
+            ))),
+            RequestKind::Batch => {
+                // one chain per batch, as the endpoint takes
+                let timestamps = &samples
+                    .iter()
+                    .find(|(id, _)| *id == chain_id)
+                    .expect("chain was sampled")
+                    .1;
+                let queries: Vec<Value> = (0..self.batch_size.max(1))
+                    .map(|_| {
+                        let ts = timestamps[rng.below(timestamps.len() as u64) as usize]
+                            + rng.below(JITTER_SECS) as i64;
+                        let direction = if rng.below(2) == 0 { "before" } else { "after" };
+                        json!({"timestamp": ts, "direction": direction})
+                    })
+                    .collect();
+                client
+                    .post(self.url(&format!("/v1/chains/{chain_id}/block/batch")))
+                    .json(&json!({ "queries": queries }))
+            }
+        }
+    }
+}
+
+/// Latencies collected per request kind.
+#[derive(Default)]
+struct Results {
+    latencies: Vec<(RequestKind, Duration)>,
+    errors: Vec<RequestKind>,
+}
+
+impl Results {
+    fn record(&mut self, kind: RequestKind, latency: Duration, ok: bool) {
+        self.latencies.push((kind, latency));
+        if !ok {
+            self.errors.push(kind);
+        }
+    }
+
+    fn report(&self, kind: RequestKind) -> Option<KindReport> {
+        let mut latencies: Vec<Duration> = self
+            .latencies
+            .iter()
+            .filter(|(k, _)| *k == kind)
+            .map(|(_, latency)| *latency)
+            .collect();
+        if latencies.is_empty() {
+            return None;
+        }
+        latencies.sort_unstable();
+        // nearest rank, as in the server's SLO report
+        let percentile = |q: f64| {
+            let rank = ((q * latencies.len() as f64).ceil() as usize).clamp(1, latencies.len());
+            latencies[rank - 1]
+        };
+        Some(KindReport {
+            kind,
+            requests: latencies.len(),
+            errors: self.errors.iter().filter(|k| **k == kind).count(),
+            p50: percentile(0.5),
+            p90: percentile(0.9),
+            p99: percentile(0.99),
+            max: latencies[latencies.len() - 1],
+        })
+    }
+}
+
+/// Latencies of one request kind.
+#[derive(Debug, Clone)]
+pub struct KindReport {
+    pub kind: RequestKind,
+    pub requests: usize,
+    /// Requests that failed or answered with a non-2xx status.
+    pub errors: usize,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+/// Outcome of a load test run.
+#[derive(Debug, Clone)]
+pub struct LoadReport {
+    /// From the first request to the last answer.
+    pub elapsed: Duration,
+    /// Requests not sent because `max_in_flight` were already waiting.
+    pub skipped: usize,
+    pub kinds: Vec<KindReport>,
+}
+
+impl LoadReport {
+    /// Requests answered per second.
+    pub fn achieved_qps(&self) -> f64 {
+        let requests: usize = self.kinds.iter().map(|k| k.requests).sum();
+        requests as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+impl fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        writeln!(
+            f,
+            "{:<6} {:>9} {:>7} {:>9} {:>9} {:>9} {:>9}",
+            "kind", "requests", "errors", "p50_ms", "p90_ms", "p99_ms", "max_ms"
+        )?;
+        for k in &self.kinds {
+            writeln!(
+                f,
+                "{:<6} {:>9} {:>7} {:>9.2} {:>9.2} {:>9.2} {:>9.2}",
+                k.kind.as_str(),
+                k.requests,
+                k.errors,
+                ms(k.p50),
+                ms(k.p90),
+                ms(k.p99),
+                ms(k.max)
+            )?;
+        }
+        write!(
+            f,
+            "{:.1} requests/s over {:.1}s, {} skipped at max in-flight",
+            self.achieved_qps(),
+            self.elapsed.as_secs_f64(),
+            self.skipped
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::extract::Path;
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// A server with one indexed chain, answering every lookup.
+    async fn spawn_server() -> Url {
+        let app = Router::new()
+            .route(
+                "/v1/indexing-status",
+                get(|| async {
+                    Json(json!([
+                        {"chain_id": 1, "last_indexed_block": 100},
+                        {"chain_id": 10, "last_indexed_block": 0},
+                    ]))
+                }),
+            )
+            .route(
+                "/v1/chains/{chain_id}/blocks/sample",
+                get(|Path(chain_id): Path<i32>| async move {
+                    assert_eq!(chain_id, 1);
+                    Json(json!({"blocks": [{"number": 1, "timestamp": 1_700_000_000}]}))
+                }),
+            )
+            .route(
+                "/v1/chains/{chain_id}/block/{direction}/{timestamp}",
+                get(|| async { Json(json!({"number": 1, "timestamp": 1_700_000_000})) }),
+            )
+            .route(
+                "/v1/chains/{chain_id}/block/batch",
+                post(|Json(body): Json<Value>| async move {
+                    let n = body["queries"].as_array().unwrap().len();
+                    assert_eq!(n, 5);
+                    Json(json!({"results": vec![json!({"status": "ok"}); n]}))
+                }),
+            );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        Url::parse(&format!("http://{addr}")).unwrap()
+    }
+
+    #[test]
+    fn mixes_parse_with_weights() {
+        assert_eq!(
+            "batch:10,point:90".parse::<LoadMix>(),
+            Ok(LoadMix {
+                point: 90,
+                batch: 10
+            })
+        );
+        assert_eq!(
+            "point:1".parse::<LoadMix>(),
+            Ok(LoadMix { point: 1, batch: 0 })
+        );
+        assert!("range:5".parse::<LoadMix>().is_err());
+        assert!("point:0".parse::<LoadMix>().is_err());
+        assert!("point".parse::<LoadMix>().is_err());
+    }
+
+    #[tokio::test]
+    async fn run_reports_latencies_per_kind() {
+        let test = LoadTest {
+            qps: 200.0,
+            duration: Duration::from_millis(300),
+            mix: LoadMix { point: 1, batch: 1 },
+            batch_size: 5,
+            ..LoadTest::new(spawn_server().await)
+        };
+        let report = test.run().await.unwrap();
+        assert_eq!(report.skipped, 0);
+        assert_eq!(report.kinds.len(), 2);
+        for kind in &report.kinds {
+            assert!(kind.requests > 0);
+            assert_eq!(kind.errors, 0);
+            assert!(kind.p50 <= kind.p99 && kind.p99 <= kind.max);
+        }
+        assert!(report.to_string().contains("requests/s"));
+    }
+}
@@ -0,0 +1,446 @@
+//! Batch lookups sharded across kizami replicas.
+//!
+//! [`ShardedClient`] spreads lookups over several replicas by chain. Each chain is
+//! owned by one replica on a consistent-hash ring ([`HashRing`]), so a replica's lookup
+//! cache only holds its own chains, and adding or removing a replica moves only the
+//! chains it gains or loses. A batch mixing chains is split per chain (and into pieces
+//! of at most [`MAX_BATCH_SIZE`]), the pieces go out concurrently, and the answers are
+//! merged back in query order.
+//!
+//! When a chain's owner is unreachable or answers 5xx, its piece is retried on the next
+//! replica clockwise on the ring. A 4xx answer would be the same on every replica and
+//! fails the batch.
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use futures_util::future::try_join_all;
+use reqwest::{Client, Url};
+use serde::Serialize;
+
+use kizami_shared::chains;
+use kizami_shared::error::AppError;
+use kizami_shared::models::{BatchItemResponse, BatchLookupResponse, Direction};
+
+/// Queries per request, the server's batch limit.
+pub const MAX_BATCH_SIZE: usize = 1000;
+
+/// Points each replica takes on the ring. More points spread chains more evenly.
+pub const VIRTUAL_NODES: usize = 128;
+
+/// Default timeout of one request to a replica.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// FNV-1a, finished with a SplitMix64 round since FNV alone clusters short keys.
+fn hash(bytes: &[u8]) -> u64 {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for &b in bytes {
+        h = (h ^ b as u64).wrapping_mul(0x0100_0000_01b3);
+    }
+    h = (h ^ (h >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    h ^ (h >> 31)
+}
+
+/// Consistent-hash ring mapping chains to nodes, identified by their index in the
+/// list the ring was built from. The mapping depends only on the node names, not on
+/// their order, so every client configured with the same replicas agrees on it.
+#[derive(Debug, Clone)]
+pub struct HashRing {
+    /// `(point, node)`, sorted by point.
+    points: Vec<(u64, usize)>,
+    nodes: usize,
+}
+
+impl HashRing {
+    pub fn new<S: AsRef<str>>(nodes: &[S]) -> Self {
+        let mut points: Vec<(u64, usize)> = nodes
+            .iter()
+            .enumerate()
+            .flat_map(|(node, name)| {
+                (0..VIRTUAL_NODES)
+                    .map(move |v| (hash(format!("{}#{v}", name.as_ref()).as_bytes()), node))
+            })
+            .collect();
+        points.sort_unstable();
+        Self {
+            points,
+            nodes: nodes.len(),
+        }
+    }
+
+    /// Nodes in the order a chain tries them: its owner first, then each other node in
+    /// the order it appears clockwise from there. Aliased chain IDs hash as the chain
+    /// they alias, so they land on the replica already caching its data.
+    pub fn owners(&self, chain_id: i32) -> Vec<usize> {
+        let chain_id = chains::chain_by_id(chain_id).map_or(chain_id, |c| c.chain_id);
+        let key = hash(&chain_id.to_be_bytes());
+        let start = self.points.partition_point(|&(point, _)| point < key);
+        let mut owners = Vec::with_capacity(self.nodes);
+        for &(_, node) in self.points[start..].iter().chain(&self.points[..start]) {
+            if !owners.contains(&node) {
+                owners.push(node);
+                if owners.len() == self.nodes {
+                    break;
+                }
+            }
+        }
+        owners
+    }
+
+    /// The node owning `chain_id`, `None` on an empty ring.
+    pub fn owner(&self, chain_id: i32) -> Option<usize> {
+        self.owners(chain_id).first().copied()
+    }
+}
+
+/// One lookup of a sharded batch. Direction and inclusivity default to the server's.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Lookup {
+    #[serde(skip)]
+    pub chain_id: i32,
+    /// Unix timestamp in seconds.
+    pub timestamp: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub direction: Option<Direction>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inclusive: Option<bool>,
+}
+
+impl Lookup {
+    pub fn new(chain_id: i32, timestamp: i64, direction: Direction) -> Self {
+        Self {
+            chain_id,
+            timestamp,
+            direction: Some(direction),
+            inclusive: None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct BatchBody<'a> {
+    queries: Vec<&'a Lookup>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    deadline_ms: Option<u64>,
+}
+
+/// Merged answer to a sharded batch.
+#[derive(Debug, Clone)]
+pub struct ShardedBatch {
+    /// Per-lookup results, in the order of the lookups.
+    pub results: Vec<BatchItemResponse>,
+    /// True if any replica ran out of time and some results are `timeout`.
+    pub partial: bool,
+    /// Highest indexed block per chain, as reported by the replicas that answered.
+    /// When a chain was answered by several, the lowest.
+    pub indexed_up_to: HashMap<i32, i64>,
+}
+
+/// Client sending batch lookups to the replica owning each chain.
+#[derive(Debug, Clone)]
+pub struct ShardedClient {
+    client: Client,
+    replicas: Vec<Url>,
+    ring: HashRing,
+}
+
+impl ShardedClient {
+    /// Shards across replicas given by base URL, e.g. `http://kizami-0:3000`.
+    pub fn new<S: AsRef<str>>(replicas: &[S]) -> Result<Self, AppError> {
+        if replicas.is_empty() {
+            return Err(AppError::Replica("no replicas configured".into()));
+        }
+        let replicas = replicas
+            .iter()
+            .map(|url| {
+                let url = url.as_ref().trim_end_matches('/');
+                Url::parse(url).map_err(|e| AppError::Replica(format!("{url}: {e}")))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let names: Vec<&str> = replicas.iter().map(Url::as_str).collect();
+        Ok(Self {
+            ring: HashRing::new(&names),
+            client: build_client(DEFAULT_TIMEOUT),
+            replicas,
+        })
+    }
+
+    /// Sets the timeout of each request to a replica (default 10s).
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = build_client(timeout);
+        self
+    }
+
+    /// Base URL of the replica currently owning `chain_id`.
+    pub fn replica_for(&self, chain_id: i32) -> &Url {
+        let owner = self.ring.owner(chain_id).unwrap_or_default();
+        &self.replicas[owner]
+    }
+
+    /// Answers lookups on any mix of chains, each chain on its owning replica.
+    /// `deadline_ms` is passed to every replica as the batch deadline.
+    pub async fn find_blocks(
+        &self,
+        lookups: &[Lookup],
+        deadline_ms: Option<u64>,
+    ) -> Result<ShardedBatch, AppError> {
+        let mut by_chain: BTreeMap<i32, Vec<usize>> = BTreeMap::new();
+        for (i, lookup) in lookups.iter().enumerate() {
+            by_chain.entry(lookup.chain_id).or_default().push(i);
+        }
+        let pieces = by_chain.iter().flat_map(|(&chain_id, indices)| {
+            indices
+                .chunks(MAX_BATCH_SIZE)
+                .map(move |chunk| (chain_id, chunk))
+        });
+        let answers = try_join_all(pieces.map(|(chain_id, indices)| async move {
+            let body = BatchBody {
+                queries: indices.iter().map(|&i| &lookups[i]).collect(),
+                deadline_ms,
+            };
+            let response = self.send(chain_id, &body).await?;
+            Ok::<_, AppError>((chain_id, indices, response))
+        }))
+        .await?;
+
+        let mut results = vec![None; lookups.len()];
+        let mut partial = false;
+        let mut indexed_up_to = HashMap::new();
+        for (chain_id, indices, response) in answers {
+            partial |= response.partial;
+            indexed_up_to
+                .entry(chain_id)
+                .and_modify(|up_to: &mut i64| *up_to = (*up_to).min(response.indexed_up_to))
+                .or_insert(response.indexed_up_to);
+            for (&i, result) in indices.iter().zip(response.results) {
+                results[i] = Some(result);
+            }
+        }
+        Ok(ShardedBatch {
+            results: results.into_iter().map(Option::unwrap).collect(),
+            partial,
+            indexed_up_to,
+        })
+    }
+
+    /// Sends one chain's piece to its owner, falling back along the ring.
+    async fn send(
+        &self,
+        chain_id: i32,
+        body: &BatchBody<'_>,
+    ) -> Result<BatchLookupResponse, AppError> {
+        let mut last_error = None;
+        for replica in self.ring.owners(chain_id) {
+            match self.post(&self.replicas[replica], chain_id, body).await {
+                Ok(response) => return Ok(response),
+                Err((true, e)) => last_error = Some(e),
+                Err((false, e)) => return Err(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| AppError::Replica("no replicas configured".into())))
+    }
+
+    /// Posts a batch to one replica. Errors say whether another replica may do better.
+    async fn post(
+        &self,
+        replica: &Url,
+        chain_id: i32,
+        body: &BatchBody<'_>,
+    ) -> Result<BatchLookupResponse, (bool, AppError)> {
+        let url = format!(
+            "{}/v1/chains/{chain_id}/block/batch",
+            replica.as_str().trim_end_matches('/')
+        );
+        let failed = |retry: bool, e: &dyn std::fmt::Display| {
+            (retry, AppError::Replica(format!("{replica}: {e}")))
+        };
+        let response = self
+            .client
+            .post(&url)
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| failed(true, &e))?;
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(failed(
+                status.is_server_error(),
+                &format_args!("{status}: {text}"),
+            ));
+        }
+        let batch: BatchLookupResponse = response.json().await.map_err(|e| failed(true, &e))?;
+        if batch.results.len() != body.queries.len() {
+            return Err(failed(
+                true,
+                &format_args!(
+                    "{} results for {} queries",
+                    batch.results.len(),
+                    body.queries.len()
+                ),
+            ));
+        }
+        Ok(batch)
+    }
+}
+
+fn build_client(timeout: Duration) -> Client {
+    Client::builder()
+        .timeout(timeout)
+        .build()
+        .expect("failed to build HTTP client")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::extract::{Path, State};
+    use axum::http::StatusCode;
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use serde_json::{json, Value};
+    use tokio::net::TcpListener;
+
+    use kizami_shared::models::BatchItemStatus;
+
+    use super::*;
+
+    /// A replica answering every query with block `timestamp + chain_id`, recording
+    /// the chains it was asked about.
+    async fn spawn_replica(seen: Arc<Mutex<Vec<i32>>>) -> String {
+        async fn batch(
+            State(seen): State<Arc<Mutex<Vec<i32>>>>,
+            Path(chain_id): Path<i32>,
+            Json(body): Json<Value>,
+        ) -> Result<Json<Value>, StatusCode> {
+            if chain_id == 404 {
+                return Err(StatusCode::NOT_FOUND);
+            }
+            seen.lock().unwrap().push(chain_id);
+            let results: Vec<Value> = body["queries"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|q| {
+                    let ts = q["timestamp"].as_i64().unwrap();
+                    json!({"status": "ok", "number": ts + chain_id as i64, "timestamp": ts})
+                })
+                .collect();
+            Ok(Json(
+                json!({"results": results, "partial": false, "indexed_up_to": 100}),
+            ))
+        }
+        let app = Router::new()
+            .route("/v1/chains/{chain_id}/block/batch", post(batch))
+            .with_state(seen);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        format!("http://{addr}")
+    }
+
+    #[test]
+    fn removing_a_node_only_moves_its_chains() {
+        let nodes = [
+            "http://a:3000",
+            "http://b:3000",
+            "http://c:3000",
+            "http://d:3000",
+        ];
+        let ring = HashRing::new(&nodes);
+        let smaller = HashRing::new(&nodes[..3]);
+        let mut owned = [0; 4];
+        for chain_id in 0..4000 {
+            let owner = ring.owner(chain_id).unwrap();
+            owned[owner] += 1;
+            if owner != 3 {
+                assert_eq!(smaller.owner(chain_id), Some(owner), "chain {chain_id}");
+            }
+            let owners = ring.owners(chain_id);
+            assert_eq!(owners.len(), 4);
+            assert_eq!(owners[0], owner);
+        }
+        // virtual nodes keep the split roughly even
+        assert!(owned.iter().all(|&n| (700..1300).contains(&n)), "{owned:?}");
+        // order of the node list doesn't matter
+        let reversed_nodes = ["http://c:3000", "http://b:3000", "http://a:3000"];
+        let reversed = HashRing::new(&reversed_nodes);
+        for chain_id in 0..100 {
+            assert_eq!(
+                nodes[smaller.owner(chain_id).unwrap()],
+                reversed_nodes[reversed.owner(chain_id).unwrap()]
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn batches_split_by_owner_and_merge_in_order() {
+        let seen: Vec<_> = (0..3).map(|_| Arc::new(Mutex::new(Vec::new()))).collect();
+        let mut urls = Vec::new();
+        for s in &seen {
+            urls.push(spawn_replica(s.clone()).await);
+        }
+        let client = ShardedClient::new(&urls).unwrap();
+
+        let chain_ids = [1, 10, 137, 8453, 42161];
+        let lookups: Vec<Lookup> = (0..2500)
+            .map(|i| {
+                Lookup::new(
+                    chain_ids[i % 5],
+                    1_700_000_000 + i as i64,
+                    Direction::Before,
+                )
+            })
+            .collect();
+        let batch = client.find_blocks(&lookups, Some(500)).await.unwrap();
+        assert!(!batch.partial);
+        assert_eq!(batch.indexed_up_to.len(), 5);
+        for (lookup, result) in lookups.iter().zip(&batch.results) {
+            assert_eq!(result.status, BatchItemStatus::Ok);
+            assert_eq!(
+                result.number,
+                Some(lookup.timestamp + lookup.chain_id as i64)
+            );
+        }
+        // each chain went to its owner only, 500 lookups in one piece
+        for chain_id in chain_ids {
+            let owner = urls
+                .iter()
+                .position(|u| client.replica_for(chain_id).as_str().starts_with(u))
+                .unwrap();
+            for (replica, s) in seen.iter().enumerate() {
+                let asked = s.lock().unwrap().iter().filter(|&&c| c == chain_id).count();
+                assert_eq!(asked, usize::from(replica == owner), "chain {chain_id}");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn unreachable_owner_fails_over_and_4xx_fails_the_batch() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let up = spawn_replica(seen.clone()).await;
+        let down = "http://127.0.0.1:1".to_string();
+        let client = ShardedClient::new(&[down.clone(), up])
+            .unwrap()
+            .with_timeout(Duration::from_secs(2));
+        let chain_id = (1..)
+            .find(|&c| client.replica_for(c).as_str().starts_with(&down))
+            .unwrap();
+
+        let batch = client
+            .find_blocks(&[Lookup::new(chain_id, 50, Direction::After)], None)
+            .await
+            .unwrap();
+        assert_eq!(batch.results[0].number, Some(50 + chain_id as i64));
+        assert_eq!(*seen.lock().unwrap(), vec![chain_id]);
+
+        let err = client
+            .find_blocks(&[Lookup::new(404, 50, Direction::After)], None)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), "REPLICA_ERROR");
+        assert!(ShardedClient::new::<&str>(&[]).is_err());
+    }
+}
@@ -0,0 +1,210 @@
+//! Deterministic synthetic block data for tests and local demos.
+//!
+//! Generates block headers for every configured chain starting at its real genesis
+//! timestamp, advancing by a per-chain realistic block time with small jitter and the
+//! occasional multi-minute pause (chain halts, sequencer outages). Sub-second chains
+//! naturally produce runs of blocks sharing a timestamp, like Arbitrum does in
+//! practice. Output depends only on the seed, so tests can assert exact results.
+//!
+//! With the `portal` feature, [`portal::MockPortal`] serves such data over HTTP the way
+//! SQD Portal does, and with the `redis` feature [`redis::MockRedis`] stands in for
+//! the Redis that replicas share state through.
+
+#[cfg(feature = "portal")]
+pub mod portal;
+#[cfg(feature = "redis")]
+pub mod redis;
+
+use kizami_shared::chains::{ChainConfig, CHAINS};
+use kizami_shared::error::AppError;
+use kizami_shared::sqd::BlockHeader;
+use kizami_shared::storage::Storage;
+
+/// Blocks inserted per storage call while seeding.
+const INSERT_CHUNK: usize = 50_000;
+
+/// Parameters for synthetic generation.
+#[derive(Debug, Clone)]
+pub struct SyntheticSpec {
+    /// PRNG seed. Same seed, same data.
+    pub seed: u64,
+    /// Blocks generated per chain, starting at block 0.
+    pub blocks_per_chain: i64,
+    /// Probability that a block is followed by a pause of 1-10 minutes.
+    pub gap_probability: f64,
+}
+
+impl Default for SyntheticSpec {
+    fn default() -> Self {
+        Self {
+            seed: 42,
+            blocks_per_chain: 10_000,
+            gap_probability: 0.001,
+        }
+    }
+}
+
+/// Approximate average block time in seconds for a chain.
+pub fn block_time_secs(chain_id: i32) -> f64 {
+    match chain_id {
+        1 | 167000 => 12.0,
+        42161 => 0.25,
+        143 => 0.4,
+        56 | 534352 | 1116 | 4200 => 3.0,
+        100 | 42220 => 5.0,
+        1088 => 4.0,
+        14 => 1.8,
+        204 | 324 | 146 | 130 | 57073 | 42793 => 1.0,
+        // most OP-stack and similar L2s
+        _ => 2.0,
+    }
+}
+
+/// SplitMix64: tiny, fast, and good enough for fixture jitter.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform float in `[0, 1)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Generates `spec.blocks_per_chain` headers for one chain.
+pub fn synthetic_chain(chain: &ChainConfig, spec: &SyntheticSpec) -> Vec<BlockHeader> {
+    let mut rng = SplitMix64(spec.seed ^ (chain.chain_id as u64).wrapping_mul(0x2545_F491));
+    let block_time = block_time_secs(chain.chain_id);
+    let mut clock = chain.genesis_timestamp as f64;
+
+    let mut headers = Vec::with_capacity(spec.blocks_per_chain.max(0) as usize);
+    for number in 0..spec.blocks_per_chain {
+        headers.push(BlockHeader {
+            number,
+            timestamp: clock as i64,
+        });
+        // +/-20% jitter around the nominal block time
+        clock += block_time * (0.8 + 0.4 * rng.next_f64());
+        if rng.next_f64() < spec.gap_probability {
+            clock += 60.0 + 540.0 * rng.next_f64();
+        }
+    }
+    headers
+}
+
+/// Summary of a seeding run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeedSummary {
+    pub chains: usize,
+    pub blocks: u64,
+}
+
+/// Writes synthetic data for `chains` (all configured chains if `None`) into storage and
+/// sets each chain's cursor to its last generated block, as if ingestion had run.
+pub fn seed_storage(
+    storage: &Storage,
+    spec: &SyntheticSpec,
+    chains: Option<&[i32]>,
+) -> Result<SeedSummary, AppError> {
+    let mut summary = SeedSummary {
+        chains: 0,
+        blocks: 0,
+    };
+    for chain in CHAINS {
+        if chains.is_some_and(|ids| !ids.contains(&chain.chain_id)) {
+            continue;
+        }
+        let headers = synthetic_chain(chain, spec);
+        for chunk in headers.chunks(INSERT_CHUNK) {
+            storage.insert_block_headers(chain.chain_id, chunk)?;
+        }
+        if let Some(last) = headers.last() {
+            storage.upsert_cursor(chain.chain_id, last.number)?;
+        }
+        summary.chains += 1;
+        summary.blocks += headers.len() as u64;
+    }
+    storage.persist()?;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use kizami_shared::chains::chain_by_id;
+    use kizami_shared::models::Bound;
+
+    use super::*;
+
+    fn spec(blocks: i64) -> SyntheticSpec {
+        SyntheticSpec {
+            blocks_per_chain: blocks,
+            ..SyntheticSpec::default()
+        }
+    }
+
+    #[test]
+    fn generation_is_deterministic() {
+        let eth = chain_by_id(1).unwrap();
+        let a = synthetic_chain(eth, &spec(500));
+        let b = synthetic_chain(eth, &spec(500));
+        assert!(a
+            .iter()
+            .zip(&b)
+            .all(|(x, y)| x.number == y.number && x.timestamp == y.timestamp));
+
+        let other_seed = SyntheticSpec {
+            seed: 7,
+            ..spec(500)
+        };
+        let c = synthetic_chain(eth, &other_seed);
+        assert_ne!(a.last().unwrap().timestamp, c.last().unwrap().timestamp);
+    }
+
+    #[test]
+    fn starts_at_genesis_and_never_goes_backwards() {
+        for chain in CHAINS {
+            let headers = synthetic_chain(chain, &spec(200));
+            assert_eq!(headers[0].timestamp, chain.genesis_timestamp);
+            assert!(headers.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+        }
+    }
+
+    #[test]
+    fn sub_second_chains_repeat_timestamps() {
+        let arb = chain_by_id(42161).unwrap();
+        let headers = synthetic_chain(arb, &spec(100));
+        assert!(headers.windows(2).any(|w| w[0].timestamp == w[1].timestamp));
+    }
+
+    #[test]
+    fn seed_storage_writes_blocks_and_cursors() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+
+        let summary = seed_storage(&storage, &spec(100), Some(&[1, 8453])).unwrap();
+        assert_eq!(
+            summary,
+            SeedSummary {
+                chains: 2,
+                blocks: 200
+            }
+        );
+        assert_eq!(storage.get_cursor(1).unwrap(), 99);
+        assert_eq!(storage.get_cursor(137).unwrap(), 0);
+
+        let eth = chain_by_id(1).unwrap();
+        assert_eq!(
+            storage
+                .find_block(1, eth.genesis_timestamp, Bound::AtOrBefore)
+                .unwrap(),
+            Some((0, eth.genesis_timestamp))
+        );
+    }
+}
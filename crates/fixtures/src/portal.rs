@@ -0,0 +1,153 @@
+//! A mock SQD portal serving synthetic headers over HTTP, for end-to-end and soak
+//! tests. Only compiled with the `portal` feature.
+//!
+//! Implements the three endpoints [`SqdClient`](kizami_shared::sqd::SqdClient) uses
+//! for a single dataset; every other dataset answers 404. Stream responses are capped
+//! at [`STREAM_PAGE`] blocks, so clients have to follow partial responses.
+
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::net::TcpListener;
+
+use kizami_shared::sqd::BlockHeader;
+
+/// Most blocks returned by one finalized-stream response.
+pub const STREAM_PAGE: usize = 700;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StreamRequest {
+    from_block: i64,
+    to_block: i64,
+}
+
+struct Dataset {
+    sqd_slug: String,
+    headers: Vec<BlockHeader>,
+    /// Blocks the head moves forward per finalized-head request; `None` serves the
+    /// last header as head from the start.
+    growth: Option<i64>,
+    /// Index of the current head in `headers`.
+    head: AtomicI64,
+}
+
+impl Dataset {
+    fn is(&self, slug: &str) -> bool {
+        self.sqd_slug == slug
+    }
+}
+
+/// A running mock portal.
+pub struct MockPortal {
+    addr: SocketAddr,
+}
+
+impl MockPortal {
+    /// Serves `headers` as `sqd_slug`, with the last header as the finalized head.
+    pub async fn spawn(sqd_slug: &str, headers: Vec<BlockHeader>) -> Self {
+        Self::start(sqd_slug, headers, None).await
+    }
+
+    /// Serves `headers` as a live chain: the head starts at the first header and moves
+    /// `blocks_per_poll` forward on every finalized-head request, up to the last one.
+    pub async fn spawn_growing(
+        sqd_slug: &str,
+        headers: Vec<BlockHeader>,
+        blocks_per_poll: i64,
+    ) -> Self {
+        Self::start(sqd_slug, headers, Some(blocks_per_poll)).await
+    }
+
+    async fn start(sqd_slug: &str, headers: Vec<BlockHeader>, growth: Option<i64>) -> Self {
+        assert!(!headers.is_empty(), "a dataset needs at least one block");
+        let head = match growth {
+            Some(_) => 0,
+            None => headers.len() as i64 - 1,
+        };
+        let dataset = Arc::new(Dataset {
+            sqd_slug: sqd_slug.to_string(),
+            headers,
+            growth,
+            head: AtomicI64::new(head),
+        });
+        let app = Router::new()
+            .route("/datasets/{slug}/finalized-head", get(finalized_head))
+            .route("/datasets/{slug}/metadata", get(metadata))
+            .route("/datasets/{slug}/finalized-stream", post(finalized_stream))
+            .with_state(dataset);
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind mock portal");
+        let addr = listener.local_addr().expect("mock portal has no address");
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        Self { addr }
+    }
+
+    /// The `/datasets` root to pass to `SqdClient::with_base_url`.
+    pub fn base_url(&self) -> String {
+        format!("http://{}/datasets", self.addr)
+    }
+}
+
+async fn finalized_head(State(dataset): State<Arc<Dataset>>, Path(slug): Path<String>) -> Response {
+    if !dataset.is(&slug) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let last = dataset.headers.len() as i64 - 1;
+    let head = match dataset.growth {
+        Some(step) => {
+            let prev = dataset
+                .head
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |h| {
+                    Some((h + step).min(last))
+                })
+                .unwrap_or(last);
+            (prev + step).min(last)
+        }
+        None => dataset.head.load(Ordering::Relaxed),
+    };
+    let number = dataset.headers[head as usize].number;
+    Json(json!({ "number": number, "hash": format!("0x{number:064x}") })).into_response()
+}
+
+async fn metadata(State(dataset): State<Arc<Dataset>>, Path(slug): Path<String>) -> Response {
+    if !dataset.is(&slug) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    Json(json!({ "dataset": slug, "start_block": dataset.headers[0].number })).into_response()
+}
+
+async fn finalized_stream(
+    State(dataset): State<Arc<Dataset>>,
+    Path(slug): Path<String>,
+    Json(req): Json<StreamRequest>,
+) -> Response {
+    if !dataset.is(&slug) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let body: String = dataset
+        .headers
+        .iter()
+        .filter(|h| h.number >= req.from_block && h.number <= req.to_block)
+        .take(STREAM_PAGE)
+        .map(|h| {
+            format!(
+                "{{\"header\":{{\"number\":{},\"timestamp\":{}}}}}\n",
+                h.number, h.timestamp
+            )
+        })
+        .collect();
+    if body.is_empty() {
+        return StatusCode::NO_CONTENT.into_response();
+    }
+    body.into_response()
+}
@@ -0,0 +1,163 @@
+//! A mock Redis server keeping strings and hashes in memory, for tests of state
+//! shared between replicas. Only compiled with the `redis` feature.
+//!
+//! Understands just the commands kizami sends (`GET`, `SET`, `DEL`, `INCR`, `EXPIRE`,
+//! `HGET`, `HSET`, `PING`, plus `AUTH` and `SELECT` on connect). Expiry is accepted
+//! and ignored: keys live until the server is dropped.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufStream};
+use tokio::net::{TcpListener, TcpStream};
+
+#[derive(Default)]
+struct Data {
+    strings: HashMap<Vec<u8>, Vec<u8>>,
+    hashes: HashMap<Vec<u8>, HashMap<Vec<u8>, Vec<u8>>>,
+}
+
+/// A running mock Redis.
+pub struct MockRedis {
+    addr: SocketAddr,
+    data: Arc<Mutex<Data>>,
+    commands: Arc<AtomicU64>,
+}
+
+impl MockRedis {
+    pub async fn spawn() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let data = Arc::new(Mutex::new(Data::default()));
+        let commands = Arc::new(AtomicU64::new(0));
+        let (server_data, server_commands) = (data.clone(), commands.clone());
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                tokio::spawn(serve(socket, server_data.clone(), server_commands.clone()));
+            }
+        });
+        Self {
+            addr,
+            data,
+            commands,
+        }
+    }
+
+    /// `redis://` URL of the server.
+    pub fn url(&self) -> String {
+        format!("redis://{}", self.addr)
+    }
+
+    /// A string key's value, if set.
+    pub fn get(&self, key: &str) -> Option<Vec<u8>> {
+        self.data
+            .lock()
+            .unwrap()
+            .strings
+            .get(key.as_bytes())
+            .cloned()
+    }
+
+    /// Sets a hash field, as `HSET` would.
+    pub fn hset(&self, key: &str, field: &str, value: &str) {
+        self.data
+            .lock()
+            .unwrap()
+            .hashes
+            .entry(key.as_bytes().to_vec())
+            .or_default()
+            .insert(field.as_bytes().to_vec(), value.as_bytes().to_vec());
+    }
+
+    /// Commands received so far, on every connection.
+    pub fn commands(&self) -> u64 {
+        self.commands.load(Ordering::Relaxed)
+    }
+}
+
+async fn serve(socket: TcpStream, data: Arc<Mutex<Data>>, commands: Arc<AtomicU64>) {
+    let mut stream = BufStream::new(socket);
+    while let Some(args) = read_command(&mut stream).await {
+        commands.fetch_add(1, Ordering::Relaxed);
+        let reply = execute(&data, &args);
+        if stream.write_all(&reply).await.is_err() || stream.flush().await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Reads a command sent as an array of bulk strings. `None` once the client is gone.
+async fn read_command(stream: &mut BufStream<TcpStream>) -> Option<Vec<Vec<u8>>> {
+    let argc: usize = read_line(stream).await?.strip_prefix('*')?.parse().ok()?;
+    let mut args = Vec::with_capacity(argc);
+    for _ in 0..argc {
+        let len: usize = read_line(stream).await?.strip_prefix('$')?.parse().ok()?;
+        let mut arg = vec![0; len + 2];
+        stream.read_exact(&mut arg).await.ok()?;
+        arg.truncate(len);
+        args.push(arg);
+    }
+    Some(args)
+}
+
+async fn read_line(stream: &mut BufStream<TcpStream>) -> Option<String> {
+    let mut line = String::new();
+    match stream.read_line(&mut line).await {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line.trim_end().to_string()),
+    }
+}
+
+fn execute(data: &Mutex<Data>, args: &[Vec<u8>]) -> Vec<u8> {
+    let mut data = data.lock().unwrap();
+    let name = args
+        .first()
+        .map(|a| String::from_utf8_lossy(a).to_ascii_uppercase())
+        .unwrap_or_default();
+    match (name.as_str(), &args[1..]) {
+        ("PING", _) => b"+PONG\r\n".to_vec(),
+        ("AUTH" | "SELECT", _) => b"+OK\r\n".to_vec(),
+        ("EXPIRE", [_, _]) => b":1\r\n".to_vec(),
+        ("GET", [key]) => bulk(data.strings.get(key)),
+        ("SET", [key, value, ..]) => {
+            data.strings.insert(key.clone(), value.clone());
+            b"+OK\r\n".to_vec()
+        }
+        ("DEL", keys) => {
+            let removed = keys
+                .iter()
+                .filter(|k| data.strings.remove(*k).is_some())
+                .count();
+            format!(":{removed}\r\n").into_bytes()
+        }
+        ("INCR", [key]) => {
+            let value = data.strings.entry(key.clone()).or_insert(b"0".to_vec());
+            let n = String::from_utf8_lossy(value).parse::<i64>().unwrap_or(0) + 1;
+            *value = n.to_string().into_bytes();
+            format!(":{n}\r\n").into_bytes()
+        }
+        ("HGET", [key, field]) => bulk(data.hashes.get(key).and_then(|h| h.get(field))),
+        ("HSET", [key, field, value]) => {
+            data.hashes
+                .entry(key.clone())
+                .or_default()
+                .insert(field.clone(), value.clone());
+            b":1\r\n".to_vec()
+        }
+        _ => format!("-ERR unsupported command {name}\r\n").into_bytes(),
+    }
+}
+
+fn bulk(value: Option<&Vec<u8>>) -> Vec<u8> {
+    match value {
+        Some(value) => {
+            let mut reply = format!("${}\r\n", value.len()).into_bytes();
+            reply.extend_from_slice(value);
+            reply.extend_from_slice(b"\r\n");
+            reply
+        }
+        None => b"$-1\r\n".to_vec(),
+    }
+}
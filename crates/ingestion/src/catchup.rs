@@ -0,0 +1,302 @@
+//! Accelerated catch-up for chains that fell far behind while the node was down.
+//!
+//! At startup, chains whose newest stored block is older than `CATCHUP_LAG_SECS` enter
+//! catch-up mode. Until a chain is within one normal batch of its head, each of its
+//! batches spans `CATCHUP_PARALLELISM` normal batches fetched concurrently (still
+//! through the SQD client's semaphore and request budget), and fsyncs are deferred
+//! until every chain has caught up, then done once. Each catch-up batch logs a
+//! `job = "catchup"` event with the blocks remaining and an ETA.
+//!
+//! A chain that is paused, or already at its head or sunset block when a cycle reaches
+//! it, leaves catch-up without a batch, so it can't hold fsyncs back for good.
+//!
+//! Chains with no stored blocks are plain backfills and don't qualify.
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use kizami_shared::chains::{ChainConfig, CHAINS};
+use kizami_shared::error::AppError;
+use kizami_shared::models::Bound;
+use kizami_shared::sqd::{BlockHeader, SqdClient};
+use kizami_shared::storage::Storage;
+
+use crate::BATCH_SIZE;
+
+/// Default age of a chain's newest block at startup that triggers catch-up, 6 hours.
+pub const DEFAULT_CATCHUP_LAG_SECS: i64 = 6 * 3600;
+
+/// Default batches fetched concurrently per catch-up batch.
+pub const DEFAULT_CATCHUP_PARALLELISM: usize = 4;
+
+/// A chain in catch-up mode.
+struct Lagging {
+    started: Instant,
+    start_cursor: i64,
+}
+
+/// Chains still catching up.
+#[derive(Default)]
+pub struct Catchup {
+    chains: HashMap<i32, Lagging>,
+    parallelism: usize,
+}
+
+impl Catchup {
+    /// Puts every chain whose newest stored block is more than `lag_secs` older than
+    /// `now` into catch-up mode. `None` disables catch-up.
+    pub fn detect(
+        storage: &Storage,
+        lag_secs: Option<i64>,
+        parallelism: usize,
+        now: i64,
+    ) -> Result<Self, AppError> {
+        let mut catchup = Self {
+            chains: HashMap::new(),
+            parallelism,
+        };
+        let Some(lag_secs) = lag_secs else {
+            return Ok(catchup);
+        };
+        for chain in CHAINS {
+            let Some((_, newest)) =
+                storage.find_block(chain.chain_id, i64::MAX, Bound::AtOrBefore)?
+            else {
+                continue;
+            };
+            if now - newest <= lag_secs {
+                continue;
+            }
+            let cursor = storage.get_cursor(chain.chain_id)?;
+            tracing::info!(
+                job = "catchup",
+                chain_slug = chain.sqd_slug,
+                chain_id = chain.chain_id,
+                lag_secs = now - newest,
+                cursor = cursor,
+                parallelism = parallelism,
+                "chain far behind, entering catch-up mode"
+            );
+            catchup.chains.insert(
+                chain.chain_id,
+                Lagging {
+                    started: Instant::now(),
+                    start_cursor: cursor,
+                },
+            );
+        }
+        Ok(catchup)
+    }
+
+    /// True while any chain is catching up.
+    pub fn is_active(&self) -> bool {
+        !self.chains.is_empty()
+    }
+
+    /// Batches to fetch at once for the chain: the configured parallelism while it is
+    /// catching up, otherwise 1.
+    pub fn parallelism(&self, chain_id: i32) -> usize {
+        if self.chains.contains_key(&chain_id) {
+            self.parallelism
+        } else {
+            1
+        }
+    }
+
+    /// Logs the chain's progress after a batch and ends its catch-up once it is within
+    /// one normal batch of `head`. Returns true if that was the last chain catching up.
+    pub fn record(&mut self, chain: &ChainConfig, cursor: i64, head: i64) -> bool {
+        let Some(lagging) = self.chains.get(&chain.chain_id) else {
+            return false;
+        };
+        let elapsed = lagging.started.elapsed().as_secs_f64();
+        let done = cursor - lagging.start_cursor;
+        let remaining = (head - cursor).max(0);
+        let blocks_per_sec = done as f64 / elapsed.max(f64::EPSILON);
+        if remaining > BATCH_SIZE {
+            tracing::info!(
+                job = "catchup",
+                chain_slug = chain.sqd_slug,
+                chain_id = chain.chain_id,
+                cursor = cursor,
+                head = head,
+                blocks_remaining = remaining,
+                blocks_per_sec = blocks_per_sec as u64,
+                eta_secs = (remaining as f64 / blocks_per_sec.max(1.0)) as u64,
+                "catching up"
+            );
+            return false;
+        }
+        tracing::info!(
+            job = "catchup",
+            chain_slug = chain.sqd_slug,
+            chain_id = chain.chain_id,
+            cursor = cursor,
+            head = head,
+            blocks_caught_up = done,
+            duration_secs = elapsed as u64,
+            "caught up, back to normal pace"
+        );
+        self.chains.remove(&chain.chain_id);
+        self.chains.is_empty()
+    }
+
+    /// Ends the chain's catch-up when the cycle has nothing to fetch for it: it is
+    /// paused, at its head or at its sunset block, so no batch would ever end it
+    /// through [`record`](Self::record). Returns true if that was the last chain
+    /// catching up.
+    pub fn settle(&mut self, chain: &ChainConfig, reason: &'static str) -> bool {
+        if self.chains.remove(&chain.chain_id).is_none() {
+            return false;
+        }
+        tracing::info!(
+            job = "catchup",
+            chain_slug = chain.sqd_slug,
+            chain_id = chain.chain_id,
+            reason = reason,
+            "nothing to catch up, back to normal pace"
+        );
+        self.chains.is_empty()
+    }
+}
+
+/// Fetches `from_block..=to_block` split into `parallelism` ranges fetched
+/// concurrently, joined in order.
+///
+/// Stops at the first range that came back short or failed, so the result is always
+/// a gap-free prefix of the range. Fails only if the first range does.
+pub async fn fetch_blocks(
+    sqd_client: &SqdClient,
+    sqd_slug: &'static str,
+    from_block: i64,
+    to_block: i64,
+    parallelism: usize,
+) -> Result<Vec<BlockHeader>, AppError> {
+    if parallelism <= 1 {
+        return sqd_client
+            .fetch_blocks(sqd_slug, from_block, to_block)
+            .await;
+    }
+    let span = ((to_block - from_block) as u64 / parallelism as u64 + 1) as i64;
+    let mut ranges = (from_block..=to_block)
+        .step_by(span as usize)
+        .map(|start| {
+            let end = (start + span - 1).min(to_block);
+            let sqd_client = sqd_client.clone();
+            let task =
+                tokio::spawn(async move { sqd_client.fetch_blocks(sqd_slug, start, end).await });
+            (end, task)
+        })
+        .collect::<Vec<_>>()
+        .into_iter();
+
+    let mut blocks = Vec::new();
+    for (end, task) in ranges.by_ref() {
+        let range = task
+            .await
+            .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+        match range {
+            Ok(range) => {
+                let complete = range.last().is_some_and(|b| b.number >= end);
+                blocks.extend(range);
+                if !complete {
+                    break;
+                }
+            }
+            Err(e) if blocks.is_empty() => return Err(e),
+            Err(e) => {
+                tracing::warn!(
+                    job = "catchup",
+                    chain_slug = sqd_slug,
+                    error = %e,
+                    "parallel range failed, keeping the blocks before it"
+                );
+                break;
+            }
+        }
+    }
+    // later ranges would leave a gap after the one that stopped short
+    for (_, task) in ranges {
+        task.abort();
+    }
+    Ok(blocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use kizami_fixtures::portal::MockPortal;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn parallel_fetch_joins_ranges_in_order() {
+        let slug = CHAINS[0].sqd_slug;
+        let headers = (0..=10_000)
+            .map(|number| BlockHeader {
+                number,
+                timestamp: 1_000 + number * 2,
+            })
+            .collect();
+        let portal = MockPortal::spawn(slug, headers).await;
+        let sqd = SqdClient::with_base_url(portal.base_url());
+
+        let blocks = fetch_blocks(&sqd, slug, 100, 2_099, 4).await.unwrap();
+        let numbers: Vec<_> = blocks.iter().map(|b| b.number).collect();
+        assert_eq!(numbers, (100..=2_099).collect::<Vec<_>>());
+
+        // ranges past the portal's head come back empty and cut the result there
+        let blocks = fetch_blocks(&sqd, slug, 9_000, 12_999, 4).await.unwrap();
+        assert_eq!(blocks.last().map(|b| b.number), Some(10_000));
+    }
+
+    #[test]
+    fn stale_chains_catch_up_until_within_a_batch() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        let (stale, fresh) = (&CHAINS[0], &CHAINS[1]);
+        storage
+            .insert_blocks(stale.chain_id, &[10], &[1_000])
+            .unwrap();
+        storage.upsert_cursor(stale.chain_id, 10).unwrap();
+        storage
+            .insert_blocks(fresh.chain_id, &[10], &[99_000])
+            .unwrap();
+
+        let now = 100_000;
+        let disabled = Catchup::detect(&storage, None, 4, now).unwrap();
+        assert!(!disabled.is_active());
+
+        let mut catchup = Catchup::detect(&storage, Some(3600), 4, now).unwrap();
+        assert_eq!(catchup.parallelism(stale.chain_id), 4);
+        assert_eq!(catchup.parallelism(fresh.chain_id), 1);
+        assert!(!catchup.record(stale, 200_010, 500_000));
+        assert!(catchup.is_active());
+        assert!(!catchup.record(fresh, 10, 500_000));
+        assert!(catchup.record(stale, 460_000, 500_000));
+        assert!(!catchup.is_active());
+        assert_eq!(catchup.parallelism(stale.chain_id), 1);
+    }
+
+    #[test]
+    fn stale_chain_already_at_its_head_settles() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        let (stale, paused) = (&CHAINS[0], &CHAINS[1]);
+        for chain in [stale, paused] {
+            storage
+                .insert_blocks(chain.chain_id, &[10], &[1_000])
+                .unwrap();
+            storage.upsert_cursor(chain.chain_id, 10).unwrap();
+        }
+
+        let mut catchup = Catchup::detect(&storage, Some(3600), 4, 100_000).unwrap();
+        assert!(catchup.is_active());
+        assert!(!catchup.settle(stale, "at_head"));
+        assert_eq!(catchup.parallelism(stale.chain_id), 1);
+        assert!(catchup.is_active());
+        assert!(catchup.settle(paused, "paused"));
+        assert!(!catchup.is_active());
+        // settling a chain that isn't catching up changes nothing
+        assert!(!catchup.settle(stale, "at_head"));
+    }
+}
@@ -0,0 +1,87 @@
+//! Event bus for written blocks.
+//!
+//! The ingestion loop and the work queue publish a [`CursorAdvance`] for every batch
+//! they write on an [`EventBus`], a `tokio::sync::broadcast` channel with any number of
+//! subscribers: the API's cache invalidation and freshness tracking, the `/v1/events`
+//! stream, and programs embedding ingestion in-process, which no longer have to poll
+//! the progress map to see it move.
+//!
+//! Publishing never waits. A subscriber that falls more than the bus capacity behind
+//! loses the oldest events and gets `RecvError::Lagged` with how many; it should treat
+//! that as "any chain may have changed".
+
+use tokio::sync::broadcast;
+
+use crate::CursorAdvance;
+
+/// Events a subscriber may fall behind by before it starts losing them.
+pub const DEFAULT_EVENT_CAPACITY: usize = 1024;
+
+/// Broadcast channel of written block ranges. Clones publish to the same subscribers.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<CursorAdvance>,
+}
+
+impl EventBus {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            sender: broadcast::channel(capacity.max(1)).0,
+        }
+    }
+
+    /// Receives every event published from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<CursorAdvance> {
+        self.sender.subscribe()
+    }
+
+    /// Sends `advance` to current subscribers, if any.
+    pub fn publish(&self, advance: CursorAdvance) {
+        let _ = self.sender.send(advance);
+    }
+
+    pub fn subscribers(&self) -> usize {
+        self.sender.receiver_count()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(DEFAULT_EVENT_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::broadcast::error::RecvError;
+
+    use super::*;
+
+    fn advance(to_block: i64) -> CursorAdvance {
+        CursorAdvance {
+            chain_id: 1,
+            from_block: to_block - 9,
+            to_block,
+            blocks: 10,
+            from_timestamp: 0,
+            to_timestamp: 0,
+            at_head: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn subscribers_each_see_events_and_learn_when_they_lag() {
+        let bus = EventBus::new(2);
+        bus.publish(advance(9));
+        let (mut fast, mut slow) = (bus.subscribe(), bus.subscribe());
+        assert_eq!(bus.subscribers(), 2);
+
+        bus.publish(advance(19));
+        assert_eq!(fast.recv().await.unwrap().to_block, 19);
+        bus.publish(advance(29));
+        bus.publish(advance(39));
+        assert_eq!(fast.recv().await.unwrap().to_block, 29);
+        assert_eq!(slow.recv().await, Err(RecvError::Lagged(1)));
+        assert_eq!(slow.recv().await.unwrap().to_block, 29);
+    }
+}
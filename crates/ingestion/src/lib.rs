@@ -1,98 +1,876 @@
 //! Background ingestion loop that fetches block headers from SQD Portal into fjall storage.
 //!
-//! Runs as a tokio task alongside the API server. Each cycle iterates over all chains
-//! sequentially: reads the cursor, checks the finalized head, fetches a batch of blocks
-//! (up to 50k), bulk-inserts into fjall, and advances the cursor.
+//! Runs on its own tokio runtime in a dedicated thread (see [`spawn_ingestion_thread`]),
+//! so backfill deserialization and inserts can't starve API request handling. Each
+//! cycle iterates over all chains sequentially: reads the cursor, checks the finalized
+//! head, fetches a batch of blocks (up to 50k), bulk-inserts into fjall, and advances
+//! the cursor.
 //!
 //! Backfill happens naturally: cursors default to 0, so the loop sees the full gap and
 //! works through it in 50k-block batches. Idempotent via key-value overwrite.
 //!
+//! Inserts are throttled on storage write pressure: batches are written in chunks off
+//! the async runtime, and ingestion waits between chunks while compaction is behind
+//! instead of letting fjall stall the writer, which keeps lookup latency flat during
+//! large backfills.
+//!
 //! Wide event logging: one structured JSON event per chain per cycle, plus one summary
 //! event per cycle with overall stats.
+//!
+//! On startup and every `CURSOR_CHECK_EVERY_N_CYCLES` cycles, each chain's cursor is
+//! checked against the highest block actually stored (see [`check_cursors`]). A cursor
+//! ahead of the data means a crash lost writes the cursor already covers, leaving a
+//! silent gap; it is logged with `alert=cursor_ahead_of_data` and, with `CURSOR_HEAL`,
+//! lowered so the missing range is fetched again.
+//!
+//! Chains left far behind by downtime catch up faster: larger, parallel fetches and
+//! deferred fsyncs until they are back near the head (see [`catchup`]).
+//!
+//! Every written batch is published on an [`EventBus`] (see [`events`]).
+//!
+//! Bytes downloaded from SQD are counted per chain and UTC day and saved after every
+//! cycle. With `SQD_DAILY_EGRESS_BUDGET_MB` set, chains more than a batch behind their
+//! head are deferred once the day's downloads reach the budget, and the work queue
+//! waits; chains following the tip keep ingesting.
 
+use std::collections::HashMap;
 use std::env;
-use std::time::{Duration, Instant};
+use std::io;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 
-use chrono::Utc;
 use tokio::sync::oneshot;
+use tokio::sync::oneshot::error::TryRecvError;
 
-use kizami_shared::chains::CHAINS;
-use kizami_shared::sqd::SqdClient;
-use kizami_shared::storage::{ChainProgress, ProgressMap, Storage};
+use kizami_shared::approximate;
+use kizami_shared::chains::{self, ChainConfig, CHAINS};
+use kizami_shared::clock::Clock;
+use kizami_shared::error::AppError;
+use kizami_shared::models::Bound;
+use kizami_shared::rpc::RpcEndpoints;
+use kizami_shared::scheduler::JobHandle;
+use kizami_shared::sqd::{BlockHeader, RequestBudget, SqdClient, SqdHealth};
+use kizami_shared::storage::{ChainProgress, CycleSummary, ProgressMap, Storage};
+use kizami_shared::validation;
+
+pub mod catchup;
+pub mod events;
+pub mod work_queue;
+
+use catchup::Catchup;
+use events::EventBus;
 
 /// Blocks per ingestion batch. At ~20 bytes/key this is well within
 /// fjall's capacity for a single batch of inserts.
 const BATCH_SIZE: i64 = 50_000;
 
-/// Fsync fjall's write-ahead journal every N cycles. Data survives process
-/// crashes without this (journal is intact), but an fsync guards against
+/// Headers written per storage call, so write pressure is re-checked several times
+/// within a batch.
+const INSERT_CHUNK: usize = 5_000;
+
+/// Write pressure (see [`WritePressure::level`](kizami_shared::storage::WritePressure::level))
+/// at or above which ingestion pauses between chunks to let compaction catch up. Half
+/// of fjall's own stall threshold.
+const THROTTLE_PRESSURE: f64 = 0.5;
+
+/// Longest ingestion waits for pressure to drop before each chunk. Past this it writes
+/// anyway and leaves backpressure to fjall.
+const MAX_THROTTLE_WAIT: Duration = Duration::from_secs(30);
+
+/// Default worker threads for the ingestion runtime. One is plenty: chains are ingested
+/// sequentially and inserts run on the runtime's blocking pool.
+const DEFAULT_INGEST_WORKER_THREADS: usize = 1;
+
+/// Default cap on blocking threads of the ingestion runtime, tokio's own default.
+const DEFAULT_INGEST_MAX_BLOCKING_THREADS: usize = 512;
+
+/// Default cycles between cursor consistency checks, about hourly at the default
+/// interval.
+const DEFAULT_CURSOR_CHECK_EVERY_N_CYCLES: u64 = 60;
+
+/// Default fsync cadence for [`PersistPolicy::Periodic`]. Data survives process
+/// crashes without an fsync (journal is intact), but an fsync guards against
 /// power loss. 5 cycles ≈ 5 minutes at the default 60s interval, which is
 /// fine since blocks are easily re-fetched from SQD.
-const PERSIST_EVERY_N_CYCLES: u64 = 5;
+const DEFAULT_PERSIST_EVERY_N_CYCLES: u64 = 5;
+
+/// Default seconds between head-only polls of every chain. One request per chain per
+/// poll, so about one request per second with 29 chains.
+const DEFAULT_HEAD_POLL_INTERVAL_SECS: u64 = 30;
+
+/// Default gap between consecutive block timestamps reported as a possible halt.
+const DEFAULT_TIMESTAMP_JUMP_ALERT_SECS: i64 = 3600;
+
+/// Default ingestion errors kept per chain for `/v1/admin/errors`.
+const DEFAULT_ERROR_LOG_SIZE: usize = 100;
+
+/// Default seconds between `job = "ingest_summary"` events.
+const DEFAULT_LOG_SUMMARY_INTERVAL_SECS: u64 = 60;
+
+/// Default seconds between runs of the work queue job.
+const DEFAULT_WORK_QUEUE_INTERVAL_SECS: u64 = 30;
+
+/// How aggressively the ingestion loop fsyncs fjall's write-ahead journal.
+///
+/// Configured via `PERSIST_MODE` (`batch`, `periodic`, or `buffer`; default `periodic`)
+/// and `PERSIST_EVERY_N_CYCLES` (default 5, only used by `periodic`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PersistPolicy {
+    /// Fsync after every successfully ingested batch. Safest, slowest.
+    EveryBatch,
+    /// Fsync once every N ingestion cycles.
+    Periodic { every_n_cycles: u64 },
+    /// Never fsync explicitly; rely on the OS to flush buffers. Suited to
+    /// ephemeral disks where the index is rebuilt from SQD anyway.
+    BufferOnly,
+}
+
+impl Default for PersistPolicy {
+    fn default() -> Self {
+        Self::Periodic {
+            every_n_cycles: DEFAULT_PERSIST_EVERY_N_CYCLES,
+        }
+    }
+}
+
+impl PersistPolicy {
+    /// Reads the policy from `PERSIST_MODE` and `PERSIST_EVERY_N_CYCLES`.
+    pub fn from_env() -> Self {
+        let mode = env::var("PERSIST_MODE").ok();
+        let every = env::var("PERSIST_EVERY_N_CYCLES").ok();
+        Self::parse(mode.as_deref(), every.as_deref())
+    }
+
+    /// Parses raw mode and cadence values, falling back to the default on bad input.
+    fn parse(mode: Option<&str>, every_n_cycles: Option<&str>) -> Self {
+        let every_n_cycles = every_n_cycles
+            .and_then(|v| v.parse().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or(DEFAULT_PERSIST_EVERY_N_CYCLES);
+
+        match mode.map(str::trim) {
+            None | Some("") | Some("periodic") => Self::Periodic { every_n_cycles },
+            Some("batch") => Self::EveryBatch,
+            Some("buffer") => Self::BufferOnly,
+            Some(other) => {
+                tracing::warn!(
+                    persist_mode = other,
+                    "unknown PERSIST_MODE, falling back to periodic"
+                );
+                Self::Periodic { every_n_cycles }
+            }
+        }
+    }
+}
+
+/// Published after blocks are written, describing the newly indexed window.
+///
+/// Consumers use it to drop cached answers that the new blocks may have changed, and
+/// to measure how far behind wall-clock time the indexed tip runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CursorAdvance {
+    pub chain_id: i32,
+    /// First block written.
+    pub from_block: i64,
+    /// The new cursor, or the last block written for a queued range (see
+    /// [`work_queue`]).
+    pub to_block: i64,
+    /// Blocks written, fewer than the range on approximate-mode chains and when blocks
+    /// were quarantined.
+    pub blocks: u64,
+    /// Timestamp of the first block in the new window. Lookups at or after it may now
+    /// resolve differently.
+    pub from_timestamp: i64,
+    /// Timestamp of the last stored block in the new window.
+    pub to_timestamp: i64,
+    /// True when the cursor reached the finalized head, i.e. the chain is caught up.
+    pub at_head: bool,
+}
+
+/// A chain whose persisted cursor points past the highest block actually stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CursorDiscrepancy {
+    pub chain_id: i32,
+    pub cursor: i64,
+    /// Highest stored block (quarantined blocks included), `None` if the chain has no
+    /// data at all.
+    pub max_stored_block: Option<i64>,
+    /// Whether the cursor was lowered to `max_stored_block`, or to 0 without data.
+    pub healed: bool,
+}
+
+/// Compares a chain's persisted cursor with the highest block in storage. Returns the
+/// discrepancy (not yet healed) if the cursor is ahead. Read-only.
+pub fn find_cursor_discrepancy(
+    storage: &Storage,
+    chain: &ChainConfig,
+) -> Result<Option<CursorDiscrepancy>, AppError> {
+    let cursor = storage.get_cursor(chain.chain_id)?;
+    let max_stored_block = storage.max_stored_block(chain.chain_id)?;
+    Ok(
+        (cursor > max_stored_block.unwrap_or(0)).then_some(CursorDiscrepancy {
+            chain_id: chain.chain_id,
+            cursor,
+            max_stored_block,
+            healed: false,
+        }),
+    )
+}
+
+/// Runs [`find_cursor_discrepancy`] on every chain and logs each chain whose cursor is
+/// ahead. With `heal`, such cursors are lowered (in storage and the progress map) so
+/// the next cycle re-fetches the missing range.
+pub async fn check_cursors(
+    storage: &Storage,
+    progress: &ProgressMap,
+    heal: bool,
+) -> Vec<CursorDiscrepancy> {
+    let mut discrepancies = Vec::new();
+    for chain in CHAINS {
+        let mut discrepancy = match find_cursor_discrepancy(storage, chain) {
+            Ok(Some(d)) => d,
+            Ok(None) => continue,
+            Err(e) => {
+                tracing::error!(
+                    job = "cursor_check",
+                    chain_slug = chain.sqd_slug,
+                    chain_id = chain.chain_id,
+                    error = %e,
+                    "failed to read cursor or stored blocks"
+                );
+                continue;
+            }
+        };
+        let healed_cursor = discrepancy.max_stored_block.unwrap_or(0);
+
+        discrepancy.healed = heal
+            && match storage.upsert_cursor(chain.chain_id, healed_cursor) {
+                Ok(()) => true,
+                Err(e) => {
+                    tracing::error!(
+                        job = "cursor_check",
+                        chain_slug = chain.sqd_slug,
+                        chain_id = chain.chain_id,
+                        error = %e,
+                        "failed to lower cursor"
+                    );
+                    false
+                }
+            };
+        if discrepancy.healed {
+            if let Some(entry) = progress.write().await.get_mut(chain.sqd_slug) {
+                entry.cursor = healed_cursor;
+            }
+        }
+
+        tracing::warn!(
+            job = "cursor_check",
+            alert = "cursor_ahead_of_data",
+            chain_slug = chain.sqd_slug,
+            chain_id = chain.chain_id,
+            cursor = discrepancy.cursor,
+            max_stored_block = discrepancy.max_stored_block,
+            missing_blocks = discrepancy.cursor - healed_cursor,
+            healed = discrepancy.healed,
+            "cursor points past stored blocks"
+        );
+        discrepancies.push(discrepancy);
+    }
+    discrepancies
+}
+
+/// Resolves the first block to ingest for a chain with no cursor.
+///
+/// Uses the dataset's `start_block` from SQD metadata, falling back to block 0
+/// if metadata is unavailable.
+async fn dataset_start_block(sqd_client: &SqdClient, chain: &ChainConfig) -> i64 {
+    match sqd_client.fetch_metadata(chain.sqd_slug).await {
+        Ok(meta) => meta.start_block.max(0),
+        Err(e) => {
+            tracing::warn!(
+                job = "ingest",
+                chain_slug = chain.sqd_slug,
+                chain_id = chain.chain_id,
+                error = %e,
+                "failed to fetch dataset metadata, starting from block 0"
+            );
+            0
+        }
+    }
+}
+
+/// Highest block to ingest given the finalized head: the head itself, or the chain's
+/// sunset block if it is deprecated and the head has passed it.
+fn ingest_ceiling(chain: &ChainConfig, head: i64) -> i64 {
+    chain.sunset_block().map_or(head, |sunset| head.min(sunset))
+}
+
+/// The stored block right before `from_block`, if it is the chain's latest block, so
+/// a batch's first header can be checked against it.
+fn previous_block(storage: &Storage, chain_id: i32, from_block: i64) -> Option<BlockHeader> {
+    let (number, timestamp) = storage
+        .find_block(chain_id, i64::MAX, Bound::AtOrBefore)
+        .ok()
+        .flatten()?;
+    (number == from_block - 1).then_some(BlockHeader { number, timestamp })
+}
+
+/// Waits (without blocking the runtime) until storage write pressure drops below
+/// [`THROTTLE_PRESSURE`] or [`MAX_THROTTLE_WAIT`] passes. Returns the time waited.
+async fn wait_for_write_pressure(storage: &Storage) -> Duration {
+    let clock = storage.clock();
+    let start = clock.instant();
+    let mut backoff = Duration::from_millis(50);
+    while storage.write_pressure().level() >= THROTTLE_PRESSURE
+        && clock.instant() - start < MAX_THROTTLE_WAIT
+    {
+        clock.sleep(backoff).await;
+        backoff = (backoff * 2).min(Duration::from_secs(1));
+    }
+    clock.instant() - start
+}
+
+/// Inserts headers in [`INSERT_CHUNK`]-sized writes on the blocking pool, waiting out
+/// write pressure before each. Returns the total time spent throttled.
+async fn insert_throttled(
+    storage: &Storage,
+    chain_id: i32,
+    headers: &[BlockHeader],
+) -> Result<Duration, AppError> {
+    let mut throttled = Duration::ZERO;
+    for chunk in headers.chunks(INSERT_CHUNK) {
+        throttled += wait_for_write_pressure(storage).await;
+        let storage = storage.clone();
+        let chunk = chunk.to_vec();
+        tokio::task::spawn_blocking(move || storage.insert_block_headers(chain_id, &chunk))
+            .await
+            .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))?;
+    }
+    Ok(throttled)
+}
+
+/// Reads a thread count such as `INGEST_WORKER_THREADS`, falling back to `default` on
+/// bad or zero input.
+fn parse_thread_count(value: Option<&str>, default: usize) -> usize {
+    value
+        .and_then(|v| v.trim().parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(default)
+}
+
+/// Ingestion settings read from the environment.
+#[derive(Debug, Clone)]
+pub struct IngestConfig {
+    /// Seconds between ingestion cycles (`INGEST_INTERVAL_SECS`, default 60), waited
+    /// on the storage [`Clock`] like every other delay of the loop.
+    pub interval_secs: u64,
+    /// Workers of the ingestion runtime (`INGEST_WORKER_THREADS`, default 1).
+    pub worker_threads: usize,
+    /// Most threads the ingestion runtime runs block writes on
+    /// (`INGEST_MAX_BLOCKING_THREADS`, default 512).
+    pub max_blocking_threads: usize,
+    /// When storage is fsynced (see [`PersistPolicy::from_env`]). Fsyncs of either
+    /// policy wait while any chain is in [`catchup`] mode.
+    pub persist_policy: PersistPolicy,
+    /// Cycles between cursor consistency checks with [`check_cursors`], which also
+    /// runs before the first cycle (`CURSOR_CHECK_EVERY_N_CYCLES`, default 60). Zero
+    /// checks at startup only.
+    pub cursor_check_every: u64,
+    /// Lower cursors found ahead of stored data (`CURSOR_HEAL`, `true` or `1`,
+    /// default false).
+    pub cursor_heal: bool,
+    /// Log per-chain successes and the cycle summary on every Nth cycle only
+    /// (`LOG_SAMPLE_INGEST_EVERY`, default 1). Warnings and errors are never sampled.
+    pub log_every_n_cycles: u64,
+    /// Interval of the aggregated `ingest_summary` event (`LOG_SUMMARY_INTERVAL_SECS`,
+    /// default 60). `None` disables it.
+    pub log_summary_interval: Option<Duration>,
+    /// SQD requests allowed per cycle across all chains (`SQD_REQUESTS_PER_CYCLE`),
+    /// drawn from one [`RequestBudget`] refilled every cycle. Once it is spent, the
+    /// chains not visited yet are deferred to the next cycle. `None` (unset or 0) is
+    /// unlimited.
+    pub sqd_requests_per_cycle: Option<u64>,
+    /// Interval of the head-only poll (`HEAD_POLL_INTERVAL_SECS`, default 30), run by
+    /// [`poll_heads`] alongside the loop; a cycle only fetches the head of a chain the
+    /// poller has not reached yet. `None` (0) disables it and each cycle fetches heads
+    /// itself.
+    pub head_poll_interval: Option<Duration>,
+    /// Chain RPC endpoints (`RPC_URLS`) polled next to SQD heads, to tell whether the
+    /// SQD dataset trails the chain.
+    pub rpc: RpcEndpoints,
+    /// Gap between consecutive block timestamps reported as an anomaly
+    /// (`TIMESTAMP_JUMP_ALERT_SECS`, default 3600). `None` (0) reports only timestamps
+    /// going backwards.
+    pub timestamp_jump_alert_secs: Option<i64>,
+    /// Interval of the `work_queue` job draining queued backfill and repair ranges
+    /// (`WORK_QUEUE_INTERVAL_SECS`, default 30). `None` (0) leaves the queue alone.
+    pub work_queue_interval: Option<Duration>,
+    /// Age of a chain's newest block at startup that puts it in [`catchup`] mode until
+    /// it is back near its head (`CATCHUP_LAG_SECS`, default 6 hours). `None` (0)
+    /// disables catch-up.
+    pub catchup_lag_secs: Option<i64>,
+    /// Batches fetched concurrently while catching up (`CATCHUP_PARALLELISM`, default 4).
+    pub catchup_parallelism: usize,
+    /// Failures kept per chain in the storage error log (`INGEST_ERROR_LOG_SIZE`,
+    /// default 100). 0 keeps none.
+    pub error_log_size: usize,
+    /// Bytes all chains may download from SQD per UTC day
+    /// (`SQD_DAILY_EGRESS_BUDGET_MB`). Once spent, chains more than [`BATCH_SIZE`]
+    /// blocks behind are deferred until the next UTC day. `None` (unset or 0) is
+    /// unlimited. Applied by [`restore_egress`].
+    pub sqd_daily_egress_budget: Option<u64>,
+}
+
+impl IngestConfig {
+    pub fn from_env() -> Self {
+        Self {
+            interval_secs: env::var("INGEST_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(60),
+            worker_threads: parse_thread_count(
+                env::var("INGEST_WORKER_THREADS").ok().as_deref(),
+                DEFAULT_INGEST_WORKER_THREADS,
+            ),
+            max_blocking_threads: parse_thread_count(
+                env::var("INGEST_MAX_BLOCKING_THREADS").ok().as_deref(),
+                DEFAULT_INGEST_MAX_BLOCKING_THREADS,
+            ),
+            persist_policy: PersistPolicy::from_env(),
+            cursor_check_every: env::var("CURSOR_CHECK_EVERY_N_CYCLES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_CURSOR_CHECK_EVERY_N_CYCLES),
+            cursor_heal: env::var("CURSOR_HEAL").is_ok_and(|v| matches!(v.as_str(), "1" | "true")),
+            log_every_n_cycles: env::var("LOG_SAMPLE_INGEST_EVERY")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|n| *n > 0)
+                .unwrap_or(1),
+            log_summary_interval: Some(
+                env::var("LOG_SUMMARY_INTERVAL_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(DEFAULT_LOG_SUMMARY_INTERVAL_SECS),
+            )
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs),
+            sqd_requests_per_cycle: env::var("SQD_REQUESTS_PER_CYCLE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|n| *n > 0),
+            head_poll_interval: Some(
+                env::var("HEAD_POLL_INTERVAL_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(DEFAULT_HEAD_POLL_INTERVAL_SECS),
+            )
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs),
+            rpc: RpcEndpoints::from_env(),
+            timestamp_jump_alert_secs: Some(
+                env::var("TIMESTAMP_JUMP_ALERT_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(DEFAULT_TIMESTAMP_JUMP_ALERT_SECS),
+            )
+            .filter(|secs| *secs > 0),
+            work_queue_interval: Some(
+                env::var("WORK_QUEUE_INTERVAL_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(DEFAULT_WORK_QUEUE_INTERVAL_SECS),
+            )
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs),
+            catchup_lag_secs: Some(
+                env::var("CATCHUP_LAG_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(catchup::DEFAULT_CATCHUP_LAG_SECS),
+            )
+            .filter(|secs| *secs > 0),
+            catchup_parallelism: env::var("CATCHUP_PARALLELISM")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|n| *n > 0)
+                .unwrap_or(catchup::DEFAULT_CATCHUP_PARALLELISM),
+            error_log_size: env::var("INGEST_ERROR_LOG_SIZE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_ERROR_LOG_SIZE),
+            sqd_daily_egress_budget: env::var("SQD_DAILY_EGRESS_BUDGET_MB")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .filter(|mb| *mb > 0)
+                .map(|mb| mb * 1_000_000),
+        }
+    }
+}
+
+/// Starts [`run_ingestion_loop`] on a dedicated OS thread with its own multi-threaded
+/// runtime (`INGEST_WORKER_THREADS` workers, default 1, and up to
+/// `INGEST_MAX_BLOCKING_THREADS` blocking threads), isolated from the runtime
+/// serving API requests. The thread exits once the loop sees `shutdown`.
+pub fn spawn_ingestion_thread(
+    config: IngestConfig,
+    storage: Storage,
+    sqd_client: SqdClient,
+    progress: ProgressMap,
+    events: EventBus,
+    job: JobHandle,
+    shutdown: oneshot::Receiver<()>,
+) -> io::Result<thread::JoinHandle<()>> {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(config.worker_threads)
+        .max_blocking_threads(config.max_blocking_threads)
+        .thread_name("kizami-ingest")
+        .enable_all()
+        .build()?;
+    thread::Builder::new()
+        .name("kizami-ingest".into())
+        .spawn(move || {
+            runtime.block_on(run_ingestion_loop(
+                config, storage, sqd_client, progress, events, job, shutdown,
+            ))
+        })
+}
+
+/// Records a freshly fetched head in the progress map, creating the entry if needed,
+/// and notes when (on `clock`) it moved forward.
+async fn record_head(progress: &ProgressMap, chain: &ChainConfig, head: i64, clock: &dyn Clock) {
+    let mut map = progress.write().await;
+    let entry = map
+        .entry(chain.sqd_slug.to_string())
+        .or_insert(ChainProgress {
+            cursor: 0,
+            head: None,
+            updated_at: None,
+            paused: false,
+            head_advanced_at: None,
+        });
+    if entry.head.is_none_or(|previous| head > previous) {
+        entry.head_advanced_at = Some(clock.now());
+    }
+    entry.head = Some(head);
+}
+
+/// Refreshes every chain's finalized head each `interval`, independent of block
+/// fetching, so `latestKnownBlock` and lag stay current between ingestion cycles.
+/// Head requests are cheap and bypass the per-cycle [`RequestBudget`]. Chains with an
+/// RPC endpoint also get the chain's own finalized head recorded in the client's
+/// [`SqdHealth`](kizami_shared::sqd::SqdHealth). Waits `interval` on `clock` after
+/// each round. Runs until aborted.
+async fn poll_heads(
+    sqd_client: SqdClient,
+    rpc: RpcEndpoints,
+    progress: ProgressMap,
+    interval: Duration,
+    clock: Arc<dyn Clock>,
+) {
+    let health = sqd_client.health();
+    loop {
+        for chain in CHAINS {
+            match sqd_client.fetch_finalized_head(chain.sqd_slug).await {
+                Ok(head) => record_head(&progress, chain, head.number, &*clock).await,
+                Err(e) => tracing::warn!(
+                    job = "head_poll",
+                    chain_slug = chain.sqd_slug,
+                    chain_id = chain.chain_id,
+                    error = %e,
+                    "failed to poll finalized head"
+                ),
+            }
+            match rpc.finalized_head(chain.chain_id).await {
+                Ok(Some(head)) => health.record_chain_head(chain.sqd_slug, head),
+                Ok(None) => {}
+                Err(e) => tracing::warn!(
+                    job = "head_poll",
+                    chain_slug = chain.sqd_slug,
+                    chain_id = chain.chain_id,
+                    error = %e,
+                    "failed to poll RPC finalized head"
+                ),
+            }
+        }
+        clock.sleep(interval).await;
+    }
+}
+
+/// The order chains are visited in a cycle: smallest known lag first, so chains
+/// following the tip are served before deep backfills when the request budget runs
+/// short. Chains with no head yet go last; ties keep config order.
+fn cycle_order(progress: &HashMap<String, ChainProgress>) -> Vec<&'static ChainConfig> {
+    let mut order: Vec<_> = CHAINS.iter().collect();
+    order.sort_by_key(|chain| {
+        progress
+            .get(chain.sqd_slug)
+            .and_then(|p| Some((p.head? - p.cursor).max(0)))
+            .unwrap_or(i64::MAX)
+    });
+    order
+}
+
+/// Days of cycle summaries kept for uptime reporting: the longest window `/v1/uptime`
+/// reports plus a day of slack.
+pub const CYCLE_RETENTION_DAYS: i64 = 31;
+
+/// Persists a cycle's summary and prunes summaries past [`CYCLE_RETENTION_DAYS`].
+/// Failures are logged; uptime history is not worth stalling ingestion over.
+fn record_cycle(storage: &Storage, cycle: &CycleSummary) {
+    let cutoff = cycle.started_at - chrono::Duration::days(CYCLE_RETENTION_DAYS);
+    if let Err(e) = storage
+        .record_cycle(cycle)
+        .and_then(|()| storage.prune_cycles(cutoff))
+    {
+        tracing::error!(job = "schedule", error = %e, "failed to record cycle summary");
+    }
+}
+
+/// Applies the daily SQD egress budget and restores the bytes downloaded today before
+/// a restart, so the budget isn't reset by one. Call before anything fetches from SQD.
+pub fn restore_egress(storage: &Storage, health: &SqdHealth, budget: Option<u64>) {
+    health.set_egress_budget(budget);
+    let today = storage.clock().now().date_naive();
+    match storage.egress_on(today) {
+        Ok(totals) => {
+            for (chain_id, bytes) in totals {
+                if let Some(chain) = chains::chain_by_id(chain_id) {
+                    health.restore_egress(chain.sqd_slug, today, bytes);
+                }
+            }
+        }
+        Err(e) => tracing::error!(error = %e, "failed to restore SQD egress totals"),
+    }
+}
+
+/// Saves today's SQD egress per chain. Failures are logged; the totals are saved
+/// again after the next cycle.
+fn save_egress(storage: &Storage, health: &SqdHealth) {
+    let today = storage.clock().now().date_naive();
+    let totals: Vec<(i32, u64)> = CHAINS
+        .iter()
+        .filter_map(|chain| {
+            let bytes = health.get(chain.sqd_slug)?.egress_bytes_on(today);
+            (bytes > 0).then_some((chain.chain_id, bytes))
+        })
+        .collect();
+    if totals.is_empty() {
+        return;
+    }
+    if let Err(e) = storage.record_egress(today, &totals) {
+        tracing::error!(job = "schedule", error = %e, "failed to save SQD egress totals");
+    }
+}
+
+/// Keeps a chain's failure in the storage error log, so it can be looked up without
+/// the logs. Failing to keep it is logged and otherwise ignored.
+fn log_error(storage: &Storage, chain: &ChainConfig, error: &AppError, keep: usize) {
+    if keep == 0 {
+        return;
+    }
+    if let Err(e) =
+        storage.record_ingest_error(chain.chain_id, error.code(), &error.to_string(), keep)
+    {
+        tracing::error!(
+            job = "ingest",
+            chain_slug = chain.sqd_slug,
+            chain_id = chain.chain_id,
+            error = %e,
+            "failed to record ingestion error"
+        );
+    }
+}
+
+/// Runs the fsyncs deferred while chains were catching up, unless the policy never
+/// fsyncs outside shutdown.
+fn persist_after_catchup(storage: &Storage, persist_policy: PersistPolicy) {
+    if persist_policy == PersistPolicy::BufferOnly {
+        return;
+    }
+    if let Err(e) = storage.persist() {
+        tracing::error!(error = %e, "failed to persist storage after catch-up");
+    }
+}
+
+/// Ingestion totals reported by the periodic `ingest_summary` event, so sampled-out
+/// cycles still show up in aggregate.
+#[derive(Debug, Default)]
+struct IngestTotals {
+    cycles: u64,
+    batches: u64,
+    blocks_fetched: u64,
+    blocks_rejected: u64,
+}
 
 /// Main ingestion loop. Runs until the shutdown signal is received.
 ///
 /// For each chain sequentially:
 /// 1. Read cursor from progress map (last ingested block number, default 0)
-/// 2. Fetch finalized head from SQD (always refreshed, cached value used as fallback)
-/// 3. If behind, compute batch range `[cursor+1, min(cursor+50k, head)]`
+/// 2. Fetch finalized head from SQD (always refreshed, cached value used as fallback),
+///    capped at the sunset block for deprecated chains
+/// 3. If behind, compute batch range `[cursor+1, min(cursor+50k, head)]`; fresh chains
+///    start at the dataset's first block and have their genesis timestamp validated
 /// 4. POST to SQD `/finalized-stream`, parse NDJSON, handle partial responses
-/// 5. Bulk-insert into fjall storage
+/// 5. Record timestamp anomalies against the previous block, validate headers,
+///    quarantine offenders, bulk-insert the rest into fjall storage, throttled on
+///    write pressure (sampled first on approximate-mode chains)
 /// 6. Upsert cursor in fjall storage
 /// 7. Update the shared progress map (used by the API for `indexedUpTo`)
+/// 8. Publish a [`CursorAdvance`] on `events`
+///
+/// The shutdown signal is checked between chains and while sleeping; the loop then
+/// finishes the chain in progress, persists storage and returns.
+///
+/// On any error, logs it, keeps it in the chain's error log and continues to the next
+/// chain. Each cycle's [`CycleSummary`] is persisted for uptime reporting, and the
+/// cycle is reported through `job` as a run of the scheduler's `ingest` job; a cycle
+/// with chain errors counts as failed. Chains are visited in [`cycle_order`].
 ///
-/// On any error, logs and continues to the next chain. Sleeps `INGEST_INTERVAL_SECS`
-/// (default 60) between cycles.
+/// Cycle interval, fsyncs, cursor checks, log sampling, request and egress budgets,
+/// head polling and catch-up are set by [`IngestConfig`], whose fields describe how
+/// each shapes the loop.
 pub async fn run_ingestion_loop(
+    config: IngestConfig,
     storage: Storage,
     sqd_client: SqdClient,
     progress: ProgressMap,
+    events: EventBus,
+    job: JobHandle,
     mut shutdown: oneshot::Receiver<()>,
 ) {
-    let interval_secs: u64 = env::var("INGEST_INTERVAL_SECS")
-        .ok()
-        .and_then(|v| v.parse().ok())
-        .unwrap_or(60);
+    let IngestConfig {
+        interval_secs,
+        persist_policy,
+        cursor_check_every,
+        cursor_heal,
+        log_every_n_cycles,
+        log_summary_interval,
+        sqd_requests_per_cycle,
+        head_poll_interval,
+        rpc,
+        timestamp_jump_alert_secs,
+        catchup_lag_secs,
+        catchup_parallelism,
+        error_log_size,
+        ..
+    } = config;
+
+    // started before the budget is attached, so polls never draw from it
+    let head_poller = head_poll_interval.map(|interval| {
+        tokio::spawn(poll_heads(
+            sqd_client.clone(),
+            rpc,
+            progress.clone(),
+            interval,
+            storage.clock().clone(),
+        ))
+    });
+
+    let budget = sqd_requests_per_cycle.map(|n| Arc::new(RequestBudget::new(n)));
+    let sqd_client = match &budget {
+        Some(budget) => sqd_client.with_budget(budget.clone()),
+        None => sqd_client,
+    };
+    let out_of_budget = || budget.as_ref().is_some_and(|b| b.remaining() == 0);
+    let sqd_health = sqd_client.health();
+    let mut egress_spent = false;
 
     tracing::info!(
         interval_secs = interval_secs,
+        persist_policy = ?persist_policy,
         chains = CHAINS.len(),
         "ingestion loop started"
     );
 
+    check_cursors(&storage, &progress, cursor_heal).await;
+
+    let mut catchup = Catchup::detect(
+        &storage,
+        catchup_lag_secs,
+        catchup_parallelism,
+        storage.clock().now().timestamp(),
+    )
+    .unwrap_or_else(|e| {
+        tracing::error!(error = %e, "failed to check chains for catch-up");
+        Catchup::default()
+    });
+
     let mut cycle_count: u64 = 0;
+    let mut stopping = false;
+    let mut totals = IngestTotals::default();
+    let clock = storage.clock().clone();
+    let mut totals_since = clock.instant();
 
     loop {
         cycle_count += 1;
-        let cycle_start = Instant::now();
+        totals.cycles += 1;
+        // the first cycle is always logged
+        let log_cycle = (cycle_count - 1).is_multiple_of(log_every_n_cycles);
+        if cursor_check_every > 0 && cycle_count.is_multiple_of(cursor_check_every) {
+            check_cursors(&storage, &progress, cursor_heal).await;
+        }
+        job.started();
+        let cycle_start = clock.instant();
+        let cycle_started_at = clock.now();
         let mut chains_checked = 0u32;
+        let mut chain_errors = 0u32;
         let mut chains_behind = 0u32;
+        let mut chains_deferred = 0u32;
+        if let Some(budget) = &budget {
+            budget.refill();
+        }
+        let egress_was_spent =
+            std::mem::replace(&mut egress_spent, sqd_health.egress_budget_spent());
+        if egress_spent && !egress_was_spent {
+            tracing::warn!(
+                job = "schedule",
+                alert = "sqd_egress_budget_spent",
+                egress_bytes_today = sqd_health.egress_today(),
+                egress_budget_bytes = sqd_health.egress_budget(),
+                "daily SQD egress budget spent, deep backfill waits for the next UTC day"
+            );
+        }
+        let order = cycle_order(&*progress.read().await);
 
-        for chain in CHAINS {
+        for (i, &chain) in order.iter().enumerate() {
+            // stop between chains, so shutdown doesn't wait out a whole backfill cycle
+            if !stopping && !matches!(shutdown.try_recv(), Err(TryRecvError::Empty)) {
+                stopping = true;
+            }
+            if stopping {
+                chains_deferred += (order.len() - i) as u32;
+                break;
+            }
+            if out_of_budget() {
+                chains_deferred += (order.len() - i) as u32;
+                break;
+            }
             chains_checked += 1;
-            let start = Instant::now();
+            let start = clock.instant();
 
-            let cursor_before = {
+            let (cursor_before, paused, known_head) = {
                 let map = progress.read().await;
-                map.get(chain.sqd_slug).map(|p| p.cursor).unwrap_or(0)
+                map.get(chain.sqd_slug)
+                    .map(|p| (p.cursor, p.paused, p.head))
+                    .unwrap_or((0, false, None))
             };
+            let polled_head = known_head.filter(|_| head_poller.is_some());
 
-            let head_number = match sqd_client.fetch_finalized_head(chain.sqd_slug).await {
+            let head_fetch = match polled_head {
+                Some(head) => Ok(head),
+                None => sqd_client
+                    .fetch_finalized_head(chain.sqd_slug)
+                    .await
+                    .map(|head| head.number),
+            };
+            let head_number = match head_fetch {
                 Ok(head) => {
-                    let mut map = progress.write().await;
-                    if let Some(entry) = map.get_mut(chain.sqd_slug) {
-                        entry.head = Some(head.number);
-                    } else {
-                        map.insert(
-                            chain.sqd_slug.to_string(),
-                            ChainProgress {
-                                cursor: cursor_before,
-                                head: Some(head.number),
-                                updated_at: None,
-                            },
-                        );
+                    if polled_head.is_none() {
+                        record_head(&progress, chain, head, &*clock).await;
                     }
-                    head.number
+                    head
                 }
                 Err(e) => {
                     tracing::error!(
@@ -103,6 +881,8 @@ pub async fn run_ingestion_loop(
                         error = %e,
                         "failed to fetch finalized head"
                     );
+                    chain_errors += 1;
+                    log_error(&storage, chain, &e, error_log_size);
                     let map = progress.read().await;
                     match map.get(chain.sqd_slug).and_then(|p| p.head) {
                         Some(v) => v,
@@ -111,19 +891,55 @@ pub async fn run_ingestion_loop(
                 }
             };
 
+            // paused chains keep their head fresh, so their lag stays visible
+            if paused {
+                if catchup.settle(chain, "paused") {
+                    persist_after_catchup(&storage, persist_policy);
+                }
+                continue;
+            }
+
+            // deprecated chains stop at their sunset block
+            let head_number = ingest_ceiling(chain, head_number);
+
             let gap = head_number - cursor_before;
             if gap <= 0 {
+                if catchup.settle(chain, "at_head") {
+                    persist_after_catchup(&storage, persist_policy);
+                }
                 continue;
             }
 
             chains_behind += 1;
+            if out_of_budget() {
+                chains_deferred += 1;
+                continue;
+            }
+            // following the tip costs little; only deep backfill waits for the budget
+            if gap > BATCH_SIZE && sqd_health.egress_budget_spent() {
+                chains_deferred += 1;
+                continue;
+            }
 
-            let from_block = cursor_before + 1;
-            let to_block = (cursor_before + BATCH_SIZE).min(head_number);
+            // a zero cursor means the chain has never been ingested: start at the
+            // dataset's first block (not every SQD dataset begins at genesis)
+            let fresh = cursor_before == 0;
+            let from_block = if fresh {
+                dataset_start_block(&sqd_client, chain).await
+            } else {
+                cursor_before + 1
+            };
+            let parallelism = catchup.parallelism(chain.chain_id);
+            let to_block = (from_block + BATCH_SIZE * parallelism as i64 - 1).min(head_number);
 
-            let blocks = match sqd_client
-                .fetch_blocks(chain.sqd_slug, from_block, to_block)
-                .await
+            let blocks = match catchup::fetch_blocks(
+                &sqd_client,
+                chain.sqd_slug,
+                from_block,
+                to_block,
+                parallelism,
+            )
+            .await
             {
                 Ok(b) => b,
                 Err(e) => {
@@ -137,27 +953,131 @@ pub async fn run_ingestion_loop(
                         error = %e,
                         "failed to fetch blocks from SQD"
                     );
+                    chain_errors += 1;
+                    log_error(&storage, chain, &e, error_log_size);
                     continue;
                 }
             };
 
+            // SQD can end a stream short of the requested range (dropped connection,
+            // 204 past its tip): only advance the cursor over blocks actually received
+            let to_block = match blocks.last() {
+                Some(last) => last.number.min(to_block),
+                None => {
+                    tracing::warn!(
+                        job = "ingest",
+                        chain_slug = chain.sqd_slug,
+                        chain_id = chain.chain_id,
+                        from_block = from_block,
+                        outcome = "empty",
+                        "SQD returned no blocks for the requested range"
+                    );
+                    continue;
+                }
+            };
             let blocks_fetched = blocks.len() as i64;
 
-            if let Err(e) = storage.insert_block_headers(chain.chain_id, &blocks) {
-                tracing::error!(
+            if fresh {
+                if let Some((number, timestamp)) = chain.genesis_mismatch(&blocks) {
+                    tracing::warn!(
+                        job = "ingest",
+                        alert = "genesis_mismatch",
+                        chain_slug = chain.sqd_slug,
+                        chain_id = chain.chain_id,
+                        configured_genesis_timestamp = chain.effective_genesis_timestamp(),
+                        block_number = number,
+                        block_timestamp = timestamp,
+                        "first fetched block disagrees with configured genesis timestamp"
+                    );
+                }
+            }
+
+            let prev = previous_block(&storage, chain.chain_id, from_block);
+            let anomalies = validation::find_timestamp_anomalies(
+                prev.as_ref(),
+                &blocks,
+                timestamp_jump_alert_secs,
+            );
+            if let Some(first) = anomalies.first() {
+                tracing::warn!(
+                    job = "ingest",
+                    alert = "timestamp_anomaly",
+                    chain_slug = chain.sqd_slug,
+                    chain_id = chain.chain_id,
+                    anomalies = anomalies.len() as u64,
+                    first_anomaly = %first.kind,
+                    block_number = first.number,
+                    block_timestamp = first.timestamp,
+                    prev_block_timestamp = first.prev_timestamp,
+                    "fetched blocks have out-of-line timestamps"
+                );
+                // reported, not blocking: the blocks are still indexed
+                if let Err(e) = storage.insert_anomalies(chain.chain_id, &anomalies) {
+                    tracing::error!(
+                        job = "ingest",
+                        chain_slug = chain.sqd_slug,
+                        chain_id = chain.chain_id,
+                        error = %e,
+                        "failed to record timestamp anomalies"
+                    );
+                }
+            }
+
+            let (blocks, rejected) =
+                validation::partition_headers(chain, blocks, storage.clock().now().timestamp());
+            // approximate-mode chains keep only every Nth block
+            let blocks = match approximate::sample_every(chain.chain_id) {
+                Some(every) => approximate::sample_headers(blocks, every, fresh),
+                None => blocks,
+            };
+
+            if !rejected.is_empty() {
+                tracing::warn!(
                     job = "ingest",
                     chain_slug = chain.sqd_slug,
                     chain_id = chain.chain_id,
                     from_block = from_block,
                     to_block = to_block,
-                    outcome = "error",
-                    error = %e,
-                    "failed to insert blocks"
+                    blocks_rejected = rejected.len() as u64,
+                    first_rejected_block = rejected[0].0.number,
+                    first_reject_reason = %rejected[0].1,
+                    "quarantining blocks that failed validation"
                 );
-                continue;
+                if let Err(e) = storage.insert_rejected(chain.chain_id, &rejected) {
+                    tracing::error!(
+                        job = "ingest",
+                        chain_slug = chain.sqd_slug,
+                        chain_id = chain.chain_id,
+                        outcome = "error",
+                        error = %e,
+                        "failed to quarantine rejected blocks"
+                    );
+                    chain_errors += 1;
+                    log_error(&storage, chain, &e, error_log_size);
+                    continue;
+                }
             }
 
-            if let Err(e) = storage.upsert_cursor(chain.sqd_slug, to_block) {
+            let throttled = match insert_throttled(&storage, chain.chain_id, &blocks).await {
+                Ok(throttled) => throttled,
+                Err(e) => {
+                    tracing::error!(
+                        job = "ingest",
+                        chain_slug = chain.sqd_slug,
+                        chain_id = chain.chain_id,
+                        from_block = from_block,
+                        to_block = to_block,
+                        outcome = "error",
+                        error = %e,
+                        "failed to insert blocks"
+                    );
+                    chain_errors += 1;
+                    log_error(&storage, chain, &e, error_log_size);
+                    continue;
+                }
+            };
+
+            if let Err(e) = storage.upsert_cursor(chain.chain_id, to_block) {
                 tracing::error!(
                     job = "ingest",
                     chain_slug = chain.sqd_slug,
@@ -166,28 +1086,66 @@ pub async fn run_ingestion_loop(
                     error = %e,
                     "failed to upsert cursor"
                 );
+                chain_errors += 1;
+                log_error(&storage, chain, &e, error_log_size);
                 continue;
             }
 
+            if catchup.record(chain, to_block, head_number) {
+                persist_after_catchup(&storage, persist_policy);
+            }
+
+            if persist_policy == PersistPolicy::EveryBatch && !catchup.is_active() {
+                if let Err(e) = storage.persist() {
+                    tracing::error!(
+                        job = "ingest",
+                        chain_slug = chain.sqd_slug,
+                        chain_id = chain.chain_id,
+                        error = %e,
+                        "failed to persist storage"
+                    );
+                }
+            }
+
             // update the shared progress map
             {
                 let mut map = progress.write().await;
                 if let Some(entry) = map.get_mut(chain.sqd_slug) {
                     entry.cursor = to_block;
-                    entry.updated_at = Some(Utc::now());
+                    entry.updated_at = Some(storage.clock().now());
                 } else {
                     map.insert(
                         chain.sqd_slug.to_string(),
                         ChainProgress {
                             cursor: to_block,
                             head: None,
-                            updated_at: Some(Utc::now()),
+                            updated_at: Some(storage.clock().now()),
+                            paused: false,
+                            head_advanced_at: None,
                         },
                     );
                 }
             }
 
-            let duration_ms = start.elapsed().as_millis();
+            if let (Some(first), Some(last)) = (blocks.first(), blocks.last()) {
+                events.publish(CursorAdvance {
+                    chain_id: chain.chain_id,
+                    from_block: first.number,
+                    to_block,
+                    blocks: blocks.len() as u64,
+                    from_timestamp: first.timestamp,
+                    to_timestamp: last.timestamp,
+                    at_head: to_block >= head_number,
+                });
+            }
+
+            totals.batches += 1;
+            totals.blocks_fetched += blocks_fetched as u64;
+            totals.blocks_rejected += rejected.len() as u64;
+            if !log_cycle {
+                continue;
+            }
+            let duration_ms = (clock.instant() - start).as_millis();
 
             tracing::info!(
                 job = "ingest",
@@ -196,33 +1154,378 @@ pub async fn run_ingestion_loop(
                 from_block = from_block,
                 to_block = to_block,
                 blocks_fetched = blocks_fetched,
+                blocks_rejected = rejected.len() as u64,
                 cursor_before = cursor_before,
                 cursor_after = to_block,
                 duration_ms = duration_ms as u64,
+                throttled_ms = throttled.as_millis() as u64,
+                write_pressure = storage.write_pressure().level(),
                 outcome = "success",
             );
         }
 
-        if cycle_count.is_multiple_of(PERSIST_EVERY_N_CYCLES) {
-            if let Err(e) = storage.persist() {
-                tracing::error!(error = %e, "failed to persist storage");
+        if let PersistPolicy::Periodic { every_n_cycles } = persist_policy {
+            if cycle_count.is_multiple_of(every_n_cycles) && !catchup.is_active() {
+                if let Err(e) = storage.persist() {
+                    tracing::error!(error = %e, "failed to persist storage");
+                }
             }
         }
 
-        tracing::info!(
-            job = "schedule",
-            chains_checked = chains_checked,
-            chains_behind = chains_behind,
-            cycle = cycle_count,
-            duration_ms = cycle_start.elapsed().as_millis() as u64,
+        save_egress(&storage, &sqd_health);
+        record_cycle(
+            &storage,
+            &CycleSummary {
+                started_at: cycle_started_at,
+                duration_ms: (clock.instant() - cycle_start).as_millis() as u64,
+                interval_secs,
+                chains_checked,
+                chains_behind,
+                chains_deferred,
+                chain_errors,
+            },
         );
 
+        if log_cycle {
+            tracing::info!(
+                job = "schedule",
+                chains_checked = chains_checked,
+                chains_behind = chains_behind,
+                chains_deferred = chains_deferred,
+                chain_errors = chain_errors,
+                sqd_requests = budget.as_ref().map(|b| b.per_cycle() - b.remaining()),
+                sqd_egress_bytes_today = sqd_health.egress_today(),
+                cycle = cycle_count,
+                duration_ms = (clock.instant() - cycle_start).as_millis() as u64,
+                log_every_n_cycles = log_every_n_cycles,
+            );
+        }
+
+        if log_summary_interval.is_some_and(|interval| (clock.instant() - totals_since) >= interval)
+        {
+            tracing::info!(
+                job = "ingest_summary",
+                interval_secs = (clock.instant() - totals_since).as_secs(),
+                cycles = totals.cycles,
+                batches = totals.batches,
+                blocks_fetched = totals.blocks_fetched,
+                blocks_rejected = totals.blocks_rejected,
+            );
+            totals = IngestTotals::default();
+            totals_since = clock.instant();
+        }
+
+        let next_cycle_at = (!stopping)
+            .then(|| storage.clock().now() + chrono::Duration::seconds(interval_secs as i64));
+        job.finished(
+            match chain_errors {
+                0 => Ok(()),
+                n => Err(format!("{n} of {chains_checked} chains failed")),
+            },
+            next_cycle_at,
+        );
+
+        if stopping {
+            break;
+        }
         tokio::select! {
-            _ = tokio::time::sleep(Duration::from_secs(interval_secs)) => {}
-            _ = &mut shutdown => {
-                tracing::info!("ingestion loop shutting down");
-                return;
+            _ = clock.sleep(Duration::from_secs(interval_secs)) => {}
+            _ = &mut shutdown => break,
+        }
+    }
+
+    tracing::info!("ingestion loop shutting down");
+    if let Some(poller) = head_poller {
+        poller.abort();
+    }
+    if let Err(e) = storage.persist() {
+        tracing::error!(error = %e, "failed to persist storage on shutdown");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use kizami_shared::clock::MockClock;
+    use kizami_shared::scheduler::JobHandle;
+
+    use super::*;
+
+    fn header(number: i64, timestamp: i64) -> BlockHeader {
+        BlockHeader { number, timestamp }
+    }
+
+    #[tokio::test]
+    async fn insert_throttled_writes_every_chunk() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        let headers: Vec<_> = (0..INSERT_CHUNK as i64 * 2 + 7)
+            .map(|n| header(n, 1000 + n))
+            .collect();
+
+        let throttled = insert_throttled(&storage, 1, &headers).await.unwrap();
+        assert!(throttled < MAX_THROTTLE_WAIT);
+        let rows = storage
+            .scan_blocks(1, (0, 0), i64::MAX, usize::MAX)
+            .unwrap();
+        assert_eq!(rows.len(), headers.len());
+    }
+
+    #[tokio::test]
+    async fn check_cursors_flags_and_heals_cursor_ahead_of_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        let eth = kizami_shared::chains::chain_by_id(1).unwrap();
+        let base = kizami_shared::chains::chain_by_id(8453).unwrap();
+        storage
+            .insert_blocks(1, &[8, 9, 10], &[100, 112, 124])
+            .unwrap();
+        storage.upsert_cursor(eth.chain_id, 20).unwrap();
+        // no data at all but a cursor: everything up to it is missing
+        storage.upsert_cursor(base.chain_id, 5).unwrap();
+        let progress: ProgressMap = Default::default();
+        progress.write().await.insert(
+            eth.sqd_slug.to_string(),
+            ChainProgress {
+                cursor: 20,
+                head: Some(30),
+                updated_at: None,
+                paused: false,
+                head_advanced_at: None,
+            },
+        );
+
+        let found = check_cursors(&storage, &progress, false).await;
+        assert_eq!(
+            found,
+            vec![
+                CursorDiscrepancy {
+                    chain_id: 1,
+                    cursor: 20,
+                    max_stored_block: Some(10),
+                    healed: false,
+                },
+                CursorDiscrepancy {
+                    chain_id: 8453,
+                    cursor: 5,
+                    max_stored_block: None,
+                    healed: false,
+                },
+            ]
+        );
+        assert_eq!(storage.get_cursor(eth.chain_id).unwrap(), 20);
+
+        let found = check_cursors(&storage, &progress, true).await;
+        assert!(found.iter().all(|d| d.healed));
+        assert_eq!(storage.get_cursor(eth.chain_id).unwrap(), 10);
+        assert_eq!(storage.get_cursor(base.chain_id).unwrap(), 0);
+        assert_eq!(progress.read().await[eth.sqd_slug].cursor, 10);
+        assert!(check_cursors(&storage, &progress, true).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn cycles_and_head_stamps_follow_the_storage_clock() {
+        let chain = kizami_shared::chains::chain_by_id(8453).unwrap();
+        let headers = kizami_fixtures::synthetic_chain(
+            chain,
+            &kizami_fixtures::SyntheticSpec {
+                blocks_per_chain: 100,
+                ..Default::default()
+            },
+        );
+        let start =
+            chrono::DateTime::from_timestamp(headers.last().unwrap().timestamp + 3600, 0).unwrap();
+        let portal =
+            kizami_fixtures::portal::MockPortal::spawn_growing(chain.sqd_slug, headers, 10).await;
+        let mock = Arc::new(MockClock::new(start));
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap().with_clock(mock.clone());
+        let progress: ProgressMap = Default::default();
+        let (stop, stopped) = oneshot::channel();
+        let config = IngestConfig {
+            interval_secs: 60,
+            worker_threads: 1,
+            max_blocking_threads: 1,
+            persist_policy: PersistPolicy::BufferOnly,
+            cursor_check_every: 0,
+            cursor_heal: false,
+            log_every_n_cycles: 1,
+            log_summary_interval: None,
+            sqd_requests_per_cycle: None,
+            head_poll_interval: None,
+            rpc: Default::default(),
+            timestamp_jump_alert_secs: None,
+            work_queue_interval: None,
+            catchup_lag_secs: None,
+            catchup_parallelism: 1,
+            error_log_size: 0,
+            sqd_daily_egress_budget: None,
+        };
+        let ingestion = tokio::spawn(run_ingestion_loop(
+            config,
+            storage.clone(),
+            SqdClient::with_base_url(portal.base_url()),
+            progress.clone(),
+            EventBus::default(),
+            JobHandle::detached("ingest", Duration::ZERO),
+            stopped,
+        ));
+
+        let cycles = || {
+            storage
+                .cycles_between(start, start + chrono::Duration::days(1))
+                .unwrap()
+                .len()
+        };
+        let wait_for_cycles = |n| async move {
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while cycles() < n {
+                assert!(Instant::now() < deadline, "cycle {n} never ran");
+                tokio::time::sleep(Duration::from_millis(10)).await;
             }
+        };
+        wait_for_cycles(1).await;
+        let first = progress.read().await[chain.sqd_slug].clone();
+        assert!(first.cursor > 0);
+        assert_eq!(first.head_advanced_at, Some(start));
+
+        // the next cycle waits on the mock clock, however long the wall clock runs
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(cycles(), 1);
+        mock.advance(Duration::from_secs(60));
+        wait_for_cycles(2).await;
+        let second = progress.read().await[chain.sqd_slug].clone();
+        assert!(second.cursor > first.cursor);
+        assert_eq!(
+            second.head_advanced_at,
+            Some(start + chrono::Duration::seconds(60))
+        );
+
+        stop.send(()).unwrap();
+        ingestion.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn poll_heads_refreshes_heads_between_cycles() {
+        let headers: Vec<_> = (0..50).map(|n| header(n, 1_700_000_000 + n)).collect();
+        let portal = kizami_fixtures::portal::MockPortal::spawn("base-mainnet", headers).await;
+        let progress: ProgressMap = Default::default();
+        let poller = tokio::spawn(poll_heads(
+            SqdClient::with_base_url(portal.base_url()),
+            RpcEndpoints::default(),
+            progress.clone(),
+            Duration::from_millis(10),
+            kizami_shared::clock::system(),
+        ));
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            let head = progress
+                .read()
+                .await
+                .get("base-mainnet")
+                .and_then(|p| p.head);
+            if head == Some(49) {
+                break;
+            }
+            assert!(Instant::now() < deadline, "head never polled");
+            tokio::time::sleep(Duration::from_millis(10)).await;
         }
+        poller.abort();
+        // block fetching never ran, so the cursor is untouched
+        assert_eq!(progress.read().await["base-mainnet"].cursor, 0);
+    }
+
+    #[test]
+    fn cycle_order_puts_tip_followers_first() {
+        let progress = |cursor, head| ChainProgress {
+            cursor,
+            head,
+            updated_at: None,
+            paused: false,
+            head_advanced_at: None,
+        };
+        let mut map = HashMap::new();
+        map.insert(
+            "ethereum-mainnet".to_string(),
+            progress(100, Some(5_000_000)),
+        );
+        map.insert("base-mainnet".to_string(), progress(1_000, Some(1_002)));
+        map.insert("arbitrum-one".to_string(), progress(0, None));
+
+        let order: Vec<_> = cycle_order(&map).iter().map(|c| c.sqd_slug).collect();
+        assert_eq!(order.len(), CHAINS.len());
+        assert_eq!(order[0], "base-mainnet");
+        assert_eq!(order[1], "ethereum-mainnet");
+        // no head yet: with the other unknown chains, after every known lag
+        let arbitrum = order.iter().position(|s| *s == "arbitrum-one").unwrap();
+        assert!(arbitrum > 1);
+    }
+
+    #[test]
+    fn ingest_ceiling_stops_at_sunset_block() {
+        let eth = kizami_shared::chains::chain_by_id(1).unwrap();
+        assert_eq!(ingest_ceiling(eth, 1000), 1000);
+
+        let retired = ChainConfig {
+            lifecycle: kizami_shared::chains::Lifecycle::Deprecated {
+                sunset_at: None,
+                sunset_block: Some(500),
+            },
+            ..*eth
+        };
+        assert_eq!(ingest_ceiling(&retired, 1000), 500);
+        assert_eq!(ingest_ceiling(&retired, 400), 400);
+    }
+
+    #[test]
+    fn previous_block_only_when_adjacent_to_the_batch() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        assert!(previous_block(&storage, 1, 1).is_none());
+
+        storage.insert_blocks(1, &[9, 10], &[90, 100]).unwrap();
+        let prev = previous_block(&storage, 1, 11).unwrap();
+        assert_eq!((prev.number, prev.timestamp), (10, 100));
+        assert!(previous_block(&storage, 1, 12).is_none());
+    }
+
+    #[test]
+    fn thread_counts_default_on_bad_input() {
+        let default = DEFAULT_INGEST_WORKER_THREADS;
+        assert_eq!(parse_thread_count(None, default), default);
+        assert_eq!(parse_thread_count(Some("0"), default), default);
+        assert_eq!(parse_thread_count(Some("many"), default), default);
+        assert_eq!(parse_thread_count(Some(" 4 "), default), 4);
+    }
+
+    #[test]
+    fn persist_policy_defaults_to_periodic() {
+        assert_eq!(PersistPolicy::parse(None, None), PersistPolicy::default());
+        assert_eq!(
+            PersistPolicy::parse(Some("periodic"), Some("10")),
+            PersistPolicy::Periodic { every_n_cycles: 10 }
+        );
+    }
+
+    #[test]
+    fn persist_policy_parses_modes() {
+        assert_eq!(
+            PersistPolicy::parse(Some("batch"), None),
+            PersistPolicy::EveryBatch
+        );
+        assert_eq!(
+            PersistPolicy::parse(Some("buffer"), None),
+            PersistPolicy::BufferOnly
+        );
+    }
+
+    #[test]
+    fn persist_policy_rejects_bad_input() {
+        assert_eq!(
+            PersistPolicy::parse(Some("sometimes"), Some("0")),
+            PersistPolicy::default()
+        );
     }
 }
@@ -0,0 +1,269 @@
+//! Draining the persisted backfill and repair queue.
+//!
+//! Ranges queued through `/v1/admin/chains/{id}/work-queue` live in the `work_queue`
+//! keyspace (see [`Storage::enqueue_work`]), so they survive restarts and deploys. The
+//! scheduler's `work_queue` job takes the highest-priority due range, fetches its next
+//! batch (up to 50k blocks) from SQD, validates and writes it the way the ingestion loop
+//! does, and records how far it got. Large ranges are worked off batch by batch across
+//! runs. The chain's cursor is never moved.
+//!
+//! A failed batch is retried with exponential backoff, up to [`MAX_ATTEMPTS`] times in
+//! a row; after that the range stays queued, with its last error, for an operator to
+//! look at and delete.
+//!
+//! Runs are skipped while the daily SQD egress budget is spent (see
+//! [`crate::restore_egress`]); the queue picks up again the next UTC day.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use kizami_shared::approximate;
+use kizami_shared::chains;
+use kizami_shared::error::AppError;
+use kizami_shared::scheduler::Scheduler;
+use kizami_shared::sqd::SqdClient;
+use kizami_shared::storage::{Storage, WorkItem};
+use kizami_shared::validation;
+
+use crate::events::EventBus;
+use crate::{insert_throttled, CursorAdvance, BATCH_SIZE};
+
+/// Failed attempts in a row after which a range is no longer retried.
+pub const MAX_ATTEMPTS: u32 = 8;
+
+/// Delay before the first retry; doubles per further failure.
+const RETRY_BASE: Duration = Duration::from_secs(30);
+
+/// Longest delay between retries.
+const RETRY_MAX: Duration = Duration::from_secs(60 * 60);
+
+/// Batches written per run, so one run can't hold SQD and storage for long.
+const BATCHES_PER_RUN: usize = 10;
+
+/// Delay before retrying a range that has failed `attempts` times in a row.
+fn retry_delay(attempts: u32) -> Duration {
+    let doublings = attempts.saturating_sub(1).min(16);
+    (RETRY_BASE * 2u32.pow(doublings)).min(RETRY_MAX)
+}
+
+/// Works off queued ranges.
+pub struct WorkQueue {
+    storage: Storage,
+    sqd_client: SqdClient,
+    events: EventBus,
+}
+
+impl WorkQueue {
+    /// Written windows are published on `events`, so cached lookups they change are
+    /// dropped.
+    pub fn new(storage: Storage, sqd_client: SqdClient, events: EventBus) -> Self {
+        Self {
+            storage,
+            sqd_client,
+            events,
+        }
+    }
+
+    /// Drains the queue every `interval` as the `work_queue` job.
+    pub fn schedule(self: Arc<Self>, scheduler: &Scheduler, interval: Duration) {
+        scheduler.spawn("work_queue", interval, interval / 10, move || {
+            let queue = self.clone();
+            async move { queue.run().await }
+        });
+    }
+
+    /// Processes up to [`BATCHES_PER_RUN`] batches of due ranges. Fails when any batch
+    /// failed.
+    async fn run(&self) -> Result<(), String> {
+        // queued ranges are deep backfill by nature, so they wait out a spent budget
+        if self.sqd_client.health().egress_budget_spent() {
+            tracing::debug!(
+                job = "work_queue",
+                "daily SQD egress budget spent, skipping"
+            );
+            return Ok(());
+        }
+        let mut failed = 0;
+        for _ in 0..BATCHES_PER_RUN {
+            let now = self.storage.clock().now();
+            let Some(mut item) = self
+                .storage
+                .next_work(now, MAX_ATTEMPTS)
+                .map_err(|e| e.to_string())?
+            else {
+                break;
+            };
+            match self.write_batch(&item).await {
+                Ok(last) if last >= item.to_block => {
+                    self.storage
+                        .remove_work(item.id)
+                        .map_err(|e| e.to_string())?;
+                    tracing::info!(
+                        job = "work_queue",
+                        id = item.id,
+                        kind = ?item.kind,
+                        chain_id = item.chain_id,
+                        to_block = item.to_block,
+                        "queued range done"
+                    );
+                }
+                Ok(last) => {
+                    item.from_block = last + 1;
+                    item.attempts = 0;
+                    item.last_error = None;
+                    self.storage.update_work(&item).map_err(|e| e.to_string())?;
+                }
+                Err(e) => {
+                    failed += 1;
+                    item.attempts += 1;
+                    item.last_error = Some(e.to_string());
+                    item.not_before = now
+                        + chrono::Duration::from_std(retry_delay(item.attempts))
+                            .unwrap_or_default();
+                    tracing::warn!(
+                        job = "work_queue",
+                        id = item.id,
+                        chain_id = item.chain_id,
+                        from_block = item.from_block,
+                        attempts = item.attempts,
+                        exhausted = item.attempts >= MAX_ATTEMPTS,
+                        error = %e,
+                        "queued range failed"
+                    );
+                    self.storage.update_work(&item).map_err(|e| e.to_string())?;
+                }
+            }
+        }
+        match failed {
+            0 => Ok(()),
+            n => Err(format!("{n} batches failed")),
+        }
+    }
+
+    /// Fetches and writes the next batch of `item`. Returns the last block covered.
+    async fn write_batch(&self, item: &WorkItem) -> Result<i64, AppError> {
+        let chain = chains::chain_by_id(item.chain_id)
+            .ok_or_else(|| AppError::ChainNotFound(item.chain_id.to_string()))?;
+        let to_block = (item.from_block + BATCH_SIZE - 1).min(item.to_block);
+        let blocks = self
+            .sqd_client
+            .fetch_blocks(chain.sqd_slug, item.from_block, to_block)
+            .await?;
+        // as in the ingestion loop, a short stream only covers what was received
+        let Some(last) = blocks.last().map(|b| b.number.min(to_block)) else {
+            return Err(AppError::SqdApi(format!(
+                "no blocks returned for {}..={to_block}",
+                item.from_block
+            )));
+        };
+
+        let (blocks, rejected) =
+            validation::partition_headers(chain, blocks, self.storage.clock().now().timestamp());
+        let blocks = match approximate::sample_every(chain.chain_id) {
+            Some(every) => approximate::sample_headers(blocks, every, false),
+            None => blocks,
+        };
+        if !rejected.is_empty() {
+            self.storage.insert_rejected(chain.chain_id, &rejected)?;
+        }
+        insert_throttled(&self.storage, chain.chain_id, &blocks).await?;
+
+        if let (Some(first), Some(last)) = (blocks.first(), blocks.last()) {
+            self.events.publish(CursorAdvance {
+                chain_id: chain.chain_id,
+                from_block: first.number,
+                to_block: last.number,
+                blocks: blocks.len() as u64,
+                from_timestamp: first.timestamp,
+                to_timestamp: last.timestamp,
+                at_head: false,
+            });
+        }
+        Ok(last)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kizami_fixtures::portal::MockPortal;
+    use kizami_fixtures::{synthetic_chain, SyntheticSpec};
+    use kizami_shared::clock::MockClock;
+    use kizami_shared::models::WorkKind;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn writes_queued_ranges_and_backs_off_failures() {
+        let chain = chains::chain_by_id(8453).unwrap();
+        let headers = synthetic_chain(
+            chain,
+            &SyntheticSpec {
+                blocks_per_chain: 100,
+                ..SyntheticSpec::default()
+            },
+        );
+        let portal = MockPortal::spawn(chain.sqd_slug, headers).await;
+        let dir = tempfile::tempdir().unwrap();
+        let start = chrono::DateTime::from_timestamp(1_800_000_000, 0).unwrap();
+        let clock = Arc::new(MockClock::new(start));
+        let storage = Storage::open(dir.path()).unwrap().with_clock(clock.clone());
+        let events = EventBus::default();
+        let mut advances = events.subscribe();
+        let queue = WorkQueue::new(
+            storage.clone(),
+            SqdClient::with_base_url(portal.base_url()),
+            events,
+        );
+
+        let range = storage
+            .enqueue_work(chain.chain_id, 10, 60, 0, WorkKind::Repair)
+            .unwrap();
+        // an unknown chain fails, but doesn't hold up the rest of the queue
+        let broken = storage
+            .enqueue_work(-1, 0, 5, 9, WorkKind::Backfill)
+            .unwrap();
+        assert_eq!(queue.run().await, Err("1 batches failed".into()));
+
+        let stored = storage
+            .scan_blocks(chain.chain_id, (0, 0), i64::MAX, 1000)
+            .unwrap();
+        let numbers: Vec<i64> = stored.iter().map(|&(number, _)| number).collect();
+        assert_eq!(numbers, (10..=60).collect::<Vec<_>>());
+        assert_eq!(storage.get_cursor(chain.chain_id).unwrap(), 0);
+        let advance = advances.try_recv().unwrap();
+        assert_eq!((advance.from_block, advance.to_block), (10, 60));
+        assert_eq!((advance.blocks, advance.at_head), (51, false));
+
+        let remaining = storage.work_items().unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].id, broken.id);
+        assert_eq!(remaining[0].attempts, 1);
+        assert_eq!(
+            remaining[0].not_before,
+            start + chrono::Duration::seconds(30)
+        );
+        assert_eq!(
+            remaining[0].last_error.as_deref(),
+            Some("chain -1 not found")
+        );
+        assert_ne!(range.id, broken.id);
+
+        // not retried before its backoff is up, then backed off twice as long
+        assert_eq!(queue.run().await, Ok(()));
+        assert_eq!(storage.work_items().unwrap()[0].attempts, 1);
+        clock.advance(Duration::from_secs(30));
+        assert_eq!(queue.run().await, Err("1 batches failed".into()));
+        let retried = &storage.work_items().unwrap()[0];
+        assert_eq!(retried.attempts, 2);
+        assert_eq!(retried.not_before, start + chrono::Duration::seconds(90));
+    }
+
+    #[test]
+    fn retries_back_off_exponentially_up_to_an_hour() {
+        assert_eq!(retry_delay(1), Duration::from_secs(30));
+        assert_eq!(retry_delay(2), Duration::from_secs(60));
+        assert_eq!(retry_delay(4), Duration::from_secs(240));
+        assert_eq!(retry_delay(8), Duration::from_secs(3600));
+        assert_eq!(retry_delay(u32::MAX), Duration::from_secs(3600));
+    }
+}
@@ -0,0 +1,326 @@
+//! Opt-in approximate lookup mode.
+//!
+//! Chains listed in `APPROXIMATE_CHAINS` as `chain_id:N` pairs (e.g. `137:100,56:1000`)
+//! only store every Nth block, plus the last block of each ingestion batch and the
+//! first block of the dataset, cutting their footprint roughly N times. Lookups on
+//! them find the two stored samples around the timestamp and interpolate linearly
+//! between them. Answers that fall between samples are flagged `approximate`; answers
+//! that land on a stored block are exact.
+//!
+//! Switching a chain into this mode only affects blocks ingested afterwards. Already
+//! stored blocks stay, so a chain migrated in place keeps exact answers for its history.
+//!
+//! [`extrapolate`] goes the other way, past the last stored block of any chain, for
+//! lookups that ask for an estimate rather than a `NOT_YET_INDEXED` answer.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use crate::chains::CHAINS;
+use crate::error::AppError;
+use crate::models::{Bound, Direction};
+use crate::sqd::BlockHeader;
+use crate::storage::ReadStorage;
+
+/// Sampling table from `APPROXIMATE_CHAINS`, read once on first access.
+static SAMPLING: LazyLock<HashMap<i32, i64>> = LazyLock::new(|| {
+    std::env::var("APPROXIMATE_CHAINS")
+        .map(|v| parse_sampling(&v))
+        .unwrap_or_default()
+});
+
+/// Parses `chain_id:N` pairs separated by commas. Malformed pairs, unknown chains and
+/// intervals below 2 are skipped with a warning.
+pub fn parse_sampling(raw: &str) -> HashMap<i32, i64> {
+    let mut sampling = HashMap::new();
+    for pair in raw.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let parsed = pair.split_once(':').and_then(|(chain, every)| {
+            Some((chain.trim().parse().ok()?, every.trim().parse().ok()?))
+        });
+        match parsed {
+            Some((chain_id, every))
+                if every >= 2 && CHAINS.iter().any(|c| c.chain_id == chain_id) =>
+            {
+                sampling.insert(chain_id, every);
+            }
+            _ => tracing::warn!(entry = pair, "ignoring invalid APPROXIMATE_CHAINS entry"),
+        }
+    }
+    sampling
+}
+
+/// The sampling interval for a chain, or `None` if it stores every block.
+pub fn sample_every(chain_id: i32) -> Option<i64> {
+    SAMPLING.get(&chain_id).copied()
+}
+
+/// Chains in approximate mode as `(chain_id, every)` pairs, sorted.
+pub fn sampled_chains() -> Vec<(i32, i64)> {
+    let mut chains: Vec<_> = SAMPLING.iter().map(|(&id, &every)| (id, every)).collect();
+    chains.sort_unstable();
+    chains
+}
+
+/// Keeps every `every`th block of an ingestion batch plus its last block, which
+/// anchors interpolation at the indexed tip. `keep_first` also keeps the first block,
+/// for a chain's first batch.
+pub fn sample_headers(headers: Vec<BlockHeader>, every: i64, keep_first: bool) -> Vec<BlockHeader> {
+    let last = headers.len().saturating_sub(1);
+    headers
+        .into_iter()
+        .enumerate()
+        .filter(|(i, h)| h.number % every == 0 || *i == last || (keep_first && *i == 0))
+        .map(|(_, h)| h)
+        .collect()
+}
+
+/// A lookup answer on an approximate chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Estimate {
+    pub number: i64,
+    pub timestamp: i64,
+    /// True when the block was interpolated rather than read from storage.
+    pub approximate: bool,
+}
+
+/// The two storage probes bracketing a lookup: the stored block the exact lookup would
+/// return, and its neighbour on the other side of the timestamp.
+fn probes(timestamp: i64, bound: Bound) -> [(i64, Bound); 2] {
+    // at-or-before (ts <= t) pairs with after (ts > t) and before with at-or-after, so
+    // the two results are adjacent in key order
+    let pair = match bound {
+        Bound::AtOrBefore | Bound::After | Bound::Nearest => [Bound::AtOrBefore, Bound::After],
+        Bound::Before | Bound::AtOrAfter => [Bound::Before, Bound::AtOrAfter],
+    };
+    pair.map(|b| (timestamp, b))
+}
+
+/// Estimates the answer to a lookup from the stored samples `lo` (last block on the
+/// before side) and `hi` (first block on the after side), as `(number, timestamp)`.
+/// `nearest` estimates both sides and takes the closer.
+pub fn estimate(
+    lo: Option<(i64, i64)>,
+    hi: Option<(i64, i64)>,
+    timestamp: i64,
+    direction: Direction,
+) -> Option<Estimate> {
+    match direction {
+        Direction::Before => estimate_side(lo, hi, timestamp, false),
+        Direction::After => estimate_side(lo, hi, timestamp, true),
+        Direction::Nearest => Direction::nearest_of(
+            timestamp,
+            estimate_side(lo, hi, timestamp, false),
+            estimate_side(lo, hi, timestamp, true),
+            |e| e.timestamp,
+        ),
+    }
+}
+
+/// [`estimate`] for a `before` lookup, or an `after` one with `after` set.
+fn estimate_side(
+    lo: Option<(i64, i64)>,
+    hi: Option<(i64, i64)>,
+    timestamp: i64,
+    after: bool,
+) -> Option<Estimate> {
+    let exact = |(number, timestamp): (i64, i64)| Estimate {
+        number,
+        timestamp,
+        approximate: false,
+    };
+    let (lo, hi) = match (after, lo, hi) {
+        (false, None, _) | (true, _, None) => return None,
+        // past the tip or before the first block: samples are kept at both ends
+        (false, Some(lo), None) => return Some(exact(lo)),
+        (true, None, Some(hi)) => return Some(exact(hi)),
+        (_, Some(lo), Some(hi)) => (lo, hi),
+    };
+
+    let gap = hi.0 - lo.0;
+    // one side of the pair is a strict bound, so hi is strictly later than lo
+    let span = (hi.1 - lo.1).max(1) as f64;
+    let at = lo.0 as f64 + (timestamp - lo.1) as f64 * gap as f64 / span;
+    let number = if after {
+        (at.ceil() as i64).clamp((lo.0 + 1).min(hi.0), hi.0)
+    } else {
+        (at.floor() as i64).clamp(lo.0, (hi.0 - 1).max(lo.0))
+    };
+    if number == lo.0 {
+        return Some(exact(lo));
+    }
+    if number == hi.0 {
+        return Some(exact(hi));
+    }
+
+    let ts = lo.1 as f64 + (number - lo.0) as f64 * span / gap as f64;
+    let ts = if after { ts.ceil() } else { ts.floor() };
+    Some(Estimate {
+        number,
+        timestamp: (ts as i64).clamp(lo.1, hi.1),
+        approximate: true,
+    })
+}
+
+/// Estimates the answer to a lookup on a timestamp past the chain's last stored block
+/// `last`, as `(number, timestamp)`, assuming a block every `block_time` seconds from
+/// there. Never earlier than `last`, which a `before` lookup less than a block time
+/// past it gets back unchanged.
+pub fn extrapolate(last: (i64, i64), block_time: f64, timestamp: i64, bound: Bound) -> (i64, i64) {
+    let side = |after| extrapolate_side(last, block_time, timestamp, bound.inclusive(), after);
+    match bound.direction() {
+        Direction::Before => side(false),
+        Direction::After => side(true),
+        Direction::Nearest => {
+            Direction::nearest_of(timestamp, Some(side(false)), Some(side(true)), |b| b.1)
+                .unwrap_or(last)
+        }
+    }
+}
+
+/// [`extrapolate`] for a `before` lookup, or an `after` one with `after` set.
+fn extrapolate_side(
+    last: (i64, i64),
+    block_time: f64,
+    timestamp: i64,
+    inclusive: bool,
+    after: bool,
+) -> (i64, i64) {
+    let blocks = (timestamp - last.1) as f64 / block_time;
+    // an estimated block lands exactly on the timestamp, which exclusive lookups skip
+    let skip = i64::from(!inclusive && blocks.fract() == 0.0);
+    let ahead = if after {
+        (blocks.ceil() as i64 + skip).max(1)
+    } else {
+        (blocks.floor() as i64 - skip).max(0)
+    };
+    let ts = last.1 as f64 + ahead as f64 * block_time;
+    let ts = if after { ts.ceil() } else { ts.floor() };
+    (last.0 + ahead, ts as i64)
+}
+
+/// Answers lookups on an approximate chain, in input order. Costs the same single
+/// multi-lookup pass as exact lookups, with two probes per query.
+pub fn find_blocks(
+    storage: &ReadStorage,
+    chain_id: i32,
+    queries: &[(i64, Bound)],
+) -> Result<Vec<Option<Estimate>>, AppError> {
+    let probes: Vec<_> = queries
+        .iter()
+        .flat_map(|&(ts, bound)| probes(ts, bound))
+        .collect();
+    let rows = storage.find_blocks_multi(chain_id, &probes)?;
+    Ok(queries
+        .iter()
+        .zip(rows.chunks(2))
+        .map(|(&(ts, bound), pair)| estimate(pair[0], pair[1], ts, bound.direction()))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Storage;
+
+    fn header(number: i64) -> BlockHeader {
+        BlockHeader {
+            number,
+            timestamp: 1000 + number * 2,
+        }
+    }
+
+    #[test]
+    fn parses_sampling_and_skips_bad_entries() {
+        let sampling = parse_sampling("137:100, 56:1000, 1:1, 999999:10, garbage");
+        assert_eq!(sampling.len(), 2);
+        assert_eq!(sampling[&137], 100);
+        assert_eq!(sampling[&56], 1000);
+    }
+
+    #[test]
+    fn samples_keep_multiples_and_batch_ends() {
+        let headers: Vec<_> = (5..=25).map(header).collect();
+        let numbers = |v: Vec<BlockHeader>| v.iter().map(|h| h.number).collect::<Vec<_>>();
+        assert_eq!(
+            numbers(sample_headers(headers.clone(), 10, false)),
+            vec![10, 20, 25]
+        );
+        assert_eq!(
+            numbers(sample_headers(headers, 10, true)),
+            vec![5, 10, 20, 25]
+        );
+    }
+
+    #[test]
+    fn interpolates_between_samples() {
+        // blocks 100..=200 at 2s each, only the ends stored
+        let (lo, hi) = ((100, 1000), (200, 1200));
+        let before = estimate(Some(lo), Some(hi), 1101, Direction::Before).unwrap();
+        assert_eq!(
+            before,
+            Estimate {
+                number: 150,
+                timestamp: 1100,
+                approximate: true
+            }
+        );
+        let after = estimate(Some(lo), Some(hi), 1101, Direction::After).unwrap();
+        assert_eq!((after.number, after.timestamp), (151, 1102));
+
+        // landing on a sample is exact
+        let at_lo = estimate(Some(lo), Some(hi), 1000, Direction::Before).unwrap();
+        assert!(!at_lo.approximate);
+        assert_eq!(at_lo.number, 100);
+    }
+
+    #[test]
+    fn ends_of_the_chain() {
+        assert_eq!(estimate(None, Some((0, 10)), 5, Direction::Before), None);
+        assert_eq!(estimate(Some((9, 90)), None, 95, Direction::After), None);
+        let tip = estimate(Some((9, 90)), None, 95, Direction::Before).unwrap();
+        assert_eq!((tip.number, tip.approximate), (9, false));
+    }
+
+    #[test]
+    fn extrapolates_past_the_last_block() {
+        let last = (100, 1000);
+        let at = |ts, bound| extrapolate(last, 12.0, ts, bound);
+        assert_eq!(at(1030, Bound::Before), (102, 1024));
+        assert_eq!(at(1030, Bound::After), (103, 1036));
+        assert_eq!(at(1031, Bound::Nearest), (103, 1036));
+        // exactly on an estimated block
+        assert_eq!(at(1024, Bound::AtOrBefore), (102, 1024));
+        assert_eq!(at(1024, Bound::Before), (101, 1012));
+        assert_eq!(at(1024, Bound::After), (103, 1036));
+        assert_eq!(at(1024, Bound::Nearest), (102, 1024));
+        // within a block time of the tip
+        assert_eq!(at(1005, Bound::Before), last);
+        assert_eq!(at(1000, Bound::After), (101, 1012));
+    }
+
+    #[test]
+    fn find_blocks_matches_exact_storage_on_samples() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        let sampled = sample_headers((0..=1000).map(header).collect(), 100, true);
+        storage.insert_block_headers(1, &sampled).unwrap();
+
+        let results = find_blocks(
+            &storage.reader(),
+            1,
+            &[
+                (1000 + 2 * 300, Bound::AtOrBefore),
+                (1000 + 2 * 300, Bound::Before),
+                (1000 + 2 * 355, Bound::AtOrAfter),
+                (999, Bound::AtOrBefore),
+            ],
+        )
+        .unwrap();
+        assert_eq!(results[0].unwrap().number, 300);
+        assert!(!results[0].unwrap().approximate);
+        assert_eq!(results[1].unwrap().number, 299);
+        assert!(results[1].unwrap().approximate);
+        assert_eq!(results[2].unwrap().number, 355);
+        assert!(results[3].is_none());
+    }
+}
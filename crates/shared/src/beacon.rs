@@ -0,0 +1,70 @@
+//! Ethereum beacon chain slot and epoch arithmetic.
+//!
+//! Slots are 12 seconds starting at beacon genesis and epochs are 32 slots, so slot and
+//! epoch boundaries are pure functions of time. Mapping them to execution blocks uses
+//! the regular block index (see `routes::beacon` in the API crate).
+
+/// Execution chain the beacon chain drives.
+pub const ETHEREUM_CHAIN_ID: i32 = 1;
+
+/// Beacon chain genesis (2020-12-01 12:00:23 UTC).
+pub const BEACON_GENESIS_TIMESTAMP: i64 = 1606824023;
+
+pub const SECONDS_PER_SLOT: i64 = 12;
+pub const SLOTS_PER_EPOCH: i64 = 32;
+
+/// First slot after the merge; earlier slots have no execution payload.
+pub const MERGE_SLOT: i64 = 4700013;
+
+/// Start time of a slot.
+pub fn slot_timestamp(slot: i64) -> Option<i64> {
+    slot.checked_mul(SECONDS_PER_SLOT)?
+        .checked_add(BEACON_GENESIS_TIMESTAMP)
+}
+
+/// Slot in progress at `timestamp`, or `None` before beacon genesis.
+pub fn slot_at(timestamp: i64) -> Option<i64> {
+    let since_genesis = timestamp.checked_sub(BEACON_GENESIS_TIMESTAMP)?;
+    (since_genesis >= 0).then_some(since_genesis / SECONDS_PER_SLOT)
+}
+
+pub fn epoch_of(slot: i64) -> i64 {
+    slot / SLOTS_PER_EPOCH
+}
+
+/// First and last slot of an epoch.
+pub fn epoch_slots(epoch: i64) -> Option<(i64, i64)> {
+    let first = epoch.checked_mul(SLOTS_PER_EPOCH)?;
+    Some((first, first.checked_add(SLOTS_PER_EPOCH - 1)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slot_timestamp_round_trips() {
+        assert_eq!(slot_timestamp(0), Some(BEACON_GENESIS_TIMESTAMP));
+        assert_eq!(slot_at(BEACON_GENESIS_TIMESTAMP + 11), Some(0));
+        assert_eq!(slot_at(BEACON_GENESIS_TIMESTAMP + 12), Some(1));
+        assert_eq!(slot_at(BEACON_GENESIS_TIMESTAMP - 1), None);
+        assert_eq!(
+            slot_at(slot_timestamp(MERGE_SLOT).unwrap()),
+            Some(MERGE_SLOT)
+        );
+    }
+
+    #[test]
+    fn merge_slot_matches_first_pos_block_time() {
+        // block 15537394, the first proof-of-stake block, has timestamp 1663224179
+        assert_eq!(slot_timestamp(MERGE_SLOT), Some(1663224179));
+    }
+
+    #[test]
+    fn epoch_boundaries() {
+        assert_eq!(epoch_of(31), 0);
+        assert_eq!(epoch_of(32), 1);
+        assert_eq!(epoch_slots(146875), Some((4700000, 4700031)));
+        assert_eq!(epoch_slots(i64::MAX), None);
+    }
+}
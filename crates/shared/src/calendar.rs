@@ -0,0 +1,169 @@
+//! Calendar arithmetic for date-based block lookups.
+//!
+//! Converts local calendar dates in an IANA timezone to Unix second ranges, handling
+//! DST: days are 23 or 25 hours around transitions, and in zones where a transition
+//! skips midnight the day starts at the first valid local time.
+
+use chrono::{Days, Months, NaiveDate, NaiveTime, TimeZone};
+use chrono_tz::Tz;
+
+use crate::error::AppError;
+
+/// Parses an IANA timezone name such as `America/New_York`, or `UTC`.
+pub fn parse_tz(name: &str) -> Result<Tz, AppError> {
+    name.parse()
+        .map_err(|_| AppError::InvalidTimezone(name.to_string()))
+}
+
+/// Parses a `YYYY-MM-DD` date.
+pub fn parse_date(date: &str) -> Result<NaiveDate, AppError> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| AppError::InvalidDate(date.to_string()))
+}
+
+/// First Unix second of a local date in `tz`.
+pub fn local_midnight(date: NaiveDate, tz: Tz) -> Result<i64, AppError> {
+    // a DST gap can swallow midnight; the day then starts at the first valid hour
+    (0..=2)
+        .filter_map(|hour| {
+            let time = NaiveTime::from_hms_opt(hour, 0, 0)?;
+            tz.from_local_datetime(&date.and_time(time)).earliest()
+        })
+        .map(|dt| dt.timestamp())
+        .next()
+        .ok_or_else(|| AppError::InvalidDate(date.to_string()))
+}
+
+/// Unix second range `[start, end)` covering `date` in `tz`.
+pub fn day_bounds(date: NaiveDate, tz: Tz) -> Result<(i64, i64), AppError> {
+    let next = date
+        .checked_add_days(Days::new(1))
+        .ok_or_else(|| AppError::InvalidDate(date.to_string()))?;
+    Ok((local_midnight(date, tz)?, local_midnight(next, tz)?))
+}
+
+/// Parses a calendar period (`2024`, `2024-Q1`, `2024-06` or `2024-06-01`) into its
+/// date range `[first, end)`.
+pub fn parse_period(period: &str) -> Result<(NaiveDate, NaiveDate), AppError> {
+    let invalid = || AppError::InvalidPeriod(period.to_string());
+    let (year, rest) = period.split_once('-').unwrap_or((period, ""));
+    let digits = |s: &str, len: usize| s.len() == len && s.bytes().all(|b| b.is_ascii_digit());
+    if !digits(year, 4) {
+        return Err(invalid());
+    }
+    let year: i32 = year.parse().map_err(|_| invalid())?;
+
+    let (month, months) = match rest {
+        "" => (1, 12),
+        q if q.len() == 2 && q.starts_with('Q') => match &q[1..] {
+            "1" => (1, 3),
+            "2" => (4, 3),
+            "3" => (7, 3),
+            "4" => (10, 3),
+            _ => return Err(invalid()),
+        },
+        m if digits(m, 2) => (m.parse().map_err(|_| invalid())?, 1),
+        _ => {
+            let date = parse_date(period).map_err(|_| invalid())?;
+            let next = date.checked_add_days(Days::new(1)).ok_or_else(invalid)?;
+            return Ok((date, next));
+        }
+    };
+    let first = NaiveDate::from_ymd_opt(year, month, 1).ok_or_else(invalid)?;
+    let end = first
+        .checked_add_months(Months::new(months))
+        .ok_or_else(invalid)?;
+    Ok((first, end))
+}
+
+/// Unix second range `[start, end)` covering `period` in `tz`.
+pub fn period_bounds(period: &str, tz: Tz) -> Result<(i64, i64), AppError> {
+    let (first, end) = parse_period(period)?;
+    Ok((local_midnight(first, tz)?, local_midnight(end, tz)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds(date: &str, tz: &str) -> (i64, i64) {
+        day_bounds(parse_date(date).unwrap(), parse_tz(tz).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn utc_day_is_86400_seconds() {
+        // 2024-06-01T00:00:00Z
+        assert_eq!(bounds("2024-06-01", "UTC"), (1717200000, 1717286400));
+    }
+
+    #[test]
+    fn new_york_summer_day_is_offset_by_four_hours() {
+        let (start, end) = bounds("2024-06-01", "America/New_York");
+        assert_eq!(start, 1717200000 + 4 * 3600);
+        assert_eq!(end - start, 86400);
+    }
+
+    #[test]
+    fn dst_transition_days_are_23_and_25_hours() {
+        let (start, end) = bounds("2024-03-10", "America/New_York");
+        assert_eq!(end - start, 23 * 3600);
+        let (start, end) = bounds("2024-11-03", "America/New_York");
+        assert_eq!(end - start, 25 * 3600);
+    }
+
+    #[test]
+    fn skipped_midnight_starts_at_first_valid_hour() {
+        // Brazil moved clocks from 00:00 to 01:00 on 2018-11-04
+        let (start, _) = bounds("2018-11-04", "America/Sao_Paulo");
+        assert_eq!(start, 1541300400); // 2018-11-04T01:00:00-02:00
+    }
+
+    #[test]
+    fn periods_cover_whole_months() {
+        let utc = parse_tz("UTC").unwrap();
+        // 2024-01-01T00:00Z .. 2024-04-01T00:00Z
+        assert_eq!(
+            period_bounds("2024-Q1", utc).unwrap(),
+            (1704067200, 1711929600)
+        );
+        assert_eq!(
+            period_bounds("2024-06", utc).unwrap(),
+            (1717200000, 1719792000)
+        );
+        assert_eq!(
+            period_bounds("2024", utc).unwrap(),
+            (1704067200, 1735689600)
+        );
+        assert_eq!(
+            period_bounds("2024-06-01", utc).unwrap(),
+            bounds("2024-06-01", "UTC")
+        );
+    }
+
+    #[test]
+    fn rejects_bad_periods() {
+        for period in [
+            "2024-Q5",
+            "2024-13",
+            "24-06",
+            "2024-6",
+            "2024-+6",
+            "2024-06-31",
+            "Q1-2024",
+        ] {
+            assert_eq!(
+                parse_period(period).unwrap_err().code(),
+                "INVALID_PERIOD",
+                "{period}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        assert_eq!(
+            parse_tz("Mars/Olympus").unwrap_err().code(),
+            "INVALID_TIMEZONE"
+        );
+        assert_eq!(parse_date("2024-13-01").unwrap_err().code(), "INVALID_DATE");
+    }
+}
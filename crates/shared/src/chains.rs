@@ -3,10 +3,20 @@
 //! All 29 chains are defined as compile-time constants with zero-allocation lookups
 //! via `LazyLock<HashMap>`. Genesis timestamps are sourced from on-chain RPC
 //! (`eth_getBlockByNumber`); where block 0 has timestamp 0, block 1 is used instead.
+//!
+//! Retired chain ids can be redirected to another chain's data (after a network
+//! migration or dataset merge) with `CHAIN_ALIASES`, e.g. `CHAIN_ALIASES=1101:137`.
+//! [`chain_by_id`] follows aliases, so old ids keep resolving everywhere.
+//!
+//! A built-in genesis timestamp can be replaced with `GENESIS_TIMESTAMPS`, e.g.
+//! `GENESIS_TIMESTAMPS=8453:1686789347`. Validation and the genesis check of fresh
+//! chains use [`ChainConfig::effective_genesis_timestamp`], which honours it.
 
 use std::collections::HashMap;
 use std::sync::LazyLock;
 
+use crate::sqd::BlockHeader;
+
 /// Configuration for a single EVM chain.
 ///
 /// All fields are `&'static str` or Copy types, so lookups never allocate.
@@ -21,6 +31,90 @@ pub struct ChainConfig {
     pub sqd_slug: &'static str,
     /// Unix timestamp of the chain's genesis block (or block 1 if block 0 is 0).
     pub genesis_timestamp: i64,
+    /// Whether the chain is still supported or on its way out.
+    pub lifecycle: Lifecycle,
+}
+
+/// Support status of a chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lifecycle {
+    Active,
+    /// The chain has shut down or migrated (e.g. a rebrand). Lookups keep working but
+    /// carry a `Deprecation` header, and ingestion stops at `sunset_block` if set.
+    Deprecated {
+        /// Unix timestamp after which the chain may be removed from the API.
+        sunset_at: Option<i64>,
+        /// Last block that will be ingested.
+        sunset_block: Option<i64>,
+    },
+}
+
+impl ChainConfig {
+    pub fn is_deprecated(&self) -> bool {
+        matches!(self.lifecycle, Lifecycle::Deprecated { .. })
+    }
+
+    /// True for chains with sub-second block times, where many blocks share each
+    /// timestamp and `tie` matters.
+    pub fn shares_timestamps(&self) -> bool {
+        // Arbitrum One, opBNB, Sonic, Monad
+        matches!(self.chain_id, 42161 | 204 | 146 | 143)
+    }
+
+    /// Unix timestamp after which the chain may be removed, if announced.
+    pub fn sunset_at(&self) -> Option<i64> {
+        match self.lifecycle {
+            Lifecycle::Deprecated { sunset_at, .. } => sunset_at,
+            Lifecycle::Active => None,
+        }
+    }
+
+    /// Last block ingestion will write, if the chain has a sunset block.
+    pub fn sunset_block(&self) -> Option<i64> {
+        match self.lifecycle {
+            Lifecycle::Deprecated { sunset_block, .. } => sunset_block,
+            Lifecycle::Active => None,
+        }
+    }
+
+    /// Genesis timestamp in use: the `GENESIS_TIMESTAMPS` override if there is one,
+    /// otherwise the built-in `genesis_timestamp`.
+    pub fn effective_genesis_timestamp(&self) -> i64 {
+        GENESIS_OVERRIDES
+            .get(&self.chain_id)
+            .copied()
+            .unwrap_or(self.genesis_timestamp)
+    }
+
+    /// Checks a chain's first headers, sorted by number, against the genesis timestamp
+    /// in use.
+    ///
+    /// If the headers start at genesis (see [`derive_genesis`]) its timestamp must match.
+    /// If the dataset starts after genesis, the first block must at least not predate
+    /// it. Returns the offending `(number, timestamp)` on mismatch.
+    pub fn genesis_mismatch(&self, headers: &[BlockHeader]) -> Option<(i64, i64)> {
+        let genesis = self.effective_genesis_timestamp();
+        match derive_genesis(headers) {
+            Some(h) if h.timestamp != genesis => Some((h.number, h.timestamp)),
+            Some(_) => None,
+            None => headers
+                .first()
+                .filter(|h| h.timestamp < genesis)
+                .map(|h| (h.number, h.timestamp)),
+        }
+    }
+}
+
+/// The genesis block among a chain's first headers, sorted by number: block 0, or block
+/// 1 when block 0 has timestamp 0. `None` if the headers don't start at block 0.
+pub fn derive_genesis(headers: &[BlockHeader]) -> Option<&BlockHeader> {
+    match headers {
+        [first, second, ..] if first.number == 0 && first.timestamp == 0 && second.number == 1 => {
+            Some(second)
+        }
+        [first, ..] if first.number == 0 => Some(first),
+        _ => None,
+    }
 }
 
 /// All supported chains, ordered roughly by volume (heavy chains first).
@@ -31,24 +125,28 @@ pub static CHAINS: &[ChainConfig] = &[
         chain_id: 137,
         sqd_slug: "polygon-mainnet",
         genesis_timestamp: 1590824836,
+        lifecycle: Lifecycle::Active,
     },
     ChainConfig {
         name: "BNB Smart Chain",
         chain_id: 56,
         sqd_slug: "binance-mainnet",
         genesis_timestamp: 1587390414,
+        lifecycle: Lifecycle::Active,
     },
     ChainConfig {
         name: "Arbitrum One",
         chain_id: 42161,
         sqd_slug: "arbitrum-one",
         genesis_timestamp: 1622243344,
+        lifecycle: Lifecycle::Active,
     },
     ChainConfig {
         name: "opBNB",
         chain_id: 204,
         sqd_slug: "opbnb-mainnet",
         genesis_timestamp: 1691753723,
+        lifecycle: Lifecycle::Active,
     },
     // ethereum + medium chains
     ChainConfig {
@@ -56,60 +154,70 @@ pub static CHAINS: &[ChainConfig] = &[
         chain_id: 1,
         sqd_slug: "ethereum-mainnet",
         genesis_timestamp: 1438269988,
+        lifecycle: Lifecycle::Active,
     },
     ChainConfig {
         name: "Base",
         chain_id: 8453,
         sqd_slug: "base-mainnet",
         genesis_timestamp: 1686789347,
+        lifecycle: Lifecycle::Active,
     },
     ChainConfig {
         name: "Optimism",
         chain_id: 10,
         sqd_slug: "optimism-mainnet",
         genesis_timestamp: 1636665399,
+        lifecycle: Lifecycle::Active,
     },
     ChainConfig {
         name: "Avalanche",
         chain_id: 43114,
         sqd_slug: "avalanche-mainnet",
         genesis_timestamp: 1600858926,
+        lifecycle: Lifecycle::Active,
     },
     ChainConfig {
         name: "Mantle",
         chain_id: 5000,
         sqd_slug: "mantle-mainnet",
         genesis_timestamp: 1688314886,
+        lifecycle: Lifecycle::Active,
     },
     ChainConfig {
         name: "Gnosis",
         chain_id: 100,
         sqd_slug: "gnosis-mainnet",
         genesis_timestamp: 1539024185,
+        lifecycle: Lifecycle::Active,
     },
     ChainConfig {
         name: "Linea",
         chain_id: 59144,
         sqd_slug: "linea-mainnet",
         genesis_timestamp: 1670496243,
+        lifecycle: Lifecycle::Active,
     },
     ChainConfig {
         name: "Scroll",
         chain_id: 534352,
         sqd_slug: "scroll-mainnet",
         genesis_timestamp: 1696917600,
+        lifecycle: Lifecycle::Active,
     },
     ChainConfig {
         name: "zkSync Era",
         chain_id: 324,
         sqd_slug: "zksync-mainnet",
         genesis_timestamp: 1676384542,
+        lifecycle: Lifecycle::Active,
     },
     ChainConfig {
         name: "Sonic",
         chain_id: 146,
         sqd_slug: "sonic-mainnet",
         genesis_timestamp: 1733011200,
+        lifecycle: Lifecycle::Active,
     },
     // lower-volume chains
     ChainConfig {
@@ -117,90 +225,105 @@ pub static CHAINS: &[ChainConfig] = &[
         chain_id: 169,
         sqd_slug: "manta-pacific",
         genesis_timestamp: 1694223959,
+        lifecycle: Lifecycle::Active,
     },
     ChainConfig {
         name: "Metis",
         chain_id: 1088,
         sqd_slug: "metis-mainnet",
         genesis_timestamp: 1637270379,
+        lifecycle: Lifecycle::Active,
     },
     ChainConfig {
         name: "Blast",
         chain_id: 81457,
         sqd_slug: "blast-l2-mainnet",
         genesis_timestamp: 1708809815,
+        lifecycle: Lifecycle::Active,
     },
     ChainConfig {
         name: "BOB",
         chain_id: 60808,
         sqd_slug: "bob-mainnet",
         genesis_timestamp: 1712861987,
+        lifecycle: Lifecycle::Active,
     },
     ChainConfig {
         name: "Berachain",
         chain_id: 80094,
         sqd_slug: "berachain-mainnet",
         genesis_timestamp: 1737381600,
+        lifecycle: Lifecycle::Active,
     },
     ChainConfig {
         name: "Unichain",
         chain_id: 130,
         sqd_slug: "unichain-mainnet",
         genesis_timestamp: 1730748359,
+        lifecycle: Lifecycle::Active,
     },
     ChainConfig {
         name: "Flare",
         chain_id: 14,
         sqd_slug: "flare-mainnet",
         genesis_timestamp: 1657740761,
+        lifecycle: Lifecycle::Active,
     },
     ChainConfig {
         name: "Etherlink",
         chain_id: 42793,
         sqd_slug: "etherlink-mainnet",
         genesis_timestamp: 1714656294,
+        lifecycle: Lifecycle::Active,
     },
     ChainConfig {
         name: "Core",
         chain_id: 1116,
         sqd_slug: "core-mainnet",
         genesis_timestamp: 1637052000,
+        lifecycle: Lifecycle::Active,
     },
     ChainConfig {
         name: "Taiko",
         chain_id: 167000,
         sqd_slug: "taiko-mainnet",
         genesis_timestamp: 1716620627,
+        lifecycle: Lifecycle::Active,
     },
     ChainConfig {
         name: "Ink",
         chain_id: 57073,
         sqd_slug: "ink-mainnet",
         genesis_timestamp: 1733498411,
+        lifecycle: Lifecycle::Active,
     },
     ChainConfig {
         name: "Merlin",
         chain_id: 4200,
         sqd_slug: "merlin-mainnet",
         genesis_timestamp: 1706877604,
+        lifecycle: Lifecycle::Active,
     },
     ChainConfig {
         name: "Celo",
         chain_id: 42220,
         sqd_slug: "celo-mainnet",
         genesis_timestamp: 1587571200,
+        lifecycle: Lifecycle::Active,
     },
     ChainConfig {
         name: "Zora",
         chain_id: 7777777,
         sqd_slug: "zora-mainnet",
         genesis_timestamp: 1686693839,
+        lifecycle: Lifecycle::Active,
     },
     ChainConfig {
         name: "Monad",
         chain_id: 143,
         sqd_slug: "monad-mainnet",
         genesis_timestamp: 1747232689,
+        lifecycle: Lifecycle::Active,
     },
 ];
 
@@ -212,9 +335,90 @@ static CHAIN_BY_ID: LazyLock<HashMap<i32, &'static ChainConfig>> =
 static CHAIN_BY_SLUG: LazyLock<HashMap<&'static str, &'static ChainConfig>> =
     LazyLock::new(|| CHAINS.iter().map(|c| (c.sqd_slug, c)).collect());
 
+/// Alias table from `CHAIN_ALIASES`, read once on first access.
+static ALIASES: LazyLock<HashMap<i32, i32>> = LazyLock::new(|| {
+    std::env::var("CHAIN_ALIASES")
+        .map(|v| parse_aliases(&v))
+        .unwrap_or_default()
+});
+
+/// Parses `from:to` pairs separated by commas. Malformed pairs, aliases shadowing a
+/// configured chain, and aliases to unknown chains are skipped with a warning.
+pub fn parse_aliases(raw: &str) -> HashMap<i32, i32> {
+    let mut aliases = HashMap::new();
+    for pair in raw.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let parsed = pair
+            .split_once(':')
+            .and_then(|(from, to)| Some((from.trim().parse().ok()?, to.trim().parse().ok()?)));
+        match parsed {
+            Some((from, to))
+                if !CHAIN_BY_ID.contains_key(&from) && CHAIN_BY_ID.contains_key(&to) =>
+            {
+                aliases.insert(from, to);
+            }
+            _ => tracing::warn!(alias = pair, "ignoring invalid CHAIN_ALIASES entry"),
+        }
+    }
+    aliases
+}
+
+/// Genesis overrides from `GENESIS_TIMESTAMPS`, read once on first access.
+static GENESIS_OVERRIDES: LazyLock<HashMap<i32, i64>> = LazyLock::new(|| {
+    std::env::var("GENESIS_TIMESTAMPS")
+        .map(|v| parse_genesis_overrides(&v))
+        .unwrap_or_default()
+});
+
+/// Parses `chain_id:timestamp` pairs separated by commas. Malformed pairs, unknown
+/// chains and negative timestamps are skipped with a warning.
+pub fn parse_genesis_overrides(raw: &str) -> HashMap<i32, i64> {
+    let mut overrides = HashMap::new();
+    for pair in raw.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let parsed = pair.split_once(':').and_then(|(chain, timestamp)| {
+            Some((chain.trim().parse().ok()?, timestamp.trim().parse().ok()?))
+        });
+        match parsed {
+            Some((chain_id, timestamp))
+                if timestamp >= 0 && CHAIN_BY_ID.contains_key(&chain_id) =>
+            {
+                overrides.insert(chain_id, timestamp);
+            }
+            _ => tracing::warn!(entry = pair, "ignoring invalid GENESIS_TIMESTAMPS entry"),
+        }
+    }
+    overrides
+}
+
+/// Active `GENESIS_TIMESTAMPS` overrides as `(chain_id, timestamp)` pairs, sorted.
+pub fn genesis_overrides() -> Vec<(i32, i64)> {
+    let mut overrides: Vec<_> = GENESIS_OVERRIDES
+        .iter()
+        .map(|(&id, &ts)| (id, ts))
+        .collect();
+    overrides.sort_unstable();
+    overrides
+}
+
+/// Active `CHAIN_ALIASES` as `(from, to)` pairs, sorted.
+pub fn aliases() -> Vec<(i32, i32)> {
+    let mut aliases: Vec<_> = ALIASES.iter().map(|(&from, &to)| (from, to)).collect();
+    aliases.sort_unstable();
+    aliases
+}
+
 /// Returns the chain config for a given EIP-155 chain ID, or `None` if unsupported.
+///
+/// Aliased ids resolve to their target chain; callers should use the returned
+/// config's `chain_id` for storage access.
 pub fn chain_by_id(chain_id: i32) -> Option<&'static ChainConfig> {
-    CHAIN_BY_ID.get(&chain_id).copied()
+    resolve(&ALIASES, chain_id)
+}
+
+fn resolve(aliases: &HashMap<i32, i32>, chain_id: i32) -> Option<&'static ChainConfig> {
+    CHAIN_BY_ID
+        .get(&chain_id)
+        .or_else(|| aliases.get(&chain_id).and_then(|to| CHAIN_BY_ID.get(to)))
+        .copied()
 }
 
 /// Returns the chain config for a given SQD Portal dataset slug, or `None` if unsupported.
@@ -246,6 +450,64 @@ mod tests {
         assert!(chain_by_slug("nonexistent").is_none());
     }
 
+    #[test]
+    fn aliases_resolve_to_target_chain() {
+        let aliases = parse_aliases("1101:137, 9999:1");
+        assert_eq!(resolve(&aliases, 1101).unwrap().chain_id, 137);
+        assert_eq!(resolve(&aliases, 9999).unwrap().chain_id, 1);
+        assert_eq!(resolve(&aliases, 1).unwrap().chain_id, 1);
+        assert!(resolve(&aliases, 4242).is_none());
+    }
+
+    #[test]
+    fn invalid_aliases_are_skipped() {
+        // shadows a real chain, unknown target, malformed
+        let aliases = parse_aliases("1:137,5555:999999,garbage,7:");
+        assert!(aliases.is_empty());
+    }
+
+    fn header(number: i64, timestamp: i64) -> BlockHeader {
+        BlockHeader { number, timestamp }
+    }
+
+    #[test]
+    fn genesis_overrides_skip_invalid_entries() {
+        let overrides = parse_genesis_overrides("8453:1686789347, 999999:5, 1:-1, 10");
+        assert_eq!(overrides, HashMap::from([(8453, 1686789347)]));
+    }
+
+    #[test]
+    fn genesis_check_accepts_matching_block_zero() {
+        let eth = chain_by_id(1).unwrap();
+        let genesis = eth.effective_genesis_timestamp();
+        let headers = [header(0, genesis), header(1, genesis + 5)];
+        assert_eq!(eth.genesis_mismatch(&headers), None);
+    }
+
+    #[test]
+    fn genesis_check_uses_block_one_when_block_zero_is_zero() {
+        let eth = chain_by_id(1).unwrap();
+        let genesis = eth.effective_genesis_timestamp();
+        let headers = [header(0, 0), header(1, genesis)];
+        assert_eq!(derive_genesis(&headers).map(|h| h.number), Some(1));
+        assert_eq!(eth.genesis_mismatch(&headers), None);
+
+        let headers = [header(0, 0), header(1, genesis + 1)];
+        assert_eq!(eth.genesis_mismatch(&headers), Some((1, genesis + 1)));
+    }
+
+    #[test]
+    fn genesis_check_flags_late_dataset_predating_genesis() {
+        let eth = chain_by_id(1).unwrap();
+        let genesis = eth.effective_genesis_timestamp();
+        assert!(derive_genesis(&[header(500, genesis + 100)]).is_none());
+        assert_eq!(eth.genesis_mismatch(&[header(500, genesis + 100)]), None);
+        assert_eq!(
+            eth.genesis_mismatch(&[header(500, genesis - 1)]),
+            Some((500, genesis - 1))
+        );
+    }
+
     #[test]
     fn all_chains_have_unique_ids() {
         let mut ids: Vec<i32> = CHAINS.iter().map(|c| c.chain_id).collect();
@@ -254,6 +516,24 @@ mod tests {
         assert_eq!(ids.len(), CHAINS.len());
     }
 
+    #[test]
+    fn sunset_fields_follow_lifecycle() {
+        let eth = chain_by_id(1).unwrap();
+        assert!(!eth.is_deprecated());
+        assert_eq!(eth.sunset_block(), None);
+
+        let retired = ChainConfig {
+            lifecycle: Lifecycle::Deprecated {
+                sunset_at: Some(1_800_000_000),
+                sunset_block: Some(500),
+            },
+            ..*eth
+        };
+        assert!(retired.is_deprecated());
+        assert_eq!(retired.sunset_at(), Some(1_800_000_000));
+        assert_eq!(retired.sunset_block(), Some(500));
+    }
+
     #[test]
     fn all_chains_have_unique_slugs() {
         let mut slugs: Vec<&str> = CHAINS.iter().map(|c| c.sqd_slug).collect();
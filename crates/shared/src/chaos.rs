@@ -0,0 +1,111 @@
+//! Fault injection for soak tests. Only compiled with the `chaos` feature.
+//!
+//! A [`Faults`] source attached to [`SqdClient`](crate::sqd::SqdClient) or
+//! [`Storage`](crate::storage::Storage) makes a configurable share of their calls fail,
+//! stall, or (for SQD streams and block inserts) complete only partially. Decisions
+//! come from a seeded PRNG, so a failing soak run can be replayed with the same seed.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How often each kind of fault fires. Rates are per call and checked in order
+/// (error, truncation, latency); their sum should stay at or below 1.
+#[derive(Debug, Clone, Copy)]
+pub struct FaultConfig {
+    /// Share of calls that fail outright.
+    pub error_rate: f64,
+    /// Share of calls cut short: SQD stream bodies lose a random tail, block inserts
+    /// write a random prefix and then fail.
+    pub truncate_rate: f64,
+    /// Share of calls delayed by up to `max_latency` before running normally.
+    pub latency_rate: f64,
+    pub max_latency: Duration,
+}
+
+/// A fault to apply to one call.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fault {
+    Error,
+    /// Keep this fraction (in `[0, 1)`) of the output.
+    Truncate(f64),
+    Latency(Duration),
+}
+
+/// Seeded source of faults, shared by the components it is attached to.
+pub struct Faults {
+    config: FaultConfig,
+    state: Mutex<u64>,
+    injected: AtomicU64,
+}
+
+impl Faults {
+    pub fn new(config: FaultConfig, seed: u64) -> Self {
+        Self {
+            config,
+            state: Mutex::new(seed),
+            injected: AtomicU64::new(0),
+        }
+    }
+
+    /// SplitMix64 step mapped to `[0, 1)`.
+    fn next_f64(&self) -> f64 {
+        let mut state = self.state.lock().unwrap();
+        *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = *state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        (z ^ (z >> 31)) as f64 / (u64::MAX as f64 + 1.0)
+    }
+
+    /// Decides the fault, if any, for the next call.
+    pub fn next(&self) -> Option<Fault> {
+        let roll = self.next_f64();
+        let c = &self.config;
+        let fault = if roll < c.error_rate {
+            Fault::Error
+        } else if roll < c.error_rate + c.truncate_rate {
+            Fault::Truncate(self.next_f64())
+        } else if roll < c.error_rate + c.truncate_rate + c.latency_rate {
+            Fault::Latency(c.max_latency.mul_f64(self.next_f64()))
+        } else {
+            return None;
+        };
+        self.injected.fetch_add(1, Ordering::Relaxed);
+        Some(fault)
+    }
+
+    /// Faults injected so far.
+    pub fn injected(&self) -> u64 {
+        self.injected.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rates_and_seeds_are_respected() {
+        let config = FaultConfig {
+            error_rate: 0.1,
+            truncate_rate: 0.1,
+            latency_rate: 0.1,
+            max_latency: Duration::from_millis(10),
+        };
+        let faults = Faults::new(config, 7);
+        let decisions: Vec<_> = (0..10_000).map(|_| faults.next()).collect();
+        let errors = decisions
+            .iter()
+            .filter(|f| **f == Some(Fault::Error))
+            .count();
+        assert!((800..1200).contains(&errors), "{errors}");
+        assert_eq!(
+            faults.injected(),
+            decisions.iter().filter(|f| f.is_some()).count() as u64
+        );
+
+        let replay = Faults::new(config, 7);
+        assert!(decisions.iter().all(|f| *f == replay.next()));
+    }
+}
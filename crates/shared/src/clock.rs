@@ -0,0 +1,411 @@
+//! Wall-clock time, corrected for host clock skew.
+//!
+//! The host clock is compared with a reference: the `Date` header of SQD head
+//! responses, or an NTP server when `NTP_SERVER` is set (then SQD is ignored). Heads
+//! are polled from startup on, and the NTP check runs at startup and every
+//! [`NTP_CHECK_INTERVAL`] as the `clock_check` job.
+//!
+//! A skew beyond `CLOCK_SKEW_TOLERANCE_SECS` (default 2) is logged with
+//! `alert=clock_skew`, and [`now`] then applies the measured offset. Everything that
+//! stamps or compares wall-clock time (`updated_at`, cursor and cycle records, the
+//! future-timestamp checks of validation and lookups) reads [`now`], so a skewed host
+//! stays consistent with the chains it indexes. Within tolerance the host clock is used
+//! as is. Cache TTLs run on the monotonic clock and are unaffected either way.
+//!
+//! Components that schedule or expire things take a [`Clock`] instead of reading the
+//! process clock directly: storage (cursor, cycle and error timestamps, and through it
+//! ingestion and the work queue) and the lookup cache (TTLs, bypass windows). Ingestion
+//! also waits on it, between cycles and head polls, through [`Clock::sleep`]. They
+//! default to [`SystemClock`]; tests hand them a [`MockClock`] and advance it to step
+//! through backoff, expiry and cycle timing without waiting on the wall clock.
+
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use tokio::sync::Notify;
+
+use crate::scheduler::Scheduler;
+
+/// Default skew tolerated before time is corrected, in seconds.
+const DEFAULT_TOLERANCE_SECS: f64 = 2.0;
+
+/// Interval of the `clock_check` job.
+pub const NTP_CHECK_INTERVAL: Duration = Duration::from_secs(600);
+
+/// How long an NTP query waits for its reply.
+const NTP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Seconds from the NTP epoch (1900) to the Unix epoch.
+const NTP_UNIX_OFFSET: i64 = 2_208_988_800;
+
+/// The process clock, tolerating `CLOCK_SKEW_TOLERANCE_SECS`.
+static CLOCK: LazyLock<HostClock> = LazyLock::new(|| {
+    let secs = std::env::var("CLOCK_SKEW_TOLERANCE_SECS")
+        .ok()
+        .and_then(|v| v.parse::<f64>().ok())
+        .filter(|secs| *secs >= 0.0)
+        .unwrap_or(DEFAULT_TOLERANCE_SECS);
+    HostClock::new(TimeDelta::milliseconds((secs * 1000.0) as i64))
+});
+
+/// `NTP_SERVER`, as `host` or `host:port`.
+static NTP_SERVER: LazyLock<Option<String>> = LazyLock::new(|| {
+    std::env::var("NTP_SERVER")
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+});
+
+/// What the skew was measured against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// `Date` headers of SQD responses, accurate to about a second.
+    Sqd,
+    /// The `NTP_SERVER`.
+    Ntp,
+}
+
+impl Source {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Sqd => "sqd",
+            Self::Ntp => "ntp",
+        }
+    }
+}
+
+/// The latest skew measurement.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Skew {
+    /// Reference time minus host time: positive when the host clock is behind.
+    pub offset: TimeDelta,
+    pub source: Source,
+    /// Host time of the measurement.
+    pub measured_at: DateTime<Utc>,
+    /// True when the offset exceeds the tolerance and [`now`] applies it.
+    pub corrected: bool,
+}
+
+/// A source of wall-clock and monotonic time.
+pub trait Clock: Send + Sync {
+    /// Current wall-clock time, for timestamps and schedules.
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Current monotonic time, for measuring intervals such as TTLs.
+    fn instant(&self) -> Instant;
+
+    /// Completes once `duration` has passed on this clock.
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send + '_>>;
+}
+
+/// The process clock: [`now`] and [`Instant::now`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        now()
+    }
+
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// The process clock as a shared [`Clock`].
+pub fn system() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+/// A clock that stands still until [`MockClock::advance`] moves it, for tests. Sleeps
+/// on it end only when it is advanced past them.
+#[derive(Debug)]
+pub struct MockClock {
+    at: Mutex<(DateTime<Utc>, Instant)>,
+    advanced: Notify,
+}
+
+impl MockClock {
+    /// A clock reading `at` as wall-clock time.
+    pub fn new(at: DateTime<Utc>) -> Self {
+        Self {
+            at: Mutex::new((at, Instant::now())),
+            advanced: Notify::new(),
+        }
+    }
+
+    /// Moves wall-clock and monotonic time forward by `by`, waking sleeps it ends.
+    pub fn advance(&self, by: Duration) {
+        {
+            let mut at = self.at.lock().unwrap();
+            at.0 += TimeDelta::from_std(by).unwrap_or(TimeDelta::MAX);
+            at.1 += by;
+        }
+        self.advanced.notify_waiters();
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.at.lock().unwrap().0
+    }
+
+    fn instant(&self) -> Instant {
+        self.at.lock().unwrap().1
+    }
+
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
+        let until = self.instant() + duration;
+        Box::pin(async move {
+            loop {
+                let advanced = self.advanced.notified();
+                tokio::pin!(advanced);
+                // registered before the check, so an advance in between isn't missed
+                advanced.as_mut().enable();
+                if self.instant() >= until {
+                    return;
+                }
+                advanced.await;
+            }
+        })
+    }
+}
+
+/// A host clock with the correction measured for it.
+#[derive(Debug)]
+pub struct HostClock {
+    tolerance: TimeDelta,
+    /// Correction [`HostClock::now`] applies, in milliseconds. Zero within tolerance.
+    applied_ms: AtomicI64,
+    last: Mutex<Option<Skew>>,
+}
+
+impl HostClock {
+    pub fn new(tolerance: TimeDelta) -> Self {
+        Self {
+            tolerance,
+            applied_ms: AtomicI64::new(0),
+            last: Mutex::new(None),
+        }
+    }
+
+    /// The host clock, corrected by the measured offset when it exceeds the tolerance.
+    pub fn now(&self) -> DateTime<Utc> {
+        Utc::now() + TimeDelta::milliseconds(self.applied_ms.load(Ordering::Relaxed))
+    }
+
+    /// The latest measurement, if any.
+    pub fn skew(&self) -> Option<Skew> {
+        *self.last.lock().unwrap()
+    }
+
+    /// Skew tolerated before [`HostClock::now`] corrects for it.
+    pub fn tolerance(&self) -> TimeDelta {
+        self.tolerance
+    }
+
+    /// Records a measured offset from `source` and updates the correction, logging
+    /// when the skew crosses the tolerance either way.
+    pub fn record(&self, offset: TimeDelta, source: Source) {
+        let corrected = offset.abs() > self.tolerance;
+        let applied = if corrected {
+            offset.num_milliseconds()
+        } else {
+            0
+        };
+        let was_corrected = self.applied_ms.swap(applied, Ordering::Relaxed) != 0;
+        *self.last.lock().unwrap() = Some(Skew {
+            offset,
+            source,
+            measured_at: Utc::now(),
+            corrected,
+        });
+        let skew_secs = offset.num_milliseconds() as f64 / 1000.0;
+        if corrected && !was_corrected {
+            tracing::warn!(
+                alert = "clock_skew",
+                skew_secs = skew_secs,
+                source = source.as_str(),
+                tolerance_secs = self.tolerance.num_milliseconds() as f64 / 1000.0,
+                "host clock is skewed, correcting timestamps by the measured offset"
+            );
+        } else if !corrected && was_corrected {
+            tracing::info!(
+                skew_secs = skew_secs,
+                source = source.as_str(),
+                "host clock back within tolerance"
+            );
+        }
+    }
+
+    /// Records the offset shown by an HTTP `Date` header received at `received_at`
+    /// (host time). Headers that don't parse are ignored.
+    pub fn observe_http_date(&self, date: &str, received_at: DateTime<Utc>) {
+        let Ok(date) = DateTime::parse_from_rfc2822(date) else {
+            return;
+        };
+        // the header is truncated to the second, so on average half a second behind
+        let reference = date.with_timezone(&Utc) + TimeDelta::milliseconds(500);
+        self.record(reference - received_at, Source::Sqd);
+    }
+}
+
+/// Current time of the process clock: the host clock, corrected when skewed.
+pub fn now() -> DateTime<Utc> {
+    CLOCK.now()
+}
+
+/// The process clock's latest measurement, if any.
+pub fn skew() -> Option<Skew> {
+    CLOCK.skew()
+}
+
+/// Skew the process clock tolerates before correcting for it.
+pub fn tolerance() -> TimeDelta {
+    CLOCK.tolerance()
+}
+
+/// The configured `NTP_SERVER`, if any.
+pub fn ntp_server() -> Option<&'static str> {
+    NTP_SERVER.as_deref()
+}
+
+/// Feeds the `Date` header of an SQD response to the process clock, unless an NTP
+/// server is configured.
+pub fn observe_http_date(date: &str, received_at: DateTime<Utc>) {
+    if ntp_server().is_none() {
+        CLOCK.observe_http_date(date, received_at);
+    }
+}
+
+/// Offset of the host clock from an SNTP server (RFC 4330): reference minus host time,
+/// with the network delay taken out.
+pub async fn ntp_offset(server: &str) -> std::io::Result<TimeDelta> {
+    let addr = if server.contains(':') {
+        server.to_string()
+    } else {
+        format!("{server}:123")
+    };
+    let socket = tokio::net::UdpSocket::bind("0.0.0.0:0").await?;
+    socket.connect(&addr).await?;
+    let mut packet = [0u8; 48];
+    // LI 0, version 4, mode 3 (client)
+    packet[0] = 0x23;
+    let sent_at = Utc::now();
+    socket.send(&packet).await?;
+    let len = tokio::time::timeout(NTP_TIMEOUT, socket.recv(&mut packet))
+        .await
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "no NTP reply"))??;
+    let received_at = Utc::now();
+    if len < 48 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "short NTP reply",
+        ));
+    }
+    let server_received = ntp_timestamp(&packet[32..40]);
+    let server_sent = ntp_timestamp(&packet[40..48]);
+    Ok(((server_received - sent_at) + (server_sent - received_at)) / 2)
+}
+
+/// Decodes a 64-bit NTP timestamp: seconds since 1900 and a 32-bit fraction.
+fn ntp_timestamp(bytes: &[u8]) -> DateTime<Utc> {
+    let secs = u32::from_be_bytes(bytes[..4].try_into().unwrap()) as i64;
+    let fraction = u32::from_be_bytes(bytes[4..8].try_into().unwrap()) as i64;
+    let nanos = (fraction * 1_000_000_000) >> 32;
+    DateTime::from_timestamp(secs - NTP_UNIX_OFFSET, nanos as u32).unwrap_or_default()
+}
+
+/// Measures skew against the `NTP_SERVER` as the `clock_check` job. A failed query is
+/// logged and leaves the last measurement in place.
+pub fn schedule(scheduler: &Scheduler, server: &'static str) {
+    scheduler.spawn(
+        "clock_check",
+        NTP_CHECK_INTERVAL,
+        Duration::ZERO,
+        move || async move {
+            match ntp_offset(server).await {
+                Ok(offset) => CLOCK.record(offset, Source::Ntp),
+                Err(e) => tracing::warn!(ntp_server = server, error = %e, "NTP query failed"),
+            }
+            Ok::<_, Infallible>(())
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skew_beyond_tolerance_is_corrected() {
+        let clock = HostClock::new(TimeDelta::seconds(2));
+        assert_eq!(clock.skew(), None);
+        let host = Utc::now();
+        clock.observe_http_date("Mon, 01 Jan 2001 00:00:00 GMT", host);
+        let skew = clock.skew().unwrap();
+        assert_eq!(skew.source, Source::Sqd);
+        assert!(skew.corrected);
+        assert!(skew.offset < TimeDelta::days(-365));
+        assert!(clock.now() < host - TimeDelta::days(365));
+
+        clock.record(TimeDelta::milliseconds(1_800), Source::Ntp);
+        assert!(!clock.skew().unwrap().corrected);
+        assert!((clock.now() - Utc::now()).abs() < TimeDelta::seconds(1));
+
+        // garbage leaves the measurement alone
+        clock.observe_http_date("yesterday", host);
+        assert_eq!(clock.skew().unwrap().source, Source::Ntp);
+    }
+
+    #[test]
+    fn mock_clock_moves_only_when_advanced() {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let clock = MockClock::new(start);
+        let instant = clock.instant();
+        assert_eq!(clock.now(), start);
+        assert_eq!(clock.instant(), instant);
+
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(clock.now(), start + TimeDelta::seconds(90));
+        assert_eq!(clock.instant() - instant, Duration::from_secs(90));
+    }
+
+    #[tokio::test]
+    async fn mock_clock_sleeps_end_when_advanced_past() {
+        let clock = Arc::new(MockClock::new(Utc::now()));
+        let sleeper = tokio::spawn({
+            let clock = clock.clone();
+            async move { clock.sleep(Duration::from_secs(60)).await }
+        });
+        tokio::task::yield_now().await;
+        clock.advance(Duration::from_secs(30));
+        tokio::task::yield_now().await;
+        assert!(!sleeper.is_finished());
+
+        clock.advance(Duration::from_secs(30));
+        tokio::time::timeout(Duration::from_secs(5), sleeper)
+            .await
+            .expect("sleep never ended")
+            .unwrap();
+    }
+
+    #[test]
+    fn ntp_timestamps_decode_to_unix_time() {
+        let mut bytes = [0u8; 8];
+        bytes[..4].copy_from_slice(&((NTP_UNIX_OFFSET + 1_700_000_000) as u32).to_be_bytes());
+        bytes[4..].copy_from_slice(&(1u32 << 31).to_be_bytes());
+        let t = ntp_timestamp(&bytes);
+        assert_eq!(t.timestamp(), 1_700_000_000);
+        assert_eq!(t.timestamp_subsec_millis(), 500);
+    }
+}
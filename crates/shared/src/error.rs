@@ -1,15 +1,28 @@
 //! Application error types with HTTP status codes and JSON error responses.
 //!
 //! Each variant maps to a specific HTTP status code and machine-readable error code.
+//! Clients that send `Accept: application/problem+json` get RFC 9457 problem details
+//! instead of the default envelope (see [`negotiate_problem_json`]). Messages are
+//! localized per `Accept-Language` (see [`crate::i18n`]); codes never are.
 
-use axum::http::StatusCode;
+use axum::extract::Request;
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::middleware::Next;
 use axum::response::{IntoResponse, Response};
 use serde_json::json;
 
+use crate::i18n::{self, Locale};
+use crate::models::{ErrorBody, ErrorDetail, ProblemDetails};
+
+/// Media type for RFC 9457 problem details.
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
 /// Unified error type for the entire application.
 ///
 /// Implements `IntoResponse` so handlers can return `Result<_, AppError>` directly.
-/// The JSON response shape is `{ "error": { "code": "...", "message": "..." } }`.
+/// The JSON response shape is `{ "error": { "code": "...", "message": "..." } }`, plus
+/// optional `retry_after_seconds`, `indexed_up_to`, and `details` fields for errors
+/// where clients can act on them without parsing the message.
 #[derive(Debug, thiserror::Error)]
 pub enum AppError {
     #[error("chain {0} not found")]
@@ -25,14 +38,118 @@ pub enum AppError {
     #[error("invalid timestamp: {0}")]
     InvalidTimestamp(String),
 
+    #[error("timestamp {timestamp} is more than {max_skew_secs}s in the future")]
+    TimestampInFuture { timestamp: i64, max_skew_secs: i64 },
+
     #[error("invalid direction: {0}")]
     InvalidDirection(String),
 
+    #[error("invalid date: {0} (expected YYYY-MM-DD)")]
+    InvalidDate(String),
+
+    #[error("unknown timezone: {0}")]
+    InvalidTimezone(String),
+
+    #[error("invalid period: {0} (expected YYYY, YYYY-Qn, YYYY-MM or YYYY-MM-DD)")]
+    InvalidPeriod(String),
+
+    #[error("invalid cursor: {0}")]
+    InvalidCursor(String),
+
+    #[error("no index snapshot available for chain {0}")]
+    SnapshotUnavailable(String),
+
+    #[error("no indexing progress recorded for chain {chain_id} at or before {at}")]
+    NoCursorHistory { chain_id: String, at: i64 },
+
+    #[error("batch of {size} queries exceeds the limit of {max}")]
+    BatchTooLarge { size: usize, max: usize },
+
+    #[error("sample size {size} must be between 1 and {max}")]
+    InvalidSampleSize { size: usize, max: usize },
+
+    #[error("limit {limit} must be between 1 and {max}")]
+    InvalidLimit { limit: usize, max: usize },
+
+    #[error("invalid block range {from_block}..={to_block}")]
+    InvalidBlockRange { from_block: i64, to_block: i64 },
+
+    #[error("work item {0} not found")]
+    WorkItemNotFound(u64),
+
+    /// A range query would cover more than `RANGE_MAX_WINDOW_DAYS` or `RANGE_MAX_ROWS`
+    /// allow. `hint` says what to do instead.
+    #[error("range of {requested} {unit} exceeds the limit of {max} {unit}; {hint}")]
+    RangeTooLarge {
+        unit: &'static str,
+        requested: i64,
+        max: i64,
+        hint: String,
+    },
+
+    /// One or more request fields failed to parse or validate. The envelope takes the
+    /// first failure's code, so a request with one bad field gets that field's code
+    /// (e.g. `INVALID_LIMIT`); all of them are listed in `details.fields`.
+    #[error("{}", describe_fields(.0))]
+    InvalidRequest(Vec<FieldError>),
+
+    #[error("invalid Idempotency-Key: {0}")]
+    InvalidIdempotencyKey(String),
+
+    /// The `Idempotency-Key` was already used for a different request.
+    #[error("Idempotency-Key was already used for a different request")]
+    IdempotencyKeyReused,
+
+    #[error("request body exceeds {max_bytes} bytes")]
+    PayloadTooLarge { max_bytes: usize },
+
+    /// The `after` side of a timestamp lies beyond what has been ingested so far.
+    #[error("no block indexed after timestamp {timestamp} on chain {chain_id} yet (indexed up to block {indexed_up_to})")]
+    NotYetIndexed {
+        chain_id: String,
+        timestamp: i64,
+        indexed_up_to: i64,
+    },
+
+    #[error("rate limit exceeded, retry in {retry_after_secs}s")]
+    RateLimited { retry_after_secs: u64 },
+
+    #[error("server overloaded, retry in {retry_after_secs}s")]
+    Overloaded { retry_after_secs: u64 },
+
+    #[error("missing or invalid admin token")]
+    Unauthorized,
+
+    #[error("missing or invalid API key for this tenant")]
+    InvalidApiKey,
+
+    #[error("admin API is disabled on this deployment")]
+    AdminDisabled,
+
     #[error("SQD API error: {0}")]
     SqdApi(String),
 
+    /// A chain JSON-RPC endpoint (`RPC_URLS`) failed or returned something unusable.
+    #[error("RPC error: {0}")]
+    Rpc(String),
+
+    /// A Redis server holding shared state was unreachable or answered with an error.
+    #[error("Redis error: {0}")]
+    Redis(String),
+
+    /// A kizami server called over HTTP (by the sharding client or the load test)
+    /// failed or answered with something unusable.
+    #[error("replica error: {0}")]
+    Replica(String),
+
+    /// Any fjall failure. Classified into unavailable (503) vs internal (500) by
+    /// [`AppError::status`] so alerting can tell infra problems from bad data.
     #[error("storage error: {0}")]
     Storage(#[from] fjall::Error),
+
+    /// A stored key or value could not be decoded (wrong length, bad encoding).
+    #[error("corrupt data in storage: {0}")]
+    CorruptData(String),
 }
 
 impl AppError {
@@ -42,36 +159,430 @@ impl AppError {
             Self::ChainNotFound(_) => "CHAIN_NOT_FOUND",
             Self::BlockNotFound { .. } => "BLOCK_NOT_FOUND",
             Self::InvalidTimestamp(_) => "INVALID_TIMESTAMP",
+            Self::TimestampInFuture { .. } => "TIMESTAMP_IN_FUTURE",
             Self::InvalidDirection(_) => "INVALID_DIRECTION",
+            Self::InvalidDate(_) => "INVALID_DATE",
+            Self::InvalidTimezone(_) => "INVALID_TIMEZONE",
+            Self::InvalidPeriod(_) => "INVALID_PERIOD",
+            Self::InvalidCursor(_) => "INVALID_CURSOR",
+            Self::SnapshotUnavailable(_) => "SNAPSHOT_UNAVAILABLE",
+            Self::NoCursorHistory { .. } => "NO_CURSOR_HISTORY",
+            Self::BatchTooLarge { .. } => "BATCH_TOO_LARGE",
+            Self::InvalidSampleSize { .. } => "INVALID_SAMPLE_SIZE",
+            Self::InvalidLimit { .. } => "INVALID_LIMIT",
+            Self::InvalidBlockRange { .. } => "INVALID_BLOCK_RANGE",
+            Self::WorkItemNotFound(_) => "WORK_ITEM_NOT_FOUND",
+            Self::RangeTooLarge { .. } => "RANGE_TOO_LARGE",
+            Self::InvalidRequest(fields) => fields.first().map_or(INVALID_REQUEST, |f| f.code),
+            Self::InvalidIdempotencyKey(_) => "INVALID_IDEMPOTENCY_KEY",
+            Self::IdempotencyKeyReused => "IDEMPOTENCY_KEY_REUSED",
+            Self::PayloadTooLarge { .. } => "PAYLOAD_TOO_LARGE",
+            Self::NotYetIndexed { .. } => "NOT_YET_INDEXED",
+            Self::RateLimited { .. } => "RATE_LIMITED",
+            Self::Overloaded { .. } => "OVERLOADED",
+            Self::Unauthorized => "UNAUTHORIZED",
+            Self::InvalidApiKey => "INVALID_API_KEY",
+            Self::AdminDisabled => "ADMIN_DISABLED",
             Self::SqdApi(_) => "SQD_API_ERROR",
-            Self::Storage(_) => "INTERNAL_ERROR",
+            Self::Rpc(_) => "RPC_ERROR",
+            Self::Redis(_) => "REDIS_ERROR",
+            Self::Replica(_) => "REPLICA_ERROR",
+            Self::Storage(e) if is_unavailable(e) => "STORAGE_UNAVAILABLE",
+            Self::Storage(_) => "STORAGE_ERROR",
+            Self::CorruptData(_) => "DATA_CORRUPTED",
         }
     }
 
     /// Returns the HTTP status code for this error.
     pub fn status(&self) -> StatusCode {
         match self {
-            Self::ChainNotFound(_) | Self::BlockNotFound { .. } => StatusCode::NOT_FOUND,
-            Self::InvalidTimestamp(_) | Self::InvalidDirection(_) => StatusCode::BAD_REQUEST,
-            Self::SqdApi(_) => StatusCode::BAD_GATEWAY,
-            Self::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::ChainNotFound(_)
+            | Self::BlockNotFound { .. }
+            | Self::NotYetIndexed { .. }
+            | Self::SnapshotUnavailable(_)
+            | Self::NoCursorHistory { .. }
+            | Self::WorkItemNotFound(_) => StatusCode::NOT_FOUND,
+            Self::InvalidTimestamp(_)
+            | Self::TimestampInFuture { .. }
+            | Self::InvalidDirection(_)
+            | Self::InvalidDate(_)
+            | Self::InvalidTimezone(_)
+            | Self::InvalidPeriod(_)
+            | Self::InvalidCursor(_)
+            | Self::BatchTooLarge { .. }
+            | Self::InvalidSampleSize { .. }
+            | Self::InvalidLimit { .. }
+            | Self::InvalidBlockRange { .. }
+            | Self::InvalidRequest(_)
+            | Self::RangeTooLarge { .. }
+            | Self::InvalidIdempotencyKey(_) => StatusCode::BAD_REQUEST,
+            Self::IdempotencyKeyReused => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::PayloadTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::Unauthorized | Self::InvalidApiKey => StatusCode::UNAUTHORIZED,
+            Self::AdminDisabled => StatusCode::FORBIDDEN,
+            Self::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            Self::SqdApi(_) | Self::Rpc(_) | Self::Redis(_) | Self::Replica(_) => {
+                StatusCode::BAD_GATEWAY
+            }
+            Self::Overloaded { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Storage(e) if is_unavailable(e) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Storage(_) | Self::CorruptData(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Seconds the client should wait before retrying, if the error is transient.
+    pub fn retry_after_secs(&self) -> Option<u64> {
+        match self {
+            Self::RateLimited { retry_after_secs } | Self::Overloaded { retry_after_secs } => {
+                Some(*retry_after_secs)
+            }
+            _ => None,
+        }
+    }
+
+    /// Highest indexed block for the chain, for errors caused by ingestion lag.
+    pub fn indexed_up_to(&self) -> Option<i64> {
+        match self {
+            Self::NotYetIndexed { indexed_up_to, .. } => Some(*indexed_up_to),
+            _ => None,
+        }
+    }
+
+    /// Values of the placeholders in this error's message templates (see
+    /// [`crate::i18n`]).
+    fn params(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Self::ChainNotFound(chain_id) | Self::SnapshotUnavailable(chain_id) => {
+                vec![("chain_id", chain_id.clone())]
+            }
+            Self::BlockNotFound {
+                chain_id,
+                timestamp,
+                direction,
+            } => vec![
+                ("chain_id", chain_id.clone()),
+                ("timestamp", timestamp.to_string()),
+                ("direction", direction.clone()),
+            ],
+            Self::InvalidTimestamp(value)
+            | Self::InvalidDirection(value)
+            | Self::InvalidDate(value)
+            | Self::InvalidTimezone(value)
+            | Self::InvalidPeriod(value)
+            | Self::InvalidCursor(value)
+            | Self::InvalidIdempotencyKey(value) => vec![("value", value.clone())],
+            Self::TimestampInFuture {
+                timestamp,
+                max_skew_secs,
+            } => vec![
+                ("timestamp", timestamp.to_string()),
+                ("max_skew_secs", max_skew_secs.to_string()),
+            ],
+            Self::NoCursorHistory { chain_id, at } => {
+                vec![("chain_id", chain_id.clone()), ("at", at.to_string())]
+            }
+            Self::BatchTooLarge { size, max } | Self::InvalidSampleSize { size, max } => {
+                vec![("size", size.to_string()), ("max", max.to_string())]
+            }
+            Self::InvalidLimit { limit, max } => {
+                vec![("limit", limit.to_string()), ("max", max.to_string())]
+            }
+            Self::InvalidBlockRange {
+                from_block,
+                to_block,
+            } => vec![
+                ("from_block", from_block.to_string()),
+                ("to_block", to_block.to_string()),
+            ],
+            Self::WorkItemNotFound(id) => vec![("id", id.to_string())],
+            Self::RangeTooLarge {
+                unit,
+                requested,
+                max,
+                hint,
+            } => vec![
+                ("unit", unit.to_string()),
+                ("requested", requested.to_string()),
+                ("max", max.to_string()),
+                ("hint", hint.clone()),
+            ],
+            Self::PayloadTooLarge { max_bytes } => vec![("max_bytes", max_bytes.to_string())],
+            Self::NotYetIndexed {
+                chain_id,
+                timestamp,
+                indexed_up_to,
+            } => vec![
+                ("chain_id", chain_id.clone()),
+                ("timestamp", timestamp.to_string()),
+                ("indexed_up_to", indexed_up_to.to_string()),
+            ],
+            Self::RateLimited { retry_after_secs } | Self::Overloaded { retry_after_secs } => {
+                vec![("retry_after_secs", retry_after_secs.to_string())]
+            }
+            Self::SqdApi(error)
+            | Self::Rpc(error)
+            | Self::Redis(error)
+            | Self::Replica(error)
+            | Self::CorruptData(error) => {
+                vec![("error", error.clone())]
+            }
+            Self::Storage(e) => vec![("error", e.to_string())],
+            Self::InvalidRequest(_)
+            | Self::IdempotencyKeyReused
+            | Self::Unauthorized
+            | Self::InvalidApiKey
+            | Self::AdminDisabled => Vec::new(),
         }
     }
+
+    /// The human-readable message in `locale`. Same as `to_string()` for English.
+    pub fn message(&self, locale: Locale) -> String {
+        match self {
+            Self::InvalidRequest(fields) if !fields.is_empty() => fields
+                .iter()
+                .map(|f| format!("{}: {}", f.field, f.message(locale)))
+                .collect::<Vec<_>>()
+                .join("; "),
+            _ => i18n::message(self.code(), locale, &self.params())
+                .unwrap_or_else(|| self.to_string()),
+        }
+    }
+
+    /// Structured, variant-specific context for the error.
+    pub fn details(&self) -> Option<serde_json::Value> {
+        self.details_in(Locale::En)
+    }
+
+    /// [`AppError::details`], with the messages of `details.fields` in `locale`.
+    fn details_in(&self, locale: Locale) -> Option<serde_json::Value> {
+        match self {
+            Self::BlockNotFound {
+                chain_id,
+                timestamp,
+                direction,
+            } => Some(json!({
+                "chain_id": chain_id,
+                "timestamp": timestamp,
+                "direction": direction,
+            })),
+            Self::NotYetIndexed {
+                chain_id,
+                timestamp,
+                ..
+            } => Some(json!({
+                "chain_id": chain_id,
+                "timestamp": timestamp,
+            })),
+            Self::NoCursorHistory { chain_id, at } => Some(json!({
+                "chain_id": chain_id,
+                "at": at,
+            })),
+            Self::TimestampInFuture {
+                timestamp,
+                max_skew_secs,
+            } => Some(json!({
+                "timestamp": timestamp,
+                "max_skew_secs": max_skew_secs,
+            })),
+            Self::BatchTooLarge { size, max } | Self::InvalidSampleSize { size, max } => {
+                Some(json!({
+                    "size": size,
+                    "max": max,
+                }))
+            }
+            Self::InvalidLimit { limit, max } => Some(json!({
+                "limit": limit,
+                "max": max,
+            })),
+            Self::InvalidBlockRange {
+                from_block,
+                to_block,
+            } => Some(json!({
+                "from_block": from_block,
+                "to_block": to_block,
+            })),
+            Self::RangeTooLarge {
+                unit,
+                requested,
+                max,
+                hint,
+            } => Some(json!({
+                "unit": unit,
+                "requested": requested,
+                "max": max,
+                "hint": hint,
+            })),
+            Self::InvalidRequest(fields) => {
+                let mut details = match fields.first().and_then(|f| f.details.clone()) {
+                    Some(serde_json::Value::Object(first)) => first,
+                    _ => serde_json::Map::new(),
+                };
+                details.insert(
+                    "fields".into(),
+                    fields.iter().map(|f| f.to_json(locale)).collect(),
+                );
+                Some(details.into())
+            }
+            _ => None,
+        }
+    }
+
+    /// Attributes this error to the request field at `field`, for
+    /// [`AppError::InvalidRequest`].
+    pub fn at(self, field: impl Into<String>) -> FieldError {
+        FieldError {
+            field: field.into(),
+            code: self.code(),
+            message: self.to_string(),
+            params: self.params(),
+            details: self.details(),
+        }
+    }
+}
+
+/// Code of a field that couldn't be parsed at all (wrong type, missing, bad JSON).
+const INVALID_REQUEST: &str = "INVALID_REQUEST";
+
+/// One failed request field, listed under `details.fields` of
+/// [`AppError::InvalidRequest`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldError {
+    /// Path of the field, e.g. `limit` or `queries[3].timestamp`.
+    pub field: String,
+    pub code: &'static str,
+    pub message: String,
+    /// Placeholder values for localizing `message`.
+    pub params: Vec<(&'static str, String)>,
+    /// The failure's own details (e.g. `max`), merged into its entry.
+    pub details: Option<serde_json::Value>,
+}
+
+impl FieldError {
+    /// A field that couldn't be deserialized.
+    pub fn malformed(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            code: INVALID_REQUEST,
+            message: message.into(),
+            params: Vec::new(),
+            details: None,
+        }
+    }
+
+    /// The message in `locale`. Deserializer messages are only available in English.
+    fn message(&self, locale: Locale) -> String {
+        i18n::message(self.code, locale, &self.params).unwrap_or_else(|| self.message.clone())
+    }
+
+    fn to_json(&self, locale: Locale) -> serde_json::Value {
+        let mut entry = match &self.details {
+            Some(serde_json::Value::Object(details)) => details.clone(),
+            _ => serde_json::Map::new(),
+        };
+        entry.insert("field".into(), self.field.clone().into());
+        entry.insert("code".into(), self.code.into());
+        entry.insert("message".into(), self.message(locale).into());
+        entry.into()
+    }
+}
+
+/// `field: message` for each failure, `; `-separated.
+fn describe_fields(fields: &[FieldError]) -> String {
+    if fields.is_empty() {
+        return "invalid request".into();
+    }
+    fields
+        .iter()
+        .map(|f| format!("{}: {}", f.field, f.message))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+impl AppError {
+    /// Builds the RFC 9457 representation of this error, with `detail` in `locale`.
+    pub fn to_problem(&self, locale: Locale) -> ProblemDetails {
+        let status = self.status();
+        ProblemDetails {
+            problem_type: format!(
+                "urn:kizami:problem:{}",
+                self.code().to_ascii_lowercase().replace('_', "-")
+            ),
+            title: status.canonical_reason().unwrap_or("Error").to_string(),
+            status: status.as_u16(),
+            detail: self.message(locale),
+            code: self.code().to_string(),
+            retry_after_seconds: self.retry_after_secs(),
+            indexed_up_to: self.indexed_up_to(),
+            details: self.details_in(locale),
+        }
+    }
+}
+
+/// Returns true for fjall errors that mean the database itself can't serve requests
+/// right now (I/O failure, poisoned after a failed write), as opposed to a single bad
+/// read. These are worth retrying against another replica.
+fn is_unavailable(err: &fjall::Error) -> bool {
+    matches!(err, fjall::Error::Io(_) | fjall::Error::Poisoned)
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         let status = self.status();
-        let body = json!({
-            "error": {
-                "code": self.code(),
-                "message": self.to_string(),
-            }
-        });
-        (status, axum::Json(body)).into_response()
+        let retry_after = self.retry_after_secs();
+        let locale = i18n::current();
+        let body = ErrorBody {
+            error: ErrorDetail {
+                code: self.code().to_string(),
+                message: self.message(locale),
+                retry_after_seconds: retry_after,
+                indexed_up_to: self.indexed_up_to(),
+                details: self.details_in(locale),
+            },
+        };
+        let problem = self.to_problem(locale);
+        let mut response = (status, axum::Json(body)).into_response();
+        response.headers_mut().insert(
+            header::CONTENT_LANGUAGE,
+            HeaderValue::from_static(locale.as_str()),
+        );
+        if let Some(secs) = retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+        }
+        // stashed so `negotiate_problem_json` can re-render without the original error
+        response.extensions_mut().insert(problem);
+        response
     }
 }
 
+/// Middleware that swaps `AppError` responses to `application/problem+json` when the
+/// request's `Accept` header asks for it. Other responses pass through untouched.
+pub async fn negotiate_problem_json(req: Request, next: Next) -> Response {
+    let wants_problem = req
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains(PROBLEM_JSON));
+
+    let response = next.run(req).await;
+    if !wants_problem {
+        return response;
+    }
+
+    let Some(problem) = response.extensions().get::<ProblemDetails>().cloned() else {
+        return response;
+    };
+
+    let (mut parts, _) = response.into_parts();
+    let body = serde_json::to_vec(&problem).unwrap_or_default();
+    parts
+        .headers
+        .insert(header::CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON));
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, body.into())
+}
+
 #[cfg(test)]
 mod tests {
     use axum::body::to_bytes;
@@ -98,11 +609,77 @@ mod tests {
             AppError::InvalidTimestamp("x".into()).code(),
             "INVALID_TIMESTAMP"
         );
+        assert_eq!(
+            AppError::TimestampInFuture {
+                timestamp: 0,
+                max_skew_secs: 0,
+            }
+            .code(),
+            "TIMESTAMP_IN_FUTURE"
+        );
         assert_eq!(
             AppError::InvalidDirection("x".into()).code(),
             "INVALID_DIRECTION"
         );
+        assert_eq!(
+            AppError::BatchTooLarge { size: 2, max: 1 }.code(),
+            "BATCH_TOO_LARGE"
+        );
+        assert_eq!(
+            AppError::IdempotencyKeyReused.code(),
+            "IDEMPOTENCY_KEY_REUSED"
+        );
+        assert_eq!(
+            AppError::InvalidSampleSize { size: 0, max: 1 }.code(),
+            "INVALID_SAMPLE_SIZE"
+        );
+        assert_eq!(
+            AppError::InvalidLimit { limit: 0, max: 1 }.code(),
+            "INVALID_LIMIT"
+        );
+        assert_eq!(
+            AppError::NoCursorHistory {
+                chain_id: "1".into(),
+                at: 0,
+            }
+            .code(),
+            "NO_CURSOR_HISTORY"
+        );
+        assert_eq!(
+            AppError::InvalidBlockRange {
+                from_block: 2,
+                to_block: 1,
+            }
+            .code(),
+            "INVALID_BLOCK_RANGE"
+        );
+        assert_eq!(AppError::WorkItemNotFound(7).code(), "WORK_ITEM_NOT_FOUND");
+        assert_eq!(
+            AppError::InvalidRequest(Vec::new()).code(),
+            "INVALID_REQUEST"
+        );
+        assert_eq!(
+            AppError::RangeTooLarge {
+                unit: "rows",
+                requested: 2,
+                max: 1,
+                hint: String::new(),
+            }
+            .code(),
+            "RANGE_TOO_LARGE"
+        );
         assert_eq!(AppError::SqdApi("err".into()).code(), "SQD_API_ERROR");
+        assert_eq!(AppError::Rpc("err".into()).code(), "RPC_ERROR");
+        assert_eq!(AppError::Redis("err".into()).code(), "REDIS_ERROR");
+        assert_eq!(AppError::Replica("err".into()).code(), "REPLICA_ERROR");
+        assert_eq!(AppError::Unauthorized.code(), "UNAUTHORIZED");
+        assert_eq!(AppError::InvalidApiKey.code(), "INVALID_API_KEY");
+        assert_eq!(AppError::AdminDisabled.code(), "ADMIN_DISABLED");
+        assert_eq!(AppError::CorruptData("x".into()).code(), "DATA_CORRUPTED");
+        assert_eq!(
+            AppError::Storage(fjall::Error::Poisoned).code(),
+            "STORAGE_UNAVAILABLE"
+        );
     }
 
     #[test]
@@ -120,10 +697,52 @@ mod tests {
             .status(),
             StatusCode::NOT_FOUND
         );
+        assert_eq!(
+            AppError::NoCursorHistory {
+                chain_id: "1".into(),
+                at: 0,
+            }
+            .status(),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            AppError::WorkItemNotFound(7).status(),
+            StatusCode::NOT_FOUND
+        );
         assert_eq!(
             AppError::InvalidTimestamp("x".into()).status(),
             StatusCode::BAD_REQUEST
         );
+        assert_eq!(
+            AppError::InvalidBlockRange {
+                from_block: 2,
+                to_block: 1,
+            }
+            .status(),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            AppError::InvalidRequest(Vec::new()).status(),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            AppError::RangeTooLarge {
+                unit: "seconds",
+                requested: 2,
+                max: 1,
+                hint: String::new(),
+            }
+            .status(),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            AppError::TimestampInFuture {
+                timestamp: 0,
+                max_skew_secs: 0,
+            }
+            .status(),
+            StatusCode::BAD_REQUEST
+        );
         assert_eq!(
             AppError::InvalidDirection("x".into()).status(),
             StatusCode::BAD_REQUEST
@@ -132,6 +751,20 @@ mod tests {
             AppError::SqdApi("err".into()).status(),
             StatusCode::BAD_GATEWAY
         );
+        assert_eq!(
+            AppError::Rpc("err".into()).status(),
+            StatusCode::BAD_GATEWAY
+        );
+        assert_eq!(AppError::Unauthorized.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(AppError::AdminDisabled.status(), StatusCode::FORBIDDEN);
+        assert_eq!(
+            AppError::CorruptData("x".into()).status(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(
+            AppError::Storage(fjall::Error::Io(std::io::Error::other("disk"))).status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
     }
 
     #[tokio::test]
@@ -147,5 +780,229 @@ mod tests {
 
         assert_eq!(json["error"]["code"], "CHAIN_NOT_FOUND");
         assert_eq!(json["error"]["message"], "chain 42 not found");
+        assert!(json["error"].get("retry_after_seconds").is_none());
+        assert!(json["error"].get("details").is_none());
+    }
+
+    #[tokio::test]
+    async fn problem_json_served_when_requested() {
+        use axum::body::Body;
+        use axum::routing::get;
+        use axum::Router;
+        use tower::ServiceExt;
+
+        let app = Router::new()
+            .route(
+                "/",
+                get(|| async { Err::<(), _>(AppError::ChainNotFound("42".into())) }),
+            )
+            .layer(axum::middleware::from_fn(negotiate_problem_json));
+
+        let response = app
+            .oneshot(
+                axum::http::Request::get("/")
+                    .header(header::ACCEPT, PROBLEM_JSON)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], PROBLEM_JSON);
+
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["type"], "urn:kizami:problem:chain-not-found");
+        assert_eq!(json["status"], 404);
+        assert_eq!(json["code"], "CHAIN_NOT_FOUND");
+        assert_eq!(json["detail"], "chain 42 not found");
+    }
+
+    #[tokio::test]
+    async fn into_response_includes_retry_metadata() {
+        let response = AppError::RateLimited {
+            retry_after_secs: 7,
+        }
+        .into_response();
+
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "7");
+
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["error"]["retry_after_seconds"], 7);
+    }
+
+    #[tokio::test]
+    async fn into_response_includes_indexed_up_to() {
+        let response = AppError::NotYetIndexed {
+            chain_id: "1".into(),
+            timestamp: 5000,
+            indexed_up_to: 102,
+        }
+        .into_response();
+
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["error"]["code"], "NOT_YET_INDEXED");
+        assert_eq!(json["error"]["indexed_up_to"], 102);
+        assert_eq!(json["error"]["details"]["timestamp"], 5000);
+    }
+
+    #[tokio::test]
+    async fn invalid_request_lists_every_field() {
+        let response = AppError::InvalidRequest(vec![
+            AppError::InvalidLimit { limit: 0, max: 100 }.at("limit"),
+            FieldError::malformed("from_ts", "invalid digit found in string"),
+        ])
+        .into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let error = &json["error"];
+        // the first failure decides the code and keeps its details
+        assert_eq!(error["code"], "INVALID_LIMIT");
+        assert_eq!(error["details"]["max"], 100);
+        assert_eq!(
+            error["message"],
+            "limit: limit 0 must be between 1 and 100; from_ts: invalid digit found in string"
+        );
+        let fields = error["details"]["fields"].as_array().unwrap();
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields[0]["field"], "limit");
+        assert_eq!(fields[0]["code"], "INVALID_LIMIT");
+        assert_eq!(fields[0]["limit"], 0);
+        assert_eq!(fields[1]["code"], "INVALID_REQUEST");
+    }
+
+    #[test]
+    fn english_catalog_matches_display() {
+        let errors = [
+            AppError::ChainNotFound("42".into()),
+            AppError::BlockNotFound {
+                chain_id: "1".into(),
+                timestamp: 5,
+                direction: "before".into(),
+            },
+            AppError::InvalidTimestamp("abc".into()),
+            AppError::TimestampInFuture {
+                timestamp: 9,
+                max_skew_secs: 60,
+            },
+            AppError::InvalidDirection("up".into()),
+            AppError::InvalidDate("2024-13-01".into()),
+            AppError::InvalidTimezone("Mars/Base".into()),
+            AppError::InvalidPeriod("2024-Q5".into()),
+            AppError::InvalidCursor("bad".into()),
+            AppError::SnapshotUnavailable("1".into()),
+            AppError::NoCursorHistory {
+                chain_id: "1".into(),
+                at: 7,
+            },
+            AppError::BatchTooLarge { size: 2, max: 1 },
+            AppError::InvalidSampleSize { size: 0, max: 1 },
+            AppError::InvalidLimit { limit: 0, max: 1 },
+            AppError::InvalidBlockRange {
+                from_block: 5,
+                to_block: 1,
+            },
+            AppError::WorkItemNotFound(3),
+            AppError::RangeTooLarge {
+                unit: "rows",
+                requested: 10,
+                max: 5,
+                hint: "export smaller windows".into(),
+            },
+            AppError::InvalidIdempotencyKey("too long".into()),
+            AppError::IdempotencyKeyReused,
+            AppError::PayloadTooLarge { max_bytes: 10 },
+            AppError::NotYetIndexed {
+                chain_id: "1".into(),
+                timestamp: 5,
+                indexed_up_to: 2,
+            },
+            AppError::RateLimited {
+                retry_after_secs: 1,
+            },
+            AppError::Overloaded {
+                retry_after_secs: 1,
+            },
+            AppError::Unauthorized,
+            AppError::InvalidApiKey,
+            AppError::AdminDisabled,
+            AppError::SqdApi("timeout".into()),
+            AppError::Rpc("timeout".into()),
+            AppError::Redis("timeout".into()),
+            AppError::Replica("timeout".into()),
+            AppError::Storage(fjall::Error::Poisoned),
+            AppError::CorruptData("short key".into()),
+        ];
+        for err in errors {
+            assert_eq!(err.message(Locale::En), err.to_string(), "{}", err.code());
+            for locale in [Locale::Es, Locale::Zh] {
+                let message = err.message(locale);
+                assert_ne!(message, err.to_string(), "{} {locale:?}", err.code());
+                assert!(!message.contains('{'), "{message}");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn messages_follow_accept_language() {
+        use axum::body::Body;
+        use axum::routing::get;
+        use axum::Router;
+        use tower::ServiceExt;
+
+        let app = Router::new()
+            .route(
+                "/",
+                get(|| async {
+                    Err::<(), _>(AppError::InvalidRequest(vec![
+                        AppError::InvalidLimit { limit: 0, max: 100 }.at("limit"),
+                        FieldError::malformed("from_ts", "invalid digit found in string"),
+                    ]))
+                }),
+            )
+            .layer(axum::middleware::from_fn(negotiate_problem_json))
+            .layer(axum::middleware::from_fn(i18n::negotiate_language));
+        let get = |accept: &'static str, language: &'static str| {
+            app.clone().oneshot(
+                axum::http::Request::get("/")
+                    .header(header::ACCEPT, accept)
+                    .header(header::ACCEPT_LANGUAGE, language)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+
+        let response = get("application/json", "es-ES,es;q=0.9").await.unwrap();
+        assert_eq!(response.headers()[header::CONTENT_LANGUAGE], "es");
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["error"]["code"], "INVALID_LIMIT");
+        assert_eq!(
+            json["error"]["message"],
+            "limit: el límite 0 debe estar entre 1 y 100; from_ts: invalid digit found in string"
+        );
+        assert_eq!(
+            json["error"]["details"]["fields"][0]["message"],
+            "el límite 0 debe estar entre 1 y 100"
+        );
+
+        let response = get(PROBLEM_JSON, "zh-CN").await.unwrap();
+        assert_eq!(response.headers()[header::CONTENT_LANGUAGE], "zh");
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "INVALID_LIMIT");
+        assert!(json["detail"]
+            .as_str()
+            .unwrap()
+            .starts_with("limit: limit 0 必须在 1 到 100 之间"));
+
+        let response = get("application/json", "fr").await.unwrap();
+        assert_eq!(response.headers()[header::CONTENT_LANGUAGE], "en");
     }
 }
@@ -0,0 +1,413 @@
+//! Localized error messages.
+//!
+//! Error codes never change with the language; only `message` (and `detail` in
+//! problem details) does. Clients pick a language with `Accept-Language`, negotiated by
+//! [`negotiate_language`]; English is the default and the fallback for languages not in
+//! the catalog. Responses say which language they used in `Content-Language`.
+//!
+//! The catalog maps each error code to one template per language. Templates name their
+//! placeholders (`{chain_id}`), filled from the error's fields, so a translation can
+//! order them as its grammar needs. UIs that localize themselves should key on `code`
+//! and read values from `details` instead of parsing messages.
+
+use axum::extract::Request;
+use axum::http::header;
+use axum::middleware::Next;
+use axum::response::Response;
+
+/// A language error messages are available in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    En,
+    Es,
+    Zh,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 3] = [Self::En, Self::Es, Self::Zh];
+
+    /// BCP 47 tag, as sent in `Content-Language`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::En => "en",
+            Self::Es => "es",
+            Self::Zh => "zh",
+        }
+    }
+
+    fn from_tag(tag: &str) -> Option<Self> {
+        let primary = tag.split(['-', '_']).next().unwrap_or_default();
+        Self::ALL
+            .into_iter()
+            .find(|locale| primary.eq_ignore_ascii_case(locale.as_str()))
+    }
+
+    /// The most preferred supported language of an `Accept-Language` value, e.g.
+    /// `zh-CN,zh;q=0.9,en;q=0.8`. Regional variants fall back to their language. Ties
+    /// go to the language listed first; nothing supported means English.
+    pub fn negotiate(accept_language: &str) -> Self {
+        let mut best: Option<(Self, f32)> = None;
+        for range in accept_language.split(',') {
+            let mut parts = range.split(';').map(str::trim);
+            let Some(locale) = parts.next().and_then(Self::from_tag) else {
+                continue;
+            };
+            let q = parts
+                .find_map(|p| p.strip_prefix("q="))
+                .map_or(Some(1.0), |q| q.parse::<f32>().ok())
+                .unwrap_or(0.0);
+            if q > 0.0 && best.is_none_or(|(_, best_q)| q > best_q) {
+                best = Some((locale, q));
+            }
+        }
+        best.map(|(locale, _)| locale).unwrap_or_default()
+    }
+}
+
+tokio::task_local! {
+    static LOCALE: Locale;
+}
+
+/// Language of the request being handled; English outside [`negotiate_language`].
+pub fn current() -> Locale {
+    LOCALE.try_with(|locale| *locale).unwrap_or_default()
+}
+
+/// Middleware making the request's `Accept-Language` the language of any `AppError`
+/// returned while handling it.
+pub async fn negotiate_language(req: Request, next: Next) -> Response {
+    let locale = req
+        .headers()
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|v| v.to_str().ok())
+        .map(Locale::negotiate)
+        .unwrap_or_default();
+    LOCALE.scope(locale, next.run(req)).await
+}
+
+/// `(code, [en, es, zh])`. The English templates reproduce `AppError`'s `Display`.
+const CATALOG: &[(&str, [&str; 3])] = &[
+    (
+        "CHAIN_NOT_FOUND",
+        [
+            "chain {chain_id} not found",
+            "cadena {chain_id} no encontrada",
+            "未找到链 {chain_id}",
+        ],
+    ),
+    (
+        "BLOCK_NOT_FOUND",
+        [
+            "no block found {direction} timestamp {timestamp} on chain {chain_id}",
+            "no se encontró ningún bloque ({direction}) para la marca de tiempo {timestamp} en la cadena {chain_id}",
+            "链 {chain_id} 上未找到时间戳 {timestamp} 的区块（{direction}）",
+        ],
+    ),
+    (
+        "INVALID_TIMESTAMP",
+        [
+            "invalid timestamp: {value}",
+            "marca de tiempo no válida: {value}",
+            "无效的时间戳：{value}",
+        ],
+    ),
+    (
+        "TIMESTAMP_IN_FUTURE",
+        [
+            "timestamp {timestamp} is more than {max_skew_secs}s in the future",
+            "la marca de tiempo {timestamp} está más de {max_skew_secs} s en el futuro",
+            "时间戳 {timestamp} 超出当前时间 {max_skew_secs} 秒以上",
+        ],
+    ),
+    (
+        "INVALID_DIRECTION",
+        [
+            "invalid direction: {value}",
+            "dirección no válida: {value}",
+            "无效的方向：{value}",
+        ],
+    ),
+    (
+        "INVALID_DATE",
+        [
+            "invalid date: {value} (expected YYYY-MM-DD)",
+            "fecha no válida: {value} (se esperaba AAAA-MM-DD)",
+            "无效的日期：{value}（应为 YYYY-MM-DD）",
+        ],
+    ),
+    (
+        "INVALID_TIMEZONE",
+        [
+            "unknown timezone: {value}",
+            "zona horaria desconocida: {value}",
+            "未知的时区：{value}",
+        ],
+    ),
+    (
+        "INVALID_PERIOD",
+        [
+            "invalid period: {value} (expected YYYY, YYYY-Qn, YYYY-MM or YYYY-MM-DD)",
+            "periodo no válido: {value} (se esperaba AAAA, AAAA-Tn, AAAA-MM o AAAA-MM-DD)",
+            "无效的时间段：{value}（应为 YYYY、YYYY-Qn、YYYY-MM 或 YYYY-MM-DD）",
+        ],
+    ),
+    (
+        "INVALID_CURSOR",
+        [
+            "invalid cursor: {value}",
+            "cursor no válido: {value}",
+            "无效的游标：{value}",
+        ],
+    ),
+    (
+        "SNAPSHOT_UNAVAILABLE",
+        [
+            "no index snapshot available for chain {chain_id}",
+            "no hay ninguna instantánea del índice disponible para la cadena {chain_id}",
+            "链 {chain_id} 没有可用的索引快照",
+        ],
+    ),
+    (
+        "NO_CURSOR_HISTORY",
+        [
+            "no indexing progress recorded for chain {chain_id} at or before {at}",
+            "no hay progreso de indexación registrado para la cadena {chain_id} en {at} o antes",
+            "链 {chain_id} 在 {at} 及之前没有索引进度记录",
+        ],
+    ),
+    (
+        "BATCH_TOO_LARGE",
+        [
+            "batch of {size} queries exceeds the limit of {max}",
+            "el lote de {size} consultas supera el límite de {max}",
+            "批量查询数 {size} 超过上限 {max}",
+        ],
+    ),
+    (
+        "INVALID_SAMPLE_SIZE",
+        [
+            "sample size {size} must be between 1 and {max}",
+            "el tamaño de muestra {size} debe estar entre 1 y {max}",
+            "样本数量 {size} 必须在 1 到 {max} 之间",
+        ],
+    ),
+    (
+        "INVALID_LIMIT",
+        [
+            "limit {limit} must be between 1 and {max}",
+            "el límite {limit} debe estar entre 1 y {max}",
+            "limit {limit} 必须在 1 到 {max} 之间",
+        ],
+    ),
+    (
+        "INVALID_BLOCK_RANGE",
+        [
+            "invalid block range {from_block}..={to_block}",
+            "rango de bloques no válido {from_block}..={to_block}",
+            "无效的区块范围 {from_block}..={to_block}",
+        ],
+    ),
+    (
+        "WORK_ITEM_NOT_FOUND",
+        [
+            "work item {id} not found",
+            "tarea {id} no encontrada",
+            "未找到工作项 {id}",
+        ],
+    ),
+    (
+        "RANGE_TOO_LARGE",
+        [
+            "range of {requested} {unit} exceeds the limit of {max} {unit}; {hint}",
+            "el rango de {requested} {unit} supera el límite de {max} {unit}; {hint}",
+            "范围 {requested} {unit} 超过上限 {max} {unit}；{hint}",
+        ],
+    ),
+    (
+        "INVALID_IDEMPOTENCY_KEY",
+        [
+            "invalid Idempotency-Key: {value}",
+            "Idempotency-Key no válida: {value}",
+            "无效的 Idempotency-Key：{value}",
+        ],
+    ),
+    (
+        "IDEMPOTENCY_KEY_REUSED",
+        [
+            "Idempotency-Key was already used for a different request",
+            "la Idempotency-Key ya se usó para otra solicitud",
+            "Idempotency-Key 已用于其他请求",
+        ],
+    ),
+    (
+        "PAYLOAD_TOO_LARGE",
+        [
+            "request body exceeds {max_bytes} bytes",
+            "el cuerpo de la solicitud supera {max_bytes} bytes",
+            "请求体超过 {max_bytes} 字节",
+        ],
+    ),
+    (
+        "NOT_YET_INDEXED",
+        [
+            "no block indexed after timestamp {timestamp} on chain {chain_id} yet (indexed up to block {indexed_up_to})",
+            "todavía no hay ningún bloque indexado después de la marca de tiempo {timestamp} en la cadena {chain_id} (indexado hasta el bloque {indexed_up_to})",
+            "链 {chain_id} 尚未索引时间戳 {timestamp} 之后的区块（已索引至区块 {indexed_up_to}）",
+        ],
+    ),
+    (
+        "RATE_LIMITED",
+        [
+            "rate limit exceeded, retry in {retry_after_secs}s",
+            "límite de solicitudes superado, reintente en {retry_after_secs} s",
+            "请求频率超限，请在 {retry_after_secs} 秒后重试",
+        ],
+    ),
+    (
+        "OVERLOADED",
+        [
+            "server overloaded, retry in {retry_after_secs}s",
+            "servidor sobrecargado, reintente en {retry_after_secs} s",
+            "服务器过载，请在 {retry_after_secs} 秒后重试",
+        ],
+    ),
+    (
+        "UNAUTHORIZED",
+        [
+            "missing or invalid admin token",
+            "token de administración ausente o no válido",
+            "缺少管理令牌或令牌无效",
+        ],
+    ),
+    (
+        "INVALID_API_KEY",
+        [
+            "missing or invalid API key for this tenant",
+            "clave de API ausente o no válida para este cliente",
+            "缺少此租户的 API 密钥或密钥无效",
+        ],
+    ),
+    (
+        "ADMIN_DISABLED",
+        [
+            "admin API is disabled on this deployment",
+            "la API de administración está desactivada en este despliegue",
+            "此部署已禁用管理 API",
+        ],
+    ),
+    (
+        "SQD_API_ERROR",
+        [
+            "SQD API error: {error}",
+            "error de la API de SQD: {error}",
+            "SQD API 错误：{error}",
+        ],
+    ),
+    (
+        "RPC_ERROR",
+        ["RPC error: {error}", "error de RPC: {error}", "RPC 错误：{error}"],
+    ),
+    (
+        "REDIS_ERROR",
+        [
+            "Redis error: {error}",
+            "error de Redis: {error}",
+            "Redis 错误：{error}",
+        ],
+    ),
+    (
+        "REPLICA_ERROR",
+        [
+            "replica error: {error}",
+            "error de la réplica: {error}",
+            "副本错误：{error}",
+        ],
+    ),
+    (
+        "STORAGE_UNAVAILABLE",
+        [
+            "storage error: {error}",
+            "error de almacenamiento: {error}",
+            "存储错误：{error}",
+        ],
+    ),
+    (
+        "STORAGE_ERROR",
+        [
+            "storage error: {error}",
+            "error de almacenamiento: {error}",
+            "存储错误：{error}",
+        ],
+    ),
+    (
+        "DATA_CORRUPTED",
+        [
+            "corrupt data in storage: {error}",
+            "datos corruptos en el almacenamiento: {error}",
+            "存储中的数据已损坏：{error}",
+        ],
+    ),
+];
+
+/// Template for `code` in `locale`, if the catalog has one.
+fn template(code: &str, locale: Locale) -> Option<&'static str> {
+    let (_, templates) = CATALOG.iter().find(|(c, _)| *c == code)?;
+    Some(templates[locale as usize])
+}
+
+/// Renders the message for `code` in `locale`, with `{name}` placeholders replaced by
+/// `params`. `None` when the catalog has no entry for `code`.
+pub fn message(code: &str, locale: Locale, params: &[(&str, String)]) -> Option<String> {
+    let mut message = template(code, locale)?.to_string();
+    for (name, value) in params {
+        message = message.replace(&format!("{{{name}}}"), value);
+    }
+    Some(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negotiates_by_quality_then_order() {
+        assert_eq!(Locale::negotiate("es"), Locale::Es);
+        assert_eq!(Locale::negotiate("zh-CN,zh;q=0.9,en;q=0.8"), Locale::Zh);
+        assert_eq!(Locale::negotiate("zh_TW"), Locale::Zh);
+        assert_eq!(Locale::negotiate("fr-FR, es;q=0.5, en;q=0.7"), Locale::En);
+        assert_eq!(Locale::negotiate("de, es-MX;q=0.3"), Locale::Es);
+        assert_eq!(Locale::negotiate("es;q=0, zh;q=0.1"), Locale::Zh);
+        assert_eq!(Locale::negotiate("es, zh"), Locale::Es);
+        assert_eq!(Locale::negotiate("fr, *;q=0.5"), Locale::En);
+        assert_eq!(Locale::negotiate(""), Locale::En);
+        assert_eq!(Locale::negotiate("es;q=abc"), Locale::En);
+    }
+
+    #[test]
+    fn every_template_uses_the_english_placeholders() {
+        let placeholders = |template: &str| {
+            let mut names: Vec<String> = template
+                .split('{')
+                .skip(1)
+                .filter_map(|s| s.split_once('}').map(|(name, _)| name.to_string()))
+                .collect();
+            names.sort();
+            names.dedup();
+            names
+        };
+        for (code, [en, es, zh]) in CATALOG {
+            assert_eq!(placeholders(es), placeholders(en), "{code} es");
+            assert_eq!(placeholders(zh), placeholders(en), "{code} zh");
+        }
+    }
+
+    #[tokio::test]
+    async fn current_locale_is_scoped_to_the_request() {
+        assert_eq!(current(), Locale::En);
+        assert_eq!(
+            LOCALE.scope(Locale::Zh, async { current() }).await,
+            Locale::Zh
+        );
+    }
+}
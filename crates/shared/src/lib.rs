@@ -1,5 +1,19 @@
+pub mod approximate;
+pub mod auth;
+pub mod beacon;
+pub mod calendar;
 pub mod chains;
+#[cfg(feature = "chaos")]
+pub mod chaos;
+pub mod clock;
 pub mod error;
+pub mod i18n;
+pub mod index_file;
 pub mod models;
+pub mod redis;
+pub mod repair;
+pub mod rpc;
+pub mod scheduler;
 pub mod sqd;
 pub mod storage;
+pub mod validation;
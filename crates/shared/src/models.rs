@@ -2,9 +2,205 @@
 //!
 //! All response types use `snake_case` field names for the JSON wire format.
 
-use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
+use crate::error::AppError;
+
+/// Which side of a timestamp a block lookup searches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// Closest block at or before the timestamp.
+    Before,
+    /// Closest block at or after the timestamp.
+    After,
+    /// Whichever of the `before` and `after` blocks is closer in time; the `before`
+    /// block when both are equally far.
+    Nearest,
+}
+
+impl Direction {
+    /// Returns the lowercase wire name (`"before"`, `"after"` or `"nearest"`).
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Before => "before",
+            Self::After => "after",
+            Self::Nearest => "nearest",
+        }
+    }
+
+    /// The side of `timestamp` a lookup in this direction found a block at `found_ts`
+    /// on: a `nearest` lookup resolves to `before` or `after`.
+    pub fn side(self, timestamp: i64, found_ts: i64) -> Self {
+        match self {
+            // in storage order, where timestamps are unsigned
+            Self::Nearest if found_ts as u64 > timestamp as u64 => Self::After,
+            Self::Nearest => Self::Before,
+            direction => direction,
+        }
+    }
+
+    /// The candidate a `nearest` lookup returns: whichever of `before` and `after` has
+    /// its timestamp (read with `ts`) closer to `timestamp`, `before` when they tie.
+    pub fn nearest_of<T>(
+        timestamp: i64,
+        before: Option<T>,
+        after: Option<T>,
+        ts: impl Fn(&T) -> i64,
+    ) -> Option<T> {
+        match (before, after) {
+            (Some(b), Some(a)) if timestamp.abs_diff(ts(&a)) < timestamp.abs_diff(ts(&b)) => {
+                Some(a)
+            }
+            (before, after) => before.or(after),
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Direction {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "before" => Ok(Self::Before),
+            "after" => Ok(Self::After),
+            "nearest" => Ok(Self::Nearest),
+            other => Err(AppError::InvalidDirection(other.to_string())),
+        }
+    }
+}
+
+/// A lookup's direction together with whether a block exactly at the timestamp
+/// matches, so a direction and an `inclusive` flag that contradict each other can't
+/// reach storage or the cache. A block at the timestamp is always the nearest one, so
+/// [`Bound::Nearest`] has no exclusive form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Bound {
+    /// Closest block strictly before the timestamp.
+    Before,
+    /// Closest block at or before the timestamp.
+    AtOrBefore,
+    /// Closest block strictly after the timestamp.
+    After,
+    /// Closest block at or after the timestamp.
+    AtOrAfter,
+    /// Whichever of the [`Bound::AtOrBefore`] and [`Bound::AtOrAfter`] blocks is
+    /// closer in time; the earlier one when both are equally far.
+    Nearest,
+}
+
+impl Bound {
+    /// Every bound, for tests and benchmarks that cover all lookups.
+    pub const ALL: [Self; 5] = [
+        Self::Before,
+        Self::AtOrBefore,
+        Self::After,
+        Self::AtOrAfter,
+        Self::Nearest,
+    ];
+
+    /// The bound of a lookup in `direction` with the request's `inclusive`. A `nearest`
+    /// lookup always includes the timestamp, so excluding it is rejected.
+    pub fn new(direction: Direction, inclusive: bool) -> Result<Self, AppError> {
+        match (direction, inclusive) {
+            (Direction::Before, false) => Ok(Self::Before),
+            (Direction::Before, true) => Ok(Self::AtOrBefore),
+            (Direction::After, false) => Ok(Self::After),
+            (Direction::After, true) => Ok(Self::AtOrAfter),
+            (Direction::Nearest, true) => Ok(Self::Nearest),
+            (Direction::Nearest, false) => Err(AppError::InvalidDirection(
+                "nearest with inclusive=false (a block at the timestamp is always the nearest)"
+                    .into(),
+            )),
+        }
+    }
+
+    /// The bound of a lookup in `direction`, taking `inclusive` from the request when
+    /// given and from `default` otherwise. `nearest` ignores the default, since it is
+    /// always inclusive, but still rejects an explicit `inclusive=false`.
+    pub fn from_request(
+        direction: Direction,
+        inclusive: Option<bool>,
+        default: bool,
+    ) -> Result<Self, AppError> {
+        let inclusive = inclusive.unwrap_or(default || direction == Direction::Nearest);
+        Self::new(direction, inclusive)
+    }
+
+    /// The side of the timestamp the block is looked for on.
+    pub fn direction(self) -> Direction {
+        match self {
+            Self::Before | Self::AtOrBefore => Direction::Before,
+            Self::After | Self::AtOrAfter => Direction::After,
+            Self::Nearest => Direction::Nearest,
+        }
+    }
+
+    /// True when a block exactly at the timestamp matches.
+    pub fn inclusive(self) -> bool {
+        !matches!(self, Self::Before | Self::After)
+    }
+}
+
+impl fmt::Display for Bound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Before | Self::After | Self::Nearest => self.direction().fmt(f),
+            Self::AtOrBefore => f.write_str("at or before"),
+            Self::AtOrAfter => f.write_str("at or after"),
+        }
+    }
+}
+
+/// Which block a lookup returns when several share the matched timestamp.
+///
+/// Without one, lookups return the block closest to the query in chain order: the
+/// highest of the tied blocks for `before`, the lowest for `after` (see
+/// [`Tie::natural`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Tie {
+    /// The lowest block number with the timestamp.
+    Low,
+    /// The highest block number with the timestamp.
+    High,
+}
+
+impl Tie {
+    /// The tie-break a lookup in `direction` gets without `tie`. A `nearest` lookup
+    /// gets that of the side it matched on, so resolve it with [`Direction::side`]
+    /// first.
+    pub fn natural(direction: Direction) -> Self {
+        match direction {
+            Direction::Before | Direction::Nearest => Self::High,
+            Direction::After => Self::Low,
+        }
+    }
+}
+
+/// A way a chain's block timestamps depart from one block per second or more.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampQuirk {
+    /// Blocks come faster than once a second, so many share a timestamp; `tie` picks
+    /// which of them a lookup returns.
+    SharedTimestamps,
+    /// Block 0 is stored with timestamp 0 rather than the genesis time, and genesis is
+    /// block 1. Block 0 answers only `before` lookups earlier than genesis.
+    ZeroGenesisTimestamp,
+}
+
 /// Response for chain information endpoints.
 #[derive(Debug, Serialize, ToSchema)]
 pub struct ChainResponse {
@@ -14,6 +210,19 @@ pub struct ChainResponse {
     pub chain_id: i32,
     /// Unix timestamp of the chain's genesis block.
     pub genesis_timestamp: i64,
+    /// True if the chain has shut down or migrated and support is being wound down.
+    pub deprecated: bool,
+    /// Unix timestamp after which a deprecated chain may be removed, if announced.
+    pub sunset_at: Option<i64>,
+    /// Typical seconds between a block's timestamp and it being queryable here
+    /// (finality, SQD and ingestion combined). Configured per chain or measured while
+    /// caught up; null until known. Lookups of "now" see blocks at least this old.
+    pub expected_delay_secs: Option<i64>,
+    /// Path of the chain's logo on this server, e.g. `/static/chains/1.svg`; null when
+    /// none is known.
+    pub logo_url: Option<String>,
+    /// Ways the chain's timestamps need care in lookups; empty for most chains.
+    pub timestamp_quirks: Vec<TimestampQuirk>,
 }
 
 /// Response for block lookup endpoints.
@@ -25,6 +234,222 @@ pub struct BlockResponse {
     pub timestamp: i64,
     /// The highest block number indexed so far for this chain.
     pub indexed_up_to: i64,
+    /// Present and true when the chain runs in approximate mode and the block was
+    /// interpolated between stored samples; `number` and `timestamp` are estimates.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub approximate: bool,
+    /// Present and true when `allow_estimate` was set and the timestamp is past the
+    /// last indexed block: `number` and `timestamp` are extrapolated from it (or from
+    /// genesis, when nothing is indexed yet) at the chain's average block time, not
+    /// read from storage.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub estimated: bool,
+    /// The chain's expected ingestion delay in seconds (see `ChainResponse`). Only
+    /// present when the block is near the indexed tip, where a newer block may exist
+    /// on chain but not be indexed yet.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_delay_secs: Option<i64>,
+    /// With `limit`, up to that many stored blocks in the lookup direction, closest
+    /// first. On approximate-mode chains these are the stored samples around the
+    /// timestamp, so the first one can differ from the interpolated `number`. Absent
+    /// when the answer is `estimated`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blocks: Option<Vec<BlockRef>>,
+}
+
+/// Response for the multi-chain lookup: one [`BlockResponse`] per chain that could be
+/// answered.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MultiChainBlockResponse {
+    /// Lookup results keyed by the requested chain ID.
+    pub blocks: BTreeMap<i32, BlockResponse>,
+    /// Chains that couldn't be answered, with the error a single lookup would return.
+    pub unresolved: Vec<SnapshotFailure>,
+}
+
+/// A block identified by number and timestamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct BlockRef {
+    pub number: i64,
+    /// Block timestamp (Unix seconds).
+    pub timestamp: i64,
+}
+
+/// Response for the day boundaries endpoint.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DayBoundariesResponse {
+    /// The requested local date (`YYYY-MM-DD`).
+    pub date: String,
+    /// IANA timezone the date was interpreted in.
+    pub tz: String,
+    /// First second of the local day (Unix seconds).
+    pub start_timestamp: i64,
+    /// First second of the following local day (Unix seconds, exclusive).
+    pub end_timestamp: i64,
+    /// First block of the day.
+    pub first_block: BlockRef,
+    /// Last block of the day.
+    pub last_block: BlockRef,
+    /// The highest block number indexed so far for this chain.
+    pub indexed_up_to: i64,
+}
+
+/// Response for the calendar period range endpoint.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PeriodRangeResponse {
+    /// The requested period (`YYYY`, `YYYY-Qn`, `YYYY-MM` or `YYYY-MM-DD`).
+    pub period: String,
+    /// IANA timezone the period was interpreted in.
+    pub tz: String,
+    /// First second of the period (Unix seconds).
+    pub start_timestamp: i64,
+    /// First second after the period (Unix seconds, exclusive).
+    pub end_timestamp: i64,
+    /// First block of the period.
+    pub start_block: BlockRef,
+    /// Last block of the period.
+    pub end_block: BlockRef,
+    /// Number of blocks from `start_block` to `end_block`, inclusive.
+    pub block_count: i64,
+    /// The highest block number indexed so far for this chain.
+    pub indexed_up_to: i64,
+}
+
+/// Outcome of a single query in a batch lookup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchItemStatus {
+    /// A block matched; `number` and `timestamp` are set.
+    Ok,
+    /// No block matches (yet) on this side of the timestamp.
+    NotFound,
+    /// The request deadline passed before this query was answered.
+    Timeout,
+}
+
+/// One entry of a batch lookup response, in the same position as its query.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BatchItemResponse {
+    pub status: BatchItemStatus,
+    /// Block number, when `status` is `ok`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub number: Option<i64>,
+    /// Block timestamp (Unix seconds), when `status` is `ok`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<i64>,
+    /// Present and true when the block was interpolated (approximate-mode chains).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub approximate: bool,
+}
+
+/// Response for the batch block lookup endpoint.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BatchLookupResponse {
+    /// Per-query results, in request order.
+    pub results: Vec<BatchItemResponse>,
+    /// True if any query timed out and the results are partial.
+    pub partial: bool,
+    /// The highest block number indexed so far for this chain.
+    pub indexed_up_to: i64,
+}
+
+/// Response for the block listing endpoint.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BlockListResponse {
+    pub chain_id: i32,
+    /// Window start (Unix seconds, inclusive).
+    pub from_ts: i64,
+    /// Window end (Unix seconds, exclusive).
+    pub to_ts: i64,
+    /// Blocks in `(timestamp, number)` order, up to `limit` of them. On chains storing
+    /// only every Nth block, just the stored ones.
+    pub blocks: Vec<BlockRef>,
+    /// Pass as `cursor` to get the next page; null on the last one. The same URL is in
+    /// the `Link: rel="next"` header.
+    pub next_cursor: Option<String>,
+}
+
+/// Response for the block sampling endpoint.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BlockSampleResponse {
+    pub chain_id: i32,
+    /// Window start (Unix seconds, inclusive).
+    pub from_ts: i64,
+    /// Window end (Unix seconds, exclusive).
+    pub to_ts: i64,
+    /// Seed the sample was drawn with. Pass it back to get the same sample again.
+    pub seed: u64,
+    /// Block numbers spanned by the window, from its first to its last stored block.
+    pub population: i64,
+    /// Sampled blocks in ascending order. Fewer than requested when the window holds
+    /// fewer blocks, or on chains storing only every Nth block.
+    pub blocks: Vec<BlockRef>,
+}
+
+/// One chain's block at the snapshot time.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SnapshotEntry {
+    pub chain_id: i32,
+    /// Latest block at or before the snapshot timestamp.
+    pub block_number: i64,
+    /// Block timestamp (Unix seconds).
+    pub block_timestamp: i64,
+    /// The highest block number indexed so far for this chain.
+    pub indexed_up_to: i64,
+}
+
+/// A chain the snapshot couldn't resolve, with the error a single lookup would return.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SnapshotFailure {
+    pub chain_id: i32,
+    /// Machine-readable error code, e.g. `CHAIN_NOT_FOUND` or `NOT_YET_INDEXED`.
+    pub code: String,
+    pub message: String,
+}
+
+/// Response for the cross-chain snapshot endpoint.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SnapshotResponse {
+    /// The requested timestamp (Unix seconds).
+    pub timestamp: i64,
+    /// Resolved chains, in request order.
+    pub results: Vec<SnapshotEntry>,
+    /// Chains that couldn't be resolved, in request order.
+    pub unresolved: Vec<SnapshotFailure>,
+}
+
+/// Response for beacon slot endpoints.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BeaconSlotResponse {
+    pub slot: i64,
+    pub epoch: i64,
+    /// Slot start time (Unix seconds).
+    pub timestamp: i64,
+    /// Execution block proposed in this slot. `null` for missed and pre-merge slots.
+    pub block_number: Option<i64>,
+    /// True if the slot is post-merge but no execution block was proposed in it.
+    pub missed: bool,
+    /// The highest Ethereum block number indexed so far.
+    pub indexed_up_to: i64,
+}
+
+/// Response for beacon epoch endpoints.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BeaconEpochResponse {
+    pub epoch: i64,
+    pub start_slot: i64,
+    pub end_slot: i64,
+    /// Start of the first slot (Unix seconds).
+    pub start_timestamp: i64,
+    /// Start of the last slot (Unix seconds).
+    pub end_timestamp: i64,
+    /// First execution block in the epoch, or after it if every slot was missed.
+    /// `null` for pre-merge epochs.
+    pub block_number: Option<i64>,
+    /// Timestamp of `block_number`.
+    pub block_timestamp: Option<i64>,
+    /// The highest Ethereum block number indexed so far.
+    pub indexed_up_to: i64,
 }
 
 /// Response for the indexing status endpoint.
@@ -43,6 +468,530 @@ pub struct IndexingStatusResponse {
     /// When the cursor was last updated (null if never ingested).
     #[schema(value_type = Option<String>)]
     pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Number of fetched blocks quarantined for failing validation.
+    pub rejected_blocks: u64,
+    /// True while an operator has paused ingestion for the chain. Omitted when false.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub paused: bool,
+    /// When the SQD head last advanced, as seen by this process (null until fetched).
+    #[schema(value_type = Option<String>)]
+    pub head_advanced_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// True when the head hasn't advanced for far longer than the chain's usual block
+    /// time, i.e. the chain or its dataset has halted rather than kizami falling behind.
+    pub chain_stalled: bool,
+    /// One-word verdict on whether the chain's answers are current.
+    pub health: ChainHealth,
+    /// Health of the chain's SQD dataset. Null until kizami has made a request for it.
+    pub sqd: Option<SqdHealthResponse>,
+    /// Region or replica that produced this status (`SERVED_BY`). Omitted when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub served_by: Option<&'static str>,
+}
+
+/// Health grade of a chain, from its lag, cursor age and recent SQD error rate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ChainHealth {
+    /// Close to the head and ingesting normally.
+    Fresh,
+    /// Too far behind the head, or the cursor hasn't moved in a while (also before the
+    /// head is first known).
+    Lagging,
+    /// The chain or its dataset has halted (see `chain_stalled`).
+    Stalled,
+    /// Most recent SQD requests for the chain fail.
+    Erroring,
+}
+
+impl ChainHealth {
+    pub const ALL: [ChainHealth; 4] = [Self::Fresh, Self::Lagging, Self::Stalled, Self::Erroring];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Fresh => "fresh",
+            Self::Lagging => "lagging",
+            Self::Stalled => "stalled",
+            Self::Erroring => "erroring",
+        }
+    }
+}
+
+/// Recent availability of a chain's SQD dataset, to tell "SQD is behind or down"
+/// apart from "kizami is behind".
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SqdHealthResponse {
+    /// Share of recent SQD requests for the dataset that failed, from 0 to 1.
+    pub error_rate: f64,
+    /// Number of recent requests the error rate covers (up to 50).
+    pub recent_requests: u32,
+    /// When a request for the dataset last succeeded.
+    #[schema(value_type = Option<String>)]
+    pub last_success_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// When a request for the dataset last failed.
+    #[schema(value_type = Option<String>)]
+    pub last_error_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Message of the last failure.
+    pub last_error: Option<String>,
+    /// The chain's own finalized head from RPC. Null unless an RPC endpoint is
+    /// configured for the chain.
+    pub chain_head: Option<i64>,
+    /// Blocks the SQD dataset head trails the chain's finalized head by. Null without
+    /// both heads.
+    pub dataset_lag: Option<i64>,
+}
+
+/// Ingestion uptime over fixed windows, from the persisted cycle summaries.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UptimeResponse {
+    /// When the most recent ingestion cycle started (null if none was recorded).
+    #[schema(value_type = Option<String>)]
+    pub last_cycle_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub windows: Vec<UptimeWindowResponse>,
+}
+
+/// Ingestion uptime over one window ending now.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UptimeWindowResponse {
+    /// Window length in days.
+    pub days: u32,
+    /// Start of the measured period: the window start, or the first recorded cycle if
+    /// history is shorter than the window.
+    #[schema(value_type = Option<String>)]
+    pub measured_from: Option<chrono::DateTime<chrono::Utc>>,
+    /// Percentage of the measured period ingestion was running, weighted by the share
+    /// of chains each cycle handled without error. Null without recorded cycles.
+    pub uptime_percent: Option<f64>,
+    /// Cycles recorded in the window.
+    pub cycles: u64,
+    /// Cycles in which at least one chain failed.
+    pub cycles_with_errors: u64,
+}
+
+/// The host clock compared with its reference.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ClockResponse {
+    /// Current time as kizami uses it, corrected when the host clock is skewed.
+    #[schema(value_type = String)]
+    pub server_time: chrono::DateTime<chrono::Utc>,
+    /// Reference time minus host time in seconds; positive when the host clock is
+    /// behind. Null until measured.
+    pub skew_secs: Option<f64>,
+    /// What the skew was measured against: `sqd` (response `Date` headers) or `ntp`.
+    /// Null until measured.
+    pub source: Option<&'static str>,
+    /// When the skew was last measured.
+    #[schema(value_type = Option<String>)]
+    pub measured_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Skew tolerated before timestamps are corrected.
+    pub tolerance_secs: f64,
+    /// True when the skew exceeds the tolerance and timestamps are being corrected.
+    pub corrected: bool,
+}
+
+/// What this deployment supports and its limits, so clients can adapt at runtime
+/// instead of assuming one deployment's configuration.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CapabilitiesResponse {
+    pub lookup: LookupCapabilities,
+    pub batch: BatchCapabilities,
+    pub estimation: EstimationCapabilities,
+    pub formats: FormatCapabilities,
+    pub rate_limits: RateLimitCapabilities,
+}
+
+/// Limits of single, multi-chain and snapshot lookups.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LookupCapabilities {
+    /// Accepted forms of a lookup timestamp: `unix_seconds` and `rfc3339`.
+    pub timestamp_formats: Vec<&'static str>,
+    /// How far past the server's clock a timestamp may be without `allow_future`.
+    pub max_future_skew_secs: i64,
+    /// Most blocks one lookup returns with `limit`.
+    pub max_limit: usize,
+    /// Most chains one multi-chain lookup or snapshot covers.
+    pub max_chains: usize,
+    /// Whether unfinalized blocks near the tip are indexed. Always false: only
+    /// finalized blocks are, so answers never reorg.
+    pub hot_blocks: bool,
+    /// Lookups per minute, across all clients, that may skip the cache with `fresh`.
+    /// Past that they are served from the cache.
+    pub fresh_lookups_per_min: u64,
+}
+
+/// Limits of the batch lookup.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BatchCapabilities {
+    /// Most queries in one batch.
+    pub max_queries: usize,
+    /// Deadline applied when a batch doesn't set `deadline_ms`.
+    pub default_deadline_ms: u64,
+    /// Longest `deadline_ms` a batch may ask for.
+    pub max_deadline_ms: u64,
+}
+
+/// Chains answered by estimation rather than exactly.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct EstimationCapabilities {
+    /// Chains in approximate mode, keyed by chain ID, with the sampling interval N
+    /// (every Nth block is stored). Lookups on them may be flagged `approximate`.
+    pub approximate_chains: BTreeMap<i32, i64>,
+}
+
+/// Response formats and bulk downloads on offer.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FormatCapabilities {
+    /// Media types JSON responses can be negotiated to with `Accept`.
+    pub response_types: Vec<&'static str>,
+    /// The NDJSON block export. Null when exports are disabled.
+    pub export: Option<ExportCapabilities>,
+    /// Whether per-chain index files are served at `/v1/chains/{chain_id}/index`.
+    pub index_files: bool,
+    /// Whether indexed blocks can be followed as server-sent events at `/v1/events`.
+    pub events: bool,
+}
+
+/// Limits of the NDJSON block export.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ExportCapabilities {
+    /// Most rows one response carries before handing off to its `next` line.
+    pub max_rows_per_response: usize,
+    /// Longest window one export may cover. Null when unlimited.
+    pub max_window_secs: Option<i64>,
+    /// Most blocks one export's window may hold. Null when unlimited.
+    pub max_rows: Option<i64>,
+}
+
+/// Request quotas applied to clients.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RateLimitCapabilities {
+    /// Requests per minute per client IP on `/v1` routes. Null when unlimited.
+    pub per_ip_per_min: Option<u32>,
+    /// Whether tenant namespaces with their own API keys and quotas are served under
+    /// `/t/{tenant}/v1`.
+    pub tenants: bool,
+}
+
+/// A chain's first block as one source reports it.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GenesisObservation {
+    /// `stored` (this node's data) or `sqd` (the dataset, fetched now).
+    pub source: &'static str,
+    /// The genesis block (block 0, or block 1 when block 0 has timestamp 0), or the
+    /// first block available when the source starts later.
+    pub number: i64,
+    pub timestamp: i64,
+    /// True when the block is genesis rather than the first block of a later start.
+    pub is_genesis: bool,
+    /// Block timestamp minus the genesis timestamp in use.
+    pub offset_secs: i64,
+    /// True when the block agrees with the genesis timestamp in use: equal to it for a
+    /// genesis block, not before it otherwise.
+    pub consistent: bool,
+}
+
+/// A chain's genesis timestamp audited against its stored and fetched first blocks.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GenesisReportResponse {
+    pub chain_id: i32,
+    pub name: &'static str,
+    /// Genesis timestamp in use, after `GENESIS_TIMESTAMPS`.
+    pub genesis_timestamp: i64,
+    /// Genesis timestamp built into kizami.
+    pub builtin_genesis_timestamp: i64,
+    /// True when `GENESIS_TIMESTAMPS` overrides the built-in value.
+    pub overridden: bool,
+    /// One entry per source that has blocks.
+    pub observations: Vec<GenesisObservation>,
+    /// Why the dataset couldn't be checked, if it couldn't.
+    pub sqd_error: Option<String>,
+    /// True when every observation is consistent.
+    pub consistent: bool,
+}
+
+/// Blocks written by ingestion or the work queue, as sent on `/v1/events`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+pub struct BlocksIndexedEvent {
+    pub chain_id: i32,
+    /// First block written.
+    pub from_block: i64,
+    /// Last block written.
+    pub to_block: i64,
+    /// Blocks written, fewer than the range on approximate-mode chains and when blocks
+    /// were quarantined.
+    pub blocks: u64,
+    /// Timestamp of the first block written (Unix seconds).
+    pub from_timestamp: i64,
+    /// Timestamp of the last block written (Unix seconds).
+    pub to_timestamp: i64,
+    /// True when the chain is now within a batch of its finalized head.
+    pub at_head: bool,
+}
+
+/// Ingestion state of a chain after a pause or resume.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct IngestionControlResponse {
+    pub chain_id: i32,
+    pub name: &'static str,
+    pub paused: bool,
+}
+
+/// One point of a chain's ingestion lag history.
+#[derive(Debug, Clone, Copy, Serialize, ToSchema)]
+pub struct LagSampleResponse {
+    /// When the sample was taken (Unix seconds).
+    pub at: i64,
+    /// Blocks between the finalized head and the cursor.
+    pub lag: i64,
+}
+
+/// How far a chain was indexed at a past moment, from the persisted cursor history.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CursorHistoryResponse {
+    pub chain_id: i32,
+    pub name: &'static str,
+    /// The moment asked about (Unix seconds).
+    pub at: i64,
+    /// Highest block indexed as of the snapshot below.
+    pub indexed_up_to: i64,
+    /// When that snapshot was taken (Unix seconds), the latest one at or before `at`.
+    /// Snapshots are taken every `CURSOR_HISTORY_INTERVAL_SECS`.
+    pub recorded_at: i64,
+}
+
+/// Recent ingestion lag for one chain, oldest sample first.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LagHistoryResponse {
+    pub chain_id: i32,
+    pub name: &'static str,
+    pub samples: Vec<LagSampleResponse>,
+}
+
+/// Lookup cache counters since startup.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CacheStatsResponse {
+    /// Cached answers right now (approximate, moka updates it lazily).
+    pub entries: u64,
+    /// Configured capacity (`CACHE_MAX_ENTRIES`).
+    pub max_entries: u64,
+    /// Lookups answered from the cache.
+    pub hits: u64,
+    /// Lookups that went to storage, including coalesced ones.
+    pub misses: u64,
+    /// Lookups that skipped the cache on request (`Cache-Control: no-cache` or
+    /// `fresh=true`).
+    pub bypasses: u64,
+    /// Bypass requests served from the cache because `CACHE_BYPASS_PER_MIN` was spent.
+    pub bypasses_limited: u64,
+    /// Local misses answered by the shared cache (`SHARED_CACHE_URL`) instead of
+    /// storage. Counted in `misses` too.
+    pub shared_hits: u64,
+    /// Shared cache reads and writes that failed or timed out.
+    pub shared_errors: u64,
+}
+
+/// Why a block range was queued. Both kinds are processed the same way: the range is
+/// re-fetched from SQD, validated and written, leaving the cursor alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkKind {
+    /// Blocks never ingested, e.g. history before a chain's configured start.
+    #[default]
+    Backfill,
+    /// Blocks ingested before but missing or wrong in storage.
+    Repair,
+}
+
+/// A queued backfill or repair range.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WorkItemResponse {
+    pub id: u64,
+    /// Higher runs first (0-255).
+    pub priority: u8,
+    pub kind: WorkKind,
+    pub chain_id: i32,
+    /// Next block to fetch; moves up as the range is worked off.
+    pub from_block: i64,
+    /// Last block of the range, inclusive.
+    pub to_block: i64,
+    /// Failed attempts since the range last made progress.
+    pub attempts: u32,
+    /// True once `attempts` reached the retry limit; the item stays queued but is no
+    /// longer run until deleted and queued again.
+    pub exhausted: bool,
+    #[schema(value_type = String)]
+    pub enqueued_at: chrono::DateTime<chrono::Utc>,
+    /// Not run before this time (retry backoff).
+    #[schema(value_type = String)]
+    pub not_before: chrono::DateTime<chrono::Utc>,
+    pub last_error: Option<String>,
+}
+
+/// A background job and its recent runs.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct JobStatusResponse {
+    /// Job name, e.g. "ingest", "cursor_history".
+    pub name: &'static str,
+    /// Delay between the end of one run and the start of the next.
+    pub interval_secs: u64,
+    /// Upper bound of the random delay added to each interval.
+    pub jitter_secs: u64,
+    /// Whether a run is in progress.
+    pub running: bool,
+    /// Runs finished since startup.
+    pub runs: u64,
+    /// Runs that returned an error.
+    pub failures: u64,
+    #[schema(value_type = Option<String>)]
+    pub last_started_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub last_duration_ms: Option<u64>,
+    /// Error of the last run, `null` when it succeeded.
+    pub last_error: Option<String>,
+    /// When the next run is due; `null` while running or when not scheduled.
+    #[schema(value_type = Option<String>)]
+    pub next_run_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// A quarantined block as returned by the admin quarantine endpoints.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RejectedBlockResponse {
+    /// Block number.
+    pub number: i64,
+    /// Block timestamp as reported by the source (Unix seconds).
+    pub timestamp: i64,
+    /// Why the block failed validation (e.g. "before_genesis", "in_future").
+    pub reason: &'static str,
+    /// When the block was quarantined.
+    #[schema(value_type = String)]
+    pub rejected_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// A block whose timestamp was out of line with the block before it at ingest time.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TimestampAnomalyResponse {
+    /// "backwards" (older than the previous block) or "jump" (gap over
+    /// `TIMESTAMP_JUMP_ALERT_SECS`).
+    pub kind: &'static str,
+    pub number: i64,
+    /// Block timestamp as reported by the source (Unix seconds).
+    pub timestamp: i64,
+    pub prev_number: i64,
+    pub prev_timestamp: i64,
+    /// `timestamp - prev_timestamp`; negative for backwards timestamps.
+    pub gap_secs: i64,
+    /// When ingestion saw the anomaly.
+    #[schema(value_type = String)]
+    pub detected_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// An ingestion failure kept in a chain's error log.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct IngestErrorResponse {
+    pub chain_id: i32,
+    pub name: &'static str,
+    /// When ingestion hit the error.
+    #[schema(value_type = String)]
+    pub at: chrono::DateTime<chrono::Utc>,
+    /// Error code, as in API error bodies (e.g. "SQD_API_ERROR", "STORAGE_ERROR").
+    pub code: String,
+    pub message: String,
+}
+
+/// Result of a quarantine accept, purge, or re-validate action.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct QuarantineActionResponse {
+    /// Number of quarantined blocks the action applied to.
+    pub affected: u64,
+    /// Number of blocks still quarantined for the chain afterwards.
+    pub remaining: u64,
+}
+
+/// Rolling latency percentiles for one route.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RouteLatencyResponse {
+    /// Matched route template (e.g. "/v1/chains/{chain_id}").
+    pub route: String,
+    /// Requests recorded since startup.
+    pub total_requests: u64,
+    /// Samples in the rolling window the percentiles are computed over.
+    pub window_samples: usize,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    /// Whether the route's p99 currently exceeds the SLO threshold.
+    pub violating: bool,
+}
+
+/// Latency SLO report across all routes.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SloReportResponse {
+    /// Configured p99 latency target in milliseconds.
+    pub p99_threshold_ms: f64,
+    pub routes: Vec<RouteLatencyResponse>,
+}
+
+/// Stored data of one chain as found at startup.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ChainExtentResponse {
+    pub chain_id: i32,
+    pub name: String,
+    /// Persisted ingestion cursor.
+    pub cursor: i64,
+    /// Earliest stored block, absent if the chain has no data.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_block: Option<BlockRef>,
+    /// Latest stored block, absent if the chain has no data.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_block: Option<BlockRef>,
+    /// Blocks held in quarantine.
+    pub quarantined: u64,
+}
+
+/// A chain whose cursor was found ahead of its stored blocks at startup.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CursorDiscrepancyResponse {
+    pub chain_id: i32,
+    pub cursor: i64,
+    /// Highest stored block, absent if the chain has no data.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_stored_block: Option<i64>,
+}
+
+/// Usage counters for one tenant namespace since startup.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TenantUsageResponse {
+    pub tenant: String,
+    /// Configured quota in requests per minute.
+    pub requests_per_minute: u32,
+    /// Requests that passed authentication, including rate-limited ones.
+    pub requests: u64,
+    /// Requests rejected by the tenant's quota.
+    pub rate_limited: u64,
+    /// Responses with a 4xx status, rate limiting excluded.
+    pub client_errors: u64,
+    /// Responses with a 5xx status.
+    pub server_errors: u64,
+}
+
+/// State the node came back in after opening storage.
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct RecoveryReportResponse {
+    /// When storage was opened.
+    #[schema(value_type = String)]
+    pub opened_at: chrono::DateTime<chrono::Utc>,
+    /// Time to open storage, including journal replay.
+    pub open_duration_ms: u64,
+    /// Time to build this report after opening.
+    pub report_duration_ms: u64,
+    /// Write-ahead journal files found on open.
+    pub journal_files: usize,
+    /// Size of those journals, i.e. what was replayed.
+    pub journal_bytes: u64,
+    /// Total database size on disk.
+    pub disk_bytes: u64,
+    pub chains: Vec<ChainExtentResponse>,
+    /// Chains whose cursor points past their stored blocks. Ingestion logs these and
+    /// heals them when `CURSOR_HEAL` is set.
+    pub cursor_discrepancies: Vec<CursorDiscrepancyResponse>,
 }
 
 /// Top-level error response body.
@@ -58,23 +1007,106 @@ pub struct ErrorDetail {
     pub code: String,
     /// Human-readable error description.
     pub message: String,
+    /// Seconds to wait before retrying (set for rate-limit and overload errors).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_seconds: Option<u64>,
+    /// Highest indexed block for the chain (set when the answer isn't indexed yet).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub indexed_up_to: Option<i64>,
+    /// Variant-specific structured context.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub details: Option<serde_json::Value>,
+}
+
+/// RFC 9457 problem details body, served as `application/problem+json` when the
+/// client asks for it via `Accept`. Carries the same metadata as [`ErrorDetail`].
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ProblemDetails {
+    /// Problem type URI, derived from the error code (e.g. `urn:kizami:problem:chain-not-found`).
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    /// Short summary of the problem type (the HTTP reason phrase).
+    pub title: String,
+    /// HTTP status code.
+    pub status: u16,
+    /// Human-readable explanation of this occurrence.
+    pub detail: String,
+    /// Machine-readable error code, same as in the default envelope.
+    pub code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_seconds: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub indexed_up_to: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub details: Option<serde_json::Value>,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn bound_folds_inclusive_into_the_direction() {
+        assert_eq!(
+            Bound::new(Direction::Before, true).unwrap(),
+            Bound::AtOrBefore
+        );
+        assert_eq!(Bound::new(Direction::After, false).unwrap(), Bound::After);
+        assert_eq!(
+            Bound::new(Direction::Nearest, false).unwrap_err().code(),
+            "INVALID_DIRECTION"
+        );
+        // nearest is inclusive whatever the deployment default
+        assert_eq!(
+            Bound::from_request(Direction::Nearest, None, false).unwrap(),
+            Bound::Nearest
+        );
+        assert_eq!(
+            Bound::from_request(Direction::After, None, true).unwrap(),
+            Bound::AtOrAfter
+        );
+        for bound in Bound::ALL {
+            assert_eq!(
+                Bound::new(bound.direction(), bound.inclusive()).unwrap(),
+                bound
+            );
+        }
+    }
+
+    #[test]
+    fn direction_parses_and_displays() {
+        assert_eq!("before".parse::<Direction>().unwrap(), Direction::Before);
+        assert_eq!("after".parse::<Direction>().unwrap(), Direction::After);
+        assert_eq!(Direction::After.to_string(), "after");
+        assert_eq!(
+            "sideways".parse::<Direction>().unwrap_err().code(),
+            "INVALID_DIRECTION"
+        );
+    }
+
     #[test]
     fn chain_response_serializes_to_snake_case() {
         let resp = ChainResponse {
             name: "Ethereum",
             chain_id: 1,
             genesis_timestamp: 1438269988,
+            deprecated: false,
+            sunset_at: None,
+            expected_delay_secs: Some(900),
+            logo_url: Some("/static/chains/1.svg".into()),
+            timestamp_quirks: vec![TimestampQuirk::ZeroGenesisTimestamp],
         };
         let json = serde_json::to_value(&resp).unwrap();
         assert_eq!(json["chain_id"], 1);
         assert_eq!(json["genesis_timestamp"], 1438269988);
         assert_eq!(json["name"], "Ethereum");
+        assert_eq!(json["deprecated"], false);
+        assert!(json["sunset_at"].is_null());
+        assert_eq!(json["expected_delay_secs"], 900);
+        assert_eq!(json["logo_url"], "/static/chains/1.svg");
+        assert_eq!(json["timestamp_quirks"][0], "zero_genesis_timestamp");
     }
 
     #[test]
@@ -83,10 +1115,18 @@ mod tests {
             number: 100,
             timestamp: 1000,
             indexed_up_to: 200,
+            approximate: false,
+            estimated: false,
+            expected_delay_secs: None,
+            blocks: None,
         };
         let json = serde_json::to_value(&resp).unwrap();
         assert_eq!(json["indexed_up_to"], 200);
         assert_eq!(json["number"], 100);
         assert_eq!(json["timestamp"], 1000);
+        assert!(json.get("approximate").is_none());
+        assert!(json.get("estimated").is_none());
+        assert!(json.get("expected_delay_secs").is_none());
+        assert!(json.get("blocks").is_none());
     }
 }
@@ -0,0 +1,374 @@
+//! Minimal Redis client, for state shared between API replicas.
+//!
+//! Speaks RESP2 over plain TCP: commands go out as arrays of bulk strings, and replies
+//! come back as [`Reply`]s. Connections are opened lazily, authenticated and switched
+//! to the URL's database on connect, and dropped on any I/O error so the next command
+//! reconnects. Commands take the first idle connection of a small fixed set, so more
+//! are only opened under concurrency, and run one command (or pipeline) at a time on
+//! it.
+//!
+//! Configured with a `redis://[user:password@]host[:port][/db]` URL. TLS (`rediss://`)
+//! is not supported.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::Url;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufStream};
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+
+use crate::error::AppError;
+
+/// Connections per client.
+const POOL_SIZE: usize = 8;
+
+/// Default time a command (connecting included) may take before it fails.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Longest bulk string or array accepted in a reply, so a confused server can't make
+/// the client allocate without bound.
+const MAX_REPLY_LEN: i64 = 64 * 1024 * 1024;
+
+/// A decoded RESP2 reply. Error replies are returned as [`AppError::Redis`] instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reply {
+    Nil,
+    Status(String),
+    Int(i64),
+    Data(Vec<u8>),
+    Array(Vec<Reply>),
+}
+
+impl Reply {
+    /// The reply as an integer, if it is one.
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Self::Int(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// The reply as a UTF-8 string, if it is bulk data or a status.
+    pub fn into_string(self) -> Option<String> {
+        match self {
+            Self::Data(data) => String::from_utf8(data).ok(),
+            Self::Status(status) => Some(status),
+            _ => None,
+        }
+    }
+
+    /// The reply's bytes, if it is bulk data.
+    pub fn into_data(self) -> Option<Vec<u8>> {
+        match self {
+            Self::Data(data) => Some(data),
+            _ => None,
+        }
+    }
+}
+
+/// Where and how to connect.
+#[derive(Clone, PartialEq, Eq)]
+struct Target {
+    addr: String,
+    username: Option<String>,
+    password: Option<String>,
+    db: u32,
+}
+
+/// A Redis server. Clones share connections.
+#[derive(Clone)]
+pub struct RedisClient {
+    target: Arc<Target>,
+    connections: Arc<[Mutex<Option<BufStream<TcpStream>>>]>,
+    next: Arc<AtomicUsize>,
+    timeout: Duration,
+}
+
+// URLs may carry a password, so only the address is printed
+impl std::fmt::Debug for RedisClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedisClient")
+            .field("addr", &self.target.addr)
+            .field("db", &self.target.db)
+            .finish()
+    }
+}
+
+impl RedisClient {
+    /// Parses a `redis://` URL. Nothing is connected until the first command.
+    pub fn from_url(url: &str) -> Result<Self, String> {
+        let url = Url::parse(url).map_err(|e| e.to_string())?;
+        if url.scheme() != "redis" {
+            return Err(format!(
+                "unsupported scheme {:?}, expected redis",
+                url.scheme()
+            ));
+        }
+        let host = url.host_str().ok_or("missing host")?;
+        let db = match url.path().trim_start_matches('/') {
+            "" => 0,
+            db => db.parse().map_err(|_| format!("invalid database {db:?}"))?,
+        };
+        let target = Target {
+            addr: format!("{host}:{}", url.port().unwrap_or(6379)),
+            username: Some(url.username())
+                .filter(|u| !u.is_empty())
+                .map(str::to_string),
+            password: url.password().map(str::to_string),
+            db,
+        };
+        Ok(Self {
+            target: Arc::new(target),
+            connections: (0..POOL_SIZE).map(|_| Mutex::new(None)).collect(),
+            next: Arc::default(),
+            timeout: COMMAND_TIMEOUT,
+        })
+    }
+
+    /// Fails commands (connecting included) that take longer than `timeout`, instead
+    /// of the default 2 seconds.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// `host:port` of the server.
+    pub fn addr(&self) -> &str {
+        &self.target.addr
+    }
+
+    /// Runs one command.
+    pub async fn query(&self, args: &[&[u8]]) -> Result<Reply, AppError> {
+        let mut replies = self.pipeline(&[args]).await?;
+        Ok(replies.pop().unwrap_or(Reply::Nil))
+    }
+
+    /// Sends several commands in one round trip and returns their replies in order.
+    /// The first error reply fails the whole pipeline, though every command ran.
+    pub async fn pipeline(&self, commands: &[&[&[u8]]]) -> Result<Vec<Reply>, AppError> {
+        let idle = self.connections.iter().find_map(|c| c.try_lock().ok());
+        let mut conn = match idle {
+            Some(conn) => conn,
+            None => {
+                let slot = self.next.fetch_add(1, Ordering::Relaxed) % self.connections.len();
+                self.connections[slot].lock().await
+            }
+        };
+        let result = tokio::time::timeout(self.timeout, async {
+            if conn.is_none() {
+                *conn = Some(connect(&self.target).await?);
+            }
+            let stream = conn.as_mut().unwrap();
+            for args in commands {
+                stream.write_all(&encode_command(args)).await?;
+            }
+            stream.flush().await?;
+            let mut replies = Vec::with_capacity(commands.len());
+            for _ in commands {
+                replies.push(read_reply(stream).await?);
+            }
+            Ok::<_, std::io::Error>(replies)
+        })
+        .await
+        .unwrap_or_else(|_| Err(std::io::Error::other("timed out")));
+        match result {
+            Ok(replies) => replies.into_iter().collect(),
+            Err(e) => {
+                // the stream may be mid-reply: start over on a fresh connection
+                *conn = None;
+                Err(AppError::Redis(format!("{}: {e}", self.target.addr)))
+            }
+        }
+    }
+}
+
+/// Opens a connection, authenticates and selects the database.
+async fn connect(target: &Target) -> std::io::Result<BufStream<TcpStream>> {
+    let stream = TcpStream::connect(&target.addr).await?;
+    stream.set_nodelay(true)?;
+    let mut stream = BufStream::new(stream);
+    let mut setup: Vec<Vec<&[u8]>> = Vec::new();
+    if let Some(password) = &target.password {
+        let mut auth: Vec<&[u8]> = vec![b"AUTH"];
+        if let Some(username) = &target.username {
+            auth.push(username.as_bytes());
+        }
+        auth.push(password.as_bytes());
+        setup.push(auth);
+    }
+    let db = target.db.to_string();
+    if target.db != 0 {
+        setup.push(vec![b"SELECT", db.as_bytes()]);
+    }
+    for args in &setup {
+        stream.write_all(&encode_command(args)).await?;
+    }
+    stream.flush().await?;
+    for _ in &setup {
+        if let Err(e) = read_reply(&mut stream).await? {
+            return Err(std::io::Error::other(e.to_string()));
+        }
+    }
+    Ok(stream)
+}
+
+/// Encodes a command as a RESP array of bulk strings.
+fn encode_command(args: &[&[u8]]) -> Vec<u8> {
+    let mut buf = format!("*{}\r\n", args.len()).into_bytes();
+    for arg in args {
+        buf.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+        buf.extend_from_slice(arg);
+        buf.extend_from_slice(b"\r\n");
+    }
+    buf
+}
+
+/// Reads one reply. The outer error is the connection's, the inner one an error reply
+/// (which leaves the connection usable).
+async fn read_reply(stream: &mut BufStream<TcpStream>) -> std::io::Result<Result<Reply, AppError>> {
+    let line = read_line(stream).await?;
+    let (kind, rest) = line.split_at_checked(1).unwrap_or(("", ""));
+    let int = || {
+        rest.parse::<i64>()
+            .map_err(|_| invalid(format!("bad length {rest:?}")))
+    };
+    Ok(Ok(match kind {
+        "+" => Reply::Status(rest.to_string()),
+        "-" => return Ok(Err(AppError::Redis(rest.to_string()))),
+        ":" => Reply::Int(int()?),
+        "$" => match int()? {
+            -1 => Reply::Nil,
+            len @ 0..=MAX_REPLY_LEN => {
+                let mut data = vec![0; len as usize + 2];
+                stream.read_exact(&mut data).await?;
+                data.truncate(len as usize);
+                Reply::Data(data)
+            }
+            len => return Err(invalid(format!("bulk length {len}"))),
+        },
+        "*" => match int()? {
+            -1 => Reply::Nil,
+            len @ 0..=MAX_REPLY_LEN => {
+                let mut items = Vec::with_capacity((len as usize).min(1024));
+                let mut failed = None;
+                for _ in 0..len {
+                    match Box::pin(read_reply(stream)).await? {
+                        Ok(item) => items.push(item),
+                        Err(e) => failed = failed.or(Some(e)),
+                    }
+                }
+                if let Some(e) = failed {
+                    return Ok(Err(e));
+                }
+                Reply::Array(items)
+            }
+            len => return Err(invalid(format!("array length {len}"))),
+        },
+        _ => return Err(invalid(format!("unexpected reply {line:?}"))),
+    }))
+}
+
+/// Reads a `\r\n`-terminated line, without the terminator.
+async fn read_line(stream: &mut BufStream<TcpStream>) -> std::io::Result<String> {
+    let mut line = String::new();
+    if stream.read_line(&mut line).await? == 0 {
+        return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+    line.truncate(line.trim_end_matches("\r\n").len());
+    Ok(line)
+}
+
+fn invalid(message: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// Serves one connection, answering each command with the next canned reply and
+    /// returning the commands it saw.
+    async fn fake_server(
+        replies: &'static [&'static str],
+    ) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let task = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut stream = BufStream::new(socket);
+            let mut seen = Vec::new();
+            for reply in replies {
+                let header = read_line(&mut stream).await.unwrap();
+                let argc: usize = header[1..].parse().unwrap();
+                let mut args = Vec::new();
+                for _ in 0..argc {
+                    read_line(&mut stream).await.unwrap();
+                    args.push(read_line(&mut stream).await.unwrap());
+                }
+                seen.push(args.join(" "));
+                stream.write_all(reply.as_bytes()).await.unwrap();
+                stream.flush().await.unwrap();
+            }
+            seen
+        });
+        (addr, task)
+    }
+
+    #[test]
+    fn parses_urls() {
+        let client = RedisClient::from_url("redis://:pw@cache.internal:6380/2").unwrap();
+        assert_eq!(client.addr(), "cache.internal:6380");
+        assert_eq!(client.target.password.as_deref(), Some("pw"));
+        assert_eq!(
+            (client.target.username.as_deref(), client.target.db),
+            (None, 2)
+        );
+        assert!(!format!("{client:?}").contains("pw"));
+
+        assert_eq!(
+            RedisClient::from_url("redis://localhost").unwrap().addr(),
+            "localhost:6379"
+        );
+        assert!(RedisClient::from_url("rediss://localhost").is_err());
+        assert!(RedisClient::from_url("redis://localhost/x").is_err());
+    }
+
+    #[tokio::test]
+    async fn sends_commands_and_decodes_replies() {
+        let (addr, server) = fake_server(&[
+            "+OK\r\n",
+            ":3\r\n",
+            "*3\r\n$5\r\nhello\r\n$-1\r\n:-2\r\n",
+            "-ERR wrong type\r\n",
+            "$0\r\n\r\n",
+        ])
+        .await;
+        let client = RedisClient::from_url(&format!("redis://{addr}/5")).unwrap();
+
+        assert_eq!(client.query(&[b"INCR", b"k"]).await.unwrap(), Reply::Int(3));
+        let replies = client
+            .pipeline(&[&[b"MGET", b"a", b"b"], &[b"HGET", b"h", b"f"]])
+            .await;
+        assert_eq!(replies.unwrap_err().code(), "REDIS_ERROR");
+        assert_eq!(
+            client.query(&[b"GET", b"e"]).await.unwrap().into_data(),
+            Some(Vec::new())
+        );
+        let seen = server.await.unwrap();
+        assert_eq!(
+            seen,
+            vec!["SELECT 5", "INCR k", "MGET a b", "HGET h f", "GET e"]
+        );
+    }
+
+    #[tokio::test]
+    async fn unreachable_server_is_an_error() {
+        let client = RedisClient::from_url("redis://127.0.0.1:1").unwrap();
+        let err = client.query(&[b"PING"]).await.unwrap_err();
+        assert_eq!(err.code(), "REDIS_ERROR");
+    }
+}
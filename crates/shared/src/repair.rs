@@ -0,0 +1,98 @@
+//! Repairs stored block timestamps against a reference source.
+//!
+//! Blocks are keyed by `(chain_id, timestamp, number)`, so a wrong timestamp can't be
+//! overwritten in place: the block has to be written under its correct key and the old
+//! key deleted, or lookups keep finding it at the wrong time. [`plan`] compares stored
+//! blocks with reference headers (re-fetched from SQD, or read from the chain's RPC)
+//! and [`Storage::retime_blocks`](crate::storage::Storage::retime_blocks) moves the
+//! affected keys. Used by `kizami repair-timestamps`.
+
+use std::collections::HashMap;
+
+use crate::sqd::BlockHeader;
+
+/// A stored block whose timestamp disagrees with the reference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimestampFix {
+    pub number: i64,
+    /// Timestamp the block is stored under.
+    pub stored: i64,
+    /// Timestamp the reference reports.
+    pub reference: i64,
+}
+
+/// Fixes for every stored `(number, timestamp)` entry the reference disagrees with.
+///
+/// A block stored under several timestamps gets a fix for each wrong one. Blocks the
+/// reference doesn't cover are left alone.
+pub fn plan(stored: &[(i64, i64)], reference: &[BlockHeader]) -> Vec<TimestampFix> {
+    let reference: HashMap<i64, i64> = reference.iter().map(|h| (h.number, h.timestamp)).collect();
+    stored
+        .iter()
+        .filter_map(|&(number, stored)| {
+            let reference = *reference.get(&number)?;
+            (stored != reference).then_some(TimestampFix {
+                number,
+                stored,
+                reference,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::Bound;
+    use crate::storage::Storage;
+
+    use super::*;
+
+    fn header(number: i64, timestamp: i64) -> BlockHeader {
+        BlockHeader { number, timestamp }
+    }
+
+    #[test]
+    fn moves_misdated_blocks_to_their_reference_timestamp() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        // block 2 was written at 0 and later again at 20; block 3 at a far-future time
+        storage
+            .insert_blocks(1, &[1, 2, 2, 3, 4], &[10, 0, 20, 9_999, 40])
+            .unwrap();
+        let reference = [header(1, 10), header(2, 20), header(3, 30)];
+
+        let stored = storage.blocks_by_number(1, 1, 4).unwrap();
+        assert_eq!(stored, vec![(1, 10), (2, 0), (2, 20), (3, 9_999), (4, 40)]);
+        let fixes = plan(&stored, &reference);
+        assert_eq!(
+            fixes,
+            vec![
+                TimestampFix {
+                    number: 2,
+                    stored: 0,
+                    reference: 20
+                },
+                TimestampFix {
+                    number: 3,
+                    stored: 9_999,
+                    reference: 30
+                },
+            ]
+        );
+
+        assert_eq!(storage.retime_blocks(1, &fixes).unwrap(), 2);
+        assert_eq!(
+            storage.blocks_by_number(1, 1, 4).unwrap(),
+            vec![(1, 10), (2, 20), (3, 30), (4, 40)]
+        );
+        assert_eq!(
+            storage.find_block(1, 5, Bound::AtOrAfter).unwrap(),
+            Some((1, 10))
+        );
+        assert_eq!(
+            storage.find_block(1, 35, Bound::AtOrBefore).unwrap(),
+            Some((3, 30))
+        );
+        assert!(plan(&storage.blocks_by_number(1, 1, 4).unwrap(), &reference).is_empty());
+    }
+}
@@ -0,0 +1,198 @@
+//! Optional JSON-RPC endpoints for reading a chain's own finalized head.
+//!
+//! Comparing it with the SQD dataset head shows whether SQD itself is behind the
+//! chain. Configured with `RPC_URLS` as `chain_id=url` pairs, e.g.
+//! `1=https://eth.example,8453=https://base.example`. Chains without an entry are
+//! simply not compared. The same endpoints serve as the reference for
+//! `kizami repair-timestamps --source rpc`.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::error::AppError;
+use crate::sqd::BlockHeader;
+
+/// Blocks requested per JSON-RPC batch by [`RpcEndpoints::block_headers`].
+const RPC_BATCH: i64 = 100;
+
+#[derive(Debug, Deserialize)]
+struct RpcResponse {
+    result: Option<RpcBlock>,
+    error: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcBlock {
+    number: String,
+    #[serde(default)]
+    timestamp: String,
+}
+
+/// RPC URLs per chain id.
+#[derive(Clone, Default)]
+pub struct RpcEndpoints {
+    client: Client,
+    urls: HashMap<i32, String>,
+}
+
+// URLs often embed provider keys, so only chain ids are printed
+impl std::fmt::Debug for RpcEndpoints {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RpcEndpoints")
+            .field("chain_ids", &self.chain_ids())
+            .finish()
+    }
+}
+
+impl RpcEndpoints {
+    pub fn new(urls: HashMap<i32, String>) -> Self {
+        Self {
+            client: Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .expect("failed to build reqwest client"),
+            urls,
+        }
+    }
+
+    /// Reads `RPC_URLS`. Empty when unset.
+    pub fn from_env() -> Self {
+        Self::new(parse_rpc_urls(
+            &std::env::var("RPC_URLS").unwrap_or_default(),
+        ))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.urls.is_empty()
+    }
+
+    /// Chain ids with an RPC URL, sorted.
+    pub fn chain_ids(&self) -> Vec<i32> {
+        let mut ids: Vec<_> = self.urls.keys().copied().collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// The chain's finalized block number, or `None` without an RPC URL for it.
+    pub async fn finalized_head(&self, chain_id: i32) -> Result<Option<i64>, AppError> {
+        let Some(url) = self.urls.get(&chain_id) else {
+            return Ok(None);
+        };
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_getBlockByNumber",
+            "params": ["finalized", false],
+        });
+        let resp: RpcResponse = self
+            .client
+            .post(url)
+            .json(&body)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| AppError::Rpc(format!("chain {chain_id}: {e}")))?
+            .json()
+            .await
+            .map_err(|e| AppError::Rpc(format!("chain {chain_id}: {e}")))?;
+        if let Some(error) = resp.error {
+            return Err(AppError::Rpc(format!("chain {chain_id}: {error}")));
+        }
+        let number = resp
+            .result
+            .ok_or_else(|| AppError::Rpc(format!("chain {chain_id}: no block")))?
+            .number;
+        parse_quantity(chain_id, &number).map(Some)
+    }
+
+    /// Headers of blocks `from_block..=to_block` as the chain's RPC reports them, or
+    /// `None` without an RPC URL for it. Sent as JSON-RPC batches of 100 blocks; a block
+    /// the node doesn't have fails the call.
+    pub async fn block_headers(
+        &self,
+        chain_id: i32,
+        from_block: i64,
+        to_block: i64,
+    ) -> Result<Option<Vec<BlockHeader>>, AppError> {
+        let Some(url) = self.urls.get(&chain_id) else {
+            return Ok(None);
+        };
+        let err = |e: &dyn std::fmt::Display| AppError::Rpc(format!("chain {chain_id}: {e}"));
+        let mut headers = Vec::new();
+        let mut start = from_block;
+        while start <= to_block {
+            let end = to_block.min(start + RPC_BATCH - 1);
+            let body: Vec<_> = (start..=end)
+                .map(|number| {
+                    json!({
+                        "jsonrpc": "2.0",
+                        "id": number,
+                        "method": "eth_getBlockByNumber",
+                        "params": [format!("{number:#x}"), false],
+                    })
+                })
+                .collect();
+            let resps: Vec<RpcResponse> = self
+                .client
+                .post(url)
+                .json(&body)
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+                .map_err(|e| err(&e))?
+                .json()
+                .await
+                .map_err(|e| err(&e))?;
+            for resp in resps {
+                if let Some(error) = resp.error {
+                    return Err(err(&error));
+                }
+                let block = resp.result.ok_or_else(|| err(&"missing block"))?;
+                headers.push(BlockHeader {
+                    number: parse_quantity(chain_id, &block.number)?,
+                    timestamp: parse_quantity(chain_id, &block.timestamp)?,
+                });
+            }
+            start = end + 1;
+        }
+        // batch responses may come back in any order
+        headers.sort_unstable_by_key(|h| h.number);
+        Ok(Some(headers))
+    }
+}
+
+/// Parses a hex `QUANTITY` such as `0x1b4`.
+fn parse_quantity(chain_id: i32, value: &str) -> Result<i64, AppError> {
+    i64::from_str_radix(value.trim_start_matches("0x"), 16)
+        .map_err(|e| AppError::Rpc(format!("chain {chain_id}: {e}")))
+}
+
+/// Parses `chain_id=url` pairs separated by commas, skipping malformed entries.
+pub fn parse_rpc_urls(spec: &str) -> HashMap<i32, String> {
+    spec.split(',')
+        .filter_map(|pair| {
+            let (id, url) = pair.trim().split_once('=')?;
+            let url = url.trim();
+            (!url.is_empty()).then_some((id.trim().parse().ok()?, url.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_rpc_urls_skips_malformed_pairs() {
+        let urls = parse_rpc_urls(
+            "1=https://eth.example/v1?key=abc, 8453 = https://base.example ,x=https://a,10=,junk",
+        );
+        assert_eq!(urls.len(), 2);
+        assert_eq!(urls[&1], "https://eth.example/v1?key=abc");
+        assert_eq!(urls[&8453], "https://base.example");
+    }
+}
@@ -0,0 +1,237 @@
+//! Embedded scheduler for background jobs.
+//!
+//! Periodic work (lag sampling, cursor history, index snapshots, lookup summaries) is
+//! registered here instead of each module spawning its own ticker loop. A job runs
+//! once at startup and then every interval plus a random delay of up to its jitter,
+//! so jobs sharing an interval don't all hit storage at the same instant. Runs of one
+//! job never overlap: the next delay starts when the previous run finishes.
+//!
+//! The ingestion loop drives its own cycles on a separate runtime; it reports them
+//! through a [`JobHandle`] from [`Scheduler::register`], so every job shows up in
+//! [`Scheduler::status`] (served by `/v1/admin/jobs`).
+
+use std::collections::hash_map::RandomState;
+use std::fmt::Display;
+use std::future::Future;
+use std::hash::BuildHasher;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+
+use crate::clock;
+use crate::models::JobStatusResponse;
+
+/// What a job has done so far.
+#[derive(Debug, Default)]
+struct JobState {
+    runs: u64,
+    failures: u64,
+    running_since: Option<Instant>,
+    last_started_at: Option<DateTime<Utc>>,
+    last_duration: Option<Duration>,
+    last_error: Option<String>,
+    next_run_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug)]
+struct Job {
+    name: &'static str,
+    interval: Duration,
+    jitter: Duration,
+    state: Mutex<JobState>,
+}
+
+/// Reports the runs of one job. Cheap to clone.
+#[derive(Debug, Clone)]
+pub struct JobHandle(Arc<Job>);
+
+impl JobHandle {
+    /// A handle not attached to any scheduler, for callers that run without one
+    /// (tests, soak runs).
+    pub fn detached(name: &'static str, interval: Duration) -> Self {
+        Self(Arc::new(Job {
+            name,
+            interval,
+            jitter: Duration::ZERO,
+            state: Mutex::default(),
+        }))
+    }
+
+    /// Marks the start of a run.
+    pub fn started(&self) {
+        let mut state = self.0.state.lock().unwrap();
+        state.running_since = Some(Instant::now());
+        state.last_started_at = Some(clock::now());
+        state.next_run_at = None;
+    }
+
+    /// Marks the end of the run begun by [`JobHandle::started`]. Failures are logged
+    /// with the job name. `next_run_at` is when the job expects to run again, if it
+    /// does.
+    pub fn finished<E: Display>(&self, result: Result<(), E>, next_run_at: Option<DateTime<Utc>>) {
+        let mut state = self.0.state.lock().unwrap();
+        state.runs += 1;
+        state.last_duration = state.running_since.take().map(|since| since.elapsed());
+        state.next_run_at = next_run_at;
+        match result {
+            Ok(()) => state.last_error = None,
+            Err(e) => {
+                tracing::error!(job = self.0.name, error = %e, "background job failed");
+                state.failures += 1;
+                state.last_error = Some(e.to_string());
+            }
+        }
+    }
+
+    fn status(&self) -> JobStatusResponse {
+        let job = &self.0;
+        let state = job.state.lock().unwrap();
+        JobStatusResponse {
+            name: job.name,
+            interval_secs: job.interval.as_secs(),
+            jitter_secs: job.jitter.as_secs(),
+            running: state.running_since.is_some(),
+            runs: state.runs,
+            failures: state.failures,
+            last_started_at: state.last_started_at,
+            last_duration_ms: state.last_duration.map(|d| d.as_millis() as u64),
+            last_error: state.last_error.clone(),
+            next_run_at: state.next_run_at,
+        }
+    }
+}
+
+/// Registry of background jobs. Cheap to clone; clones share the registry.
+#[derive(Debug, Clone, Default)]
+pub struct Scheduler {
+    jobs: Arc<Mutex<Vec<JobHandle>>>,
+}
+
+impl Scheduler {
+    /// Registers a job whose runs are driven by the caller.
+    pub fn register(&self, name: &'static str, interval: Duration) -> JobHandle {
+        self.add(name, interval, Duration::ZERO)
+    }
+
+    fn add(&self, name: &'static str, interval: Duration, jitter: Duration) -> JobHandle {
+        let handle = JobHandle(Arc::new(Job {
+            name,
+            interval,
+            jitter,
+            state: Mutex::default(),
+        }));
+        self.jobs.lock().unwrap().push(handle.clone());
+        handle
+    }
+
+    /// Runs `job` now and then every `interval` plus up to `jitter`, forever, on the
+    /// current tokio runtime.
+    pub fn spawn<F, Fut, E>(
+        &self,
+        name: &'static str,
+        interval: Duration,
+        jitter: Duration,
+        mut job: F,
+    ) -> tokio::task::JoinHandle<()>
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), E>> + Send,
+        E: Display,
+    {
+        let handle = self.add(name, interval, jitter);
+        let hasher = RandomState::new();
+        tokio::spawn(async move {
+            for run in 0u64.. {
+                handle.started();
+                let result = job().await;
+                let delay = interval + jitter_delay(jitter, hasher.hash_one(run));
+                let next = chrono::Duration::from_std(delay)
+                    .ok()
+                    .map(|d| clock::now() + d);
+                handle.finished(result, next);
+                tokio::time::sleep(delay).await;
+            }
+        })
+    }
+
+    /// Status of every registered job, by name.
+    pub fn status(&self) -> Vec<JobStatusResponse> {
+        let mut status: Vec<_> = self
+            .jobs
+            .lock()
+            .unwrap()
+            .iter()
+            .map(JobHandle::status)
+            .collect();
+        status.sort_by_key(|s| s.name);
+        status
+    }
+}
+
+/// A delay in `[0, jitter]` picked by `random`.
+fn jitter_delay(jitter: Duration, random: u64) -> Duration {
+    let millis = jitter.as_millis() as u64;
+    if millis == 0 {
+        return Duration::ZERO;
+    }
+    Duration::from_millis(random % (millis + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        assert_eq!(jitter_delay(Duration::ZERO, 12345), Duration::ZERO);
+        for random in [0, 1, 999, 1000, 1001, u64::MAX] {
+            assert!(jitter_delay(Duration::from_secs(1), random) <= Duration::from_secs(1));
+        }
+        assert_eq!(
+            jitter_delay(Duration::from_secs(1), 1000),
+            Duration::from_secs(1)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn spawned_jobs_report_runs_and_failures() {
+        let scheduler = Scheduler::default();
+        let mut calls = 0;
+        scheduler.spawn(
+            "flaky",
+            Duration::from_secs(60),
+            Duration::ZERO,
+            move || {
+                calls += 1;
+                let fail = calls % 2 == 0;
+                async move {
+                    if fail {
+                        Err("storage unavailable")
+                    } else {
+                        Ok(())
+                    }
+                }
+            },
+        );
+        let ingest = scheduler.register("ingest", Duration::from_secs(12));
+        ingest.started();
+
+        tokio::time::sleep(Duration::from_secs(61)).await;
+        let status = scheduler.status();
+        assert_eq!(status.len(), 2);
+        let (flaky, ingesting) = (&status[0], &status[1]);
+        assert_eq!(flaky.name, "flaky");
+        assert_eq!((flaky.runs, flaky.failures), (2, 1));
+        assert_eq!(flaky.last_error.as_deref(), Some("storage unavailable"));
+        assert!(!flaky.running && flaky.next_run_at.is_some());
+        assert!(ingesting.running && ingesting.runs == 0);
+
+        ingest.finished(Ok::<_, String>(()), None);
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        let status = scheduler.status();
+        assert_eq!((status[0].runs, status[0].failures), (3, 1));
+        assert_eq!(status[0].last_error, None);
+        assert_eq!((status[1].running, status[1].runs), (false, 1));
+    }
+}
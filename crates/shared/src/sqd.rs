@@ -2,21 +2,34 @@
 //!
 //! The client uses a tokio semaphore (20 permits) to respect the public portal rate limit
 //! of 20 requests per 10 seconds. A single `reqwest::Client` is reused for connection pooling.
+//! A [`RequestBudget`] can additionally cap the number of requests per ingestion cycle.
+//! Every request outcome is recorded per dataset in [`SqdHealth`], so status can tell
+//! an SQD outage apart from kizami falling behind. So are the response bytes
+//! downloaded, per UTC day, against an optional daily egress budget.
 //!
 //! See: <https://beta.docs.sqd.dev/api/evm/finalized-stream>
 //! See: <https://docs.sqd.dev/portal-closed-beta-information>
 
-use std::sync::Arc;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
+use chrono::{DateTime, NaiveDate, Utc};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use tokio::sync::Semaphore;
 
+#[cfg(feature = "chaos")]
+use crate::chaos::{Fault, Faults};
+use crate::clock;
 use crate::error::AppError;
 
 const SQD_PORTAL_BASE: &str = "https://portal.sqd.dev/datasets";
 
+/// Request outcomes kept per dataset for the error rate.
+const HEALTH_WINDOW: usize = 50;
+
 /// The latest finalized block as reported by SQD Portal.
 #[derive(Debug, Deserialize)]
 pub struct FinalizedHead {
@@ -24,6 +37,14 @@ pub struct FinalizedHead {
     pub hash: String,
 }
 
+/// Dataset metadata as reported by SQD Portal.
+#[derive(Debug, Deserialize)]
+pub struct DatasetMetadata {
+    pub dataset: String,
+    /// First block available in the dataset. Not every dataset starts at genesis.
+    pub start_block: i64,
+}
+
 /// A single block in the NDJSON stream response.
 #[derive(Debug, Deserialize)]
 struct NdjsonBlock {
@@ -59,13 +80,198 @@ struct BlockFields {
     timestamp: bool,
 }
 
+/// A number of SQD requests shared by every chain in one ingestion cycle.
+///
+/// The semaphore only bounds concurrency; the budget bounds volume, so a cycle over
+/// many backfilling chains stays within the portal quota. Refilled at the start of
+/// each cycle.
+#[derive(Debug)]
+pub struct RequestBudget {
+    per_cycle: u64,
+    remaining: AtomicU64,
+}
+
+impl RequestBudget {
+    pub fn new(per_cycle: u64) -> Self {
+        Self {
+            per_cycle,
+            remaining: AtomicU64::new(per_cycle),
+        }
+    }
+
+    /// Restores the full per-cycle allowance.
+    pub fn refill(&self) {
+        self.remaining.store(self.per_cycle, Ordering::Relaxed);
+    }
+
+    /// Takes one request from the budget. Returns `false` once it is spent.
+    pub fn try_take(&self) -> bool {
+        self.remaining
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+            .is_ok()
+    }
+
+    pub fn remaining(&self) -> u64 {
+        self.remaining.load(Ordering::Relaxed)
+    }
+
+    pub fn per_cycle(&self) -> u64 {
+        self.per_cycle
+    }
+}
+
+/// Availability of one SQD dataset as seen by this process.
+#[derive(Debug, Clone, Default)]
+pub struct DatasetHealth {
+    /// Outcomes of the last [`HEALTH_WINDOW`] requests, oldest first (`true` = ok).
+    recent: VecDeque<bool>,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub last_error_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    /// Finalized head reported by the chain's own RPC, when one is configured.
+    pub chain_head: Option<i64>,
+    /// Response bytes downloaded since startup.
+    pub egress_bytes: u64,
+    /// UTC day `egress_bytes_today` counts.
+    egress_day: Option<NaiveDate>,
+    egress_bytes_today: u64,
+}
+
+impl DatasetHealth {
+    /// Requests in the window.
+    pub fn recent_requests(&self) -> usize {
+        self.recent.len()
+    }
+
+    /// Share of failed requests in the window, `None` before the first request.
+    pub fn error_rate(&self) -> Option<f64> {
+        let failed = self.recent.iter().filter(|ok| !**ok).count();
+        (!self.recent.is_empty()).then(|| failed as f64 / self.recent.len() as f64)
+    }
+
+    /// Bytes downloaded on `day` (UTC), including any restored from before a restart.
+    pub fn egress_bytes_on(&self, day: NaiveDate) -> u64 {
+        if self.egress_day == Some(day) {
+            self.egress_bytes_today
+        } else {
+            0
+        }
+    }
+
+    /// Adds `bytes` to `day`'s count, starting over when the day has changed.
+    fn add_egress(&mut self, day: NaiveDate, bytes: u64) {
+        if self.egress_day != Some(day) {
+            self.egress_day = Some(day);
+            self.egress_bytes_today = 0;
+        }
+        self.egress_bytes_today += bytes;
+    }
+
+    fn record(&mut self, ok: bool) {
+        if self.recent.len() == HEALTH_WINDOW {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(ok);
+    }
+}
+
+/// Per-dataset request outcomes, keyed by SQD slug. Shared by every clone of a client.
+#[derive(Debug, Default)]
+pub struct SqdHealth {
+    datasets: Mutex<HashMap<String, DatasetHealth>>,
+    /// Bytes all datasets together may download per UTC day, 0 for no limit.
+    egress_budget: AtomicU64,
+}
+
+impl SqdHealth {
+    pub fn record_success(&self, sqd_slug: &str) {
+        let mut datasets = self.datasets.lock().unwrap();
+        let health = datasets.entry(sqd_slug.to_string()).or_default();
+        health.record(true);
+        health.last_success_at = Some(clock::now());
+    }
+
+    pub fn record_error(&self, sqd_slug: &str, error: &AppError) {
+        let mut datasets = self.datasets.lock().unwrap();
+        let health = datasets.entry(sqd_slug.to_string()).or_default();
+        health.record(false);
+        health.last_error_at = Some(clock::now());
+        health.last_error = Some(error.to_string());
+    }
+
+    /// Records the chain's finalized head from RPC, to compare with the dataset head.
+    pub fn record_chain_head(&self, sqd_slug: &str, head: i64) {
+        let mut datasets = self.datasets.lock().unwrap();
+        datasets.entry(sqd_slug.to_string()).or_default().chain_head = Some(head);
+    }
+
+    pub fn get(&self, sqd_slug: &str) -> Option<DatasetHealth> {
+        self.datasets.lock().unwrap().get(sqd_slug).cloned()
+    }
+
+    /// Counts `bytes` of response body downloaded for the dataset.
+    pub fn record_egress(&self, sqd_slug: &str, bytes: u64) {
+        let today = clock::now().date_naive();
+        let mut datasets = self.datasets.lock().unwrap();
+        let health = datasets.entry(sqd_slug.to_string()).or_default();
+        health.egress_bytes += bytes;
+        health.add_egress(today, bytes);
+    }
+
+    /// Sets the dataset's count for `day` to `bytes` as persisted before a restart,
+    /// keeping whatever this process has downloaded on top. Other days are ignored.
+    pub fn restore_egress(&self, sqd_slug: &str, day: NaiveDate, bytes: u64) {
+        if day != clock::now().date_naive() {
+            return;
+        }
+        let mut datasets = self.datasets.lock().unwrap();
+        datasets
+            .entry(sqd_slug.to_string())
+            .or_default()
+            .add_egress(day, bytes);
+    }
+
+    /// Bytes downloaded today (UTC) across every dataset.
+    pub fn egress_today(&self) -> u64 {
+        let today = clock::now().date_naive();
+        let datasets = self.datasets.lock().unwrap();
+        datasets.values().map(|h| h.egress_bytes_on(today)).sum()
+    }
+
+    /// Limits downloads to `bytes` per UTC day across every dataset; `None` lifts it.
+    pub fn set_egress_budget(&self, bytes: Option<u64>) {
+        self.egress_budget
+            .store(bytes.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    pub fn egress_budget(&self) -> Option<u64> {
+        Some(self.egress_budget.load(Ordering::Relaxed)).filter(|&b| b > 0)
+    }
+
+    /// Whether today's downloads have reached the daily budget. Nothing stops requests
+    /// by itself; ingestion checks this before starting deep backfill.
+    pub fn egress_budget_spent(&self) -> bool {
+        self.egress_budget()
+            .is_some_and(|budget| self.egress_today() >= budget)
+    }
+}
+
 /// HTTP client for the SQD Portal API with built-in rate limiting.
 ///
 /// The semaphore limits concurrent requests to 20 to stay within SQD's public rate limit.
 /// The reqwest client is configured with a 120s timeout for large block range fetches.
+/// Clones share the connection pool and the semaphore, but not a budget added after
+/// cloning.
+#[derive(Clone)]
 pub struct SqdClient {
     client: Client,
     semaphore: Arc<Semaphore>,
+    /// Datasets root, `https://portal.sqd.dev/datasets` unless overridden.
+    base_url: String,
+    budget: Option<Arc<RequestBudget>>,
+    health: Arc<SqdHealth>,
+    #[cfg(feature = "chaos")]
+    faults: Option<Arc<Faults>>,
 }
 
 impl Default for SqdClient {
@@ -76,38 +282,162 @@ impl Default for SqdClient {
 
 impl SqdClient {
     pub fn new() -> Self {
+        Self::with_base_url(SQD_PORTAL_BASE)
+    }
+
+    /// A client for a portal-compatible server at `base_url` (the `/datasets` root),
+    /// e.g. a self-hosted portal or a mock in tests.
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
         Self {
             client: Client::builder()
                 .timeout(Duration::from_secs(120))
                 .build()
                 .expect("failed to build reqwest client"),
             semaphore: Arc::new(Semaphore::new(20)),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            budget: None,
+            health: Default::default(),
+            #[cfg(feature = "chaos")]
+            faults: None,
+        }
+    }
+
+    /// Request outcomes per dataset, shared with every clone of this client.
+    pub fn health(&self) -> Arc<SqdHealth> {
+        self.health.clone()
+    }
+
+    /// Records a request outcome in [`SqdHealth`] and passes it through.
+    fn track<T>(&self, sqd_slug: &str, result: Result<T, AppError>) -> Result<T, AppError> {
+        match &result {
+            Ok(_) => self.health.record_success(sqd_slug),
+            Err(e) => self.health.record_error(sqd_slug, e),
         }
+        result
+    }
+
+    /// Charges every request against `budget`. Once it is spent, head and metadata
+    /// requests fail and block streams stop early with the blocks received so far.
+    pub fn with_budget(mut self, budget: Arc<RequestBudget>) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Takes one request from the budget, if any. Always succeeds without a budget.
+    fn take_budget(&self) -> bool {
+        self.budget.as_ref().is_none_or(|b| b.try_take())
+    }
+
+    /// Injects faults into every request (see [`crate::chaos`]).
+    #[cfg(feature = "chaos")]
+    pub fn with_faults(mut self, faults: Arc<Faults>) -> Self {
+        self.faults = Some(faults);
+        self
+    }
+
+    /// Applies an injected fault before a request: fails it, delays it, or returns the
+    /// share of the response body to keep.
+    #[cfg(feature = "chaos")]
+    async fn inject(&self, endpoint: &str) -> Result<Option<f64>, AppError> {
+        match self.faults.as_ref().and_then(|f| f.next()) {
+            Some(Fault::Error) => Err(AppError::SqdApi(format!("injected {endpoint} failure"))),
+            Some(Fault::Latency(delay)) => {
+                tokio::time::sleep(delay).await;
+                Ok(None)
+            }
+            Some(Fault::Truncate(keep)) => Ok(Some(keep)),
+            None => Ok(None),
+        }
+    }
+
+    #[cfg(not(feature = "chaos"))]
+    async fn inject(&self, _endpoint: &str) -> Result<Option<f64>, AppError> {
+        Ok(None)
     }
 
     /// Returns the latest finalized block number and hash for a chain.
     ///
     /// See: <https://beta.docs.sqd.dev/api/evm/finalized-head>
     pub async fn fetch_finalized_head(&self, sqd_slug: &str) -> Result<FinalizedHead, AppError> {
+        if !self.take_budget() {
+            return Err(AppError::SqdApi("SQD request budget exhausted".into()));
+        }
         let _permit = self.semaphore.acquire().await.expect("semaphore closed");
-        let url = format!("{SQD_PORTAL_BASE}/{sqd_slug}/finalized-head");
-        let resp = self
-            .client
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| AppError::SqdApi(e.to_string()))?;
-
-        if !resp.status().is_success() {
-            return Err(AppError::SqdApi(format!(
-                "finalized-head for {sqd_slug} returned {}",
-                resp.status()
-            )));
+        let url = format!("{}/{sqd_slug}/finalized-head", self.base_url);
+        let result = async {
+            // a cut-short JSON body fails to parse, same as an error
+            if self.inject("finalized-head").await?.is_some() {
+                return Err(AppError::SqdApi("truncated finalized-head response".into()));
+            }
+            let resp = self
+                .client
+                .get(&url)
+                .send()
+                .await
+                .map_err(|e| AppError::SqdApi(e.to_string()))?;
+            // head polls are frequent and small, a good sample of the host's clock skew
+            if let Some(date) = resp.headers().get(reqwest::header::DATE) {
+                if let Ok(date) = date.to_str() {
+                    clock::observe_http_date(date, Utc::now());
+                }
+            }
+
+            if !resp.status().is_success() {
+                return Err(AppError::SqdApi(format!(
+                    "finalized-head for {sqd_slug} returned {}",
+                    resp.status()
+                )));
+            }
+
+            let body = resp
+                .bytes()
+                .await
+                .map_err(|e| AppError::SqdApi(e.to_string()))?;
+            self.health.record_egress(sqd_slug, body.len() as u64);
+            serde_json::from_slice::<FinalizedHead>(&body)
+                .map_err(|e| AppError::SqdApi(e.to_string()))
         }
+        .await;
+        self.track(sqd_slug, result)
+    }
+
+    /// Returns dataset metadata for a chain, including the first available block.
+    ///
+    /// See: <https://beta.docs.sqd.dev/api/evm/metadata>
+    pub async fn fetch_metadata(&self, sqd_slug: &str) -> Result<DatasetMetadata, AppError> {
+        if !self.take_budget() {
+            return Err(AppError::SqdApi("SQD request budget exhausted".into()));
+        }
+        let _permit = self.semaphore.acquire().await.expect("semaphore closed");
+        let url = format!("{}/{sqd_slug}/metadata", self.base_url);
+        let result = async {
+            if self.inject("metadata").await?.is_some() {
+                return Err(AppError::SqdApi("truncated metadata response".into()));
+            }
+            let resp = self
+                .client
+                .get(&url)
+                .send()
+                .await
+                .map_err(|e| AppError::SqdApi(e.to_string()))?;
+
+            if !resp.status().is_success() {
+                return Err(AppError::SqdApi(format!(
+                    "metadata for {sqd_slug} returned {}",
+                    resp.status()
+                )));
+            }
 
-        resp.json::<FinalizedHead>()
-            .await
-            .map_err(|e| AppError::SqdApi(e.to_string()))
+            let body = resp
+                .bytes()
+                .await
+                .map_err(|e| AppError::SqdApi(e.to_string()))?;
+            self.health.record_egress(sqd_slug, body.len() as u64);
+            serde_json::from_slice::<DatasetMetadata>(&body)
+                .map_err(|e| AppError::SqdApi(e.to_string()))
+        }
+        .await;
+        self.track(sqd_slug, result)
     }
 
     /// Fetches all finalized blocks in `[from_block, to_block]`, handling partial responses.
@@ -120,6 +450,7 @@ impl SqdClient {
     /// those with matching logs/transactions.
     ///
     /// 204 = requested range is beyond available dataset blocks (nothing left to fetch).
+    /// The loop also ends once the [`RequestBudget`] is spent, returning a partial range.
     ///
     /// See: <https://beta.docs.sqd.dev/api/evm/finalized-stream>
     pub async fn fetch_blocks(
@@ -131,45 +462,59 @@ impl SqdClient {
         let mut blocks = Vec::new();
         let mut cursor = from_block;
 
-        while cursor <= to_block {
+        while cursor <= to_block && self.take_budget() {
             let _permit = self.semaphore.acquire().await.expect("semaphore closed");
-            let url = format!("{SQD_PORTAL_BASE}/{sqd_slug}/finalized-stream");
-            let body = StreamRequest {
-                r#type: "evm",
-                from_block: cursor,
-                to_block,
-                include_all_blocks: true,
-                fields: StreamFields {
-                    block: BlockFields {
-                        number: true,
-                        timestamp: true,
+            let url = format!("{}/{sqd_slug}/finalized-stream", self.base_url);
+            let page = async {
+                let keep = self.inject("finalized-stream").await?;
+                let body = StreamRequest {
+                    r#type: "evm",
+                    from_block: cursor,
+                    to_block,
+                    include_all_blocks: true,
+                    fields: StreamFields {
+                        block: BlockFields {
+                            number: true,
+                            timestamp: true,
+                        },
                     },
-                },
-            };
-
-            let resp = self
-                .client
-                .post(&url)
-                .json(&body)
-                .send()
-                .await
-                .map_err(|e| AppError::SqdApi(e.to_string()))?;
-
-            if resp.status().as_u16() == 204 {
-                break;
-            }
-
-            if !resp.status().is_success() {
-                return Err(AppError::SqdApi(format!(
-                    "finalized-stream for {sqd_slug} returned {}",
-                    resp.status()
-                )));
+                };
+
+                let resp = self
+                    .client
+                    .post(&url)
+                    .json(&body)
+                    .send()
+                    .await
+                    .map_err(|e| AppError::SqdApi(e.to_string()))?;
+
+                if resp.status().as_u16() == 204 {
+                    return Ok(None);
+                }
+
+                if !resp.status().is_success() {
+                    return Err(AppError::SqdApi(format!(
+                        "finalized-stream for {sqd_slug} returned {}",
+                        resp.status()
+                    )));
+                }
+
+                let mut text = resp
+                    .text()
+                    .await
+                    .map_err(|e| AppError::SqdApi(e.to_string()))?;
+                self.health.record_egress(sqd_slug, text.len() as u64);
+                if let Some(keep) = keep {
+                    // a dropped connection: the body ends mid-line at a random point
+                    let cut = (text.len() as f64 * keep) as usize;
+                    text.truncate(text.floor_char_boundary(cut));
+                }
+                Ok(Some(text))
             }
-
-            let text = resp
-                .text()
-                .await
-                .map_err(|e| AppError::SqdApi(e.to_string()))?;
+            .await;
+            let Some(text) = self.track(sqd_slug, page)? else {
+                break;
+            };
 
             let batch = parse_ndjson::<NdjsonBlock>(&text);
             if batch.is_empty() {
@@ -200,6 +545,65 @@ fn parse_ndjson<T: serde::de::DeserializeOwned>(text: &str) -> Vec<T> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn request_budget_stops_at_zero_until_refilled() {
+        let budget = RequestBudget::new(2);
+        assert!(budget.try_take());
+        assert!(budget.try_take());
+        assert!(!budget.try_take());
+        assert_eq!(budget.remaining(), 0);
+        budget.refill();
+        assert_eq!(budget.remaining(), 2);
+    }
+
+    #[test]
+    fn health_error_rate_covers_recent_requests_only() {
+        let health = SqdHealth::default();
+        assert!(health.get("base-mainnet").is_none());
+
+        let error = AppError::SqdApi("503".into());
+        for _ in 0..10 {
+            health.record_error("base-mainnet", &error);
+        }
+        let h = health.get("base-mainnet").unwrap();
+        assert_eq!(h.error_rate(), Some(1.0));
+        assert!(h.last_success_at.is_none());
+
+        for _ in 0..HEALTH_WINDOW - 5 {
+            health.record_success("base-mainnet");
+        }
+        let h = health.get("base-mainnet").unwrap();
+        assert_eq!(h.recent_requests(), HEALTH_WINDOW);
+        assert_eq!(h.error_rate(), Some(5.0 / HEALTH_WINDOW as f64));
+        assert!(h.last_success_at.is_some());
+        assert_eq!(h.last_error.as_deref(), Some(error.to_string().as_str()));
+    }
+
+    #[test]
+    fn egress_counts_today_against_the_budget() {
+        let health = SqdHealth::default();
+        let today = clock::now().date_naive();
+        health.record_egress("base-mainnet", 400);
+        health.restore_egress("base-mainnet", today, 1_000);
+        // an earlier day's total doesn't count towards today's budget
+        health.restore_egress("ethereum-mainnet", today.pred_opt().unwrap(), 5_000);
+        health.record_egress("ethereum-mainnet", 100);
+
+        let base = health.get("base-mainnet").unwrap();
+        assert_eq!(base.egress_bytes, 400);
+        assert_eq!(base.egress_bytes_on(today), 1_400);
+        assert_eq!(health.egress_today(), 1_500);
+
+        assert!(!health.egress_budget_spent());
+        health.set_egress_budget(Some(2_000));
+        assert!(!health.egress_budget_spent());
+        health.record_egress("base-mainnet", 500);
+        assert!(health.egress_budget_spent());
+        health.set_egress_budget(None);
+        assert_eq!(health.egress_budget(), None);
+        assert!(!health.egress_budget_spent());
+    }
+
     #[test]
     fn parse_ndjson_basic() {
         let input = r#"{"header":{"number":1,"timestamp":1438269988}}
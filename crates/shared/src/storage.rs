@@ -1,12 +1,26 @@
 use std::collections::HashMap;
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use chrono::{DateTime, Utc};
-use fjall::{Database, Keyspace, KeyspaceCreateOptions, PersistMode};
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use fjall::{Database, Keyspace, KeyspaceCreateOptions, PersistMode, Readable};
 use tokio::sync::RwLock;
 
+use crate::chains;
+#[cfg(feature = "chaos")]
+use crate::chaos::{Fault, Faults};
+use crate::clock::{self, Clock};
 use crate::error::AppError;
+use crate::models::{BlockRef, Bound, Direction, Tie, WorkKind};
+use crate::repair::TimestampFix;
+use crate::sqd::BlockHeader;
+use crate::validation::{AnomalyKind, RejectReason, TimestampAnomaly};
+
+mod roles;
+
+pub use roles::{ReadStorage, RoleMetrics, RoleStats, StorageMetrics};
 
 /// Progress tracking for a single chain's ingestion state.
 #[derive(Debug, Clone)]
@@ -17,33 +31,299 @@ pub struct ChainProgress {
     pub head: Option<i64>,
     /// When the cursor was last updated.
     pub updated_at: Option<DateTime<Utc>>,
+    /// Set by the admin API to hold ingestion for this chain. Not persisted, so a
+    /// restart resumes every chain.
+    pub paused: bool,
+    /// When `head` last moved forward (not persisted; the first head fetched after a
+    /// restart counts as a move).
+    pub head_advanced_at: Option<DateTime<Utc>>,
 }
 
 /// Shared progress map: sqd_slug -> ChainProgress.
 pub type ProgressMap = Arc<RwLock<HashMap<String, ChainProgress>>>;
 
+/// L0 tables at which fjall starts stalling writers (its halt point is 30).
+const L0_STALL_TABLES: usize = 20;
+
+/// Queued memtable flushes at which fjall halts writers.
+const FLUSH_HALT_QUEUE: usize = 4;
+
+/// Compaction and flush debt in the `blocks` keyspace.
+///
+/// fjall slows writers down itself once L0 fills up or flushes queue, but it does so
+/// by spinning and sleeping the calling thread, which stalls whatever else shares it
+/// and starves lookups of compaction bandwidth. Writers can poll this and back off
+/// before fjall's own stall kicks in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WritePressure {
+    /// Tables in level 0 of the `blocks` tree, awaiting compaction.
+    pub l0_tables: usize,
+    /// Sealed memtables queued for flushing.
+    pub pending_flushes: usize,
+}
+
+impl WritePressure {
+    /// Pressure as a fraction of fjall's stall thresholds: 0.0 is idle, 1.0 is where
+    /// fjall starts stalling writers. Can exceed 1.0.
+    pub fn level(&self) -> f64 {
+        let l0 = self.l0_tables as f64 / L0_STALL_TABLES as f64;
+        let flushes = self.pending_flushes as f64 / FLUSH_HALT_QUEUE as f64;
+        l0.max(flushes)
+    }
+}
+
 /// Embedded storage backed by fjall (LSM-tree key-value store).
 ///
-/// Two keyspaces:
+/// Nine keyspaces:
 /// - `blocks`: key = `chain_id(4B) | timestamp(8B) | number(8B)`, value = empty
-/// - `cursors`: key = sqd_slug (UTF-8), value = `last_block(8B) | updated_at_secs(8B)`
+/// - `cursors`: key = `chain_id(4B)`, value = `last_block(8B) | updated_at_secs(8B)`
+/// - `rejected`: key = `chain_id(4B) | number(8B)`,
+///   value = `timestamp(8B) | rejected_at_secs(8B) | reason (UTF-8)`
+/// - `cursor_history`: key = `chain_id(4B) | recorded_at_secs(8B)`, value = `last_block(8B)`
+/// - `anomalies`: key = `chain_id(4B) | number(8B)`, value = `prev_number(8B) |
+///   prev_timestamp(8B) | timestamp(8B) | detected_at_secs(8B) | kind (UTF-8)`
+/// - `cycles`: key = `started_at_ms(8B)`, value = `duration_ms(8B) | interval_secs(8B) |
+///   chains_checked(4B) | chains_behind(4B) | chains_deferred(4B) | chain_errors(4B)`
+/// - `work_queue`: key = `255 - priority(1B) | id(8B)`, value = `chain_id(4B) |
+///   from_block(8B) | to_block(8B) | attempts(4B) | enqueued_at_ms(8B) |
+///   not_before_ms(8B) | kind(1B) | last_error (UTF-8)`
+/// - `errors`: key = `chain_id(4B) | at_us(8B)`, value = `code_len(1B) | code (UTF-8) |
+///   message (UTF-8)`
+/// - `egress`: key = `day_from_ce(4B) | chain_id(4B)`, value = `bytes(8B)`
 #[derive(Clone)]
 pub struct Storage {
     db: Database,
     blocks: Keyspace,
     cursors: Keyspace,
+    rejected: Keyspace,
+    cursor_history: Keyspace,
+    anomalies: Keyspace,
+    cycles: Keyspace,
+    work_queue: Keyspace,
+    errors: Keyspace,
+    egress: Keyspace,
+    /// Id given to the next queued work item.
+    next_work_id: Arc<AtomicU64>,
+    metrics: Arc<StorageMetrics>,
+    /// Stamps cursors, cycles, work items and other records.
+    clock: Arc<dyn Clock>,
+    #[cfg(feature = "chaos")]
+    faults: Option<Arc<Faults>>,
+}
+
+/// What one [`Storage::backup_to`] copied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BackupStats {
+    /// Key-value pairs, across every keyspace.
+    pub entries: u64,
+    /// Key and value bytes.
+    pub bytes: u64,
+}
+
+/// A block header that failed validation and was quarantined instead of indexed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RejectedBlock {
+    pub number: i64,
+    pub timestamp: i64,
+    pub reason: RejectReason,
+    pub rejected_at: DateTime<Utc>,
+}
+
+/// A timestamp anomaly recorded during ingestion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordedAnomaly {
+    pub anomaly: TimestampAnomaly,
+    pub detected_at: DateTime<Utc>,
+}
+
+/// An ingestion failure kept in a chain's error log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IngestErrorEntry {
+    pub chain_id: i32,
+    pub at: DateTime<Utc>,
+    /// The error's [`AppError::code`].
+    pub code: String,
+    pub message: String,
+}
+
+/// Outcome of one ingestion cycle, kept for uptime reporting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CycleSummary {
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    /// Configured sleep before the next cycle.
+    pub interval_secs: u64,
+    pub chains_checked: u32,
+    pub chains_behind: u32,
+    pub chains_deferred: u32,
+    /// Chains whose head fetch, block fetch or write failed during the cycle.
+    pub chain_errors: u32,
+}
+
+/// A block range queued for backfill or repair, drained by the `work_queue` job.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkItem {
+    pub id: u64,
+    /// Higher runs first; equal priorities run in the order they were queued.
+    pub priority: u8,
+    pub kind: WorkKind,
+    pub chain_id: i32,
+    /// Next block to fetch. Moves up as the range is worked off, so a restart resumes
+    /// where the last run stopped.
+    pub from_block: i64,
+    /// Last block of the range, inclusive.
+    pub to_block: i64,
+    /// Failed attempts since the range last made progress.
+    pub attempts: u32,
+    pub enqueued_at: DateTime<Utc>,
+    /// Not retried before this time.
+    pub not_before: DateTime<Utc>,
+    pub last_error: Option<String>,
+}
+
+const WORK_KEY_LEN: usize = 9;
+const WORK_VALUE_LEN: usize = 41;
+
+fn encode_work_key(priority: u8, id: u64) -> [u8; WORK_KEY_LEN] {
+    let mut key = [0u8; WORK_KEY_LEN];
+    key[0] = u8::MAX - priority;
+    key[1..].copy_from_slice(&id.to_be_bytes());
+    key
+}
+
+fn encode_work_value(item: &WorkItem) -> Vec<u8> {
+    let error = item.last_error.as_deref().unwrap_or_default();
+    let mut buf = Vec::with_capacity(WORK_VALUE_LEN + error.len());
+    buf.extend_from_slice(&item.chain_id.to_be_bytes());
+    buf.extend_from_slice(&item.from_block.to_be_bytes());
+    buf.extend_from_slice(&item.to_block.to_be_bytes());
+    buf.extend_from_slice(&item.attempts.to_be_bytes());
+    buf.extend_from_slice(&item.enqueued_at.timestamp_millis().to_be_bytes());
+    buf.extend_from_slice(&item.not_before.timestamp_millis().to_be_bytes());
+    buf.push(match item.kind {
+        WorkKind::Backfill => 0,
+        WorkKind::Repair => 1,
+    });
+    buf.extend_from_slice(error.as_bytes());
+    buf
+}
+
+fn decode_work(key: &[u8], val: &[u8]) -> Result<WorkItem, AppError> {
+    let corrupt = || AppError::CorruptData("malformed work queue entry".into());
+    if key.len() != WORK_KEY_LEN || val.len() < WORK_VALUE_LEN {
+        return Err(corrupt());
+    }
+    let i64_at = |i: usize| i64::from_be_bytes(val[i..i + 8].try_into().unwrap());
+    let time_at = |i: usize| DateTime::from_timestamp_millis(i64_at(i)).ok_or_else(corrupt);
+    let kind = match val[40] {
+        0 => WorkKind::Backfill,
+        1 => WorkKind::Repair,
+        _ => return Err(corrupt()),
+    };
+    let error = std::str::from_utf8(&val[WORK_VALUE_LEN..]).map_err(|_| corrupt())?;
+    Ok(WorkItem {
+        id: u64::from_be_bytes(key[1..].try_into().unwrap()),
+        priority: u8::MAX - key[0],
+        kind,
+        chain_id: i32::from_be_bytes(val[..4].try_into().unwrap()),
+        from_block: i64_at(4),
+        to_block: i64_at(12),
+        attempts: u32::from_be_bytes(val[20..24].try_into().unwrap()),
+        enqueued_at: time_at(24)?,
+        not_before: time_at(32)?,
+        last_error: (!error.is_empty()).then(|| error.to_string()),
+    })
+}
+
+const CYCLE_VALUE_LEN: usize = 32;
+
+fn encode_cycle_value(cycle: &CycleSummary) -> [u8; CYCLE_VALUE_LEN] {
+    let mut buf = [0u8; CYCLE_VALUE_LEN];
+    buf[..8].copy_from_slice(&cycle.duration_ms.to_be_bytes());
+    buf[8..16].copy_from_slice(&cycle.interval_secs.to_be_bytes());
+    buf[16..20].copy_from_slice(&cycle.chains_checked.to_be_bytes());
+    buf[20..24].copy_from_slice(&cycle.chains_behind.to_be_bytes());
+    buf[24..28].copy_from_slice(&cycle.chains_deferred.to_be_bytes());
+    buf[28..].copy_from_slice(&cycle.chain_errors.to_be_bytes());
+    buf
+}
+
+fn decode_cycle(key: &[u8], val: &[u8]) -> Result<CycleSummary, AppError> {
+    let corrupt = || AppError::CorruptData("malformed cycle summary".into());
+    if key.len() != 8 || val.len() != CYCLE_VALUE_LEN {
+        return Err(corrupt());
+    }
+    let u64_at = |i: usize| u64::from_be_bytes(val[i..i + 8].try_into().unwrap());
+    let u32_at = |i: usize| u32::from_be_bytes(val[i..i + 4].try_into().unwrap());
+    let started_ms = u64::from_be_bytes(key.try_into().unwrap()) as i64;
+    Ok(CycleSummary {
+        started_at: DateTime::from_timestamp_millis(started_ms).ok_or_else(corrupt)?,
+        duration_ms: u64_at(0),
+        interval_secs: u64_at(8),
+        chains_checked: u32_at(16),
+        chains_behind: u32_at(20),
+        chains_deferred: u32_at(24),
+        chain_errors: u32_at(28),
+    })
 }
 
 // key layout constants
 const CHAIN_ID_LEN: usize = 4;
 const TIMESTAMP_LEN: usize = 8;
 const NUMBER_LEN: usize = 8;
-const BLOCK_KEY_LEN: usize = CHAIN_ID_LEN + TIMESTAMP_LEN + NUMBER_LEN;
+pub const BLOCK_KEY_LEN: usize = CHAIN_ID_LEN + TIMESTAMP_LEN + NUMBER_LEN;
+const REJECTED_KEY_LEN: usize = CHAIN_ID_LEN + NUMBER_LEN;
+const HISTORY_KEY_LEN: usize = CHAIN_ID_LEN + TIMESTAMP_LEN;
+
+/// Queries in a `find_blocks_multi` batch whose timestamps are within this many seconds
+/// of each other share one forward scan. Wider gaps start a new scan, so a batch spanning
+/// years doesn't walk every block in between.
+const MULTI_SCAN_MAX_GAP_SECS: u64 = 3600;
+
+/// A lookup normalized to a single timestamp threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Probe {
+    /// First block with `ts >= t`.
+    AtLeast(u64),
+    /// Last block with `ts <= t`.
+    AtMost(u64),
+}
+
+impl Probe {
+    /// Normalizes a lookup; `None` means no block can ever match (before-exclusive 0,
+    /// after-exclusive `u64::MAX`). A nearest lookup is two probes, so callers split it
+    /// into a before and an after lookup first (see [`Storage::find_block`]).
+    fn new(timestamp: u64, bound: Bound) -> Option<Self> {
+        match bound {
+            Bound::Nearest => None,
+            Bound::AtOrBefore => Some(Self::AtMost(timestamp)),
+            Bound::Before => timestamp.checked_sub(1).map(Self::AtMost),
+            Bound::AtOrAfter => Some(Self::AtLeast(timestamp)),
+            Bound::After => timestamp.checked_add(1).map(Self::AtLeast),
+        }
+    }
+
+    fn threshold(self) -> u64 {
+        match self {
+            Self::AtLeast(t) | Self::AtMost(t) => t,
+        }
+    }
+
+    /// Scan order: by threshold, with `AtLeast` before `AtMost` at equal thresholds so
+    /// the forward scan answers it on the boundary key before moving past it.
+    fn sort_key(self) -> (u64, bool) {
+        (self.threshold(), matches!(self, Self::AtMost(_)))
+    }
+}
 
 /// fjall block cache size. Dominates RSS, tune based on available memory.
 const BLOCK_CACHE_SIZE: u64 = 64 * 1024 * 1024;
 
-fn encode_block_key(chain_id: u32, timestamp: u64, number: u64) -> [u8; BLOCK_KEY_LEN] {
+/// Bytes a backup copies per write batch, and so between two throttling pauses.
+const BACKUP_BATCH_BYTES: u64 = 1024 * 1024;
+
+/// Encodes a `blocks` keyspace key. Big-endian so byte order matches numeric order.
+pub fn encode_block_key(chain_id: u32, timestamp: u64, number: u64) -> [u8; BLOCK_KEY_LEN] {
     let mut key = [0u8; BLOCK_KEY_LEN];
     key[..CHAIN_ID_LEN].copy_from_slice(&chain_id.to_be_bytes());
     key[CHAIN_ID_LEN..CHAIN_ID_LEN + TIMESTAMP_LEN].copy_from_slice(&timestamp.to_be_bytes());
@@ -51,7 +331,20 @@ fn encode_block_key(chain_id: u32, timestamp: u64, number: u64) -> [u8; BLOCK_KE
     key
 }
 
-fn decode_block_key(key: &[u8]) -> (u32, u64, u64) {
+/// Largest possible `blocks` key for a chain, used as an inclusive upper bound so range
+/// scans never need to name the next chain (which doesn't exist for `u32::MAX`).
+fn chain_end_key(chain_id: u32) -> [u8; BLOCK_KEY_LEN] {
+    encode_block_key(chain_id, u64::MAX, u64::MAX)
+}
+
+/// Decodes a `blocks` keyspace key into `(chain_id, timestamp, number)`.
+pub fn decode_block_key(key: &[u8]) -> Result<(u32, u64, u64), AppError> {
+    if key.len() != BLOCK_KEY_LEN {
+        return Err(AppError::CorruptData(format!(
+            "block key has length {}, expected {BLOCK_KEY_LEN}",
+            key.len()
+        )));
+    }
     let chain_id = u32::from_be_bytes(key[..CHAIN_ID_LEN].try_into().unwrap());
     let timestamp = u64::from_be_bytes(
         key[CHAIN_ID_LEN..CHAIN_ID_LEN + TIMESTAMP_LEN]
@@ -59,7 +352,25 @@ fn decode_block_key(key: &[u8]) -> (u32, u64, u64) {
             .unwrap(),
     );
     let number = u64::from_be_bytes(key[CHAIN_ID_LEN + TIMESTAMP_LEN..].try_into().unwrap());
-    (chain_id, timestamp, number)
+    Ok((chain_id, timestamp, number))
+}
+
+/// Cursor key: chain_id (4B u32 BE). Versions before this one keyed cursors by SQD
+/// slug; [`Storage::open`] migrates those.
+fn encode_cursor_key(chain_id: i32) -> [u8; CHAIN_ID_LEN] {
+    (chain_id as u32).to_be_bytes()
+}
+
+fn decode_cursor_key(key: &[u8]) -> Option<i32> {
+    Some(u32::from_be_bytes(key.try_into().ok()?) as i32)
+}
+
+/// Egress totals sort by day, then chain.
+fn encode_egress_key(day: NaiveDate, chain_id: i32) -> [u8; 2 * CHAIN_ID_LEN] {
+    let mut key = [0u8; 2 * CHAIN_ID_LEN];
+    key[..CHAIN_ID_LEN].copy_from_slice(&(day.num_days_from_ce() as u32).to_be_bytes());
+    key[CHAIN_ID_LEN..].copy_from_slice(&chain_id.to_be_bytes());
+    key
 }
 
 /// Encode cursor value: last_block (8B i64 BE) | updated_at unix secs (8B i64 BE).
@@ -70,10 +381,152 @@ fn encode_cursor_value(last_block: i64, updated_at_secs: i64) -> [u8; 16] {
     buf
 }
 
-fn decode_cursor_value(val: &[u8]) -> (i64, i64) {
+fn decode_cursor_value(val: &[u8]) -> Result<(i64, i64), AppError> {
+    if val.len() != 16 {
+        return Err(AppError::CorruptData(format!(
+            "cursor value has length {}, expected 16",
+            val.len()
+        )));
+    }
     let last_block = i64::from_be_bytes(val[..8].try_into().unwrap());
     let updated_at_secs = i64::from_be_bytes(val[8..].try_into().unwrap());
-    (last_block, updated_at_secs)
+    Ok((last_block, updated_at_secs))
+}
+
+/// Cursor history key: chain_id (4B u32 BE) | recorded_at secs (8B u64 BE).
+fn encode_history_key(chain_id: u32, recorded_at: u64) -> [u8; HISTORY_KEY_LEN] {
+    let mut key = [0u8; HISTORY_KEY_LEN];
+    key[..CHAIN_ID_LEN].copy_from_slice(&chain_id.to_be_bytes());
+    key[CHAIN_ID_LEN..].copy_from_slice(&recorded_at.to_be_bytes());
+    key
+}
+
+fn decode_history_entry(key: &[u8], val: &[u8]) -> Result<(i64, i64), AppError> {
+    if key.len() != HISTORY_KEY_LEN || val.len() != 8 {
+        return Err(AppError::CorruptData(format!(
+            "cursor history entry has key length {} and value length {}, expected {HISTORY_KEY_LEN} and 8",
+            key.len(),
+            val.len()
+        )));
+    }
+    let recorded_at = u64::from_be_bytes(key[CHAIN_ID_LEN..].try_into().unwrap()) as i64;
+    let last_block = i64::from_be_bytes(val.try_into().unwrap());
+    Ok((recorded_at, last_block))
+}
+
+fn encode_rejected_key(chain_id: u32, number: u64) -> [u8; REJECTED_KEY_LEN] {
+    let mut key = [0u8; REJECTED_KEY_LEN];
+    key[..CHAIN_ID_LEN].copy_from_slice(&chain_id.to_be_bytes());
+    key[CHAIN_ID_LEN..].copy_from_slice(&number.to_be_bytes());
+    key
+}
+
+fn encode_rejected_value(timestamp: i64, rejected_at_secs: i64, reason: RejectReason) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(16 + reason.as_str().len());
+    buf.extend_from_slice(&timestamp.to_be_bytes());
+    buf.extend_from_slice(&rejected_at_secs.to_be_bytes());
+    buf.extend_from_slice(reason.as_str().as_bytes());
+    buf
+}
+
+/// Anomaly keys share the rejected key layout: chain_id | number.
+fn encode_anomaly_value(anomaly: &TimestampAnomaly, detected_at_secs: i64) -> Vec<u8> {
+    let kind = anomaly.kind.as_str();
+    let mut buf = Vec::with_capacity(32 + kind.len());
+    buf.extend_from_slice(&anomaly.prev_number.to_be_bytes());
+    buf.extend_from_slice(&anomaly.prev_timestamp.to_be_bytes());
+    buf.extend_from_slice(&anomaly.timestamp.to_be_bytes());
+    buf.extend_from_slice(&detected_at_secs.to_be_bytes());
+    buf.extend_from_slice(kind.as_bytes());
+    buf
+}
+
+fn decode_anomaly(key: &[u8], val: &[u8]) -> Result<RecordedAnomaly, AppError> {
+    let number = decode_rejected_number(key)?;
+    let field = |i: usize| {
+        val.get(i * 8..(i + 1) * 8)
+            .map(|b| i64::from_be_bytes(b.try_into().unwrap()))
+    };
+    let corrupt = || AppError::CorruptData(format!("malformed anomaly for block {number}"));
+    let (Some(prev_number), Some(prev_timestamp), Some(timestamp), Some(detected_at_secs)) =
+        (field(0), field(1), field(2), field(3))
+    else {
+        return Err(corrupt());
+    };
+    let kind = std::str::from_utf8(&val[32..])
+        .ok()
+        .and_then(AnomalyKind::parse)
+        .ok_or_else(corrupt)?;
+    let detected_at = DateTime::from_timestamp(detected_at_secs, 0).ok_or_else(corrupt)?;
+    Ok(RecordedAnomaly {
+        anomaly: TimestampAnomaly {
+            kind,
+            number,
+            timestamp,
+            prev_number,
+            prev_timestamp,
+        },
+        detected_at,
+    })
+}
+
+/// Error log keys share the cursor history layout, with the time in microseconds.
+fn encode_error_value(code: &str, message: &str) -> Vec<u8> {
+    let code = &code.as_bytes()[..code.len().min(u8::MAX as usize)];
+    let mut buf = Vec::with_capacity(1 + code.len() + message.len());
+    buf.push(code.len() as u8);
+    buf.extend_from_slice(code);
+    buf.extend_from_slice(message.as_bytes());
+    buf
+}
+
+fn decode_error_entry(key: &[u8], val: &[u8]) -> Result<IngestErrorEntry, AppError> {
+    let corrupt = || AppError::CorruptData("malformed ingest error entry".into());
+    if key.len() != HISTORY_KEY_LEN {
+        return Err(corrupt());
+    }
+    let chain_id = u32::from_be_bytes(key[..CHAIN_ID_LEN].try_into().unwrap()) as i32;
+    let at_us = u64::from_be_bytes(key[CHAIN_ID_LEN..].try_into().unwrap()) as i64;
+    let (&code_len, rest) = val.split_first().ok_or_else(corrupt)?;
+    let (code, message) = rest
+        .split_at_checked(code_len as usize)
+        .ok_or_else(corrupt)?;
+    Ok(IngestErrorEntry {
+        chain_id,
+        at: DateTime::from_timestamp_micros(at_us).ok_or_else(corrupt)?,
+        code: String::from_utf8(code.to_vec()).map_err(|_| corrupt())?,
+        message: String::from_utf8_lossy(message).into_owned(),
+    })
+}
+
+fn decode_rejected_number(key: &[u8]) -> Result<i64, AppError> {
+    if key.len() != REJECTED_KEY_LEN {
+        return Err(AppError::CorruptData("malformed rejected block key".into()));
+    }
+    Ok(u64::from_be_bytes(key[CHAIN_ID_LEN..].try_into().unwrap()) as i64)
+}
+
+fn decode_rejected(key: &[u8], val: &[u8]) -> Result<RejectedBlock, AppError> {
+    if key.len() != REJECTED_KEY_LEN || val.len() < 16 {
+        return Err(AppError::CorruptData(
+            "malformed rejected block entry".into(),
+        ));
+    }
+    let number = u64::from_be_bytes(key[CHAIN_ID_LEN..].try_into().unwrap()) as i64;
+    let timestamp = i64::from_be_bytes(val[..8].try_into().unwrap());
+    let rejected_at_secs = i64::from_be_bytes(val[8..16].try_into().unwrap());
+    let reason = std::str::from_utf8(&val[16..])
+        .ok()
+        .and_then(RejectReason::parse)
+        .ok_or_else(|| AppError::CorruptData("unknown reject reason".into()))?;
+    let rejected_at = DateTime::from_timestamp(rejected_at_secs, 0)
+        .ok_or_else(|| AppError::CorruptData("invalid rejected_at timestamp".into()))?;
+    Ok(RejectedBlock {
+        number,
+        timestamp,
+        reason,
+        rejected_at,
+    })
 }
 
 impl Storage {
@@ -84,64 +537,517 @@ impl Storage {
             .open()?;
         let blocks = db.keyspace("blocks", KeyspaceCreateOptions::default)?;
         let cursors = db.keyspace("cursors", KeyspaceCreateOptions::default)?;
-        Ok(Self {
+        let rejected = db.keyspace("rejected", KeyspaceCreateOptions::default)?;
+        let cursor_history = db.keyspace("cursor_history", KeyspaceCreateOptions::default)?;
+        let anomalies = db.keyspace("anomalies", KeyspaceCreateOptions::default)?;
+        let cycles = db.keyspace("cycles", KeyspaceCreateOptions::default)?;
+        let work_queue = db.keyspace("work_queue", KeyspaceCreateOptions::default)?;
+        let errors = db.keyspace("errors", KeyspaceCreateOptions::default)?;
+        let egress = db.keyspace("egress", KeyspaceCreateOptions::default)?;
+        let mut next_work_id = 0;
+        for guard in work_queue.iter() {
+            let key = guard.key()?;
+            if key.len() == WORK_KEY_LEN {
+                let id = u64::from_be_bytes(key[1..].try_into().unwrap());
+                next_work_id = next_work_id.max(id + 1);
+            }
+        }
+        let storage = Self {
             db,
             blocks,
             cursors,
-        })
+            rejected,
+            cursor_history,
+            anomalies,
+            cycles,
+            work_queue,
+            errors,
+            egress,
+            next_work_id: Arc::new(AtomicU64::new(next_work_id)),
+            metrics: Arc::default(),
+            clock: clock::system(),
+            #[cfg(feature = "chaos")]
+            faults: None,
+        };
+        storage.migrate_slug_cursors()?;
+        Ok(storage)
+    }
+
+    /// Rewrites cursors still keyed by SQD slug to chain id keys, so a later slug
+    /// rename can't orphan them. If both keys exist the higher cursor wins. Slugs no
+    /// chain uses are left in place and ignored by the cursor accessors.
+    fn migrate_slug_cursors(&self) -> Result<(), AppError> {
+        let mut batch = self.db.batch();
+        let mut migrated = 0;
+        for guard in self.cursors.iter() {
+            let (key, value) = guard.into_inner()?;
+            if key.len() == CHAIN_ID_LEN {
+                continue;
+            }
+            let slug = String::from_utf8_lossy(&key).into_owned();
+            let Some(chain) = chains::chain_by_slug(&slug) else {
+                tracing::warn!(slug = %slug, "ignoring cursor for unknown sqd slug");
+                continue;
+            };
+            let (last_block, _) = decode_cursor_value(&value)?;
+            let new_key = encode_cursor_key(chain.chain_id);
+            let current = match self.cursors.get(new_key)? {
+                Some(val) => decode_cursor_value(&val)?.0,
+                None => i64::MIN,
+            };
+            if last_block > current {
+                batch.insert(&self.cursors, new_key, value);
+            }
+            batch.remove(&self.cursors, key);
+            migrated += 1;
+            tracing::info!(
+                slug = %slug,
+                chain_id = chain.chain_id,
+                last_block,
+                "migrating cursor to chain id key"
+            );
+        }
+        if migrated > 0 {
+            batch.commit()?;
+            self.db.persist(PersistMode::SyncAll)?;
+        }
+        Ok(())
+    }
+
+    /// Reads time from `clock` instead of the process clock, for the records this
+    /// storage stamps and the ingestion scheduling that goes by it.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The clock this storage stamps records with.
+    pub fn clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
+
+    /// Injects faults into block inserts and cursor writes (see [`crate::chaos`]).
+    #[cfg(feature = "chaos")]
+    pub fn with_faults(mut self, faults: Arc<Faults>) -> Self {
+        self.faults = Some(faults);
+        self
+    }
+
+    /// Applies an injected fault before a write: fails it, delays it, or returns how
+    /// many of `len` items to write before failing.
+    #[cfg(feature = "chaos")]
+    fn inject(&self, len: usize) -> Result<Option<usize>, AppError> {
+        let injected = || fjall::Error::Io(std::io::Error::other("injected write failure"));
+        match self.faults.as_ref().and_then(|f| f.next()) {
+            Some(Fault::Error) => Err(injected().into()),
+            Some(Fault::Latency(delay)) => {
+                std::thread::sleep(delay);
+                Ok(None)
+            }
+            Some(Fault::Truncate(keep)) => Ok(Some((len as f64 * keep) as usize)),
+            None => Ok(None),
+        }
+    }
+
+    #[cfg(not(feature = "chaos"))]
+    fn inject(&self, _len: usize) -> Result<Option<usize>, AppError> {
+        Ok(None)
     }
 
-    /// Finds the closest block to a given timestamp in the specified direction.
+    /// Finds the closest block to a given timestamp within `bound`.
     ///
-    /// Returns `(number, timestamp)` or `None`.
+    /// Returns `(number, timestamp)` or `None`. When several blocks share the closest
+    /// timestamp, keys order them by number, so the answer is the one nearest the
+    /// query: the highest for `before` and the lowest for `after` ([`Tie::natural`]).
+    /// Binary searches over block numbers rely on this staying stable; every other
+    /// lookup here ([`Storage::find_blocks_near`], [`Storage::find_blocks_multi`])
+    /// breaks ties the same way.
+    ///
+    /// `nearest` is an `at or before` lookup and, unless that lands on the timestamp
+    /// itself, an `after` one, whichever is closer ([`Direction::nearest_of`]).
     pub fn find_block(
         &self,
         chain_id: i32,
         timestamp: i64,
-        direction: &str,
-        inclusive: bool,
+        bound: Bound,
     ) -> Result<Option<(i64, i64)>, AppError> {
+        if bound == Bound::Nearest {
+            let before = self.find_block(chain_id, timestamp, Bound::AtOrBefore)?;
+            if before.is_some_and(|(_, ts)| ts == timestamp) {
+                return Ok(before);
+            }
+            let after = self.find_block(chain_id, timestamp, Bound::After)?;
+            return Ok(Direction::nearest_of(timestamp, before, after, |b| b.1));
+        }
         let c = chain_id as u32;
-        let ts = timestamp as u64;
 
-        let result = match (direction, inclusive) {
-            // before inclusive: ts <= T => range(C|0|0 ..= C|T|MAX).next_back()
-            ("before", true) => {
+        // every range stays inside this chain's key space: C|0|0 ..= C|MAX|MAX
+        let result = match Probe::new(timestamp as u64, bound) {
+            // nothing can precede ts 0 or follow ts u64::MAX
+            None => None,
+            // last block with ts <= t
+            Some(Probe::AtMost(t)) => {
                 let lo = encode_block_key(c, 0, 0);
-                let hi = encode_block_key(c, ts, u64::MAX);
+                let hi = encode_block_key(c, t, u64::MAX);
                 self.blocks.range(lo..=hi).next_back()
             }
-            // before exclusive: ts < T => range(C|0|0 .. C|T|0).next_back()
-            ("before", false) => {
-                let lo = encode_block_key(c, 0, 0);
-                let hi = encode_block_key(c, ts, 0);
-                self.blocks.range(lo..hi).next_back()
-            }
-            // after inclusive: ts >= T => range(C|T|0 .. C+1|0|0).next()
-            ("after", true) => {
-                let lo = encode_block_key(c, ts, 0);
-                let hi = encode_block_key(c + 1, 0, 0);
-                self.blocks.range(lo..hi).next()
-            }
-            // after exclusive: ts > T => range(C|T+1|0 .. C+1|0|0).next()
-            ("after", false) => {
-                let lo = encode_block_key(c, ts + 1, 0);
-                let hi = encode_block_key(c + 1, 0, 0);
-                self.blocks.range(lo..hi).next()
-            }
-            _ => None,
+            // first block with ts >= t
+            Some(Probe::AtLeast(t)) => {
+                let lo = encode_block_key(c, t, 0);
+                self.blocks.range(lo..=chain_end_key(c)).next()
+            }
         };
 
         match result {
             Some(guard) => {
                 let key = guard.key()?;
-                let (_, block_ts, block_num) = decode_block_key(&key);
+                let (_, block_ts, block_num) = decode_block_key(&key)?;
                 Ok(Some((block_num as i64, block_ts as i64)))
             }
             None => Ok(None),
         }
     }
 
+    /// [`Storage::find_block`] with an explicit tie-break: among the blocks sharing the
+    /// matched timestamp, the lowest or highest number. Costs a second seek only when
+    /// `tie` differs from the natural one.
+    pub fn find_block_tied(
+        &self,
+        chain_id: i32,
+        timestamp: i64,
+        bound: Bound,
+        tie: Tie,
+    ) -> Result<Option<(i64, i64)>, AppError> {
+        let found = self.find_block(chain_id, timestamp, bound)?;
+        match found {
+            Some((_, block_ts))
+                if tie != Tie::natural(bound.direction().side(timestamp, block_ts)) =>
+            {
+                Ok(self
+                    .tied_block(chain_id, block_ts, tie)?
+                    .map(|number| (number, block_ts)))
+            }
+            found => Ok(found),
+        }
+    }
+
+    /// The lowest or highest block number stored with exactly `timestamp`, to break a
+    /// tie between blocks sharing it. `None` if no block has that timestamp.
+    pub fn tied_block(
+        &self,
+        chain_id: i32,
+        timestamp: i64,
+        tie: Tie,
+    ) -> Result<Option<i64>, AppError> {
+        let c = chain_id as u32;
+        let ts = timestamp as u64;
+        let mut range = self
+            .blocks
+            .range(encode_block_key(c, ts, 0)..=encode_block_key(c, ts, u64::MAX));
+        let guard = match tie {
+            Tie::Low => range.next(),
+            Tie::High => range.next_back(),
+        };
+        match guard {
+            Some(guard) => Ok(Some(decode_block_key(&guard.key()?)?.2 as i64)),
+            None => Ok(None),
+        }
+    }
+
+    /// Up to `limit` blocks closest to a timestamp within `bound`, closest
+    /// first. The first entry is what [`Storage::find_block`] returns; the rest come
+    /// from the same bounded range read.
+    pub fn find_blocks_near(
+        &self,
+        chain_id: i32,
+        timestamp: i64,
+        bound: Bound,
+        limit: usize,
+    ) -> Result<Vec<(i64, i64)>, AppError> {
+        if bound == Bound::Nearest {
+            // both sides are closest first, so merging them by distance keeps that
+            let before = self.find_blocks_near(chain_id, timestamp, Bound::AtOrBefore, limit)?;
+            let after = self.find_blocks_near(chain_id, timestamp, Bound::After, limit)?;
+            let (mut before, mut after) =
+                (before.into_iter().peekable(), after.into_iter().peekable());
+            let mut blocks = Vec::with_capacity(limit.min(1024));
+            while blocks.len() < limit {
+                let next = Direction::nearest_of(
+                    timestamp,
+                    before.peek().copied(),
+                    after.peek().copied(),
+                    |b| b.1,
+                );
+                let side = match next {
+                    Some(b) if before.peek() == Some(&b) => &mut before,
+                    Some(_) => &mut after,
+                    None => break,
+                };
+                blocks.extend(side.next());
+            }
+            return Ok(blocks);
+        }
+        let c = chain_id as u32;
+        let rows: Box<dyn Iterator<Item = _>> = match Probe::new(timestamp as u64, bound) {
+            None => return Ok(Vec::new()),
+            Some(Probe::AtMost(t)) => {
+                let lo = encode_block_key(c, 0, 0);
+                let hi = encode_block_key(c, t, u64::MAX);
+                Box::new(self.blocks.range(lo..=hi).rev())
+            }
+            Some(Probe::AtLeast(t)) => {
+                let lo = encode_block_key(c, t, 0);
+                Box::new(self.blocks.range(lo..=chain_end_key(c)))
+            }
+        };
+
+        let mut blocks = Vec::with_capacity(limit.min(1024));
+        for guard in rows.take(limit) {
+            let (_, block_ts, block_num) = decode_block_key(&guard.key()?)?;
+            blocks.push((block_num as i64, block_ts as i64));
+        }
+        Ok(blocks)
+    }
+
+    /// Answers many lookups on one chain, returning results in input order.
+    ///
+    /// Queries are sorted by timestamp and grouped into clusters (see
+    /// `MULTI_SCAN_MAX_GAP_SECS`). Each cluster costs one reverse seek for the block
+    /// preceding it plus one forward range scan, instead of one seek per query.
+    pub fn find_blocks_multi(
+        &self,
+        chain_id: i32,
+        queries: &[(i64, Bound)],
+    ) -> Result<Vec<Option<(i64, i64)>>, AppError> {
+        if queries.iter().any(|q| q.1 == Bound::Nearest) {
+            // each nearest query becomes an at-or-before and an after query, as in
+            // `find_block`, and is answered by the closer of the two
+            let split: Vec<_> = queries
+                .iter()
+                .flat_map(|&(ts, bound)| match bound {
+                    Bound::Nearest => vec![(ts, Bound::AtOrBefore), (ts, Bound::After)],
+                    _ => vec![(ts, bound)],
+                })
+                .collect();
+            let mut rows = self.find_blocks_multi(chain_id, &split)?.into_iter();
+            return Ok(queries
+                .iter()
+                .map(|&(ts, bound)| {
+                    let first = rows.next().flatten();
+                    match bound {
+                        Bound::Nearest => {
+                            Direction::nearest_of(ts, first, rows.next().flatten(), |b| b.1)
+                        }
+                        _ => first,
+                    }
+                })
+                .collect());
+        }
+        let c = chain_id as u32;
+        let mut results = vec![None; queries.len()];
+
+        let mut probes: Vec<(Probe, usize)> = queries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &(ts, bound))| Probe::new(ts as u64, bound).map(|p| (p, i)))
+            .collect();
+        probes.sort_unstable_by_key(|(p, _)| p.sort_key());
+
+        let mut start = 0;
+        while start < probes.len() {
+            let mut end = start + 1;
+            while end < probes.len()
+                && probes[end].0.threshold() - probes[end - 1].0.threshold()
+                    <= MULTI_SCAN_MAX_GAP_SECS
+            {
+                end += 1;
+            }
+            self.scan_cluster(c, &probes[start..end], &mut results)?;
+            start = end;
+        }
+
+        Ok(results)
+    }
+
+    /// Resolves a sorted cluster of probes with a single forward pass.
+    fn scan_cluster(
+        &self,
+        c: u32,
+        probes: &[(Probe, usize)],
+        results: &mut [Option<(i64, i64)>],
+    ) -> Result<(), AppError> {
+        let first = probes[0].0.threshold();
+
+        // last block strictly before the cluster, for AtMost probes that no key exceeds
+        let mut prev = match self
+            .blocks
+            .range(encode_block_key(c, 0, 0)..encode_block_key(c, first, 0))
+            .next_back()
+        {
+            Some(guard) => {
+                let (_, ts, num) = decode_block_key(&guard.key()?)?;
+                Some((num as i64, ts as i64))
+            }
+            None => None,
+        };
+
+        let mut i = 0;
+        let lo = encode_block_key(c, first, 0);
+        for guard in self.blocks.range(lo..=chain_end_key(c)) {
+            let (_, ts, num) = decode_block_key(&guard.key()?)?;
+            let current = Some((num as i64, ts as i64));
+            while let Some(&(probe, idx)) = probes.get(i) {
+                match probe {
+                    Probe::AtLeast(t) if ts >= t => results[idx] = current,
+                    Probe::AtMost(t) if ts > t => results[idx] = prev,
+                    _ => break,
+                }
+                i += 1;
+            }
+            if i == probes.len() {
+                return Ok(());
+            }
+            prev = current;
+        }
+
+        // ran off the end of the chain: AtMost probes take the last block seen,
+        // AtLeast probes have no answer yet
+        for &(probe, idx) in &probes[i..] {
+            if let Probe::AtMost(_) = probe {
+                results[idx] = prev;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads up to `limit` blocks in key order, starting at `(from_ts, from_number)`
+    /// inclusive and stopping before `to_ts`.
+    ///
+    /// Returns `(number, timestamp)` pairs. Callers page through a range by resuming
+    /// from the last block returned with `from_number + 1`.
+    pub fn scan_blocks(
+        &self,
+        chain_id: i32,
+        (from_ts, from_number): (i64, i64),
+        to_ts: i64,
+        limit: usize,
+    ) -> Result<Vec<(i64, i64)>, AppError> {
+        let c = chain_id as u32;
+        if limit == 0 || from_ts >= to_ts {
+            return Ok(Vec::new());
+        }
+        let lo = encode_block_key(c, from_ts as u64, from_number as u64);
+        let hi = encode_block_key(c, to_ts as u64, 0);
+
+        let mut rows = Vec::with_capacity(limit.min(4096));
+        for guard in self.blocks.range(lo..hi).take(limit) {
+            let (_, ts, num) = decode_block_key(&guard.key()?)?;
+            rows.push((num as i64, ts as i64));
+        }
+        Ok(rows)
+    }
+
+    /// First block with number `>= number` among blocks with `from_ts <= timestamp <
+    /// to_ts`, or `None` if every block in the window is lower.
+    ///
+    /// Keys are ordered by timestamp, not number, so this binary searches timestamps for
+    /// the first one whose latest block reaches `number` (block numbers grow with time),
+    /// costing one reverse seek per step: about 32 seeks for any window.
+    pub fn find_block_by_number(
+        &self,
+        chain_id: i32,
+        number: i64,
+        from_ts: i64,
+        to_ts: i64,
+    ) -> Result<Option<(i64, i64)>, AppError> {
+        let c = chain_id as u32;
+        if from_ts >= to_ts {
+            return Ok(None);
+        }
+        let window_start = encode_block_key(c, from_ts as u64, 0);
+        // highest block number with from_ts <= ts <= t
+        let latest_up_to = |t: i64| -> Result<Option<i64>, AppError> {
+            match self
+                .blocks
+                .range(window_start..=encode_block_key(c, t as u64, u64::MAX))
+                .next_back()
+            {
+                Some(guard) => Ok(Some(decode_block_key(&guard.key()?)?.2 as i64)),
+                None => Ok(None),
+            }
+        };
+
+        let (mut lo, mut hi) = (from_ts, to_ts - 1);
+        if latest_up_to(hi)?.is_none_or(|n| n < number) {
+            return Ok(None);
+        }
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if latest_up_to(mid)?.is_some_and(|n| n >= number) {
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
+        }
+
+        // several blocks can share the timestamp found; take the first one reaching it
+        let ts = lo as u64;
+        for guard in self
+            .blocks
+            .range(encode_block_key(c, ts, 0)..=encode_block_key(c, ts, u64::MAX))
+        {
+            let (_, block_ts, block_num) = decode_block_key(&guard.key()?)?;
+            if block_num as i64 >= number {
+                return Ok(Some((block_num as i64, block_ts as i64)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Every stored `(number, timestamp)` entry of blocks `from_number..=to_number`,
+    /// sorted by number. A block stored under several timestamps appears once for each.
+    ///
+    /// A wrong timestamp can put a block anywhere in the chain's key range, so unlike
+    /// [`Self::find_block_by_number`] this scans all of the chain's keys. Meant for
+    /// offline repairs, not request handling.
+    pub fn blocks_by_number(
+        &self,
+        chain_id: i32,
+        from_number: i64,
+        to_number: i64,
+    ) -> Result<Vec<(i64, i64)>, AppError> {
+        let c = chain_id as u32;
+        let mut rows = Vec::new();
+        for guard in self
+            .blocks
+            .range(encode_block_key(c, 0, 0)..=chain_end_key(c))
+        {
+            let (_, ts, num) = decode_block_key(&guard.key()?)?;
+            if (from_number..=to_number).contains(&(num as i64)) {
+                rows.push((num as i64, ts as i64));
+            }
+        }
+        rows.sort_unstable();
+        Ok(rows)
+    }
+
+    /// Average seconds per block over roughly the chain's last `span` stored blocks,
+    /// or `None` with fewer than two blocks stored.
+    pub fn recent_block_time(&self, chain_id: i32, span: i64) -> Result<Option<f64>, AppError> {
+        let Some((last, last_ts)) = self.find_block(chain_id, i64::MAX, Bound::AtOrBefore)? else {
+            return Ok(None);
+        };
+        let Some((first, first_ts)) =
+            self.find_block_by_number(chain_id, (last - span).max(0), 0, i64::MAX)?
+        else {
+            return Ok(None);
+        };
+        if first >= last {
+            return Ok(None);
+        }
+        Ok(Some((last_ts - first_ts) as f64 / (last - first) as f64))
+    }
+
     /// Bulk-inserts blocks from parallel number/timestamp slices.
     /// Idempotent (overwrites with same empty value).
     pub fn insert_blocks(
@@ -150,11 +1056,14 @@ impl Storage {
         numbers: &[i64],
         timestamps: &[i64],
     ) -> Result<(), AppError> {
+        let _timed = self.metrics.writes.time();
         let c = chain_id as u32;
         for (num, ts) in numbers.iter().zip(timestamps.iter()) {
             self.blocks
                 .insert(encode_block_key(c, *ts as u64, *num as u64), [])?;
         }
+        self.metrics
+            .add_blocks_written(numbers.len().min(timestamps.len()));
         Ok(())
     }
 
@@ -165,52 +1074,648 @@ impl Storage {
         chain_id: i32,
         headers: &[crate::sqd::BlockHeader],
     ) -> Result<(), AppError> {
+        let _timed = self.metrics.writes.time();
         let c = chain_id as u32;
-        for h in headers {
+        let partial = self.inject(headers.len())?;
+        for h in &headers[..partial.unwrap_or(headers.len())] {
             self.blocks
                 .insert(encode_block_key(c, h.timestamp as u64, h.number as u64), [])?;
         }
+        if partial.is_some() {
+            let e = fjall::Error::Io(std::io::Error::other("injected partial write"));
+            return Err(e.into());
+        }
+        self.metrics.add_blocks_written(headers.len());
         Ok(())
     }
 
+    /// Moves blocks to corrected timestamps: each block's key at `stored` is replaced by
+    /// one at `reference`, in a single batch so a crash can't leave a block under both
+    /// or neither. Returns how many stored keys were moved.
+    pub fn retime_blocks(&self, chain_id: i32, fixes: &[TimestampFix]) -> Result<u64, AppError> {
+        let _timed = self.metrics.writes.time();
+        let c = chain_id as u32;
+        let mut batch = self.db.batch();
+        let mut moved = 0;
+        for fix in fixes {
+            let old = encode_block_key(c, fix.stored as u64, fix.number as u64);
+            if !self.blocks.contains_key(old)? {
+                continue;
+            }
+            let new = encode_block_key(c, fix.reference as u64, fix.number as u64);
+            batch.insert(&self.blocks, new, []);
+            batch.remove(&self.blocks, old);
+            moved += 1;
+        }
+        if moved > 0 {
+            batch.commit()?;
+        }
+        Ok(moved)
+    }
+
+    /// Quarantines headers that failed validation, keyed by block number.
+    /// Re-rejecting the same block overwrites the previous entry.
+    pub fn insert_rejected(
+        &self,
+        chain_id: i32,
+        rejected: &[(BlockHeader, RejectReason)],
+    ) -> Result<(), AppError> {
+        let _timed = self.metrics.writes.time();
+        let c = chain_id as u32;
+        let now = self.clock.now().timestamp();
+        for (h, reason) in rejected {
+            self.rejected.insert(
+                encode_rejected_key(c, h.number as u64),
+                encode_rejected_value(h.timestamp, now, *reason),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Returns the number of quarantined blocks for a chain.
+    pub fn rejected_count(&self, chain_id: i32) -> Result<u64, AppError> {
+        let mut count = 0;
+        for guard in self.rejected.prefix((chain_id as u32).to_be_bytes()) {
+            guard.key()?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// First and last stored block of a chain in key order. `None` if the chain has no
+    /// blocks.
+    pub fn block_extent(&self, chain_id: i32) -> Result<Option<(BlockRef, BlockRef)>, AppError> {
+        let c = chain_id as u32;
+        let mut range = self
+            .blocks
+            .range(encode_block_key(c, 0, 0)..=chain_end_key(c));
+        let decode = |guard: fjall::Guard| -> Result<BlockRef, AppError> {
+            let (_, ts, number) = decode_block_key(&guard.key()?)?;
+            Ok(BlockRef {
+                number: number as i64,
+                timestamp: ts as i64,
+            })
+        };
+        let Some(first) = range.next() else {
+            return Ok(None);
+        };
+        let first = decode(first)?;
+        let last = match range.next_back() {
+            Some(guard) => decode(guard)?,
+            None => first,
+        };
+        Ok(Some((first, last)))
+    }
+
+    /// Write-ahead journal files and their size on disk. Read right after opening, this
+    /// is what fjall just replayed.
+    pub fn journal_stats(&self) -> Result<(usize, u64), AppError> {
+        Ok((self.db.journal_count(), self.db.journal_disk_space()?))
+    }
+
+    /// Total size of the database on disk in bytes.
+    pub fn disk_space(&self) -> Result<u64, AppError> {
+        Ok(self.db.disk_space()?)
+    }
+
+    /// Highest block number stored for a chain, counting quarantined blocks since
+    /// ingestion advances its cursor past those too. `None` if the chain has no data.
+    ///
+    /// Blocks are keyed by timestamp first, so this reads the latest block by time,
+    /// which is also the highest number on any chain with monotonic timestamps.
+    pub fn max_stored_block(&self, chain_id: i32) -> Result<Option<i64>, AppError> {
+        let c = chain_id as u32;
+        let latest = match self
+            .blocks
+            .range(encode_block_key(c, 0, 0)..=chain_end_key(c))
+            .next_back()
+        {
+            Some(guard) => Some(decode_block_key(&guard.key()?)?.2 as i64),
+            None => None,
+        };
+        let rejected = match self.rejected.prefix(c.to_be_bytes()).next_back() {
+            Some(guard) => Some(decode_rejected_number(&guard.key()?)?),
+            None => None,
+        };
+        Ok(latest.max(rejected))
+    }
+
+    /// Lists quarantined blocks for a chain in block-number order, starting at
+    /// `from_number` and up to `limit` entries.
+    pub fn list_rejected(
+        &self,
+        chain_id: i32,
+        from_number: i64,
+        limit: usize,
+    ) -> Result<Vec<RejectedBlock>, AppError> {
+        let c = chain_id as u32;
+        let lo = encode_rejected_key(c, from_number as u64);
+        let hi = encode_rejected_key(c, u64::MAX);
+        let mut results = Vec::new();
+        for guard in self.rejected.range(lo..=hi).take(limit) {
+            let (key, value) = guard.into_inner()?;
+            results.push(decode_rejected(&key, &value)?);
+        }
+        Ok(results)
+    }
+
+    /// Moves quarantined blocks into the serving index. `numbers = None` accepts all of
+    /// the chain's quarantined blocks. Returns how many were moved.
+    pub fn accept_rejected(&self, chain_id: i32, numbers: Option<&[i64]>) -> Result<u64, AppError> {
+        let _timed = self.metrics.writes.time();
+        let c = chain_id as u32;
+        let entries = self.select_rejected(chain_id, numbers)?;
+        for entry in &entries {
+            // insert before removing so a crash in between leaves the block in both
+            // places (harmless) rather than neither
+            self.blocks.insert(
+                encode_block_key(c, entry.timestamp as u64, entry.number as u64),
+                [],
+            )?;
+            self.rejected
+                .remove(encode_rejected_key(c, entry.number as u64))?;
+        }
+        Ok(entries.len() as u64)
+    }
+
+    /// Deletes quarantined blocks without indexing them. `numbers = None` purges all of
+    /// the chain's quarantined blocks. Returns how many were removed.
+    pub fn purge_rejected(&self, chain_id: i32, numbers: Option<&[i64]>) -> Result<u64, AppError> {
+        let _timed = self.metrics.writes.time();
+        let c = chain_id as u32;
+        let entries = self.select_rejected(chain_id, numbers)?;
+        for entry in &entries {
+            self.rejected
+                .remove(encode_rejected_key(c, entry.number as u64))?;
+        }
+        Ok(entries.len() as u64)
+    }
+
+    /// Resolves a selection of quarantined blocks: all of them, or the listed numbers
+    /// that are actually quarantined.
+    fn select_rejected(
+        &self,
+        chain_id: i32,
+        numbers: Option<&[i64]>,
+    ) -> Result<Vec<RejectedBlock>, AppError> {
+        let Some(numbers) = numbers else {
+            return self.list_rejected(chain_id, 0, usize::MAX);
+        };
+        let mut results = Vec::with_capacity(numbers.len());
+        for &n in numbers {
+            let key = encode_rejected_key(chain_id as u32, n as u64);
+            if let Some(value) = self.rejected.get(key)? {
+                results.push(decode_rejected(&key, &value)?);
+            }
+        }
+        Ok(results)
+    }
+
     /// Returns the last ingested block number for a chain, or 0 if no cursor exists.
-    pub fn get_cursor(&self, sqd_slug: &str) -> Result<i64, AppError> {
-        match self.cursors.get(sqd_slug)? {
-            Some(val) => Ok(decode_cursor_value(&val).0),
+    pub fn get_cursor(&self, chain_id: i32) -> Result<i64, AppError> {
+        match self.cursors.get(encode_cursor_key(chain_id))? {
+            Some(val) => Ok(decode_cursor_value(&val)?.0),
             None => Ok(0),
         }
     }
 
     /// Upserts the ingestion cursor for a chain.
-    pub fn upsert_cursor(&self, sqd_slug: &str, last_block: i64) -> Result<(), AppError> {
+    pub fn upsert_cursor(&self, chain_id: i32, last_block: i64) -> Result<(), AppError> {
+        let _timed = self.metrics.writes.time();
+        if self.inject(0)?.is_some() {
+            let e = fjall::Error::Io(std::io::Error::other("injected cursor write failure"));
+            return Err(e.into());
+        }
         self.cursors.insert(
-            sqd_slug,
-            encode_cursor_value(last_block, Utc::now().timestamp()),
+            encode_cursor_key(chain_id),
+            encode_cursor_value(last_block, self.clock.now().timestamp()),
         )?;
         Ok(())
     }
 
-    /// Returns all cursors as `(sqd_slug, last_block, updated_at)`.
-    pub fn get_all_cursors(&self) -> Result<Vec<(String, i64, DateTime<Utc>)>, AppError> {
+    /// Returns all cursors as `(chain_id, last_block, updated_at)`.
+    pub fn get_all_cursors(&self) -> Result<Vec<(i32, i64, DateTime<Utc>)>, AppError> {
         let mut results = Vec::new();
         for guard in self.cursors.iter() {
             let (key, value) = guard.into_inner()?;
-            let (last_block, updated_at_secs) = decode_cursor_value(&value);
+            // unmigrated slug keys (see `migrate_slug_cursors`)
+            let Some(chain_id) = decode_cursor_key(&key) else {
+                continue;
+            };
+            let (last_block, updated_at_secs) = decode_cursor_value(&value)?;
             if let Some(dt) = DateTime::from_timestamp(updated_at_secs, 0) {
-                results.push((
-                    String::from_utf8(key.to_vec()).unwrap_or_default(),
-                    last_block,
-                    dt,
-                ));
+                results.push((chain_id, last_block, dt));
+            }
+        }
+        Ok(results)
+    }
+
+    /// Records timestamp anomalies found while ingesting a chain. Re-detecting a block
+    /// overwrites its entry.
+    pub fn insert_anomalies(
+        &self,
+        chain_id: i32,
+        anomalies: &[TimestampAnomaly],
+    ) -> Result<(), AppError> {
+        let _timed = self.metrics.writes.time();
+        let c = chain_id as u32;
+        let now = self.clock.now().timestamp();
+        let mut batch = self.db.batch();
+        for anomaly in anomalies {
+            batch.insert(
+                &self.anomalies,
+                encode_rejected_key(c, anomaly.number as u64),
+                encode_anomaly_value(anomaly, now),
+            );
+        }
+        batch.commit()?;
+        Ok(())
+    }
+
+    /// The chain's most recent `limit` anomalies, highest block number first.
+    pub fn list_anomalies(
+        &self,
+        chain_id: i32,
+        limit: usize,
+    ) -> Result<Vec<RecordedAnomaly>, AppError> {
+        let mut results = Vec::new();
+        for guard in self
+            .anomalies
+            .prefix((chain_id as u32).to_be_bytes())
+            .rev()
+            .take(limit)
+        {
+            let (key, value) = guard.into_inner()?;
+            results.push(decode_anomaly(&key, &value)?);
+        }
+        Ok(results)
+    }
+
+    /// Appends an ingestion failure to the chain's error log, then drops all but its
+    /// newest `keep` entries.
+    pub fn record_ingest_error(
+        &self,
+        chain_id: i32,
+        code: &str,
+        message: &str,
+        keep: usize,
+    ) -> Result<(), AppError> {
+        let _timed = self.metrics.writes.time();
+        let c = chain_id as u32;
+        let at_us = self.clock.now().timestamp_micros().max(0) as u64;
+        self.errors.insert(
+            encode_history_key(c, at_us),
+            encode_error_value(code, message),
+        )?;
+        let mut batch = self.db.batch();
+        let mut removed = 0;
+        for guard in self.errors.prefix(c.to_be_bytes()).rev().skip(keep) {
+            batch.remove(&self.errors, guard.key()?);
+            removed += 1;
+        }
+        if removed > 0 {
+            batch.commit()?;
+        }
+        Ok(())
+    }
+
+    /// Logged ingestion failures at or after `since`, newest first: the chain's, or
+    /// every chain's with `chain_id` unset. At most `limit`.
+    pub fn list_ingest_errors(
+        &self,
+        chain_id: Option<i32>,
+        since: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<IngestErrorEntry>, AppError> {
+        let since_us = since.timestamp_micros().max(0) as u64;
+        let mut results = Vec::new();
+        match chain_id {
+            Some(chain_id) => {
+                let c = chain_id as u32;
+                let range = encode_history_key(c, since_us)..=encode_history_key(c, u64::MAX);
+                for guard in self.errors.range(range).rev().take(limit) {
+                    let (key, value) = guard.into_inner()?;
+                    results.push(decode_error_entry(&key, &value)?);
+                }
+            }
+            None => {
+                // each chain keeps a bounded log, so a full scan stays small
+                for guard in self.errors.iter() {
+                    let (key, value) = guard.into_inner()?;
+                    let entry = decode_error_entry(&key, &value)?;
+                    if entry.at >= since {
+                        results.push(entry);
+                    }
+                }
+                results.sort_by_key(|e| std::cmp::Reverse(e.at));
+                results.truncate(limit);
+            }
+        }
+        Ok(results)
+    }
+
+    /// Records every chain's cursor as of `recorded_at` (Unix seconds) in the cursor
+    /// history, in one batch.
+    pub fn record_cursor_history(
+        &self,
+        recorded_at: i64,
+        cursors: &[(i32, i64)],
+    ) -> Result<(), AppError> {
+        let _timed = self.metrics.writes.time();
+        let mut batch = self.db.batch();
+        for &(chain_id, last_block) in cursors {
+            batch.insert(
+                &self.cursor_history,
+                encode_history_key(chain_id as u32, recorded_at as u64),
+                last_block.to_be_bytes(),
+            );
+        }
+        batch.commit()?;
+        Ok(())
+    }
+
+    /// The latest cursor history entry for a chain recorded at or before `at`, as
+    /// `(recorded_at, last_block)`.
+    pub fn cursor_at(&self, chain_id: i32, at: i64) -> Result<Option<(i64, i64)>, AppError> {
+        if at < 0 {
+            return Ok(None);
+        }
+        let c = chain_id as u32;
+        let lo = encode_history_key(c, 0);
+        let hi = encode_history_key(c, at as u64);
+        match self.cursor_history.range(lo..=hi).next_back() {
+            Some(guard) => {
+                let (key, value) = guard.into_inner()?;
+                Ok(Some(decode_history_entry(&key, &value)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Deletes cursor history entries recorded before `before` (Unix seconds) on every
+    /// chain. Returns how many were removed.
+    pub fn prune_cursor_history(&self, before: i64) -> Result<u64, AppError> {
+        let _timed = self.metrics.writes.time();
+        let mut batch = self.db.batch();
+        let mut removed = 0;
+        for guard in self.cursor_history.iter() {
+            let (key, value) = guard.into_inner()?;
+            let (recorded_at, _) = decode_history_entry(&key, &value)?;
+            if recorded_at < before {
+                batch.remove(&self.cursor_history, key);
+                removed += 1;
+            }
+        }
+        if removed > 0 {
+            batch.commit()?;
+        }
+        Ok(removed)
+    }
+
+    /// Records an ingestion cycle's summary, keyed by its start time.
+    pub fn record_cycle(&self, cycle: &CycleSummary) -> Result<(), AppError> {
+        let _timed = self.metrics.writes.time();
+        let started_ms = cycle.started_at.timestamp_millis().max(0) as u64;
+        self.cycles
+            .insert(started_ms.to_be_bytes(), encode_cycle_value(cycle))?;
+        Ok(())
+    }
+
+    /// Cycle summaries that started in `[from, to)`, oldest first.
+    pub fn cycles_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<CycleSummary>, AppError> {
+        let lo = from.timestamp_millis().max(0) as u64;
+        let hi = to.timestamp_millis().max(0) as u64;
+        if lo >= hi {
+            return Ok(Vec::new());
+        }
+        let mut results = Vec::new();
+        for guard in self.cycles.range(lo.to_be_bytes()..hi.to_be_bytes()) {
+            let (key, value) = guard.into_inner()?;
+            results.push(decode_cycle(&key, &value)?);
+        }
+        Ok(results)
+    }
+
+    /// Deletes cycle summaries that started before `before`. Returns how many were
+    /// removed.
+    pub fn prune_cycles(&self, before: DateTime<Utc>) -> Result<u64, AppError> {
+        let _timed = self.metrics.writes.time();
+        let hi = before.timestamp_millis().max(0) as u64;
+        let mut batch = self.db.batch();
+        let mut removed = 0;
+        for guard in self.cycles.range(..hi.to_be_bytes()) {
+            batch.remove(&self.cycles, guard.key()?);
+            removed += 1;
+        }
+        if removed > 0 {
+            batch.commit()?;
+        }
+        Ok(removed)
+    }
+
+    /// Sets the bytes downloaded from SQD on `day` (UTC) for each chain, replacing the
+    /// totals recorded earlier that day.
+    pub fn record_egress(&self, day: NaiveDate, bytes: &[(i32, u64)]) -> Result<(), AppError> {
+        let _timed = self.metrics.writes.time();
+        let mut batch = self.db.batch();
+        for &(chain_id, total) in bytes {
+            batch.insert(
+                &self.egress,
+                encode_egress_key(day, chain_id),
+                total.to_be_bytes(),
+            );
+        }
+        batch.commit()?;
+        Ok(())
+    }
+
+    /// Bytes downloaded from SQD on `day` (UTC) per chain, as last recorded.
+    pub fn egress_on(&self, day: NaiveDate) -> Result<Vec<(i32, u64)>, AppError> {
+        let prefix = (day.num_days_from_ce() as u32).to_be_bytes();
+        let mut results = Vec::new();
+        for guard in self.egress.prefix(prefix) {
+            let (key, value) = guard.into_inner()?;
+            let (Some(chain_id), Ok(total)) = (
+                key.get(CHAIN_ID_LEN..).and_then(decode_cursor_key),
+                <[u8; 8]>::try_from(&value[..]),
+            ) else {
+                return Err(AppError::CorruptData("invalid egress entry".into()));
+            };
+            results.push((chain_id, u64::from_be_bytes(total)));
+        }
+        Ok(results)
+    }
+
+    /// Queues `from_block..=to_block` of `chain_id` for the `work_queue` job.
+    pub fn enqueue_work(
+        &self,
+        chain_id: i32,
+        from_block: i64,
+        to_block: i64,
+        priority: u8,
+        kind: WorkKind,
+    ) -> Result<WorkItem, AppError> {
+        let _timed = self.metrics.writes.time();
+        let now = self.clock.now();
+        let item = WorkItem {
+            id: self.next_work_id.fetch_add(1, Ordering::Relaxed),
+            priority,
+            kind,
+            chain_id,
+            from_block,
+            to_block,
+            attempts: 0,
+            enqueued_at: now,
+            not_before: now,
+            last_error: None,
+        };
+        self.update_work(&item)?;
+        Ok(item)
+    }
+
+    /// Writes back a queued item after a run, keeping its place in the queue.
+    pub fn update_work(&self, item: &WorkItem) -> Result<(), AppError> {
+        let _timed = self.metrics.writes.time();
+        self.work_queue.insert(
+            encode_work_key(item.priority, item.id),
+            encode_work_value(item),
+        )?;
+        Ok(())
+    }
+
+    /// Every queued item in the order they will run: highest priority first, then
+    /// oldest first.
+    pub fn work_items(&self) -> Result<Vec<WorkItem>, AppError> {
+        let mut items = Vec::new();
+        for guard in self.work_queue.iter() {
+            let (key, value) = guard.into_inner()?;
+            items.push(decode_work(&key, &value)?);
+        }
+        Ok(items)
+    }
+
+    /// The first item in queue order that is due at `now` and has failed fewer than
+    /// `max_attempts` times in a row.
+    pub fn next_work(
+        &self,
+        now: DateTime<Utc>,
+        max_attempts: u32,
+    ) -> Result<Option<WorkItem>, AppError> {
+        for guard in self.work_queue.iter() {
+            let (key, value) = guard.into_inner()?;
+            let item = decode_work(&key, &value)?;
+            if item.not_before <= now && item.attempts < max_attempts {
+                return Ok(Some(item));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Removes a queued item by id. Returns it if it was queued.
+    pub fn remove_work(&self, id: u64) -> Result<Option<WorkItem>, AppError> {
+        let _timed = self.metrics.writes.time();
+        for guard in self.work_queue.iter() {
+            let (key, value) = guard.into_inner()?;
+            let item = decode_work(&key, &value)?;
+            if item.id == id {
+                self.work_queue.remove(key)?;
+                return Ok(Some(item));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Operation counts of every handle to this database, by role.
+    pub fn metrics(&self) -> &StorageMetrics {
+        &self.metrics
+    }
+
+    /// Current compaction and flush debt. Cheap enough to call before every write.
+    pub fn write_pressure(&self) -> WritePressure {
+        WritePressure {
+            l0_tables: self.blocks.l0_table_count(),
+            pending_flushes: self.db.outstanding_flushes(),
+        }
+    }
+
+    /// Flushes all data to disk for guaranteed durability.
+    pub fn persist(&self) -> Result<(), AppError> {
+        let _timed = self.metrics.writes.time();
+        self.db.persist(PersistMode::SyncAll)?;
+        Ok(())
+    }
+
+    fn keyspaces(&self) -> [(&'static str, &Keyspace); 9] {
+        [
+            ("blocks", &self.blocks),
+            ("cursors", &self.cursors),
+            ("rejected", &self.rejected),
+            ("cursor_history", &self.cursor_history),
+            ("anomalies", &self.anomalies),
+            ("cycles", &self.cycles),
+            ("work_queue", &self.work_queue),
+            ("errors", &self.errors),
+            ("egress", &self.egress),
+        ]
+    }
+
+    /// Copies the database as it is now into a new one at `dest`, while ingestion and
+    /// lookups carry on. Every keyspace is read from one fjall snapshot, so the copy is
+    /// consistent across them (no cursor ahead of its blocks) however long it takes;
+    /// writes made meanwhile just aren't in it. The result is an ordinary database
+    /// that [`Storage::open`] restores from.
+    ///
+    /// With `max_bytes_per_sec`, copying pauses between batches to stay under that
+    /// rate, so the backup doesn't take the disk from ingestion and lookups. Blocks
+    /// the calling thread throughout. `dest` must not exist yet.
+    pub fn backup_to(
+        &self,
+        dest: &Path,
+        max_bytes_per_sec: Option<u64>,
+    ) -> Result<BackupStats, AppError> {
+        if dest.exists() {
+            let message = format!("backup destination {} already exists", dest.display());
+            return Err(fjall::Error::Io(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                message,
+            ))
+            .into());
+        }
+        let snapshot = self.db.snapshot();
+        let backup = Database::builder(dest).open()?;
+        let started = Instant::now();
+        let mut stats = BackupStats::default();
+        for (name, source) in self.keyspaces() {
+            let target = backup.keyspace(name, KeyspaceCreateOptions::default)?;
+            let mut batch = backup.batch();
+            let mut batch_bytes = 0;
+            for guard in snapshot.iter(source) {
+                let (key, value) = guard.into_inner()?;
+                let bytes = (key.len() + value.len()) as u64;
+                batch.insert(&target, key, value);
+                stats.entries += 1;
+                stats.bytes += bytes;
+                batch_bytes += bytes;
+                if batch_bytes >= BACKUP_BATCH_BYTES {
+                    batch.commit()?;
+                    batch = backup.batch();
+                    batch_bytes = 0;
+                    if let Some(rate) = max_bytes_per_sec.filter(|r| *r > 0) {
+                        let due = Duration::from_secs_f64(stats.bytes as f64 / rate as f64);
+                        if let Some(wait) = due.checked_sub(started.elapsed()) {
+                            std::thread::sleep(wait);
+                        }
+                    }
+                }
             }
+            batch.commit()?;
         }
-        Ok(results)
-    }
-
-    /// Flushes all data to disk for guaranteed durability.
-    pub fn persist(&self) -> Result<(), AppError> {
-        self.db.persist(PersistMode::SyncAll)?;
-        Ok(())
+        backup.persist(PersistMode::SyncAll)?;
+        Ok(stats)
     }
 }
 
@@ -224,15 +1729,41 @@ mod tests {
         (storage, dir)
     }
 
+    #[test]
+    fn write_pressure_is_relative_to_stall_thresholds() {
+        assert_eq!(WritePressure::default().level(), 0.0);
+        let pressure = WritePressure {
+            l0_tables: 10,
+            pending_flushes: 4,
+        };
+        assert_eq!(pressure.level(), 1.0);
+
+        let (storage, _dir) = test_storage();
+        storage.insert_blocks(1, &[1, 2], &[100, 112]).unwrap();
+        assert!(storage.write_pressure().level() < 1.0);
+    }
+
     #[test]
     fn encode_decode_block_key_roundtrip() {
         let key = encode_block_key(1, 1000, 42);
-        let (chain_id, ts, num) = decode_block_key(&key);
+        let (chain_id, ts, num) = decode_block_key(&key).unwrap();
         assert_eq!(chain_id, 1);
         assert_eq!(ts, 1000);
         assert_eq!(num, 42);
     }
 
+    #[test]
+    fn decode_rejects_truncated_values() {
+        assert_eq!(
+            decode_block_key(&[0u8; 7]).unwrap_err().code(),
+            "DATA_CORRUPTED"
+        );
+        assert_eq!(
+            decode_cursor_value(&[0u8; 8]).unwrap_err().code(),
+            "DATA_CORRUPTED"
+        );
+    }
+
     #[test]
     fn block_key_lexicographic_ordering() {
         let k1 = encode_block_key(1, 100, 5);
@@ -245,7 +1776,7 @@ mod tests {
     #[test]
     fn encode_decode_cursor_value_roundtrip() {
         let val = encode_cursor_value(12345, 1700000000);
-        let (block, ts) = decode_cursor_value(&val);
+        let (block, ts) = decode_cursor_value(&val).unwrap();
         assert_eq!(block, 12345);
         assert_eq!(ts, 1700000000);
     }
@@ -257,7 +1788,7 @@ mod tests {
             .insert_blocks(1, &[100, 101, 102], &[1000, 2000, 3000])
             .unwrap();
 
-        let result = storage.find_block(1, 2000, "before", true).unwrap();
+        let result = storage.find_block(1, 2000, Bound::AtOrBefore).unwrap();
         assert_eq!(result, Some((101, 2000)));
     }
 
@@ -268,7 +1799,7 @@ mod tests {
             .insert_blocks(1, &[100, 101, 102], &[1000, 2000, 3000])
             .unwrap();
 
-        let result = storage.find_block(1, 2000, "before", false).unwrap();
+        let result = storage.find_block(1, 2000, Bound::Before).unwrap();
         assert_eq!(result, Some((100, 1000)));
     }
 
@@ -279,7 +1810,7 @@ mod tests {
             .insert_blocks(1, &[100, 101, 102], &[1000, 2000, 3000])
             .unwrap();
 
-        let result = storage.find_block(1, 2000, "after", true).unwrap();
+        let result = storage.find_block(1, 2000, Bound::AtOrAfter).unwrap();
         assert_eq!(result, Some((101, 2000)));
     }
 
@@ -290,18 +1821,220 @@ mod tests {
             .insert_blocks(1, &[100, 101, 102], &[1000, 2000, 3000])
             .unwrap();
 
-        let result = storage.find_block(1, 2000, "after", false).unwrap();
+        let result = storage.find_block(1, 2000, Bound::After).unwrap();
         assert_eq!(result, Some((102, 3000)));
     }
 
+    #[test]
+    fn find_blocks_multi_matches_single_lookups() {
+        let (storage, _dir) = test_storage();
+        // includes repeated timestamps and a large gap that splits the scan
+        let numbers = [100, 101, 102, 103, 104, 105];
+        let timestamps = [1000, 2000, 2000, 3000, 100_000, 100_012];
+        storage.insert_blocks(1, &numbers, &timestamps).unwrap();
+        storage.insert_blocks(2, &[1], &[2500]).unwrap();
+
+        let mut queries = Vec::new();
+        for ts in [
+            0, 999, 1000, 1500, 2000, 2001, 3000, 50_000, 100_000, 100_012, 200_000,
+        ] {
+            for bound in Bound::ALL {
+                queries.push((ts, bound));
+            }
+        }
+        // shuffle-ish: reverse so input order isn't sorted
+        queries.reverse();
+
+        let multi = storage.find_blocks_multi(1, &queries).unwrap();
+        for (q, got) in queries.iter().zip(&multi) {
+            let expected = storage.find_block(1, q.0, q.1).unwrap();
+            assert_eq!(*got, expected, "query {q:?}");
+        }
+    }
+
+    #[test]
+    fn find_blocks_multi_empty_chain() {
+        let (storage, _dir) = test_storage();
+        let results = storage
+            .find_blocks_multi(1, &[(1000, Bound::AtOrBefore)])
+            .unwrap();
+        assert_eq!(results, vec![None]);
+    }
+
     #[test]
     fn find_block_returns_none_when_no_match() {
         let (storage, _dir) = test_storage();
 
-        let result = storage.find_block(1, 5000, "before", true).unwrap();
+        let result = storage.find_block(1, 5000, Bound::AtOrBefore).unwrap();
         assert_eq!(result, None);
     }
 
+    #[test]
+    fn scan_blocks_pages_through_range() {
+        let (storage, _dir) = test_storage();
+        storage
+            .insert_blocks(1, &[10, 11, 12, 13, 14], &[100, 100, 101, 102, 103])
+            .unwrap();
+        storage.insert_blocks(2, &[1], &[101]).unwrap();
+
+        let first = storage.scan_blocks(1, (100, 0), 103, 2).unwrap();
+        assert_eq!(first, vec![(10, 100), (11, 100)]);
+
+        // resume after the last row, which shares its timestamp with the previous one
+        let rest = storage.scan_blocks(1, (100, 12), 103, 10).unwrap();
+        assert_eq!(rest, vec![(12, 101), (13, 102)]);
+
+        assert!(storage
+            .scan_blocks(1, (103, 0), 103, 10)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn find_block_by_number_seeks_within_window() {
+        let (storage, _dir) = test_storage();
+        storage
+            .insert_blocks(
+                1,
+                &[10, 11, 12, 14, 15, 16],
+                &[100, 100, 100, 250, 9_000, 1_000_000],
+            )
+            .unwrap();
+        storage.insert_blocks(2, &[13], &[200]).unwrap();
+
+        let find = |number, from_ts, to_ts| {
+            storage
+                .find_block_by_number(1, number, from_ts, to_ts)
+                .unwrap()
+        };
+        assert_eq!(find(11, 0, i64::MAX), Some((11, 100)));
+        // 13 is missing on chain 1 (stored on chain 2): the next block up
+        assert_eq!(find(13, 0, i64::MAX), Some((14, 250)));
+        assert_eq!(find(0, 0, i64::MAX), Some((10, 100)));
+        assert_eq!(find(16, 0, i64::MAX), Some((16, 1_000_000)));
+        assert_eq!(find(17, 0, i64::MAX), None);
+        // the window bounds the answer on both sides
+        assert_eq!(find(10, 200, 10_000), Some((14, 250)));
+        assert_eq!(find(16, 200, 10_000), None);
+        assert_eq!(find(10, 100, 100), None);
+    }
+
+    #[test]
+    fn ties_resolve_to_the_lowest_or_highest_block() {
+        let (storage, _dir) = test_storage();
+        storage
+            .insert_blocks(1, &[10, 11, 12, 13], &[100, 101, 101, 101])
+            .unwrap();
+
+        // without a tie-break the key order gives the block nearest the query
+        let before = storage.find_block(1, 101, Bound::AtOrBefore).unwrap();
+        assert_eq!(before, Some((13, 101)));
+        let after = storage.find_block(1, 101, Bound::AtOrAfter).unwrap();
+        assert_eq!(after, Some((11, 101)));
+
+        assert_eq!(storage.tied_block(1, 101, Tie::Low).unwrap(), Some(11));
+        assert_eq!(storage.tied_block(1, 101, Tie::High).unwrap(), Some(13));
+        assert_eq!(storage.tied_block(1, 100, Tie::High).unwrap(), Some(10));
+        assert_eq!(storage.tied_block(1, 102, Tie::Low).unwrap(), None);
+        assert_eq!(storage.tied_block(2, 101, Tie::Low).unwrap(), None);
+    }
+
+    #[test]
+    fn equal_timestamps_break_ties_the_same_way_everywhere() {
+        let (storage, _dir) = test_storage();
+        // four blocks a second, as on Arbitrum: 100..=103 at 1000, 104..=107 at 1001
+        let numbers: Vec<i64> = (100..108).collect();
+        let timestamps: Vec<i64> = numbers.iter().map(|n| 1000 + (n - 100) / 4).collect();
+        storage.insert_blocks(42161, &numbers, &timestamps).unwrap();
+
+        let cases = [
+            (1000, Bound::AtOrBefore, (103, 1000), (100, 1000)),
+            (1001, Bound::Before, (103, 1000), (100, 1000)),
+            (1001, Bound::AtOrBefore, (107, 1001), (104, 1001)),
+            (1000, Bound::AtOrAfter, (100, 1000), (103, 1000)),
+            (1000, Bound::After, (104, 1001), (107, 1001)),
+            (999, Bound::After, (100, 1000), (103, 1000)),
+        ];
+        for (ts, bound, natural, other) in cases {
+            let case = format!("{bound} {ts}");
+            let found = storage.find_block(42161, ts, bound).unwrap();
+            assert_eq!(found, Some(natural), "{case}");
+            let near = storage.find_blocks_near(42161, ts, bound, 1).unwrap();
+            assert_eq!(near, [natural], "{case}");
+            let multi = storage.find_blocks_multi(42161, &[(ts, bound)]).unwrap();
+            assert_eq!(multi, [Some(natural)], "{case}");
+
+            let tied = |tie| storage.find_block_tied(42161, ts, bound, tie).unwrap();
+            let direction = bound.direction();
+            assert_eq!(tied(Tie::natural(direction)), Some(natural), "{case}");
+            let opposite = match Tie::natural(direction) {
+                Tie::Low => Tie::High,
+                Tie::High => Tie::Low,
+            };
+            assert_eq!(tied(opposite), Some(other), "{case}");
+        }
+
+        // no block to tie with stays no block
+        let none = storage.find_block_tied(42161, 1002, Bound::AtOrAfter, Tie::High);
+        assert_eq!(none.unwrap(), None);
+    }
+
+    #[test]
+    fn find_blocks_near_reads_closest_first() {
+        let (storage, _dir) = test_storage();
+        storage
+            .insert_blocks(1, &[10, 11, 12, 13, 14], &[100, 100, 110, 120, 130])
+            .unwrap();
+        storage.insert_blocks(2, &[99], &[105]).unwrap();
+
+        let near = |ts, bound, limit| storage.find_blocks_near(1, ts, bound, limit).unwrap();
+        assert_eq!(
+            near(110, Bound::AtOrBefore, 3),
+            vec![(12, 110), (11, 100), (10, 100)]
+        );
+        assert_eq!(near(110, Bound::Before, 5), vec![(11, 100), (10, 100)]);
+        assert_eq!(near(110, Bound::After, 2), vec![(13, 120), (14, 130)]);
+        assert_eq!(near(131, Bound::AtOrAfter, 2), vec![]);
+        assert_eq!(near(0, Bound::Before, 2), vec![]);
+        // the first entry always agrees with find_block
+        for ts in [95, 100, 105, 130] {
+            for bound in [Bound::AtOrBefore, Bound::AtOrAfter] {
+                assert_eq!(
+                    near(ts, bound, 4).first().copied(),
+                    storage.find_block(1, ts, bound).unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn recent_block_time_averages_the_tail() {
+        let (storage, _dir) = test_storage();
+        assert_eq!(storage.recent_block_time(1, 100).unwrap(), None);
+        // 15s blocks early on, 12s blocks for the last stretch
+        let numbers: Vec<i64> = (0..300).collect();
+        let timestamps: Vec<i64> = numbers
+            .iter()
+            .map(|&n| {
+                if n < 200 {
+                    n * 15
+                } else {
+                    3_000 + (n - 200) * 12
+                }
+            })
+            .collect();
+        storage.insert_blocks(1, &numbers, &timestamps).unwrap();
+
+        assert_eq!(storage.recent_block_time(1, 50).unwrap(), Some(12.0));
+        // a span past genesis averages over everything stored
+        assert_eq!(
+            storage.recent_block_time(1, 1_000).unwrap(),
+            Some(4_188.0 / 299.0)
+        );
+        storage.insert_blocks(2, &[5], &[100]).unwrap();
+        assert_eq!(storage.recent_block_time(2, 50).unwrap(), None);
+    }
+
     #[test]
     fn insert_blocks_is_idempotent() {
         let (storage, _dir) = test_storage();
@@ -320,9 +2053,9 @@ mod tests {
     #[test]
     fn cursor_round_trip() {
         let (storage, _dir) = test_storage();
-        storage.upsert_cursor("ethereum-mainnet", 42).unwrap();
+        storage.upsert_cursor(1, 42).unwrap();
 
-        let value = storage.get_cursor("ethereum-mainnet").unwrap();
+        let value = storage.get_cursor(1).unwrap();
         assert_eq!(value, 42);
     }
 
@@ -330,34 +2063,268 @@ mod tests {
     fn cursor_defaults_to_zero() {
         let (storage, _dir) = test_storage();
 
-        let value = storage.get_cursor("nonexistent-slug").unwrap();
+        let value = storage.get_cursor(999999).unwrap();
         assert_eq!(value, 0);
     }
 
     #[test]
     fn cursor_upsert_updates_existing() {
         let (storage, _dir) = test_storage();
-        storage.upsert_cursor("ethereum-mainnet", 100).unwrap();
-        storage.upsert_cursor("ethereum-mainnet", 200).unwrap();
+        storage.upsert_cursor(1, 100).unwrap();
+        storage.upsert_cursor(1, 200).unwrap();
 
-        let value = storage.get_cursor("ethereum-mainnet").unwrap();
+        let value = storage.get_cursor(1).unwrap();
         assert_eq!(value, 200);
     }
 
     #[test]
     fn get_all_cursors_returns_all() {
         let (storage, _dir) = test_storage();
-        storage.upsert_cursor("ethereum-mainnet", 100).unwrap();
-        storage.upsert_cursor("base-mainnet", 200).unwrap();
+        storage.upsert_cursor(1, 100).unwrap();
+        storage.upsert_cursor(8453, 200).unwrap();
 
         let mut cursors = storage.get_all_cursors().unwrap();
-        cursors.sort_by(|a, b| a.0.cmp(&b.0));
+        cursors.sort_by_key(|c| c.0);
 
         assert_eq!(cursors.len(), 2);
-        assert_eq!(cursors[0].0, "base-mainnet");
-        assert_eq!(cursors[0].1, 200);
-        assert_eq!(cursors[1].0, "ethereum-mainnet");
-        assert_eq!(cursors[1].1, 100);
+        assert_eq!(cursors[0].0, 1);
+        assert_eq!(cursors[0].1, 100);
+        assert_eq!(cursors[1].0, 8453);
+        assert_eq!(cursors[1].1, 200);
+    }
+
+    #[test]
+    fn cursor_history_answers_as_of_a_time() {
+        let (storage, _dir) = test_storage();
+        storage
+            .record_cursor_history(1_000, &[(1, 100), (8453, 5)])
+            .unwrap();
+        storage
+            .record_cursor_history(2_000, &[(1, 180), (8453, 9)])
+            .unwrap();
+
+        assert_eq!(storage.cursor_at(1, 999).unwrap(), None);
+        assert_eq!(storage.cursor_at(1, 1_000).unwrap(), Some((1_000, 100)));
+        assert_eq!(storage.cursor_at(1, 1_999).unwrap(), Some((1_000, 100)));
+        assert_eq!(storage.cursor_at(8453, 5_000).unwrap(), Some((2_000, 9)));
+        assert_eq!(storage.cursor_at(10, 5_000).unwrap(), None);
+
+        assert_eq!(storage.prune_cursor_history(1_500).unwrap(), 2);
+        assert_eq!(storage.cursor_at(1, 1_999).unwrap(), None);
+        assert_eq!(storage.cursor_at(1, 2_000).unwrap(), Some((2_000, 180)));
+    }
+
+    #[test]
+    fn cycles_round_trip_and_prune_by_start_time() {
+        let (storage, _dir) = test_storage();
+        let cycle = |ms, chain_errors| CycleSummary {
+            started_at: DateTime::from_timestamp_millis(ms).unwrap(),
+            duration_ms: 1_500,
+            interval_secs: 60,
+            chains_checked: 30,
+            chains_behind: 4,
+            chains_deferred: 1,
+            chain_errors,
+        };
+        for (ms, errors) in [(1_000_000, 0), (1_061_500, 2), (1_123_000, 0)] {
+            storage.record_cycle(&cycle(ms, errors)).unwrap();
+        }
+        let at = |ms| DateTime::from_timestamp_millis(ms).unwrap();
+
+        let cycles = storage
+            .cycles_between(at(1_000_001), at(2_000_000))
+            .unwrap();
+        assert_eq!(cycles, vec![cycle(1_061_500, 2), cycle(1_123_000, 0)]);
+        assert!(storage.cycles_between(at(5), at(5)).unwrap().is_empty());
+
+        assert_eq!(storage.prune_cycles(at(1_100_000)).unwrap(), 2);
+        let cycles = storage.cycles_between(at(0), at(2_000_000)).unwrap();
+        assert_eq!(cycles, vec![cycle(1_123_000, 0)]);
+    }
+
+    #[test]
+    fn records_are_stamped_by_the_storage_clock() {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let clock = Arc::new(clock::MockClock::new(start));
+        let (storage, _dir) = test_storage();
+        let storage = storage.with_clock(clock.clone());
+
+        storage.upsert_cursor(1, 10).unwrap();
+        clock.advance(Duration::from_secs(60));
+        let item = storage
+            .enqueue_work(1, 0, 5, 0, WorkKind::Backfill)
+            .unwrap();
+
+        assert_eq!(storage.get_all_cursors().unwrap(), vec![(1, 10, start)]);
+        assert_eq!(item.enqueued_at, start + chrono::Duration::seconds(60));
+        assert_eq!(item.not_before, item.enqueued_at);
+    }
+
+    #[test]
+    fn egress_totals_are_kept_per_day() {
+        let (storage, _dir) = test_storage();
+        let day = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+        let next = day.succ_opt().unwrap();
+        storage.record_egress(day, &[(1, 100), (8453, 50)]).unwrap();
+        storage.record_egress(day, &[(1, 300)]).unwrap();
+        storage.record_egress(next, &[(1, 7)]).unwrap();
+
+        assert_eq!(storage.egress_on(day).unwrap(), vec![(1, 300), (8453, 50)]);
+        assert_eq!(storage.egress_on(next).unwrap(), vec![(1, 7)]);
+        assert!(storage
+            .egress_on(day.pred_opt().unwrap())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn work_queue_orders_by_priority_and_survives_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        let low = storage
+            .enqueue_work(1, 100, 200, 0, WorkKind::Backfill)
+            .unwrap();
+        let high = storage.enqueue_work(10, 5, 9, 5, WorkKind::Repair).unwrap();
+        let low_again = storage
+            .enqueue_work(1, 300, 400, 0, WorkKind::Backfill)
+            .unwrap();
+        assert_eq!((low.id, high.id, low_again.id), (0, 1, 2));
+
+        let ids = |items: Vec<WorkItem>| items.iter().map(|i| i.id).collect::<Vec<_>>();
+        assert_eq!(ids(storage.work_items().unwrap()), vec![1, 0, 2]);
+
+        // a failing item backs off and stops being picked once out of attempts
+        let now = Utc::now();
+        let mut failed = high.clone();
+        failed.attempts = 1;
+        failed.not_before = now + chrono::Duration::seconds(30);
+        failed.last_error = Some("SQD API error: timeout".into());
+        storage.update_work(&failed).unwrap();
+        assert_eq!(storage.next_work(now, 3).unwrap().map(|i| i.id), Some(0));
+        let later = now + chrono::Duration::seconds(31);
+        assert_eq!(storage.next_work(later, 3).unwrap().map(|i| i.id), Some(1));
+        assert_eq!(storage.next_work(later, 1).unwrap().map(|i| i.id), Some(0));
+
+        drop(storage);
+        let storage = Storage::open(dir.path()).unwrap();
+        let items = storage.work_items().unwrap();
+        assert_eq!(items[0].last_error, failed.last_error);
+        assert_eq!(
+            items[0].not_before.timestamp_millis(),
+            failed.not_before.timestamp_millis()
+        );
+        // ids keep increasing across restarts
+        let next = storage
+            .enqueue_work(1, 1, 1, 0, WorkKind::Backfill)
+            .unwrap();
+        assert_eq!(next.id, 3);
+
+        assert_eq!(
+            storage.remove_work(0).unwrap().map(|i| i.from_block),
+            Some(100)
+        );
+        assert_eq!(storage.remove_work(0).unwrap(), None);
+        assert_eq!(ids(storage.work_items().unwrap()), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn anomalies_list_newest_first() {
+        let (storage, _dir) = test_storage();
+        let anomaly = |number, kind| TimestampAnomaly {
+            kind,
+            number,
+            timestamp: 1_000 + number,
+            prev_number: number - 1,
+            prev_timestamp: 2_000,
+        };
+        storage
+            .insert_anomalies(
+                1,
+                &[
+                    anomaly(10, AnomalyKind::Backwards),
+                    anomaly(30, AnomalyKind::Jump),
+                ],
+            )
+            .unwrap();
+        storage
+            .insert_anomalies(2, &[anomaly(20, AnomalyKind::Jump)])
+            .unwrap();
+
+        let listed = storage.list_anomalies(1, 10).unwrap();
+        let numbers: Vec<_> = listed.iter().map(|r| r.anomaly.number).collect();
+        assert_eq!(numbers, vec![30, 10]);
+        assert_eq!(listed[1].anomaly, anomaly(10, AnomalyKind::Backwards));
+        assert_eq!(storage.list_anomalies(1, 1).unwrap().len(), 1);
+        assert!(storage.list_anomalies(3, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn error_log_keeps_newest_entries_per_chain() {
+        let (storage, _dir) = test_storage();
+        let start = clock::now();
+        for i in 0..5 {
+            storage
+                .record_ingest_error(1, "SQD_API_ERROR", &format!("attempt {i}"), 3)
+                .unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+        storage
+            .record_ingest_error(2, "STORAGE_ERROR", "disk full", 3)
+            .unwrap();
+
+        let chain = storage.list_ingest_errors(Some(1), start, 10).unwrap();
+        let messages: Vec<_> = chain.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, vec!["attempt 4", "attempt 3", "attempt 2"]);
+        assert_eq!(chain[0].code, "SQD_API_ERROR");
+
+        let all = storage.list_ingest_errors(None, start, 2).unwrap();
+        assert_eq!(
+            (all[0].chain_id, all[0].code.as_str()),
+            (2, "STORAGE_ERROR")
+        );
+        assert_eq!(all[1].message, "attempt 4");
+
+        let later = storage.list_ingest_errors(None, chain[0].at, 10).unwrap();
+        assert_eq!(later.len(), 2);
+        assert!(storage
+            .list_ingest_errors(Some(1), clock::now() + chrono::Duration::seconds(1), 10)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn slug_cursors_migrate_to_chain_ids_on_open() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let storage = Storage::open(dir.path()).unwrap();
+            let legacy = |slug: &str, block| {
+                storage
+                    .cursors
+                    .insert(slug, encode_cursor_value(block, 1_700_000_000))
+                    .unwrap()
+            };
+            legacy("ethereum-mainnet", 100);
+            legacy("base-mainnet", 50);
+            legacy("retired-testnet", 7);
+            // base already has a newer chain id cursor, which must survive
+            storage.upsert_cursor(8453, 80).unwrap();
+            storage.persist().unwrap();
+        }
+
+        let storage = Storage::open(dir.path()).unwrap();
+        assert_eq!(storage.get_cursor(1).unwrap(), 100);
+        assert_eq!(storage.get_cursor(8453).unwrap(), 80);
+        assert!(storage.cursors.get("ethereum-mainnet").unwrap().is_none());
+        // the unknown slug stays put but isn't reported
+        assert!(storage.cursors.get("retired-testnet").unwrap().is_some());
+        let mut ids: Vec<_> = storage
+            .get_all_cursors()
+            .unwrap()
+            .into_iter()
+            .map(|c| c.0)
+            .collect();
+        ids.sort_unstable();
+        assert_eq!(ids, vec![1, 8453]);
     }
 
     #[test]
@@ -367,14 +2334,159 @@ mod tests {
         storage.insert_blocks(2, &[200], &[2000]).unwrap();
 
         assert_eq!(
-            storage.find_block(1, 5000, "before", true).unwrap(),
+            storage.find_block(1, 5000, Bound::AtOrBefore).unwrap(),
             Some((100, 1000))
         );
         assert_eq!(
-            storage.find_block(2, 5000, "before", true).unwrap(),
+            storage.find_block(2, 5000, Bound::AtOrBefore).unwrap(),
             Some((200, 2000))
         );
-        assert_eq!(storage.find_block(3, 5000, "before", true).unwrap(), None);
+        assert_eq!(
+            storage.find_block(3, 5000, Bound::AtOrBefore).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn rejected_blocks_are_counted_per_chain() {
+        let (storage, _dir) = test_storage();
+        let bad = [
+            (
+                BlockHeader {
+                    number: 5,
+                    timestamp: -1,
+                },
+                RejectReason::NegativeTimestamp,
+            ),
+            (
+                BlockHeader {
+                    number: 6,
+                    timestamp: 10,
+                },
+                RejectReason::BeforeGenesis,
+            ),
+        ];
+        storage.insert_rejected(1, &bad).unwrap();
+        storage.insert_rejected(1, &bad[..1]).unwrap();
+
+        assert_eq!(storage.rejected_count(1).unwrap(), 2);
+        assert_eq!(storage.rejected_count(2).unwrap(), 0);
+        // quarantined blocks never reach the serving index
+        assert_eq!(storage.find_block(1, 100, Bound::AtOrBefore).unwrap(), None);
+    }
+
+    #[test]
+    fn block_extent_spans_first_and_last_block() {
+        let (storage, _dir) = test_storage();
+        assert_eq!(storage.block_extent(1).unwrap(), None);
+        storage.insert_blocks(1, &[5], &[100]).unwrap();
+        let block = |number, timestamp| BlockRef { number, timestamp };
+        assert_eq!(
+            storage.block_extent(1).unwrap(),
+            Some((block(5, 100), block(5, 100)))
+        );
+        storage.insert_blocks(1, &[6, 7], &[112, 124]).unwrap();
+        storage.insert_blocks(2, &[1], &[50]).unwrap();
+        assert_eq!(
+            storage.block_extent(1).unwrap(),
+            Some((block(5, 100), block(7, 124)))
+        );
+    }
+
+    #[test]
+    fn max_stored_block_counts_quarantined_blocks() {
+        let (storage, _dir) = test_storage();
+        assert_eq!(storage.max_stored_block(1).unwrap(), None);
+
+        storage
+            .insert_blocks(1, &[1, 2, 3], &[100, 112, 124])
+            .unwrap();
+        storage.insert_blocks(2, &[50], &[100]).unwrap();
+        assert_eq!(storage.max_stored_block(1).unwrap(), Some(3));
+
+        let bad = [(
+            BlockHeader {
+                number: 4,
+                timestamp: -1,
+            },
+            RejectReason::NegativeTimestamp,
+        )];
+        storage.insert_rejected(1, &bad).unwrap();
+        assert_eq!(storage.max_stored_block(1).unwrap(), Some(4));
+        assert_eq!(storage.max_stored_block(2).unwrap(), Some(50));
+    }
+
+    #[test]
+    fn accept_and_purge_rejected() {
+        let (storage, _dir) = test_storage();
+        let bad: Vec<_> = (1..=3)
+            .map(|n| {
+                (
+                    BlockHeader {
+                        number: n,
+                        timestamp: n * 1000,
+                    },
+                    RejectReason::BeforeGenesis,
+                )
+            })
+            .collect();
+        storage.insert_rejected(1, &bad).unwrap();
+
+        let listed = storage.list_rejected(1, 0, 10).unwrap();
+        assert_eq!(
+            listed.iter().map(|r| r.number).collect::<Vec<_>>(),
+            [1, 2, 3]
+        );
+        let rest = storage.list_rejected(1, 2, 10).unwrap();
+        assert_eq!(rest.iter().map(|r| r.number).collect::<Vec<_>>(), [2, 3]);
+
+        assert_eq!(storage.accept_rejected(1, Some(&[2, 99])).unwrap(), 1);
+        assert_eq!(
+            storage.find_block(1, 2000, Bound::AtOrBefore).unwrap(),
+            Some((2, 2000))
+        );
+
+        assert_eq!(storage.purge_rejected(1, None).unwrap(), 2);
+        assert_eq!(storage.rejected_count(1).unwrap(), 0);
+        assert_eq!(
+            storage.find_block(1, 1000, Bound::AtOrBefore).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn rejected_value_round_trip() {
+        let key = encode_rejected_key(1, 42);
+        let val = encode_rejected_value(1000, 1700000000, RejectReason::InFuture);
+        let entry = decode_rejected(&key, &val).unwrap();
+        assert_eq!(entry.number, 42);
+        assert_eq!(entry.timestamp, 1000);
+        assert_eq!(entry.reason, RejectReason::InFuture);
+        assert_eq!(entry.rejected_at.timestamp(), 1700000000);
+    }
+
+    #[test]
+    fn backup_is_an_openable_copy() {
+        let (storage, dir) = test_storage();
+        storage
+            .insert_blocks(1, &[1, 2, 3], &[100, 112, 124])
+            .unwrap();
+        storage.upsert_cursor(1, 3).unwrap();
+
+        let dest = dir.path().join("backup");
+        let stats = storage.backup_to(&dest, Some(1)).unwrap();
+        assert!(stats.entries >= 4);
+        // later writes stay out of the copy
+        storage.insert_blocks(1, &[4], &[136]).unwrap();
+        assert!(storage.backup_to(&dest, None).is_err());
+
+        let restored = Storage::open(&dest).unwrap();
+        assert_eq!(restored.get_cursor(1).unwrap(), 3);
+        assert_eq!(restored.max_stored_block(1).unwrap(), Some(3));
+        assert_eq!(
+            restored.scan_blocks(1, (0, 0), i64::MAX, 10).unwrap().len(),
+            3
+        );
     }
 
     #[test]
@@ -384,3 +2496,165 @@ mod tests {
         storage.persist().unwrap();
     }
 }
+
+/// Property tests checking the fjall key layout against a naive ordered-map model.
+#[cfg(test)]
+mod proptests {
+    use std::collections::BTreeSet;
+
+    use proptest::prelude::*;
+
+    use super::*;
+
+    /// Chain ids around the interesting `u32` boundaries once cast for key encoding.
+    const CHAINS: [i32; 7] = [0, 1, 2, i32::MAX, i32::MIN, -2, -1];
+
+    /// Reference model: every stored block as `(chain, ts, number)` in key order.
+    struct Model(BTreeSet<(u32, u64, u64)>);
+
+    impl Model {
+        fn find(&self, chain_id: i32, ts: i64, bound: Bound) -> Option<(i64, i64)> {
+            let c = chain_id as u32;
+            let t = ts as u64;
+            let mut on_chain = self.0.iter().filter(|(bc, _, _)| *bc == c);
+            let hit = match bound {
+                Bound::AtOrBefore => on_chain.rfind(|(_, bt, _)| *bt <= t),
+                Bound::Before => on_chain.rfind(|(_, bt, _)| *bt < t),
+                Bound::AtOrAfter => on_chain.find(|(_, bt, _)| *bt >= t),
+                Bound::After => on_chain.find(|(_, bt, _)| *bt > t),
+                Bound::Nearest => {
+                    let before = self.find(chain_id, ts, Bound::AtOrBefore);
+                    let after = self.find(chain_id, ts, Bound::After);
+                    return Direction::nearest_of(ts, before, after, |&(_, bt)| bt);
+                }
+            };
+            hit.map(|&(_, bt, n)| (n as i64, bt as i64))
+        }
+
+        /// [`Model::find`], then the lowest or highest block sharing its timestamp.
+        fn find_tied(&self, chain_id: i32, ts: i64, bound: Bound, tie: Tie) -> Option<(i64, i64)> {
+            let (_, found_ts) = self.find(chain_id, ts, bound)?;
+            let mut tied = self
+                .0
+                .iter()
+                .filter(|&&(bc, bt, _)| bc == chain_id as u32 && bt == found_ts as u64)
+                .map(|&(_, _, n)| n as i64);
+            let number = match tie {
+                Tie::Low => tied.next(),
+                Tie::High => tied.next_back(),
+            }?;
+            Some((number, found_ts))
+        }
+    }
+
+    fn bound() -> impl Strategy<Value = Bound> {
+        prop::sample::select(Bound::ALL.to_vec())
+    }
+
+    fn tie() -> impl Strategy<Value = Tie> {
+        prop_oneof![Just(Tie::Low), Just(Tie::High)]
+    }
+
+    fn chain() -> impl Strategy<Value = i32> {
+        prop::sample::select(CHAINS.to_vec())
+    }
+
+    /// Timestamps cluster in a small range so queries hit exact matches and ties,
+    /// with the occasional extreme value.
+    fn timestamp() -> impl Strategy<Value = i64> {
+        prop_oneof![
+            8 => 0i64..64,
+            1 => Just(i64::MAX),
+            1 => Just(i64::MAX - 1),
+            1 => Just(-1),
+        ]
+    }
+
+    fn blocks() -> impl Strategy<Value = Vec<(i32, i64, i64)>> {
+        prop::collection::vec((chain(), timestamp(), 0i64..10_000), 0..64)
+    }
+
+    fn load(blocks: &[(i32, i64, i64)]) -> (Storage, tempfile::TempDir, Model) {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        let mut model = Model(BTreeSet::new());
+        for &(chain_id, ts, number) in blocks {
+            storage.insert_blocks(chain_id, &[number], &[ts]).unwrap();
+            model.0.insert((chain_id as u32, ts as u64, number as u64));
+        }
+        (storage, dir, model)
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn find_block_matches_model(
+            blocks in blocks(),
+            queries in prop::collection::vec((chain(), timestamp(), bound()), 1..32),
+        ) {
+            let (storage, _dir, model) = load(&blocks);
+            for (chain_id, ts, bound) in queries {
+                prop_assert_eq!(
+                    storage.find_block(chain_id, ts, bound).unwrap(),
+                    model.find(chain_id, ts, bound),
+                    "chain {} ts {} {}", chain_id, ts, bound
+                );
+            }
+        }
+
+        #[test]
+        fn find_block_tied_matches_model(
+            blocks in blocks(),
+            queries in prop::collection::vec((chain(), timestamp(), bound(), tie()), 1..32),
+        ) {
+            let (storage, _dir, model) = load(&blocks);
+            for (chain_id, ts, bound, tie) in queries {
+                prop_assert_eq!(
+                    storage.find_block_tied(chain_id, ts, bound, tie).unwrap(),
+                    model.find_tied(chain_id, ts, bound, tie),
+                    "chain {} ts {} {} tie={:?}", chain_id, ts, bound, tie
+                );
+            }
+        }
+
+        #[test]
+        fn find_blocks_multi_matches_model(
+            blocks in blocks(),
+            chain_id in chain(),
+            queries in prop::collection::vec((timestamp(), bound()), 0..32),
+        ) {
+            let (storage, _dir, model) = load(&blocks);
+            let expected: Vec<_> = queries
+                .iter()
+                .map(|&(ts, bound)| model.find(chain_id, ts, bound))
+                .collect();
+            prop_assert_eq!(storage.find_blocks_multi(chain_id, &queries).unwrap(), expected);
+        }
+
+        #[test]
+        fn block_key_order_matches_tuple_order(
+            a in (any::<u32>(), any::<u64>(), any::<u64>()),
+            b in (any::<u32>(), any::<u64>(), any::<u64>()),
+        ) {
+            let ka = encode_block_key(a.0, a.1, a.2);
+            let kb = encode_block_key(b.0, b.1, b.2);
+            prop_assert_eq!(ka.cmp(&kb), a.cmp(&b));
+            prop_assert_eq!(decode_block_key(&ka).unwrap(), a);
+        }
+    }
+
+    #[test]
+    fn max_chain_id_matches_model() {
+        let (storage, _dir, model) = load(&[(-1, 10, 1), (-1, 20, 2), (-2, 30, 3), (-1, -1, 4)]);
+        // -1 encodes as timestamp u64::MAX, so after-exclusive has no successor to seek to
+        for ts in [0, 10, 15, 20, 25, -1] {
+            for bound in Bound::ALL {
+                assert_eq!(
+                    storage.find_block(-1, ts, bound).unwrap(),
+                    model.find(-1, ts, bound)
+                );
+            }
+        }
+    }
+}
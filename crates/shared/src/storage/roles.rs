@@ -0,0 +1,222 @@
+//! Read and write handles, with metrics per role.
+//!
+//! [`Storage`] is the write handle: ingestion, background jobs and the admin routes
+//! that change data hold one. [`ReadStorage`] is a clone of the same database that can
+//! only read, and is what request handlers get, so route code can't write by mistake.
+//! Both count into one [`StorageMetrics`], split by role: reads are timed through the
+//! read handle, writes (and blocks written) inside `Storage`'s write methods.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+
+use super::{
+    CycleSummary, IngestErrorEntry, RecordedAnomaly, RejectedBlock, Storage, WorkItem,
+    WritePressure,
+};
+use crate::error::AppError;
+use crate::models::{BlockRef, Bound, Tie};
+
+/// Operation count and time spent in one role.
+#[derive(Debug, Default)]
+pub struct RoleMetrics {
+    operations: AtomicU64,
+    nanos: AtomicU64,
+}
+
+impl RoleMetrics {
+    /// Counts one operation, timed until the guard drops.
+    pub(crate) fn time(&self) -> Timed<'_> {
+        Timed {
+            role: self,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn snapshot(&self) -> RoleStats {
+        RoleStats {
+            operations: self.operations.load(Ordering::Relaxed),
+            busy_secs: self.nanos.load(Ordering::Relaxed) as f64 / 1e9,
+        }
+    }
+}
+
+/// Records an operation on drop, so early returns are counted too.
+pub(crate) struct Timed<'a> {
+    role: &'a RoleMetrics,
+    started: Instant,
+}
+
+impl Drop for Timed<'_> {
+    fn drop(&mut self) {
+        let nanos = self.started.elapsed().as_nanos() as u64;
+        self.role.operations.fetch_add(1, Ordering::Relaxed);
+        self.role.nanos.fetch_add(nanos, Ordering::Relaxed);
+    }
+}
+
+/// Totals for one role since startup.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RoleStats {
+    pub operations: u64,
+    /// Seconds spent in those operations; divided by `operations`, the mean latency.
+    pub busy_secs: f64,
+}
+
+/// Storage operations by role, shared by every handle of one database.
+#[derive(Debug, Default)]
+pub struct StorageMetrics {
+    pub reads: RoleMetrics,
+    pub writes: RoleMetrics,
+    blocks_written: AtomicU64,
+}
+
+impl StorageMetrics {
+    pub(crate) fn add_blocks_written(&self, blocks: usize) {
+        self.blocks_written
+            .fetch_add(blocks as u64, Ordering::Relaxed);
+    }
+
+    /// Blocks inserted into the index, the write throughput that matters.
+    pub fn blocks_written(&self) -> u64 {
+        self.blocks_written.load(Ordering::Relaxed)
+    }
+}
+
+/// A read-only handle to [`Storage`]. Cheap to clone.
+#[derive(Clone)]
+pub struct ReadStorage(Storage);
+
+impl Storage {
+    /// A read-only handle to the same database.
+    pub fn reader(&self) -> ReadStorage {
+        ReadStorage(self.clone())
+    }
+}
+
+/// Forwards each read to `Storage`, timed as a read.
+macro_rules! reads {
+    ($(fn $name:ident(&self $(, $arg:ident: $ty:ty)*) -> $ret:ty;)*) => {
+        impl ReadStorage {
+            $(
+                #[doc = concat!("See [`Storage::", stringify!($name), "`].")]
+                pub fn $name(&self $(, $arg: $ty)*) -> $ret {
+                    let _timed = self.0.metrics.reads.time();
+                    self.0.$name($($arg),*)
+                }
+            )*
+        }
+    };
+}
+
+reads! {
+    fn find_block(
+        &self,
+        chain_id: i32,
+        timestamp: i64,
+        bound: Bound
+    ) -> Result<Option<(i64, i64)>, AppError>;
+    fn find_block_tied(
+        &self,
+        chain_id: i32,
+        timestamp: i64,
+        bound: Bound,
+        tie: Tie
+    ) -> Result<Option<(i64, i64)>, AppError>;
+    fn tied_block(&self, chain_id: i32, timestamp: i64, tie: Tie) -> Result<Option<i64>, AppError>;
+    fn find_blocks_near(
+        &self,
+        chain_id: i32,
+        timestamp: i64,
+        bound: Bound,
+        limit: usize
+    ) -> Result<Vec<(i64, i64)>, AppError>;
+    fn find_blocks_multi(
+        &self,
+        chain_id: i32,
+        queries: &[(i64, Bound)]
+    ) -> Result<Vec<Option<(i64, i64)>>, AppError>;
+    fn scan_blocks(
+        &self,
+        chain_id: i32,
+        from: (i64, i64),
+        to_ts: i64,
+        limit: usize
+    ) -> Result<Vec<(i64, i64)>, AppError>;
+    fn find_block_by_number(
+        &self,
+        chain_id: i32,
+        number: i64,
+        from_ts: i64,
+        to_ts: i64
+    ) -> Result<Option<(i64, i64)>, AppError>;
+    fn recent_block_time(&self, chain_id: i32, span: i64) -> Result<Option<f64>, AppError>;
+    fn rejected_count(&self, chain_id: i32) -> Result<u64, AppError>;
+    fn block_extent(&self, chain_id: i32) -> Result<Option<(BlockRef, BlockRef)>, AppError>;
+    fn journal_stats(&self) -> Result<(usize, u64), AppError>;
+    fn disk_space(&self) -> Result<u64, AppError>;
+    fn max_stored_block(&self, chain_id: i32) -> Result<Option<i64>, AppError>;
+    fn list_rejected(
+        &self,
+        chain_id: i32,
+        from_number: i64,
+        limit: usize
+    ) -> Result<Vec<RejectedBlock>, AppError>;
+    fn get_cursor(&self, chain_id: i32) -> Result<i64, AppError>;
+    fn get_all_cursors(&self) -> Result<Vec<(i32, i64, DateTime<Utc>)>, AppError>;
+    fn list_anomalies(&self, chain_id: i32, limit: usize) -> Result<Vec<RecordedAnomaly>, AppError>;
+    fn cursor_at(&self, chain_id: i32, at: i64) -> Result<Option<(i64, i64)>, AppError>;
+    fn list_ingest_errors(
+        &self,
+        chain_id: Option<i32>,
+        since: DateTime<Utc>,
+        limit: usize
+    ) -> Result<Vec<IngestErrorEntry>, AppError>;
+    fn cycles_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>
+    ) -> Result<Vec<CycleSummary>, AppError>;
+    fn work_items(&self) -> Result<Vec<WorkItem>, AppError>;
+}
+
+impl ReadStorage {
+    /// See [`Storage::write_pressure`].
+    pub fn write_pressure(&self) -> WritePressure {
+        self.0.write_pressure()
+    }
+
+    /// See [`Storage::metrics`].
+    pub fn metrics(&self) -> &StorageMetrics {
+        self.0.metrics()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roles_count_separately_across_handles() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        let reader = storage.reader();
+
+        storage.insert_blocks(1, &[1, 2, 3], &[10, 20, 30]).unwrap();
+        storage.upsert_cursor(1, 3).unwrap();
+        assert_eq!(
+            reader.find_block(1, 25, Bound::AtOrBefore).unwrap(),
+            Some((2, 20))
+        );
+        assert_eq!(reader.get_cursor(1).unwrap(), 3);
+        // reads through the write handle are part of the write path, not counted
+        storage.get_cursor(1).unwrap();
+
+        let metrics = storage.metrics();
+        assert_eq!(metrics.writes.snapshot().operations, 2);
+        assert_eq!(metrics.reads.snapshot().operations, 2);
+        assert_eq!(metrics.blocks_written(), 3);
+        assert!(reader.metrics().reads.snapshot().busy_secs > 0.0);
+    }
+}
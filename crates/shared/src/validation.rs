@@ -0,0 +1,284 @@
+//! Sanity checks applied to block headers before they enter the serving index.
+//!
+//! Headers that fail validation are quarantined in the `rejected` keyspace with a
+//! reason instead of being written to `blocks`, so a bad upstream batch can't poison
+//! lookups.
+//!
+//! Timestamp anomalies between consecutive blocks (a block older than its parent, or
+//! an unusually long gap) are only reported: both happen on healthy chains now and
+//! then, so the blocks are still indexed and the anomaly is recorded for inspection.
+
+use std::fmt;
+
+use crate::chains::ChainConfig;
+use crate::sqd::BlockHeader;
+
+/// How far past the local clock a block timestamp may be before it is rejected.
+/// Generous enough to absorb clock skew between us, SQD, and the chain's validators.
+pub const MAX_FUTURE_DRIFT_SECS: i64 = 15 * 60;
+
+/// Why a block header was quarantined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RejectReason {
+    NegativeNumber,
+    NegativeTimestamp,
+    BeforeGenesis,
+    InFuture,
+}
+
+impl RejectReason {
+    /// Stable machine-readable name, also used as the persisted form.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::NegativeNumber => "negative_number",
+            Self::NegativeTimestamp => "negative_timestamp",
+            Self::BeforeGenesis => "before_genesis",
+            Self::InFuture => "in_future",
+        }
+    }
+
+    /// Parses the persisted form back into a reason.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "negative_number" => Some(Self::NegativeNumber),
+            "negative_timestamp" => Some(Self::NegativeTimestamp),
+            "before_genesis" => Some(Self::BeforeGenesis),
+            "in_future" => Some(Self::InFuture),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What is odd about a block's timestamp relative to the block before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AnomalyKind {
+    /// Earlier than the previous block.
+    Backwards,
+    /// Later than the previous block by more than the alert threshold.
+    Jump,
+}
+
+impl AnomalyKind {
+    /// Stable machine-readable name, also used as the persisted form.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Backwards => "backwards",
+            Self::Jump => "jump",
+        }
+    }
+
+    /// Parses the persisted form back into a kind.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "backwards" => Some(Self::Backwards),
+            "jump" => Some(Self::Jump),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for AnomalyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A block whose timestamp is out of line with the block before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimestampAnomaly {
+    pub kind: AnomalyKind,
+    pub number: i64,
+    pub timestamp: i64,
+    pub prev_number: i64,
+    pub prev_timestamp: i64,
+}
+
+/// Checks each header against the one before it. `prev` is the block preceding the
+/// first header, if known. Gaps over `max_jump_secs` are reported as jumps; `None`
+/// reports backwards timestamps only.
+pub fn find_timestamp_anomalies(
+    prev: Option<&BlockHeader>,
+    headers: &[BlockHeader],
+    max_jump_secs: Option<i64>,
+) -> Vec<TimestampAnomaly> {
+    let pairs = prev
+        .into_iter()
+        .chain(headers)
+        .zip(headers.iter().skip(usize::from(prev.is_none())));
+    pairs
+        .filter_map(|(prev, header)| {
+            let delta = header.timestamp - prev.timestamp;
+            let kind = if delta < 0 {
+                AnomalyKind::Backwards
+            } else if max_jump_secs.is_some_and(|max| delta > max) {
+                AnomalyKind::Jump
+            } else {
+                return None;
+            };
+            Some(TimestampAnomaly {
+                kind,
+                number: header.number,
+                timestamp: header.timestamp,
+                prev_number: prev.number,
+                prev_timestamp: prev.timestamp,
+            })
+        })
+        .collect()
+}
+
+/// Validates a single header against its chain config and the current time (Unix secs).
+///
+/// Block 0 is exempt from the genesis check since several chains report a zero
+/// timestamp for it (see `ChainConfig::genesis_timestamp`). The override from
+/// `GENESIS_TIMESTAMPS` applies if there is one.
+pub fn validate_header(
+    chain: &ChainConfig,
+    header: &BlockHeader,
+    now_secs: i64,
+) -> Result<(), RejectReason> {
+    if header.number < 0 {
+        return Err(RejectReason::NegativeNumber);
+    }
+    if header.timestamp < 0 {
+        return Err(RejectReason::NegativeTimestamp);
+    }
+    if header.number > 0 && header.timestamp < chain.effective_genesis_timestamp() {
+        return Err(RejectReason::BeforeGenesis);
+    }
+    if header.timestamp > now_secs + MAX_FUTURE_DRIFT_SECS {
+        return Err(RejectReason::InFuture);
+    }
+    Ok(())
+}
+
+/// Splits a batch into headers that passed validation and quarantined ones with reasons.
+pub fn partition_headers(
+    chain: &ChainConfig,
+    headers: Vec<BlockHeader>,
+    now_secs: i64,
+) -> (Vec<BlockHeader>, Vec<(BlockHeader, RejectReason)>) {
+    let mut valid = Vec::with_capacity(headers.len());
+    let mut rejected = Vec::new();
+    for header in headers {
+        match validate_header(chain, &header, now_secs) {
+            Ok(()) => valid.push(header),
+            Err(reason) => rejected.push((header, reason)),
+        }
+    }
+    (valid, rejected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chains::chain_by_id;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn header(number: i64, timestamp: i64) -> BlockHeader {
+        BlockHeader { number, timestamp }
+    }
+
+    #[test]
+    fn accepts_normal_header() {
+        let eth = chain_by_id(1).unwrap();
+        assert_eq!(
+            validate_header(eth, &header(100, 1_500_000_000), NOW),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn block_zero_exempt_from_genesis_check() {
+        let eth = chain_by_id(1).unwrap();
+        assert_eq!(validate_header(eth, &header(0, 0), NOW), Ok(()));
+    }
+
+    #[test]
+    fn rejects_each_reason() {
+        let eth = chain_by_id(1).unwrap();
+        assert_eq!(
+            validate_header(eth, &header(-1, 1_500_000_000), NOW),
+            Err(RejectReason::NegativeNumber)
+        );
+        assert_eq!(
+            validate_header(eth, &header(1, -5), NOW),
+            Err(RejectReason::NegativeTimestamp)
+        );
+        assert_eq!(
+            validate_header(eth, &header(1, eth.genesis_timestamp - 1), NOW),
+            Err(RejectReason::BeforeGenesis)
+        );
+        assert_eq!(
+            validate_header(eth, &header(1, NOW + MAX_FUTURE_DRIFT_SECS + 1), NOW),
+            Err(RejectReason::InFuture)
+        );
+    }
+
+    #[test]
+    fn partition_splits_batch() {
+        let eth = chain_by_id(1).unwrap();
+        let (valid, rejected) = partition_headers(
+            eth,
+            vec![header(1, 1_500_000_000), header(2, NOW * 1000)],
+            NOW,
+        );
+        assert_eq!(valid.len(), 1);
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].1, RejectReason::InFuture);
+    }
+
+    #[test]
+    fn finds_backwards_timestamps_and_long_gaps() {
+        let headers = [header(11, 1_012), header(12, 1_010), header(13, 5_000)];
+        let anomalies = find_timestamp_anomalies(Some(&header(10, 1_000)), &headers, Some(3600));
+        assert_eq!(
+            anomalies,
+            vec![
+                TimestampAnomaly {
+                    kind: AnomalyKind::Backwards,
+                    number: 12,
+                    timestamp: 1_010,
+                    prev_number: 11,
+                    prev_timestamp: 1_012,
+                },
+                TimestampAnomaly {
+                    kind: AnomalyKind::Jump,
+                    number: 13,
+                    timestamp: 5_000,
+                    prev_number: 12,
+                    prev_timestamp: 1_010,
+                },
+            ]
+        );
+
+        // the batch boundary is checked when the previous block is known
+        let anomalies = find_timestamp_anomalies(Some(&header(10, 2_000)), &headers[..1], None);
+        assert_eq!(anomalies[0].kind, AnomalyKind::Backwards);
+        assert!(find_timestamp_anomalies(None, &headers[..1], None).is_empty());
+        // equal timestamps are normal on fast chains; no threshold, no jumps
+        let same = [header(1, 100), header(2, 100), header(3, 1_000_000)];
+        assert!(find_timestamp_anomalies(None, &same, None).is_empty());
+    }
+
+    #[test]
+    fn reason_round_trips() {
+        for reason in [
+            RejectReason::NegativeNumber,
+            RejectReason::NegativeTimestamp,
+            RejectReason::BeforeGenesis,
+            RejectReason::InFuture,
+        ] {
+            assert_eq!(RejectReason::parse(reason.as_str()), Some(reason));
+        }
+        for kind in [AnomalyKind::Backwards, AnomalyKind::Jump] {
+            assert_eq!(AnomalyKind::parse(kind.as_str()), Some(kind));
+        }
+    }
+}